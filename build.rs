@@ -1,3 +1,5 @@
+use std::process::Command;
+
 fn main() {
     println!("cargo:rustc-link-lib=opencv_imgcodecs");
     println!("cargo:rustc-link-lib=opencv_imgproc");
@@ -5,4 +7,19 @@ fn main() {
     println!("cargo:rustc-link-lib=opencv_highgui");
     println!("cargo:rustc-link-lib=opencv_videoio");
     println!("cargo:rustc-link-lib=stdc++");
+
+    // Recorded in run_manifest.json, not set when building outside a git checkout
+    let commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=GIT_COMMIT={}", commit);
+    }
+
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/dribbling_detection.proto")
+        .expect("Failed to compile proto/dribbling_detection.proto");
 }
@@ -0,0 +1,54 @@
+/*
+ * C API of the dribble detector, implemented in src/ffi.rs. Build the shared library with
+ *
+ *     cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * and link against target/release/libdribbling_detection_algorithm.so.
+ */
+#ifndef DRIBBLING_DETECTION_H
+#define DRIBBLING_DETECTION_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct DdDetector DdDetector;
+
+/* A player in a frame. `team` may be NULL. */
+typedef struct DdPlayer {
+    uint32_t id;
+    double x;
+    double y;
+    double velocity_x;
+    double velocity_y;
+    const char *team;
+} DdPlayer;
+
+/* Config at `config_path`, or the defaults if NULL. Returns NULL if the config is invalid. */
+DdDetector *dd_detector_new(const char *config_path, double distance_scale);
+void dd_detector_free(DdDetector *detector);
+
+/* Returns 1 if an event finished in this frame, 0 if not and -1 if `detector` is NULL or the
+ * frame can not be processed, e.g. because a position is NaN. */
+int32_t dd_process_frame(DdDetector *detector, uint32_t frame_number, const DdPlayer *players,
+                         size_t num_players, double ball_x, double ball_y);
+
+size_t dd_event_count(const DdDetector *detector);
+void dd_events_clear(DdDetector *detector);
+
+/* Out of range indices return 0 or false. */
+uint32_t dd_event_start_frame(const DdDetector *detector, size_t index);
+uint32_t dd_event_end_frame(const DdDetector *detector, size_t index);
+uint32_t dd_event_possession_holder(const DdDetector *detector, size_t index);
+bool dd_event_is_tackle(const DdDetector *detector, size_t index);
+bool dd_event_is_contested(const DdDetector *detector, size_t index);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,830 @@
+//! Per-player statistics of a run, written to `player_stats.json` and `player_stats.csv`:
+//! dribbles attempted and completed, contested dribbles, tackles suffered and made, defenders
+//! beaten, event durations and the distance the ball was carried.
+//!
+//! Track ids are only unique within a video, so players are keyed by video and track id, with
+//! the jersey number and team of their annotations if they have one. Distances are in the
+//! units of the detection radii: meters with `use_2d`, pixels otherwise.
+//!
+//! The events are also counted by team and pitch zone, the third and channel of the pitch
+//! (see [`PitchModel::third`]) where the holder was when the event started, in
+//! `team_zones.json` and `team_zones.csv`, per video and for the whole run. Zones come from the
+//! pitch coordinates of the annotations regardless of `use_2d`. Thirds are seen from the team
+//! of the holder: the `left` team attacks the goal at positive x, the `right` team the other
+//! one. The thirds of holders of unknown teams are not known.
+//!
+//! Every completed dribble also gets a difficulty score, in `event_difficulty.json` and
+//! `event_difficulty.csv` ranked from the hardest, to find the best dribbles of a match. The
+//! score is the pressure of the defenders, the seconds every opponent spent in the inner zone
+//! plus half the seconds they spent only in the outer zone, scaled up for a fast holder and
+//! for dribbles in the attacking third and the center of the pitch, see
+//! [`TrackRecorder::event_difficulty`].
+
+use crate::data::models::{Annotation, ExportInfo};
+use crate::dribbling_detection::dribble_detector::DribbleDetector;
+use crate::dribbling_detection::dribble_models::{DribbleEvent, DribbleFrame};
+use crate::dribbling_detection::possession::Possession;
+use crate::utils::annotation_calculations::calculate_bbox_pitch_coordinates;
+use crate::utils::pitch_model::{attacks_right, Channel, PitchModel, Third};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the statistics in the output directory
+pub const PLAYER_STATS_FILE: &str = "player_stats.json";
+/// Name of the statistics as CSV in the output directory
+pub const PLAYER_STATS_CSV: &str = "player_stats.csv";
+/// Name of the event counts by team and zone in the output directory
+pub const TEAM_ZONES_FILE: &str = "team_zones.json";
+/// Name of the event counts by team and zone as CSV in the output directory
+pub const TEAM_ZONES_CSV: &str = "team_zones.csv";
+/// Name of the difficulty scores of the dribbles in the output directory
+pub const EVENT_DIFFICULTY_FILE: &str = "event_difficulty.json";
+/// Name of the difficulty scores as CSV in the output directory
+pub const EVENT_DIFFICULTY_CSV: &str = "event_difficulty.csv";
+/// Video id of the counts of the whole run
+pub const RUN_TOTAL: &str = "all";
+
+/// Weight of a second of an opponent only in the outer zone, relative to the inner zone
+const OUTER_ZONE_WEIGHT: f64 = 0.5;
+/// Increase of the difficulty per outer radius the holder moves per second
+const SPEED_WEIGHT: f64 = 0.5;
+/// Difficulty factors of dribbles starting in the defensive, middle and attacking third
+const THIRD_FACTORS: [f64; 3] = [1.0, 1.1, 1.25];
+/// Difficulty factor of dribbles starting in the center of the pitch
+const CENTER_FACTOR: f64 = 1.1;
+
+/// The `[analytics]` section. Statistics are computed if the section is present and enabled.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnalyticsConfig {
+    pub enabled: bool,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// The statistics of a player in a video.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub video_id: String,
+    pub track_id: u32,
+    pub jersey: Option<String>,
+    pub team: Option<String>,
+    /// Events with the player as the possession holder
+    pub dribbles_attempted: usize,
+    /// Attempts that ended as a dribble
+    pub dribbles_completed: usize,
+    /// Attempts with a defender in the inner zone long enough to contest it
+    pub contested: usize,
+    /// Attempts that ended as a tackle
+    pub tackles_suffered: usize,
+    /// Tackles on other players after which this player had the ball
+    pub tackles_made: usize,
+    /// Distinct opponents in the inner zone during a completed dribble, on average
+    pub mean_defenders_beaten: f64,
+    pub mean_duration_frames: f64,
+    pub mean_duration_secs: f64,
+    /// Distance the player moved during their attempts
+    pub distance_carried: f64,
+}
+
+/// The events of a team in a zone of the pitch.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZoneCounts {
+    /// [`RUN_TOTAL`] for the counts of the whole run
+    pub video_id: String,
+    /// Team of the holder, `None` if unknown or if teams are ignored
+    pub team: Option<String>,
+    /// `None` for events without pitch coordinates of the holder
+    pub third: Option<Third>,
+    pub channel: Option<Channel>,
+    pub dribbles: usize,
+    pub tackles: usize,
+    pub contested: usize,
+}
+
+impl ZoneCounts {
+    fn add(&mut self, other: &ZoneCounts) {
+        self.dribbles += other.dribbles;
+        self.tackles += other.tackles;
+        self.contested += other.contested;
+    }
+}
+
+/// The difficulty of a completed dribble.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventDifficulty {
+    pub video_id: String,
+    pub start_frame: u32,
+    pub end_frame: u32,
+    /// Track id of the holder
+    pub track_id: u32,
+    pub jersey: Option<String>,
+    pub team: Option<String>,
+    /// Seconds of every opponent in the inner zone, summed over the opponents
+    pub inner_defender_secs: f64,
+    /// Seconds of every opponent in the outer but not the inner zone
+    pub outer_defender_secs: f64,
+    /// Distance the holder moved per second
+    pub mean_speed: f64,
+    pub third: Option<Third>,
+    pub channel: Option<Channel>,
+    pub score: f64,
+}
+
+/// Counts of a player, summed over the events of a video.
+#[derive(Clone, Copy, Debug, Default)]
+struct Totals {
+    attempted: usize,
+    completed: usize,
+    contested: usize,
+    tackles_suffered: usize,
+    tackles_made: usize,
+    defenders_beaten: usize,
+    duration_frames: u32,
+    distance: f64,
+}
+
+/// Positions of the players of every frame of a video, with the jersey number and team of
+/// every track, recorded during detection.
+#[derive(Clone, Debug, Default)]
+pub struct TrackRecorder {
+    frames: BTreeMap<u32, Vec<(u32, (f64, f64))>>,
+    /// Pitch coordinates of the tracks of every frame, which are not the positions of
+    /// `frames` without `use_2d`
+    pitch_positions: BTreeMap<u32, Vec<(u32, (f64, f64))>>,
+    jerseys: HashMap<u32, String>,
+    teams: HashMap<u32, String>,
+}
+
+impl TrackRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the players of `frame`, and the jersey numbers of its `annotations`.
+    pub fn process_frame(&mut self, frame: &DribbleFrame, annotations: &[Annotation]) {
+        let positions = frame.players.iter().map(|p| (p.id, (p.x, p.y))).collect();
+        self.frames.insert(frame.frame_number, positions);
+        for player in &frame.players {
+            if let Some(team) = &player.team {
+                self.teams.entry(player.id).or_insert_with(|| team.clone());
+            }
+        }
+        let pitch_positions = annotations
+            .iter()
+            .filter_map(|a| {
+                let position = calculate_bbox_pitch_coordinates(a.clone(), true)?;
+                Some((a.track_id?, position))
+            })
+            .collect();
+        self.pitch_positions
+            .insert(frame.frame_number, pitch_positions);
+        for annotation in annotations {
+            let jersey = annotation
+                .attributes
+                .as_ref()
+                .and_then(|a| a.jersey.as_ref());
+            if let (Some(track_id), Some(jersey)) = (annotation.track_id, jersey) {
+                self.jerseys
+                    .entry(track_id)
+                    .or_insert_with(|| jersey.clone());
+            }
+        }
+    }
+
+    /// Length of the path of `track_id` from `start` to `end`.
+    fn distance_moved(&self, track_id: u32, start: u32, end: u32) -> f64 {
+        let path: Vec<(f64, f64)> = self
+            .frames
+            .range(start..=end)
+            .filter_map(|(_, players)| {
+                players
+                    .iter()
+                    .find(|(id, _)| *id == track_id)
+                    .map(|&(_, position)| position)
+            })
+            .collect();
+        path.windows(2)
+            .map(|pair| DribbleDetector::distance(pair[0], pair[1]))
+            .sum()
+    }
+
+    /// The opponents within `radius` of `holder` among `players`, none if the holder is not
+    /// one of them. Teammates are left out if the teams are known.
+    fn opponents_within(
+        &self,
+        holder: u32,
+        players: &[(u32, (f64, f64))],
+        radius: f64,
+    ) -> Vec<u32> {
+        let team = self.teams.get(&holder);
+        let Some(&(_, holder_position)) = players.iter().find(|(id, _)| *id == holder) else {
+            return Vec::new();
+        };
+        players
+            .iter()
+            .filter(|&&(id, position)| {
+                let teammate = team.is_some() && self.teams.get(&id) == team;
+                id != holder
+                    && !teammate
+                    && DribbleDetector::distance(position, holder_position) < radius
+            })
+            .map(|&(id, _)| id)
+            .collect()
+    }
+
+    /// Number of distinct players that came within `radius` of `holder` from `start` to `end`.
+    /// Teammates are left out if the teams are known.
+    fn defenders_within(&self, holder: u32, start: u32, end: u32, radius: f64) -> usize {
+        let defenders: HashSet<u32> = self
+            .frames
+            .range(start..=end)
+            .flat_map(|(_, players)| self.opponents_within(holder, players, radius))
+            .collect();
+        defenders.len()
+    }
+
+    /// The difficulty of every completed dribble of `events`. The score is
+    ///
+    /// `(inner + 0.5 * outer) * (1 + 0.5 * speed / outer_rad) * zone`
+    ///
+    /// with `inner` and `outer` the seconds of the opponents in the inner zone and only in the
+    /// outer zone, summed over the opponents, and `speed` the distance the holder moved per
+    /// second. `zone` is 1, 1.1 or 1.25 in the defensive, middle or attacking third of the
+    /// holder's team, times 1.1 in the center.
+    pub fn event_difficulty(
+        &self,
+        video_id: &str,
+        events: &[DribbleEvent],
+        inner_rad: f64,
+        outer_rad: f64,
+        frame_rate: f64,
+        pitch: &PitchModel,
+    ) -> Vec<EventDifficulty> {
+        events
+            .iter()
+            .filter(|event| event.detected_dribble)
+            .map(|event| {
+                let holder = event.possession_holder;
+                let end_frame = event.end_frame.unwrap_or(event.start_frame);
+                let (mut inner_frames, mut outer_frames) = (0, 0);
+                for (_, players) in self.frames.range(event.start_frame..=end_frame) {
+                    let inner = self.opponents_within(holder, players, inner_rad).len();
+                    let outer = self.opponents_within(holder, players, outer_rad).len();
+                    inner_frames += inner;
+                    outer_frames += outer.saturating_sub(inner);
+                }
+                let duration_secs =
+                    f64::from(end_frame.saturating_sub(event.start_frame) + 1) / frame_rate;
+                let mean_speed =
+                    self.distance_moved(holder, event.start_frame, end_frame) / duration_secs;
+
+                let team = self.teams.get(&holder).cloned();
+                let position = self.pitch_position(holder, event.start_frame, end_frame);
+                let third = position
+                    .zip(attacks_right(team.as_deref()))
+                    .map(|((x, _), attacking_right)| pitch.third(x, attacking_right));
+                let channel = position.map(|(_, y)| pitch.channel(y));
+                let third_factor = match third {
+                    Some(Third::Defensive) | None => THIRD_FACTORS[0],
+                    Some(Third::Middle) => THIRD_FACTORS[1],
+                    Some(Third::Attacking) => THIRD_FACTORS[2],
+                };
+                let channel_factor = if channel == Some(Channel::Center) {
+                    CENTER_FACTOR
+                } else {
+                    1.0
+                };
+
+                let inner_defender_secs = inner_frames as f64 / frame_rate;
+                let outer_defender_secs = outer_frames as f64 / frame_rate;
+                let pressure = inner_defender_secs + OUTER_ZONE_WEIGHT * outer_defender_secs;
+                let speed_factor = if outer_rad > 0.0 {
+                    1.0 + SPEED_WEIGHT * mean_speed / outer_rad
+                } else {
+                    1.0
+                };
+                EventDifficulty {
+                    video_id: video_id.to_string(),
+                    start_frame: event.start_frame,
+                    end_frame,
+                    track_id: holder,
+                    jersey: self.jerseys.get(&holder).cloned(),
+                    team,
+                    inner_defender_secs,
+                    outer_defender_secs,
+                    mean_speed,
+                    third,
+                    channel,
+                    score: pressure * speed_factor * third_factor * channel_factor,
+                }
+            })
+            .collect()
+    }
+
+    /// The statistics of every player with an event in `events`, by track id. The player that
+    /// made a tackle is the first other player to start a possession spell during it.
+    pub fn player_stats(
+        &self,
+        video_id: &str,
+        events: &[DribbleEvent],
+        possessions: &[Possession],
+        inner_rad: f64,
+        frame_rate: f64,
+    ) -> Vec<PlayerStats> {
+        let mut totals: BTreeMap<u32, Totals> = BTreeMap::new();
+        for event in events {
+            let holder = event.possession_holder;
+            let end_frame = event.end_frame.unwrap_or(event.start_frame);
+            let player = totals.entry(holder).or_default();
+            player.attempted += 1;
+            player.duration_frames += end_frame.saturating_sub(event.start_frame) + 1;
+            player.distance += self.distance_moved(holder, event.start_frame, end_frame);
+            if event.ever_contested {
+                player.contested += 1;
+            }
+            if event.detected_tackle {
+                player.tackles_suffered += 1;
+                let tackler = possessions.iter().find(|p| {
+                    p.player_id != holder
+                        && (event.start_frame..=end_frame).contains(&p.start_frame)
+                });
+                if let Some(tackler) = tackler {
+                    totals.entry(tackler.player_id).or_default().tackles_made += 1;
+                }
+            } else if event.detected_dribble {
+                player.completed += 1;
+                player.defenders_beaten +=
+                    self.defenders_within(holder, event.start_frame, end_frame, inner_rad);
+            }
+        }
+
+        let mean = |total: f64, count: usize| {
+            if count == 0 {
+                0.0
+            } else {
+                total / count as f64
+            }
+        };
+        totals
+            .into_iter()
+            .map(|(track_id, t)| {
+                let mean_duration_frames = mean(f64::from(t.duration_frames), t.attempted);
+                PlayerStats {
+                    video_id: video_id.to_string(),
+                    track_id,
+                    jersey: self.jerseys.get(&track_id).cloned(),
+                    team: self.teams.get(&track_id).cloned(),
+                    dribbles_attempted: t.attempted,
+                    dribbles_completed: t.completed,
+                    contested: t.contested,
+                    tackles_suffered: t.tackles_suffered,
+                    tackles_made: t.tackles_made,
+                    mean_defenders_beaten: mean(t.defenders_beaten as f64, t.completed),
+                    mean_duration_frames,
+                    mean_duration_secs: mean_duration_frames / frame_rate,
+                    distance_carried: t.distance,
+                }
+            })
+            .collect()
+    }
+
+    /// Pitch coordinates of `track_id` in the first frame from `start` to `end` that has them.
+    fn pitch_position(&self, track_id: u32, start: u32, end: u32) -> Option<(f64, f64)> {
+        self.pitch_positions
+            .range(start..=end)
+            .find_map(|(_, positions)| {
+                positions
+                    .iter()
+                    .find(|(id, _)| *id == track_id)
+                    .map(|&(_, position)| position)
+            })
+    }
+
+    /// The events of `events` by team of the holder and zone of `pitch` where they started.
+    pub fn zone_counts(
+        &self,
+        video_id: &str,
+        events: &[DribbleEvent],
+        pitch: &PitchModel,
+    ) -> Vec<ZoneCounts> {
+        let mut counts: BTreeMap<(Option<String>, Option<Third>, Option<Channel>), ZoneCounts> =
+            BTreeMap::new();
+        for event in events {
+            let holder = event.possession_holder;
+            let team = self.teams.get(&holder).cloned();
+            let end_frame = event.end_frame.unwrap_or(event.start_frame);
+            let position = self.pitch_position(holder, event.start_frame, end_frame);
+            let third = position
+                .zip(attacks_right(team.as_deref()))
+                .map(|((x, _), attacking_right)| pitch.third(x, attacking_right));
+            let channel = position.map(|(_, y)| pitch.channel(y));
+
+            let zone = counts
+                .entry((team.clone(), third, channel))
+                .or_insert_with(|| ZoneCounts {
+                    video_id: video_id.to_string(),
+                    team,
+                    third,
+                    channel,
+                    ..Default::default()
+                });
+            if event.detected_tackle {
+                zone.tackles += 1;
+            } else if event.detected_dribble {
+                zone.dribbles += 1;
+            }
+            if event.ever_contested {
+                zone.contested += 1;
+            }
+        }
+        counts.into_values().collect()
+    }
+}
+
+/// The counts of `videos` summed over the videos, by team and zone.
+pub fn summarize_zones(videos: &[ZoneCounts]) -> Vec<ZoneCounts> {
+    let mut totals: BTreeMap<(Option<String>, Option<Third>, Option<Channel>), ZoneCounts> =
+        BTreeMap::new();
+    for counts in videos {
+        totals
+            .entry((counts.team.clone(), counts.third, counts.channel))
+            .or_insert_with(|| ZoneCounts {
+                video_id: RUN_TOTAL.to_string(),
+                team: counts.team.clone(),
+                third: counts.third,
+                channel: counts.channel,
+                ..Default::default()
+            })
+            .add(counts);
+    }
+    totals.into_values().collect()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TeamZonesExport {
+    pub info: ExportInfo,
+    /// Counts of the whole run
+    pub run: Vec<ZoneCounts>,
+    /// Counts per video
+    pub videos: Vec<ZoneCounts>,
+}
+
+/// Writes the counts of `videos`, and their sums over the run, to [`TEAM_ZONES_FILE`] and
+/// [`TEAM_ZONES_CSV`] in `output_path`. The CSV has the sums in the rows of video
+/// [`RUN_TOTAL`], and `unknown` for missing teams and zones.
+pub fn write_team_zones(
+    output_path: &Path,
+    mut videos: Vec<ZoneCounts>,
+    incomplete: bool,
+) -> io::Result<()> {
+    videos.sort_by(|a, b| a.video_id.cmp(&b.video_id));
+    let run = summarize_zones(&videos);
+
+    let mut csv = String::from("video_id,team,third,channel,dribbles,tackles,contested\n");
+    for counts in run.iter().chain(&videos) {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{}",
+            counts.video_id,
+            counts.team.as_deref().unwrap_or("unknown"),
+            counts
+                .third
+                .map_or("unknown".to_string(), |t| t.to_string()),
+            counts
+                .channel
+                .map_or("unknown".to_string(), |c| c.to_string()),
+            counts.dribbles,
+            counts.tackles,
+            counts.contested
+        );
+    }
+
+    let export = TeamZonesExport {
+        info: ExportInfo {
+            version: "team_zones_1.0".to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            incomplete,
+        },
+        run,
+        videos,
+    };
+    fs::create_dir_all(output_path)?;
+    fs::write(output_path.join(TEAM_ZONES_CSV), csv)?;
+    fs::write(
+        output_path.join(TEAM_ZONES_FILE),
+        serde_json::to_string_pretty(&export)?,
+    )
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventDifficultyExport {
+    pub info: ExportInfo,
+    /// Dribbles from the hardest to the easiest
+    pub events: Vec<EventDifficulty>,
+}
+
+/// Sorts `events` from the hardest to the easiest, the earliest first on ties.
+pub fn rank_by_difficulty(events: &mut [EventDifficulty]) {
+    events.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| (&a.video_id, a.start_frame).cmp(&(&b.video_id, b.start_frame)))
+    });
+}
+
+/// Writes `events`, ranked by [`rank_by_difficulty`], to [`EVENT_DIFFICULTY_FILE`] and
+/// [`EVENT_DIFFICULTY_CSV`] in `output_path`.
+pub fn write_event_difficulty(
+    output_path: &Path,
+    mut events: Vec<EventDifficulty>,
+    incomplete: bool,
+) -> io::Result<()> {
+    rank_by_difficulty(&mut events);
+
+    let mut csv = String::from(
+        "rank,video_id,start_frame,end_frame,track_id,jersey,team,inner_defender_secs,\
+         outer_defender_secs,mean_speed,third,channel,score\n",
+    );
+    for (rank, e) in events.iter().enumerate() {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{:.2},{:.2},{:.2},{},{},{:.3}",
+            rank + 1,
+            e.video_id,
+            e.start_frame,
+            e.end_frame,
+            e.track_id,
+            e.jersey.as_deref().unwrap_or_default(),
+            e.team.as_deref().unwrap_or_default(),
+            e.inner_defender_secs,
+            e.outer_defender_secs,
+            e.mean_speed,
+            e.third.map_or("unknown".to_string(), |t| t.to_string()),
+            e.channel.map_or("unknown".to_string(), |c| c.to_string()),
+            e.score
+        );
+    }
+
+    let export = EventDifficultyExport {
+        info: ExportInfo {
+            version: "event_difficulty_1.0".to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            incomplete,
+        },
+        events,
+    };
+    fs::create_dir_all(output_path)?;
+    fs::write(output_path.join(EVENT_DIFFICULTY_CSV), csv)?;
+    fs::write(
+        output_path.join(EVENT_DIFFICULTY_FILE),
+        serde_json::to_string_pretty(&export)?,
+    )
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerStatsExport {
+    pub info: ExportInfo,
+    pub players: Vec<PlayerStats>,
+}
+
+/// Writes `players` to [`PLAYER_STATS_FILE`] and [`PLAYER_STATS_CSV`] in `output_path`,
+/// sorted by video and track id.
+pub fn write_player_stats(
+    output_path: &Path,
+    mut players: Vec<PlayerStats>,
+    incomplete: bool,
+) -> io::Result<()> {
+    players.sort_by(|a, b| (&a.video_id, a.track_id).cmp(&(&b.video_id, b.track_id)));
+
+    let mut csv = String::from(
+        "video_id,track_id,jersey,team,dribbles_attempted,dribbles_completed,contested,\
+         tackles_suffered,tackles_made,mean_defenders_beaten,mean_duration_frames,\
+         mean_duration_secs,distance_carried\n",
+    );
+    for p in &players {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{:.2},{:.1},{:.2},{:.2}",
+            p.video_id,
+            p.track_id,
+            p.jersey.as_deref().unwrap_or_default(),
+            p.team.as_deref().unwrap_or_default(),
+            p.dribbles_attempted,
+            p.dribbles_completed,
+            p.contested,
+            p.tackles_suffered,
+            p.tackles_made,
+            p.mean_defenders_beaten,
+            p.mean_duration_frames,
+            p.mean_duration_secs,
+            p.distance_carried
+        );
+    }
+
+    let export = PlayerStatsExport {
+        info: ExportInfo {
+            version: "player_stats_1.0".to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            incomplete,
+        },
+        players,
+    };
+    fs::create_dir_all(output_path)?;
+    fs::write(output_path.join(PLAYER_STATS_CSV), csv)?;
+    fs::write(
+        output_path.join(PLAYER_STATS_FILE),
+        serde_json::to_string_pretty(&export)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::{Attribute, BboxPitch};
+    use crate::dribbling_detection::dribble_models::{Ball, Player};
+
+    fn frame(frame_number: u32, players: &[(u32, f64)]) -> DribbleFrame {
+        DribbleFrame {
+            frame_number,
+            players: players
+                .iter()
+                .map(|&(id, x)| Player {
+                    id,
+                    x,
+                    y: 0.0,
+                    velocity: (0.0, 0.0),
+                    acceleration: (0.0, 0.0),
+                    within_inner_rad: false,
+                    team: None,
+                })
+                .collect(),
+            ball: Ball::new(0.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn test_player_stats() {
+        let mut recorder = TrackRecorder::new();
+        // Player 1 carries the ball past 2 and 3, then loses it to 4
+        for f in 0..10 {
+            let x = f64::from(f);
+            recorder.process_frame(&frame(f, &[(1, x), (2, 2.5), (3, 6.5), (4, 20.0)]), &[]);
+        }
+        for f in 10..20 {
+            recorder.process_frame(&frame(f, &[(1, 9.0), (4, 9.5)]), &[]);
+        }
+
+        let mut dribble = DribbleEvent::new(1, 0, "SNGS-001".to_string());
+        dribble.end_frame = Some(9);
+        dribble.detected_dribble = true;
+        let mut tackle = DribbleEvent::new(1, 12, "SNGS-001".to_string());
+        tackle.end_frame = Some(15);
+        tackle.detected_tackle = true;
+        tackle.ever_contested = true;
+        let possession = |id, player_id, start_frame, end_frame| Possession {
+            id,
+            player_id,
+            team: None,
+            start_frame,
+            end_frame,
+        };
+        let possessions = [possession(0, 1, 0, 13), possession(1, 4, 14, 19)];
+
+        let stats = recorder.player_stats("SNGS-001", &[dribble, tackle], &possessions, 1.0, 25.0);
+        assert_eq!(stats.len(), 2);
+        let carrier = &stats[0];
+        assert_eq!(carrier.track_id, 1);
+        assert_eq!(carrier.dribbles_attempted, 2);
+        assert_eq!(carrier.dribbles_completed, 1);
+        assert_eq!(carrier.contested, 1);
+        assert_eq!(carrier.tackles_suffered, 1);
+        assert_eq!(carrier.mean_defenders_beaten, 2.0);
+        assert_eq!(carrier.mean_duration_frames, 7.0);
+        assert!((carrier.mean_duration_secs - 0.28).abs() < 1e-9);
+        assert!((carrier.distance_carried - 9.0).abs() < 1e-9);
+
+        let tackler = &stats[1];
+        assert_eq!((tackler.track_id, tackler.tackles_made), (4, 1));
+        assert_eq!(tackler.dribbles_attempted, 0);
+    }
+
+    #[test]
+    fn test_event_difficulty() {
+        let mut recorder = TrackRecorder::new();
+        // Player 1 runs past 2, then stands next to 4
+        for f in 0..10 {
+            recorder.process_frame(&frame(f, &[(1, f64::from(f)), (2, 5.0)]), &[]);
+        }
+        for f in 10..20 {
+            recorder.process_frame(&frame(f, &[(1, 9.0), (4, 9.5)]), &[]);
+        }
+        let dribble = |start_frame, end_frame| {
+            let mut event = DribbleEvent::new(1, start_frame, "SNGS-001".to_string());
+            event.end_frame = Some(end_frame);
+            event.detected_dribble = true;
+            event
+        };
+        let mut tackle = dribble(15, 19);
+        tackle.detected_dribble = false;
+        tackle.detected_tackle = true;
+        let events = [dribble(10, 19), dribble(0, 9), tackle];
+
+        let pitch = PitchModel::default();
+        let mut scores = recorder.event_difficulty("SNGS-001", &events, 1.0, 3.0, 25.0, &pitch);
+        assert_eq!(scores.len(), 2);
+        rank_by_difficulty(&mut scores);
+        // 2 is in the inner zone for 1 frame and only in the outer zone for 4, and player 1
+        // moves 9 in 0.4 seconds
+        let run = &scores[0];
+        assert_eq!(run.start_frame, 0);
+        assert!((run.inner_defender_secs - 0.04).abs() < 1e-9);
+        assert!((run.outer_defender_secs - 0.16).abs() < 1e-9);
+        assert!((run.mean_speed - 22.5).abs() < 1e-9);
+        assert!((run.score - 0.12 * 4.75).abs() < 1e-9);
+        assert_eq!(run.third, None);
+        // 4 is in the inner zone for all 10 frames, but player 1 stands still
+        let standing = &scores[1];
+        assert!((standing.score - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_zone_counts() {
+        let annotation = |track_id, x, y, team: &str| Annotation {
+            track_id: Some(track_id),
+            bbox_pitch: Some(BboxPitch {
+                x_bottom_left: x,
+                y_bottom_left: y,
+                x_bottom_right: x,
+                y_bottom_right: y,
+                x_bottom_middle: x,
+                y_bottom_middle: y,
+            }),
+            attributes: Some(Attribute {
+                role: None,
+                jersey: Some("10".to_string()),
+                team: Some(team.to_string()),
+            }),
+            ..Default::default()
+        };
+        let mut recorder = TrackRecorder::new();
+        let mut players = frame(0, &[(1, 0.0), (2, 0.0), (4, 0.0)]);
+        players.players[0].team = Some("left".to_string());
+        players.players[1].team = Some("right".to_string());
+        let annotations = [
+            annotation(1, 40.0, 30.0, "left"),
+            annotation(2, 40.0, 0.0, "right"),
+            annotation(4, 40.0, 30.0, "left"),
+        ];
+        recorder.process_frame(&players, &annotations);
+
+        let event = |holder, tackle| {
+            let mut event = DribbleEvent::new(holder, 0, "SNGS-001".to_string());
+            event.end_frame = Some(0);
+            event.detected_tackle = tackle;
+            event.detected_dribble = !tackle;
+            event
+        };
+        let events = [
+            event(1, false),
+            event(1, true),
+            event(2, false),
+            event(3, false),
+            event(4, false),
+        ];
+        let pitch = PitchModel::default();
+        let counts = recorder.zone_counts("SNGS-001", &events, &pitch);
+        assert_eq!(counts.len(), 4);
+        // Player 3 has no team and no pitch coordinates
+        assert_eq!((counts[0].team.as_deref(), counts[0].third), (None, None));
+        assert_eq!(counts[0].channel, None);
+        // Player 4 has pitch coordinates but no team, so only the channel is known
+        assert_eq!((counts[1].team.as_deref(), counts[1].third), (None, None));
+        assert_eq!(counts[1].channel, Some(Channel::Flank));
+        assert_eq!(counts[2].team.as_deref(), Some("left"));
+        assert_eq!(counts[2].third, Some(Third::Attacking));
+        assert_eq!(counts[2].channel, Some(Channel::Flank));
+        assert_eq!((counts[2].dribbles, counts[2].tackles), (1, 1));
+        // The right team attacks the goal at negative x
+        assert_eq!(counts[3].third, Some(Third::Defensive));
+        assert_eq!(counts[3].channel, Some(Channel::Center));
+
+        let mut other_video = counts.clone();
+        other_video
+            .iter_mut()
+            .for_each(|c| c.video_id = "SNGS-002".to_string());
+        let run = summarize_zones(&[counts, other_video].concat());
+        assert_eq!(run.len(), 4);
+        assert_eq!(run[2].video_id, RUN_TOTAL);
+        assert_eq!((run[2].dribbles, run[2].tackles), (2, 2));
+    }
+}
@@ -0,0 +1,202 @@
+//! Approximate ground truth from the SoccerNet action of every clip, to evaluate on public data
+//! before hand labels exist. Game-state clips are cut around an action whose class and position
+//! are in the `action_class` and `action_position` of their `info`. Clips of the configured
+//! action classes get one event around the action: a dribble leading up to a shot, or a duel
+//! for a foul. The boundaries are a fixed window around the action, so match with a low IoU
+//! threshold.
+
+use crate::config::DEFAULT_FRAME_RATE;
+use crate::data::models::{
+    ClipIdentity, ClipTiming, DribbleEventsExport, DribbleLabel, ExportInfo, Info, VideoData,
+    VideoDribbleEvents,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the derived ground truth in the output directory
+pub const BOOTSTRAP_FILE: &str = "bootstrap_ground_truth.json";
+
+/// The `[bootstrap]` section: which actions become which events, and the window around them.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BootstrapConfig {
+    /// Action classes that are preceded by a dribble
+    pub dribble_actions: Vec<String>,
+    /// Action classes of a duel for the ball, exported as tackles
+    pub tackle_actions: Vec<String>,
+    /// Start of the event before the action
+    pub seconds_before: f64,
+    /// End of the event after the action
+    pub seconds_after: f64,
+}
+
+impl Default for BootstrapConfig {
+    fn default() -> Self {
+        let labels = |labels: &[&str]| labels.iter().map(|l| l.to_string()).collect();
+        Self {
+            dribble_actions: labels(&["Shots on target", "Shots off target"]),
+            tackle_actions: labels(&["Foul"]),
+            seconds_before: 2.0,
+            seconds_after: 1.0,
+        }
+    }
+}
+
+/// Frame of the clip's action, starting at 1, or `None` if it has no action or the action is
+/// outside of the clip.
+///
+/// Like `clip_start`, the position is in milliseconds since the start of the half. Positions
+/// before `clip_start`, e.g. of clips without clip times, are taken to be relative to the start
+/// of the clip.
+pub fn action_frame(info: &Info) -> Option<u32> {
+    let position: u64 = info.action_position.as_deref()?.trim().parse().ok()?;
+    let clip_start: u64 = info.clip_start.trim().parse().unwrap_or(0);
+    let offset = if position >= clip_start {
+        position - clip_start
+    } else {
+        position
+    };
+    let frame_rate = if info.frame_rate > 0.0 {
+        info.frame_rate as f64
+    } else {
+        DEFAULT_FRAME_RATE
+    };
+    let frame = (offset as f64 / 1000.0 * frame_rate).round() as u32 + 1;
+    (info.seq_length == 0 || frame <= info.seq_length).then_some(frame)
+}
+
+/// The event around the clip's action, if its class is one of the configured ones.
+pub fn action_event(info: &Info, config: &BootstrapConfig) -> Option<DribbleLabel> {
+    let class = info.action_class.as_deref()?.trim();
+    let is_class = |classes: &[String]| classes.iter().any(|c| c.eq_ignore_ascii_case(class));
+    let tackle = if is_class(&config.tackle_actions) {
+        true
+    } else if is_class(&config.dribble_actions) {
+        false
+    } else {
+        return None;
+    };
+
+    let frame = action_frame(info)?;
+    let frame_rate = if info.frame_rate > 0.0 {
+        info.frame_rate as f64
+    } else {
+        DEFAULT_FRAME_RATE
+    };
+    let before = (config.seconds_before.max(0.0) * frame_rate).round() as u32;
+    let after = (config.seconds_after.max(0.0) * frame_rate).round() as u32;
+    let start_frame = frame.saturating_sub(before).max(1);
+    let mut end_frame = frame + after;
+    if info.seq_length > 0 {
+        end_frame = end_frame.min(info.seq_length);
+    }
+
+    let timing = ClipTiming::new(info);
+    Some(DribbleLabel {
+        finished: true,
+        detected_dribble: !tackle,
+        detected_tackle: tackle,
+        ever_contested: tackle,
+        // The player is not known
+        possession_holder: 0,
+        start_frame,
+        end_frame: Some(end_frame),
+        possession_id: None,
+        start_seconds: timing.seconds(start_frame),
+        end_seconds: timing.seconds(end_frame),
+        start_game_time: timing.game_time(start_frame),
+        end_game_time: timing.game_time(end_frame),
+        confidence: None,
+    })
+}
+
+/// The ground truth of a video of `subset`, or `None` if its action is not converted. Clips of
+/// other actions are left out rather than exported without events, as they may well contain
+/// dribbles.
+pub fn bootstrap_video(
+    video_data: &VideoData,
+    subset: Option<&str>,
+    config: &BootstrapConfig,
+) -> Option<VideoDribbleEvents> {
+    let info = &video_data.labels.info;
+    let event = action_event(info, config)?;
+    Some(VideoDribbleEvents {
+        video_id: video_data.name(),
+        dataset: video_data.dataset.clone(),
+        subset: subset.map(str::to_string),
+        clip: ClipIdentity::from_info(info),
+        frame_rate: ClipTiming::new(info).frame_rate,
+        dribble_events: vec![event],
+        shielding_events: Vec::new(),
+    })
+}
+
+/// Writes the videos to [`BOOTSTRAP_FILE`] in `output_dir`, in the format of
+/// `dribble_events.json`.
+pub fn write_bootstrap(output_dir: &Path, videos: Vec<VideoDribbleEvents>) -> io::Result<()> {
+    let export = DribbleEventsExport {
+        info: ExportInfo {
+            version: "dribble_events_1.0".to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            incomplete: false,
+        },
+        videos,
+    };
+    fs::create_dir_all(output_dir)?;
+    fs::write(
+        output_dir.join(BOOTSTRAP_FILE),
+        serde_json::to_string_pretty(&export)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_event() {
+        let info = Info {
+            action_class: Some("Foul".to_string()),
+            action_position: Some("1030000".to_string()),
+            clip_start: "1020000".to_string(),
+            clip_stop: "1050000".to_string(),
+            game_time_start: Some("1 - 17:00".to_string()),
+            frame_rate: 25.0,
+            seq_length: 750,
+            ..Default::default()
+        };
+        let config = BootstrapConfig::default();
+
+        // 10 seconds into the clip
+        assert_eq!(action_frame(&info), Some(251));
+        let event = action_event(&info, &config).unwrap();
+        assert!(event.detected_tackle && !event.detected_dribble);
+        assert_eq!((event.start_frame, event.end_frame), (201, Some(276)));
+        assert_eq!(event.start_seconds, Some(8.0));
+        assert_eq!(event.start_game_time.as_deref(), Some("1 - 17:08"));
+
+        // Classes are compared without case, and events end with the clip
+        let shot = Info {
+            action_class: Some("shots on target".to_string()),
+            action_position: Some("1049000".to_string()),
+            ..info.clone()
+        };
+        let event = action_event(&shot, &config).unwrap();
+        assert!(event.detected_dribble);
+        assert_eq!(event.end_frame, Some(750));
+
+        let corner = Info {
+            action_class: Some("Corner".to_string()),
+            ..info.clone()
+        };
+        assert!(action_event(&corner, &config).is_none());
+        let after_clip = Info {
+            action_position: Some("1080000".to_string()),
+            ..info
+        };
+        assert_eq!(action_frame(&after_clip), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! Per-video checkpoints, so a run that crashes or is interrupted can be resumed with
+//! `--resume` instead of reprocessing every video.
+
+use crate::pipeline::ProcessedVideo;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the checkpoint file in the output directory
+pub const CHECKPOINT_FILE: &str = "checkpoint.jsonl";
+
+/// Appends every finished video to `checkpoint.jsonl` as a single JSON line. Videos are
+/// recorded from several threads, so writes are serialized through a lock.
+pub struct Checkpoint {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Checkpoint {
+    /// Opens the checkpoint file in `output_dir`. When resuming, the videos that were already
+    /// recorded are returned and new videos are appended; otherwise the file is started over.
+    pub fn open(output_dir: &Path, resume: bool) -> io::Result<(Self, Vec<ProcessedVideo>)> {
+        fs::create_dir_all(output_dir)?;
+        let path = output_dir.join(CHECKPOINT_FILE);
+
+        let finished = if resume && path.exists() {
+            read_checkpoint(&path)?
+        } else {
+            Vec::new()
+        };
+
+        // Rewriting the valid entries drops a line that was cut off by a crash
+        let mut file = File::create(&path)?;
+        for video in &finished {
+            writeln!(file, "{}", serde_json::to_string(video)?)?;
+        }
+        file.flush()?;
+        let file = OpenOptions::new().append(true).open(&path)?;
+
+        Ok((
+            Self {
+                path,
+                file: Mutex::new(file),
+            },
+            finished,
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a finished video to the checkpoint file.
+    pub fn record(&self, video: &ProcessedVideo) -> io::Result<()> {
+        let line = format!("{}\n", serde_json::to_string(video)?);
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+}
+
+/// Reads the videos recorded in a checkpoint file. Lines that can not be parsed, such as a
+/// last line that was only partly written, are skipped.
+pub fn read_checkpoint(path: &Path) -> io::Result<Vec<ProcessedVideo>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut videos = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        match serde_json::from_str::<ProcessedVideo>(&line) {
+            Ok(video) => videos.push(video),
+            Err(e) if !line.trim().is_empty() => {
+                eprintln!("Skipping invalid line in {}: {}", path.display(), e)
+            }
+            Err(_) => {}
+        }
+    }
+    Ok(videos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dribbling_detection::dribble_models::DribbleEvent;
+
+    fn processed_video(file_name: &str) -> ProcessedVideo {
+        ProcessedVideo {
+            file_name: file_name.to_string(),
+            dataset: None,
+            dribble_events: vec![DribbleEvent::new(3, 10, file_name.to_string())],
+            shielding_events: Vec::new(),
+            possessions: Vec::new(),
+            player_stats: Vec::new(),
+            zone_counts: Vec::new(),
+            event_difficulty: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_resume_checkpoint() {
+        let output_dir = std::env::temp_dir().join(format!("checkpoint_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+
+        let (checkpoint, finished) = Checkpoint::open(&output_dir, true).unwrap();
+        assert!(finished.is_empty());
+        checkpoint.record(&processed_video("SNGS-001")).unwrap();
+        checkpoint.record(&processed_video("SNGS-002")).unwrap();
+        drop(checkpoint);
+
+        // Simulate a crash while writing the next video
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(output_dir.join(CHECKPOINT_FILE))
+            .unwrap();
+        write!(file, "{{\"file_name\": \"SNGS-0").unwrap();
+        drop(file);
+
+        let (checkpoint, finished) = Checkpoint::open(&output_dir, true).unwrap();
+        let names: Vec<&str> = finished.iter().map(|v| v.file_name.as_str()).collect();
+        assert_eq!(names, vec!["SNGS-001", "SNGS-002"]);
+        assert_eq!(finished[0].dribble_events[0].start_frame, 10);
+        checkpoint.record(&processed_video("SNGS-003")).unwrap();
+        assert_eq!(read_checkpoint(checkpoint.path()).unwrap().len(), 3);
+
+        // Without resuming, the checkpoint starts over
+        let (_, finished) = Checkpoint::open(&output_dir, false).unwrap();
+        assert!(finished.is_empty());
+        assert!(read_checkpoint(&output_dir.join(CHECKPOINT_FILE))
+            .unwrap()
+            .is_empty());
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
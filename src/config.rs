@@ -1,27 +1,232 @@
-use serde::Deserialize;
+use crate::analytics::AnalyticsConfig;
+use crate::bootstrap::BootstrapConfig;
+use crate::data::splits::Split;
+use crate::dribbling_detection::sweep::SweepConfig;
+use crate::evaluation::EvaluationConfig;
+use crate::preprocessing::PreprocessingConfig;
+use crate::utils::colors::ColorsConfig;
+use crate::utils::config_reload::ConfigSource;
+use crate::utils::draw_style::DrawSizes;
+use crate::utils::event_clips::EventExportFormat;
+use crate::utils::hud::HudCorner;
+use crate::utils::layout::Layout;
+use crate::utils::pitch_model::PitchModel;
+use crate::utils::subtitles::SubtitleFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Clone, Debug, Deserialize)]
+/// Prefix of the environment variables that override config keys. Sections and keys are
+/// separated by double underscores, e.g. `DRIBBLE__DATA__OUTPUT_PATH` for `data.output_path`.
+pub const ENV_PREFIX: &str = "DRIBBLE__";
+/// Environment variable that selects a profile, if `--profile` is not given
+pub const PROFILE_ENV: &str = "DRIBBLE__PROFILE";
+/// Frame rate of the sequences without a `frame_rate`
+pub const DEFAULT_FRAME_RATE: f64 = 25.0;
+/// Commented config with the default values, written by `--init-config`
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+/// Replaces the secrets of configs that are written to the outputs
+pub const REDACTED: &str = "<redacted>";
+
+/// How the frames of the videos are handled while detecting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VideoMode {
+    /// Frames are not read, detection only uses the annotations
+    #[default]
+    None,
+    /// A video with the overlay of every clip is written to the output directory
+    Download,
+    /// Frames are shown in a window, which needs a display
+    Display,
+    /// The overlay is exported as transparent PNG layers per frame
+    Layers,
+    /// Videos with the annotations are written without running the detector
+    RenderOnly,
+}
+
+/// Verbosity of the log output. Only `debug` prints more, and `none` prints less.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+    None,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct GeneralConfig {
+    /// Defaults to the number of CPU cores
     pub num_cores: u32,
-    pub log_level: String,
-    pub video_mode: String,
+    pub log_level: LogLevel,
+    pub video_mode: VideoMode,
 
     /// If `true`, we will parse an existing dribble_events.json and let the user
     /// step through each clip to label it as d/t/n.
     pub review_mode: Option<bool>,
+
+    /// If `true`, runs that process no videos or detect no events exit with a non-zero code.
+    pub fail_on_empty: Option<bool>,
+
+    /// Number of frames to read ahead on a background thread while a frame is processed
+    pub prefetch_frames: Option<usize>,
+
+    /// If `false`, no progress bars are shown. They are never shown in display mode.
+    pub progress: Option<bool>,
+
+    /// Seconds a video may take before it is stopped and reported in `failures.json`
+    pub video_timeout_secs: Option<u64>,
+
+    /// Frames one core processes per second, used by `--dry-run` to estimate the runtime
+    pub dry_run_fps: Option<f64>,
+
+    /// Name of the run, e.g. an experiment, that replaces `{tag}` in `output_path`
+    pub tag: Option<String>,
+
+    /// If `true`, the events of every video are appended to `dribble_events.jsonl` as soon
+    /// as it is finished
+    pub stream_events: Option<bool>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+impl Default for GeneralConfig {
+    fn default() -> Self {
+        Self {
+            num_cores: std::thread::available_parallelism().map_or(1, |n| n.get() as u32),
+            log_level: LogLevel::default(),
+            video_mode: VideoMode::default(),
+            review_mode: None,
+            fail_on_empty: None,
+            prefetch_frames: None,
+            progress: None,
+            video_timeout_secs: None,
+            dry_run_fps: None,
+            tag: None,
+            stream_events: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct DataConfig {
+    /// Directory of the subsets, a `.zip` archive containing them, or an `s3://` or `http(s)://`
+    /// URL of a directory in remote storage
     pub data_path: String,
     pub dribble_events_path: String,
     pub subsets: Vec<String>,
+    /// If set, only this subset is processed instead of all `subsets`
+    pub active_subset: Option<String>,
+    /// If set, only sequences whose directory name matches one of these patterns are processed.
+    /// `*` matches any number of characters and `?` a single character.
+    pub include_sequences: Option<Vec<String>>,
+    /// If set, only sequences whose number (the last digits of the directory name) is within
+    /// this inclusive range are processed
+    pub sequence_range: Option<[u32; 2]>,
+    /// Maximum number of videos processed per subset
+    pub max_videos: Option<usize>,
+    /// If true, the parsed labels are cached in a binary file next to each
+    /// `Labels-GameState.json`, which is much faster to load than the JSON
+    pub labels_cache: Option<bool>,
+    /// If true, annotations that can not be parsed are skipped instead of failing the whole
+    /// labels file. The skipped annotations are listed by `--validate`.
+    pub lenient_labels: Option<bool>,
+    /// If set, annotations without a `bbox_pitch` get one projected from their `bbox_image`
+    /// with the homographies in this JSON file, relative to the sequence directory
+    pub homography_file: Option<String>,
+    /// Name of the labels file of a sequence, `Labels-GameState.json` by default. `{sequence}`
+    /// is replaced by the name of the sequence directory.
+    pub labels_file_name: Option<String>,
+    /// Directory of the frames in a sequence directory, overriding the `im_dir` of the labels
+    pub image_dir_override: Option<String>,
+    /// If set, the labels are read from this directory next to the sequence directories
+    /// instead of from the sequence directories themselves
+    pub annotations_dir: Option<String>,
     pub output_path: String,
     pub huggingface_dataset_url: String,
+    /// If set, `--download` copies a `<subset>.zip` per subset from this `s3://` or `http(s)://`
+    /// URL instead of downloading from `huggingface_dataset_url`
+    pub archive_url: Option<String>,
+    /// If set, the sequences of all these datasets are processed instead of `data_path`
+    pub datasets: Option<Vec<DatasetConfig>>,
+}
+
+impl Default for DataConfig {
+    fn default() -> Self {
+        Self {
+            data_path: "data".to_string(),
+            dribble_events_path: "data/output/dribble_events.json".to_string(),
+            subsets: ["train", "valid", "test", "challenge"]
+                .map(str::to_string)
+                .to_vec(),
+            active_subset: None,
+            include_sequences: None,
+            sequence_range: None,
+            max_videos: None,
+            labels_cache: None,
+            lenient_labels: None,
+            homography_file: None,
+            labels_file_name: None,
+            image_dir_override: None,
+            annotations_dir: None,
+            output_path: "data/output".to_string(),
+            huggingface_dataset_url: "https://datasets-server.huggingface.co/first-rows?\
+                                      dataset=SoccerNet%2FSN-GSR-2025&config=default"
+                .to_string(),
+            archive_url: None,
+            datasets: None,
+        }
+    }
+}
+
+/// A dataset root with its own subsets and category names, e.g. to process SoccerNet and
+/// in-house clips in the same run.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DatasetConfig {
+    /// Added to the exported videos of this dataset
+    pub name: String,
+    pub data_path: String,
+    /// Defaults to the `subsets` of `[data]`
+    pub subsets: Option<Vec<String>>,
+    /// Maps category names of this dataset to "ball", "player", "goalkeeper" or "referee"
+    #[serde(default)]
+    pub categories: HashMap<String, String>,
+}
+
+/// Settings for sequences annotated with a COCO file instead of `Labels-GameState.json`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CocoConfig {
+    /// Name of the COCO annotation file in each sequence directory
+    pub labels_file: String,
+    /// Directory of the frames in each sequence directory
+    pub image_dir: String,
+    pub frame_rate: f32,
+    /// Maps COCO category names to "ball", "player", "goalkeeper" or "referee". Annotations of
+    /// other categories are ignored.
+    pub categories: HashMap<String, String>,
+}
+
+impl Default for CocoConfig {
+    fn default() -> Self {
+        Self {
+            labels_file: "annotations.json".to_string(),
+            image_dir: "img1".to_string(),
+            frame_rate: DEFAULT_FRAME_RATE,
+            categories: HashMap::new(),
+        }
+    }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct DribblingDetectionConfig {
     pub use_2d: bool,
     pub outer_threshold: u32,
@@ -34,9 +239,368 @@ pub struct DribblingDetectionConfig {
     pub ignore_teams: bool,
     pub outer_in_threshold: u32,
     pub outer_out_threshold: u32,
+
+    /// Number of frames nobody may have the ball before a possession spell ends (default 5)
+    pub possession_max_gap: Option<u32>,
+}
+
+impl Default for DribblingDetectionConfig {
+    fn default() -> Self {
+        Self {
+            use_2d: false,
+            outer_threshold: 3,
+            inner_threshold: 4,
+            frame_skip: 1,
+            min_duration: 1.3,
+            inner_radius: 0.8,
+            outer_radius: 2.7,
+            ignore_person_classes: true,
+            ignore_teams: true,
+            outer_in_threshold: 2,
+            outer_out_threshold: 8,
+            possession_max_gap: None,
+        }
+    }
+}
+
+/// Settings for detecting shielding (holder keeps the ball while standing still under pressure).
+/// Distances are in the same units as the dribbling detection radii.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ShieldingConfig {
+    pub enabled: bool,
+    /// Minimum number of frames for a shielding event
+    pub min_frames: u32,
+    /// Maximum distance the holder may move during the event
+    pub max_movement: f64,
+}
+
+impl Default for ShieldingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_frames: 25,
+            max_movement: 1.0,
+        }
+    }
+}
+
+/// Settings for refining event end frames to the frame where the ball was released, found as
+/// a spike in ball speed. Distances are in the same units as the dribbling detection radii.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BallSpeedConfig {
+    pub enabled: bool,
+    /// Minimum ball movement per frame counted as a pass or shot
+    pub spike_threshold: f64,
+    /// Maximum number of frames an end frame may be moved
+    pub search_window: u32,
+}
+
+impl Default for BallSpeedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            spike_threshold: 1.5,
+            search_window: 10,
+        }
+    }
+}
+
+/// A smoothing filter for one signal. Selected with `filter = "<name>"`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(tag = "filter", rename_all = "snake_case")]
+pub enum SmoothingFilterConfig {
+    #[default]
+    None,
+    /// Mean of the last `window` frames
+    MovingAverage { window: usize },
+    /// Exponential moving average, a higher `alpha` (0-1) follows the signal more closely
+    Ema { alpha: f64 },
+    /// Constant velocity Kalman filter
+    Kalman {
+        process_noise: f64,
+        measurement_noise: f64,
+    },
+    /// Polynomial fit of degree `order` over the last `window` frames
+    SavitzkyGolay { window: usize, order: usize },
+}
+
+/// Smoothing filters for the positions and velocities used by the detectors. Signals without
+/// a filter are not smoothed.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SmoothingConfig {
+    #[serde(default)]
+    pub ball: SmoothingFilterConfig,
+    #[serde(default)]
+    pub players: SmoothingFilterConfig,
+    #[serde(default)]
+    pub velocity: SmoothingFilterConfig,
+}
+
+/// Sanity filters for the annotations of one category, applied before the player and ball
+/// models are created. Sizes are image bounding box sizes in pixels.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AnnotationFilterConfig {
+    /// Annotations of tracks with fewer frames are dropped
+    pub min_track_length: Option<usize>,
+    pub min_bbox_height: Option<f64>,
+    pub max_bbox_height: Option<f64>,
+    pub min_bbox_width: Option<f64>,
+    pub max_bbox_width: Option<f64>,
+}
+
+/// Settings for the audit pack: a random sample of detected events and non-event intervals,
+/// rendered as clips for a quick quality check of a run.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// Number of events and number of non-event intervals in the pack
+    pub sample_size: usize,
+    /// Length of the non-event clips in frames
+    pub clip_length: u32,
+    /// Seed of the random sample. A random seed is used (and written to the manifest) if unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_size: 10,
+            clip_length: 100,
+            seed: None,
+        }
+    }
+}
+
+/// How imported events are merged with the detected ones, see
+/// [`crate::data::event_import::merge_imported_events`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    #[default]
+    Union,
+    Intersection,
+    Priority,
+}
+
+/// Event files of other detectors that are merged with the detected events before export.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ImportConfig {
+    /// `.json` files in the format of `dribble_events.json`, or `.csv` files
+    pub files: Vec<String>,
+    pub strategy: MergeStrategy,
+}
+
+/// CVAT annotation files of the detected events, to import them as pre-annotations.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CvatConfig {
+    pub enabled: bool,
+    /// Maps the event kinds "dribble" and "tackle" to the label names of the CVAT project.
+    /// Unmapped kinds keep their name.
+    pub labels: HashMap<String, String>,
+}
+
+impl Default for CvatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            labels: HashMap::new(),
+        }
+    }
+}
+
+/// Results of the detected events in the SoccerNet action spotting format.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SpottingConfig {
+    pub enabled: bool,
+    /// Maps the event kinds "dribble" and "tackle" to spotting labels. Unmapped kinds keep
+    /// their name.
+    pub labels: HashMap<String, String>,
+    /// Confidence of every prediction, as the detector does not score its events
+    pub confidence: f64,
+    /// Maps game ids to the game directories of the SoccerNet data, e.g.
+    /// "england_epl/2014-2015/2015-02-21 - 18-00 Chelsea 3 - 1 Burnley". Unmapped games keep
+    /// their id.
+    pub game_dirs: HashMap<String, String>,
+}
+
+impl Default for SpottingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            labels: HashMap::new(),
+            confidence: 1.0,
+            game_dirs: HashMap::new(),
+        }
+    }
+}
+
+/// Webhooks that are notified when a run completes or fails, e.g. Slack or Teams incoming
+/// webhooks, so long unattended runs do not have to be watched.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// Notifications are only sent if enabled, also with `--webhook`
+    pub enabled: bool,
+    /// URLs the summary JSON is POSTed to
+    pub webhooks: Vec<String>,
+    pub on_complete: bool,
+    /// Runs fail if they are interrupted, a video has an error or the binary panics
+    pub on_failure: bool,
+    /// Also notify every time this many more events have been detected
+    pub every_n_events: Option<usize>,
+    pub timeout_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            webhooks: Vec::new(),
+            on_complete: true,
+            on_failure: true,
+            every_n_events: None,
+            timeout_secs: 10,
+        }
+    }
+}
+
+/// Settings for SoccerNet action spotting labels (`Labels-v2.json`), whose ground truth actions
+/// are attached to the videos.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ActionsConfig {
+    /// Path of the labels file relative to the sequence directory
+    pub labels_file: String,
+    /// Actions after which the ball is out of play
+    pub dead_ball_labels: Vec<String>,
+    /// Actions that bring the ball back into play
+    pub restart_labels: Vec<String>,
+    /// Longest dead-ball period, for when there is no restart action
+    pub max_dead_ball_seconds: f64,
+    /// Drop detected events that overlap a dead-ball period
+    pub suppress_dead_ball_events: bool,
+}
+
+impl Default for ActionsConfig {
+    fn default() -> Self {
+        let labels = |labels: &[&str]| labels.iter().map(|l| l.to_string()).collect();
+        Self {
+            labels_file: "Labels-v2.json".to_string(),
+            dead_ball_labels: labels(&["Ball out of play", "Foul", "Offside", "Goal"]),
+            restart_labels: labels(&[
+                "Throw-in",
+                "Corner",
+                "Clearance",
+                "Direct free-kick",
+                "Indirect free-kick",
+                "Penalty",
+                "Kick-off",
+            ]),
+            max_dead_ball_seconds: 30.0,
+            suppress_dead_ball_events: true,
+        }
+    }
+}
+
+/// Settings for assigning sequences to reproducible train/val/test splits.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SplitsConfig {
+    /// Train, val and test ratios
+    pub ratios: [f64; 3],
+    /// Changing the seed reshuffles the splits
+    pub seed: u64,
+    /// If set, only the sequences of this split are processed
+    pub active: Option<Split>,
+}
+
+impl Default for SplitsConfig {
+    fn default() -> Self {
+        Self {
+            ratios: [0.8, 0.1, 0.1],
+            seed: 0,
+            active: None,
+        }
+    }
+}
+
+/// Settings for datasets in S3 (`s3://bucket/...`) or served over HTTP (`https://host/...`).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Overrides the region of the AWS environment
+    pub s3_region: Option<String>,
+    /// Endpoint of an S3-compatible service, e.g. "http://localhost:9000" for MinIO
+    pub s3_endpoint: Option<String>,
+    /// Headers sent with every HTTP request, e.g. an `Authorization` header
+    pub http_headers: HashMap<String, String>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+/// Order of the clips of a review session, see [`crate::review_queue::load_review_queue`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewOrder {
+    /// The order of the events file
+    #[default]
+    File,
+    /// The shortest events first
+    Shortest,
+    /// The events with the lowest `confidence` first
+    LowestConfidence,
+}
+
+impl FromStr for ReviewOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "file" => Ok(Self::File),
+            "shortest" => Ok(Self::Shortest),
+            "lowest_confidence" => Ok(Self::LowestConfidence),
+            _ => Err(format!(
+                "unknown review order \"{}\", expected file, shortest or lowest_confidence",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReviewConfig {
+    /// Tags that can be toggled on a clip in review mode with the number keys 1-9
+    pub tags: Vec<String>,
+    /// Skip the clips that already have a decision in `reviewed_events.json`
+    pub resume: bool,
+    pub order: ReviewOrder,
+    /// Only review events in which a defender contested the ball
+    pub only_contested: bool,
+    /// Only review events of these kinds, "dribble" or "tackle". All kinds if empty
+    pub kinds: Vec<String>,
+    /// Only review the events of these videos. All videos if empty
+    pub videos: Vec<String>,
+    /// Name of the reviewer. Every annotator writes their decisions to their own file
+    pub annotator: Option<String>,
+    /// Playback speed of review mode, e.g. 0.25 for slow motion
+    pub speed: Option<f64>,
+    /// Seconds shown before and after the event, 2 by default
+    pub pre_roll_secs: Option<f64>,
+    pub post_roll_secs: Option<f64>,
+    /// Also write the class of every event frame to `training_labels_frames.csv`
+    pub frame_labels: bool,
+    /// Hide whether the detector called the event a dribble or a tackle until the verdict
+    pub blind: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct VisualizationConfig {
     pub autoplay: bool,
     pub scale_factor: f64,
@@ -48,35 +612,352 @@ pub struct VisualizationConfig {
     pub x_max: f64,
     pub y_min: f64,
     pub y_max: f64,
+
+    /// If `true`, a montage video with one representative frame per detected event
+    /// is written to the output directory after the run.
+    pub event_montage: Option<bool>,
+
+    /// If `true`, a clip with the overlay of every detected event is written to `events/`
+    /// in the output directory after the run.
+    pub event_clips: Option<bool>,
+    /// Frames shown before and after every event in its clip
+    pub event_clip_padding: Option<u32>,
+    /// File format of the event clips: a video, an animated GIF or a strip of frames
+    pub event_export_format: EventExportFormat,
+    /// Every Nth frame of an event is shown in its strip
+    pub event_strip_step: Option<u32>,
+    /// If `true`, an HTML gallery of the detected events is written to `report/` in the output
+    /// directory after the run
+    pub report: Option<bool>,
+
+    /// If set, a subtitle file with the detected events is written next to every video
+    pub subtitles: Option<SubtitleFormat>,
+
+    /// Four character code of the codec of the videos written in download mode, e.g. "avc1"
+    pub video_codec: Option<String>,
+    /// Extension of the videos written in download mode, which selects the container
+    pub video_extension: Option<String>,
+
+    /// If `true`, the frame number, detector state, defender counts, possession holder and
+    /// keyboard shortcuts are drawn on the frames
+    pub hud: Option<bool>,
+    /// Corner of the frame the HUD is drawn in
+    pub hud_corner: HudCorner,
+    /// If `true`, graphs of the pressure on the possession holder and of its speed over the
+    /// last `graph_frames` frames are drawn in the bottom right corner
+    pub graphs: Option<bool>,
+    pub graph_frames: Option<usize>,
+    /// Arrangement of the camera view, the minimap and the event log, see [`Layout`]
+    pub layout: Layout,
+    /// If `true`, a ring is drawn around the possession holder and a line from the holder to
+    /// the ball. Defaults to `true`.
+    pub possession_indicator: Option<bool>,
+
+    /// `dribble_events.json` file with the ground truth dribbles. If set, the ground truth and
+    /// the detector output are shown side by side in display mode.
+    pub comparison_labels: Option<String>,
+
+    /// Colors of the annotations, zones, pitch and event highlights, see [`ColorsConfig`]
+    pub colors: Option<ColorsConfig>,
+
+    /// Dimensions of the pitch drawn on the minimap, see [`PitchModel`]
+    pub pitch: Option<PitchModel>,
+    /// If `true`, the thirds and the flanks of the pitch zones of the team statistics are
+    /// drawn on the minimap
+    pub pitch_zones: Option<bool>,
+
+    /// Line widths, font and dot sizes of the drawings, see [`DrawSizes`]
+    pub sizes: Option<DrawSizes>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+impl Default for VisualizationConfig {
+    fn default() -> Self {
+        Self {
+            autoplay: true,
+            scale_factor: 0.6,
+            minimap_x: 20,
+            minimap_y: 200,
+            minimap_width: 926,
+            minimap_height: 600,
+            x_min: -52.0,
+            x_max: 52.0,
+            y_min: -34.0,
+            y_max: 34.0,
+            event_montage: None,
+            event_clips: None,
+            event_clip_padding: None,
+            event_export_format: EventExportFormat::default(),
+            event_strip_step: None,
+            report: None,
+            subtitles: None,
+            video_codec: None,
+            video_extension: None,
+            hud: None,
+            hud_corner: HudCorner::default(),
+            graphs: None,
+            graph_frames: None,
+            layout: Layout::default(),
+            possession_indicator: None,
+            comparison_labels: None,
+            colors: None,
+            pitch: None,
+            pitch_zones: None,
+            sizes: None,
+        }
+    }
+}
+
+/// The whole config. Every section and key is optional; missing ones get the values of
+/// [`DEFAULT_CONFIG`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Config {
     pub general: GeneralConfig,
     pub data: DataConfig,
     pub dribbling_detection: DribblingDetectionConfig,
     pub visualization: VisualizationConfig,
+    pub shielding: Option<ShieldingConfig>,
+    pub ball_speed: Option<BallSpeedConfig>,
+    pub review: Option<ReviewConfig>,
+    pub coco: Option<CocoConfig>,
+    /// Annotation sanity filters per category name ("ball", "player", "goalkeeper", "referee")
+    pub annotation_filters: Option<HashMap<String, AnnotationFilterConfig>>,
+    pub preprocessing: Option<PreprocessingConfig>,
+    pub smoothing: Option<SmoothingConfig>,
+    pub audit: Option<AuditConfig>,
+    pub cvat: Option<CvatConfig>,
+    pub import: Option<ImportConfig>,
+    pub spotting: Option<SpottingConfig>,
+    pub splits: Option<SplitsConfig>,
+    pub actions: Option<ActionsConfig>,
+    pub storage: Option<StorageConfig>,
+    pub sweep: Option<SweepConfig>,
+    pub evaluation: Option<EvaluationConfig>,
+    pub analytics: Option<AnalyticsConfig>,
+    pub bootstrap: Option<BootstrapConfig>,
+    pub notifications: Option<NotificationsConfig>,
+    /// Keys of the display window by action, e.g. `next_frame = ["right", "l"]`. Actions that
+    /// are not listed keep their default keys, see [`crate::utils::keymap`]
+    pub keybindings: Option<HashMap<String, Vec<String>>>,
+    /// Name of the `[profiles.<name>]` section applied by [`Config::load`], if any
+    pub profile: Option<String>,
+    /// File the config was loaded from, to reload it when it changes
+    #[serde(skip)]
+    pub source: Option<ConfigSource>,
 }
 
 impl Config {
-    /// Applies overrides from environment variables, if set.
+    /// Reads the config file at `path`, and layers the selected profile, the overrides of the
+    /// [`ENV_PREFIX`] environment variables and then those of [`Config::apply_env_overrides`] on
+    /// top of it.
+    ///
+    /// The profile is `profile`, or else the one named by [`PROFILE_ENV`] or the `profile` key of
+    /// the file. Its `[profiles.<name>]` section holds config sections that replace the keys
+    /// they contain, e.g. `[profiles.strict.dribbling_detection]`.
+    pub fn load(path: &Path, profile: Option<&str>) -> io::Result<Config> {
+        // Taken first, so changes while the file is read are noticed by a reload
+        let source = ConfigSource::new(path);
+        let content = fs::read_to_string(path)?;
+        let mut value: toml::Value = toml::from_str(&content).map_err(io::Error::other)?;
+
+        let profiles = match &mut value {
+            toml::Value::Table(table) => table.remove("profiles"),
+            _ => None,
+        };
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| env::var(PROFILE_ENV).ok())
+            .or_else(|| value.get("profile").and_then(|p| p.as_str()).map(str::to_string));
+        if let Some(name) = profile {
+            let Some(overrides) = profiles.as_ref().and_then(|p| p.get(&name)) else {
+                let available: Vec<&String> = profiles
+                    .as_ref()
+                    .and_then(|p| p.as_table())
+                    .map(|p| p.keys().collect())
+                    .unwrap_or_default();
+                return Err(io::Error::other(format!(
+                    "Unknown profile {}, available profiles: {:?}",
+                    name, available
+                )));
+            };
+            eprintln!("Using profile {}", name);
+            merge_toml(&mut value, overrides.clone());
+            if let toml::Value::Table(table) = &mut value {
+                table.insert("profile".to_string(), toml::Value::String(name));
+            }
+        }
+
+        for key in apply_prefixed_overrides(&mut value, env::vars())? {
+            eprintln!("Overriding {} from the environment", key);
+        }
+        let mut config: Config = value.try_into().map_err(io::Error::other)?;
+        config.source = Some(source);
+        Ok(config.apply_env_overrides())
+    }
+
+    /// Applies overrides from environment variables, if set. These predate the [`ENV_PREFIX`]
+    /// variables, and are kept for the Docker setup.
     ///
     /// - `DATA_PATH`: overrides `data.data_path`
     /// - `OUTPUT_PATH`: overrides `data.output_path`
     ///
     pub fn apply_env_overrides(mut self) -> Self {
-        if self.general.log_level != "none" {
-            println!("applying existing env overrides");
+        if self.general.log_level != LogLevel::None {
+            eprintln!("applying existing env overrides");
         }
 
         if let Ok(dp) = env::var("DATA_PATH") {
-            println!("Overriding data path: {}", dp);
+            eprintln!("Overriding data path: {}", dp);
             self.data.data_path = dp;
         }
         if let Ok(op) = env::var("OUTPUT_PATH") {
-            println!("Overriding output path: {}", op);
+            eprintln!("Overriding output path: {}", op);
             self.data.output_path = op;
         }
         self
     }
+
+    /// A copy without secrets, for configs that are printed or written to the outputs. The
+    /// values of the `storage.http_headers` (e.g. auth tokens) and the `notifications.webhooks`
+    /// URLs are replaced with [`REDACTED`].
+    pub fn redacted(&self) -> Config {
+        let mut config = self.clone();
+        if let Some(storage) = config.storage.as_mut() {
+            for value in storage.http_headers.values_mut() {
+                *value = REDACTED.to_string();
+            }
+        }
+        if let Some(notifications) = config.notifications.as_mut() {
+            for url in notifications.webhooks.iter_mut() {
+                *url = REDACTED.to_string();
+            }
+        }
+        config
+    }
+}
+
+/// Merges `overrides` into `value`. Tables are merged key by key, other values are replaced.
+fn merge_toml(value: &mut toml::Value, overrides: toml::Value) {
+    match (value, overrides) {
+        (toml::Value::Table(table), toml::Value::Table(overrides)) => {
+            for (key, override_value) in overrides {
+                match table.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, override_value),
+                    None => {
+                        table.insert(key, override_value);
+                    }
+                }
+            }
+        }
+        (value, overrides) => *value = overrides,
+    }
+}
+
+/// Sets the config key of every [`ENV_PREFIX`] variable in `vars` in `value`, the parsed config
+/// file. Sections that are not in the file are created. Values are parsed as TOML, e.g. `true`,
+/// `4` or `[1, 2]`, and used as strings if they are not valid TOML or the key is a string in
+/// the file.
+///
+/// Returns the overridden keys, e.g. `data.output_path`.
+fn apply_prefixed_overrides(
+    value: &mut toml::Value,
+    vars: impl Iterator<Item = (String, String)>,
+) -> io::Result<Vec<String>> {
+    let mut keys = Vec::new();
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let path: Vec<String> = path.split("__").map(str::to_lowercase).collect();
+        if path.iter().any(String::is_empty) {
+            return Err(io::Error::other(format!("Invalid config variable {}", name)));
+        }
+
+        let (key, sections) = path.split_last().expect("split always yields a segment");
+        let mut table = value;
+        for section in sections {
+            table = match table {
+                toml::Value::Table(t) => t
+                    .entry(section.clone())
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new())),
+                _ => return Err(io::Error::other(format!("{} is not a section", name))),
+            };
+        }
+        let toml::Value::Table(table) = table else {
+            return Err(io::Error::other(format!("{} is not a section", name)));
+        };
+
+        let parsed = match table.get(key) {
+            Some(toml::Value::String(_)) => None,
+            _ => toml::from_str::<toml::Table>(&format!("value = {}", raw))
+                .ok()
+                .and_then(|mut t| t.remove("value")),
+        };
+        table.insert(key.clone(), parsed.unwrap_or(toml::Value::String(raw)));
+        keys.push(path.join("."));
+    }
+    keys.sort();
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_prefixed_overrides() {
+        let mut value: toml::Value =
+            toml::from_str("[general]\nnum_cores = 1\n[data]\noutput_path = \"out\"\n").unwrap();
+        let vars = [
+            ("DRIBBLE__GENERAL__NUM_CORES", "8"),
+            ("DRIBBLE__DATA__OUTPUT_PATH", "2025"),
+            ("DRIBBLE__SMOOTHING__ENABLED", "true"),
+            ("HOME", "/root"),
+        ]
+        .map(|(name, raw)| (name.to_string(), raw.to_string()));
+
+        let keys = apply_prefixed_overrides(&mut value, vars.into_iter()).unwrap();
+        assert_eq!(keys, ["data.output_path", "general.num_cores", "smoothing.enabled"]);
+        assert_eq!(value["general"]["num_cores"].as_integer(), Some(8));
+        // Keys that are strings in the file stay strings
+        assert_eq!(value["data"]["output_path"].as_str(), Some("2025"));
+        assert_eq!(value["smoothing"]["enabled"].as_bool(), Some(true));
+
+        let invalid = [("DRIBBLE__GENERAL__NUM_CORES__X".to_string(), "1".to_string())];
+        assert!(apply_prefixed_overrides(&mut value, invalid.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_default_config() {
+        let default_config: Config = toml::from_str(DEFAULT_CONFIG).unwrap();
+        assert_eq!(
+            toml::to_string(&default_config).unwrap(),
+            toml::to_string(&Config::default()).unwrap()
+        );
+
+        // A minimal config only sets what differs from the defaults
+        let config: Config =
+            toml::from_str("[data]\ndata_path = \"clips\"\n[shielding]\nmin_frames = 10\n")
+                .unwrap();
+        assert_eq!(config.data.data_path, "clips");
+        assert_eq!(config.data.output_path, "data/output");
+        assert_eq!(config.dribbling_detection.inner_radius, 0.8);
+        assert!(config.shielding.is_some_and(|s| s.enabled && s.min_frames == 10));
+    }
+
+    #[test]
+    fn test_merge_profile() {
+        let mut value: toml::Value = toml::from_str(
+            "[dribbling_detection]\ninner_radius = 2.0\nouter_radius = 4.0\n\
+             [profiles.strict.dribbling_detection]\ninner_radius = 1.5\n",
+        )
+        .unwrap();
+        let strict = value["profiles"]["strict"].clone();
+        merge_toml(&mut value, strict);
+
+        let detection = &value["dribbling_detection"];
+        assert_eq!(detection["inner_radius"].as_float(), Some(1.5));
+        // Keys that are not in the profile are kept
+        assert_eq!(detection["outer_radius"].as_float(), Some(4.0));
+    }
 }
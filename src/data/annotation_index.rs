@@ -0,0 +1,54 @@
+use super::models::Annotation;
+use std::collections::HashMap;
+
+/// Annotations of a video grouped by image_id, so the annotations of a frame can be looked up
+/// without scanning all annotations of the video.
+#[derive(Clone, Debug, Default)]
+pub struct AnnotationIndex {
+    by_image: HashMap<String, Vec<Annotation>>,
+}
+
+impl AnnotationIndex {
+    pub fn new<'a>(annotations: impl IntoIterator<Item = &'a Annotation>) -> Self {
+        let mut by_image: HashMap<String, Vec<Annotation>> = HashMap::new();
+        for annotation in annotations {
+            by_image
+                .entry(annotation.image_id.clone())
+                .or_default()
+                .push(annotation.clone());
+        }
+        Self { by_image }
+    }
+
+    /// Returns the annotations of `image_id`, or an empty slice if it has none.
+    pub fn get(&self, image_id: &str) -> &[Annotation] {
+        self.by_image
+            .get(image_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation_index() {
+        let annotations: Vec<Annotation> = ["1", "2", "1"]
+            .iter()
+            .enumerate()
+            .map(|(i, image_id)| Annotation {
+                id: i.to_string(),
+                image_id: image_id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+
+        let index = AnnotationIndex::new(&annotations);
+        let ids: Vec<&str> = index.get("1").iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["0", "2"]);
+        assert_eq!(index.get("2").len(), 1);
+        assert!(index.get("3").is_empty());
+    }
+}
@@ -0,0 +1,333 @@
+//! Reads datasets directly from a `.zip` archive instead of an extracted directory.
+//!
+//! Files in an archive are addressed by the path of the archive joined with their path inside
+//! it, e.g. `data/SoccerNet.zip/train/SNGS-060/img1/000001.jpg`, so the rest of the code can
+//! keep passing paths around. The methods of [`Archives`] fall back to the file system for
+//! paths that are not inside an archive.
+
+use super::storage::DirEntry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// Number of archives a thread keeps open. Opening another one closes the least recently used.
+const MAX_OPEN_ARCHIVES: usize = 4;
+
+/// The archives of a dataset that are open, owned by its [`Storage`](super::storage::Storage).
+#[derive(Debug, Default)]
+pub struct Archives {
+    /// Paths that are known to be archives, so paths inside them are found without reading the
+    /// metadata of every parent directory
+    known: Mutex<HashSet<PathBuf>>,
+    /// Archives opened by every thread, so their central directory is only read once. The most
+    /// recently used archive of a thread is last.
+    open: Mutex<HashMap<ThreadId, Vec<(PathBuf, ZipArchive<File>)>>>,
+}
+
+/// Returns `true` if `path` is a `.zip` file.
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        && path.is_file()
+}
+
+impl Archives {
+    /// Splits a path into the archive containing it and the name of the entry inside the archive
+    /// (empty for the archive itself). Returns `None` if the path is not inside an archive.
+    pub fn split_archive_path(&self, path: &Path) -> Option<(PathBuf, String)> {
+        {
+            let known = self.known.lock().unwrap();
+            if let Some(split) = split_at(path, |archive| known.contains(archive)) {
+                return Some(split);
+            }
+        }
+        let split = split_at(path, is_archive)?;
+        self.known.lock().unwrap().insert(split.0.clone());
+        Some(split)
+    }
+
+    /// Runs `f` with the archive at `archive_path`, opening it if this thread has not yet. The
+    /// archive is taken out of the open ones while `f` runs, so other threads are not blocked.
+    fn with_archive<T>(
+        &self,
+        archive_path: &Path,
+        f: impl FnOnce(&mut ZipArchive<File>) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let thread = thread::current().id();
+        let open = {
+            let mut open = self.open.lock().unwrap();
+            let archives = open.entry(thread).or_default();
+            archives
+                .iter()
+                .position(|(path, _)| path == archive_path)
+                .map(|index| archives.remove(index))
+        };
+        let (path, mut archive) = match open {
+            Some(open) => open,
+            None => {
+                let archive = ZipArchive::new(File::open(archive_path)?).map_err(zip_error)?;
+                (archive_path.to_path_buf(), archive)
+            }
+        };
+        let result = f(&mut archive);
+
+        let mut open = self.open.lock().unwrap();
+        let archives = open.entry(thread).or_default();
+        if archives.len() >= MAX_OPEN_ARCHIVES {
+            archives.remove(0);
+        }
+        archives.push((path, archive));
+        result
+    }
+
+    /// Paths of the archives the current thread has open, the most recently used last.
+    #[cfg(test)]
+    fn open_archives(&self) -> Vec<PathBuf> {
+        let open = self.open.lock().unwrap();
+        open.get(&thread::current().id())
+            .map(|archives| archives.iter().map(|(path, _)| path.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Calls `read` with a reader of the file at `path`. Files inside an archive are decompressed
+    /// while they are read, so they are never extracted to disk.
+    pub fn with_reader<T>(
+        &self,
+        path: &Path,
+        read: impl FnOnce(&mut dyn Read) -> io::Result<T>,
+    ) -> io::Result<T> {
+        match self.split_archive_path(path) {
+            Some((archive_path, name)) => self.with_archive(&archive_path, |archive| {
+                let mut file = archive.by_name(&name).map_err(zip_error)?;
+                read(&mut file)
+            }),
+            None => read(&mut File::open(path)?),
+        }
+    }
+
+    /// Reads the whole file at `path`.
+    pub fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.with_reader(path, |reader| {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+    }
+
+    /// Returns `true` if the file or directory at `path` exists.
+    pub fn exists(&self, path: &Path) -> bool {
+        match self.split_archive_path(path) {
+            Some((archive_path, name)) => self
+                .with_archive(&archive_path, |archive| {
+                    Ok(archive.index_for_name(&name).is_some() || has_dir(archive, &name))
+                })
+                .unwrap_or(false),
+            None => path.exists(),
+        }
+    }
+
+    /// Returns `true` if `path` is a directory, or the root or a directory of an archive.
+    pub fn is_dir(&self, path: &Path) -> bool {
+        match self.split_archive_path(path) {
+            Some((archive_path, name)) => self
+                .with_archive(&archive_path, |archive| Ok(has_dir(archive, &name)))
+                .unwrap_or(false),
+            None => path.is_dir(),
+        }
+    }
+
+    /// Returns the subdirectories of `dir`, ordered alphabetically.
+    pub fn list_dirs(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .list_dir(dir)?
+            .into_iter()
+            .filter(|entry| entry.is_dir)
+            .map(|entry| dir.join(entry.name))
+            .collect())
+    }
+
+    /// Returns the files and directories directly inside `dir`, ordered by name.
+    pub fn list_dir(&self, dir: &Path) -> io::Result<Vec<DirEntry>> {
+        let Some((archive_path, name)) = self.split_archive_path(dir) else {
+            let mut entries: Vec<DirEntry> = fs::read_dir(dir)?
+                .filter_map(|e| e.ok())
+                .map(|e| DirEntry {
+                    name: e.file_name().to_string_lossy().to_string(),
+                    is_dir: e.path().is_dir(),
+                })
+                .collect();
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            return Ok(entries);
+        };
+
+        let prefix = dir_prefix(&name);
+        let entries: BTreeMap<String, bool> = self.with_archive(&archive_path, |archive| {
+            let mut entries = BTreeMap::new();
+            for file_name in archive.file_names() {
+                let Some(rest) = file_name.strip_prefix(&prefix) else {
+                    continue;
+                };
+                // Entries with a further path component are inside a subdirectory
+                match rest.split_once('/') {
+                    Some((subdir, _)) if !subdir.is_empty() => {
+                        entries.insert(subdir.to_string(), true);
+                    }
+                    None if !rest.is_empty() => {
+                        entries.entry(rest.to_string()).or_insert(false);
+                    }
+                    _ => {}
+                }
+            }
+            Ok(entries)
+        })?;
+        Ok(entries
+            .into_iter()
+            .map(|(name, is_dir)| DirEntry { name, is_dir })
+            .collect())
+    }
+}
+
+/// Splits `path` at the first of its parents for which `is_archive` returns `true`.
+fn split_at(path: &Path, mut is_archive: impl FnMut(&Path) -> bool) -> Option<(PathBuf, String)> {
+    let mut archive = PathBuf::new();
+    let mut components = path.components();
+    while let Some(component) = components.next() {
+        archive.push(component);
+        if is_archive(&archive) {
+            let entry = components
+                .as_path()
+                .to_string_lossy()
+                .replace('\\', "/");
+            return Some((archive, entry));
+        }
+    }
+    None
+}
+
+/// Directories are often not stored as entries of their own, so a directory exists if any
+/// entry is inside it.
+fn has_dir(archive: &ZipArchive<File>, name: &str) -> bool {
+    let prefix = dir_prefix(name);
+    archive
+        .file_names()
+        .any(|file_name| file_name.starts_with(&prefix))
+}
+
+fn dir_prefix(name: &str) -> String {
+    match name.trim_end_matches('/') {
+        "" => String::new(),
+        name => format!("{}/", name),
+    }
+}
+
+fn zip_error(e: ZipError) -> io::Error {
+    match e {
+        ZipError::FileNotFound => io::Error::new(io::ErrorKind::NotFound, e),
+        e => io::Error::other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    fn test_read_archive() {
+        let dir = std::env::temp_dir().join(format!("archive_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let archive_path = dir.join("dataset.zip");
+
+        let mut writer = ZipWriter::new(File::create(&archive_path).unwrap());
+        for name in [
+            "train/SNGS-002/Labels-GameState.json",
+            "train/SNGS-001/Labels-GameState.json",
+            "train/SNGS-001/img1/000001.jpg",
+            "test/SNGS-003/Labels-GameState.json",
+        ] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(name.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let archives = Archives::default();
+        assert_eq!(
+            archives.split_archive_path(&archive_path.join("train/SNGS-001")),
+            Some((archive_path.clone(), "train/SNGS-001".to_string()))
+        );
+        assert_eq!(archives.split_archive_path(&dir.join("train")), None);
+
+        assert_eq!(
+            archives.list_dirs(&archive_path).unwrap(),
+            vec![archive_path.join("test"), archive_path.join("train")]
+        );
+        assert_eq!(
+            archives.list_dirs(&archive_path.join("train")).unwrap(),
+            vec![
+                archive_path.join("train/SNGS-001"),
+                archive_path.join("train/SNGS-002")
+            ]
+        );
+
+        let image = archive_path.join("train/SNGS-001/img1/000001.jpg");
+        assert!(archives.exists(&image));
+        assert!(archives.is_dir(&archive_path.join("train/SNGS-001")));
+        assert!(!archives.exists(&archive_path.join("train/SNGS-004")));
+        assert_eq!(
+            archives.read(&image).unwrap(),
+            b"train/SNGS-001/img1/000001.jpg"
+        );
+        assert_eq!(
+            archives
+                .read(&archive_path.join("missing.json"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_archive_limit() {
+        let dir = std::env::temp_dir().join(format!("archive_limit_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let archive_paths: Vec<PathBuf> = (0..=MAX_OPEN_ARCHIVES)
+            .map(|i| dir.join(format!("part{}.zip", i)))
+            .collect();
+        for archive_path in &archive_paths {
+            let mut writer = ZipWriter::new(File::create(archive_path).unwrap());
+            writer
+                .start_file("labels.json", SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"{}").unwrap();
+            writer.finish().unwrap();
+        }
+
+        let archives = Archives::default();
+        let open_archives = || archives.open_archives();
+        for archive_path in &archive_paths {
+            assert_eq!(
+                archives.read(&archive_path.join("labels.json")).unwrap(),
+                b"{}"
+            );
+        }
+        // The first archive was closed, and is opened again when it is read
+        assert_eq!(open_archives(), archive_paths[1..]);
+        assert!(archives.exists(&archive_paths[0].join("labels.json")));
+        assert_eq!(open_archives().last(), Some(&archive_paths[0]));
+        assert_eq!(open_archives().len(), MAX_OPEN_ARCHIVES);
+        // The archives of another dataset are opened separately
+        assert!(Archives::default().open_archives().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,25 +1,95 @@
-use super::models::{DribbleEventsExport, VideoData};
-use crate::config::Config;
-use crate::data::models::Labels;
+use super::formats::{actions, coco, mot};
+use super::labels_reader::LabelsReader;
+use super::layout::SequenceLayout;
+use super::models::{video_key, DribbleEventsExport, VideoData};
+use super::splits::assign_split;
+use super::storage::Storage;
+use crate::config::{Config, DataConfig};
+use crate::utils::frame_source::find_video_file;
+use crate::utils::homography::Homographies;
+use crate::watchdog::Watchdog;
 use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{self, BufReader};
-use std::path::PathBuf;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Clone, Debug)]
 pub struct Dataset {
-    pub base_dir: PathBuf,
+    pub roots: Vec<DatasetRoot>,
+    /// Subsets of all roots, in the order they are configured
     pub subsets: Vec<String>,
     pub num_cores: usize,
     pub config: Config,
+    /// Reads the files of all roots, with the `[storage]` options of `config`
+    pub storage: Arc<Storage>,
+}
+
+/// A directory of subsets, configured by `data_path` or one of the `datasets`.
+#[derive(Clone, Debug)]
+pub struct DatasetRoot {
+    /// Not set for `data_path`
+    pub name: Option<String>,
+    pub base_dir: PathBuf,
+    pub subsets: Vec<String>,
+    /// Maps category names of this dataset to the names the detector uses
+    pub categories: HashMap<String, String>,
+}
+
+impl DatasetRoot {
+    /// Tags a sequence of this root with its dataset name and renames its categories.
+    fn apply(&self, video_data: &mut VideoData) {
+        video_data.dataset = self.name.clone();
+        for category in video_data.labels.categories.iter_mut() {
+            if let Some(name) = self.categories.get(&category.name) {
+                category.name = name.clone();
+            }
+        }
+    }
+}
+
+/// Returns the `datasets` of the config, or `data_path` if none are configured. With
+/// `active_subset`, only that subset is used from every root.
+pub fn dataset_roots(data_config: &DataConfig) -> Vec<DatasetRoot> {
+    let subsets = |subsets: Option<&Vec<String>>| match &data_config.active_subset {
+        Some(subset) => vec![subset.clone()],
+        None => subsets.unwrap_or(&data_config.subsets).clone(),
+    };
+
+    match &data_config.datasets {
+        Some(datasets) => datasets
+            .iter()
+            .map(|dataset| DatasetRoot {
+                name: Some(dataset.name.clone()),
+                base_dir: PathBuf::from(&dataset.data_path),
+                subsets: subsets(dataset.subsets.as_ref()),
+                categories: dataset.categories.clone(),
+            })
+            .collect(),
+        None => vec![DatasetRoot {
+            name: None,
+            base_dir: PathBuf::from(&data_config.data_path),
+            subsets: subsets(None),
+            categories: HashMap::new(),
+        }],
+    }
+}
+
+/// Resolves `.`, `..` and symbolic links of a local path. Other paths are returned as they are.
+fn resolve(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
 }
 
 /// Helper to load dribble-events map if in review mode.
 /// Returns a HashMap<video_id, HashSet<frame_nums_in_events>> or None if not in review mode.
 pub fn load_dribble_events_map(config: &Config) -> Option<HashMap<String, Vec<(u32, u32)>>> {
-    // Attempt to read dribble_events.json
+    load_event_intervals(&config.data.dribble_events_path)
+}
 
-    let dribble_events_path = &config.data.dribble_events_path;
+/// Reads the start and end frame of every dribble event in a `dribble_events.json` file,
+/// per video.
+pub fn load_event_intervals(dribble_events_path: &str) -> Option<HashMap<String, Vec<(u32, u32)>>> {
+    // Attempt to read dribble_events.json
     let dribble_json = match fs::read_to_string(dribble_events_path) {
         Ok(txt) => txt,
         Err(e) => {
@@ -59,79 +129,452 @@ pub fn load_dribble_events_map(config: &Config) -> Option<HashMap<String, Vec<(u
     Some(video_to_valid_frames)
 }
 
+/// Reads the labels and image paths of a sequence directory. The annotations are not loaded;
+/// they are streamed through `VideoData::labels_reader`.
+///
+/// If the frames have not been extracted to images, they are read from a video file in the
+/// sequence directory instead. `seq_dir` may be inside a zip archive or in remote storage, see
+/// [`Storage`].
+///
+/// With `homography_file`, missing pitch bounding boxes are projected from the image bounding
+/// boxes. With `[actions]`, the ground truth actions of the sequence's action spotting file are
+/// attached as well.
+pub fn load_video_data(
+    seq_dir: &Path,
+    config: &Config,
+    storage: &Arc<Storage>,
+) -> io::Result<VideoData> {
+    let mut video_data = load_labels(seq_dir, config, storage)?;
+
+    if !video_data
+        .image_paths
+        .first()
+        .is_some_and(|p| storage.exists(p))
+    {
+        video_data.video_path = find_video_file(seq_dir);
+    }
+
+    if let Some(homography_file) = &config.data.homography_file {
+        let homography_path = seq_dir.join(homography_file);
+        if storage.exists(&homography_path) {
+            match Homographies::load(&homography_path, storage) {
+                Ok(homographies) => video_data.set_homographies(homographies),
+                Err(e) => eprintln!(
+                    "Could not read homographies from {:?}: {}",
+                    homography_path, e
+                ),
+            }
+        }
+    }
+
+    if let Some(actions_config) = &config.actions {
+        let actions_file = seq_dir.join(&actions_config.labels_file);
+        if storage.exists(&actions_file) {
+            match actions::load_actions(&actions_file, &video_data.labels.info, storage) {
+                Ok(actions) => video_data.actions = actions,
+                Err(e) => eprintln!("Could not read actions from {:?}: {}", actions_file, e),
+            }
+        }
+    }
+    Ok(video_data)
+}
+
+/// Sequences without a labels file (`Labels-GameState.json`, see [`SequenceLayout`]) are
+/// converted from a COCO file (if `[coco]` is configured) or from MOT Challenge files instead.
+/// Sequences in an archive or in remote storage must have a labels file.
+fn load_labels(seq_dir: &Path, config: &Config, storage: &Arc<Storage>) -> io::Result<VideoData> {
+    let layout = SequenceLayout::from_config(&config.data);
+    let labels_file = layout.labels_file(seq_dir);
+    let on_disk = storage.is_local_file(seq_dir);
+    if on_disk && !labels_file.exists() {
+        if let Some(coco_config) = &config.coco {
+            if coco::is_coco_sequence(seq_dir, coco_config) {
+                return coco::load_coco_sequence(seq_dir, coco_config);
+            }
+        }
+        if mot::is_mot_sequence(seq_dir) {
+            return mot::load_mot_sequence(seq_dir);
+        }
+    }
+    let lenient = config.data.lenient_labels.unwrap_or(false);
+    // The cache is written next to the labels file, which is only possible on disk
+    let (labels, labels_reader) = if config.data.labels_cache.unwrap_or(false) && on_disk {
+        LabelsReader::open_cached(&labels_file, lenient, storage)?
+    } else {
+        LabelsReader::open_with(&labels_file, lenient, storage)?
+    };
+    if !labels_reader.invalid_annotations().is_empty() {
+        eprintln!(
+            "Skipped {} invalid annotations in {:?}",
+            labels_reader.invalid_annotations().len(),
+            labels_file
+        );
+    }
+
+    let image_dir = layout.image_dir(labels.info.im_dir.as_deref());
+    let image_paths: Vec<PathBuf> = labels
+        .images
+        .iter()
+        .map(|image| seq_dir.join(&image_dir).join(&image.file_name))
+        .collect();
+
+    Ok(VideoData {
+        dir_path: seq_dir.to_path_buf(),
+        image_paths,
+        labels,
+        video_path: None,
+        video_first_frame: None,
+        dataset: None,
+        actions: Vec::new(),
+        labels_reader: Some(labels_reader),
+        storage: storage.clone(),
+    })
+}
+
 impl Dataset {
     pub fn new(config: Config) -> Self {
-        let base_dir = PathBuf::from(&config.data.data_path);
-        let subsets = config.data.subsets.clone();
+        let roots = dataset_roots(&config.data);
+        let mut subsets: Vec<String> = Vec::new();
+        for subset in roots.iter().flat_map(|root| &root.subsets) {
+            if !subsets.contains(subset) {
+                subsets.push(subset.clone());
+            }
+        }
         let num_cores = config.general.num_cores as usize;
+        let storage = Arc::new(Storage::from_config(&config));
 
         Self {
-            base_dir,
+            roots,
             subsets,
             num_cores,
             config,
+            storage,
         }
     }
 
-    // Create an iterator for a specific subset, ordered alphabetically
-    /// and filters out any frames not in the dribble-event ranges if in review mode.
-    pub fn iter_subset(&self, subset: &str) -> impl Iterator<Item = io::Result<VideoData>> {
-        let subset_dir = self.base_dir.join(subset);
-        if !subset_dir.exists() {
-            return Box::new(std::iter::empty()) as Box<dyn Iterator<Item = io::Result<VideoData>>>;
-        }
+    /// Create an iterator for a specific subset, ordered alphabetically.
+    /// The annotations are not loaded; they are streamed through `VideoData::labels_reader`.
+    pub fn iter_subset(&self, subset: &str) -> impl Iterator<Item = io::Result<VideoData>> + '_ {
+        self.load_subset(subset, |seq_dir| self.load_sequence(seq_dir))
+    }
 
-        // Read and collect entries (directories) under this subset
-        let mut entries = match fs::read_dir(&subset_dir) {
-            Ok(dir_entries) => dir_entries.filter_map(|e| e.ok()).collect::<Vec<_>>(),
-            Err(err) => {
-                eprintln!("Could not read directory {:?}: {}", subset_dir, err);
-                vec![]
+    /// Like [`iter_subset`](Self::iter_subset), but the labels of every sequence are read under
+    /// the timeout of `watchdog`. Sequences whose labels take longer than that are left out and
+    /// reported by the watchdog.
+    pub fn iter_subset_watched<'a>(
+        &'a self,
+        subset: &str,
+        watchdog: &'a Watchdog,
+    ) -> impl Iterator<Item = io::Result<VideoData>> + 'a {
+        self.load_subset(subset, move |seq_dir| {
+            let watch = watchdog.watch(&self.sequence_key(seq_dir));
+            let video_data = self.load_sequence(seq_dir)?;
+            match watch.is_expired() {
+                true => Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "reading the labels timed out",
+                )),
+                false => Ok(video_data),
             }
-        };
+        })
+    }
 
-        // Sort entries alphabetically
-        entries.sort_by(|a, b| a.path().cmp(&b.path()));
+    fn load_subset<'a>(
+        &'a self,
+        subset: &str,
+        load: impl Fn(&Path) -> io::Result<VideoData> + 'a,
+    ) -> impl Iterator<Item = io::Result<VideoData>> + 'a {
+        let max_videos = self.config.data.max_videos.unwrap_or(usize::MAX);
+        self.filtered_sequence_dirs(subset)
+            .into_iter()
+            .filter_map(move |seq_dir| match load(&seq_dir) {
+                Ok(video_data) => Some(Ok(video_data)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    println!("No labels file found for sequence {:?}", seq_dir);
+                    None
+                }
+                // Already reported by the watchdog
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => None,
+                Err(err) => {
+                    eprintln!("Failed to deserialize labels of {:?}: {}", seq_dir, err);
+                    None
+                }
+            })
+            .take(max_videos)
+    }
+
+    /// Loads a sequence like [`load_video_data`], tagged with the dataset it belongs to and
+    /// with the categories of that dataset renamed.
+    pub fn load_sequence(&self, seq_dir: &Path) -> io::Result<VideoData> {
+        let mut video_data = load_video_data(seq_dir, &self.config, &self.storage)?;
+        if let Some(root) = self.root_of(seq_dir) {
+            root.apply(&mut video_data);
+        }
+        Ok(video_data)
+    }
+
+    /// The id of a sequence, see [`video_key`].
+    pub fn sequence_key(&self, seq_dir: &Path) -> String {
+        let name = seq_dir.file_name().unwrap_or_default().to_string_lossy();
+        let root = self.root_of(seq_dir);
+        video_key(root.and_then(|root| root.name.as_deref()), &name)
+    }
+
+    /// Returns the root a sequence directory belongs to, the innermost one if roots are nested.
+    /// Paths are compared by component, with local paths resolved first, so `data/a` does not
+    /// contain `data/ab/train`.
+    pub fn root_of(&self, seq_dir: &Path) -> Option<&DatasetRoot> {
+        let seq_dir = resolve(seq_dir);
+        self.roots
+            .iter()
+            .map(|root| (root, resolve(&root.base_dir)))
+            .filter(|(_, base_dir)| seq_dir.starts_with(base_dir))
+            .max_by_key(|(_, base_dir)| base_dir.components().count())
+            .map(|(root, _)| root)
+    }
+
+    /// Returns `true` if one of the roots configured with `subset` has a directory for it.
+    pub fn has_subset(&self, subset: &str) -> bool {
+        self.roots_with_subset(subset)
+            .any(|root| self.storage.is_dir(&root.base_dir.join(subset)))
+    }
+
+    /// Returns the subsets that do not have a directory in any root.
+    pub fn missing_subsets(&self) -> Vec<&String> {
+        self.subsets
+            .iter()
+            .filter(|subset| !self.has_subset(subset))
+            .collect()
+    }
 
-        // Create an iterator producing VideoData
-        let iter = entries.into_iter().filter_map(move |entry| {
-            let seq_dir = entry.path();
-            if !seq_dir.is_dir() {
-                return None;
+    /// Returns the names of all directories in the roots, ordered alphabetically.
+    pub fn available_subsets(&self) -> Vec<String> {
+        let mut subsets: Vec<String> = self
+            .roots
+            .iter()
+            .filter_map(|root| self.storage.list_dirs(&root.base_dir).ok())
+            .flatten()
+            .map(|dir| dir.file_name().unwrap_or_default().to_string_lossy().to_string())
+            .collect();
+        subsets.sort();
+        subsets.dedup();
+        subsets
+    }
+
+    /// Returns the sequence directories of a subset, ordered alphabetically within each root.
+    pub fn sequence_dirs(&self, subset: &str) -> Vec<PathBuf> {
+        let layout = SequenceLayout::from_config(&self.config.data);
+        let mut seq_dirs = Vec::new();
+        for root in self.roots_with_subset(subset) {
+            let subset_dir = root.base_dir.join(subset);
+            if !self.storage.exists(&subset_dir) {
+                continue;
             }
 
-            let labels_file = seq_dir.join("Labels-GameState.json");
-            if !labels_file.exists() {
-                println!("No labels file found for sequence {:?}", seq_dir);
-                return None;
+            match self.storage.list_dirs(&subset_dir) {
+                Ok(root_dirs) => seq_dirs.extend(
+                    root_dirs
+                        .into_iter()
+                        .filter(|dir| !layout.is_annotations_dir(dir)),
+                ),
+                Err(err) => eprintln!("Could not read directory {:?}: {}", subset_dir, err),
             }
+        }
+        seq_dirs
+    }
 
-            // Parse the Labels JSON
-            let file = File::open(&labels_file).ok()?;
-            let reader = BufReader::new(file);
-            let labels: Labels = match serde_json::from_reader(reader) {
-                Ok(labels) => labels,
-                Err(err) => {
-                    eprintln!("Failed to deserialize JSON file {:?}: {}", labels_file, err);
-                    return None;
-                }
-            };
-
-            let image_dir = labels.clone().info.im_dir.unwrap_or("img1".to_string());
-            let image_paths: Vec<PathBuf> = labels
-                .images
-                .iter()
-                .map(|image| seq_dir.join(&image_dir).join(&image.file_name))
-                .collect();
-
-            // Return VideoData
-            Some(Ok(VideoData {
-                dir_path: seq_dir,
-                image_paths,
-                labels,
-            }))
+    fn roots_with_subset<'a>(&'a self, subset: &'a str) -> impl Iterator<Item = &'a DatasetRoot> {
+        self.roots
+            .iter()
+            .filter(move |root| root.subsets.iter().any(|s| s == subset))
+    }
+
+    /// Returns the sequence directories of a subset that pass the `include_sequences` and
+    /// `sequence_range` filters and are in the `active` split, ordered alphabetically.
+    pub fn filtered_sequence_dirs(&self, subset: &str) -> Vec<PathBuf> {
+        let split = self
+            .config
+            .splits
+            .as_ref()
+            .and_then(|splits| splits.active.map(|active| (splits, active)));
+        self.sequence_dirs(subset)
+            .into_iter()
+            .filter(|seq_dir| self.matches_sequence_filters(seq_dir))
+            .filter(|seq_dir| {
+                split.is_none_or(|(splits, active)| {
+                    let name = seq_dir.file_name().unwrap_or_default().to_string_lossy();
+                    assign_split(&name, splits) == active
+                })
+            })
+            .collect()
+    }
+
+    /// Returns `true` if a sequence passes the `include_sequences` and `sequence_range` filters.
+    pub fn matches_sequence_filters(&self, seq_dir: &Path) -> bool {
+        let data_config = &self.config.data;
+        let name = seq_dir.file_name().unwrap_or_default().to_string_lossy();
+        let included = data_config
+            .include_sequences
+            .as_ref()
+            .is_none_or(|patterns| patterns.iter().any(|p| glob_match(p, &name)));
+        let in_range = data_config.sequence_range.is_none_or(|[start, end]| {
+            sequence_number(&name).is_some_and(|n| (start..=end).contains(&n))
         });
+        included && in_range
+    }
+}
 
-        Box::new(iter)
+/// Returns the number of a sequence, which is the last run of digits in its name
+/// (e.g. "SNGS-060" -> 60).
+pub fn sequence_number(name: &str) -> Option<u32> {
+    let digits: String = name
+        .chars()
+        .rev()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.chars().rev().collect::<String>().parse().ok()
+}
+
+/// Matches `name` against a pattern where `*` matches any number of characters and `?` a
+/// single character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern, and the name position it was matched at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` match one more character
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("SNGS-06*", "SNGS-060"));
+        assert!(glob_match("SNGS-06*", "SNGS-06"));
+        assert!(glob_match("*-0?0", "SNGS-060"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("SNGS-06*", "SNGS-160"));
+        assert!(!glob_match("SNGS-0?", "SNGS-060"));
+    }
+
+    #[test]
+    fn test_sequence_number() {
+        assert_eq!(sequence_number("SNGS-060"), Some(60));
+        assert_eq!(sequence_number("MOT17-02-FRCNN"), Some(2));
+        assert_eq!(sequence_number("clip"), None);
+    }
+
+    #[test]
+    fn test_dataset_roots() {
+        let data_config: DataConfig = toml::from_str(
+            r#"
+            data_path = "data/soccernet"
+            dribble_events_path = "output/dribble_events.json"
+            subsets = ["train", "test"]
+            output_path = "output"
+            huggingface_dataset_url = ""
+
+            [[datasets]]
+            name = "soccernet"
+            data_path = "data/soccernet"
+
+            [[datasets]]
+            name = "in_house"
+            data_path = "data/in_house"
+            subsets = ["clips"]
+            categories = { person = "player", sports_ball = "ball" }
+            "#,
+        )
+        .unwrap();
+
+        let roots = dataset_roots(&data_config);
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0].subsets, vec!["train", "test"]);
+        assert_eq!(roots[1].base_dir, PathBuf::from("data/in_house"));
+        assert_eq!(roots[1].subsets, vec!["clips"]);
+
+        let mut video_data = VideoData::default();
+        video_data.labels.categories = ["person", "sports_ball", "referee"]
+            .iter()
+            .map(|name| crate::data::models::Category {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        roots[1].apply(&mut video_data);
+        assert_eq!(video_data.dataset.as_deref(), Some("in_house"));
+        let names: Vec<&str> = video_data
+            .labels
+            .categories
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["player", "ball", "referee"]);
+
+        let data_config = DataConfig {
+            datasets: None,
+            active_subset: Some("test".to_string()),
+            ..data_config
+        };
+        let roots = dataset_roots(&data_config);
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].name, None);
+        assert_eq!(roots[0].subsets, vec!["test"]);
+    }
+
+    #[test]
+    fn test_root_of() {
+        let dir = std::env::temp_dir().join(format!("root_of_{}", std::process::id()));
+        for root in ["a", "ab", "ab/extra"] {
+            fs::create_dir_all(dir.join(root).join("train/SNGS-001")).unwrap();
+        }
+        let root = |name: &str, base_dir: PathBuf| DatasetRoot {
+            name: Some(name.to_string()),
+            base_dir,
+            subsets: vec!["train".to_string()],
+            categories: HashMap::new(),
+        };
+        let dataset = Dataset {
+            roots: vec![
+                root("a", dir.join("a")),
+                root("ab", dir.join("ab")),
+                root("extra", dir.join("ab/./extra")),
+            ],
+            subsets: vec!["train".to_string()],
+            num_cores: 1,
+            config: Config::default(),
+            storage: Arc::default(),
+        };
+
+        let name_of = |seq_dir: &str| {
+            let root = dataset.root_of(&dir.join(seq_dir))?;
+            root.name.clone()
+        };
+        assert_eq!(name_of("ab/train/SNGS-001").as_deref(), Some("ab"));
+        assert_eq!(name_of("a/train/SNGS-001").as_deref(), Some("a"));
+        assert_eq!(name_of("ab/extra/train/SNGS-001").as_deref(), Some("extra"));
+        assert_eq!(name_of("c/train/SNGS-001"), None);
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
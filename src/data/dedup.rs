@@ -0,0 +1,117 @@
+//! Merges the exports of sequences that show the same physical clip, so a clip that is in
+//! several subsets (or dataset roots) appears once in `dribble_events.json`.
+
+use super::models::{ClipIdentity, DribbleLabel, VideoDribbleEvents};
+use crate::dribbling_detection::dribble_models::ShieldingEvent;
+use std::collections::HashMap;
+
+/// Keeps the first video of every [`ClipIdentity`], in the order of `videos`. Events of the
+/// later duplicates that do not overlap one of its events are added to it, ordered by start
+/// frame. Videos without an identity are all kept.
+///
+/// Returns the merged videos and the number of duplicates that were merged away.
+pub fn deduplicate_videos(videos: Vec<VideoDribbleEvents>) -> (Vec<VideoDribbleEvents>, usize) {
+    let mut merged: Vec<VideoDribbleEvents> = Vec::new();
+    let mut index_of: HashMap<ClipIdentity, usize> = HashMap::new();
+    let mut num_duplicates = 0;
+
+    for video in videos {
+        let Some(clip) = video.clip.clone() else {
+            merged.push(video);
+            continue;
+        };
+        let Some(&index) = index_of.get(&clip) else {
+            index_of.insert(clip, merged.len());
+            merged.push(video);
+            continue;
+        };
+
+        num_duplicates += 1;
+        let kept = &mut merged[index];
+        merge_events(&mut kept.dribble_events, video.dribble_events, label_range);
+        kept.dribble_events.sort_by_key(|e| e.start_frame);
+        merge_events(
+            &mut kept.shielding_events,
+            video.shielding_events,
+            shielding_range,
+        );
+        kept.shielding_events.sort_by_key(|e| e.start_frame);
+    }
+    (merged, num_duplicates)
+}
+
+/// Adds the events of `other` that do not overlap an event of `events`.
+fn merge_events<T>(events: &mut Vec<T>, other: Vec<T>, range: fn(&T) -> (u32, u32)) {
+    for event in other {
+        let (start, end) = range(&event);
+        let overlaps = events.iter().any(|e| {
+            let (e_start, e_end) = range(e);
+            start <= e_end && e_start <= end
+        });
+        if !overlaps {
+            events.push(event);
+        }
+    }
+}
+
+fn label_range(label: &DribbleLabel) -> (u32, u32) {
+    (label.start_frame, label.end_frame.unwrap_or(label.start_frame))
+}
+
+fn shielding_range(event: &ShieldingEvent) -> (u32, u32) {
+    (event.start_frame, event.end_frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dribbling_detection::dribble_models::DribbleEvent;
+
+    #[test]
+    fn test_deduplicate_videos() {
+        let clip = ClipIdentity {
+            game_id: "3".to_string(),
+            half: Some(1),
+            clip_start: 1_020_000,
+            clip_stop: 1_050_000,
+        };
+        let label = |start: u32, end: u32| {
+            let mut event = DribbleEvent::new(1, start, "SNGS-001".to_string());
+            event.end_frame = Some(end);
+            DribbleLabel::from(&event)
+        };
+        let video = |subset: &str, clip: Option<ClipIdentity>, events: Vec<DribbleLabel>| {
+            VideoDribbleEvents {
+                video_id: "SNGS-001".to_string(),
+                dataset: None,
+                subset: Some(subset.to_string()),
+                clip,
+                frame_rate: None,
+                dribble_events: events,
+                shielding_events: Vec::new(),
+            }
+        };
+
+        let (videos, num_duplicates) = deduplicate_videos(vec![
+            video("gt", Some(clip.clone()), vec![label(100, 150)]),
+            video("test", None, vec![label(10, 20)]),
+            video(
+                "interpolated-predictions",
+                Some(clip),
+                vec![label(140, 160), label(20, 40)],
+            ),
+        ]);
+
+        assert_eq!(num_duplicates, 1);
+        assert_eq!(videos.len(), 2);
+        assert_eq!(videos[0].subset.as_deref(), Some("gt"));
+        // The overlapping prediction is dropped, the other one is merged in
+        let starts: Vec<u32> = videos[0]
+            .dribble_events
+            .iter()
+            .map(|e| e.start_frame)
+            .collect();
+        assert_eq!(starts, vec![20, 100]);
+        assert_eq!(videos[1].subset.as_deref(), Some("test"));
+    }
+}
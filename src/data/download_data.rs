@@ -3,11 +3,13 @@ use reqwest::Client;
 use std::fs::{self, File};
 use std::io::{self, Read};
 use std::path::Path;
+use std::sync::Arc;
 use tokio::fs::File as TokioFile;
 use tokio::io::AsyncWriteExt;
 use zip::read::ZipArchive;
 
 use crate::config::Config;
+use crate::data::storage::Storage;
 
 ///////////////////////////////////////////////////////////
 ////////////  Notice: This is not working yet  ///////////
@@ -67,6 +69,31 @@ async fn download_large_file(url: &str, local_path: &str) {
     );
 }
 
+/// Copies a file from a storage backend to disk without holding it in memory
+async fn download_from_storage(storage: &Arc<Storage>, url: &str, local_path: &str) {
+    let (source, destination) = (url.to_string(), local_path.to_string());
+    let storage = storage.clone();
+    // The storage backends block, so they must not run on the threads of this runtime
+    let copied = tokio::task::spawn_blocking(move || {
+        if let Some(parent) = Path::new(&destination).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let (backend, path) = storage.backend(Path::new(&source))?;
+        backend.copy_to(&path, &mut File::create(&destination)?)
+    })
+    .await
+    .expect("Download task panicked");
+
+    match copied {
+        Ok(bytes) => println!(
+            "Download complete: {} ({} MB)",
+            local_path,
+            bytes / (1024 * 1024)
+        ),
+        Err(e) => panic!("Failed to download {}: {}", url, e),
+    }
+}
+
 /// Extracts a ZIP file from disk and saves contents to a directory
 fn extract_zip(zip_path: &str, output_dir: &str) -> io::Result<()> {
     let mut file = File::open(zip_path)?;
@@ -111,16 +138,27 @@ pub async fn download_and_extract_dataset(config: &Config) {
         fs::create_dir_all(data_path).expect("Failed to create local directory");
     }
 
+    let storage = Arc::new(Storage::from_config(config));
     for subset in &config.data.subsets {
         let zip_file_path = format!("{}/{}_dataset.zip", data_path, subset);
-        let dataset_url = format!("{}&split={}", config.data.huggingface_dataset_url, subset);
 
         // 2. Download large ZIP file (streamed directly to disk)
-        println!(
-            "Starting download of {} dataset from: {}",
-            subset, dataset_url
-        );
-        download_large_file(&dataset_url, &zip_file_path).await;
+        match &config.data.archive_url {
+            Some(archive_url) => {
+                let url = format!("{}/{}.zip", archive_url.trim_end_matches('/'), subset);
+                println!("Starting download of {} dataset from: {}", subset, url);
+                download_from_storage(&storage, &url, &zip_file_path).await;
+            }
+            None => {
+                let dataset_url =
+                    format!("{}&split={}", config.data.huggingface_dataset_url, subset);
+                println!(
+                    "Starting download of {} dataset from: {}",
+                    subset, dataset_url
+                );
+                download_large_file(&dataset_url, &zip_file_path).await;
+            }
+        }
 
         // 3. Validate and Extract the ZIP file to the output directory
         println!("Extracting ZIP file: {}", zip_file_path);
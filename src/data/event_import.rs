@@ -0,0 +1,227 @@
+//! Events of other detectors, merged with the detected events before they are exported, e.g.
+//! for ensemble experiments.
+
+use super::models::{video_key, DribbleEventsExport};
+use crate::config::{ImportConfig, MergeStrategy};
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Events per video of an event file, by [`video_key`].
+pub type ImportedEvents = HashMap<String, Vec<DribbleEvent>>;
+
+/// Reads an event file: a `.csv` file, or JSON in the format of `dribble_events.json`.
+///
+/// CSV files have a header with the columns `video_id`, `start_frame` and `end_frame`, and
+/// optionally `kind` ("dribble" or "tackle") and `possession_holder`, in any order.
+pub fn load_event_file(path: &Path) -> io::Result<ImportedEvents> {
+    let text = fs::read_to_string(path)?;
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv")) {
+        return parse_event_csv(&text);
+    }
+
+    let export: DribbleEventsExport = serde_json::from_str(&text)?;
+    Ok(export
+        .videos
+        .iter()
+        .map(|video| {
+            let events = video
+                .dribble_events
+                .iter()
+                .map(|label| label.to_event(&video.video_id))
+                .collect();
+            (video_key(video.dataset.as_deref(), &video.video_id), events)
+        })
+        .collect())
+}
+
+fn parse_event_csv(text: &str) -> io::Result<ImportedEvents> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<&str> = lines
+        .next()
+        .ok_or_else(|| invalid("the file is empty".to_string()))?
+        .split(',')
+        .map(str::trim)
+        .collect();
+    let column = |name: &str| header.iter().position(|c| *c == name);
+    let (Some(video_col), Some(start_col), Some(end_col)) =
+        (column("video_id"), column("start_frame"), column("end_frame"))
+    else {
+        return Err(invalid(
+            "the header needs video_id, start_frame and end_frame columns".to_string(),
+        ));
+    };
+    let kind_col = column("kind");
+    let holder_col = column("possession_holder");
+
+    let mut events = ImportedEvents::new();
+    for (line_num, line) in lines.enumerate() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |col: usize| fields.get(col).copied().unwrap_or_default();
+        let frame = |col: usize| {
+            field(col).parse::<u32>().map_err(|_| {
+                invalid(format!("line {}: invalid frame {:?}", line_num + 2, field(col)))
+            })
+        };
+        let start_frame = frame(start_col)?;
+        let end_frame = frame(end_col)?;
+        let holder = holder_col.and_then(|col| field(col).parse().ok()).unwrap_or(0);
+
+        let video_id = field(video_col).to_string();
+        let mut event = DribbleEvent::new(holder, start_frame, video_id.clone());
+        event.end_frame = Some(end_frame);
+        event.frames = (start_frame..=end_frame).collect();
+        event.finished = true;
+        if kind_col.is_some_and(|col| field(col).eq_ignore_ascii_case("tackle")) {
+            event.detected_tackle = true;
+        } else {
+            event.detected_dribble = true;
+        }
+        events.entry(video_id).or_default().push(event);
+    }
+    Ok(events)
+}
+
+/// Reads the event files of `config`. Files that can not be read are reported and skipped.
+pub fn load_imported_events(config: &ImportConfig) -> Vec<ImportedEvents> {
+    config
+        .files
+        .iter()
+        .filter_map(|file| match load_event_file(Path::new(file)) {
+            Ok(events) => Some(events),
+            Err(e) => {
+                eprintln!("Error importing events from {}: {}", file, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Merges the imported events of every video of `events` into its detected events:
+/// - `union`: the events of all sources, with overlapping events combined into one that
+///   covers them all
+/// - `intersection`: the detected events that overlap an event of every imported file
+/// - `priority`: the events of all sources, but of overlapping events only the one of the
+///   first source, with the detected events first and then the files in order
+///
+/// `events` are keyed by [`video_key`]. Imported events without a dataset, e.g. of CSV files,
+/// match videos of any dataset by name. Videos that are not in `events` are ignored.
+pub fn merge_imported_events(
+    events: &mut HashMap<String, Vec<DribbleEvent>>,
+    imported: &[ImportedEvents],
+    strategy: MergeStrategy,
+) {
+    for (video_id, video_events) in events.iter_mut() {
+        let others: Vec<&[DribbleEvent]> = imported
+            .iter()
+            .map(|source| {
+                let name = video_id.rsplit('/').next().unwrap_or(video_id);
+                source
+                    .get(video_id)
+                    .or_else(|| source.get(name))
+                    .map_or(&[][..], Vec::as_slice)
+            })
+            .collect();
+        match strategy {
+            MergeStrategy::Union => {
+                let tagged = video_events.drain(..).map(|e| (0, e)).chain(
+                    others.iter().enumerate().flat_map(|(i, source)| {
+                        source.iter().map(move |e| (i + 1, e.clone()))
+                    }),
+                );
+                *video_events = combine_overlapping(tagged.collect());
+            }
+            MergeStrategy::Intersection => video_events.retain(|event| {
+                others
+                    .iter()
+                    .all(|source| source.iter().any(|other| overlaps(event, other)))
+            }),
+            MergeStrategy::Priority => {
+                for event in others.iter().flat_map(|source| source.iter()) {
+                    if !video_events.iter().any(|e| overlaps(e, event)) {
+                        video_events.push(event.clone());
+                    }
+                }
+            }
+        }
+        video_events.sort_by_key(|e| e.start_frame);
+    }
+}
+
+fn frame_range(event: &DribbleEvent) -> (u32, u32) {
+    (event.start_frame, event.end_frame.unwrap_or(event.start_frame))
+}
+
+fn overlaps(a: &DribbleEvent, b: &DribbleEvent) -> bool {
+    let (a_start, a_end) = frame_range(a);
+    let (b_start, b_end) = frame_range(b);
+    a_start <= b_end && b_start <= a_end
+}
+
+/// Combines overlapping events into one that covers them all, ordered by start frame. Events
+/// are tagged with the index of their source, and a combined event keeps the kind and holder
+/// of the event of the first source.
+fn combine_overlapping(mut events: Vec<(usize, DribbleEvent)>) -> Vec<DribbleEvent> {
+    events.sort_by_key(|(_, e)| e.start_frame);
+    let mut combined: Vec<(usize, DribbleEvent)> = Vec::new();
+    for (source, event) in events {
+        match combined.last_mut() {
+            Some((last_source, last)) if overlaps(last, &event) => {
+                let (start, last_end) = frame_range(last);
+                let end = last_end.max(frame_range(&event).1);
+                if source < *last_source {
+                    *last_source = source;
+                    *last = event;
+                }
+                last.start_frame = start;
+                last.end_frame = Some(end);
+                last.frames = (start..=end).collect();
+            }
+            _ => combined.push((source, event)),
+        }
+    }
+    combined.into_iter().map(|(_, event)| event).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_imported_events() {
+        let csv = "video_id,start_frame,end_frame,kind\n\
+                   SNGS-001,90,120,tackle\n\
+                   SNGS-001,300,340,dribble\n\
+                   SNGS-002,10,20,dribble\n";
+        let imported = parse_event_csv(csv).unwrap();
+        assert!(imported["SNGS-001"][0].detected_tackle);
+        assert!(parse_event_csv("video,start\n").is_err());
+
+        let mut detected = DribbleEvent::new(7, 100, "SNGS-001".to_string());
+        detected.end_frame = Some(150);
+        detected.detected_dribble = true;
+        let detected_events = || HashMap::from([("SNGS-001".to_string(), vec![detected.clone()])]);
+        let ranges = |events: &HashMap<String, Vec<DribbleEvent>>| -> Vec<(u32, u32)> {
+            events["SNGS-001"].iter().map(frame_range).collect()
+        };
+        let sources = [imported];
+
+        let mut union = detected_events();
+        merge_imported_events(&mut union, &sources, MergeStrategy::Union);
+        assert_eq!(ranges(&union), vec![(90, 150), (300, 340)]);
+        assert!(union["SNGS-001"][0].detected_dribble);
+        // Videos that were not processed are not added
+        assert!(!union.contains_key("SNGS-002"));
+
+        let mut priority = detected_events();
+        merge_imported_events(&mut priority, &sources, MergeStrategy::Priority);
+        assert_eq!(ranges(&priority), vec![(100, 150), (300, 340)]);
+
+        let mut intersection = detected_events();
+        merge_imported_events(&mut intersection, &sources, MergeStrategy::Intersection);
+        assert_eq!(ranges(&intersection), vec![(100, 150)]);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::config::{ActionsConfig, DEFAULT_FRAME_RATE};
+use crate::data::models::{GroundTruthAction, Info};
+use crate::data::storage::Storage;
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+
+/// A SoccerNet action spotting file (`Labels-v2.json`) of a game.
+#[derive(Debug, Deserialize)]
+pub struct ActionLabels {
+    pub annotations: Vec<ActionAnnotation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActionAnnotation {
+    /// Half and time in the half, e.g. "1 - 35:09"
+    #[serde(rename = "gameTime")]
+    pub game_time: String,
+    pub label: String,
+    /// Milliseconds since the start of the half
+    pub position: String,
+    #[serde(default)]
+    pub team: Option<String>,
+    #[serde(default)]
+    pub visibility: Option<String>,
+}
+
+/// Reads the actions of a `Labels-v2.json` file that happened during the clip of `info`.
+pub fn load_actions(
+    path: &Path,
+    info: &Info,
+    storage: &Storage,
+) -> io::Result<Vec<GroundTruthAction>> {
+    let action_labels: ActionLabels =
+        storage.with_reader(path, |reader| Ok(serde_json::from_reader(reader)?))?;
+    Ok(clip_actions(&action_labels, info))
+}
+
+/// Converts the actions within the clip to frame numbers of the clip.
+///
+/// Game-state clips store their position in the half as `clip_start`/`clip_stop` (in
+/// milliseconds) and their half in `game_time_start`. Without them, the actions are taken to
+/// be relative to the start of the clip.
+pub fn clip_actions(action_labels: &ActionLabels, info: &Info) -> Vec<GroundTruthAction> {
+    let clip_start: u64 = info.clip_start.trim().parse().unwrap_or(0);
+    let clip_stop: Option<u64> = info.clip_stop.trim().parse().ok();
+    let clip_half = info.game_time_start.as_deref().and_then(half);
+    let frame_rate = if info.frame_rate > 0.0 {
+        info.frame_rate as f64
+    } else {
+        DEFAULT_FRAME_RATE
+    };
+
+    let mut actions: Vec<GroundTruthAction> = action_labels
+        .annotations
+        .iter()
+        .filter_map(|annotation| {
+            let position: u64 = annotation.position.trim().parse().ok()?;
+            let in_half = clip_half.is_none_or(|h| half(&annotation.game_time) == Some(h));
+            let in_clip = position >= clip_start && clip_stop.is_none_or(|stop| position <= stop);
+            if !in_half || !in_clip {
+                return None;
+            }
+
+            let seconds = (position - clip_start) as f64 / 1000.0;
+            Some(GroundTruthAction {
+                label: annotation.label.clone(),
+                team: annotation.team.clone(),
+                visible: annotation.visibility.as_deref() != Some("not shown"),
+                frame: (seconds * frame_rate).round() as u32 + 1,
+            })
+        })
+        .collect();
+    actions.sort_by_key(|action| action.frame);
+    actions
+}
+
+/// Parses the half of a game time, e.g. "2 - 12:31" -> 2.
+fn half(game_time: &str) -> Option<u32> {
+    game_time.split('-').next()?.trim().parse().ok()
+}
+
+/// Frame ranges in which the ball is out of play: from a dead-ball action (such as "Ball out
+/// of play" or "Foul") to the next restart (such as "Throw-in"), but at most
+/// `max_dead_ball_seconds`. `actions` must be ordered by frame.
+pub fn dead_ball_periods(
+    actions: &[GroundTruthAction],
+    config: &ActionsConfig,
+    frame_rate: f32,
+) -> Vec<(u32, u32)> {
+    let frame_rate = if frame_rate > 0.0 {
+        frame_rate
+    } else {
+        DEFAULT_FRAME_RATE as f32
+    };
+    let max_frames = (config.max_dead_ball_seconds * frame_rate as f64).round() as u32;
+
+    let mut periods: Vec<(u32, u32)> = Vec::new();
+    for (i, action) in actions.iter().enumerate() {
+        if !config.dead_ball_labels.contains(&action.label) {
+            continue;
+        }
+        let max_end = action.frame + max_frames;
+        let end = actions[i + 1..]
+            .iter()
+            .find(|a| config.restart_labels.contains(&a.label))
+            .map_or(max_end, |restart| restart.frame.min(max_end));
+
+        match periods.last_mut() {
+            Some((_, last_end)) if action.frame <= *last_end => *last_end = (*last_end).max(end),
+            _ => periods.push((action.frame, end)),
+        }
+    }
+    periods
+}
+
+/// Removes the events that overlap one of the dead-ball `periods`.
+pub fn suppress_dead_ball_events(events: &mut Vec<DribbleEvent>, periods: &[(u32, u32)]) {
+    events.retain(|event| {
+        let end = event.end_frame.unwrap_or(event.start_frame);
+        !periods
+            .iter()
+            .any(|(start, stop)| event.start_frame <= *stop && *start <= end)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS_V2_JSON: &str = r#"{
+        "UrlLocal": "england_epl/2014-2015/game/",
+        "annotations": [
+            {"gameTime": "1 - 17:05", "label": "Ball out of play", "position": "1025000", "team": "not applicable", "visibility": "visible"},
+            {"gameTime": "1 - 17:12", "label": "Throw-in", "position": "1032000", "team": "home", "visibility": "visible"},
+            {"gameTime": "1 - 17:40", "label": "Foul", "position": "1060000", "team": "away", "visibility": "visible"},
+            {"gameTime": "2 - 17:06", "label": "Corner", "position": "1026000", "team": "home", "visibility": "not shown"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_clip_actions() {
+        let action_labels: ActionLabels = serde_json::from_str(LABELS_V2_JSON).unwrap();
+        let info = Info {
+            clip_start: "1020000".to_string(),
+            clip_stop: "1050000".to_string(),
+            game_time_start: Some("1 - 17:00".to_string()),
+            frame_rate: 25.0,
+            ..Default::default()
+        };
+
+        // The foul is after the clip, and the corner is in the other half
+        let actions = clip_actions(&action_labels, &info);
+        let labels: Vec<(&str, u32)> = actions
+            .iter()
+            .map(|a| (a.label.as_str(), a.frame))
+            .collect();
+        assert_eq!(labels, vec![("Ball out of play", 126), ("Throw-in", 301)]);
+
+        let config = ActionsConfig::default();
+        let periods = dead_ball_periods(&actions, &config, 25.0);
+        assert_eq!(periods, vec![(126, 301)]);
+
+        let mut events = vec![
+            DribbleEvent::new(1, 100, "SNGS-001".to_string()),
+            DribbleEvent::new(2, 310, "SNGS-001".to_string()),
+        ];
+        events[0].end_frame = Some(130);
+        suppress_dead_ball_events(&mut events, &periods);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].start_frame, 310);
+    }
+}
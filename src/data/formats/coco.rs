@@ -0,0 +1,248 @@
+use super::{categories, CATEGORIES};
+use crate::config::CocoConfig;
+use crate::data::models::{Annotation, Attribute, BboxImage, Image, Info, Labels, VideoData};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// A COCO annotation file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CocoDataset {
+    pub images: Vec<CocoImage>,
+    pub annotations: Vec<CocoAnnotation>,
+    pub categories: Vec<CocoCategory>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CocoImage {
+    pub id: u64,
+    pub file_name: String,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CocoAnnotation {
+    pub id: u64,
+    pub image_id: u64,
+    pub category_id: u64,
+    /// `[x, y, width, height]` in pixels
+    pub bbox: [f64; 4],
+    /// Track id, as written by video labeling tools
+    #[serde(default)]
+    pub track_id: Option<u32>,
+    /// Free-form attributes. CVAT stores `track_id` and custom fields such as `team` here.
+    #[serde(default)]
+    pub attributes: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CocoCategory {
+    pub id: u64,
+    pub name: String,
+}
+
+pub fn is_coco_sequence(seq_dir: &Path, coco_config: &CocoConfig) -> bool {
+    seq_dir.join(&coco_config.labels_file).exists()
+}
+
+/// Loads a sequence annotated with a COCO file and converts it to the internal
+/// `Labels`/`VideoData` structures.
+pub fn load_coco_sequence(seq_dir: &Path, coco_config: &CocoConfig) -> io::Result<VideoData> {
+    let file = File::open(seq_dir.join(&coco_config.labels_file))?;
+    let dataset: CocoDataset = serde_json::from_reader(BufReader::new(file))?;
+
+    let name = seq_dir
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let labels = to_labels(&name, &dataset, coco_config);
+    let image_paths = labels
+        .images
+        .iter()
+        .map(|image| seq_dir.join(&coco_config.image_dir).join(&image.file_name))
+        .collect();
+
+    Ok(VideoData {
+        dir_path: seq_dir.to_path_buf(),
+        image_paths,
+        labels,
+        video_path: None,
+        video_first_frame: None,
+        dataset: None,
+        actions: Vec::new(),
+        labels_reader: None,
+        storage: Default::default(),
+    })
+}
+
+/// Converts a COCO dataset to labels. Images are ordered by file name, and annotations whose
+/// category is not mapped to one of the detector's categories are dropped.
+pub fn to_labels(name: &str, dataset: &CocoDataset, coco_config: &CocoConfig) -> Labels {
+    // COCO category id -> detector category id
+    let category_ids: HashMap<u64, u32> = dataset
+        .categories
+        .iter()
+        .filter_map(|category| {
+            let mapped = coco_config
+                .categories
+                .get(&category.name)
+                .unwrap_or(&category.name);
+            CATEGORIES
+                .iter()
+                .find(|(_, name)| name == mapped)
+                .map(|(id, _)| (category.id, *id))
+        })
+        .collect();
+
+    let mut images: Vec<&CocoImage> = dataset.images.iter().collect();
+    images.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    let image_order: HashMap<u64, usize> = images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| (image.id, i))
+        .collect();
+
+    // Grouped per image in image order, like in the SoccerNet files
+    let mut coco_annotations: Vec<&CocoAnnotation> = dataset
+        .annotations
+        .iter()
+        .filter(|a| image_order.contains_key(&a.image_id))
+        .collect();
+    coco_annotations.sort_by_key(|a| (image_order[&a.image_id], a.id));
+
+    let annotations = coco_annotations
+        .into_iter()
+        .filter_map(|annotation| {
+            let category_id = *category_ids.get(&annotation.category_id)?;
+            let [x, y, w, h] = annotation.bbox;
+            let track_id = annotation.track_id.or_else(|| {
+                annotation
+                    .attributes
+                    .get("track_id")
+                    .and_then(|v| v.as_u64())
+                    .map(|id| id as u32)
+            });
+            let attribute = |key: &str| {
+                annotation
+                    .attributes
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string())
+            };
+
+            Some(Annotation {
+                id: annotation.id.to_string(),
+                image_id: annotation.image_id.to_string(),
+                track_id,
+                supercategory: "object".to_string(),
+                category_id,
+                bbox_image: Some(BboxImage {
+                    x,
+                    y,
+                    x_center: x + w / 2.0,
+                    y_center: y + h / 2.0,
+                    w,
+                    h,
+                }),
+                bbox_pitch: None,
+                bbox_pitch_raw: None,
+                attributes: Some(Attribute {
+                    role: attribute("role"),
+                    jersey: attribute("jersey"),
+                    team: attribute("team"),
+                }),
+                lines: None,
+            })
+        })
+        .collect();
+
+    let seq_length = images.len() as u32;
+    Labels {
+        info: Info {
+            version: "coco".to_string(),
+            clip_start: "0".to_string(),
+            clip_stop: seq_length.to_string(),
+            name: name.to_string(),
+            im_dir: Some(coco_config.image_dir.clone()),
+            frame_rate: coco_config.frame_rate,
+            seq_length,
+            im_ext: images
+                .first()
+                .and_then(|image| Path::new(&image.file_name).extension())
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+                .unwrap_or_default(),
+            ..Default::default()
+        },
+        images: images
+            .into_iter()
+            .map(|image| Image {
+                is_labeled: true,
+                image_id: image.id.to_string(),
+                file_name: image.file_name.clone(),
+                height: image.height,
+                width: image.width,
+                has_labeled_person: None,
+                has_labeled_pitch: None,
+            })
+            .collect(),
+        annotations,
+        categories: categories(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COCO_JSON: &str = r#"{
+        "images": [
+            {"id": 2, "file_name": "000002.jpg", "width": 1920, "height": 1080},
+            {"id": 1, "file_name": "000001.jpg", "width": 1920, "height": 1080}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 2, "category_id": 1, "bbox": [10, 20, 30, 40], "track_id": 3},
+            {"id": 2, "image_id": 1, "category_id": 2, "bbox": [0, 0, 4, 4]},
+            {"id": 3, "image_id": 1, "category_id": 1, "bbox": [10, 20, 30, 40],
+             "attributes": {"track_id": 5, "team": "left"}},
+            {"id": 4, "image_id": 1, "category_id": 3, "bbox": [0, 0, 1, 1]}
+        ],
+        "categories": [
+            {"id": 1, "name": "person"},
+            {"id": 2, "name": "ball"},
+            {"id": 3, "name": "crowd"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_coco_to_labels() {
+        let dataset: CocoDataset = serde_json::from_str(COCO_JSON).unwrap();
+        let coco_config = CocoConfig {
+            labels_file: "annotations.json".to_string(),
+            image_dir: "images".to_string(),
+            frame_rate: 25.0,
+            categories: HashMap::from([("person".to_string(), "player".to_string())]),
+        };
+        let labels = to_labels("seq", &dataset, &coco_config);
+
+        assert_eq!(labels.images[0].file_name, "000001.jpg");
+        assert_eq!(labels.info.im_ext, ".jpg");
+
+        // The "crowd" annotation is dropped, the rest is in image order
+        let ids: Vec<&str> = labels.annotations.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "3", "1"]);
+        assert_eq!(labels.annotations[0].category_id, 4);
+        assert_eq!(labels.annotations[1].category_id, 1);
+        assert_eq!(labels.annotations[1].track_id, Some(5));
+        assert_eq!(
+            labels.annotations[1].attributes.as_ref().unwrap().team.as_deref(),
+            Some("left")
+        );
+        assert_eq!(labels.annotations[2].track_id, Some(3));
+    }
+}
@@ -0,0 +1,23 @@
+use super::models::Category;
+
+pub mod actions;
+pub mod coco;
+pub mod mot;
+
+/// Category ids and names used by the detector. The ids match the SoccerNet category ids, so
+/// labels converted from other formats look like a `Labels-GameState.json`.
+pub const CATEGORIES: [(u32, &str); 4] =
+    [(1, "player"), (2, "goalkeeper"), (3, "referee"), (4, "ball")];
+
+/// Returns [`CATEGORIES`] as label categories.
+pub fn categories() -> Vec<Category> {
+    CATEGORIES
+        .iter()
+        .map(|(id, name)| Category {
+            supercategory: "object".to_string(),
+            id: *id,
+            name: name.to_string(),
+            lines: None,
+        })
+        .collect()
+}
@@ -0,0 +1,297 @@
+//! Loader for sequences in the MOT Challenge format:
+//!
+//! ```text
+//! <sequence>/seqinfo.ini    (optional)
+//! <sequence>/gt/gt.txt      or <sequence>/det/det.txt
+//! <sequence>/img1/000001.jpg
+//! ```
+//!
+//! Each line of `gt.txt`/`det.txt` is `frame, id, bb_left, bb_top, bb_width, bb_height, conf,
+//! class, ...`. MOT files have no pitch coordinates, so MOT sequences only work with
+//! `use_2d = false`.
+
+use super::categories;
+use crate::data::models::{Annotation, Attribute, BboxImage, Image, Info, Labels, VideoData};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Sequence metadata from `seqinfo.ini`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeqInfo {
+    pub name: String,
+    pub im_dir: String,
+    pub frame_rate: f32,
+    pub seq_length: Option<u32>,
+    pub im_width: u32,
+    pub im_height: u32,
+    pub im_ext: String,
+}
+
+impl Default for SeqInfo {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            im_dir: "img1".to_string(),
+            frame_rate: 25.0,
+            seq_length: None,
+            im_width: 0,
+            im_height: 0,
+            im_ext: ".jpg".to_string(),
+        }
+    }
+}
+
+/// A single line of a MOT annotation file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MotEntry {
+    pub frame: u32,
+    pub track_id: Option<u32>,
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+    pub conf: f64,
+    pub class: u32,
+}
+
+/// Returns the annotation file of a MOT sequence, preferring ground truth over detections.
+pub fn mot_annotation_file(seq_dir: &Path) -> Option<PathBuf> {
+    [
+        seq_dir.join("gt").join("gt.txt"),
+        seq_dir.join("gt.txt"),
+        seq_dir.join("det").join("det.txt"),
+        seq_dir.join("det.txt"),
+    ]
+    .into_iter()
+    .find(|path| path.exists())
+}
+
+pub fn is_mot_sequence(seq_dir: &Path) -> bool {
+    mot_annotation_file(seq_dir).is_some()
+}
+
+/// Loads a MOT sequence and converts it to the internal `Labels`/`VideoData` structures.
+pub fn load_mot_sequence(seq_dir: &Path) -> io::Result<VideoData> {
+    let annotation_file = mot_annotation_file(seq_dir).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No gt.txt or det.txt found in {:?}", seq_dir),
+        )
+    })?;
+
+    let mut seq_info = match fs::read_to_string(seq_dir.join("seqinfo.ini")) {
+        Ok(content) => parse_seqinfo(&content),
+        Err(_) => SeqInfo::default(),
+    };
+    if seq_info.name.is_empty() {
+        seq_info.name = seq_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+    }
+
+    let entries = parse_mot_entries(&fs::read_to_string(&annotation_file)?)?;
+    // In ground truth files, conf 0 marks entries that should be ignored
+    let is_gt = annotation_file
+        .file_name()
+        .is_some_and(|name| name == "gt.txt");
+    let entries: Vec<MotEntry> = entries
+        .into_iter()
+        .filter(|e| !is_gt || e.conf != 0.0)
+        .collect();
+
+    let labels = to_labels(&seq_info, &entries);
+    let image_paths = labels
+        .images
+        .iter()
+        .map(|image| seq_dir.join(&seq_info.im_dir).join(&image.file_name))
+        .collect();
+
+    Ok(VideoData {
+        dir_path: seq_dir.to_path_buf(),
+        image_paths,
+        labels,
+        video_path: None,
+        video_first_frame: None,
+        dataset: None,
+        actions: Vec::new(),
+        labels_reader: None,
+        storage: Default::default(),
+    })
+}
+
+/// Parses the `[Sequence]` section of a `seqinfo.ini` file. Missing keys keep their defaults.
+pub fn parse_seqinfo(content: &str) -> SeqInfo {
+    let mut seq_info = SeqInfo::default();
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "name" => seq_info.name = value.to_string(),
+            "imDir" => seq_info.im_dir = value.to_string(),
+            "frameRate" => seq_info.frame_rate = value.parse().unwrap_or(seq_info.frame_rate),
+            "seqLength" => seq_info.seq_length = value.parse().ok(),
+            "imWidth" => seq_info.im_width = value.parse().unwrap_or(0),
+            "imHeight" => seq_info.im_height = value.parse().unwrap_or(0),
+            "imExt" => seq_info.im_ext = value.to_string(),
+            _ => {}
+        }
+    }
+    seq_info
+}
+
+/// Parses the lines of a `gt.txt`/`det.txt` file. Detection files without a class column
+/// are treated as players.
+pub fn parse_mot_entries(content: &str) -> io::Result<Vec<MotEntry>> {
+    let invalid = |line_num: usize, line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid MOT line {}: {:?}", line_num + 1, line),
+        )
+    };
+
+    let mut entries = Vec::new();
+    for (line_num, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let values: Vec<f64> = line
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid(line_num, line))?;
+        if values.len() < 6 {
+            return Err(invalid(line_num, line));
+        }
+
+        let class = values.get(7).copied().unwrap_or(1.0);
+        entries.push(MotEntry {
+            frame: values[0] as u32,
+            track_id: (values[1] >= 0.0).then_some(values[1] as u32),
+            x: values[2],
+            y: values[3],
+            w: values[4],
+            h: values[5],
+            conf: values.get(6).copied().unwrap_or(1.0),
+            // Detection files use -1 for "no class"
+            class: if class > 0.0 { class as u32 } else { 1 },
+        });
+    }
+    Ok(entries)
+}
+
+/// Builds labels with one image per frame (`1..=seq_length`) and one annotation per entry.
+pub fn to_labels(seq_info: &SeqInfo, entries: &[MotEntry]) -> Labels {
+    let seq_length = seq_info
+        .seq_length
+        .unwrap_or_else(|| entries.iter().map(|e| e.frame).max().unwrap_or(0));
+
+    let images = (1..=seq_length)
+        .map(|frame| Image {
+            is_labeled: true,
+            image_id: frame.to_string(),
+            file_name: format!("{:06}{}", frame, seq_info.im_ext),
+            height: seq_info.im_height,
+            width: seq_info.im_width,
+            has_labeled_person: None,
+            has_labeled_pitch: None,
+        })
+        .collect();
+
+    // Sorted by frame, so annotations are grouped per image like in the SoccerNet files
+    let mut by_frame: BTreeMap<u32, Vec<&MotEntry>> = BTreeMap::new();
+    for entry in entries {
+        by_frame.entry(entry.frame).or_default().push(entry);
+    }
+
+    let annotations = by_frame
+        .into_values()
+        .flatten()
+        .enumerate()
+        .map(|(i, entry)| Annotation {
+            id: i.to_string(),
+            image_id: entry.frame.to_string(),
+            track_id: entry.track_id,
+            supercategory: "object".to_string(),
+            // MOT classes use the same ids as the SoccerNet categories
+            category_id: entry.class,
+            bbox_image: Some(BboxImage {
+                x: entry.x,
+                y: entry.y,
+                x_center: entry.x + entry.w / 2.0,
+                y_center: entry.y + entry.h / 2.0,
+                w: entry.w,
+                h: entry.h,
+            }),
+            bbox_pitch: None,
+            bbox_pitch_raw: None,
+            attributes: Some(Attribute::default()),
+            lines: None,
+        })
+        .collect();
+
+    Labels {
+        info: Info {
+            version: "mot".to_string(),
+            clip_start: "0".to_string(),
+            clip_stop: seq_length.to_string(),
+            name: seq_info.name.clone(),
+            im_dir: Some(seq_info.im_dir.clone()),
+            frame_rate: seq_info.frame_rate,
+            seq_length,
+            im_ext: seq_info.im_ext.clone(),
+            ..Default::default()
+        },
+        images,
+        annotations,
+        categories: categories(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_seqinfo() {
+        let content = "[Sequence]\nname=SNMOT-060\nimDir=img1\nframeRate=25\nseqLength=750\nimWidth=1920\nimHeight=1080\nimExt=.jpg\n";
+        let seq_info = parse_seqinfo(content);
+        assert_eq!(seq_info.name, "SNMOT-060");
+        assert_eq!(seq_info.seq_length, Some(750));
+        assert_eq!(seq_info.im_width, 1920);
+        assert_eq!(seq_info.frame_rate, 25.0);
+    }
+
+    #[test]
+    fn test_mot_to_labels() {
+        let content = "2,7,100,200,20,40,1,1,1\n1,7,90,200,20,40,1,1,1\n1,-1,50,60,4,4,0.8,-1,-1,-1\n";
+        let entries = parse_mot_entries(content).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].track_id, None);
+        assert_eq!(entries[2].class, 1);
+
+        let labels = to_labels(&SeqInfo::default(), &entries);
+        assert_eq!(labels.images.len(), 2);
+        assert_eq!(labels.images[0].file_name, "000001.jpg");
+        assert_eq!(labels.annotations.len(), 3);
+        assert_eq!(labels.annotations[0].image_id, "1");
+        assert_eq!(labels.annotations[2].image_id, "2");
+
+        let bbox = labels.annotations[2].bbox_image.as_ref().unwrap();
+        assert_eq!((bbox.x_center, bbox.y_center), (110.0, 220.0));
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        assert!(parse_mot_entries("1,2,3\n").is_err());
+        assert!(parse_mot_entries("a,b,c,d,e,f\n").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use super::labels_reader::{InvalidAnnotation, LabelsReader};
+use super::models::Labels;
+use super::storage::Storage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// Bumped whenever the cached structures change, so old caches are parsed again
+const CACHE_VERSION: u32 = 3;
+
+/// Size and modification time of a labels file. A cache is only used if they still match.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+struct SourceFingerprint {
+    len: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+/// Everything [`LabelsReader::open`] reads from a labels file, stored in a binary format next
+/// to it. The annotations themselves are still streamed from the JSON file.
+#[derive(Deserialize, Serialize)]
+struct LabelsCache {
+    version: u32,
+    source: SourceFingerprint,
+    labels: Labels,
+    /// Annotations skipped by a lenient read. A cache with skipped annotations is not used by
+    /// strict reads, which should fail on them instead.
+    invalid_annotations: Vec<InvalidAnnotation>,
+    bbox_heights: HashMap<u32, (f64, usize)>,
+    track_lengths: HashMap<u32, usize>,
+    in_image_order: bool,
+}
+
+/// Path of the cache of a labels file, e.g. `Labels-GameState.json` ->
+/// `Labels-GameState.cache.bin`.
+pub fn cache_path(labels_path: &Path) -> PathBuf {
+    labels_path.with_extension("cache.bin")
+}
+
+impl LabelsReader {
+    /// Like [`LabelsReader::open`], but reads the labels from a binary cache next to the file
+    /// if it was written for the current version of the file. Otherwise the file is parsed and
+    /// the cache is (re)written. See [`LabelsReader::open_lenient`] for `lenient`. The
+    /// annotations are read from `storage`.
+    pub fn open_cached(
+        path: &Path,
+        lenient: bool,
+        storage: &Arc<Storage>,
+    ) -> io::Result<(Labels, Self)> {
+        let source = fingerprint(path)?;
+        let cache_path = cache_path(path);
+
+        let cache = read_cache(&cache_path)
+            .filter(|c| c.source == source && (lenient || c.invalid_annotations.is_empty()));
+        if let Some(cache) = cache {
+            return Ok((
+                cache.labels,
+                Self {
+                    path: path.to_path_buf(),
+                    lenient,
+                    invalid_annotations: cache.invalid_annotations,
+                    bbox_heights: cache.bbox_heights,
+                    track_lengths: cache.track_lengths,
+                    in_image_order: cache.in_image_order,
+                    homographies: None,
+                    storage: storage.clone(),
+                },
+            ));
+        }
+
+        let (labels, reader) = Self::open_with(path, lenient, storage)?;
+        let cache = LabelsCache {
+            version: CACHE_VERSION,
+            source,
+            labels,
+            invalid_annotations: reader.invalid_annotations.clone(),
+            bbox_heights: reader.bbox_heights.clone(),
+            track_lengths: reader.track_lengths.clone(),
+            in_image_order: reader.in_image_order,
+        };
+        if let Err(e) = write_cache(&cache_path, &cache) {
+            eprintln!("Could not write labels cache {:?}: {}", cache_path, e);
+        }
+        Ok((cache.labels, reader))
+    }
+}
+
+fn fingerprint(path: &Path) -> io::Result<SourceFingerprint> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(SourceFingerprint {
+        len: metadata.len(),
+        modified_secs: modified.as_secs(),
+        modified_nanos: modified.subsec_nanos(),
+    })
+}
+
+/// Returns `None` if there is no cache, or if it can not be read or has another version.
+fn read_cache(cache_path: &Path) -> Option<LabelsCache> {
+    let file = File::open(cache_path).ok()?;
+    let cache: LabelsCache = bincode::deserialize_from(BufReader::new(file)).ok()?;
+    (cache.version == CACHE_VERSION).then_some(cache)
+}
+
+/// Writes to a temporary file first, so a crash never leaves a half-written cache behind.
+fn write_cache(cache_path: &Path, cache: &LabelsCache) -> io::Result<()> {
+    let tmp_path = cache_path.with_extension("bin.tmp");
+    let writer = BufWriter::new(File::create(&tmp_path)?);
+    bincode::serialize_into(writer, cache).map_err(io::Error::other)?;
+    fs::rename(tmp_path, cache_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS_JSON: &str = r#"{
+        "info": {"version": "1.3", "clip_start": "0", "clip_stop": "1", "name": "SNGS-001",
+                 "frame_rate": 25, "seq_length": 1, "im_ext": ".jpg"},
+        "images": [
+            {"is_labeled": true, "image_id": "1", "file_name": "000001.jpg", "height": 1080, "width": 1920}
+        ],
+        "annotations": [
+            {"id": "a", "image_id": "1", "track_id": 7, "supercategory": "object", "category_id": 1,
+             "bbox_image": {"x": 0.0, "y": 0.0, "x_center": 5.0, "y_center": 10.0, "w": 10.0, "h": 20.0},
+             "bbox_pitch": null, "bbox_pitch_raw": null, "attributes": null}
+        ],
+        "categories": [{"supercategory": "object", "id": 1, "name": "player"}]
+    }"#;
+
+    #[test]
+    fn test_labels_cache() {
+        let dir = std::env::temp_dir().join(format!("labels_cache_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Labels-GameState.json");
+        fs::write(&path, LABELS_JSON).unwrap();
+        let storage = Arc::default();
+
+        let (labels, _) = LabelsReader::open_cached(&path, false, &storage).unwrap();
+        assert!(cache_path(&path).exists());
+        assert_eq!(cache_path(&path), dir.join("Labels-GameState.cache.bin"));
+
+        // Read from the cache
+        let (cached_labels, reader) = LabelsReader::open_cached(&path, false, &storage).unwrap();
+        assert_eq!(cached_labels.info.name, labels.info.name);
+        assert_eq!(cached_labels.images.len(), 1);
+        assert_eq!(reader.average_bbox_height(1), 20.0);
+        assert_eq!(reader.track_lengths()[&7], 1);
+        assert_eq!(reader.read_annotations().unwrap().len(), 1);
+
+        // A changed file invalidates the cache
+        fs::write(&path, LABELS_JSON.replace("SNGS-001", "SNGS-0002")).unwrap();
+        let (labels, _) = LabelsReader::open_cached(&path, false, &storage).unwrap();
+        assert_eq!(labels.info.name, "SNGS-0002");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
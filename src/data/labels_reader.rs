@@ -0,0 +1,625 @@
+use super::models::{Annotation, Category, Image, Info, Labels};
+use super::storage::Storage;
+use crate::utils::homography::Homographies;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+
+/// Number of images whose annotations may be read ahead of the consumer
+const PREFETCH_BATCHES: usize = 64;
+
+/// Reads a `Labels-GameState.json` file without keeping all annotations in memory.
+///
+/// [`LabelsReader::open`] reads everything except the annotations, which can then be streamed
+/// one image at a time with [`LabelsReader::batches`]. In the SoccerNet files annotations are
+/// grouped by image, in the same order as `images`, which lets [`FrameAnnotations`] hand them
+/// out without reading ahead. Files in any other order are still read correctly.
+///
+/// Opened with [`LabelsReader::open_lenient`], annotations that do not match the expected
+/// format are skipped instead of failing the whole file. They are listed in
+/// [`LabelsReader::invalid_annotations`].
+#[derive(Clone, Debug)]
+pub struct LabelsReader {
+    pub(super) path: PathBuf,
+    pub(super) lenient: bool,
+    pub(super) invalid_annotations: Vec<InvalidAnnotation>,
+    /// Sum and count of the image bounding box heights per category id
+    pub(super) bbox_heights: HashMap<u32, (f64, usize)>,
+    /// Number of annotations per track id
+    pub(super) track_lengths: HashMap<u32, usize>,
+    /// Whether the annotations are grouped by image, in the same order as `images`
+    pub(super) in_image_order: bool,
+    /// Computes the missing pitch bounding boxes of the annotations, see
+    /// [`LabelsReader::set_homographies`]
+    pub(super) homographies: Option<Arc<Homographies>>,
+    /// Storage of the dataset the file is read from
+    pub(super) storage: Arc<Storage>,
+}
+
+/// An annotation that was skipped when reading a labels file leniently.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct InvalidAnnotation {
+    pub file: PathBuf,
+    /// Position of the annotation in the `annotations` list
+    pub index: usize,
+    /// Path of the field that could not be read, e.g. `bbox_image.h`. Not set for errors of
+    /// the annotation itself, such as a missing field.
+    pub field: Option<String>,
+    pub error: String,
+}
+
+/// Field path and error of an annotation that could not be parsed.
+type InvalidField = (Option<String>, String);
+
+/// All annotations of a single image.
+#[derive(Clone, Debug)]
+pub struct AnnotationBatch {
+    pub image_id: String,
+    pub annotations: Vec<Annotation>,
+}
+
+impl LabelsReader {
+    /// Reads the info, images and categories of a labels file. The returned `Labels` has no
+    /// annotations, they are read through the returned reader instead.
+    pub fn open(path: &Path) -> io::Result<(Labels, Self)> {
+        Self::open_with(path, false, &Arc::default())
+    }
+
+    /// Like [`LabelsReader::open`], but skips annotations that can not be parsed instead of
+    /// failing. The info, images and categories must still be valid.
+    pub fn open_lenient(path: &Path) -> io::Result<(Labels, Self)> {
+        Self::open_with(path, true, &Arc::default())
+    }
+
+    /// Like [`LabelsReader::open`], with the file and the annotations read from `storage`.
+    pub(super) fn open_with(
+        path: &Path,
+        lenient: bool,
+        storage: &Arc<Storage>,
+    ) -> io::Result<(Labels, Self)> {
+        let mut bbox_heights: HashMap<u32, (f64, usize)> = HashMap::new();
+        let mut track_lengths: HashMap<u32, usize> = HashMap::new();
+        let mut invalid_annotations: Vec<InvalidAnnotation> = Vec::new();
+        let mut image_groups = ImageGroups::default();
+        let on_invalid = |index, (field, error): InvalidField| {
+            invalid_annotations.push(InvalidAnnotation {
+                file: path.to_path_buf(),
+                index,
+                field,
+                error,
+            })
+        };
+        let labels = read_labels(storage, path, lenient.then_some(on_invalid), |annotation| {
+            if let Some(bbox) = &annotation.bbox_image {
+                let entry = bbox_heights.entry(annotation.category_id).or_default();
+                entry.0 += bbox.h;
+                entry.1 += 1;
+            }
+            if let Some(track_id) = annotation.track_id {
+                *track_lengths.entry(track_id).or_default() += 1;
+            }
+            image_groups.push(&annotation.image_id);
+            true
+        })?;
+        let in_image_order = image_groups.in_order_of(&labels.images);
+
+        Ok((
+            labels,
+            Self {
+                path: path.to_path_buf(),
+                lenient,
+                invalid_annotations,
+                bbox_heights,
+                track_lengths,
+                in_image_order,
+                homographies: None,
+                storage: storage.clone(),
+            },
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Projects the image bounding boxes of annotations without a pitch bounding box onto the
+    /// pitch when they are read.
+    pub fn set_homographies(&mut self, homographies: Arc<Homographies>) {
+        self.homographies = Some(homographies);
+    }
+
+    /// Annotations that were skipped because they could not be parsed. Always empty unless the
+    /// reader was opened with [`LabelsReader::open_lenient`].
+    pub fn invalid_annotations(&self) -> &[InvalidAnnotation] {
+        &self.invalid_annotations
+    }
+
+    /// Average image bounding box height of the annotations in `category_id`, or 1.0 if there
+    /// are none.
+    pub fn average_bbox_height(&self, category_id: u32) -> f64 {
+        match self.bbox_heights.get(&category_id) {
+            Some((total, count)) if *count > 0 => total / *count as f64,
+            _ => 1.0,
+        }
+    }
+
+    /// Number of annotations (i.e. frames) of each track id.
+    pub fn track_lengths(&self) -> &HashMap<u32, usize> {
+        &self.track_lengths
+    }
+
+    /// Streams the annotations grouped per image. The file is parsed on a background thread
+    /// which reads at most `PREFETCH_BATCHES` images ahead.
+    pub fn batches(&self) -> AnnotationBatches {
+        let (sender, receiver) = mpsc::sync_channel(PREFETCH_BATCHES);
+        let path = self.path.clone();
+        let lenient = self.lenient;
+        let homographies = self.homographies.clone();
+        let storage = self.storage.clone();
+
+        thread::spawn(move || {
+            let mut batcher = Batcher {
+                sender,
+                current: None,
+                stopped: false,
+            };
+            let skip_invalid = lenient.then_some(|_: usize, _: InvalidField| {});
+            let on_annotation = |mut annotation: Annotation| {
+                if let Some(homographies) = &homographies {
+                    homographies.reproject(&mut annotation);
+                }
+                batcher.push(annotation)
+            };
+            match read_labels(&storage, &path, skip_invalid, on_annotation) {
+                Ok(_) => batcher.flush(),
+                Err(e) if !batcher.stopped => {
+                    let _ = batcher.sender.send(Err(e));
+                }
+                Err(_) => {}
+            }
+        });
+
+        AnnotationBatches { receiver }
+    }
+
+    /// Returns a cursor handing out the annotations of `images` one frame at a time.
+    pub fn frame_annotations(&self, images: &[Image]) -> FrameAnnotations {
+        FrameAnnotations {
+            path: self.path.clone(),
+            batches: Some(self.batches()),
+            image_order: images
+                .iter()
+                .enumerate()
+                .map(|(i, image)| (image.image_id.clone(), i))
+                .collect(),
+            in_image_order: self.in_image_order,
+            pending: HashMap::new(),
+            furthest: None,
+        }
+    }
+
+    /// Reads all annotations of the file into memory.
+    pub fn read_annotations(&self) -> io::Result<Vec<Annotation>> {
+        let mut annotations = Vec::new();
+        let skip_invalid = self.lenient.then_some(|_: usize, _: InvalidField| {});
+        read_labels(&self.storage, &self.path, skip_invalid, |mut annotation| {
+            if let Some(homographies) = &self.homographies {
+                homographies.reproject(&mut annotation);
+            }
+            annotations.push(annotation);
+            true
+        })?;
+        Ok(annotations)
+    }
+}
+
+/// Iterator over the annotation batches of a labels file, see [`LabelsReader::batches`].
+pub struct AnnotationBatches {
+    receiver: Receiver<io::Result<AnnotationBatch>>,
+}
+
+impl Iterator for AnnotationBatches {
+    type Item = io::Result<AnnotationBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Hands out the annotations of one frame at a time from a stream of annotation batches.
+pub struct FrameAnnotations {
+    path: PathBuf,
+    /// `None` once the whole file has been read
+    batches: Option<AnnotationBatches>,
+    image_order: HashMap<String, usize>,
+    /// Whether the annotations are grouped by image, in the same order as `images`
+    in_image_order: bool,
+    /// Annotations that have been read but not handed out yet, by image id
+    pending: HashMap<String, Vec<Annotation>>,
+    /// Position in `images` of the last image whose annotations were read
+    furthest: Option<usize>,
+}
+
+impl FrameAnnotations {
+    /// Returns the annotations of `image_id`, or an empty list if it has none. The annotations
+    /// of an image are handed out once.
+    ///
+    /// If the file lists the annotations in image order, it is read up to the first image after
+    /// `image_id` and the annotations of images that were skipped are dropped, so images should
+    /// be requested in order. Otherwise the whole file is read on the first call.
+    pub fn annotations_for(&mut self, image_id: &str) -> Vec<Annotation> {
+        let Some(&target) = self.image_order.get(image_id) else {
+            return Vec::new();
+        };
+
+        while !self.in_image_order || self.furthest.is_none_or(|furthest| furthest <= target) {
+            let Some(batches) = self.batches.as_mut() else {
+                break;
+            };
+            match batches.next() {
+                Some(Ok(batch)) => {
+                    // Annotations of unknown images can never be requested
+                    let Some(&index) = self.image_order.get(&batch.image_id) else {
+                        continue;
+                    };
+                    self.furthest = self.furthest.max(Some(index));
+                    self.pending
+                        .entry(batch.image_id)
+                        .or_default()
+                        .extend(batch.annotations);
+                }
+                Some(Err(e)) => {
+                    eprintln!("Failed to read annotations from {:?}: {}", self.path, e);
+                    self.batches = None;
+                }
+                None => self.batches = None,
+            }
+        }
+
+        let annotations = self.pending.remove(image_id).unwrap_or_default();
+        if self.in_image_order {
+            let image_order = &self.image_order;
+            self.pending
+                .retain(|id, _| image_order.get(id).is_some_and(|&index| index > target));
+        }
+        annotations
+    }
+}
+
+/// Tracks whether the annotations of a file are grouped by image while it is read.
+#[derive(Default)]
+struct ImageGroups {
+    /// Image ids in the order their annotations start
+    order: Vec<String>,
+    seen: HashSet<String>,
+    grouped: bool,
+}
+
+impl ImageGroups {
+    fn push(&mut self, image_id: &str) {
+        if self.order.last().is_some_and(|last| last == image_id) {
+            return;
+        }
+        if self.order.is_empty() {
+            self.grouped = true;
+        }
+        if !self.seen.insert(image_id.to_string()) {
+            self.grouped = false;
+        }
+        self.order.push(image_id.to_string());
+    }
+
+    /// Whether every image's annotations were listed together, in the same order as `images`.
+    /// Annotations of unknown images are ignored.
+    fn in_order_of(&self, images: &[Image]) -> bool {
+        if self.order.is_empty() {
+            return true;
+        }
+        let positions: HashMap<&str, usize> = images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| (image.image_id.as_str(), i))
+            .collect();
+        let indices: Vec<usize> = self
+            .order
+            .iter()
+            .filter_map(|id| positions.get(id.as_str()).copied())
+            .collect();
+        self.grouped && indices.windows(2).all(|pair| pair[0] < pair[1])
+    }
+}
+
+/// Groups consecutive annotations of the same image and sends them to the consumer.
+struct Batcher {
+    sender: SyncSender<io::Result<AnnotationBatch>>,
+    current: Option<AnnotationBatch>,
+    /// Set once the consumer has been dropped
+    stopped: bool,
+}
+
+impl Batcher {
+    /// Adds an annotation, sending the current batch when the next image starts.
+    /// Returns `false` if the consumer has been dropped.
+    fn push(&mut self, annotation: Annotation) -> bool {
+        match self.current.as_mut() {
+            Some(batch) if batch.image_id == annotation.image_id => {
+                batch.annotations.push(annotation);
+                return true;
+            }
+            _ => {}
+        }
+
+        let next = AnnotationBatch {
+            image_id: annotation.image_id.clone(),
+            annotations: vec![annotation],
+        };
+        if let Some(batch) = self.current.replace(next) {
+            if self.sender.send(Ok(batch)).is_err() {
+                self.stopped = true;
+                return false;
+            }
+        }
+        true
+    }
+
+    fn flush(&mut self) {
+        if let Some(batch) = self.current.take() {
+            let _ = self.sender.send(Ok(batch));
+        }
+    }
+}
+
+/// Parses a labels file, passing every annotation to `on_annotation` instead of collecting
+/// them. Parsing is aborted with an error if `on_annotation` returns `false`.
+///
+/// If `on_invalid` is set, annotations that can not be parsed are passed to it with their
+/// index, field and error instead of aborting.
+fn read_labels<F, G>(
+    storage: &Storage,
+    path: &Path,
+    on_invalid: Option<G>,
+    on_annotation: F,
+) -> io::Result<Labels>
+where
+    F: FnMut(Annotation) -> bool,
+    G: FnMut(usize, InvalidField),
+{
+    storage.with_reader(path, |reader| {
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+        let visitor = LabelsVisitor {
+            on_annotation,
+            on_invalid,
+        };
+        let labels = serde::Deserializer::deserialize_map(&mut deserializer, visitor)?;
+        deserializer.end()?;
+        Ok(labels)
+    })
+}
+
+struct LabelsVisitor<F, G> {
+    on_annotation: F,
+    on_invalid: Option<G>,
+}
+
+impl<'de, F, G> Visitor<'de> for LabelsVisitor<F, G>
+where
+    F: FnMut(Annotation) -> bool,
+    G: FnMut(usize, InvalidField),
+{
+    type Value = Labels;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a labels object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<Labels, A::Error> {
+        let mut info: Option<Info> = None;
+        let mut images: Option<Vec<Image>> = None;
+        let mut categories: Option<Vec<Category>> = None;
+        let mut has_annotations = false;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "info" => info = Some(map.next_value()?),
+                "images" => images = Some(map.next_value()?),
+                "categories" => categories = Some(map.next_value()?),
+                "annotations" => {
+                    map.next_value_seed(AnnotationsSeed {
+                        on_annotation: &mut self.on_annotation,
+                        on_invalid: self.on_invalid.as_mut(),
+                    })?;
+                    has_annotations = true;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        if !has_annotations {
+            return Err(de::Error::missing_field("annotations"));
+        }
+
+        Ok(Labels {
+            info: info.ok_or_else(|| de::Error::missing_field("info"))?,
+            images: images.ok_or_else(|| de::Error::missing_field("images"))?,
+            annotations: Vec::new(),
+            categories: categories.ok_or_else(|| de::Error::missing_field("categories"))?,
+        })
+    }
+}
+
+/// Deserializes the annotation list one element at a time.
+struct AnnotationsSeed<'a, F, G> {
+    on_annotation: &'a mut F,
+    on_invalid: Option<&'a mut G>,
+}
+
+impl<'de, F, G> DeserializeSeed<'de> for AnnotationsSeed<'_, F, G>
+where
+    F: FnMut(Annotation) -> bool,
+    G: FnMut(usize, InvalidField),
+{
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F, G> Visitor<'de> for AnnotationsSeed<'_, F, G>
+where
+    F: FnMut(Annotation) -> bool,
+    G: FnMut(usize, InvalidField),
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of annotations")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let Some(on_invalid) = self.on_invalid else {
+            while let Some(annotation) = seq.next_element::<Annotation>()? {
+                if !(self.on_annotation)(annotation) {
+                    return Err(de::Error::custom("annotation reader was stopped"));
+                }
+            }
+            return Ok(());
+        };
+
+        // Parsed in two steps, so a malformed annotation does not end the list
+        let mut index = 0;
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            match serde_path_to_error::deserialize::<_, Annotation>(value) {
+                Ok(annotation) => {
+                    if !(self.on_annotation)(annotation) {
+                        return Err(de::Error::custom("annotation reader was stopped"));
+                    }
+                }
+                Err(e) => {
+                    let field = e.path().iter().next().is_some().then(|| e.path().to_string());
+                    on_invalid(index, (field, e.inner().to_string()));
+                }
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const LABELS_JSON: &str = r#"{
+        "info": {
+            "version": "1.3",
+            "clip_start": "0",
+            "clip_stop": "30000",
+            "name": "SNGS-001",
+            "frame_rate": 25,
+            "seq_length": 3,
+            "im_ext": ".jpg"
+        },
+        "images": [
+            {"is_labeled": true, "image_id": "1", "file_name": "000001.jpg", "height": 1080, "width": 1920},
+            {"is_labeled": true, "image_id": "2", "file_name": "000002.jpg", "height": 1080, "width": 1920},
+            {"is_labeled": true, "image_id": "3", "file_name": "000003.jpg", "height": 1080, "width": 1920}
+        ],
+        "annotations": [
+            {"id": "a", "image_id": "1", "track_id": 1, "supercategory": "object", "category_id": 1,
+             "bbox_image": {"x": 0.0, "y": 0.0, "x_center": 5.0, "y_center": 10.0, "w": 10.0, "h": 20.0},
+             "bbox_pitch": null, "bbox_pitch_raw": null, "attributes": null},
+            {"id": "b", "image_id": "1", "track_id": 2, "supercategory": "object", "category_id": 1,
+             "bbox_image": {"x": 0.0, "y": 0.0, "x_center": 5.0, "y_center": 20.0, "w": 10.0, "h": 40.0},
+             "bbox_pitch": null, "bbox_pitch_raw": null, "attributes": null},
+            {"id": "c", "image_id": "3", "track_id": 1, "supercategory": "object", "category_id": 1,
+             "bbox_image": null, "bbox_pitch": null, "bbox_pitch_raw": null, "attributes": null}
+        ],
+        "categories": [
+            {"supercategory": "object", "id": 1, "name": "player"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_stream_annotations() {
+        let path = std::env::temp_dir().join(format!("labels_reader_{}.json", std::process::id()));
+        fs::write(&path, LABELS_JSON).unwrap();
+
+        let (labels, reader) = LabelsReader::open(&path).unwrap();
+        assert!(reader.in_image_order);
+        assert_eq!(labels.images.len(), 3);
+        assert_eq!(labels.categories[0].name, "player");
+        assert!(labels.annotations.is_empty());
+        assert_eq!(reader.average_bbox_height(1), 30.0);
+        assert_eq!(reader.average_bbox_height(2), 1.0);
+
+        let batches: Vec<AnnotationBatch> = reader.batches().map(|b| b.unwrap()).collect();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].annotations.len(), 2);
+        assert_eq!(batches[1].image_id, "3");
+
+        let mut frames = reader.frame_annotations(&labels.images);
+        assert_eq!(frames.annotations_for("1").len(), 2);
+        assert!(frames.annotations_for("2").is_empty());
+        assert_eq!(frames.annotations_for("3")[0].id, "c");
+
+        assert_eq!(reader.read_annotations().unwrap().len(), 3);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unordered_annotations() {
+        let path =
+            std::env::temp_dir().join(format!("labels_unordered_{}.json", std::process::id()));
+        // The annotations of image 1 are split by those of image 3
+        let json = LABELS_JSON
+            .replacen(r#""b", "image_id": "1""#, r#""b", "image_id": "3""#, 1)
+            .replacen(r#""c", "image_id": "3""#, r#""c", "image_id": "1""#, 1);
+        fs::write(&path, json).unwrap();
+
+        let (labels, reader) = LabelsReader::open(&path).unwrap();
+        assert!(!reader.in_image_order);
+        let mut frames = reader.frame_annotations(&labels.images);
+        let ids = |annotations: Vec<Annotation>| -> Vec<String> {
+            annotations.into_iter().map(|a| a.id).collect()
+        };
+        assert_eq!(ids(frames.annotations_for("1")), ["a", "c"]);
+        assert!(frames.annotations_for("2").is_empty());
+        assert_eq!(ids(frames.annotations_for("3")), ["b"]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_lenient_labels() {
+        let path = std::env::temp_dir().join(format!("labels_lenient_{}.json", std::process::id()));
+        let json = LABELS_JSON
+            .replacen(r#""track_id": 2"#, r#""track_id": "two""#, 1)
+            .replacen(r#""category_id": 1,"#, "", 1);
+        fs::write(&path, json).unwrap();
+
+        assert!(LabelsReader::open(&path).is_err());
+
+        let (_, reader) = LabelsReader::open_lenient(&path).unwrap();
+        let invalid = reader.invalid_annotations();
+        assert_eq!(invalid.len(), 2);
+        assert_eq!((invalid[0].index, invalid[0].field.as_deref()), (0, None));
+        assert!(invalid[0].error.contains("category_id"));
+        assert_eq!(invalid[1].index, 1);
+        assert_eq!(invalid[1].field.as_deref(), Some("track_id"));
+
+        let annotations = reader.read_annotations().unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].id, "c");
+        assert_eq!(reader.batches().count(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Where the labels and frames of a sequence are stored. By default this is the SoccerNet
+//! layout, `<subset>/<sequence>/Labels-GameState.json` with the frames in `img1/`, but other
+//! dataset exports can be read without renaming their files.
+
+use crate::config::DataConfig;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_LABELS_FILE_NAME: &str = "Labels-GameState.json";
+pub const DEFAULT_IMAGE_DIR: &str = "img1";
+
+/// Placeholder in `labels_file_name` for the name of the sequence directory
+const SEQUENCE_PLACEHOLDER: &str = "{sequence}";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SequenceLayout {
+    pub labels_file_name: String,
+    /// Overrides the `im_dir` of the labels
+    pub image_dir_override: Option<String>,
+    /// Directory next to the sequence directories that holds the labels of all of them
+    pub annotations_dir: Option<String>,
+}
+
+impl Default for SequenceLayout {
+    fn default() -> Self {
+        Self {
+            labels_file_name: DEFAULT_LABELS_FILE_NAME.to_string(),
+            image_dir_override: None,
+            annotations_dir: None,
+        }
+    }
+}
+
+impl SequenceLayout {
+    pub fn from_config(data_config: &DataConfig) -> Self {
+        Self {
+            labels_file_name: data_config
+                .labels_file_name
+                .clone()
+                .unwrap_or(DEFAULT_LABELS_FILE_NAME.to_string()),
+            image_dir_override: data_config.image_dir_override.clone(),
+            annotations_dir: data_config.annotations_dir.clone(),
+        }
+    }
+
+    /// Path of the labels file of a sequence. `{sequence}` in `labels_file_name` is replaced by
+    /// the name of the sequence directory.
+    ///
+    /// With `annotations_dir`, the file is read from that directory next to the sequence
+    /// directories instead, e.g. `train/annotations/SNGS-001.json` for a `labels_file_name` of
+    /// `"{sequence}.json"`. If the name does not contain `{sequence}`, every sequence has a
+    /// subdirectory there, e.g. `train/annotations/SNGS-001/Labels-GameState.json`.
+    pub fn labels_file(&self, seq_dir: &Path) -> PathBuf {
+        let sequence = seq_dir.file_name().unwrap_or_default().to_string_lossy();
+        let file_name = self.labels_file_name.replace(SEQUENCE_PLACEHOLDER, &sequence);
+
+        let Some(annotations_dir) = &self.annotations_dir else {
+            return seq_dir.join(file_name);
+        };
+        let annotations_dir = seq_dir
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(annotations_dir);
+        if self.labels_file_name.contains(SEQUENCE_PLACEHOLDER) {
+            annotations_dir.join(file_name)
+        } else {
+            annotations_dir.join(&*sequence).join(file_name)
+        }
+    }
+
+    /// Directory of the frames within the sequence directory: `image_dir_override`, or else
+    /// the `im_dir` of the labels, or else `img1`.
+    pub fn image_dir(&self, im_dir: Option<&str>) -> String {
+        self.image_dir_override
+            .as_deref()
+            .or(im_dir)
+            .unwrap_or(DEFAULT_IMAGE_DIR)
+            .to_string()
+    }
+
+    /// Returns `true` if `dir` is the `annotations_dir` of its subset rather than a sequence.
+    pub fn is_annotations_dir(&self, dir: &Path) -> bool {
+        self.annotations_dir
+            .as_deref()
+            .is_some_and(|name| dir.file_name().is_some_and(|n| n == name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_layout() {
+        let seq_dir = Path::new("data/train/SNGS-001");
+        let layout = SequenceLayout::default();
+        assert_eq!(
+            layout.labels_file(seq_dir),
+            seq_dir.join("Labels-GameState.json")
+        );
+        assert_eq!(layout.image_dir(None), "img1");
+        assert_eq!(layout.image_dir(Some("frames")), "frames");
+
+        let layout = SequenceLayout {
+            labels_file_name: "{sequence}.json".to_string(),
+            image_dir_override: Some("images".to_string()),
+            annotations_dir: Some("annotations".to_string()),
+        };
+        assert_eq!(
+            layout.labels_file(seq_dir),
+            Path::new("data/train/annotations/SNGS-001.json")
+        );
+        assert_eq!(layout.image_dir(Some("img1")), "images");
+        assert!(layout.is_annotations_dir(Path::new("data/train/annotations")));
+        assert!(!layout.is_annotations_dir(seq_dir));
+
+        let layout = SequenceLayout {
+            annotations_dir: Some("annotations".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            layout.labels_file(seq_dir),
+            Path::new("data/train/annotations/SNGS-001/Labels-GameState.json")
+        );
+    }
+}
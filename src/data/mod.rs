@@ -1,3 +1,15 @@
+pub mod annotation_index;
+pub mod archive;
 pub mod dataset;
+pub mod dedup;
 pub mod download_data;
+pub mod event_import;
+pub mod formats;
+pub mod labels_cache;
+pub mod layout;
+pub mod labels_reader;
 pub mod models;
+pub mod splits;
+pub mod stats;
+pub mod storage;
+pub mod validation;
@@ -1,13 +1,24 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::dribbling_detection::dribble_models::DribbleEvent;
+use super::labels_reader::{AnnotationBatch, InvalidAnnotation, LabelsReader};
+use super::storage::Storage;
+use crate::dribbling_detection::dribble_models::{DribbleEvent, ShieldingEvent};
+use crate::dribbling_detection::possession::Possession;
+use crate::utils::annotation_calculations::compute_average_player_bbox_height;
+use crate::utils::annotation_filters::count_track_lengths;
+use crate::utils::homography::Homographies;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExportInfo {
     pub version: String,
     pub generated_at: String,
+    /// `true` if the run was interrupted, so not all videos are included
+    #[serde(default)]
+    pub incomplete: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -19,6 +30,22 @@ pub struct DribbleLabel {
     pub possession_holder: u32,
     pub start_frame: u32,
     pub end_frame: Option<u32>,
+    #[serde(default)]
+    pub possession_id: Option<u32>,
+    /// Seconds since the start of the video, if its frame rate is known
+    #[serde(default)]
+    pub start_seconds: Option<f64>,
+    #[serde(default)]
+    pub end_seconds: Option<f64>,
+    /// Half and time in the half, e.g. "1 - 17:05", if the video has a `game_time_start`
+    #[serde(default)]
+    pub start_game_time: Option<String>,
+    #[serde(default)]
+    pub end_game_time: Option<String>,
+    /// Score of the event, for event files of detectors that score their events. Used to
+    /// review the least certain events first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
 }
 
 impl From<&DribbleEvent> for DribbleLabel {
@@ -31,7 +58,122 @@ impl From<&DribbleEvent> for DribbleLabel {
             possession_holder: event.possession_holder,
             start_frame: event.start_frame,
             end_frame: event.end_frame,
+            possession_id: event.possession_id,
+            start_seconds: None,
+            end_seconds: None,
+            start_game_time: None,
+            end_game_time: None,
+            confidence: None,
+        }
+    }
+}
+
+impl DribbleLabel {
+    /// Converts an event, adding the timestamps computed from the video's `info`.
+    pub fn from_event(event: &DribbleEvent, info: &Info) -> Self {
+        let timing = ClipTiming::new(info);
+        let end_frame = event.end_frame.unwrap_or(event.start_frame);
+        DribbleLabel {
+            start_seconds: timing.seconds(event.start_frame),
+            end_seconds: timing.seconds(end_frame),
+            start_game_time: timing.game_time(event.start_frame),
+            end_game_time: timing.game_time(end_frame),
+            ..DribbleLabel::from(event)
+        }
+    }
+
+    /// The event of this label in the video `file_name`, e.g. to write the exports of an
+    /// earlier run. Defenders are not exported, so the event has none.
+    pub fn to_event(&self, file_name: &str) -> DribbleEvent {
+        let end_frame = self.end_frame.unwrap_or(self.start_frame);
+        DribbleEvent {
+            finished: self.finished,
+            detected_dribble: self.detected_dribble,
+            detected_tackle: self.detected_tackle,
+            ever_contested: self.ever_contested,
+            end_frame: self.end_frame,
+            frames: (self.start_frame..=end_frame).collect(),
+            possession_id: self.possession_id,
+            ..DribbleEvent::new(self.possession_holder, self.start_frame, file_name.to_string())
+        }
+    }
+}
+
+/// Converts frame numbers of a video to seconds and game time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipTiming {
+    /// Frame number of the first frame, which is at 0 seconds
+    pub first_frame: u32,
+    pub frame_rate: Option<f64>,
+    /// Half and seconds in the half of the first frame
+    pub game_time_start: Option<(u32, u32)>,
+    /// Milliseconds since the start of the half of the first frame, which is more precise than
+    /// the seconds of `game_time_start`
+    pub clip_start: Option<u64>,
+}
+
+impl ClipTiming {
+    pub fn new(info: &Info) -> Self {
+        Self {
+            first_frame: 1,
+            frame_rate: (info.frame_rate > 0.0).then_some(info.frame_rate as f64),
+            game_time_start: info.game_time_start.as_deref().and_then(parse_game_time),
+            // Clips without clip times have a `clip_start` of 0
+            clip_start: info.clip_start.trim().parse().ok().filter(|&ms| ms > 0),
+        }
+    }
+
+    pub fn seconds(&self, frame: u32) -> Option<f64> {
+        let frame_rate = self.frame_rate?;
+        Some(frame.saturating_sub(self.first_frame) as f64 / frame_rate)
+    }
+
+    /// Game time of a frame in the format of the SoccerNet labels, e.g. "1 - 17:05".
+    pub fn game_time(&self, frame: u32) -> Option<String> {
+        let (half, start) = self.game_time_start?;
+        let start_ms = self.clip_start.unwrap_or(u64::from(start) * 1000);
+        let seconds = (start_ms + (self.seconds(frame)? * 1000.0).round() as u64) / 1000;
+        Some(format!("{} - {:02}:{:02}", half, seconds / 60, seconds % 60))
+    }
+}
+
+/// Parses a game time like "1 - 17:05" into the half and the seconds in the half.
+pub fn parse_game_time(game_time: &str) -> Option<(u32, u32)> {
+    let (half, time) = game_time.split_once('-')?;
+    let (minutes, seconds) = time.trim().split_once(':')?;
+    let minutes: u32 = minutes.parse().ok()?;
+    let seconds: u32 = seconds.parse().ok()?;
+    Some((half.trim().parse().ok()?, minutes * 60 + seconds))
+}
+
+/// The part of a game a sequence was cut from. Sequences of different subsets with the same
+/// identity show the same physical clip, e.g. in `interpolated-predictions` and `gt`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClipIdentity {
+    pub game_id: String,
+    pub half: Option<u32>,
+    /// Milliseconds since the start of the half
+    pub clip_start: u64,
+    pub clip_stop: u64,
+}
+
+impl ClipIdentity {
+    /// Returns `None` if the info has no game id or clip times.
+    pub fn from_info(info: &Info) -> Option<Self> {
+        let game_id = info.game_id.as_deref()?.trim();
+        if game_id.is_empty() {
+            return None;
         }
+        Some(Self {
+            game_id: game_id.to_string(),
+            half: info
+                .game_time_start
+                .as_deref()
+                .and_then(parse_game_time)
+                .map(|(half, _)| half),
+            clip_start: info.clip_start.trim().parse().ok()?,
+            clip_stop: info.clip_stop.trim().parse().ok()?,
+        })
     }
 }
 
@@ -39,7 +181,55 @@ impl From<&DribbleEvent> for DribbleLabel {
 #[derive(Clone, Serialize, Deserialize)]
 pub struct VideoDribbleEvents {
     pub video_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subset: Option<String>,
+    /// Used to find the same clip in other subsets, see [`crate::data::dedup`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clip: Option<ClipIdentity>,
+    /// Frame rate of the sequence, `None` if its `info` has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_rate: Option<f64>,
     pub dribble_events: Vec<DribbleLabel>,
+    #[serde(default)]
+    pub shielding_events: Vec<ShieldingEvent>,
+}
+
+impl VideoDribbleEvents {
+    /// The export of the events detected in a video of `subset`.
+    pub fn from_video(
+        video_data: &VideoData,
+        subset: Option<&str>,
+        events: &[DribbleEvent],
+        shielding_events: &[ShieldingEvent],
+    ) -> Self {
+        Self {
+            video_id: video_data.name(),
+            dataset: video_data.dataset.clone(),
+            subset: subset.map(str::to_string),
+            clip: ClipIdentity::from_info(&video_data.labels.info),
+            frame_rate: ClipTiming::new(&video_data.labels.info).frame_rate,
+            dribble_events: events
+                .iter()
+                .map(|e| DribbleLabel::from_event(e, &video_data.labels.info))
+                .collect(),
+            shielding_events: shielding_events.to_vec(),
+        }
+    }
+
+    /// See [`video_key`].
+    pub fn key(&self) -> String {
+        video_key(self.dataset.as_deref(), &self.video_id)
+    }
+
+    /// The dribble events, with their frames restored from the start and end frame.
+    pub fn to_events(&self) -> Vec<DribbleEvent> {
+        self.dribble_events
+            .iter()
+            .map(|label| label.to_event(&self.video_id))
+            .collect()
+    }
 }
 
 // This is the top-level export pub.
@@ -49,6 +239,22 @@ pub struct DribbleEventsExport {
     pub videos: Vec<VideoDribbleEvents>,
 }
 
+// Each video's possession spells are stored here.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VideoPossessions {
+    pub video_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+    pub possessions: Vec<Possession>,
+}
+
+// Top-level structure of possessions.json
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PossessionsExport {
+    pub info: ExportInfo,
+    pub videos: Vec<VideoPossessions>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum SpecialHighlight {
     PossesionHolder,
@@ -167,14 +373,142 @@ pub struct Labels {
     pub categories: Vec<Category>,
 }
 
+/// A ground truth action from a SoccerNet action spotting file, e.g. "Ball out of play".
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct GroundTruthAction {
+    pub label: String,
+    pub team: Option<String>,
+    /// `false` if the action happened off camera
+    pub visible: bool,
+    /// Frame number in the clip, starting at 1
+    pub frame: u32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct VideoData {
     pub dir_path: PathBuf,
     pub image_paths: Vec<PathBuf>,
     pub labels: Labels,
+
+    /// Video file to read the frames from, if they have not been extracted to `image_paths`.
+    /// The image paths are still used to number the frames.
+    #[serde(default)]
+    pub video_path: Option<PathBuf>,
+
+    /// Frame number of the first frame of `video_path`. Only set on clips cut out of a
+    /// sequence, whose first image is not the first frame of the video.
+    #[serde(default)]
+    pub video_first_frame: Option<u32>,
+
+    /// Name of the configured dataset the sequence belongs to, if there are several
+    #[serde(default)]
+    pub dataset: Option<String>,
+
+    /// Ground truth actions during the clip, ordered by frame, if `[actions]` is configured
+    #[serde(default)]
+    pub actions: Vec<GroundTruthAction>,
+
+    /// Set if `labels.annotations` has not been loaded yet. The annotations can then be
+    /// streamed from the labels file instead.
+    #[serde(skip)]
+    pub labels_reader: Option<LabelsReader>,
+
+    /// Storage of the dataset the sequence belongs to, which its images are read from
+    #[serde(skip)]
+    pub storage: Arc<Storage>,
+}
+
+/// Identifies a video across dataset roots, which may have sequences of the same name:
+/// `<dataset>/<name>`, or just the name for `data_path`.
+pub fn video_key(dataset: Option<&str>, name: &str) -> String {
+    match dataset {
+        Some(dataset) => format!("{}/{}", dataset, name),
+        None => name.to_string(),
+    }
 }
 
 impl VideoData {
+    /// Name of the sequence directory, which is used as the video id in the exports.
+    pub fn name(&self) -> String {
+        self.dir_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// See [`video_key`].
+    pub fn key(&self) -> String {
+        video_key(self.dataset.as_deref(), &self.name())
+    }
+
+    /// Reads all annotations into `labels` if they are not loaded yet.
+    pub fn load_annotations(&mut self) -> io::Result<()> {
+        if let Some(reader) = &self.labels_reader {
+            self.labels.annotations = reader.read_annotations()?;
+            self.labels_reader = None;
+        }
+        Ok(())
+    }
+
+    /// Computes the pitch bounding boxes of annotations that only have an image bounding box,
+    /// both for the loaded annotations and for those streamed from the labels file.
+    pub fn set_homographies(&mut self, homographies: Homographies) {
+        for annotation in self.labels.annotations.iter_mut() {
+            homographies.reproject(annotation);
+        }
+        if let Some(reader) = self.labels_reader.as_mut() {
+            reader.set_homographies(Arc::new(homographies));
+        }
+    }
+
+    /// Average image bounding box height of the players, or 1.0 if there are none.
+    pub fn average_player_bbox_height(&self, category_map: &HashMap<String, u32>) -> f64 {
+        match &self.labels_reader {
+            Some(reader) => {
+                reader.average_bbox_height(category_map.get("player").copied().unwrap_or(1))
+            }
+            None => compute_average_player_bbox_height(&self.labels.annotations, category_map),
+        }
+    }
+
+    /// Annotations grouped per image, streamed from the labels file if they are not loaded.
+    pub fn annotation_batches(&self) -> Box<dyn Iterator<Item = io::Result<AnnotationBatch>>> {
+        if let Some(reader) = &self.labels_reader {
+            return Box::new(reader.batches());
+        }
+
+        let mut batches: Vec<AnnotationBatch> = Vec::new();
+        for annotation in &self.labels.annotations {
+            match batches.last_mut() {
+                Some(batch) if batch.image_id == annotation.image_id => {
+                    batch.annotations.push(annotation.clone())
+                }
+                _ => batches.push(AnnotationBatch {
+                    image_id: annotation.image_id.clone(),
+                    annotations: vec![annotation.clone()],
+                }),
+            }
+        }
+        Box::new(batches.into_iter().map(Ok))
+    }
+
+    /// Annotations skipped when the labels were read leniently.
+    pub fn invalid_annotations(&self) -> &[InvalidAnnotation] {
+        match &self.labels_reader {
+            Some(reader) => reader.invalid_annotations(),
+            None => &[],
+        }
+    }
+
+    /// Number of annotations (i.e. frames) of each track id.
+    pub fn track_lengths(&self) -> HashMap<u32, usize> {
+        match &self.labels_reader {
+            Some(reader) => reader.track_lengths().clone(),
+            None => count_track_lengths(&self.labels.annotations),
+        }
+    }
+
     pub fn add_video_annotation(
         &mut self,
         image_path: PathBuf,
@@ -197,4 +531,186 @@ pub struct ReviewedVideoData {
     pub dribble_data: Vec<VideoData>,
     pub tackle_data: Vec<VideoData>,
     pub other_data: Vec<VideoData>,
+    pub events: Vec<ReviewedEvent>,
+}
+
+/// The label a reviewer gave to a clip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewLabel {
+    Dribble,
+    Tackle,
+    Other,
+}
+
+impl std::fmt::Display for ReviewLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReviewLabel::Dribble => write!(f, "dribble"),
+            ReviewLabel::Tackle => write!(f, "tackle"),
+            ReviewLabel::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// How a reviewer's label relates to the detected event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewVerdict {
+    /// The event is what was detected
+    Accepted,
+    /// The event is neither a dribble nor a tackle
+    Rejected,
+    /// A detected dribble is a tackle or the other way around
+    Reclassified,
+}
+
+impl ReviewVerdict {
+    /// The verdict of labeling an event of the `detected` kind, if known, with `label`.
+    pub fn new(detected: Option<ReviewLabel>, label: ReviewLabel) -> Self {
+        match (detected, label) {
+            (_, ReviewLabel::Other) => Self::Rejected,
+            (Some(detected), label) if detected != label => Self::Reclassified,
+            _ => Self::Accepted,
+        }
+    }
+}
+
+/// A reviewed clip with the reviewer's label, tags and notes. `start_frame` and `end_frame`
+/// are the frames of the event under review.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReviewedEvent {
+    pub video_id: String,
+    pub start_frame: u32,
+    pub end_frame: u32,
+    pub label: ReviewLabel,
+    /// Not set in the review files of earlier versions
+    #[serde(default)]
+    pub verdict: Option<ReviewVerdict>,
+    /// Boundaries set by the reviewer, if they differ from `start_frame` and `end_frame`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_start_frame: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_end_frame: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS_JSON: &str = r#"{
+        "info": {
+            "version": "1.3",
+            "clip_start": "0",
+            "clip_stop": "30000",
+            "name": "SNGS-001",
+            "im_dir": "img1",
+            "frame_rate": 25,
+            "seq_length": 750,
+            "im_ext": ".jpg"
+        },
+        "images": [
+            {
+                "is_labeled": true,
+                "image_id": "1001000001",
+                "file_name": "000001.jpg",
+                "height": 1080,
+                "width": 1920
+            }
+        ],
+        "annotations": [
+            {
+                "id": "1001000000",
+                "image_id": "1001000001",
+                "track_id": 7,
+                "supercategory": "object",
+                "category_id": 1,
+                "bbox_image": {"x": 10.0, "y": 20.0, "x_center": 15.0, "y_center": 30.0, "w": 10.0, "h": 20.0},
+                "bbox_pitch": null,
+                "bbox_pitch_raw": null,
+                "attributes": {"role": "player", "jersey": "10", "team": "left"}
+            }
+        ],
+        "categories": [
+            {"supercategory": "object", "id": 1, "name": "player"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_labels() {
+        let labels: Labels = serde_json::from_str(LABELS_JSON).unwrap();
+
+        assert_eq!(labels.info.frame_rate, 25.0);
+        assert_eq!(labels.info.im_dir.as_deref(), Some("img1"));
+        assert_eq!(labels.images[0].file_name, "000001.jpg");
+        assert_eq!(labels.images[0].has_labeled_person, None);
+
+        let annotation = &labels.annotations[0];
+        assert_eq!(annotation.track_id, Some(7));
+        assert_eq!(annotation.bbox_image.as_ref().unwrap().h, 20.0);
+        assert!(annotation.bbox_pitch.is_none());
+        assert!(annotation.lines.is_none());
+        assert_eq!(
+            annotation.attributes.as_ref().unwrap().team.as_deref(),
+            Some("left")
+        );
+    }
+
+    #[test]
+    fn test_labels_round_trip() {
+        let labels: Labels = serde_json::from_str(LABELS_JSON).unwrap();
+        let json = serde_json::to_string(&labels).unwrap();
+        let parsed: Labels = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.info.frame_rate, labels.info.frame_rate);
+        assert_eq!(parsed.images.len(), labels.images.len());
+        assert_eq!(parsed.annotations[0].id, labels.annotations[0].id);
+        assert_eq!(parsed.categories[0].name, labels.categories[0].name);
+    }
+
+    #[test]
+    fn test_fractional_frame_rate() {
+        let json = LABELS_JSON.replace("\"frame_rate\": 25", "\"frame_rate\": 29.97");
+        let labels: Labels = serde_json::from_str(&json).unwrap();
+        assert!((labels.info.frame_rate - 29.97).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_dribble_label_timing() {
+        let info = Info {
+            frame_rate: 25.0,
+            game_time_start: Some("2 - 44:50".to_string()),
+            ..Default::default()
+        };
+        let mut event = DribbleEvent::new(3, 26, "SNGS-001".to_string());
+        event.end_frame = Some(351);
+
+        let label = DribbleLabel::from_event(&event, &info);
+        assert_eq!(label.start_seconds, Some(1.0));
+        assert_eq!(label.end_seconds, Some(14.0));
+        assert_eq!(label.start_game_time.as_deref(), Some("2 - 44:51"));
+        assert_eq!(label.end_game_time.as_deref(), Some("2 - 45:04"));
+
+        let restored = label.to_event("SNGS-001");
+        assert_eq!((restored.start_frame, restored.end_frame), (26, Some(351)));
+        assert_eq!(restored.frames.len(), 326);
+
+        // The clip starts half a second later than its game time says
+        let info = Info {
+            clip_start: "2690600".to_string(),
+            ..info
+        };
+        event.start_frame = 13;
+        let label = DribbleLabel::from_event(&event, &info);
+        assert_eq!(label.start_game_time.as_deref(), Some("2 - 44:51"));
+
+        // Without a frame rate, only the frame numbers are known
+        let label = DribbleLabel::from_event(&event, &Info::default());
+        assert_eq!((label.start_seconds, label.start_game_time), (None, None));
+        assert_eq!(parse_game_time("1 - 17:05"), Some((1, 1025)));
+    }
 }
@@ -0,0 +1,172 @@
+//! Reproducible train/val/test splits. A sequence is assigned to a split by a hash of its name,
+//! so it stays in the same split when sequences are added or removed.
+
+use super::dataset::Dataset;
+use crate::config::SplitsConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    Train,
+    Val,
+    Test,
+}
+
+impl fmt::Display for Split {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Train => "train",
+            Self::Val => "val",
+            Self::Test => "test",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Split {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "train" => Ok(Self::Train),
+            "val" => Ok(Self::Val),
+            "test" => Ok(Self::Test),
+            _ => Err(format!("unknown split \"{}\", expected train, val or test", s)),
+        }
+    }
+}
+
+/// Split of a single sequence in the manifest.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SplitAssignment {
+    pub subset: String,
+    pub sequence: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+    pub split: Split,
+}
+
+/// Contents of `splits.json`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SplitManifest {
+    pub generated_at: String,
+    pub seed: u64,
+    /// Train, val and test ratios
+    pub ratios: [f64; 3],
+    pub sequences: Vec<SplitAssignment>,
+}
+
+/// 64-bit FNV-1a hash of the seed and the name. Unlike `DefaultHasher`, it is the same on every
+/// platform and Rust version.
+pub fn split_hash(name: &str, seed: u64) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = seed
+        .to_le_bytes()
+        .iter()
+        .chain(name.as_bytes())
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(PRIME)
+        });
+
+    // FNV barely changes the high bits for names that only differ at the end, such as
+    // "SNGS-001" and "SNGS-002", so they are mixed with the SplitMix64 finalizer
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Assigns a sequence to a split. The ratios do not have to add up to 1.
+pub fn assign_split(sequence: &str, config: &SplitsConfig) -> Split {
+    let [train, val, test] = config.ratios.map(|ratio| ratio.max(0.0));
+    let total = train + val + test;
+    if total <= 0.0 {
+        return Split::Train;
+    }
+
+    // The top 53 bits give a uniform value in [0, 1)
+    let value = (split_hash(sequence, config.seed) >> 11) as f64 / (1u64 << 53) as f64;
+    if value < train / total {
+        Split::Train
+    } else if value < (train + val) / total {
+        Split::Val
+    } else {
+        Split::Test
+    }
+}
+
+impl Dataset {
+    /// Assigns every sequence of the configured subsets to a split. The sequence filters are
+    /// applied, except for the `active` split.
+    pub fn split_manifest(&self, config: &SplitsConfig) -> SplitManifest {
+        let sequences = self
+            .subsets
+            .iter()
+            .flat_map(|subset| {
+                self.sequence_dirs(subset)
+                    .into_iter()
+                    .filter(|seq_dir| self.matches_sequence_filters(seq_dir))
+                    .map(move |seq_dir| (subset, seq_dir))
+            })
+            .map(|(subset, seq_dir)| {
+                let sequence = seq_dir
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                SplitAssignment {
+                    subset: subset.clone(),
+                    split: assign_split(&sequence, config),
+                    dataset: self.root_of(&seq_dir).and_then(|root| root.name.clone()),
+                    sequence,
+                }
+            })
+            .collect();
+
+        SplitManifest {
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            seed: config.seed,
+            ratios: config.ratios,
+            sequences,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign_split() {
+        let config = SplitsConfig::default();
+        let names: Vec<String> = (0..1000).map(|i| format!("SNGS-{:03}", i)).collect();
+        let splits: Vec<Split> = names.iter().map(|n| assign_split(n, &config)).collect();
+
+        // Deterministic, and close to the configured ratios
+        assert_eq!(
+            splits,
+            names
+                .iter()
+                .map(|n| assign_split(n, &config))
+                .collect::<Vec<_>>()
+        );
+        let count = |split| splits.iter().filter(|s| **s == split).count();
+        assert!((750..850).contains(&count(Split::Train)));
+        assert!((60..140).contains(&count(Split::Val)));
+        assert!((60..140).contains(&count(Split::Test)));
+
+        // The hash must never change, or existing splits would be reshuffled
+        assert_eq!(split_hash("", 0), 0x813f_0174_a236_7c13);
+
+        let config = SplitsConfig {
+            ratios: [0.0, 0.0, 1.0],
+            ..Default::default()
+        };
+        assert_eq!(assign_split("SNGS-001", &config), Split::Test);
+        assert_eq!("Val".parse::<Split>(), Ok(Split::Val));
+    }
+}
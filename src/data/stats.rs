@@ -0,0 +1,184 @@
+use super::dataset::Dataset;
+use super::models::VideoData;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Summary of the annotations of a subset, returned by [`Dataset::stats`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SubsetStats {
+    pub subset: String,
+    pub num_sequences: usize,
+    /// Sequences whose labels could not be read. They are not part of the other counts.
+    pub num_unreadable_sequences: usize,
+    pub num_frames: usize,
+    pub num_annotations: usize,
+    /// Number of annotations per category name
+    pub annotations_per_category: BTreeMap<String, usize>,
+    /// Frames without a ball annotation
+    pub frames_missing_ball: usize,
+    /// Average number of annotations per frame of every category name
+    pub avg_per_frame: BTreeMap<String, f64>,
+    /// Fraction of the annotations (0-1) with pitch coordinates, which `use_2d` needs
+    pub pitch_coverage: f64,
+}
+
+impl SubsetStats {
+    /// Fraction of the frames (0-1) without a ball annotation.
+    pub fn missing_ball_ratio(&self) -> f64 {
+        if self.num_frames == 0 {
+            return 0.0;
+        }
+        self.frames_missing_ball as f64 / self.num_frames as f64
+    }
+
+    /// Adds the frames and annotations of a video. The averages are computed by
+    /// [`SubsetStats::finish`] once all videos are added.
+    fn add_video(&mut self, video_data: &VideoData, counts: &mut AnnotationCounts) {
+        let category_names: HashMap<u32, &str> = video_data
+            .labels
+            .categories
+            .iter()
+            .map(|c| (c.id, c.name.as_str()))
+            .collect();
+        let image_ids: HashSet<&str> = video_data
+            .labels
+            .images
+            .iter()
+            .map(|i| i.image_id.as_str())
+            .collect();
+
+        self.num_sequences += 1;
+        self.num_frames += image_ids.len();
+
+        let mut frames_with_ball: HashSet<String> = HashSet::new();
+        for batch in video_data.annotation_batches() {
+            let Ok(batch) = batch else {
+                break;
+            };
+            for annotation in &batch.annotations {
+                let name = category_names
+                    .get(&annotation.category_id)
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("unknown ({})", annotation.category_id));
+
+                self.num_annotations += 1;
+                if annotation.bbox_pitch.is_some() {
+                    counts.with_pitch += 1;
+                }
+                if name == "ball" && image_ids.contains(batch.image_id.as_str()) {
+                    frames_with_ball.insert(batch.image_id.clone());
+                }
+                *self.annotations_per_category.entry(name).or_default() += 1;
+            }
+        }
+        self.frames_missing_ball += image_ids.len() - frames_with_ball.len();
+    }
+
+    fn finish(&mut self, counts: &AnnotationCounts) {
+        if self.num_frames > 0 {
+            self.avg_per_frame = self
+                .annotations_per_category
+                .iter()
+                .map(|(name, &count)| (name.clone(), count as f64 / self.num_frames as f64))
+                .collect();
+        }
+        if self.num_annotations > 0 {
+            self.pitch_coverage = counts.with_pitch as f64 / self.num_annotations as f64;
+        }
+    }
+}
+
+#[derive(Default)]
+struct AnnotationCounts {
+    with_pitch: usize,
+}
+
+impl Dataset {
+    /// Counts the sequences, frames and annotations of a subset, to help choose thresholds
+    /// before a long detection run. Uses the same sequence filters as `iter_subset`.
+    pub fn stats(&self, subset: &str) -> SubsetStats {
+        let mut stats = SubsetStats {
+            subset: subset.to_string(),
+            ..Default::default()
+        };
+        let mut counts = AnnotationCounts::default();
+
+        for seq_dir in self.filtered_sequence_dirs(subset) {
+            match self.load_sequence(&seq_dir) {
+                Ok(video_data) => stats.add_video(&video_data, &mut counts),
+                Err(_) => stats.num_unreadable_sequences += 1,
+            }
+        }
+
+        stats.finish(&counts);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::{Annotation, BboxPitch, Category, Image};
+
+    #[test]
+    fn test_subset_stats() {
+        let mut video_data = VideoData::default();
+        video_data.labels.images = ["1", "2"]
+            .iter()
+            .map(|id| Image {
+                is_labeled: true,
+                image_id: id.to_string(),
+                file_name: format!("00000{}.jpg", id),
+                height: 1080,
+                width: 1920,
+                has_labeled_person: None,
+                has_labeled_pitch: None,
+            })
+            .collect();
+        video_data.labels.categories = [(1, "player"), (2, "goalkeeper"), (4, "ball")]
+            .iter()
+            .map(|(id, name)| Category {
+                id: *id,
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let annotation = |image_id: &str, category_id: u32, pitch: bool| Annotation {
+            image_id: image_id.to_string(),
+            category_id,
+            bbox_pitch: pitch.then_some(BboxPitch {
+                x_bottom_left: 0.0,
+                y_bottom_left: 0.0,
+                x_bottom_right: 0.0,
+                y_bottom_right: 0.0,
+                x_bottom_middle: 0.0,
+                y_bottom_middle: 0.0,
+            }),
+            ..Default::default()
+        };
+        video_data.labels.annotations = vec![
+            annotation("1", 1, true),
+            annotation("1", 1, true),
+            annotation("1", 4, true),
+            annotation("2", 1, false),
+            annotation("2", 2, true),
+        ];
+
+        let mut stats = SubsetStats::default();
+        let mut counts = AnnotationCounts::default();
+        stats.add_video(&video_data, &mut counts);
+        stats.finish(&counts);
+
+        assert_eq!(stats.num_sequences, 1);
+        assert_eq!(stats.num_frames, 2);
+        assert_eq!(stats.num_annotations, 5);
+        assert_eq!(stats.annotations_per_category["player"], 3);
+        assert_eq!(stats.annotations_per_category["goalkeeper"], 1);
+        assert_eq!(stats.annotations_per_category["ball"], 1);
+        assert_eq!(stats.frames_missing_ball, 1);
+        assert_eq!(stats.missing_ball_ratio(), 0.5);
+        assert_eq!(stats.avg_per_frame["player"], 1.5);
+        assert_eq!(stats.avg_per_frame["goalkeeper"], 0.5);
+        assert_eq!(stats.pitch_coverage, 0.8);
+    }
+}
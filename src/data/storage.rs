@@ -0,0 +1,547 @@
+//! Storage backends, so datasets can be read from object storage instead of the local disk.
+//!
+//! Remote files are addressed by URL, e.g. `s3://bucket/SoccerNet/train/SNGS-060/img1/000001.jpg`
+//! or `https://data.example.com/SoccerNet/train/SNGS-060/img1/000001.jpg`, so `data_path` (or the
+//! `data_path` of one of the `datasets`) can point to a bucket and the rest of the code can keep
+//! passing paths around. Other paths are read by [`LocalStorage`], which also reads `.zip`
+//! archives, see [`archive`](super::archive).
+//!
+//! The backends are created by the [`Storage`] of a dataset, with the `[storage]` options of
+//! its config, so datasets with different options can be read in the same process.
+
+use super::archive::Archives;
+use crate::config::{Config, StorageConfig};
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::runtime::Runtime;
+
+/// An entry of a directory listed by [`StorageBackend::list_dir`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Reads files from a storage location. Paths are relative to the bucket or host of the
+/// backend, or plain file system paths for [`LocalStorage`].
+pub trait StorageBackend: Send + Sync {
+    /// Reads the whole file at `path`.
+    fn read_file(&self, path: &str) -> io::Result<Vec<u8>>;
+
+    /// Returns the files and directories directly inside `path`, ordered by name.
+    fn list_dir(&self, path: &str) -> io::Result<Vec<DirEntry>>;
+
+    /// Returns `true` if the file or directory at `path` exists.
+    fn exists(&self, path: &str) -> bool {
+        self.read_file(path).is_ok() || self.is_dir(path)
+    }
+
+    /// Returns `true` if `path` is a directory with at least one entry.
+    fn is_dir(&self, path: &str) -> bool {
+        self.list_dir(path).is_ok_and(|entries| !entries.is_empty())
+    }
+
+    /// Writes the file at `path` to `writer` and returns the number of bytes written. Backends
+    /// that can stream a file override this, so large files are not held in memory.
+    fn copy_to(&self, path: &str, writer: &mut dyn Write) -> io::Result<u64> {
+        let bytes = self.read_file(path)?;
+        writer.write_all(&bytes)?;
+        Ok(bytes.len() as u64)
+    }
+}
+
+/// The local file system, including the inside of `.zip` archives.
+#[derive(Clone, Debug, Default)]
+pub struct LocalStorage {
+    archives: Arc<Archives>,
+}
+
+impl StorageBackend for LocalStorage {
+    fn read_file(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.archives.read(Path::new(path))
+    }
+
+    fn list_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        self.archives.list_dir(Path::new(path))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.archives.exists(Path::new(path))
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        self.archives.is_dir(Path::new(path))
+    }
+
+    fn copy_to(&self, path: &str, writer: &mut dyn Write) -> io::Result<u64> {
+        self.archives
+            .with_reader(Path::new(path), |reader| io::copy(reader, writer))
+    }
+}
+
+/// An S3 bucket, or a bucket of an S3-compatible service if `s3_endpoint` is configured.
+/// Credentials and the region are read from the environment like in the AWS CLI.
+pub struct S3Storage {
+    bucket: String,
+    client: aws_sdk_s3::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl S3Storage {
+    pub fn new(bucket: &str, config: &StorageConfig, runtime: Arc<Runtime>) -> Self {
+        let sdk_config = runtime.block_on(async {
+            let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+            if let Some(region) = &config.s3_region {
+                loader = loader.region(Region::new(region.clone()));
+            }
+            loader.load().await
+        });
+        let mut s3_config = aws_sdk_s3::config::Builder::from(&sdk_config);
+        if let Some(endpoint) = &config.s3_endpoint {
+            // S3-compatible services such as MinIO do not support bucket subdomains
+            s3_config = s3_config.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Self {
+            bucket: bucket.to_string(),
+            client: aws_sdk_s3::Client::from_conf(s3_config.build()),
+            runtime,
+        }
+    }
+}
+
+impl StorageBackend for S3Storage {
+    fn read_file(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.runtime.block_on(async {
+            let object = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(path)
+                .send()
+                .await
+                .map_err(s3_error)?;
+            let data = object.body.collect().await.map_err(io::Error::other)?;
+            Ok(data.into_bytes().to_vec())
+        })
+    }
+
+    fn list_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        let prefix = dir_prefix(path);
+        self.runtime.block_on(async {
+            let mut pages = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(&prefix)
+                .delimiter("/")
+                .into_paginator()
+                .send();
+
+            let mut entries = Vec::new();
+            while let Some(page) = pages.next().await {
+                let page = page.map_err(s3_error)?;
+                // With a delimiter, the subdirectories are returned as common prefixes
+                let dirs = page
+                    .common_prefixes()
+                    .iter()
+                    .filter_map(|p| p.prefix()?.strip_prefix(&prefix))
+                    .map(|name| (name.trim_end_matches('/'), true));
+                let files = page
+                    .contents()
+                    .iter()
+                    .filter_map(|object| object.key()?.strip_prefix(&prefix))
+                    .map(|name| (name, false));
+                entries.extend(
+                    dirs.chain(files)
+                        .filter(|(name, _)| !name.is_empty())
+                        .map(|(name, is_dir)| DirEntry {
+                            name: name.to_string(),
+                            is_dir,
+                        }),
+                );
+            }
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            Ok(entries)
+        })
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        let head = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(path)
+            .send();
+        self.runtime.block_on(head).is_ok() || self.is_dir(path)
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        let list = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(dir_prefix(path))
+            .max_keys(1)
+            .send();
+        self.runtime
+            .block_on(list)
+            .is_ok_and(|output| output.key_count().unwrap_or(0) > 0)
+    }
+
+    fn copy_to(&self, path: &str, writer: &mut dyn Write) -> io::Result<u64> {
+        self.runtime.block_on(async {
+            let mut object = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(path)
+                .send()
+                .await
+                .map_err(s3_error)?;
+
+            let mut written = 0;
+            while let Some(chunk) = object.body.try_next().await.map_err(io::Error::other)? {
+                writer.write_all(&chunk)?;
+                written += chunk.len() as u64;
+            }
+            Ok(written)
+        })
+    }
+}
+
+/// Files served over HTTP(S). Directories are listed by parsing the links of the index page
+/// the server returns for them, like the autoindex pages of nginx or `python -m http.server`.
+pub struct HttpStorage {
+    /// Scheme and host, e.g. "https://data.example.com"
+    base_url: String,
+    client: reqwest::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl HttpStorage {
+    pub fn new(base_url: &str, config: &StorageConfig, runtime: Arc<Runtime>) -> Self {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.http_headers {
+            match (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    headers.insert(name, value);
+                }
+                _ => eprintln!("Ignoring invalid HTTP header \"{}\"", name),
+            }
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client,
+            runtime,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    async fn get(&self, url: &str) -> io::Result<reqwest::Response> {
+        let response = self.client.get(url).send().await.map_err(io::Error::other)?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} was not found", url),
+            )),
+            status if !status.is_success() => Err(io::Error::other(format!(
+                "Request to {} failed with status {}",
+                url, status
+            ))),
+            _ => Ok(response),
+        }
+    }
+}
+
+impl StorageBackend for HttpStorage {
+    fn read_file(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.runtime.block_on(async {
+            let response = self.get(&self.url(path)).await?;
+            let bytes = response.bytes().await.map_err(io::Error::other)?;
+            Ok(bytes.to_vec())
+        })
+    }
+
+    fn list_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        let url = self.url(&dir_prefix(path));
+        self.runtime.block_on(async {
+            let response = self.get(&url).await?;
+            let html = response.text().await.map_err(io::Error::other)?;
+            Ok(parse_index_links(&html))
+        })
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        let head = self.client.head(self.url(path)).send();
+        let found = self.runtime.block_on(head);
+        found.is_ok_and(|response| response.status().is_success()) || self.is_dir(path)
+    }
+
+    fn copy_to(&self, path: &str, writer: &mut dyn Write) -> io::Result<u64> {
+        self.runtime.block_on(async {
+            let mut response = self.get(&self.url(path)).await?;
+            let mut written = 0;
+            while let Some(chunk) = response.chunk().await.map_err(io::Error::other)? {
+                writer.write_all(&chunk)?;
+                written += chunk.len() as u64;
+            }
+            Ok(written)
+        })
+    }
+}
+
+/// Returns the entries linked from a directory index page. Links to parent directories,
+/// other hosts and query strings (such as sort links) are skipped.
+pub fn parse_index_links(html: &str) -> Vec<DirEntry> {
+    let mut entries: Vec<DirEntry> = html
+        .split("href=\"")
+        .skip(1)
+        .filter_map(|link| link.split('"').next())
+        .filter(|link| {
+            !link.is_empty()
+                && !link.starts_with(['?', '#', '/', '.'])
+                && !link.contains("://")
+        })
+        .map(|link| DirEntry {
+            name: link.trim_end_matches('/').replace("%20", " "),
+            is_dir: link.ends_with('/'),
+        })
+        .filter(|entry| !entry.name.contains('/'))
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries.dedup();
+    entries
+}
+
+/// Splits a URL into its scheme, its bucket or host, and the path after it.
+pub fn split_url(path: &str) -> Option<(&str, &str, &str)> {
+    let (scheme, rest) = path.split_once("://")?;
+    let (authority, key) = rest.split_once('/').unwrap_or((rest, ""));
+    Some((scheme, authority, key.trim_matches('/')))
+}
+
+/// Returns `true` if `path` is a URL of a remote backend.
+pub fn is_remote(path: &Path) -> bool {
+    split_url(&path.to_string_lossy()).is_some()
+}
+
+/// The files of a dataset: the local disk with the `.zip` archives opened so far, and the
+/// remote backends, which are created with the `[storage]` options the first time one of
+/// their paths is used. Shared by the sequences of the dataset.
+#[derive(Default)]
+pub struct Storage {
+    config: StorageConfig,
+    local: LocalStorage,
+    /// Remote backends by scheme and bucket or host, e.g. "s3://bucket"
+    backends: Mutex<HashMap<String, Arc<dyn StorageBackend>>>,
+    /// Runs the futures of the remote backends, which are synchronous so they can be used from
+    /// the rayon threads of the pipeline. Started with the first remote backend.
+    runtime: OnceLock<Arc<Runtime>>,
+}
+
+impl Debug for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let backends = self.backends.lock().unwrap();
+        f.debug_struct("Storage")
+            .field("config", &self.config)
+            .field("backends", &backends.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Storage {
+    pub fn new(config: &StorageConfig) -> Self {
+        Self {
+            config: config.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// The storage of the `[storage]` options of `config`.
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(&config.storage.clone().unwrap_or_default())
+    }
+
+    /// Returns the backend `path` is stored in and the path within that backend. Remote
+    /// backends are created the first time one of their paths is used.
+    pub fn backend(&self, path: &Path) -> io::Result<(Arc<dyn StorageBackend>, String)> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let Some((scheme, authority, key)) = split_url(&path) else {
+            return Ok((Arc::new(self.local.clone()), path));
+        };
+
+        let name = format!("{}://{}", scheme, authority);
+        let mut backends = self.backends.lock().unwrap();
+        if let Some(backend) = backends.get(&name) {
+            return Ok((backend.clone(), key.to_string()));
+        }
+
+        let backend: Arc<dyn StorageBackend> = match scheme {
+            "s3" => Arc::new(S3Storage::new(authority, &self.config, self.runtime())),
+            "http" | "https" => Arc::new(HttpStorage::new(&name, &self.config, self.runtime())),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("Unsupported storage scheme \"{}\" in {}", scheme, path),
+                ))
+            }
+        };
+        backends.insert(name, backend.clone());
+        Ok((backend, key.to_string()))
+    }
+
+    /// Must not be used from an async context.
+    fn runtime(&self) -> Arc<Runtime> {
+        self.runtime
+            .get_or_init(|| Arc::new(Runtime::new().expect("Failed to start the storage runtime")))
+            .clone()
+    }
+
+    /// Calls `read` with a reader of the file at `path`. Local files are streamed, remote
+    /// files are read into memory first.
+    pub fn with_reader<T>(
+        &self,
+        path: &Path,
+        read: impl FnOnce(&mut dyn Read) -> io::Result<T>,
+    ) -> io::Result<T> {
+        if !is_remote(path) {
+            return self.local.archives.with_reader(path, read);
+        }
+        let (backend, key) = self.backend(path)?;
+        read(&mut Cursor::new(backend.read_file(&key)?))
+    }
+
+    /// Reads the whole file at `path`.
+    pub fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let (backend, key) = self.backend(path)?;
+        backend.read_file(&key)
+    }
+
+    /// Returns `true` if the file or directory at `path` exists.
+    pub fn exists(&self, path: &Path) -> bool {
+        self.backend(path)
+            .is_ok_and(|(backend, key)| backend.exists(&key))
+    }
+
+    /// Returns `true` if `path` is a directory.
+    pub fn is_dir(&self, path: &Path) -> bool {
+        self.backend(path)
+            .is_ok_and(|(backend, key)| backend.is_dir(&key))
+    }
+
+    /// Returns the subdirectories of `dir`, ordered alphabetically.
+    pub fn list_dirs(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let (backend, key) = self.backend(dir)?;
+        Ok(backend
+            .list_dir(&key)?
+            .into_iter()
+            .filter(|entry| entry.is_dir)
+            .map(|entry| dir.join(entry.name))
+            .collect())
+    }
+
+    /// Returns `true` if `path` is a plain file on the local disk, i.e. neither remote nor
+    /// inside an archive, so it can be passed to libraries that open files themselves.
+    pub fn is_local_file(&self, path: &Path) -> bool {
+        !is_remote(path) && self.local.archives.split_archive_path(path).is_none()
+    }
+}
+
+fn dir_prefix(path: &str) -> String {
+    match path.trim_matches('/') {
+        "" => String::new(),
+        path => format!("{}/", path),
+    }
+}
+
+fn s3_error<E, R>(e: SdkError<E, R>) -> io::Error
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+    R: Debug,
+{
+    let kind = match e.as_service_error().and_then(|e| e.code()) {
+        Some("NoSuchKey" | "NoSuchBucket" | "NotFound") => io::ErrorKind::NotFound,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, DisplayErrorContext(&e).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_storage_backends() {
+        assert_eq!(
+            split_url("s3://bucket/SoccerNet/train/"),
+            Some(("s3", "bucket", "SoccerNet/train"))
+        );
+        assert_eq!(
+            split_url("https://data.example.com"),
+            Some(("https", "data.example.com", ""))
+        );
+        assert_eq!(split_url("data/SoccerNet/train"), None);
+        assert!(is_remote(&Path::new("s3://bucket/SoccerNet").join("train")));
+
+        let html = r#"<html><body><h1>Index of /SoccerNet/train/</h1>
+            <a href="../">../</a>
+            <a href="?C=N;O=D">Name</a>
+            <a href="SNGS-002/">SNGS-002/</a>
+            <a href="SNGS-001/">SNGS-001/</a>
+            <a href="Labels-v2.json">Labels-v2.json</a>
+            <a href="https://example.com/">elsewhere</a>
+            </body></html>"#;
+        let entry = |name: &str, is_dir| DirEntry {
+            name: name.to_string(),
+            is_dir,
+        };
+        assert_eq!(
+            parse_index_links(html),
+            vec![
+                entry("Labels-v2.json", false),
+                entry("SNGS-001", true),
+                entry("SNGS-002", true)
+            ]
+        );
+
+        let storage = Storage::default();
+        let dir = std::env::temp_dir().join(format!("storage_{}", std::process::id()));
+        fs::create_dir_all(dir.join("train/SNGS-001")).unwrap();
+        fs::write(dir.join("train/notes.txt"), "notes").unwrap();
+        assert_eq!(
+            storage.list_dirs(&dir.join("train")).unwrap(),
+            vec![dir.join("train/SNGS-001")]
+        );
+        let (backend, key) = storage.backend(&dir.join("train")).unwrap();
+        assert_eq!(
+            backend.list_dir(&key).unwrap(),
+            vec![entry("SNGS-001", true), entry("notes.txt", false)]
+        );
+        assert_eq!(
+            storage.read(&dir.join("train/notes.txt")).unwrap(),
+            b"notes"
+        );
+        assert!(storage.is_local_file(&dir.join("train/notes.txt")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+use super::dataset::Dataset;
+use super::models::VideoData;
+use crate::utils::frame_source::frame_number;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// A problem found in a sequence by [`Dataset::validate`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationIssue {
+    /// The labels file is missing or could not be parsed
+    UnreadableLabels { error: String },
+    /// An annotation that could not be parsed and was skipped (with `lenient_labels`)
+    InvalidAnnotation {
+        index: usize,
+        field: Option<String>,
+        error: String,
+    },
+    /// An image listed in the labels does not exist on disk
+    MissingImage { file_name: String },
+    /// Annotations reference an image_id that is not in `images`
+    UnknownImageId {
+        image_id: String,
+        num_annotations: usize,
+    },
+    /// The same track_id is used by several annotations in one frame
+    DuplicateTrackId { image_id: String, track_id: u32 },
+    /// There is no "ball" category, so no ball can be found in any frame
+    MissingBallCategory,
+    /// The frame number of an image is not larger than the one of the image before it
+    NonMonotonicFrame {
+        file_name: String,
+        previous_file_name: String,
+    },
+}
+
+/// Validation result of a single sequence.
+#[derive(Clone, Debug, Serialize)]
+pub struct SequenceReport {
+    pub subset: String,
+    pub sequence: String,
+    pub issues: Vec<ValidationIssue>,
+}
+
+/// Machine-readable result of [`Dataset::validate`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub num_sequences: usize,
+    pub num_invalid_sequences: usize,
+    pub num_issues: usize,
+    pub sequences: Vec<SequenceReport>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.num_issues == 0
+    }
+}
+
+impl Dataset {
+    /// Checks every sequence of the configured subsets for missing image files, annotations
+    /// referencing unknown image_ids, annotations skipped by `lenient_labels`, duplicate
+    /// track_ids in a frame, a missing ball category and non-monotonic frame numbering.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        for subset in &self.subsets {
+            for seq_dir in self.filtered_sequence_dirs(subset) {
+                let issues = match self.load_sequence(&seq_dir) {
+                    Ok(video_data) => validate_video(&video_data),
+                    Err(e) => vec![ValidationIssue::UnreadableLabels {
+                        error: e.to_string(),
+                    }],
+                };
+
+                report.num_sequences += 1;
+                if !issues.is_empty() {
+                    report.num_invalid_sequences += 1;
+                    report.num_issues += issues.len();
+                }
+                report.sequences.push(SequenceReport {
+                    subset: subset.clone(),
+                    sequence: file_name(&seq_dir),
+                    issues,
+                });
+            }
+        }
+
+        report
+    }
+}
+
+/// Returns all issues found in a single video.
+pub fn validate_video(video_data: &VideoData) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let labels = &video_data.labels;
+
+    if !labels.categories.iter().any(|c| c.name == "ball") {
+        issues.push(ValidationIssue::MissingBallCategory);
+    }
+
+    issues.extend(video_data.invalid_annotations().iter().map(|invalid| {
+        ValidationIssue::InvalidAnnotation {
+            index: invalid.index,
+            field: invalid.field.clone(),
+            error: invalid.error.clone(),
+        }
+    }));
+
+    // Frames read from a video file have no images on disk
+    let image_paths = match video_data.video_path {
+        Some(_) => &[][..],
+        None => &video_data.image_paths[..],
+    };
+    for image_path in image_paths {
+        if !video_data.storage.exists(image_path) {
+            issues.push(ValidationIssue::MissingImage {
+                file_name: file_name(image_path),
+            });
+        }
+    }
+
+    let mut previous: Option<(u32, &str)> = None;
+    for image in &labels.images {
+        let Some(frame_num) = frame_number(Path::new(&image.file_name)) else {
+            continue;
+        };
+        if let Some((previous_num, previous_file_name)) = previous {
+            if frame_num <= previous_num {
+                issues.push(ValidationIssue::NonMonotonicFrame {
+                    file_name: image.file_name.clone(),
+                    previous_file_name: previous_file_name.to_string(),
+                });
+            }
+        }
+        previous = Some((frame_num, image.file_name.as_str()));
+    }
+
+    let image_ids: HashSet<&str> = labels.images.iter().map(|i| i.image_id.as_str()).collect();
+    let mut unknown_image_ids: HashMap<String, usize> = HashMap::new();
+
+    for batch in video_data.annotation_batches() {
+        let batch = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                issues.push(ValidationIssue::UnreadableLabels {
+                    error: e.to_string(),
+                });
+                break;
+            }
+        };
+
+        if !image_ids.contains(batch.image_id.as_str()) {
+            *unknown_image_ids.entry(batch.image_id).or_default() += batch.annotations.len();
+            continue;
+        }
+
+        let mut track_ids = HashSet::new();
+        let mut duplicates = HashSet::new();
+        for track_id in batch.annotations.iter().filter_map(|a| a.track_id) {
+            if !track_ids.insert(track_id) && duplicates.insert(track_id) {
+                issues.push(ValidationIssue::DuplicateTrackId {
+                    image_id: batch.image_id.clone(),
+                    track_id,
+                });
+            }
+        }
+    }
+
+    let mut unknown_image_ids: Vec<_> = unknown_image_ids.into_iter().collect();
+    unknown_image_ids.sort();
+    issues.extend(unknown_image_ids.into_iter().map(|(image_id, num_annotations)| {
+        ValidationIssue::UnknownImageId {
+            image_id,
+            num_annotations,
+        }
+    }));
+
+    issues
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::{Annotation, Category, Image};
+
+    fn image(image_id: &str, file_name: &str) -> Image {
+        Image {
+            is_labeled: true,
+            image_id: image_id.to_string(),
+            file_name: file_name.to_string(),
+            height: 1080,
+            width: 1920,
+            has_labeled_person: None,
+            has_labeled_pitch: None,
+        }
+    }
+
+    fn annotation(image_id: &str, track_id: u32) -> Annotation {
+        Annotation {
+            image_id: image_id.to_string(),
+            track_id: Some(track_id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_video() {
+        let mut video_data = VideoData::default();
+        video_data.labels.images = vec![
+            image("1", "000001.jpg"),
+            image("2", "000003.jpg"),
+            image("3", "000002.jpg"),
+        ];
+        video_data.labels.annotations = vec![
+            annotation("1", 1),
+            annotation("1", 1),
+            annotation("2", 1),
+            annotation("9", 1),
+        ];
+        video_data.labels.categories = vec![Category {
+            name: "player".to_string(),
+            ..Default::default()
+        }];
+
+        let issues = validate_video(&video_data);
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::MissingBallCategory,
+                ValidationIssue::NonMonotonicFrame {
+                    file_name: "000002.jpg".to_string(),
+                    previous_file_name: "000003.jpg".to_string(),
+                },
+                ValidationIssue::DuplicateTrackId {
+                    image_id: "1".to_string(),
+                    track_id: 1,
+                },
+                ValidationIssue::UnknownImageId {
+                    image_id: "9".to_string(),
+                    num_annotations: 1,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Finds the likely cause of runs that process no videos or detect no events, which are
+//! almost always configuration mistakes rather than data without dribbles.
+
+use crate::config::{Config, DribblingDetectionConfig};
+use crate::data::dataset::Dataset;
+use crate::data::models::VideoData;
+use crate::dribbling_detection::autotune::{collect_distance_samples, percentile, DistanceSample};
+use std::fmt;
+
+/// Exit code of `--fail-on-empty` runs where no videos were processed
+pub const EXIT_NO_VIDEOS: i32 = 2;
+/// Exit code of `--fail-on-empty` runs where videos were processed, but no events detected
+pub const EXIT_NO_EVENTS: i32 = 3;
+
+/// Number of videos whose annotations are loaded to check the radii
+const NUM_SAMPLE_VIDEOS: usize = 5;
+/// A zone entered in fewer than this fraction of the sampled frames is considered never entered
+const MIN_ZONE_FRACTION: f64 = 0.01;
+
+/// Categories the detector needs to find the ball and the players
+const REQUIRED_CATEGORIES: [&str; 2] = ["ball", "player"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EmptyRunCause {
+    /// The subset has no directory in `data_path`
+    MissingSubset {
+        subset: String,
+        available: Vec<String>,
+    },
+    /// The subset directory has no sequence directories
+    EmptySubset { subset: String },
+    /// No sequence passes the `include_sequences`/`sequence_range` filters
+    NoMatchingSequences { subset: String, num_sequences: usize },
+    /// None of the sequences in the subset could be loaded
+    UnreadableSequences { subset: String, num_sequences: usize },
+    /// No video has these categories, e.g. because of a wrong `[coco.categories]` mapping
+    MissingCategories { categories: Vec<String> },
+    /// No sampled frame has both a ball and a player
+    NoBallOrPlayers,
+    /// The inner zone is not inside the outer zone
+    RadiiOverlap { inner_radius: f64, outer_radius: f64 },
+    /// The closest player is (almost) never within the inner radius, so nobody has possession
+    InnerRadiusTooSmall {
+        inner_radius: f64,
+        holder_ball_median: f64,
+    },
+    /// Defenders are (almost) never within the outer radius of the holder
+    OuterRadiusTooSmall {
+        outer_radius: f64,
+        nearest_defender_median: f64,
+    },
+}
+
+impl fmt::Display for EmptyRunCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSubset { subset, available } => write!(
+                f,
+                "Subset \"{}\" does not exist in data_path. Available subsets: {:?}",
+                subset, available
+            ),
+            Self::EmptySubset { subset } => {
+                write!(f, "Subset \"{}\" has no sequence directories", subset)
+            }
+            Self::NoMatchingSequences {
+                subset,
+                num_sequences,
+            } => write!(
+                f,
+                "None of the {} sequences in \"{}\" match include_sequences/sequence_range",
+                num_sequences, subset
+            ),
+            Self::UnreadableSequences {
+                subset,
+                num_sequences,
+            } => write!(
+                f,
+                "None of the {} sequences in \"{}\" could be loaded. Run --validate for details",
+                num_sequences, subset
+            ),
+            Self::MissingCategories { categories } => write!(
+                f,
+                "No video has the categories {:?}. Check the category names of the labels \
+                 (or [coco.categories])",
+                categories
+            ),
+            Self::NoBallOrPlayers => write!(
+                f,
+                "No sampled frame has both a ball and a player annotation. Check the category ids \
+                 of the annotations"
+            ),
+            Self::RadiiOverlap {
+                inner_radius,
+                outer_radius,
+            } => write!(
+                f,
+                "inner_radius ({}) must be smaller than outer_radius ({})",
+                inner_radius, outer_radius
+            ),
+            Self::InnerRadiusTooSmall {
+                inner_radius,
+                holder_ball_median,
+            } => write!(
+                f,
+                "inner_radius ({}) is too small: the median holder-ball distance is {:.2}. \
+                 Run --autotune to get suggested radii",
+                inner_radius, holder_ball_median
+            ),
+            Self::OuterRadiusTooSmall {
+                outer_radius,
+                nearest_defender_median,
+            } => write!(
+                f,
+                "outer_radius ({}) is too small: the median nearest-defender distance is {:.2}. \
+                 Run --autotune to get suggested radii",
+                outer_radius, nearest_defender_median
+            ),
+        }
+    }
+}
+
+/// Explains why no videos were loaded from a subset.
+pub fn diagnose_no_videos(dataset: &Dataset, subset: &str) -> EmptyRunCause {
+    if !dataset.has_subset(subset) {
+        return EmptyRunCause::MissingSubset {
+            subset: subset.to_string(),
+            available: dataset.available_subsets(),
+        };
+    }
+
+    let num_sequences = dataset.sequence_dirs(subset).len();
+    if num_sequences == 0 {
+        return EmptyRunCause::EmptySubset {
+            subset: subset.to_string(),
+        };
+    }
+
+    match dataset.filtered_sequence_dirs(subset).len() {
+        0 => EmptyRunCause::NoMatchingSequences {
+            subset: subset.to_string(),
+            num_sequences,
+        },
+        num_sequences => EmptyRunCause::UnreadableSequences {
+            subset: subset.to_string(),
+            num_sequences,
+        },
+    }
+}
+
+/// Explains why no events were detected in `videos`. The radii are checked against the
+/// distances in the first few videos, whose annotations are loaded for this.
+pub fn diagnose_no_events(videos: &[VideoData], config: &Config) -> Vec<EmptyRunCause> {
+    let missing: Vec<String> = REQUIRED_CATEGORIES
+        .iter()
+        .filter(|name| {
+            !videos
+                .iter()
+                .any(|v| v.labels.categories.iter().any(|c| c.name == **name))
+        })
+        .map(|name| name.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return vec![EmptyRunCause::MissingCategories {
+            categories: missing,
+        }];
+    }
+
+    let samples: Vec<DistanceSample> = videos
+        .iter()
+        .take(NUM_SAMPLE_VIDEOS)
+        .filter_map(|video_data| {
+            let mut video_data = video_data.clone();
+            video_data.load_annotations().ok()?;
+            Some(collect_distance_samples(&video_data, config))
+        })
+        .flatten()
+        .collect();
+
+    diagnose_radii(&config.dribbling_detection, &samples)
+}
+
+/// Checks whether the radii make it (nearly) impossible for an event to start.
+pub fn diagnose_radii(
+    config: &DribblingDetectionConfig,
+    samples: &[DistanceSample],
+) -> Vec<EmptyRunCause> {
+    let mut causes = Vec::new();
+    if config.inner_radius >= config.outer_radius {
+        causes.push(EmptyRunCause::RadiiOverlap {
+            inner_radius: config.inner_radius,
+            outer_radius: config.outer_radius,
+        });
+    }
+    if samples.is_empty() {
+        causes.push(EmptyRunCause::NoBallOrPlayers);
+        return causes;
+    }
+
+    let possession: Vec<&DistanceSample> = samples
+        .iter()
+        .filter(|s| s.holder_ball <= config.inner_radius)
+        .collect();
+    if (possession.len() as f64) < samples.len() as f64 * MIN_ZONE_FRACTION {
+        let mut holder_ball: Vec<f64> = samples.iter().map(|s| s.holder_ball).collect();
+        causes.push(EmptyRunCause::InnerRadiusTooSmall {
+            inner_radius: config.inner_radius,
+            holder_ball_median: percentile(&mut holder_ball, 50.0).unwrap_or_default(),
+        });
+        return causes;
+    }
+
+    let mut nearest_defender: Vec<f64> =
+        possession.iter().filter_map(|s| s.nearest_defender).collect();
+    let num_pressured = nearest_defender
+        .iter()
+        .filter(|&&d| d <= config.outer_radius)
+        .count();
+    if (num_pressured as f64) < possession.len() as f64 * MIN_ZONE_FRACTION {
+        causes.push(EmptyRunCause::OuterRadiusTooSmall {
+            outer_radius: config.outer_radius,
+            nearest_defender_median: percentile(&mut nearest_defender, 50.0)
+                .unwrap_or(f64::INFINITY),
+        });
+    }
+
+    causes
+}
+
+/// Exit code for `--fail-on-empty`, or `None` if the run processed videos and found events.
+pub fn empty_run_exit_code(num_videos: usize, num_events: usize) -> Option<i32> {
+    if num_videos == 0 {
+        Some(EXIT_NO_VIDEOS)
+    } else if num_events == 0 {
+        Some(EXIT_NO_EVENTS)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection_config(inner_radius: f64, outer_radius: f64) -> DribblingDetectionConfig {
+        DribblingDetectionConfig {
+            use_2d: false,
+            outer_threshold: 3,
+            inner_threshold: 4,
+            frame_skip: 1,
+            min_duration: 1.3,
+            inner_radius,
+            outer_radius,
+            ignore_person_classes: true,
+            ignore_teams: true,
+            outer_in_threshold: 2,
+            outer_out_threshold: 8,
+            possession_max_gap: None,
+        }
+    }
+
+    fn samples(holder_ball: f64, nearest_defender: f64) -> Vec<DistanceSample> {
+        vec![
+            DistanceSample {
+                holder_ball,
+                nearest_defender: Some(nearest_defender),
+            };
+            10
+        ]
+    }
+
+    #[test]
+    fn test_diagnose_radii() {
+        let ok = diagnose_radii(&detection_config(0.8, 2.7), &samples(0.5, 2.0));
+        assert!(ok.is_empty());
+
+        let causes = diagnose_radii(&detection_config(0.1, 2.7), &samples(0.5, 2.0));
+        assert_eq!(
+            causes,
+            vec![EmptyRunCause::InnerRadiusTooSmall {
+                inner_radius: 0.1,
+                holder_ball_median: 0.5,
+            }]
+        );
+
+        let causes = diagnose_radii(&detection_config(0.8, 1.0), &samples(0.5, 2.0));
+        assert_eq!(
+            causes,
+            vec![EmptyRunCause::OuterRadiusTooSmall {
+                outer_radius: 1.0,
+                nearest_defender_median: 2.0,
+            }]
+        );
+
+        let causes = diagnose_radii(&detection_config(2.7, 0.8), &[]);
+        assert_eq!(causes.len(), 2);
+        assert_eq!(causes[1], EmptyRunCause::NoBallOrPlayers);
+    }
+
+    #[test]
+    fn test_empty_run_exit_code() {
+        assert_eq!(empty_run_exit_code(0, 0), Some(EXIT_NO_VIDEOS));
+        assert_eq!(empty_run_exit_code(3, 0), Some(EXIT_NO_EVENTS));
+        assert_eq!(empty_run_exit_code(3, 1), None);
+    }
+}
@@ -0,0 +1,228 @@
+use super::create_dribble_models::{get_ball_model, get_player_models};
+use super::dribble_detector::DribbleDetector;
+use crate::config::Config;
+use crate::data::annotation_index::AnnotationIndex;
+use crate::data::models::VideoData;
+use crate::utils::annotation_calculations::{
+    compute_average_player_bbox_height, filter_annotations,
+};
+use crate::utils::annotation_filters::{count_track_lengths, AnnotationFilter};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Percentile of the holder-to-ball distance used as the suggested inner radius.
+/// The closest player is within this distance of the ball in most frames.
+const INNER_PERCENTILE: f64 = 75.0;
+/// Percentile of the nearest-defender distance (while in possession) used as the suggested
+/// outer radius. A defender is this close in roughly a quarter of all possession frames.
+const OUTER_PERCENTILE: f64 = 25.0;
+
+/// Distances measured in a single frame, in config units (i.e. divided by the same scale
+/// factor that is applied to the radii when not using 2d coordinates).
+#[derive(Clone, Copy, Debug)]
+pub struct DistanceSample {
+    pub holder_ball: f64,
+    pub nearest_defender: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RadiusSuggestion {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub num_frames: usize,
+    pub num_possession_frames: usize,
+    pub holder_ball_median: f64,
+    pub nearest_defender_median: f64,
+}
+
+/// Collects holder-ball and nearest-defender distances for every frame in a video.
+/// Frames without a ball or without players are skipped.
+pub fn collect_distance_samples(video_data: &VideoData, config: &Config) -> Vec<DistanceSample> {
+    let category_map: HashMap<String, u32> = video_data
+        .labels
+        .categories
+        .iter()
+        .map(|c| (c.name.clone(), c.id))
+        .collect();
+
+    let scale_factor = if config.dribbling_detection.use_2d {
+        1.0
+    } else {
+        compute_average_player_bbox_height(&video_data.labels.annotations, &category_map) * 0.2
+    };
+
+    let annotation_filter = config
+        .annotation_filters
+        .as_ref()
+        .map(|filters| {
+            AnnotationFilter::new(
+                filters,
+                &category_map,
+                count_track_lengths(&video_data.labels.annotations),
+            )
+        })
+        .unwrap_or_default();
+
+    let annotation_index = AnnotationIndex::new(
+        video_data
+            .labels
+            .annotations
+            .iter()
+            .filter(|a| annotation_filter.is_plausible(a)),
+    );
+
+    let mut samples = Vec::new();
+    for image in &video_data.labels.images {
+        let filtered_annotations = filter_annotations(
+            annotation_index.get(&image.image_id),
+            &category_map,
+            config.dribbling_detection.ignore_person_classes,
+            config.dribbling_detection.ignore_teams,
+        );
+
+        let Some(ball) = get_ball_model(&category_map, &filtered_annotations, config) else {
+            continue;
+        };
+        let players = get_player_models(&category_map, &filtered_annotations, config)
+            .unwrap_or_default();
+
+        let Some(holder) = players.iter().min_by(|p1, p2| {
+            let p1_dis = DribbleDetector::distance((p1.x, p1.y), (ball.x, ball.y));
+            let p2_dis = DribbleDetector::distance((p2.x, p2.y), (ball.x, ball.y));
+            p1_dis.partial_cmp(&p2_dis).unwrap()
+        }) else {
+            continue;
+        };
+
+        let holder_ball = DribbleDetector::distance((holder.x, holder.y), (ball.x, ball.y));
+        let nearest_defender = players
+            .iter()
+            .filter(|p| p.id != holder.id)
+            .map(|p| DribbleDetector::distance((p.x, p.y), (holder.x, holder.y)))
+            .min_by(|a, b| a.partial_cmp(b).unwrap());
+
+        samples.push(DistanceSample {
+            holder_ball: holder_ball / scale_factor,
+            nearest_defender: nearest_defender.map(|d| d / scale_factor),
+        });
+    }
+
+    samples
+}
+
+/// Suggests inner and outer radii from distance samples.
+///
+/// - `inner_radius` is a high percentile of the holder-ball distance, so the closest player
+///   is considered to have possession in most frames.
+/// - `outer_radius` is a low percentile of the nearest-defender distance in frames where the
+///   holder is within the suggested inner radius, so only actual pressure activates the zone.
+pub fn suggest_radii(samples: &[DistanceSample]) -> Option<RadiusSuggestion> {
+    let mut holder_ball: Vec<f64> = samples.iter().map(|s| s.holder_ball).collect();
+    let inner_radius = percentile(&mut holder_ball, INNER_PERCENTILE)?;
+    let holder_ball_median = percentile(&mut holder_ball, 50.0)?;
+
+    let mut nearest_defender: Vec<f64> = samples
+        .iter()
+        .filter(|s| s.holder_ball <= inner_radius)
+        .filter_map(|s| s.nearest_defender)
+        .collect();
+    let num_possession_frames = nearest_defender.len();
+    let outer_radius = percentile(&mut nearest_defender, OUTER_PERCENTILE)?;
+    let nearest_defender_median = percentile(&mut nearest_defender, 50.0)?;
+
+    // The outer zone must always contain the inner zone
+    let outer_radius = if outer_radius > inner_radius {
+        outer_radius
+    } else {
+        inner_radius * 2.0
+    };
+
+    Some(RadiusSuggestion {
+        inner_radius,
+        outer_radius,
+        num_frames: samples.len(),
+        num_possession_frames,
+        holder_ball_median,
+        nearest_defender_median,
+    })
+}
+
+/// Linearly interpolated percentile (0-100). Sorts `values` in place.
+pub fn percentile(values: &mut [f64], p: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let rank = (p / 100.0).clamp(0.0, 1.0) * (values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+
+    Some(values[lower] * (1.0 - weight) + values[upper] * weight)
+}
+
+/// Writes a copy of the current config with the suggested radii filled in.
+pub fn write_suggested_config(
+    config: &Config,
+    suggestion: &RadiusSuggestion,
+    path: &Path,
+) -> io::Result<()> {
+    let mut suggested = config.clone();
+    suggested.dribbling_detection.inner_radius = round_to(suggestion.inner_radius, 2);
+    suggested.dribbling_detection.outer_radius = round_to(suggestion.outer_radius, 2);
+
+    let toml_data = toml::to_string_pretty(&suggested).map_err(io::Error::other)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, toml_data)
+}
+
+fn round_to(value: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    (value * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let mut values = vec![4.0, 1.0, 3.0, 2.0, 5.0];
+        assert_eq!(percentile(&mut values, 0.0), Some(1.0));
+        assert_eq!(percentile(&mut values, 50.0), Some(3.0));
+        assert_eq!(percentile(&mut values, 100.0), Some(5.0));
+        assert_eq!(percentile(&mut values, 25.0), Some(2.0));
+        assert_eq!(percentile(&mut [], 50.0), None);
+    }
+
+    #[test]
+    fn test_suggest_radii_outer_contains_inner() {
+        let samples: Vec<DistanceSample> = (1..=10)
+            .map(|i| DistanceSample {
+                holder_ball: i as f64 * 0.1,
+                nearest_defender: Some(0.5),
+            })
+            .collect();
+
+        let suggestion = suggest_radii(&samples).unwrap();
+        assert!((suggestion.inner_radius - 0.775).abs() < 1e-9);
+        assert!(suggestion.outer_radius > suggestion.inner_radius);
+        assert_eq!(suggestion.num_frames, 10);
+        assert_eq!(suggestion.num_possession_frames, 7);
+    }
+
+    #[test]
+    fn test_suggest_radii_without_defenders() {
+        let samples = vec![DistanceSample {
+            holder_ball: 0.3,
+            nearest_defender: None,
+        }];
+        assert!(suggest_radii(&samples).is_none());
+    }
+}
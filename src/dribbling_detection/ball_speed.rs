@@ -0,0 +1,110 @@
+use super::dribble_detector::DribbleDetector;
+use super::dribble_models::{Ball, DribbleEvent};
+
+/// Finds ball speed spikes: frames where the ball suddenly moves faster than `spike_threshold`
+/// per frame, which usually means it was kicked (a pass or a shot). Frames without a detected
+/// ball are skipped, so a missing ball does not show up as a jump.
+#[derive(Clone)]
+pub struct BallSpeedTracker {
+    /// Minimum ball movement per frame counted as a kick.
+    pub spike_threshold: f64,
+
+    last_ball: Option<(u32, Ball)>,
+    above_threshold: bool,
+    spikes: Vec<u32>,
+}
+
+impl BallSpeedTracker {
+    pub fn new(spike_threshold: f64) -> Self {
+        Self {
+            spike_threshold,
+            last_ball: None,
+            above_threshold: false,
+            spikes: Vec::new(),
+        }
+    }
+
+    pub fn process_frame(&mut self, frame_number: u32, ball: Option<Ball>) {
+        let Some(ball) = ball else {
+            return;
+        };
+
+        if let Some((last_frame, last_ball)) = self.last_ball {
+            if frame_number > last_frame {
+                let speed = DribbleDetector::distance((last_ball.x, last_ball.y), (ball.x, ball.y))
+                    / (frame_number - last_frame) as f64;
+
+                // Only the first frame of a fast movement is the kick
+                let fast = speed >= self.spike_threshold;
+                if fast && !self.above_threshold {
+                    self.spikes.push(last_frame);
+                }
+                self.above_threshold = fast;
+            }
+        }
+        self.last_ball = Some((frame_number, ball));
+    }
+
+    /// Returns the frames where the ball was kicked, in order.
+    pub fn finish(self) -> Vec<u32> {
+        self.spikes
+    }
+}
+
+/// Moves the end frame of each finished event to the closest ball speed spike within `window`
+/// frames of it (and after the start of the event). This is where the holder released the
+/// ball, which is more precise than when the zones around the ball emptied.
+pub fn refine_event_end_frames(events: &mut [DribbleEvent], spikes: &[u32], window: u32) {
+    for event in events {
+        let Some(end) = event.end_frame else {
+            continue;
+        };
+
+        let release = spikes
+            .iter()
+            .filter(|&&spike| spike > event.start_frame && spike.abs_diff(end) <= window)
+            .min_by_key(|&&spike| spike.abs_diff(end));
+
+        if let Some(&release) = release {
+            event.end_frame = Some(release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spikes_and_refinement() {
+        let mut tracker = BallSpeedTracker::new(2.0);
+        // The ball is dribbled slowly, goes missing for a frame and is then kicked at frame 5
+        let positions = [
+            Some(0.0),
+            Some(0.5),
+            Some(1.0),
+            None,
+            Some(2.0),
+            Some(2.5),
+            Some(6.0),
+            Some(10.0),
+            Some(10.5),
+        ];
+        for (i, x) in positions.iter().enumerate() {
+            tracker.process_frame(i as u32, x.map(|x| Ball::new(x, 0.0)));
+        }
+        let spikes = tracker.finish();
+        assert_eq!(spikes, vec![5]);
+
+        let mut events = vec![
+            DribbleEvent::new(1, 0, "video".to_string()),
+            DribbleEvent::new(1, 0, "video".to_string()),
+        ];
+        events[0].end_frame = Some(8);
+        events[1].end_frame = Some(20);
+        refine_event_end_frames(&mut events, &spikes, 4);
+
+        assert_eq!(events[0].end_frame, Some(5));
+        assert_eq!(events[1].end_frame, Some(20));
+    }
+}
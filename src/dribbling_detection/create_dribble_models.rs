@@ -1,5 +1,6 @@
 use super::dribble_models::{Ball, Player};
-use crate::config::Config;
+use super::smoothing::{build_smoother, Smoother, MAX_GAP};
+use crate::config::{Config, SmoothingFilterConfig, DEFAULT_FRAME_RATE};
 use crate::data::models::Annotation;
 use crate::utils::annotation_calculations::calculate_bbox_pitch_coordinates;
 use std::collections::HashMap;
@@ -20,7 +21,7 @@ pub fn get_ball_model(
             if a.category_id == ball_id {
                 let (x, y) =
                     calculate_bbox_pitch_coordinates(a.clone(), config.dribbling_detection.use_2d)?;
-                Some(Ball { x: x, y: y })
+                Some(Ball::new(x, y))
             } else {
                 None
             }
@@ -54,7 +55,13 @@ pub fn get_player_models(
                     x: x,
                     y: y,
                     velocity: (0.0, 0.0),
+                    acceleration: (0.0, 0.0),
                     within_inner_rad: false,
+                    team: if config.dribbling_detection.ignore_teams {
+                        None
+                    } else {
+                        a.attributes.as_ref().and_then(|attr| attr.team.clone())
+                    },
                 })
             } else {
                 None
@@ -64,3 +71,156 @@ pub fn get_player_models(
 
     Some(players)
 }
+
+/// What is known of the motion of the ball or a player from the frames before.
+struct Motion {
+    frame: u32,
+    position: (f64, f64),
+    /// `None` until the second frame
+    velocity: Option<(f64, f64)>,
+    smoother: Box<dyn Smoother>,
+}
+
+impl Motion {
+    fn new(frame: u32, position: (f64, f64), filter: &SmoothingFilterConfig) -> Self {
+        Self {
+            frame,
+            position,
+            velocity: None,
+            smoother: build_smoother(filter),
+        }
+    }
+
+    /// The velocity and acceleration at `frame`, where the ball or player is at `position`.
+    /// Both are 0 after a gap of more than [`MAX_GAP`] frames or a jump back to an earlier
+    /// frame, e.g. when a clip is replayed, as the motion starts over.
+    fn update(
+        &mut self,
+        frame: u32,
+        position: (f64, f64),
+        frame_rate: f64,
+    ) -> ((f64, f64), (f64, f64)) {
+        let gap = frame.saturating_sub(self.frame);
+        if gap == 0 || gap > MAX_GAP {
+            self.smoother.reset();
+            self.frame = frame;
+            self.position = position;
+            self.velocity = None;
+            return ((0.0, 0.0), (0.0, 0.0));
+        }
+
+        let seconds = f64::from(gap) / frame_rate;
+        let velocity = self.smoother.smooth((
+            (position.0 - self.position.0) / seconds,
+            (position.1 - self.position.1) / seconds,
+        ));
+        let acceleration = self.velocity.map_or((0.0, 0.0), |previous| {
+            (
+                (velocity.0 - previous.0) / seconds,
+                (velocity.1 - previous.1) / seconds,
+            )
+        });
+        self.frame = frame;
+        self.position = position;
+        self.velocity = Some(velocity);
+        (velocity, acceleration)
+    }
+}
+
+/// Computes the velocity and acceleration of the ball and the players by differentiating their
+/// positions across frames, in distance per second and per second squared at the frame rate of
+/// the sequence. Differentiating amplifies tracking noise, so the positions should be smoothed
+/// first (see [`super::smoothing::FrameSmoother`]), and the velocities are smoothed with the
+/// `velocity` filter of `[smoothing]`. Players without a track id keep a velocity of 0.
+pub struct Kinematics {
+    frame_rate: f64,
+    velocity_filter: SmoothingFilterConfig,
+    ball: Option<Motion>,
+    players: HashMap<u32, Motion>,
+}
+
+impl Kinematics {
+    pub fn new(frame_rate: f64, velocity_filter: &SmoothingFilterConfig) -> Self {
+        Self {
+            frame_rate: if frame_rate > 0.0 {
+                frame_rate
+            } else {
+                DEFAULT_FRAME_RATE
+            },
+            velocity_filter: velocity_filter.clone(),
+            ball: None,
+            players: HashMap::new(),
+        }
+    }
+
+    /// Sets the velocity and acceleration of `ball` and `players` in frame `frame_number`.
+    pub fn process_frame(
+        &mut self,
+        frame_number: u32,
+        ball: Option<&mut Ball>,
+        players: &mut [Player],
+    ) {
+        if let Some(ball) = ball {
+            let position = (ball.x, ball.y);
+            let motion = self
+                .ball
+                .get_or_insert_with(|| Motion::new(frame_number, position, &self.velocity_filter));
+            (ball.velocity, ball.acceleration) =
+                motion.update(frame_number, position, self.frame_rate);
+        }
+
+        for player in players.iter_mut().filter(|p| p.id != u32::MAX) {
+            let position = (player.x, player.y);
+            let motion = self
+                .players
+                .entry(player.id)
+                .or_insert_with(|| Motion::new(frame_number, position, &self.velocity_filter));
+            (player.velocity, player.acceleration) =
+                motion.update(frame_number, position, self.frame_rate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(x: f64) -> Player {
+        Player {
+            id: 1,
+            x,
+            y: 0.0,
+            velocity: (0.0, 0.0),
+            acceleration: (0.0, 0.0),
+            within_inner_rad: false,
+            team: None,
+        }
+    }
+
+    #[test]
+    fn test_kinematics() {
+        let mut kinematics = Kinematics::new(25.0, &SmoothingFilterConfig::None);
+        // The player speeds up by 0.04 per frame, i.e. 25 per second squared, and the ball
+        // moves 0.2 per frame
+        let mut velocities = Vec::new();
+        for frame in 0..4 {
+            let t = f64::from(frame);
+            let mut players = [player(0.02 * t * t)];
+            let mut ball = Ball::new(0.2 * t, 0.0);
+            kinematics.process_frame(frame, Some(&mut ball), &mut players);
+            assert!((ball.velocity.0 - if frame == 0 { 0.0 } else { 5.0 }).abs() < 1e-9);
+            velocities.push((players[0].velocity.0, players[0].acceleration.0));
+        }
+        // The velocity of a frame is that since the frame before
+        assert_eq!(velocities[0], (0.0, 0.0));
+        assert!((velocities[2].0 - 1.5).abs() < 1e-9);
+        assert!((velocities[3].1 - 25.0).abs() < 1e-9);
+
+        // Gaps are bridged at the frame rate, and long ones start over
+        let mut players = [player(1.0)];
+        kinematics.process_frame(5, None, &mut players);
+        assert!((players[0].velocity.0 - (1.0 - 0.18) * 12.5).abs() < 1e-9);
+        kinematics.process_frame(30, None, &mut players);
+        assert_eq!(players[0].velocity, (0.0, 0.0));
+    }
+}
@@ -1,9 +1,35 @@
 use std::collections::HashSet;
+use std::fmt;
 
 use crate::config::Config;
 
 use super::dribble_models::{DribbleEvent, DribbleFrame, Player};
 
+/// What the detector is doing at the current frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetectorState {
+    /// No defenders near the ball
+    Idle,
+    /// Defenders are in the outer zone, but no event has started
+    OuterZoneActive,
+    /// An event is ongoing
+    Event,
+    /// An ongoing event where a defender was in the inner zone for `inner_threshold` frames
+    ContestedEvent,
+}
+
+impl fmt::Display for DetectorState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            Self::Idle => "idle",
+            Self::OuterZoneActive => "outer zone active",
+            Self::Event => "event",
+            Self::ContestedEvent => "contested event",
+        };
+        write!(f, "{}", state)
+    }
+}
+
 /// Detects dribble events. An event is started when a defender enters the outer radius,
 /// becomes contested if a defender is inside the inner radius for at least `inner_threshold` frames,
 /// and is only considered valid if it lasts at least `outer_threshold` frames.
@@ -71,10 +97,35 @@ impl DribbleDetector {
         }
     }
 
+    /// A detector with the `[dribbling_detection]` parameters of `config`, with the radii
+    /// multiplied by the `distance_scale` of the video.
+    pub fn from_config(video_name: String, distance_scale: f64, config: Config) -> Self {
+        let detection = &config.dribbling_detection;
+        Self::new(
+            video_name,
+            detection.inner_radius * distance_scale,
+            detection.outer_radius * distance_scale,
+            detection.inner_threshold,
+            detection.outer_threshold,
+            detection.outer_in_threshold,
+            detection.outer_out_threshold,
+            config,
+        )
+    }
+
     pub fn current_active_event(&self) -> Option<&DribbleEvent> {
         self.active_event.as_ref()
     }
 
+    pub fn state(&self) -> DetectorState {
+        match &self.active_event {
+            Some(event) if event.ever_contested => DetectorState::ContestedEvent,
+            Some(_) => DetectorState::Event,
+            None if self.outer_zone_active => DetectorState::OuterZoneActive,
+            None => DetectorState::Idle,
+        }
+    }
+
     /// Returns the Euclidean distance between two points.
     pub fn distance(p1: (f64, f64), p2: (f64, f64)) -> f64 {
         ((p2.0 - p1.0).powi(2) + (p2.1 - p1.1).powi(2)).sqrt()
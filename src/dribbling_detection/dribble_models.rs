@@ -1,20 +1,41 @@
 use std::f64;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone)]
 pub struct Player {
     pub id: u32,
     pub x: f64,
     pub y: f64,
+    /// Distance per second, see [`super::create_dribble_models::Kinematics`]
     pub velocity: (f64, f64),
+    /// Change of the velocity per second
+    pub acceleration: (f64, f64),
     pub within_inner_rad: bool,
+    /// Team from the annotation attributes. `None` if unknown or if teams are ignored.
+    pub team: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ball {
     pub x: f64,
     pub y: f64,
+    /// Distance per second, see [`super::create_dribble_models::Kinematics`]
+    pub velocity: (f64, f64),
+    /// Change of the velocity per second
+    pub acceleration: (f64, f64),
+}
+
+impl Ball {
+    /// A ball at `(x, y)` that is not known to move.
+    pub fn new(x: f64, y: f64) -> Self {
+        Self {
+            x,
+            y,
+            velocity: (0.0, 0.0),
+            acceleration: (0.0, 0.0),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -24,7 +45,7 @@ pub struct DribbleFrame {
     pub ball: Ball,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DribbleEvent {
     pub file_name: String,
     pub finished: bool,
@@ -38,6 +59,8 @@ pub struct DribbleEvent {
     pub active_defenders: Vec<u32>,
     pub inner_defenders: Vec<u32>,
     pub ball_between_occurred: bool,
+    /// Id of the possession spell this event happened in, if any.
+    pub possession_id: Option<u32>,
 }
 
 impl DribbleEvent {
@@ -57,6 +80,7 @@ impl DribbleEvent {
 
             // Initialize new field to false
             ball_between_occurred: false,
+            possession_id: None,
         }
     }
 
@@ -75,3 +99,15 @@ impl DribbleEvent {
         }
     }
 }
+
+/// A period where the possession holder keeps the ball while standing still under pressure.
+/// `end_frame` is the last frame the holder was still shielding the ball.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShieldingEvent {
+    pub file_name: String,
+    pub possession_holder: u32,
+    pub start_frame: u32,
+    pub end_frame: u32,
+    pub duration_frames: u32,
+    pub defenders: Vec<u32>,
+}
@@ -1,3 +1,11 @@
+pub mod autotune;
+pub mod ball_speed;
 pub mod create_dribble_models;
 pub mod dribble_detector;
 pub mod dribble_models;
+pub mod possession;
+pub mod shielding_detector;
+pub mod smoothing;
+pub mod sweep;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
@@ -0,0 +1,156 @@
+use super::dribble_detector::DribbleDetector;
+use super::dribble_models::{DribbleEvent, DribbleFrame};
+use serde::{Deserialize, Serialize};
+
+/// A possession spell: a single player keeping the ball from `start_frame` to `end_frame`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Possession {
+    /// Sequential id, unique within a video.
+    pub id: u32,
+    pub player_id: u32,
+    pub team: Option<String>,
+    pub start_frame: u32,
+    pub end_frame: u32,
+}
+
+/// Segments a video into possession spells using the same proximity logic as the dribble
+/// detector: the player closest to the ball has possession if the ball is within `inner_rad`.
+/// A spell ends when another player gains possession, or when nobody has had the ball for more
+/// than `max_gap` frames.
+#[derive(Clone)]
+pub struct PossessionTracker {
+    pub inner_rad: f64,
+    pub max_gap: u32,
+
+    next_id: u32,
+    current: Option<Possession>,
+    frames_without_holder: u32,
+    possessions: Vec<Possession>,
+}
+
+impl PossessionTracker {
+    pub fn new(inner_rad: f64, max_gap: u32) -> Self {
+        Self {
+            inner_rad,
+            max_gap,
+            next_id: 0,
+            current: None,
+            frames_without_holder: 0,
+            possessions: Vec::new(),
+        }
+    }
+
+    pub fn process_frame(&mut self, frame: &DribbleFrame) {
+        let ball = (frame.ball.x, frame.ball.y);
+        let holder = frame
+            .players
+            .iter()
+            .map(|p| (p, DribbleDetector::distance((p.x, p.y), ball)))
+            .min_by(|(_, d1), (_, d2)| d1.partial_cmp(d2).unwrap())
+            .filter(|(_, d)| *d < self.inner_rad)
+            .map(|(p, _)| p);
+
+        let Some(holder) = holder else {
+            self.frames_without_holder += 1;
+            if self.frames_without_holder > self.max_gap {
+                self.close_current();
+            }
+            return;
+        };
+
+        self.frames_without_holder = 0;
+        if let Some(current) = self.current.as_mut() {
+            if current.player_id == holder.id {
+                current.end_frame = frame.frame_number;
+                return;
+            }
+        }
+
+        self.close_current();
+        self.current = Some(Possession {
+            id: self.next_id,
+            player_id: holder.id,
+            team: holder.team.clone(),
+            start_frame: frame.frame_number,
+            end_frame: frame.frame_number,
+        });
+        self.next_id += 1;
+    }
+
+    /// Closes the current spell and returns all possessions found in the video.
+    pub fn finish(mut self) -> Vec<Possession> {
+        self.close_current();
+        self.possessions
+    }
+
+    fn close_current(&mut self) {
+        if let Some(possession) = self.current.take() {
+            self.possessions.push(possession);
+        }
+    }
+}
+
+/// Sets `possession_id` on each event to the possession spell of its holder that contains
+/// the first detected frame of the event.
+pub fn link_events_to_possessions(events: &mut [DribbleEvent], possessions: &[Possession]) {
+    for event in events {
+        let frame = event.frames.first().copied().unwrap_or(event.start_frame);
+        event.possession_id = possessions
+            .iter()
+            .find(|p| {
+                p.player_id == event.possession_holder
+                    && p.start_frame <= frame
+                    && frame <= p.end_frame
+            })
+            .map(|p| p.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dribbling_detection::dribble_models::{Ball, Player};
+
+    fn frame(frame_number: u32, ball_x: f64) -> DribbleFrame {
+        let player = |id, x| Player {
+            id,
+            x,
+            y: 0.0,
+            velocity: (0.0, 0.0),
+            acceleration: (0.0, 0.0),
+            within_inner_rad: false,
+            team: None,
+        };
+        DribbleFrame {
+            frame_number,
+            players: vec![player(1, 0.0), player(2, 10.0)],
+            ball: Ball::new(ball_x, 0.0),
+        }
+    }
+
+    #[test]
+    fn test_possession_segments() {
+        let mut tracker = PossessionTracker::new(1.0, 2);
+        // Player 1 has the ball, it travels for 2 frames (within max_gap), then player 2 has it
+        let ball_positions = [0.0, 0.5, 0.2, 3.0, 6.0, 9.5, 10.0];
+        for (i, x) in ball_positions.iter().enumerate() {
+            tracker.process_frame(&frame(i as u32, *x));
+        }
+        let possessions = tracker.finish();
+
+        assert_eq!(possessions.len(), 2);
+        assert_eq!(
+            (possessions[0].player_id, possessions[0].start_frame, possessions[0].end_frame),
+            (1, 0, 2)
+        );
+        assert_eq!(
+            (possessions[1].player_id, possessions[1].start_frame, possessions[1].end_frame),
+            (2, 5, 6)
+        );
+
+        let mut events = vec![DribbleEvent::new(2, 0, "video".to_string())];
+        events[0].frames = vec![6];
+        link_events_to_possessions(&mut events, &possessions);
+        assert_eq!(events[0].possession_id, Some(1));
+    }
+}
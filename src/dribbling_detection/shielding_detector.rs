@@ -0,0 +1,193 @@
+use super::dribble_detector::DribbleDetector;
+use super::dribble_models::{DribbleFrame, Player, ShieldingEvent};
+
+/// Detects shielding: the possession holder keeps the ball while standing (roughly) still,
+/// with at least one defender inside the outer radius for at least `min_frames` frames.
+/// Shielding events are reported separately from dribbles, so they do not affect dribble counts.
+#[derive(Clone)]
+pub struct ShieldingDetector {
+    pub video_name: String,
+    pub inner_rad: f64,
+    pub outer_rad: f64,
+    /// Minimum number of frames the holder must shield the ball for an event to be kept.
+    pub min_frames: u32,
+    /// Maximum distance the holder may move away from where the event started.
+    pub max_movement: f64,
+
+    active_event: Option<ShieldingEvent>,
+    /// Position of the possession holder when the active event started.
+    anchor: (f64, f64),
+}
+
+impl ShieldingDetector {
+    pub fn new(
+        video_name: String,
+        inner_rad: f64,
+        outer_rad: f64,
+        min_frames: u32,
+        max_movement: f64,
+    ) -> Self {
+        Self {
+            video_name,
+            inner_rad,
+            outer_rad,
+            min_frames,
+            max_movement,
+            active_event: None,
+            anchor: (0.0, 0.0),
+        }
+    }
+
+    /// Process a frame, returning a shielding event if one finished here.
+    pub fn process_frame(&mut self, frame: &DribbleFrame) -> Option<ShieldingEvent> {
+        let ball = (frame.ball.x, frame.ball.y);
+        let Some(holder) = Self::closest_to_ball(&frame.players, ball) else {
+            return self.end_event(true);
+        };
+        let holder_ball_dist = DribbleDetector::distance((holder.x, holder.y), ball);
+
+        let Some(event) = self.active_event.as_mut() else {
+            self.try_start_event(frame, holder, holder_ball_dist);
+            return None;
+        };
+
+        if holder.id != event.possession_holder {
+            // A defender taking the ball means the holder lost it, which is not shielding
+            let lost_to_defender =
+                event.defenders.contains(&holder.id) && holder_ball_dist < self.inner_rad;
+            return self.end_event(!lost_to_defender);
+        }
+
+        let moved = DribbleDetector::distance(self.anchor, (holder.x, holder.y));
+        if holder_ball_dist > self.inner_rad || moved > self.max_movement {
+            return self.end_event(true);
+        }
+
+        let (defenders, _inner_defenders) =
+            DribbleDetector::calc_defenders(&frame.players, holder, self.outer_rad, self.inner_rad);
+        if defenders.is_empty() {
+            return self.end_event(true);
+        }
+
+        event.end_frame = frame.frame_number;
+        event.duration_frames = event.end_frame - event.start_frame + 1;
+        for defender in defenders {
+            if !event.defenders.contains(&defender) {
+                event.defenders.push(defender);
+            }
+        }
+        None
+    }
+
+    /// Flushes the active event at the end of a video.
+    pub fn finish(&mut self) -> Option<ShieldingEvent> {
+        self.end_event(true)
+    }
+
+    fn try_start_event(&mut self, frame: &DribbleFrame, holder: &Player, holder_ball_dist: f64) {
+        if holder_ball_dist > self.inner_rad {
+            return;
+        }
+
+        let (defenders, _inner_defenders) =
+            DribbleDetector::calc_defenders(&frame.players, holder, self.outer_rad, self.inner_rad);
+        if defenders.is_empty() {
+            return;
+        }
+
+        self.anchor = (holder.x, holder.y);
+        self.active_event = Some(ShieldingEvent {
+            file_name: self.video_name.clone(),
+            possession_holder: holder.id,
+            start_frame: frame.frame_number,
+            end_frame: frame.frame_number,
+            duration_frames: 1,
+            defenders,
+        });
+    }
+
+    /// Ends the active event. It is only returned if `keep` is set (the ball was not lost)
+    /// and it lasted at least `min_frames`.
+    fn end_event(&mut self, keep: bool) -> Option<ShieldingEvent> {
+        let event = self.active_event.take()?;
+        if keep && event.duration_frames >= self.min_frames {
+            Some(event)
+        } else {
+            None
+        }
+    }
+
+    fn closest_to_ball(players: &[Player], ball: (f64, f64)) -> Option<&Player> {
+        players.iter().min_by(|p1, p2| {
+            let p1_dis = DribbleDetector::distance((p1.x, p1.y), ball);
+            let p2_dis = DribbleDetector::distance((p2.x, p2.y), ball);
+            p1_dis.partial_cmp(&p2_dis).unwrap()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dribbling_detection::dribble_models::Ball;
+
+    fn player(id: u32, x: f64, y: f64) -> Player {
+        Player {
+            id,
+            x,
+            y,
+            velocity: (0.0, 0.0),
+            acceleration: (0.0, 0.0),
+            within_inner_rad: false,
+            team: None,
+        }
+    }
+
+    fn frame(frame_number: u32, holder_x: f64, defender: Option<Player>) -> DribbleFrame {
+        let mut players = vec![player(1, holder_x, 0.0)];
+        players.extend(defender);
+        DribbleFrame {
+            frame_number,
+            players,
+            ball: Ball::new(holder_x + 0.1, 0.0),
+        }
+    }
+
+    #[test]
+    fn test_stationary_holder_under_pressure_is_shielding() {
+        let mut detector = ShieldingDetector::new("video".to_string(), 1.0, 3.0, 5, 1.0);
+
+        for i in 0..10 {
+            assert!(detector
+                .process_frame(&frame(i, 0.0, Some(player(2, 2.0, 0.0))))
+                .is_none());
+        }
+        let event = detector.process_frame(&frame(10, 0.0, None)).unwrap();
+
+        assert_eq!(event.possession_holder, 1);
+        assert_eq!(event.start_frame, 0);
+        assert_eq!(event.end_frame, 9);
+        assert_eq!(event.duration_frames, 10);
+        assert_eq!(event.defenders, vec![2]);
+    }
+
+    #[test]
+    fn test_moving_holder_or_short_pressure_is_not_shielding() {
+        let mut detector = ShieldingDetector::new("video".to_string(), 1.0, 3.0, 5, 1.0);
+        for i in 0..3 {
+            detector.process_frame(&frame(i, 0.0, Some(player(2, 2.0, 0.0))));
+        }
+        assert!(detector.process_frame(&frame(3, 0.0, None)).is_none());
+
+        let mut detector = ShieldingDetector::new("video".to_string(), 1.0, 3.0, 2, 1.0);
+        detector.process_frame(&frame(0, 0.0, Some(player(2, 2.0, 0.0))));
+        // Holder moves 0.6 per frame, so the event ends after the second frame
+        for i in 1..10 {
+            let x = i as f64 * 0.6;
+            let event = detector.process_frame(&frame(i, x, Some(player(2, x + 2.0, 0.0))));
+            if i == 2 {
+                assert_eq!(event.unwrap().duration_frames, 2);
+            }
+        }
+    }
+}
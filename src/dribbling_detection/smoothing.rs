@@ -0,0 +1,375 @@
+use super::dribble_models::{Ball, Player};
+use crate::config::{SmoothingConfig, SmoothingFilterConfig};
+use std::collections::{HashMap, VecDeque};
+
+/// Number of frames a signal may be missing before its smoother starts over
+pub const MAX_GAP: u32 = 10;
+
+/// A causal filter for a 2d signal: every value is smoothed using only the values before it,
+/// so it can be applied frame by frame while processing a video.
+pub trait Smoother: Send {
+    /// Adds the next value of the signal and returns its smoothed value.
+    fn smooth(&mut self, value: (f64, f64)) -> (f64, f64);
+
+    /// Forgets all earlier values, e.g. after the signal was missing for a while.
+    fn reset(&mut self);
+}
+
+/// Builds the smoother selected by `config`.
+pub fn build_smoother(config: &SmoothingFilterConfig) -> Box<dyn Smoother> {
+    match *config {
+        SmoothingFilterConfig::None => Box::new(NoSmoothing),
+        SmoothingFilterConfig::MovingAverage { window } => Box::new(MovingAverage::new(window)),
+        SmoothingFilterConfig::Ema { alpha } => Box::new(Ema::new(alpha)),
+        SmoothingFilterConfig::Kalman {
+            process_noise,
+            measurement_noise,
+        } => Box::new(Kalman::new(process_noise, measurement_noise)),
+        SmoothingFilterConfig::SavitzkyGolay { window, order } => {
+            Box::new(SavitzkyGolay::new(window, order))
+        }
+    }
+}
+
+/// Passes values through unchanged.
+pub struct NoSmoothing;
+
+impl Smoother for NoSmoothing {
+    fn smooth(&mut self, value: (f64, f64)) -> (f64, f64) {
+        value
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// Mean of the last `window` values.
+pub struct MovingAverage {
+    window: usize,
+    values: VecDeque<(f64, f64)>,
+}
+
+impl MovingAverage {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            values: VecDeque::new(),
+        }
+    }
+}
+
+impl Smoother for MovingAverage {
+    fn smooth(&mut self, value: (f64, f64)) -> (f64, f64) {
+        if self.values.len() == self.window {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+
+        let n = self.values.len() as f64;
+        let (sum_x, sum_y) = self
+            .values
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        (sum_x / n, sum_y / n)
+    }
+
+    fn reset(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// Exponential moving average. A higher `alpha` follows the signal more closely.
+pub struct Ema {
+    alpha: f64,
+    state: Option<(f64, f64)>,
+}
+
+impl Ema {
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha: alpha.clamp(0.0, 1.0),
+            state: None,
+        }
+    }
+}
+
+impl Smoother for Ema {
+    fn smooth(&mut self, value: (f64, f64)) -> (f64, f64) {
+        let smoothed = match self.state {
+            Some((x, y)) => (
+                x + self.alpha * (value.0 - x),
+                y + self.alpha * (value.1 - y),
+            ),
+            None => value,
+        };
+        self.state = Some(smoothed);
+        smoothed
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// Constant velocity Kalman filter, run independently on each axis.
+pub struct Kalman {
+    process_noise: f64,
+    measurement_noise: f64,
+    axes: Option<[KalmanAxis; 2]>,
+}
+
+/// Position/velocity state and its covariance for a single axis.
+#[derive(Clone, Copy)]
+struct KalmanAxis {
+    position: f64,
+    velocity: f64,
+    covariance: [[f64; 2]; 2],
+}
+
+impl KalmanAxis {
+    fn new(position: f64, measurement_noise: f64) -> Self {
+        Self {
+            position,
+            velocity: 0.0,
+            covariance: [[measurement_noise, 0.0], [0.0, 1.0]],
+        }
+    }
+
+    fn update(&mut self, measurement: f64, process_noise: f64, measurement_noise: f64) -> f64 {
+        // Predict one frame ahead
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let position = self.position + self.velocity;
+        let p00 = p00 + p01 + p10 + p11 + process_noise;
+        let p01 = p01 + p11;
+        let p10 = p10 + p11;
+        let p11 = p11 + process_noise;
+
+        // Correct with the measured position
+        let innovation = measurement - position;
+        let s = p00 + measurement_noise;
+        let (k0, k1) = (p00 / s, p10 / s);
+        self.position = position + k0 * innovation;
+        self.velocity += k1 * innovation;
+        self.covariance = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+        self.position
+    }
+}
+
+impl Kalman {
+    pub fn new(process_noise: f64, measurement_noise: f64) -> Self {
+        Self {
+            process_noise,
+            measurement_noise,
+            axes: None,
+        }
+    }
+}
+
+impl Smoother for Kalman {
+    fn smooth(&mut self, value: (f64, f64)) -> (f64, f64) {
+        let Some([x, y]) = self.axes.as_mut() else {
+            self.axes = Some([
+                KalmanAxis::new(value.0, self.measurement_noise),
+                KalmanAxis::new(value.1, self.measurement_noise),
+            ]);
+            return value;
+        };
+        (
+            x.update(value.0, self.process_noise, self.measurement_noise),
+            y.update(value.1, self.process_noise, self.measurement_noise),
+        )
+    }
+
+    fn reset(&mut self) {
+        self.axes = None;
+    }
+}
+
+/// Fits a polynomial of degree `order` to the last `window` values and evaluates it at the
+/// newest one. Keeps the shape of fast movements better than an average of the same window.
+pub struct SavitzkyGolay {
+    window: usize,
+    order: usize,
+    values: VecDeque<(f64, f64)>,
+}
+
+impl SavitzkyGolay {
+    pub fn new(window: usize, order: usize) -> Self {
+        Self {
+            window: window.max(1),
+            order,
+            values: VecDeque::new(),
+        }
+    }
+}
+
+impl Smoother for SavitzkyGolay {
+    fn smooth(&mut self, value: (f64, f64)) -> (f64, f64) {
+        if self.values.len() == self.window {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+
+        let order = self.order.min(self.values.len() - 1);
+        let weights = savitzky_golay_weights(self.values.len(), order);
+        self.values
+            .iter()
+            .zip(&weights)
+            .fold((0.0, 0.0), |(sx, sy), ((x, y), w)| (sx + w * x, sy + w * y))
+    }
+
+    fn reset(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// Weights of `n` values for the least squares polynomial fit of degree `order`, evaluated at
+/// the last value. Found by solving the normal equations `(AᵀA)c = Aᵀy` for the constant term,
+/// where the last value is at t = 0.
+pub fn savitzky_golay_weights(n: usize, order: usize) -> Vec<f64> {
+    let m = order + 1;
+    let ts: Vec<f64> = (0..n).map(|i| i as f64 - (n - 1) as f64).collect();
+
+    // Augmented [AᵀA | Aᵀ]
+    let mut rows: Vec<Vec<f64>> = (0..m)
+        .map(|j| {
+            let mut row: Vec<f64> = (0..m)
+                .map(|k| ts.iter().map(|t| t.powi((j + k) as i32)).sum())
+                .collect();
+            row.extend(ts.iter().map(|t| t.powi(j as i32)));
+            row
+        })
+        .collect();
+
+    // Gauss-Jordan elimination with partial pivoting
+    for col in 0..m {
+        let pivot = (col..m)
+            .max_by(|&a, &b| rows[a][col].abs().partial_cmp(&rows[b][col].abs()).unwrap())
+            .unwrap();
+        rows.swap(col, pivot);
+        let divisor = rows[col][col];
+        if divisor.abs() < f64::EPSILON {
+            continue;
+        }
+        for value in rows[col].iter_mut() {
+            *value /= divisor;
+        }
+        for row in 0..m {
+            if row != col {
+                let factor = rows[row][col];
+                let pivot_row = rows[col].clone();
+                for (value, pivot_value) in rows[row].iter_mut().zip(pivot_row) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+
+    rows[0][m..].to_vec()
+}
+
+/// Smoothing state of a single player track.
+struct TrackState {
+    position: Box<dyn Smoother>,
+    /// Frame of the last time the player was seen
+    last_frame: u32,
+}
+
+/// Applies the configured smoothers to the ball and player positions of each frame. The
+/// velocities are computed from the smoothed positions afterwards, see
+/// [`super::create_dribble_models::Kinematics`], which applies the `velocity` filter.
+pub struct FrameSmoother {
+    config: SmoothingConfig,
+    ball: Box<dyn Smoother>,
+    last_ball_frame: Option<u32>,
+    tracks: HashMap<u32, TrackState>,
+}
+
+impl FrameSmoother {
+    pub fn new(config: &SmoothingConfig) -> Self {
+        Self {
+            config: config.clone(),
+            ball: build_smoother(&config.ball),
+            last_ball_frame: None,
+            tracks: HashMap::new(),
+        }
+    }
+
+    pub fn process_frame(
+        &mut self,
+        frame_number: u32,
+        ball: Option<Ball>,
+        players: &mut [Player],
+    ) -> Option<Ball> {
+        let ball = ball.map(|ball| {
+            if self
+                .last_ball_frame
+                .is_some_and(|last| frame_number.saturating_sub(last) > MAX_GAP)
+            {
+                self.ball.reset();
+            }
+            self.last_ball_frame = Some(frame_number);
+            let (x, y) = self.ball.smooth((ball.x, ball.y));
+            Ball { x, y, ..ball }
+        });
+
+        // Players without a track id can not be followed between frames
+        for player in players.iter_mut().filter(|p| p.id != u32::MAX) {
+            let track = self.tracks.entry(player.id).or_insert_with(|| TrackState {
+                position: build_smoother(&self.config.players),
+                last_frame: frame_number,
+            });
+            if frame_number.saturating_sub(track.last_frame) > MAX_GAP {
+                track.position.reset();
+            }
+
+            let (x, y) = track.position.smooth((player.x, player.y));
+            player.x = x;
+            player.y = y;
+            track.last_frame = frame_number;
+        }
+
+        ball
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(smoother: &mut dyn Smoother, values: &[f64]) -> Vec<f64> {
+        values.iter().map(|&v| smoother.smooth((v, -v)).0).collect()
+    }
+
+    #[test]
+    fn test_smoothers() {
+        assert_eq!(run(&mut NoSmoothing, &[1.0, 5.0]), vec![1.0, 5.0]);
+        assert_eq!(
+            run(&mut MovingAverage::new(2), &[1.0, 3.0, 5.0]),
+            vec![1.0, 2.0, 4.0]
+        );
+        assert_eq!(run(&mut Ema::new(0.5), &[0.0, 4.0, 4.0]), vec![0.0, 2.0, 3.0]);
+
+        // A line is fitted exactly by a first order polynomial
+        let smoothed = run(&mut SavitzkyGolay::new(5, 1), &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        for (value, expected) in smoothed.iter().zip([0.0, 1.0, 2.0, 3.0, 4.0, 5.0]) {
+            assert!((value - expected).abs() < 1e-9);
+        }
+
+        // The Kalman filter converges towards a constant signal
+        let smoothed = run(&mut Kalman::new(0.01, 1.0), &[0.0, 10.0, 10.0, 10.0, 10.0, 10.0]);
+        assert!(smoothed[1] < 10.0);
+        assert!((smoothed[5] - 10.0).abs() < 1.5);
+    }
+
+    #[test]
+    fn test_savitzky_golay_weights() {
+        // Order 0 is a plain average
+        assert_eq!(savitzky_golay_weights(4, 0), vec![0.25; 4]);
+
+        let weights = savitzky_golay_weights(5, 2);
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
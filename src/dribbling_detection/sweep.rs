@@ -0,0 +1,428 @@
+use crate::config::{Config, DribblingDetectionConfig, VideoMode};
+use crate::data::dataset::load_event_intervals;
+use crate::data::models::{ClipTiming, VideoData};
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use crate::evaluation::{match_events, BoundaryErrors, BoundaryOffsets, EventRange};
+use crate::pipeline::{process_video, CancellationToken};
+use crate::progress::ProgressSender;
+use crate::watchdog::Watchdog;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::ThreadPool;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the file the results of a sweep are written to, in the output directory
+pub const SWEEP_FILE: &str = "sweep.csv";
+
+/// Values of a swept parameter, either listed or as a range, e.g. `[0.6, 0.8]` or
+/// `{ start = 0.6, end = 1.2, step = 0.2 }`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SweepValues {
+    List(Vec<f64>),
+    /// From `start` to `end` (inclusive) in steps of `step`
+    Range { start: f64, end: f64, step: f64 },
+}
+
+impl SweepValues {
+    pub fn values(&self) -> Vec<f64> {
+        match self {
+            Self::List(values) => values.clone(),
+            Self::Range { start, end, step } if *step > 0.0 => {
+                // The epsilon keeps floating point errors from dropping the end of the range
+                let num_steps = ((end - start) / step + 1e-9).floor().max(0.0) as usize;
+                (0..=num_steps).map(|i| start + step * i as f64).collect()
+            }
+            Self::Range { start, .. } => vec![*start],
+        }
+    }
+}
+
+/// The `[sweep]` section. Parameters without values keep their `[dribbling_detection]` value.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SweepConfig {
+    pub inner_radius: Option<SweepValues>,
+    pub outer_radius: Option<SweepValues>,
+    pub inner_threshold: Option<SweepValues>,
+    pub outer_threshold: Option<SweepValues>,
+    pub outer_in_threshold: Option<SweepValues>,
+    pub outer_out_threshold: Option<SweepValues>,
+    /// `dribble_events.json` file with the ground truth events. If set, the precision and
+    /// recall of every combination are computed.
+    pub ground_truth: Option<String>,
+}
+
+/// A combination of the swept parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweepParams {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub inner_threshold: u32,
+    pub outer_threshold: u32,
+    pub outer_in_threshold: u32,
+    pub outer_out_threshold: u32,
+}
+
+impl SweepParams {
+    fn from_config(config: &DribblingDetectionConfig) -> Self {
+        Self {
+            inner_radius: config.inner_radius,
+            outer_radius: config.outer_radius,
+            inner_threshold: config.inner_threshold,
+            outer_threshold: config.outer_threshold,
+            outer_in_threshold: config.outer_in_threshold,
+            outer_out_threshold: config.outer_out_threshold,
+        }
+    }
+
+    fn apply(&self, config: &mut DribblingDetectionConfig) {
+        config.inner_radius = self.inner_radius;
+        config.outer_radius = self.outer_radius;
+        config.inner_threshold = self.inner_threshold;
+        config.outer_threshold = self.outer_threshold;
+        config.outer_in_threshold = self.outer_in_threshold;
+        config.outer_out_threshold = self.outer_out_threshold;
+    }
+}
+
+/// Every combination of the values in `sweep`, with the values of `base` for the parameters
+/// that are not swept.
+pub fn parameter_grid(sweep: &SweepConfig, base: &DribblingDetectionConfig) -> Vec<SweepParams> {
+    let mut grid = vec![SweepParams::from_config(base)];
+    let axes: [(&Option<SweepValues>, fn(&mut SweepParams, f64)); 6] = [
+        (&sweep.inner_radius, |p, v| p.inner_radius = v),
+        (&sweep.outer_radius, |p, v| p.outer_radius = v),
+        (&sweep.inner_threshold, |p, v| p.inner_threshold = v.round() as u32),
+        (&sweep.outer_threshold, |p, v| p.outer_threshold = v.round() as u32),
+        (&sweep.outer_in_threshold, |p, v| p.outer_in_threshold = v.round() as u32),
+        (&sweep.outer_out_threshold, |p, v| p.outer_out_threshold = v.round() as u32),
+    ];
+    for (values, set) in axes {
+        let Some(values) = values.as_ref().map(SweepValues::values) else {
+            continue;
+        };
+        grid = grid
+            .iter()
+            .flat_map(|params| {
+                values.iter().map(move |&value| {
+                    let mut params = *params;
+                    set(&mut params, value);
+                    params
+                })
+            })
+            .collect();
+    }
+    grid
+}
+
+/// Detected events matched against the ground truth. A detected event is a true positive if
+/// it matches a ground truth event by temporal IoU, see [`crate::evaluation`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventMatches {
+    pub true_positives: usize,
+    pub num_detected: usize,
+    pub num_ground_truth: usize,
+}
+
+impl EventMatches {
+    /// Matches the `(start, end)` frames of the `detected` events of a video with those of its
+    /// `ground_truth` events, like the evaluation does. Returns the counts and the matched
+    /// `(detected, ground truth)` pairs.
+    pub fn matched(
+        detected: &[(u32, u32)],
+        ground_truth: &[(u32, u32)],
+        iou_threshold: f64,
+    ) -> (Self, Vec<((u32, u32), (u32, u32))>) {
+        // Matches count regardless of the class
+        let ranges = |intervals: &[(u32, u32)]| -> Vec<EventRange> {
+            intervals
+                .iter()
+                .map(|&(start_frame, end_frame)| EventRange {
+                    start_frame,
+                    end_frame,
+                    class: "dribble",
+                    confidence: None,
+                })
+                .collect()
+        };
+        let pairs: Vec<((u32, u32), (u32, u32))> =
+            match_events(&ranges(detected), &ranges(ground_truth), iou_threshold)
+                .into_iter()
+                .map(|(i, j, _)| (detected[i], ground_truth[j]))
+                .collect();
+        let matches = Self {
+            true_positives: pairs.len(),
+            num_detected: detected.len(),
+            num_ground_truth: ground_truth.len(),
+        };
+        (matches, pairs)
+    }
+
+    pub fn add(&mut self, other: EventMatches) {
+        self.true_positives += other.true_positives;
+        self.num_detected += other.num_detected;
+        self.num_ground_truth += other.num_ground_truth;
+    }
+
+    pub fn precision(&self) -> f64 {
+        ratio(self.true_positives, self.num_detected)
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.true_positives, self.num_ground_truth)
+    }
+
+    pub fn f1(&self) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// Event counts of a combination, and its matches and their boundary offsets if there is a
+/// ground truth.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepResult {
+    pub params: SweepParams,
+    pub num_dribbles: usize,
+    pub num_tackles: usize,
+    pub matches: Option<EventMatches>,
+    /// `None` without a ground truth or without matches
+    pub boundaries: Option<BoundaryErrors>,
+}
+
+/// Runs detection on `videos` for every combination of the `[sweep]` parameters. The
+/// annotations of each video are parsed once and reused for all combinations, and videos
+/// are processed in parallel on `pool`. Frames are never read or drawn. Fails before any
+/// detection if the `ground_truth` can not be read.
+pub fn run_sweep(
+    videos: &[VideoData],
+    config: &Config,
+    sweep: &SweepConfig,
+    pool: &ThreadPool,
+    cancel: &CancellationToken,
+    progress: &ProgressSender,
+) -> io::Result<Vec<SweepResult>> {
+    let grid = parameter_grid(sweep, &config.dribbling_detection);
+    let ground_truth = sweep
+        .ground_truth
+        .as_deref()
+        .map(|path| {
+            load_event_intervals(path).ok_or_else(|| {
+                io::Error::other(format!("could not read the ground truth {}", path))
+            })
+        })
+        .transpose()?;
+    let evaluation = config.evaluation.clone().unwrap_or_default();
+
+    let mut config = config.clone();
+    config.general.video_mode = VideoMode::None;
+    config.general.review_mode = Some(false);
+    let configs: Vec<Config> = grid
+        .iter()
+        .map(|params| {
+            let mut config = config.clone();
+            params.apply(&mut config.dribbling_detection);
+            config
+        })
+        .collect();
+
+    progress.videos_queued(videos.len() * grid.len());
+    let watchdog = Watchdog::disabled();
+    // Frame rate and events of every combination, per video
+    let events: Vec<(String, f64, Vec<Vec<DribbleEvent>>)> = pool.install(|| {
+        videos
+            .par_iter()
+            .filter_map(|video_data| {
+                let mut video_data = video_data.clone();
+                if let Err(e) = video_data.load_annotations() {
+                    eprintln!("Failed to load annotations for {:?}: {}", video_data.dir_path, e);
+                    return None;
+                }
+                let name = video_data.name();
+                let runs = configs
+                    .iter()
+                    .map(|config| {
+                        let video_progress =
+                            progress.start_video(&name, video_data.image_paths.len());
+                        process_video(
+                            &video_data,
+                            config,
+                            cancel,
+                            &None,
+                            &Mutex::new(None),
+                            &video_progress,
+                            &watchdog.watch(&name),
+                        )
+                        .map(|processed| processed.dribble_events)
+                        .unwrap_or_default()
+                    })
+                    .collect();
+                let frame_rate = ClipTiming::new(&video_data.labels.info)
+                    .frame_rate
+                    .unwrap_or_else(|| evaluation.frame_rate());
+                Some((name, frame_rate, runs))
+            })
+            .collect()
+    });
+
+    Ok(grid
+        .into_iter()
+        .enumerate()
+        .map(|(i, params)| {
+            let mut result = SweepResult {
+                params,
+                num_dribbles: 0,
+                num_tackles: 0,
+                matches: ground_truth.as_ref().map(|_| EventMatches::default()),
+                boundaries: None,
+            };
+            let mut offsets = BoundaryOffsets::default();
+            for (name, frame_rate, runs) in &events {
+                let video_events = &runs[i];
+                let num_tackles = video_events.iter().filter(|e| e.detected_tackle).count();
+                result.num_tackles += num_tackles;
+                result.num_dribbles += video_events.len() - num_tackles;
+                if let (Some(matches), Some(ground_truth)) = (&mut result.matches, &ground_truth) {
+                    let detected: Vec<(u32, u32)> = video_events
+                        .iter()
+                        .map(|e| (e.start_frame, e.end_frame.unwrap_or(e.start_frame)))
+                        .collect();
+                    let expected = ground_truth.get(name).map_or(&[][..], Vec::as_slice);
+                    let (video_matches, pairs) =
+                        EventMatches::matched(&detected, expected, evaluation.iou_threshold());
+                    matches.add(video_matches);
+                    for (detected, expected) in pairs {
+                        offsets.push(detected, expected, *frame_rate);
+                    }
+                }
+            }
+            result.boundaries = offsets.errors(evaluation.boundary_tolerance_secs());
+            result
+        })
+        .collect())
+}
+
+/// Writes one CSV row per combination to [`SWEEP_FILE`] in `output_path`. The precision,
+/// recall, F1 and boundary offset columns are only written if there is a ground truth; the
+/// offsets are empty for combinations without matches.
+pub fn write_sweep_csv(output_path: &Path, results: &[SweepResult]) -> io::Result<()> {
+    let with_matches = results.iter().any(|r| r.matches.is_some());
+    let mut csv = String::from(
+        "inner_radius,outer_radius,inner_threshold,outer_threshold,outer_in_threshold,\
+         outer_out_threshold,dribbles,tackles",
+    );
+    if with_matches {
+        csv.push_str(
+            ",true_positives,ground_truth,precision,recall,f1,\
+             mean_start_offset,median_start_offset,mean_end_offset,median_end_offset,\
+             mean_start_offset_secs,median_start_offset_secs,mean_end_offset_secs,\
+             median_end_offset_secs,within_tolerance",
+        );
+    }
+    csv.push('\n');
+
+    for result in results {
+        let p = &result.params;
+        let _ = write!(
+            csv,
+            "{},{},{},{},{},{},{},{}",
+            p.inner_radius,
+            p.outer_radius,
+            p.inner_threshold,
+            p.outer_threshold,
+            p.outer_in_threshold,
+            p.outer_out_threshold,
+            result.num_dribbles,
+            result.num_tackles
+        );
+        if let Some(m) = result.matches.filter(|_| with_matches) {
+            let _ = write!(
+                csv,
+                ",{},{},{:.4},{:.4},{:.4}",
+                m.true_positives,
+                m.num_ground_truth,
+                m.precision(),
+                m.recall(),
+                m.f1()
+            );
+            match result.boundaries {
+                Some(b) => {
+                    let _ = write!(
+                        csv,
+                        ",{:.2},{:.2},{:.2},{:.2},{:.3},{:.3},{:.3},{:.3},{:.4}",
+                        b.start.mean_frames,
+                        b.start.median_frames,
+                        b.end.mean_frames,
+                        b.end.median_frames,
+                        b.start.mean_seconds,
+                        b.start.median_seconds,
+                        b.end.mean_seconds,
+                        b.end.median_seconds,
+                        b.within_tolerance
+                    );
+                }
+                None => csv.push_str(",,,,,,,,,"),
+            }
+        }
+        csv.push('\n');
+    }
+
+    fs::create_dir_all(output_path)?;
+    fs::write(output_path.join(SWEEP_FILE), csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameter_grid_and_matches() {
+        let base: DribblingDetectionConfig = toml::from_str(
+            "use_2d = false\nouter_threshold = 3\ninner_threshold = 4\nframe_skip = 1\n\
+             min_duration = 1.3\ninner_radius = 0.8\nouter_radius = 2.7\n\
+             ignore_person_classes = true\nignore_teams = true\n\
+             outer_in_threshold = 2\nouter_out_threshold = 8\n",
+        )
+        .unwrap();
+        let sweep: SweepConfig = toml::from_str(
+            "inner_radius = { start = 0.6, end = 1.0, step = 0.2 }\ninner_threshold = [3, 5]\n",
+        )
+        .unwrap();
+
+        let grid = parameter_grid(&sweep, &base);
+        assert_eq!(grid.len(), 6);
+        assert!((grid[5].inner_radius - 1.0).abs() < 1e-9);
+        assert_eq!(grid[5].inner_threshold, 5);
+        // Parameters that are not swept keep their config value
+        assert!(grid.iter().all(|p| p.outer_radius == 2.7 && p.outer_threshold == 3));
+
+        // Both detections overlap the first event, but (15, 25) has the higher IoU of 8 / 26
+        let detected = [(10, 20), (15, 25), (100, 120)];
+        let ground_truth = [(18, 40), (200, 210)];
+        let (matches, pairs) = EventMatches::matched(&detected, &ground_truth, 0.3);
+        assert_eq!(matches.true_positives, 1);
+        assert!((matches.precision() - 1.0 / 3.0).abs() < 1e-9);
+        assert!((matches.recall() - 0.5).abs() < 1e-9);
+        assert_eq!(pairs, vec![((15, 25), (18, 40))]);
+        let (matches, _) = EventMatches::matched(&detected, &ground_truth, 0.5);
+        assert_eq!(matches.true_positives, 0);
+    }
+}
@@ -0,0 +1,14 @@
+//! A short scene shared by the tests of the interfaces that feed the detector frame by frame.
+
+/// Id of the player holding the ball
+pub const HOLDER: u32 = 7;
+
+/// Players as id and x position on the line y = 0: the holder with a defender close by
+pub const PLAYERS: [(u32, f64); 2] = [(HOLDER, 0.0), (9, 2.0)];
+
+/// Frame numbers and x positions of the ball. The holder loses the ball in the last frame
+/// with the defender still close.
+pub const LOST_BALL: [(u32, f64); 5] = [(1, 0.0), (2, 0.0), (3, 0.0), (4, 0.0), (5, 1.5)];
+
+/// Start and end frame of the dribble the detector reports for [`LOST_BALL`]
+pub const DRIBBLE_FRAMES: (u32, u32) = (2, 5);
@@ -0,0 +1,184 @@
+//! `--dry-run`: what a run would process and roughly how long it would take and how much it
+//! would write, from the labels alone. No frames are decoded, so it is cheap enough to run
+//! before every long cluster job.
+
+use crate::config::{Config, VideoMode};
+use crate::data::dataset::Dataset;
+use crate::data::models::VideoData;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Frames one core processes per second if `general.dry_run_fps` is not set
+pub const DEFAULT_DRY_RUN_FPS: f64 = 25.0;
+
+/// Approximate size of an mp4 frame per pixel of the scaled frame, with the minimap
+const VIDEO_BYTES_PER_PIXEL: f64 = 0.08;
+
+/// Approximate size of a transparent overlay PNG per pixel of the scaled frame
+const LAYER_BYTES_PER_PIXEL: f64 = 0.15;
+
+/// Size of the frames if a sequence has no image entries to read it from
+const DEFAULT_FRAME_SIZE: (u32, u32) = (1920, 1080);
+
+/// A sequence that would be processed.
+#[derive(Clone, Debug, Serialize)]
+pub struct SequencePlan {
+    pub name: String,
+    pub num_frames: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SequencePlan {
+    pub fn from_video(video_data: &VideoData) -> Self {
+        let (width, height) = video_data
+            .labels
+            .images
+            .first()
+            .map_or(DEFAULT_FRAME_SIZE, |image| (image.width, image.height));
+        Self {
+            name: video_data.labels.info.name.clone(),
+            num_frames: video_data.image_paths.len(),
+            width,
+            height,
+        }
+    }
+
+    /// Estimated number of bytes the video mode writes for this sequence. Only the modes
+    /// that write frames are counted, the event exports are small in comparison.
+    pub fn estimated_output_bytes(&self, config: &Config) -> u64 {
+        let bytes_per_pixel = match config.general.video_mode {
+            VideoMode::Download | VideoMode::RenderOnly => VIDEO_BYTES_PER_PIXEL,
+            VideoMode::Layers => LAYER_BYTES_PER_PIXEL,
+            VideoMode::Display | VideoMode::None => return 0,
+        };
+        let scale = config.visualization.scale_factor;
+        let pixels = self.width as f64 * scale * self.height as f64 * scale;
+        (pixels * bytes_per_pixel * self.num_frames as f64) as u64
+    }
+}
+
+/// The sequences of a subset that would be processed.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SubsetPlan {
+    pub subset: String,
+    pub sequences: Vec<SequencePlan>,
+    /// Sequences whose labels could not be read, which a run skips
+    pub num_unreadable_sequences: usize,
+}
+
+impl SubsetPlan {
+    pub fn num_frames(&self) -> usize {
+        self.sequences.iter().map(|s| s.num_frames).sum()
+    }
+
+    pub fn estimated_output_bytes(&self, config: &Config) -> u64 {
+        self.sequences
+            .iter()
+            .map(|s| s.estimated_output_bytes(config))
+            .sum()
+    }
+}
+
+impl Dataset {
+    /// Lists the sequences and frames `iter_subset` would yield, with the same filters and
+    /// `max_videos` limit, without reading any frames.
+    pub fn plan_subset(&self, subset: &str) -> SubsetPlan {
+        let max_videos = self.config.data.max_videos.unwrap_or(usize::MAX);
+        let mut plan = SubsetPlan {
+            subset: subset.to_string(),
+            ..Default::default()
+        };
+        for seq_dir in self.filtered_sequence_dirs(subset) {
+            if plan.sequences.len() >= max_videos {
+                break;
+            }
+            match self.load_sequence(&seq_dir) {
+                Ok(video_data) => plan.sequences.push(SequencePlan::from_video(&video_data)),
+                Err(_) => plan.num_unreadable_sequences += 1,
+            }
+        }
+        plan
+    }
+}
+
+/// Estimated time to process `num_frames` frames at `general.dry_run_fps` frames per second
+/// per core. Display mode runs on a single core.
+pub fn estimate_runtime(num_frames: usize, config: &Config) -> Duration {
+    let fps = config.general.dry_run_fps.unwrap_or(DEFAULT_DRY_RUN_FPS);
+    let cores = if config.general.video_mode == VideoMode::Display {
+        1
+    } else {
+        config.general.num_cores.max(1)
+    };
+    if fps <= 0.0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64(num_frames as f64 / (fps * cores as f64))
+}
+
+/// Formats a duration as e.g. `2h 05m 09s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+    }
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `1.5 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates() {
+        let mut config = Config::default();
+        config.general.num_cores = 4;
+        config.general.dry_run_fps = Some(10.0);
+        config.general.video_mode = VideoMode::Download;
+        config.visualization.scale_factor = 0.5;
+        let sequence = SequencePlan {
+            name: "SNGS-021".to_string(),
+            num_frames: 750,
+            width: 1920,
+            height: 1080,
+        };
+
+        assert_eq!(
+            estimate_runtime(750, &config),
+            Duration::from_secs_f64(18.75)
+        );
+        let bytes = sequence.estimated_output_bytes(&config);
+        assert_eq!(
+            bytes,
+            (960.0 * 540.0 * VIDEO_BYTES_PER_PIXEL * 750.0) as u64
+        );
+
+        // Display mode runs on one core and writes no frames
+        config.general.video_mode = VideoMode::Display;
+        assert_eq!(estimate_runtime(750, &config), Duration::from_secs(75));
+        assert_eq!(sequence.estimated_output_bytes(&config), 0);
+
+        assert_eq!(format_duration(Duration::from_secs(7509)), "2h 05m 09s");
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 * 1024 * 1024 / 2), "1.5 MiB");
+    }
+}
@@ -0,0 +1,681 @@
+//! `evaluation.json`: the detected events compared with ground truth events in the format of
+//! `dribble_events.json`. A detected and a ground truth event of the same video match if their
+//! temporal IoU (the frames both cover over the frames either covers) is at least the
+//! configured threshold; every event matches at most once, the pairs with the highest IoU
+//! first. Matches count for detection regardless of the class, and the classes of the matched
+//! pairs make up the confusion between dribbles and tackles.
+//!
+//! The boundaries of the matched pairs are compared as well: the start and end offsets of the
+//! detections (detected minus ground truth frame, so negative offsets are early) in frames and
+//! in seconds, and the share of matches with both boundaries within a tolerance.
+
+use crate::config::{Config, DEFAULT_FRAME_RATE};
+use crate::data::models::{DribbleEventsExport, ExportInfo, VideoData};
+use crate::dribbling_detection::sweep::EventMatches;
+use crate::utils::event_clips::{render_clips, DEFAULT_EVENT_CLIP_PADDING};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the evaluation in the output directory
+pub const EVALUATION_FILE: &str = "evaluation.json";
+/// Directory in the output directory the clips of the errors are written to
+pub const ERROR_CLIPS_DIR: &str = "evaluation_errors";
+/// Minimum IoU of a match if `iou_threshold` is not set
+pub const DEFAULT_IOU_THRESHOLD: f64 = 0.5;
+/// Largest offset of a boundary within tolerance if `boundary_tolerance_secs` is not set
+pub const DEFAULT_BOUNDARY_TOLERANCE_SECS: f64 = 1.0;
+/// Class of the confusion matrix for events without a match
+const UNMATCHED: &str = "none";
+
+/// The `[evaluation]` section.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EvaluationConfig {
+    /// `dribble_events.json` file with the ground truth events. If set, detection runs are
+    /// evaluated against it when they finish.
+    pub ground_truth: Option<String>,
+    /// Minimum temporal IoU of a detected and a ground truth event to match them
+    pub iou_threshold: Option<f64>,
+    /// Also render a clip of every false positive and miss to [`ERROR_CLIPS_DIR`]
+    pub error_clips: bool,
+    /// Frame rate of the videos whose events have no `frame_rate`, to report the boundary
+    /// offsets in seconds
+    pub frame_rate: Option<f64>,
+    /// Largest start and end offset, in seconds, of a match with accurate boundaries
+    pub boundary_tolerance_secs: Option<f64>,
+    /// Also evaluate at every confidence of the detected events, see [`crate::pr_curve`]
+    pub pr_curve: bool,
+    /// Also draw the precision-recall curve as an image
+    pub pr_curve_image: bool,
+}
+
+impl EvaluationConfig {
+    pub fn iou_threshold(&self) -> f64 {
+        self.iou_threshold.unwrap_or(DEFAULT_IOU_THRESHOLD)
+    }
+
+    pub fn frame_rate(&self) -> f64 {
+        self.frame_rate.unwrap_or(DEFAULT_FRAME_RATE)
+    }
+
+    pub fn boundary_tolerance_secs(&self) -> f64 {
+        self.boundary_tolerance_secs
+            .unwrap_or(DEFAULT_BOUNDARY_TOLERANCE_SECS)
+    }
+}
+
+/// An event of a video, with both frames included.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct EventRange {
+    pub start_frame: u32,
+    pub end_frame: u32,
+    /// `dribble` or `tackle`
+    pub class: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+/// A detected event and the ground truth event it matched.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct MatchedEvent {
+    pub detected: EventRange,
+    pub ground_truth: EventRange,
+    pub iou: f64,
+}
+
+/// The events of every video of `export`.
+pub(crate) fn intervals(export: &DribbleEventsExport) -> HashMap<&str, Vec<EventRange>> {
+    export
+        .videos
+        .iter()
+        .map(|video| {
+            let intervals = video
+                .dribble_events
+                .iter()
+                .map(|event| EventRange {
+                    start_frame: event.start_frame,
+                    end_frame: event
+                        .end_frame
+                        .unwrap_or(event.start_frame)
+                        .max(event.start_frame),
+                    class: if event.detected_tackle {
+                        "tackle"
+                    } else {
+                        "dribble"
+                    },
+                    confidence: event.confidence,
+                })
+                .collect();
+            (video.video_id.as_str(), intervals)
+        })
+        .collect()
+}
+
+/// Frames covered by both intervals over the frames covered by either, both ends included.
+pub fn temporal_iou((a_start, a_end): (u32, u32), (b_start, b_end): (u32, u32)) -> f64 {
+    let intersection = (a_end.min(b_end) + 1).saturating_sub(a_start.max(b_start));
+    let union = (a_end + 1 - a_start) + (b_end + 1 - b_start) - intersection;
+    if union == 0 {
+        0.0
+    } else {
+        f64::from(intersection) / f64::from(union)
+    }
+}
+
+/// The matched `(detected, ground truth, IoU)` index pairs: one-to-one, by descending IoU, and
+/// only pairs with an IoU of at least `threshold`.
+pub(crate) fn match_events(
+    detected: &[EventRange],
+    ground_truth: &[EventRange],
+    threshold: f64,
+) -> Vec<(usize, usize, f64)> {
+    let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+    for (i, d) in detected.iter().enumerate() {
+        for (j, g) in ground_truth.iter().enumerate() {
+            let iou = temporal_iou((d.start_frame, d.end_frame), (g.start_frame, g.end_frame));
+            if iou > 0.0 && iou >= threshold {
+                candidates.push((iou, i, j));
+            }
+        }
+    }
+    // Ties keep the order of the events
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut detected_matched = vec![false; detected.len()];
+    let mut ground_truth_matched = vec![false; ground_truth.len()];
+    let mut matches = Vec::new();
+    for (iou, i, j) in candidates {
+        if !detected_matched[i] && !ground_truth_matched[j] {
+            detected_matched[i] = true;
+            ground_truth_matched[j] = true;
+            matches.push((i, j, iou));
+        }
+    }
+    matches
+}
+
+/// Start and end offsets of matched events, detected minus ground truth, in frames and seconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BoundaryOffsets {
+    start: Vec<(i64, f64)>,
+    end: Vec<(i64, f64)>,
+}
+
+impl BoundaryOffsets {
+    /// Adds the offsets of a matched pair of `(start, end)` frames.
+    pub fn push(&mut self, detected: (u32, u32), ground_truth: (u32, u32), frame_rate: f64) {
+        let offset = |detected: u32, expected: u32| {
+            let frames = i64::from(detected) - i64::from(expected);
+            (frames, frames as f64 / frame_rate)
+        };
+        self.start.push(offset(detected.0, ground_truth.0));
+        self.end.push(offset(detected.1, ground_truth.1));
+    }
+
+    pub fn append(&mut self, other: &BoundaryOffsets) {
+        self.start.extend_from_slice(&other.start);
+        self.end.extend_from_slice(&other.end);
+    }
+
+    /// Statistics of the offsets, `None` without matches.
+    pub fn errors(&self, tolerance_secs: f64) -> Option<BoundaryErrors> {
+        if self.start.is_empty() {
+            return None;
+        }
+        let within_tolerance = self
+            .start
+            .iter()
+            .zip(&self.end)
+            .filter(|(start, end)| start.1.abs() <= tolerance_secs && end.1.abs() <= tolerance_secs)
+            .count();
+        Some(BoundaryErrors {
+            num_matched: self.start.len(),
+            start: OffsetStats::new(&self.start),
+            end: OffsetStats::new(&self.end),
+            tolerance_secs,
+            within_tolerance: within_tolerance as f64 / self.start.len() as f64,
+        })
+    }
+}
+
+/// Mean, median and mean absolute value of the offsets of a boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct OffsetStats {
+    pub mean_frames: f64,
+    pub median_frames: f64,
+    pub mean_abs_frames: f64,
+    pub mean_seconds: f64,
+    pub median_seconds: f64,
+    pub mean_abs_seconds: f64,
+}
+
+impl OffsetStats {
+    fn new(offsets: &[(i64, f64)]) -> Self {
+        let frames: Vec<f64> = offsets.iter().map(|&(frames, _)| frames as f64).collect();
+        let seconds: Vec<f64> = offsets.iter().map(|&(_, seconds)| seconds).collect();
+        Self {
+            mean_frames: mean(&frames),
+            median_frames: median(&frames),
+            mean_abs_frames: mean_abs(&frames),
+            mean_seconds: mean(&seconds),
+            median_seconds: median(&seconds),
+            mean_abs_seconds: mean_abs(&seconds),
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn mean_abs(values: &[f64]) -> f64 {
+    values.iter().map(|value| value.abs()).sum::<f64>() / values.len() as f64
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut values = values.to_vec();
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+/// How far the boundaries of the matched events are off.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct BoundaryErrors {
+    pub num_matched: usize,
+    /// Offsets of the first frames, negative if the detection starts early
+    pub start: OffsetStats,
+    /// Offsets of the last frames, negative if the detection ends early
+    pub end: OffsetStats,
+    pub tolerance_secs: f64,
+    /// Share of the matches with both offsets within `tolerance_secs`
+    pub within_tolerance: f64,
+}
+
+/// Precision, recall and F1 of a set of matches.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Metrics {
+    pub true_positives: usize,
+    pub num_detected: usize,
+    pub num_ground_truth: usize,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+impl From<EventMatches> for Metrics {
+    fn from(matches: EventMatches) -> Self {
+        Self {
+            true_positives: matches.true_positives,
+            num_detected: matches.num_detected,
+            num_ground_truth: matches.num_ground_truth,
+            precision: matches.precision(),
+            recall: matches.recall(),
+            f1: matches.f1(),
+        }
+    }
+}
+
+/// The metrics of a video, and its events by outcome.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct VideoEvaluation {
+    pub video_id: String,
+    pub metrics: Metrics,
+    pub boundaries: Option<BoundaryErrors>,
+    pub matched: Vec<MatchedEvent>,
+    /// Detected events without a matching ground truth event
+    pub false_positives: Vec<EventRange>,
+    /// Ground truth events without a matching detection
+    pub misses: Vec<EventRange>,
+}
+
+impl VideoEvaluation {
+    pub fn has_errors(&self) -> bool {
+        !self.false_positives.is_empty() || !self.misses.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct EvaluationReport {
+    pub info: ExportInfo,
+    pub iou_threshold: f64,
+    /// Matches of events of any class
+    pub overall: Metrics,
+    /// Boundary offsets of the matches of events of any class
+    pub boundaries: Option<BoundaryErrors>,
+    /// Matches of events of the same class, by class
+    pub classes: BTreeMap<String, Metrics>,
+    /// Number of events by ground truth class and detected class. Missed events have the
+    /// detected class `none`, false detections the ground truth class `none`
+    pub confusion: BTreeMap<String, BTreeMap<String, usize>>,
+    pub videos: Vec<VideoEvaluation>,
+    /// Videos of the ground truth that are not in the detector output, which are left out
+    pub missing_videos: Vec<String>,
+}
+
+/// Compares the events of `detected` with those of `ground_truth`. Only the videos of the
+/// ground truth are evaluated, so a ground truth of part of the dataset can be used.
+pub fn evaluate(
+    detected: &DribbleEventsExport,
+    ground_truth: &DribbleEventsExport,
+    config: &EvaluationConfig,
+) -> EvaluationReport {
+    let iou_threshold = config.iou_threshold();
+    let tolerance_secs = config.boundary_tolerance_secs();
+    // Frame rates of the sequences, preferably those of the ground truth
+    let frame_rates: HashMap<&str, f64> = [detected, ground_truth]
+        .into_iter()
+        .flat_map(|export| &export.videos)
+        .filter_map(|video| Some((video.video_id.as_str(), video.frame_rate?)))
+        .collect();
+    let detected = intervals(detected);
+    let ground_truth = intervals(ground_truth);
+    let mut video_ids: Vec<&str> = ground_truth.keys().copied().collect();
+    video_ids.sort_unstable();
+
+    let mut overall = EventMatches::default();
+    let mut overall_offsets = BoundaryOffsets::default();
+    let mut classes: BTreeMap<String, EventMatches> = ["dribble", "tackle"]
+        .into_iter()
+        .map(|class| (class.to_string(), EventMatches::default()))
+        .collect();
+    let mut confusion: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    let mut count = |expected: &str, found: &str| {
+        *confusion
+            .entry(expected.to_string())
+            .or_default()
+            .entry(found.to_string())
+            .or_default() += 1;
+    };
+    let mut videos = Vec::new();
+    let mut missing_videos = Vec::new();
+
+    for video_id in video_ids {
+        let Some(video_detected) = detected.get(video_id) else {
+            missing_videos.push(video_id.to_string());
+            continue;
+        };
+        let video_ground_truth = &ground_truth[video_id];
+        let matches = match_events(video_detected, video_ground_truth, iou_threshold);
+
+        let video_matches = EventMatches {
+            true_positives: matches.len(),
+            num_detected: video_detected.len(),
+            num_ground_truth: video_ground_truth.len(),
+        };
+        overall.add(video_matches);
+
+        for (class, class_matches) in classes.iter_mut() {
+            let class = class.as_str();
+            class_matches.add(EventMatches {
+                true_positives: matches
+                    .iter()
+                    .filter(|&&(i, j, _)| {
+                        video_detected[i].class == class && video_ground_truth[j].class == class
+                    })
+                    .count(),
+                num_detected: video_detected.iter().filter(|d| d.class == class).count(),
+                num_ground_truth: video_ground_truth
+                    .iter()
+                    .filter(|g| g.class == class)
+                    .count(),
+            });
+        }
+
+        let matched: Vec<MatchedEvent> = matches
+            .iter()
+            .map(|&(i, j, iou)| MatchedEvent {
+                detected: video_detected[i],
+                ground_truth: video_ground_truth[j],
+                iou,
+            })
+            .collect();
+        let frame_rate = frame_rates
+            .get(video_id)
+            .copied()
+            .unwrap_or_else(|| config.frame_rate());
+        let mut offsets = BoundaryOffsets::default();
+        for event in &matched {
+            offsets.push(
+                (event.detected.start_frame, event.detected.end_frame),
+                (event.ground_truth.start_frame, event.ground_truth.end_frame),
+                frame_rate,
+            );
+        }
+        overall_offsets.append(&offsets);
+        let misses: Vec<EventRange> = (0..video_ground_truth.len())
+            .filter(|&j| !matches.iter().any(|&(_, matched, _)| matched == j))
+            .map(|j| video_ground_truth[j])
+            .collect();
+        let false_positives: Vec<EventRange> = (0..video_detected.len())
+            .filter(|&i| !matches.iter().any(|&(matched, _, _)| matched == i))
+            .map(|i| video_detected[i])
+            .collect();
+
+        for event in &matched {
+            count(event.ground_truth.class, event.detected.class);
+        }
+        for event in &misses {
+            count(event.class, UNMATCHED);
+        }
+        for event in &false_positives {
+            count(UNMATCHED, event.class);
+        }
+        videos.push(VideoEvaluation {
+            video_id: video_id.to_string(),
+            metrics: video_matches.into(),
+            boundaries: offsets.errors(tolerance_secs),
+            matched,
+            false_positives,
+            misses,
+        });
+    }
+
+    EvaluationReport {
+        info: ExportInfo {
+            version: "evaluation_1.0".to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            incomplete: false,
+        },
+        iou_threshold,
+        overall: overall.into(),
+        boundaries: overall_offsets.errors(tolerance_secs),
+        classes: classes
+            .into_iter()
+            .map(|(class, matches)| (class, matches.into()))
+            .collect(),
+        confusion,
+        videos,
+        missing_videos,
+    }
+}
+
+/// Reads an event file in the format of `dribble_events.json`.
+pub fn read_events(path: &Path) -> io::Result<DribbleEventsExport> {
+    serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+/// Reads the events of `detected_path` and `ground_truth_path` and evaluates them.
+pub fn evaluate_files(
+    detected_path: &Path,
+    ground_truth_path: &Path,
+    config: &EvaluationConfig,
+) -> io::Result<EvaluationReport> {
+    Ok(evaluate(
+        &read_events(detected_path)?,
+        &read_events(ground_truth_path)?,
+        config,
+    ))
+}
+
+/// Writes a clip of every false positive and miss of `report` to [`ERROR_CLIPS_DIR`] in
+/// `output_dir`, named `<video>_<false_positive|miss>_<index>_<class>.<mp4|gif|png>`, with the
+/// same padding and format as the event clips. Videos that are not in `videos` are skipped.
+///
+/// Returns the number of clips written.
+pub fn create_error_clips(
+    output_dir: &Path,
+    videos: &[VideoData],
+    report: &EvaluationReport,
+    config: &Config,
+) -> io::Result<usize> {
+    let padding = config
+        .visualization
+        .event_clip_padding
+        .unwrap_or(DEFAULT_EVENT_CLIP_PADDING);
+    let extension = config.visualization.event_export_format.extension();
+    let clips_dir = output_dir.join(ERROR_CLIPS_DIR);
+    if clips_dir.exists() {
+        fs::remove_dir_all(&clips_dir)?;
+    }
+
+    let mut num_clips = 0;
+    for video in report.videos.iter().filter(|video| video.has_errors()) {
+        let Some(video_data) = videos.iter().find(|v| v.name() == video.video_id) else {
+            continue;
+        };
+        let errors = [
+            ("false_positive", &video.false_positives),
+            ("miss", &video.misses),
+        ];
+        let clips: Vec<(String, (u32, u32))> = errors
+            .iter()
+            .flat_map(|(kind, events)| {
+                events.iter().enumerate().map(move |(index, event)| {
+                    let file_name = format!(
+                        "{}_{}_{}_{}.{}",
+                        video.video_id, kind, index, event.class, extension
+                    );
+                    let frames = (
+                        event.start_frame.saturating_sub(padding),
+                        event.end_frame.saturating_add(padding),
+                    );
+                    (file_name, frames)
+                })
+            })
+            .collect();
+        num_clips += render_clips(&clips_dir, video_data, &clips, config)?;
+    }
+
+    if num_clips > 0 {
+        println!("Wrote {} error clips to {}", num_clips, clips_dir.display());
+    }
+    Ok(num_clips)
+}
+
+/// Writes `report` to [`EVALUATION_FILE`] in `output_dir`.
+pub fn write_evaluation(output_dir: &Path, report: &EvaluationReport) -> io::Result<()> {
+    fs::write(
+        output_dir.join(EVALUATION_FILE),
+        serde_json::to_string_pretty(report)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(videos: &[(&str, &[(u32, u32, bool)])]) -> DribbleEventsExport {
+        let videos: Vec<String> = videos
+            .iter()
+            .map(|(video_id, events)| {
+                let events: Vec<String> = events
+                    .iter()
+                    .map(|&(start, end, tackle)| {
+                        format!(
+                            r#"{{"finished": true, "detected_dribble": {}, "detected_tackle": {},
+                                "ever_contested": false, "possession_holder": 7,
+                                "start_frame": {}, "end_frame": {}}}"#,
+                            !tackle, tackle, start, end
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"{{"video_id": "{}", "dribble_events": [{}]}}"#,
+                    video_id,
+                    events.join(",")
+                )
+            })
+            .collect();
+        let json = format!(
+            r#"{{"info": {{"version": "1.0", "generated_at": "2025-03-14T09:26:53Z"}},
+                "videos": [{}]}}"#,
+            videos.join(",")
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_temporal_iou() {
+        assert_eq!(temporal_iou((10, 19), (10, 19)), 1.0);
+        assert_eq!(temporal_iou((10, 19), (15, 24)), 5.0 / 15.0);
+        assert_eq!(temporal_iou((10, 19), (20, 29)), 0.0);
+        assert_eq!(temporal_iou((10, 10), (10, 10)), 1.0);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let ground_truth = export(&[
+            (
+                "SNGS-001",
+                &[(100, 199, false), (300, 349, true), (500, 549, false)],
+            ),
+            ("SNGS-002", &[(10, 59, false)]),
+        ]);
+        // A dribble detected as a tackle, a tackle found twice, one missed and one false
+        // detection. SNGS-002 was not processed.
+        let detected = export(&[(
+            "SNGS-001",
+            &[
+                (110, 199, true),
+                (300, 349, true),
+                (305, 349, true),
+                (700, 749, false),
+            ],
+        )]);
+
+        let report = evaluate(&detected, &ground_truth, &EvaluationConfig::default());
+        assert_eq!(report.missing_videos, vec!["SNGS-002"]);
+        assert_eq!(report.overall.true_positives, 2);
+        assert_eq!(report.overall.num_detected, 4);
+        assert_eq!(report.overall.num_ground_truth, 3);
+        let boundaries = report.boundaries.unwrap();
+        assert_eq!(boundaries.num_matched, 2);
+        assert_eq!(boundaries.start.mean_frames, 5.0);
+        assert!((boundaries.start.mean_seconds - 0.2).abs() < 1e-9);
+        assert!((report.overall.recall - 2.0 / 3.0).abs() < 1e-9);
+
+        let tackle = &report.classes["tackle"];
+        assert_eq!((tackle.true_positives, tackle.num_detected), (1, 3));
+        assert_eq!(report.classes["dribble"].true_positives, 0);
+
+        assert_eq!(report.confusion["dribble"]["tackle"], 1);
+        assert_eq!(report.confusion["dribble"]["none"], 1);
+        assert_eq!(report.confusion["tackle"]["tackle"], 1);
+        assert_eq!(report.confusion["none"]["tackle"], 1);
+        assert_eq!(report.confusion["none"]["dribble"], 1);
+
+        let video = &report.videos[0];
+        assert_eq!(video.matched.len(), 2);
+        assert_eq!(video.matched[0].iou, 1.0);
+        let ranges = |events: &[EventRange]| -> Vec<(u32, u32)> {
+            events
+                .iter()
+                .map(|e| (e.start_frame, e.end_frame))
+                .collect()
+        };
+        assert_eq!(ranges(&video.false_positives), vec![(305, 349), (700, 749)]);
+        assert_eq!(ranges(&video.misses), vec![(500, 549)]);
+
+        // A strict threshold leaves only the near-exact match
+        let strict = EvaluationConfig {
+            iou_threshold: Some(0.95),
+            ..Default::default()
+        };
+        let strict = evaluate(&detected, &ground_truth, &strict);
+        assert_eq!(strict.overall.true_positives, 1);
+
+        // The offsets are converted to seconds with the frame rate of the sequence
+        let mut ground_truth = ground_truth;
+        ground_truth.videos[0].frame_rate = Some(50.0);
+        let report = evaluate(&detected, &ground_truth, &EvaluationConfig::default());
+        let boundaries = report.boundaries.unwrap();
+        assert!((boundaries.start.mean_seconds - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_boundary_errors() {
+        let mut offsets = BoundaryOffsets::default();
+        assert_eq!(offsets.errors(1.0), None);
+        offsets.push((110, 199), (100, 199), 25.0);
+        offsets.push((300, 349), (300, 349), 25.0);
+        offsets.push((90, 230), (100, 200), 25.0);
+
+        let errors = offsets.errors(1.0).unwrap();
+        assert_eq!(errors.num_matched, 3);
+        assert!((errors.start.mean_frames - 0.0).abs() < 1e-9);
+        assert_eq!(errors.start.median_frames, 0.0);
+        assert!((errors.start.mean_abs_frames - 20.0 / 3.0).abs() < 1e-9);
+        assert!((errors.start.mean_abs_seconds - 20.0 / 75.0).abs() < 1e-9);
+        assert_eq!(errors.end.median_frames, 0.0);
+        assert!((errors.end.mean_seconds - 1.2 / 3.0).abs() < 1e-9);
+        // The last detection ends 1.2 seconds late
+        assert!((errors.within_tolerance - 2.0 / 3.0).abs() < 1e-9);
+
+        offsets.push((20, 30), (10, 30), 25.0);
+        assert_eq!(offsets.errors(1.0).unwrap().start.median_frames, 5.0);
+    }
+}
@@ -0,0 +1,164 @@
+//! `dribble_events.jsonl`: the events of every video, appended as soon as the video is
+//! finished, so the results of a run that crashes are not lost. At the end of the run the
+//! events are compacted into the pretty `dribble_events.json`.
+
+use crate::data::models::VideoDribbleEvents;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the event stream in the output directory
+pub const EVENT_STREAM_FILE: &str = "dribble_events.jsonl";
+
+/// Appends the events of every finished video to `dribble_events.jsonl` as a single JSON line
+/// in the format of the videos of `dribble_events.json`. Videos are recorded from several
+/// threads, so writes are serialized through a lock.
+pub struct EventStream {
+    path: PathBuf,
+    subset: String,
+    /// Keys of the videos that were recorded before the stream was opened
+    resumed: HashSet<String>,
+    file: Mutex<File>,
+}
+
+impl EventStream {
+    /// Opens the event stream of `subset` in `output_dir`. When resuming, the videos that
+    /// were already recorded are kept; otherwise the file is started over.
+    pub fn open(output_dir: &Path, subset: &str, resume: bool) -> io::Result<Self> {
+        fs::create_dir_all(output_dir)?;
+        let path = output_dir.join(EVENT_STREAM_FILE);
+
+        let recorded = if resume && path.exists() {
+            read_event_stream(&path)?
+        } else {
+            Vec::new()
+        };
+
+        // Rewriting the valid entries drops a line that was cut off by a crash
+        let mut file = File::create(&path)?;
+        for video in &recorded {
+            writeln!(file, "{}", serde_json::to_string(video)?)?;
+        }
+        file.flush()?;
+        let file = OpenOptions::new().append(true).open(&path)?;
+
+        Ok(Self {
+            path,
+            subset: subset.to_string(),
+            resumed: recorded.iter().map(VideoDribbleEvents::key).collect(),
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The subset the videos of the stream belong to.
+    pub fn subset(&self) -> &str {
+        &self.subset
+    }
+
+    /// Returns `true` if the stream was resumed with the events of the video with `key`, see
+    /// [`VideoDribbleEvents::key`].
+    pub fn has_resumed(&self, key: &str) -> bool {
+        self.resumed.contains(key)
+    }
+
+    /// Appends the events of a finished video.
+    pub fn record(&self, video: &VideoDribbleEvents) -> io::Result<()> {
+        let line = format!("{}\n", serde_json::to_string(video)?);
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+}
+
+/// Reads the videos of an event stream, ordered by dataset and video id. A video that was
+/// recorded more than once, e.g. because it was interrupted and processed again after
+/// resuming, has the events of its last line. Lines that can not be parsed, such as a last line
+/// that was only partly written, are skipped.
+pub fn read_event_stream(path: &Path) -> io::Result<Vec<VideoDribbleEvents>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut videos: BTreeMap<String, VideoDribbleEvents> = BTreeMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        match serde_json::from_str::<VideoDribbleEvents>(&line) {
+            Ok(video) => {
+                videos.insert(video.key(), video);
+            }
+            Err(e) if !line.trim().is_empty() => {
+                eprintln!("Skipping invalid line in {}: {}", path.display(), e)
+            }
+            Err(_) => {}
+        }
+    }
+    Ok(videos.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::DribbleLabel;
+    use crate::dribbling_detection::dribble_models::DribbleEvent;
+
+    fn video_events(video_id: &str, start_frame: u32) -> VideoDribbleEvents {
+        let event = DribbleEvent::new(3, start_frame, video_id.to_string());
+        VideoDribbleEvents {
+            video_id: video_id.to_string(),
+            dataset: None,
+            subset: Some("valid".to_string()),
+            clip: None,
+            frame_rate: None,
+            dribble_events: vec![DribbleLabel::from(&event)],
+            shielding_events: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let output_dir = std::env::temp_dir().join(format!("event_stream_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+
+        let stream = EventStream::open(&output_dir, "valid", false).unwrap();
+        stream.record(&video_events("SNGS-002", 10)).unwrap();
+        stream.record(&video_events("SNGS-001", 20)).unwrap();
+        drop(stream);
+
+        // Simulate a crash while writing the next video
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(output_dir.join(EVENT_STREAM_FILE))
+            .unwrap();
+        write!(file, "{{\"video_id\": \"SNGS-0").unwrap();
+        drop(file);
+
+        // After resuming, a video that is processed again replaces its earlier events, but not
+        // the video with the same name in another dataset
+        let stream = EventStream::open(&output_dir, "valid", true).unwrap();
+        assert!(stream.has_resumed("SNGS-001") && !stream.has_resumed("SNGS-003"));
+        stream.record(&video_events("SNGS-002", 30)).unwrap();
+        let other_dataset = VideoDribbleEvents {
+            dataset: Some("other".to_string()),
+            ..video_events("SNGS-001", 40)
+        };
+        stream.record(&other_dataset).unwrap();
+        let videos = read_event_stream(stream.path()).unwrap();
+        let starts: Vec<(String, u32)> = videos
+            .iter()
+            .map(|v| (v.key(), v.dribble_events[0].start_frame))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                ("SNGS-001".to_string(), 20),
+                ("SNGS-002".to_string(), 30),
+                ("other/SNGS-001".to_string(), 40)
+            ]
+        );
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
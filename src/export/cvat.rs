@@ -0,0 +1,173 @@
+use crate::config::CvatConfig;
+use crate::data::models::{Image, VideoData};
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use crate::utils::frame_source::frame_number;
+use crate::utils::report::escape_html;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Directory in the output directory with the CVAT annotation files
+pub const CVAT_DIR: &str = "cvat";
+
+/// Kinds of events, the keys of `[cvat.labels]`
+const EVENT_KINDS: [&str; 2] = ["dribble", "tackle"];
+
+impl CvatConfig {
+    /// The CVAT label of an event kind, the kind itself if it is not mapped.
+    pub fn label<'a>(&'a self, kind: &'a str) -> &'a str {
+        self.labels.get(kind).map_or(kind, String::as_str)
+    }
+}
+
+/// Writes a CVAT for images 1.1 XML file for every video with detected events to
+/// `<output_path>/cvat/<video>.xml`. Every frame of an event gets a tag with the label of the
+/// event kind, so the files can be imported as pre-annotations into a task with the frames of
+/// the sequence and the events verified there.
+///
+/// Returns the number of files written.
+pub fn write_cvat_annotations(
+    output_path: &Path,
+    videos: &[VideoData],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+    config: &CvatConfig,
+) -> io::Result<usize> {
+    let cvat_dir = output_path.join(CVAT_DIR);
+    let mut num_files = 0;
+    for video_data in videos {
+        let video_id = video_data.name();
+        let Some(video_events) = events.get(&video_data.key()).filter(|e| !e.is_empty()) else {
+            continue;
+        };
+        fs::create_dir_all(&cvat_dir)?;
+        let xml = render_cvat_xml(&video_id, &video_data.labels.images, video_events, config);
+        fs::write(cvat_dir.join(format!("{}.xml", video_id)), xml)?;
+        num_files += 1;
+    }
+    Ok(num_files)
+}
+
+/// The CVAT XML of the events of a sequence with `images`. The frames of an event share its
+/// `event_id`, which keeps the temporal segments apart in CVAT.
+pub fn render_cvat_xml(
+    task_name: &str,
+    images: &[Image],
+    events: &[DribbleEvent],
+    config: &CvatConfig,
+) -> String {
+    let mut labels: Vec<&str> = EVENT_KINDS.iter().map(|kind| config.label(kind)).collect();
+    labels.dedup();
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<annotations>\n");
+    xml.push_str("  <version>1.1</version>\n  <meta>\n    <task>\n");
+    let _ = writeln!(xml, "      <name>{}</name>", escape_html(task_name));
+    let _ = writeln!(xml, "      <size>{}</size>", images.len());
+    xml.push_str("      <mode>annotation</mode>\n      <labels>\n");
+    for label in labels {
+        let _ = writeln!(
+            xml,
+            "        <label>\n          <name>{}</name>\n          <type>tag</type>\n          \
+             <attributes>",
+            escape_html(label)
+        );
+        for attribute in ["event_id", "possession_holder"] {
+            let _ = writeln!(
+                xml,
+                "            <attribute>\n              <name>{}</name>\n              \
+                 <mutable>False</mutable>\n              \
+                 <input_type>text</input_type>\n              \
+                 <default_value></default_value>\n              \
+                 <values></values>\n            </attribute>",
+                attribute
+            );
+        }
+        xml.push_str("          </attributes>\n        </label>\n");
+    }
+    xml.push_str("      </labels>\n    </task>\n  </meta>\n");
+
+    for (id, image) in images.iter().enumerate() {
+        let _ = write!(
+            xml,
+            "  <image id=\"{}\" name=\"{}\" width=\"{}\" height=\"{}\"",
+            id,
+            escape_html(&image.file_name),
+            image.width,
+            image.height
+        );
+        let frame = frame_number(Path::new(&image.file_name));
+        let tags: Vec<(usize, &DribbleEvent)> = events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| frame.is_some_and(|frame| contains_frame(event, frame)))
+            .collect();
+        if tags.is_empty() {
+            xml.push_str(" />\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        for (event_id, event) in tags {
+            let kind = if event.detected_tackle { "tackle" } else { "dribble" };
+            let _ = writeln!(
+                xml,
+                "    <tag label=\"{}\" source=\"auto\">\n      \
+                 <attribute name=\"event_id\">{}</attribute>\n      \
+                 <attribute name=\"possession_holder\">{}</attribute>\n    </tag>",
+                escape_html(config.label(kind)),
+                event_id,
+                event.possession_holder
+            );
+        }
+        xml.push_str("  </image>\n");
+    }
+    xml.push_str("</annotations>\n");
+    xml
+}
+
+fn contains_frame(event: &DribbleEvent, frame: u32) -> bool {
+    let end_frame = event
+        .end_frame
+        .or(event.frames.last().copied())
+        .unwrap_or(event.start_frame);
+    (event.start_frame..=end_frame).contains(&frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_cvat_xml() {
+        let images: Vec<Image> = (1..=4)
+            .map(|id| Image {
+                is_labeled: true,
+                image_id: id.to_string(),
+                file_name: format!("00000{}.jpg", id),
+                height: 1080,
+                width: 1920,
+                has_labeled_person: None,
+                has_labeled_pitch: None,
+            })
+            .collect();
+        let mut dribble = DribbleEvent::new(7, 2, "SNGS-001".to_string());
+        dribble.end_frame = Some(3);
+        let mut tackle = DribbleEvent::new(9, 4, "SNGS-001".to_string());
+        tackle.detected_tackle = true;
+        let config = CvatConfig {
+            labels: HashMap::from([("dribble".to_string(), "Dribble".to_string())]),
+            ..Default::default()
+        };
+
+        let xml = render_cvat_xml("SNGS-001", &images, &[dribble, tackle], &config);
+        assert!(xml.contains("<size>4</size>"));
+        assert!(xml.contains("<name>Dribble</name>"));
+        assert!(xml.contains("<name>tackle</name>"));
+        let empty_image = "<image id=\"0\" name=\"000001.jpg\" width=\"1920\" height=\"1080\" />";
+        assert!(xml.contains(empty_image));
+        assert_eq!(xml.matches("<tag label=\"Dribble\"").count(), 2);
+        assert_eq!(xml.matches("<tag label=\"tackle\"").count(), 1);
+        assert!(xml.contains("<attribute name=\"possession_holder\">9</attribute>"));
+    }
+}
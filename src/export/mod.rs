@@ -0,0 +1,4 @@
+//! Exports of the detected events for other tools.
+
+pub mod cvat;
+pub mod spotting;
@@ -0,0 +1,173 @@
+use crate::config::{SpottingConfig, DEFAULT_FRAME_RATE};
+use crate::data::models::{ClipIdentity, ClipTiming, Info, VideoData};
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Directory in the output directory with a results file for every game
+pub const SPOTTING_DIR: &str = "spotting";
+/// Name of the results file of a game, as the SoccerNet evaluation expects it
+pub const SPOTTING_FILE: &str = "results_spotting.json";
+
+/// The `results_spotting.json` of a game. Like in the SoccerNet labels, the numbers of the
+/// predictions are strings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpottingResults {
+    #[serde(rename = "UrlLocal")]
+    pub url_local: String,
+    pub predictions: Vec<SpottingPrediction>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpottingPrediction {
+    /// Half and time in the half, e.g. "1 - 17:05"
+    #[serde(rename = "gameTime")]
+    pub game_time: String,
+    pub label: String,
+    /// Milliseconds since the start of the half
+    pub position: String,
+    pub half: String,
+    pub confidence: String,
+}
+
+impl SpottingConfig {
+    /// The spotting label of an event kind, the kind itself if it is not mapped.
+    pub fn label<'a>(&'a self, kind: &'a str) -> &'a str {
+        self.labels.get(kind).map_or(kind, String::as_str)
+    }
+
+    /// The directory of a game, `league/season/game` in the SoccerNet data.
+    pub fn game_dir<'a>(&'a self, game_id: &'a str) -> &'a str {
+        self.game_dirs.get(game_id).map_or(game_id, String::as_str)
+    }
+}
+
+/// Writes the detected events in the SoccerNet action spotting format to
+/// `<output_path>/spotting/<game>/results_spotting.json`, one file per game with the events of
+/// all its sequences, so they can be scored by the official evaluation.
+///
+/// Returns the number of files written.
+pub fn write_spotting_results(
+    output_path: &Path,
+    videos: &[VideoData],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+    config: &SpottingConfig,
+) -> io::Result<usize> {
+    let mut games: BTreeMap<String, Vec<SpottingPrediction>> = BTreeMap::new();
+    for video_data in videos {
+        let Some(video_events) = events.get(&video_data.key()).filter(|e| !e.is_empty()) else {
+            continue;
+        };
+        let (game, predictions) =
+            spotting_predictions(&video_data.name(), &video_data.labels.info, video_events, config);
+        games.entry(game).or_default().extend(predictions);
+    }
+
+    let num_games = games.len();
+    for (game, mut predictions) in games {
+        predictions.sort_by_key(|p| (p.half.clone(), p.position.parse::<u64>().unwrap_or(0)));
+        let url_local = config.game_dir(&game).to_string();
+        let game_dir = output_path.join(SPOTTING_DIR).join(&url_local);
+        fs::create_dir_all(&game_dir)?;
+        let results = SpottingResults {
+            url_local,
+            predictions,
+        };
+        fs::write(game_dir.join(SPOTTING_FILE), serde_json::to_string_pretty(&results)?)?;
+    }
+    Ok(num_games)
+}
+
+/// The game id of a sequence and the predictions of its `events`, at the middle frame of each
+/// event. Positions are relative to the start of the half if the sequence has a `clip_start`
+/// and `game_time_start`, and to the start of the sequence otherwise. Sequences without a game
+/// id are treated as games of their own.
+pub fn spotting_predictions(
+    video_id: &str,
+    info: &Info,
+    events: &[DribbleEvent],
+    config: &SpottingConfig,
+) -> (String, Vec<SpottingPrediction>) {
+    let clip = ClipIdentity::from_info(info);
+    let mut timing = ClipTiming::new(info);
+    timing.frame_rate = timing.frame_rate.or(Some(DEFAULT_FRAME_RATE));
+    let half = clip.as_ref().and_then(|c| c.half).unwrap_or(1);
+    let clip_start = clip.as_ref().map_or(0, |c| c.clip_start);
+
+    let predictions = events
+        .iter()
+        .map(|event| {
+            let end_frame = event.end_frame.unwrap_or(event.start_frame);
+            let middle_frame = (event.start_frame + end_frame) / 2;
+            let seconds = timing.seconds(middle_frame).unwrap_or_default();
+            let position = clip_start + (seconds * 1000.0).round() as u64;
+            let kind = if event.detected_tackle { "tackle" } else { "dribble" };
+            SpottingPrediction {
+                game_time: format!(
+                    "{} - {:02}:{:02}",
+                    half,
+                    position / 60_000,
+                    position / 1000 % 60
+                ),
+                label: config.label(kind).to_string(),
+                position: position.to_string(),
+                half: half.to_string(),
+                confidence: config.confidence.to_string(),
+            }
+        })
+        .collect();
+    let game = clip.map_or(video_id.to_string(), |c| c.game_id);
+    (game, predictions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spotting_predictions() {
+        let info = Info {
+            game_id: Some("3".to_string()),
+            clip_start: "1020000".to_string(),
+            clip_stop: "1050000".to_string(),
+            game_time_start: Some("2 - 17:00".to_string()),
+            frame_rate: 25.0,
+            ..Default::default()
+        };
+        let mut dribble = DribbleEvent::new(7, 101, "SNGS-001".to_string());
+        dribble.end_frame = Some(151);
+        let mut tackle = DribbleEvent::new(9, 751, "SNGS-001".to_string());
+        tackle.detected_tackle = true;
+        let config = SpottingConfig {
+            labels: HashMap::from([("dribble".to_string(), "Dribble".to_string())]),
+            confidence: 0.5,
+            ..Default::default()
+        };
+
+        let events = [dribble, tackle];
+        let (game, predictions) = spotting_predictions("SNGS-001", &info, &events, &config);
+        assert_eq!(game, "3");
+        assert_eq!(
+            predictions[0],
+            SpottingPrediction {
+                game_time: "2 - 17:05".to_string(),
+                label: "Dribble".to_string(),
+                position: "1025000".to_string(),
+                half: "2".to_string(),
+                confidence: "0.5".to_string(),
+            }
+        );
+        assert_eq!(predictions[1].label, "tackle");
+        assert_eq!(predictions[1].position, "1050000");
+
+        // Without a game id, the sequence is its own game and positions start at 0
+        let (game, predictions) =
+            spotting_predictions("SNGS-001", &Info::default(), &events[..1], &config);
+        assert_eq!(game, "SNGS-001");
+        assert_eq!(predictions[0].position, "5000");
+        assert_eq!(predictions[0].half, "1");
+    }
+}
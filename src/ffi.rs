@@ -0,0 +1,289 @@
+//! C API around [`DribbleDetector`], so detection can run in-process in other video pipelines
+//! instead of writing frames to disk for the binary. Built with the `ffi` feature as a shared
+//! library, see `include/dribbling_detection.h` for the declarations.
+//!
+//! A detector is fed one frame at a time with `dd_process_frame`. Finished events are kept by
+//! the detector and read with the `dd_event_*` accessors until `dd_events_clear` is called.
+//!
+//! Panics must not unwind into the caller, so every function catches them and returns an error
+//! value instead, e.g. for frames with NaN positions.
+
+use crate::config::Config;
+use crate::dribbling_detection::dribble_detector::DribbleDetector;
+use crate::dribbling_detection::dribble_models::{Ball, DribbleEvent, DribbleFrame, Player};
+use std::ffi::{c_char, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+/// Name of the video in the events of detectors created through the C API
+const FFI_VIDEO_NAME: &str = "ffi";
+
+/// A player in a frame passed to `dd_process_frame`. `team` may be null.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct DdPlayer {
+    pub id: u32,
+    pub x: f64,
+    pub y: f64,
+    pub velocity_x: f64,
+    pub velocity_y: f64,
+    pub team: *const c_char,
+}
+
+/// Runs `f`, or returns `on_panic` if it panics. The panic message is still printed by the
+/// panic hook.
+fn catch_panic<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+/// A detector with the events it finished so far.
+pub struct DdDetector {
+    detector: DribbleDetector,
+    events: Vec<DribbleEvent>,
+}
+
+/// Creates a detector with the config at `config_path`, or the default config if it is null.
+/// The radii are multiplied by `distance_scale`, as the binary does with the scale of a video.
+/// Returns null if the config can not be loaded or the detector can not be created.
+///
+/// # Safety
+///
+/// `config_path` must be null or a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dd_detector_new(
+    config_path: *const c_char,
+    distance_scale: f64,
+) -> *mut DdDetector {
+    catch_panic(ptr::null_mut(), || {
+        let config = if config_path.is_null() {
+            Config::default()
+        } else {
+            let path = CStr::from_ptr(config_path).to_string_lossy();
+            match Config::load(Path::new(path.as_ref()), None) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Error loading config {}: {}", path, e);
+                    return ptr::null_mut();
+                }
+            }
+        };
+
+        let detector =
+            DribbleDetector::from_config(FFI_VIDEO_NAME.to_string(), distance_scale, config);
+        Box::into_raw(Box::new(DdDetector {
+            detector,
+            events: Vec::new(),
+        }))
+    })
+}
+
+/// Frees a detector created by `dd_detector_new`. Does nothing if `detector` is null.
+///
+/// # Safety
+///
+/// `detector` must be null or a detector that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn dd_detector_free(detector: *mut DdDetector) {
+    catch_panic((), || {
+        if !detector.is_null() {
+            drop(Box::from_raw(detector));
+        }
+    })
+}
+
+/// Processes a frame with `num_players` players and the ball at `ball_x`, `ball_y`. Returns 1
+/// if an event finished in this frame, 0 if not and -1 if `detector` is null or the frame can
+/// not be processed, e.g. because a position is NaN.
+///
+/// # Safety
+///
+/// `detector` must be a live detector and `players` must point to `num_players` players, or be
+/// null if `num_players` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn dd_process_frame(
+    detector: *mut DdDetector,
+    frame_number: u32,
+    players: *const DdPlayer,
+    num_players: usize,
+    ball_x: f64,
+    ball_y: f64,
+) -> i32 {
+    catch_panic(-1, || {
+        let Some(detector) = detector.as_mut() else {
+            return -1;
+        };
+        let players = if players.is_null() || num_players == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(players, num_players)
+        };
+
+        let frame = DribbleFrame {
+            frame_number,
+            players: players
+                .iter()
+                .map(|p| Player {
+                    id: p.id,
+                    x: p.x,
+                    y: p.y,
+                    velocity: (p.velocity_x, p.velocity_y),
+                    acceleration: (0.0, 0.0),
+                    within_inner_rad: false,
+                    team: (!p.team.is_null())
+                        .then(|| CStr::from_ptr(p.team).to_string_lossy().to_string()),
+                })
+                .collect(),
+            ball: Ball::new(ball_x, ball_y),
+        };
+
+        // Like the binary, only keep events that ended in a dribble or a tackle
+        match detector.detector.process_frame(frame) {
+            Some(event) if event.detected_dribble || event.detected_tackle => {
+                detector.events.push(event);
+                1
+            }
+            _ => 0,
+        }
+    })
+}
+
+/// Number of finished events, 0 if `detector` is null.
+///
+/// # Safety
+///
+/// `detector` must be null or a live detector.
+#[no_mangle]
+pub unsafe extern "C" fn dd_event_count(detector: *const DdDetector) -> usize {
+    catch_panic(0, || detector.as_ref().map_or(0, |d| d.events.len()))
+}
+
+/// Removes the finished events, e.g. after they were read.
+///
+/// # Safety
+///
+/// `detector` must be null or a live detector.
+#[no_mangle]
+pub unsafe extern "C" fn dd_events_clear(detector: *mut DdDetector) {
+    catch_panic((), || {
+        if let Some(detector) = detector.as_mut() {
+            detector.events.clear();
+        }
+    })
+}
+
+unsafe fn event<'a>(detector: *const DdDetector, index: usize) -> Option<&'a DribbleEvent> {
+    detector.as_ref()?.events.get(index)
+}
+
+/// First frame of event `index`, 0 if there is no such event.
+///
+/// # Safety
+///
+/// `detector` must be null or a live detector.
+#[no_mangle]
+pub unsafe extern "C" fn dd_event_start_frame(detector: *const DdDetector, index: usize) -> u32 {
+    catch_panic(0, || event(detector, index).map_or(0, |e| e.start_frame))
+}
+
+/// Last frame of event `index`, 0 if there is no such event.
+///
+/// # Safety
+///
+/// `detector` must be null or a live detector.
+#[no_mangle]
+pub unsafe extern "C" fn dd_event_end_frame(detector: *const DdDetector, index: usize) -> u32 {
+    catch_panic(0, || {
+        event(detector, index).map_or(0, |e| e.end_frame.unwrap_or(e.start_frame))
+    })
+}
+
+/// Id of the player that had the ball in event `index`, 0 if there is no such event.
+///
+/// # Safety
+///
+/// `detector` must be null or a live detector.
+#[no_mangle]
+pub unsafe extern "C" fn dd_event_possession_holder(
+    detector: *const DdDetector,
+    index: usize,
+) -> u32 {
+    catch_panic(0, || {
+        event(detector, index).map_or(0, |e| e.possession_holder)
+    })
+}
+
+/// Whether event `index` ended in a tackle rather than a dribble.
+///
+/// # Safety
+///
+/// `detector` must be null or a live detector.
+#[no_mangle]
+pub unsafe extern "C" fn dd_event_is_tackle(detector: *const DdDetector, index: usize) -> bool {
+    catch_panic(false, || {
+        event(detector, index).is_some_and(|e| e.detected_tackle)
+    })
+}
+
+/// Whether a defender was in the inner zone long enough to contest event `index`.
+///
+/// # Safety
+///
+/// `detector` must be null or a live detector.
+#[no_mangle]
+pub unsafe extern "C" fn dd_event_is_contested(detector: *const DdDetector, index: usize) -> bool {
+    catch_panic(false, || {
+        event(detector, index).is_some_and(|e| e.ever_contested)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dribbling_detection::test_fixtures::{DRIBBLE_FRAMES, HOLDER, LOST_BALL, PLAYERS};
+
+    #[test]
+    fn test_detect_through_c_api() {
+        let players = PLAYERS.map(|(id, x)| DdPlayer {
+            id,
+            x,
+            y: 0.0,
+            velocity_x: 0.0,
+            velocity_y: 0.0,
+            team: ptr::null(),
+        });
+
+        unsafe {
+            let detector = dd_detector_new(ptr::null(), 1.0);
+            assert!(!detector.is_null());
+            let finished: Vec<i32> = LOST_BALL
+                .iter()
+                .map(|&(frame, ball_x)| {
+                    dd_process_frame(detector, frame, players.as_ptr(), 2, ball_x, 0.0)
+                })
+                .collect();
+            assert_eq!(finished, [0, 0, 0, 0, 1]);
+
+            assert_eq!(dd_event_count(detector), 1);
+            assert_eq!(dd_event_start_frame(detector, 0), DRIBBLE_FRAMES.0);
+            assert_eq!(dd_event_end_frame(detector, 0), DRIBBLE_FRAMES.1);
+            assert_eq!(dd_event_possession_holder(detector, 0), HOLDER);
+            assert!(!dd_event_is_tackle(detector, 0));
+            // Out of range indices and null detectors return defaults
+            assert_eq!(dd_event_start_frame(detector, 1), 0);
+            assert_eq!(dd_event_count(ptr::null()), 0);
+            assert_eq!(dd_process_frame(ptr::null_mut(), 6, ptr::null(), 0, 0.0, 0.0), -1);
+
+            dd_events_clear(detector);
+            assert_eq!(dd_event_count(detector), 0);
+
+            // Panics do not unwind into the caller
+            assert_eq!(
+                dd_process_frame(detector, 6, players.as_ptr(), 2, f64::NAN, 0.0),
+                -1
+            );
+            dd_detector_free(detector);
+        }
+    }
+}
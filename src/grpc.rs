@@ -0,0 +1,217 @@
+//! gRPC server of the detector, so live tracking services can stream player and ball
+//! positions and get the dribble events back, without writing frames to disk. Built with the
+//! `grpc` feature, the service is defined in `proto/dribbling_detection.proto`.
+
+use crate::config::Config;
+use crate::dribbling_detection::dribble_detector::DribbleDetector;
+use crate::dribbling_detection::dribble_models::{self, Ball, DribbleFrame, Player};
+use std::net::SocketAddr;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+
+pub mod proto {
+    tonic::include_proto!("dribbling_detection");
+}
+
+use proto::detect_request::Request as DetectRequestKind;
+use proto::dribble_detection_server::{DribbleDetection, DribbleDetectionServer};
+
+/// Name of the video of streams that do not send a `Start`
+const DEFAULT_VIDEO_NAME: &str = "grpc";
+
+/// Events buffered per stream before the detection of a stream waits for its client
+const EVENT_BUFFER: usize = 64;
+
+impl From<dribble_models::DribbleEvent> for proto::DribbleEvent {
+    fn from(event: dribble_models::DribbleEvent) -> Self {
+        Self {
+            end_frame: event.end_frame.unwrap_or(event.start_frame),
+            video_name: event.file_name,
+            start_frame: event.start_frame,
+            possession_holder: event.possession_holder,
+            detected_dribble: event.detected_dribble,
+            detected_tackle: event.detected_tackle,
+            ever_contested: event.ever_contested,
+            defenders: event.active_defenders,
+        }
+    }
+}
+
+impl From<proto::Frame> for DribbleFrame {
+    fn from(frame: proto::Frame) -> Self {
+        let ball = frame.ball.unwrap_or_default();
+        Self {
+            frame_number: frame.frame_number,
+            players: frame
+                .players
+                .into_iter()
+                .map(|p| Player {
+                    id: p.id,
+                    x: p.x,
+                    y: p.y,
+                    velocity: (p.velocity_x, p.velocity_y),
+                    acceleration: (0.0, 0.0),
+                    within_inner_rad: false,
+                    team: p.team,
+                })
+                .collect(),
+            ball: Ball::new(ball.x, ball.y),
+        }
+    }
+}
+
+/// The detection of one `Detect` stream. The detector is created by the `Start` message, or
+/// with the defaults by the first frame.
+pub struct DetectSession {
+    config: Config,
+    detector: Option<DribbleDetector>,
+}
+
+impl DetectSession {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            detector: None,
+        }
+    }
+
+    /// Handles a message of the stream, returning the event that finished in it, if any.
+    /// Like the binary, only events that ended in a dribble or a tackle are returned.
+    pub fn handle(
+        &mut self,
+        request: proto::DetectRequest,
+    ) -> Result<Option<proto::DribbleEvent>, Status> {
+        match request.request {
+            Some(DetectRequestKind::Start(start)) => {
+                if self.detector.is_some() {
+                    return Err(Status::failed_precondition(
+                        "Start must be sent before the first frame",
+                    ));
+                }
+                let distance_scale = if start.distance_scale > 0.0 {
+                    start.distance_scale
+                } else {
+                    1.0
+                };
+                self.detector = Some(DribbleDetector::from_config(
+                    start.video_name,
+                    distance_scale,
+                    self.config.clone(),
+                ));
+                Ok(None)
+            }
+            Some(DetectRequestKind::Frame(frame)) => {
+                let config = &self.config;
+                let detector = self.detector.get_or_insert_with(|| {
+                    let video_name = DEFAULT_VIDEO_NAME.to_string();
+                    DribbleDetector::from_config(video_name, 1.0, config.clone())
+                });
+                Ok(detector
+                    .process_frame(frame.into())
+                    .filter(|event| event.detected_dribble || event.detected_tackle)
+                    .map(proto::DribbleEvent::from))
+            }
+            None => Err(Status::invalid_argument("empty DetectRequest")),
+        }
+    }
+}
+
+/// The `DribbleDetection` service. Every stream gets its own detector with `config`.
+pub struct DetectionService {
+    config: Config,
+}
+
+impl DetectionService {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+#[tonic::async_trait]
+impl DribbleDetection for DetectionService {
+    type DetectStream = ReceiverStream<Result<proto::DribbleEvent, Status>>;
+
+    async fn detect(
+        &self,
+        request: Request<Streaming<proto::DetectRequest>>,
+    ) -> Result<Response<Self::DetectStream>, Status> {
+        let mut frames = request.into_inner();
+        let mut session = DetectSession::new(self.config.clone());
+        let (tx, rx) = mpsc::channel(EVENT_BUFFER);
+
+        tokio::spawn(async move {
+            loop {
+                let result = match frames.message().await {
+                    Ok(Some(request)) => session.handle(request),
+                    Ok(None) => break,
+                    Err(status) => Err(status),
+                };
+                let failed = result.is_err();
+                if let Some(response) = result.transpose() {
+                    // The client went away, so there is nobody left to detect for
+                    if tx.send(response).await.is_err() || failed {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Serves the `DribbleDetection` service on `addr` until the process is stopped.
+pub async fn serve(addr: SocketAddr, config: Config) -> Result<(), tonic::transport::Error> {
+    println!("Serving dribble detection over gRPC on {}", addr);
+    Server::builder()
+        .add_service(DribbleDetectionServer::new(DetectionService::new(config)))
+        .serve(addr)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dribbling_detection::test_fixtures::{DRIBBLE_FRAMES, HOLDER, LOST_BALL, PLAYERS};
+
+    #[test]
+    fn test_detect_session() {
+        let frame = |frame_number, ball_x| proto::DetectRequest {
+            request: Some(DetectRequestKind::Frame(proto::Frame {
+                frame_number,
+                players: PLAYERS
+                    .into_iter()
+                    .map(|(id, x)| proto::Player {
+                        id,
+                        x,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ball: Some(proto::Ball { x: ball_x, y: 0.0 }),
+            })),
+        };
+        let start = proto::DetectRequest {
+            request: Some(DetectRequestKind::Start(proto::Start {
+                video_name: "SNGS-021".to_string(),
+                distance_scale: 1.0,
+            })),
+        };
+
+        let mut session = DetectSession::new(Config::default());
+        assert_eq!(session.handle(start.clone()).unwrap(), None);
+        let (last, frames) = LOST_BALL.split_last().unwrap();
+        for &(frame_number, ball_x) in frames {
+            assert_eq!(session.handle(frame(frame_number, ball_x)).unwrap(), None);
+        }
+        let event = session.handle(frame(last.0, last.1)).unwrap().unwrap();
+        assert_eq!(event.video_name, "SNGS-021");
+        assert_eq!((event.start_frame, event.end_frame), DRIBBLE_FRAMES);
+        assert_eq!(event.possession_holder, HOLDER);
+        assert!(event.detected_dribble);
+
+        assert!(session.handle(start).is_err());
+        assert!(session.handle(proto::DetectRequest { request: None }).is_err());
+    }
+}
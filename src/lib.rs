@@ -1,4 +1,28 @@
+pub mod analytics;
+pub mod bootstrap;
+pub mod checkpoint;
 pub mod config;
 pub mod data;
+pub mod diagnostics;
 pub mod dribbling_detection;
+pub mod dry_run;
+pub mod evaluation;
+pub mod event_stream;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod notifications;
+pub mod pipeline;
+pub mod pr_curve;
+pub mod preprocessing;
+pub mod progress;
+pub mod review_log;
+pub mod review_queue;
+pub mod review_report;
+pub mod run_manifest;
+pub mod run_statistics;
+pub mod training_labels;
 pub mod utils;
+pub mod watchdog;
@@ -1,292 +1,1074 @@
-use chrono::Utc;
+use chrono::{DateTime, Local, Utc};
 use clap::Parser;
-use dribbling_detection_algorithm::data::dataset::load_dribble_events_map;
+use dribbling_detection_algorithm::analytics::{
+    rank_by_difficulty, summarize_zones, write_event_difficulty, write_player_stats,
+    write_team_zones, EventDifficulty, PlayerStats, ZoneCounts, EVENT_DIFFICULTY_FILE,
+    PLAYER_STATS_FILE, TEAM_ZONES_FILE,
+};
+use dribbling_detection_algorithm::bootstrap::{bootstrap_video, write_bootstrap, BOOTSTRAP_FILE};
+use dribbling_detection_algorithm::checkpoint::Checkpoint;
+use dribbling_detection_algorithm::data::dedup::deduplicate_videos;
 use dribbling_detection_algorithm::data::download_data::download_and_extract_dataset;
+use dribbling_detection_algorithm::data::event_import::{
+    load_imported_events, merge_imported_events,
+};
 use dribbling_detection_algorithm::data::models::{
-    Annotation, DribbleEventsExport, DribbleLabel, ExportInfo, Image, ReviewedVideoData, VideoData,
-    VideoDribbleEvents,
+    ClipTiming, DribbleEventsExport, ExportInfo, PossessionsExport, VideoData, VideoDribbleEvents,
+    VideoPossessions,
+};
+use dribbling_detection_algorithm::data::splits::Split;
+use dribbling_detection_algorithm::data::stats::SubsetStats;
+use dribbling_detection_algorithm::diagnostics::{
+    diagnose_no_events, diagnose_no_videos, empty_run_exit_code, EmptyRunCause,
 };
-use dribbling_detection_algorithm::dribbling_detection::create_dribble_models::{
-    get_ball_model, get_player_models,
+use dribbling_detection_algorithm::dribbling_detection::autotune::{
+    collect_distance_samples, suggest_radii, write_suggested_config,
 };
-use dribbling_detection_algorithm::dribbling_detection::dribble_detector::DribbleDetector;
 use dribbling_detection_algorithm::dribbling_detection::dribble_models::{
-    Ball, DribbleEvent, DribbleFrame,
+    DribbleEvent, ShieldingEvent,
+};
+use dribbling_detection_algorithm::dribbling_detection::possession::Possession;
+use dribbling_detection_algorithm::dribbling_detection::sweep::{
+    run_sweep, write_sweep_csv, EventMatches, SWEEP_FILE,
+};
+use dribbling_detection_algorithm::dry_run::{
+    estimate_runtime, format_bytes, format_duration, SubsetPlan,
+};
+use dribbling_detection_algorithm::evaluation::{
+    create_error_clips, evaluate_files, read_events, write_evaluation, EvaluationConfig,
+    EventRange, EVALUATION_FILE,
+};
+use dribbling_detection_algorithm::event_stream::{
+    read_event_stream, EventStream, EVENT_STREAM_FILE,
+};
+use dribbling_detection_algorithm::export::cvat::write_cvat_annotations;
+use dribbling_detection_algorithm::export::spotting::write_spotting_results;
+use dribbling_detection_algorithm::notifications::{Notifier, RunSummary};
+use dribbling_detection_algorithm::pipeline::{
+    build_thread_pool, run_detection, CancellationToken, ProcessedVideo,
+};
+use dribbling_detection_algorithm::pr_curve::{
+    best_f1, pr_curve, write_pr_curve_csv, write_pr_curve_image, PR_CURVE_FILE, PR_CURVE_IMAGE,
 };
-use dribbling_detection_algorithm::utils::annotation_calculations::{
-    compute_average_player_bbox_height, filter_annotations,
+use dribbling_detection_algorithm::progress::{ProgressReporter, ProgressSender};
+use dribbling_detection_algorithm::review_log::{
+    check_annotator, review_log_file, ReviewLog, ReviewedEventsExport, VerdictCounts,
 };
-use dribbling_detection_algorithm::utils::keyboard_args::Args;
-use dribbling_detection_algorithm::utils::keyboard_input::{
-    wait_for_keyboard_input, KeyboardInput,
+use dribbling_detection_algorithm::review_queue::{group_by_video, load_review_queue};
+use dribbling_detection_algorithm::review_report::{
+    create_review_report, load_review_logs, write_review_report, REVIEW_REPORT_FILE,
 };
+use dribbling_detection_algorithm::run_manifest::{
+    RunManifest, VideoRecord, VideoStatus, RUN_MANIFEST_FILE,
+};
+use dribbling_detection_algorithm::run_statistics::RunStatistics;
+use dribbling_detection_algorithm::training_labels::{
+    training_labels, write_training_labels, TRAINING_LABELS_FILE,
+};
+use dribbling_detection_algorithm::utils::audit_pack::create_audit_pack;
+use dribbling_detection_algorithm::utils::colors::ColorTheme;
+use dribbling_detection_algorithm::utils::event_clips::create_event_clips;
+use dribbling_detection_algorithm::utils::event_montage::create_event_montage;
+use dribbling_detection_algorithm::utils::keyboard_args::{Args, Command};
+use dribbling_detection_algorithm::utils::keymap::{probe_keys, Keymap};
+use dribbling_detection_algorithm::utils::output_template::resolve_output_path;
+use dribbling_detection_algorithm::utils::render_only::render_videos;
+use dribbling_detection_algorithm::utils::report::create_report;
+use dribbling_detection_algorithm::utils::subtitles::write_subtitles;
 use dribbling_detection_algorithm::utils::video_processing::export_reviewed_data;
-use dribbling_detection_algorithm::utils::visualizations::VisualizationBuilder;
+use dribbling_detection_algorithm::utils::visualizations::display_available;
+use dribbling_detection_algorithm::watchdog::{
+    write_failures, VideoFailure, Watchdog, FAILURES_FILE,
+};
+use dribbling_detection_algorithm::config::{
+    AuditConfig, CvatConfig, LogLevel, ReviewConfig, SplitsConfig, SpottingConfig, VideoMode,
+    DEFAULT_CONFIG,
+};
 use dribbling_detection_algorithm::{config::Config, data::dataset::Dataset};
-use opencv::imgcodecs;
+use prettytable::{row, Table};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::collections::HashMap;
+use rayon::ThreadPool;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::runtime::Runtime;
 
+/// Set on the first Ctrl+C, after which the run writes what it detected and exits
 static EXIT_FLAG: AtomicBool = AtomicBool::new(false);
 
+/// Exit code of an interrupted run, as for processes stopped by SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
 fn main() {
     let start_time = Utc::now();
     let args = Args::parse();
+    if args.init_config {
+        init_config(Path::new(&args.config));
+        return;
+    }
+    let command = args.command();
+    let config = load_config(&args, &command);
 
-    if args.download {
-        println!("Data download initiated...");
-        let config_content =
-            fs::read_to_string("config.toml").expect("Unable to read the config file");
-        let mut config: Config =
-            toml::from_str(&config_content).expect("Unable to parse the config file");
+    if args.print_config {
+        match toml::to_string_pretty(&config) {
+            Ok(toml) => print!("{}", toml),
+            Err(e) => eprintln!("Error serializing the config: {}", e),
+        }
+        return;
+    }
 
-        config = config.apply_env_overrides();
+    if args.dry_run {
+        run_dry_run(&config, &Dataset::new(config.clone()));
+        return;
+    }
 
-        if let Some(ip) = &args.input {
-            config.data.data_path = ip.clone();
-        }
-        if let Some(op) = &args.output {
-            config.data.output_path = op.clone();
-        }
+    if let Command::Serve { addr } = &command {
+        run_server(&config, addr);
+        return;
+    }
 
+    if command == Command::ReviewReport {
+        run_review_report(&config);
+        return;
+    }
+
+    if command == Command::ProbeKeys {
+        run_key_probe(&config);
+        return;
+    }
+
+    // `--download` downloads before detecting, the `download` command only downloads
+    if command == Command::Download || args.download {
+        println!("Data download initiated...");
         let rt = Runtime::new().unwrap();
         rt.block_on(download_and_extract_dataset(&config));
         println!("Data download complete.");
+        if command == Command::Download {
+            return;
+        }
     }
 
     println!("\nRunning dribbling detection");
+    println!("{:#?}", config.redacted());
+
+    if config.general.video_mode == VideoMode::Display {
+        // highgui aborts without a display, so fail with a hint before processing anything
+        if !display_available() {
+            eprintln!(
+                "Video mode \"display\" needs an X11 or Wayland display, but neither DISPLAY \
+                 nor WAYLAND_DISPLAY is set. Use \"download\" or \"layers\" to render headless."
+            );
+            std::process::exit(1);
+        }
+        println!("Using 1 core since video mode is set to \"display\"");
+    }
+    let pool = build_thread_pool(&config).expect("Failed to build thread pool");
+    let cancel = CancellationToken::new();
+    handle_interrupts(&cancel);
 
-    let config_content = fs::read_to_string("config.toml").expect("Unable to read the config file");
-    let mut config: Config =
-        toml::from_str(&config_content).expect("Unable to parse the config file");
-    config = config.apply_env_overrides();
+    let dataset = Dataset::new(config.clone());
 
-    // If the input or output paths were set on the command line, override the config
-    if let Some(ip) = &args.input {
-        println!("Overriding input path: {}", ip);
-        config.data.data_path = ip.clone();
+    // Without this, a wrong subset name silently results in an empty run
+    let missing_subsets = dataset.missing_subsets();
+    if !missing_subsets.is_empty() {
+        eprintln!(
+            "Warning: subsets {:?} were not found in {}. Available subsets: {:?}",
+            missing_subsets,
+            dataset
+                .roots
+                .iter()
+                .map(|root| root.base_dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            dataset.available_subsets()
+        );
     }
-    if let Some(op) = &args.output {
-        println!("Overriding output path: {}", op);
-        config.data.output_path = op.clone();
+
+    match &command {
+        Command::Validate => {
+            run_validation(&config, &dataset);
+            return;
+        }
+        Command::Stats => {
+            run_stats(&config, &dataset);
+            return;
+        }
+        Command::Export { events } => {
+            let events_path = match events {
+                Some(path) => PathBuf::from(path),
+                None => Path::new(&config.data.output_path).join("dribble_events.json"),
+            };
+            run_export(&config, &dataset, &events_path);
+            return;
+        }
+        Command::Sweep => {
+            run_parameter_sweep(&config, &dataset, &pool, &cancel);
+            return;
+        }
+        Command::Evaluate { events } => {
+            let events_path = match events {
+                Some(path) => PathBuf::from(path),
+                None => Path::new(&config.data.output_path).join("dribble_events.json"),
+            };
+            run_evaluation(&config, &dataset, &events_path);
+            return;
+        }
+        Command::BootstrapGroundTruth => {
+            run_bootstrap(&config, &dataset);
+            return;
+        }
+        Command::Detect
+        | Command::Download
+        | Command::Review
+        | Command::ReviewReport
+        | Command::ProbeKeys
+        | Command::Serve { .. } => {}
     }
-    if args.review.is_some() && args.review.unwrap() {
-        println!("Enabling review mode from keyboard args");
-        config.general.review_mode = Some(true);
-        config.general.video_mode = "display".to_string();
+
+    if args.write_splits {
+        run_write_splits(&config, &dataset);
+        return;
     }
 
-    println!("{:#?}", config);
+    if args.autotune {
+        run_autotune(&config, &dataset, &pool, args.autotune_samples);
+        return;
+    }
 
-    let video_mode: &String = &config.general.video_mode;
-    let num_threads: usize = if config.general.video_mode == "display" {
-        println!("Using 1 core since video mode is set to \"display\"");
-        1
+    if config.general.video_mode == VideoMode::RenderOnly {
+        run_render_only(&config, &dataset, &pool, &cancel);
+        return;
+    }
+
+    if config.general.review_mode.unwrap_or(false) {
+        let videos: Vec<VideoData> = dataset
+            .subsets
+            .iter()
+            .flat_map(|subset| dataset.iter_subset(subset))
+            .filter_map(|v| v.ok())
+            .collect();
+        println!("Number of videos to review: {}", videos.len());
+
+        run_review(&config, &videos, &pool, &cancel, start_time);
+        return;
+    }
+
+    let notifier = Notifier::new(&config, &dataset.subsets).map(Arc::new);
+    if let Some(notifier) = &notifier {
+        notify_on_panic(notifier.clone(), start_time);
+    }
+
+    // Subsets are processed concurrently, each with its own output directory if there are
+    // several of them. The videos within a subset share the same thread pool.
+    let multiple_subsets = dataset.subsets.len() > 1;
+    // Progress bars would be drawn over the video window in display mode
+    let reporter = if config.general.progress.unwrap_or(true)
+        && config.general.video_mode != VideoMode::Display
+    {
+        ProgressReporter::start()
     } else {
-        config.general.num_cores as usize
+        ProgressReporter::disabled()
     };
+    let summaries: Vec<SubsetSummary> = pool.install(|| {
+        dataset
+            .subsets
+            .par_iter()
+            .map(|subset| {
+                let mut subset_config = config.clone();
+                if multiple_subsets {
+                    subset_config.data.output_path = Path::new(&config.data.output_path)
+                        .join(subset)
+                        .to_string_lossy()
+                        .to_string();
+                }
+                run_subset(
+                    subset,
+                    &subset_config,
+                    &dataset,
+                    &pool,
+                    &cancel,
+                    args.resume,
+                    notifier.as_deref(),
+                    reporter.sender(),
+                )
+            })
+            .collect()
+    });
+    reporter.finish();
+
+    // The same clip may be in several subsets, e.g. in the predictions and the ground truth.
+    // The combined export has every clip once, preferring the subsets configured first.
+    let all_videos = summaries.iter().flat_map(|s| s.videos.clone()).collect();
+    let (videos, num_duplicates) = deduplicate_videos(all_videos);
+    let statistics = RunStatistics::new(&videos);
+    if multiple_subsets {
+        println!("Merged {} clips that are in several subsets", num_duplicates);
+        let output_path = Path::new(&config.data.output_path);
+        fs::create_dir_all(output_path).expect("Error creating output directory");
+        write_dribble_events(output_path, videos, cancel.is_cancelled());
+    }
 
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(num_threads)
-        .build_global()
-        .unwrap();
+    let mut table = Table::new();
+    table.add_row(row!["Subset", "Videos", "Dribbles", "Tackles", "Shielding", "Output"]);
+    for summary in &summaries {
+        table.add_row(row![
+            summary.subset,
+            summary.num_videos,
+            summary.num_dribbles,
+            summary.num_tackles,
+            summary.num_shielding,
+            summary.output_path
+        ]);
+    }
+    table.add_row(row![
+        "Total",
+        summaries.iter().map(|s| s.num_videos).sum::<usize>(),
+        summaries.iter().map(|s| s.num_dribbles).sum::<usize>(),
+        summaries.iter().map(|s| s.num_tackles).sum::<usize>(),
+        summaries.iter().map(|s| s.num_shielding).sum::<usize>(),
+        ""
+    ]);
+    println!();
+    table.printstd();
 
-    let dataset = Dataset::new(config.clone());
-    let data_iter: Vec<_> = dataset.iter_subset(&"interpolated-predictions").collect();
+    let cur_time = Utc::now();
+    let duration = cur_time - start_time;
 
-    // let inner_rad = config.dribbling_detection.inner_radius;
-    // let outer_rad = config.dribbling_detection.outer_radius;
+    println!(
+        "\n\nDetected {} dribble events in {}H:{}M:{}S",
+        summaries
+            .iter()
+            .map(|s| s.num_dribbles + s.num_tackles)
+            .sum::<usize>(),
+        duration.num_hours(),
+        duration.num_minutes() % 60,
+        duration.num_seconds() % 60
+    );
+    if config.shielding.as_ref().is_some_and(|s| s.enabled) {
+        println!(
+            "Detected {} shielding events",
+            summaries.iter().map(|s| s.num_shielding).sum::<usize>()
+        );
+    }
+    print_run_statistics(&statistics);
 
-    println!("Number of videos to process: {}", data_iter.len());
+    let video_records = summaries
+        .iter()
+        .flat_map(|s| s.video_records.clone())
+        .collect();
+    let manifest = RunManifest::new(&config, &dataset.subsets, start_time, video_records)
+        .with_statistics(statistics);
+    match manifest.write(Path::new(&config.data.output_path)) {
+        Ok(_) => println!(
+            "{} videos processed, {} skipped and {} with errors, see {}",
+            manifest.count(VideoStatus::Processed),
+            manifest.count(VideoStatus::Skipped),
+            manifest.count(VideoStatus::Error),
+            RUN_MANIFEST_FILE
+        ),
+        Err(e) => eprintln!("Error writing {}: {}", RUN_MANIFEST_FILE, e),
+    }
 
-    // Shared map of all detected events
-    let all_detected_events = Arc::new(Mutex::new(HashMap::new()));
+    // Interrupted runs are not evaluated, their missing events would count as misses
+    if config.evaluation.as_ref().is_some_and(|e| e.ground_truth.is_some())
+        && !cancel.is_cancelled()
+    {
+        run_evaluation(
+            &config,
+            &dataset,
+            &Path::new(&config.data.output_path).join("dribble_events.json"),
+        );
+    }
 
-    let dribble_events_map = if config.general.review_mode.unwrap_or(false) {
-        load_dribble_events_map(&config)
-    } else {
-        None
-    };
+    if let Some(notifier) = &notifier {
+        let num_errors = manifest.count(VideoStatus::Error);
+        let reason = if EXIT_FLAG.load(Ordering::Relaxed) {
+            Some("interrupted".to_string())
+        } else if num_errors > 0 {
+            Some(format!("{} videos with errors", num_errors))
+        } else {
+            None
+        };
+        let summary = RunSummary {
+            num_videos: summaries.iter().map(|s| s.num_videos).sum(),
+            num_errors,
+            num_dribbles: summaries.iter().map(|s| s.num_dribbles).sum(),
+            num_tackles: summaries.iter().map(|s| s.num_tackles).sum(),
+            num_shielding: summaries.iter().map(|s| s.num_shielding).sum(),
+            duration_secs: duration.num_seconds(),
+        };
+        notifier.run_finished(summary, reason.as_deref());
+    }
 
-    let all_reviewed_video_data = if config.general.review_mode.unwrap_or(false) {
-        Arc::new(Mutex::new(Some(Vec::new())))
-    } else {
-        Arc::new(Mutex::new(None))
-    };
+    for summary in summaries.iter().filter(|s| !s.causes.is_empty()) {
+        eprintln!("\nNothing detected in {}. Likely causes:", summary.subset);
+        for cause in &summary.causes {
+            eprintln!(" * {}", cause);
+        }
+    }
 
-    // ---------------------------------------------------------------------------------------------
-    // Define the per-video processing function
-    // ---------------------------------------------------------------------------------------------
-    let process_item = |video_data: &Result<VideoData, _>| {
-        // Skip this video if it can't be unwrapped
-        let video_data = match video_data {
-            Ok(vd) => vd.clone(),
-            Err(_) => return,
-        };
+    if EXIT_FLAG.load(Ordering::Relaxed) {
+        std::process::exit(INTERRUPTED_EXIT_CODE);
+    }
 
-        let category_map: HashMap<String, u32> = video_data
-            .labels
-            .categories
-            .iter()
-            .map(|c| (c.name.clone(), c.id))
-            .collect();
+    if config.general.fail_on_empty.unwrap_or(false) {
+        let num_videos = summaries.iter().map(|s| s.num_videos).sum();
+        let num_events = summaries.iter().map(|s| s.num_events()).sum();
+        if let Some(code) = empty_run_exit_code(num_videos, num_events) {
+            std::process::exit(code);
+        }
+    }
+}
 
-        let average_bbox_height =
-            compute_average_player_bbox_height(&video_data.labels.annotations, &category_map);
-        let scale_factor = average_bbox_height * 0.2;
+/// Prints the statistics of the detected events, with a histogram of the event durations, the
+/// gaps between events and the events per video.
+fn print_run_statistics(statistics: &RunStatistics) {
+    if statistics.num_dribbles + statistics.num_tackles == 0 {
+        return;
+    }
+    println!(
+        "{:.1}% of the events were contested, {}",
+        statistics.contested_fraction * 100.0,
+        statistics
+            .tackles_per_dribble
+            .map_or("no dribbles".to_string(), |ratio| {
+                format!("{:.2} tackles per dribble", ratio)
+            })
+    );
 
-        let (inner_rad, outer_rad) = match config.dribbling_detection.use_2d {
-            true => (
-                config.dribbling_detection.inner_radius,
-                config.dribbling_detection.outer_radius,
-            ),
-            false => (
-                config.dribbling_detection.inner_radius * scale_factor,
-                config.dribbling_detection.outer_radius * scale_factor,
-            ),
+    let mut table = Table::new();
+    table.add_row(row!["Statistic", "Count", "Mean", "Median", "Min", "Max"]);
+    let distributions = [
+        ("Duration (s)", &statistics.durations),
+        ("Gap between events (s)", &statistics.gaps),
+        ("Events per video", &statistics.events_per_video),
+    ];
+    for (name, distribution) in distributions {
+        table.add_row(row![
+            name,
+            distribution.count,
+            format!("{:.2}", distribution.mean),
+            format!("{:.2}", distribution.median),
+            format!("{:.2}", distribution.min),
+            format!("{:.2}", distribution.max)
+        ]);
+    }
+    table.printstd();
+
+    for (name, distribution) in distributions {
+        let Some(max_count) = distribution.histogram.iter().map(|bin| bin.count).max() else {
+            continue;
         };
+        println!("\n{}", name);
+        for bin in &distribution.histogram {
+            // Bars of at most 40 characters
+            let width = (bin.count * 40).div_ceil(max_count.max(1));
+            println!("{:>8} | {:<40} {}", bin.label(), "#".repeat(width), bin.count);
+        }
+    }
+}
 
-        let video_name = video_data
-            .dir_path
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .to_string();
-
-        // Build a DribbleDetector for this video
-        // let video_name = format!("{:06}.jpg", vid_num);
-        let dribble_detector = DribbleDetector::new(
-            video_name.clone(),
-            inner_rad,
-            outer_rad,
-            config.dribbling_detection.inner_threshold,
-            config.dribbling_detection.outer_threshold,
-            config.dribbling_detection.outer_in_threshold,
-            config.dribbling_detection.outer_out_threshold,
-            config.clone(),
+/// Reports a panic as a failed run to the webhooks, after the usual panic message. The
+/// notifier posts from a thread of its own, so this also works for panics inside a runtime.
+fn notify_on_panic(notifier: Arc<Notifier>, start_time: DateTime<Utc>) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let summary = RunSummary {
+            duration_secs: (Utc::now() - start_time).num_seconds(),
+            ..Default::default()
+        };
+        notifier.run_finished(summary, Some(&info.to_string()));
+    }));
+}
+
+/// Stops the run on Ctrl+C. The videos in progress stop after their current frame and the
+/// events detected so far are exported, marked as incomplete. A second Ctrl+C exits right away.
+fn handle_interrupts(cancel: &CancellationToken) {
+    let cancel = cancel.clone();
+    let result = ctrlc::set_handler(move || {
+        if EXIT_FLAG.swap(true, Ordering::Relaxed) {
+            eprintln!("\nInterrupted again, exiting without writing the outputs");
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+        eprintln!(
+            "\nInterrupted, writing the events detected so far. Press Ctrl+C again to exit \
+             right away."
         );
+        cancel.cancel();
+    });
+    if let Err(e) = result {
+        eprintln!("Error installing the Ctrl+C handler: {}", e);
+    }
+}
 
-        // Check for early exit
-        if EXIT_FLAG.load(Ordering::Relaxed) {
-            return;
+/// Writes the commented default config to `path`, unless a file already exists there.
+fn init_config(path: &Path) {
+    if path.exists() {
+        eprintln!("{} already exists, remove it first to write the default config", path.display());
+        std::process::exit(1);
+    }
+    match fs::write(path, DEFAULT_CONFIG) {
+        Ok(_) => println!("Wrote the default config to {}", path.display()),
+        Err(e) => {
+            eprintln!("Error writing {}: {}", path.display(), e);
+            std::process::exit(1);
         }
+    }
+}
 
-        // Process the video
-        let processed_video = detect_events(
-            video_name,
-            video_data,
-            config.clone(),
-            video_mode,
-            dribble_detector.clone(),
-            &dribble_events_map,
-            all_reviewed_video_data.clone(),
-            inner_rad,
-            outer_rad,
-        );
+/// Reads the config file of `args` and applies the environment and command line overrides, in
+/// that order.
+fn load_config(args: &Args, command: &Command) -> Config {
+    let mut config = match Config::load(Path::new(&args.config), args.profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Unable to load the config file {}: {}", args.config, e);
+            std::process::exit(1);
+        }
+    };
+
+    // If the input or output paths were set on the command line, override the config
+    if let Some(ip) = &args.input {
+        eprintln!("Overriding input path: {}", ip);
+        config.data.data_path = ip.clone();
+    }
+    if let Some(op) = &args.output {
+        eprintln!("Overriding output path: {}", op);
+        config.data.output_path = op.clone();
+    }
+    if let Some(threads) = args.threads {
+        config.general.num_cores = threads;
+    }
+    if *command == Command::Review {
+        eprintln!("Enabling review mode from keyboard args");
+        config.general.review_mode = Some(true);
+        config.general.video_mode = VideoMode::Display;
+    }
+    if args.resume_review {
+        config.review.get_or_insert_with(Default::default).resume = true;
+    }
+    if args.blind {
+        config.review.get_or_insert_with(Default::default).blind = true;
+    }
+    if let Some(annotator) = &args.annotator {
+        config.review.get_or_insert_with(Default::default).annotator = Some(annotator.clone());
+    }
+    if let Some(speed) = args.review_speed {
+        config.review.get_or_insert_with(Default::default).speed = Some(speed);
+    }
+    if let Some(order) = args.review_order {
+        config.review.get_or_insert_with(Default::default).order = order;
+    }
+    if args.only_contested {
+        config.review.get_or_insert_with(Default::default).only_contested = true;
+    }
+    if !args.review_kinds.is_empty() {
+        config.review.get_or_insert_with(Default::default).kinds = args.review_kinds.clone();
+    }
+    if !args.review_videos.is_empty() {
+        config.review.get_or_insert_with(Default::default).videos = args.review_videos.clone();
+    }
+    if let Some(ground_truth) = &args.ground_truth {
+        config.evaluation.get_or_insert_with(Default::default).ground_truth =
+            Some(ground_truth.clone());
+    }
+    if let Some(iou_threshold) = args.iou_threshold {
+        config.evaluation.get_or_insert_with(Default::default).iou_threshold = Some(iou_threshold);
+    }
+    if let Some(tolerance) = args.boundary_tolerance {
+        config
+            .evaluation
+            .get_or_insert_with(Default::default)
+            .boundary_tolerance_secs = Some(tolerance);
+    }
+    if args.error_clips {
+        config.evaluation.get_or_insert_with(Default::default).error_clips = true;
+    }
+    if args.player_stats {
+        config.analytics.get_or_insert_with(Default::default).enabled = true;
+    }
+    if args.pr_curve {
+        config.evaluation.get_or_insert_with(Default::default).pr_curve = true;
+    }
+    if args.pr_curve_image {
+        config.evaluation.get_or_insert_with(Default::default).pr_curve_image = true;
+    }
+    if let Some(evaluation) = &config.evaluation {
+        let iou_threshold = evaluation.iou_threshold();
+        if !(iou_threshold > 0.0 && iou_threshold <= 1.0) {
+            eprintln!("The IoU threshold must be above 0 and at most 1, got {}", iou_threshold);
+            std::process::exit(1);
+        }
+        if evaluation.frame_rate() <= 0.0 {
+            eprintln!("The evaluation frame rate must be above 0, got {}", evaluation.frame_rate());
+            std::process::exit(1);
+        }
+        if evaluation.boundary_tolerance_secs() < 0.0 {
+            eprintln!(
+                "The boundary tolerance must not be negative, got {}",
+                evaluation.boundary_tolerance_secs()
+            );
+            std::process::exit(1);
+        }
+    }
+    if args.montage {
+        config.visualization.event_montage = Some(true);
+    }
+    if args.event_clips {
+        config.visualization.event_clips = Some(true);
+    }
+    if args.report {
+        config.visualization.report = Some(true);
+    }
+    if args.cvat {
+        let cvat_config = config.cvat.take().unwrap_or_default();
+        config.cvat = Some(CvatConfig {
+            enabled: true,
+            ..cvat_config
+        });
+    }
+    if !args.import.is_empty() {
+        let mut import_config = config.import.take().unwrap_or_default();
+        import_config.files.extend(args.import.iter().cloned());
+        config.import = Some(import_config);
+    }
+    if !args.webhook.is_empty() {
+        let mut notifications_config = config.notifications.take().unwrap_or_default();
+        notifications_config.enabled = true;
+        notifications_config.webhooks.extend(args.webhook.iter().cloned());
+        config.notifications = Some(notifications_config);
+    }
+    if args.spotting {
+        let spotting_config = config.spotting.take().unwrap_or_default();
+        config.spotting = Some(SpottingConfig {
+            enabled: true,
+            ..spotting_config
+        });
+    }
+    if args.audit {
+        let audit_config = config.audit.take().unwrap_or_default();
+        config.audit = Some(AuditConfig {
+            enabled: true,
+            ..audit_config
+        });
+    }
+    if let Some(subset) = &args.subset {
+        eprintln!("Only processing subset: {}", subset);
+        config.data.active_subset = Some(subset.clone());
+    }
+    if !args.sequences.is_empty() {
+        eprintln!("Only processing sequences matching: {:?}", args.sequences);
+        config.data.include_sequences = Some(args.sequences.clone());
+    }
+    if let Some(range) = &args.sequence_range {
+        eprintln!("Only processing sequences {} to {}", range[0], range[1]);
+        config.data.sequence_range = Some([range[0], range[1]]);
+    }
+    if let Some(split) = args.split {
+        eprintln!("Only processing the {} split", split);
+        let splits_config = config.splits.take().unwrap_or_default();
+        config.splits = Some(SplitsConfig {
+            active: Some(split),
+            ..splits_config
+        });
+    }
+    if let Some(max_videos) = args.max_videos {
+        config.data.max_videos = Some(max_videos);
+    }
+    if args.lenient_labels {
+        config.data.lenient_labels = Some(true);
+    }
+    if args.fail_on_empty {
+        config.general.fail_on_empty = Some(true);
+    }
+    if args.no_progress {
+        config.general.progress = Some(false);
+    }
+    if let Some(timeout) = args.video_timeout {
+        config.general.video_timeout_secs = Some(timeout);
+    }
+    if args.render_only {
+        config.general.video_mode = VideoMode::RenderOnly;
+    }
+    if let Some(tag) = &args.tag {
+        config.general.tag = Some(tag.clone());
+    }
+
+    // Wrong bindings would only show up as dead keys once the window is open
+    if let Err(e) = Keymap::from_config(&config) {
+        eprintln!("Invalid key bindings: {}", e);
+        std::process::exit(1);
+    }
 
-        if processed_video.is_some() {
-            let (file_name, merged_events) = processed_video.unwrap();
-            // Then each worker (thread or single) adds all events to the global map
-            let mut all_events = all_detected_events.lock().unwrap();
-            all_events.insert(file_name, merged_events);
+    // Templated output paths are resolved once, so every output of the run ends up together
+    match resolve_output_path(
+        &config.data.output_path,
+        Local::now(),
+        config.profile.as_deref(),
+        config.general.tag.as_deref(),
+    ) {
+        Ok(output_path) => {
+            if output_path != config.data.output_path {
+                eprintln!("Writing the outputs to {}", output_path);
+            }
+            config.data.output_path = output_path;
+        }
+        Err(e) => {
+            eprintln!("Invalid output path: {}", e);
+            std::process::exit(1);
         }
+    }
+    config
+}
+
+/// Writes `dribble_events.json` to `output_path`, marked as `incomplete` if the run was
+/// interrupted.
+fn write_dribble_events(
+    output_path: &Path,
+    videos: Vec<VideoDribbleEvents>,
+    incomplete: bool,
+) -> DribbleEventsExport {
+    let export = DribbleEventsExport {
+        info: ExportInfo {
+            version: "dribble_events_1.0".to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            incomplete,
+        },
+        videos,
     };
+    let json_data =
+        serde_json::to_string_pretty(&export).expect("Error serializing dribble events to JSON");
 
-    // -------------------------------
-    // Use parallel or sequential iteration based on num_threads
-    // -------------------------------
-    if num_threads > 1 {
-        data_iter.par_iter().for_each(process_item);
-    } else {
-        data_iter.iter().for_each(process_item);
+    let json_path = output_path.join("dribble_events.json");
+    fs::write(json_path, json_data).expect("Error writing dribble_events.json file");
+    export
+}
+
+/// Event counts of a processed subset.
+struct SubsetSummary {
+    subset: String,
+    output_path: String,
+    /// Exported videos, to merge the clips of all subsets
+    videos: Vec<VideoDribbleEvents>,
+    num_videos: usize,
+    num_dribbles: usize,
+    num_tackles: usize,
+    num_shielding: usize,
+    /// Likely causes if no videos were processed or no events were detected
+    causes: Vec<EmptyRunCause>,
+    /// What happened to every video, for the run manifest
+    video_records: Vec<VideoRecord>,
+}
+
+impl SubsetSummary {
+    fn num_events(&self) -> usize {
+        self.num_dribbles + self.num_tackles + self.num_shielding
     }
+}
 
-    if config.general.review_mode.unwrap_or(false) {
-        let cur_time = Utc::now();
-        let duration = cur_time - start_time;
-        let all_reviewed_video_data = Arc::try_unwrap(all_reviewed_video_data)
-            .unwrap()
-            .into_inner()
-            .unwrap()
-            .unwrap_or_default();
+/// Prints the events of a subset by team and pitch zone.
+fn print_team_zones(subset: &str, run: &[ZoneCounts]) {
+    if run.is_empty() {
+        return;
+    }
+    println!("\nEvents in {} by team and pitch zone", subset);
+    let unknown = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+    let mut table = Table::new();
+    table.add_row(row!["Team", "Third", "Channel", "Dribbles", "Tackles", "Contested"]);
+    for counts in run {
+        table.add_row(row![
+            unknown(counts.team.clone()),
+            unknown(counts.third.map(|t| t.to_string())),
+            unknown(counts.channel.map(|c| c.to_string())),
+            counts.dribbles,
+            counts.tackles,
+            counts.contested
+        ]);
+    }
+    table.printstd();
+}
 
-        println!(
-            "\n\nReview mode done in {}H:{}M:{}S",
-            duration.num_hours(),
-            duration.num_minutes() % 60,
-            duration.num_seconds() % 60
-        );
+/// Prints the 10 hardest of the dribbles in `ranked`, which are ranked by difficulty.
+fn print_hardest_dribbles(subset: &str, ranked: &[EventDifficulty]) {
+    if ranked.is_empty() {
+        return;
+    }
+    println!("\nHardest dribbles in {}", subset);
+    let mut table = Table::new();
+    table.add_row(row![
+        "Video",
+        "Frames",
+        "Player",
+        "Team",
+        "Inner (s)",
+        "Outer (s)",
+        "Speed",
+        "Third",
+        "Score"
+    ]);
+    for event in ranked.iter().take(10) {
+        let player = match &event.jersey {
+            Some(jersey) => format!("#{} ({})", jersey, event.track_id),
+            None => event.track_id.to_string(),
+        };
+        table.add_row(row![
+            event.video_id,
+            format!("{}-{}", event.start_frame, event.end_frame),
+            player,
+            event.team.as_deref().unwrap_or("unknown"),
+            format!("{:.2}", event.inner_defender_secs),
+            format!("{:.2}", event.outer_defender_secs),
+            format!("{:.2}", event.mean_speed),
+            event.third.map_or("unknown".to_string(), |t| t.to_string()),
+            format!("{:.3}", event.score)
+        ]);
+    }
+    table.printstd();
+}
 
-        let total_dribbles: usize = all_reviewed_video_data
-            .iter()
-            .map(|r| r.dribble_data.len())
-            .sum();
-        let total_tackles: usize = all_reviewed_video_data
-            .iter()
-            .map(|r| r.tackle_data.len())
-            .sum();
-        let total_others: usize = all_reviewed_video_data
-            .iter()
-            .map(|r| r.other_data.len())
-            .sum();
+/// Runs detection on all videos of a subset and writes `dribble_events.json`,
+/// `possessions.json` and the optional montage and event clips to the output directory of `config`.
+/// Finished videos are checkpointed, and with `resume` the videos of the last checkpoint are
+/// not processed again. Progress is reported to `progress` and events are counted by `notifier`.
+fn run_subset(
+    subset: &str,
+    config: &Config,
+    dataset: &Dataset,
+    pool: &ThreadPool,
+    cancel: &CancellationToken,
+    resume: bool,
+    notifier: Option<&Notifier>,
+    progress: &ProgressSender,
+) -> SubsetSummary {
+    // The timeout of a video also covers reading its labels
+    let watchdog = match config.general.video_timeout_secs {
+        Some(timeout) => Watchdog::start(Duration::from_secs(timeout), progress.clone()),
+        None => Watchdog::disabled(),
+    };
+    let videos: Vec<VideoData> = dataset
+        .iter_subset_watched(subset, &watchdog)
+        .filter_map(|v| v.ok())
+        .collect();
 
-        println!(
-            "Approved {} dribbles, {} tackles and disaproved {} events",
-            total_dribbles, total_tackles, total_others,
-        );
+    let output_path = Path::new(&config.data.output_path);
+    let (checkpoint, mut processed_videos) = match Checkpoint::open(output_path, resume) {
+        Ok((checkpoint, finished)) => (Some(checkpoint), finished),
+        Err(e) => {
+            eprintln!("Error opening checkpoint, processing without it: {}", e);
+            (None, Vec::new())
+        }
+    };
+    // The checkpoint may hold videos that the current sequence filters leave out
+    let video_keys: HashSet<String> = videos.iter().map(VideoData::key).collect();
+    processed_videos.retain(|p| video_keys.contains(&p.key()));
 
-        println!(
-            "\n\nExporting reviewed data to {}...",
-            config.data.output_path
-        );
+    // Videos from an earlier run are skipped when resuming
+    let finished: HashSet<String> = processed_videos.iter().map(ProcessedVideo::key).collect();
+    let pending: Vec<VideoData> = videos
+        .iter()
+        .filter(|v| !finished.contains(&v.key()))
+        .cloned()
+        .collect();
+    if resume {
+        progress.println(format!(
+            "Resuming {}: {} videos already processed",
+            subset,
+            videos.len() - pending.len()
+        ));
+    }
 
-        if let Err(e) = export_reviewed_data(
-            Path::new(&config.data.output_path),
-            &all_reviewed_video_data,
-        ) {
-            eprintln!("Error exporting reviewed data: {}", e);
+    progress.println(format!(
+        "Number of videos to process in {}: {}",
+        subset,
+        pending.len()
+    ));
+
+    let event_stream = if config.general.stream_events.unwrap_or(false) {
+        match EventStream::open(output_path, subset, resume) {
+            Ok(event_stream) => Some(event_stream),
+            Err(e) => {
+                eprintln!("Error opening {}, not streaming events: {}", EVENT_STREAM_FILE, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    // The events of the checkpoint are read back from the stream, if it has them
+    if let Some(event_stream) = &event_stream {
+        for processed in processed_videos.iter_mut() {
+            if event_stream.has_resumed(&processed.key()) {
+                processed.dribble_events = Vec::new();
+                processed.shielding_events = Vec::new();
+            }
         }
-        return;
     }
 
-    // Once all threads finish, safely unwrap the final events
-    let all_detected_events = Arc::try_unwrap(all_detected_events)
-        .unwrap()
-        .into_inner()
-        .unwrap();
+    let detected = run_detection(
+        &pending,
+        config,
+        pool,
+        cancel,
+        &None,
+        &Mutex::new(None),
+        checkpoint.as_ref(),
+        event_stream.as_ref(),
+        notifier,
+        progress,
+        &watchdog,
+    );
+    let failures = watchdog.finish();
+    let video_records = video_records(
+        subset,
+        dataset,
+        &videos,
+        &pending,
+        &detected,
+        &failures,
+        cancel.is_cancelled(),
+    );
+    processed_videos.extend(detected);
+
+    // Sequences of different dataset roots may have the same name
+    let videos_by_key: HashMap<String, &VideoData> = videos.iter().map(|v| (v.key(), v)).collect();
+
+    // Once all threads finish, collect the final events per video
+    let mut all_detected_events: HashMap<String, Vec<DribbleEvent>> = HashMap::new();
+    let mut all_shielding_events: HashMap<String, Vec<ShieldingEvent>> = HashMap::new();
+    let mut all_possessions: HashMap<String, Vec<Possession>> = HashMap::new();
+    let mut player_stats: Vec<PlayerStats> = Vec::new();
+    let mut zone_counts: Vec<ZoneCounts> = Vec::new();
+    let mut event_difficulty: Vec<EventDifficulty> = Vec::new();
+    for processed in processed_videos {
+        player_stats.extend(processed.player_stats);
+        zone_counts.extend(processed.zone_counts);
+        event_difficulty.extend(processed.event_difficulty);
+        let key = processed.key();
+        all_detected_events.insert(key.clone(), processed.dribble_events);
+        all_shielding_events.insert(key.clone(), processed.shielding_events);
+        all_possessions.insert(key, processed.possessions);
+    }
+
+    // Events that were streamed are not kept in memory during the run, so the final events
+    // are built from the stream
+    if let Some(event_stream) = &event_stream {
+        match read_event_stream(event_stream.path()) {
+            Ok(streamed) => {
+                for video in streamed {
+                    let key = video.key();
+                    let Some(events) = all_detected_events.get_mut(&key) else {
+                        continue;
+                    };
+                    // Videos whose events could not be streamed still have them
+                    if events.is_empty() {
+                        *events = video.to_events();
+                    }
+                    let shielding_events = all_shielding_events.entry(key).or_default();
+                    if shielding_events.is_empty() {
+                        *shielding_events = video.shielding_events;
+                    }
+                }
+            }
+            Err(e) => eprintln!("Error reading {}: {}", event_stream.path().display(), e),
+        }
+    }
+
+    if let Some(import_config) = config.import.as_ref().filter(|i| !i.files.is_empty()) {
+        let imported = load_imported_events(import_config);
+        merge_imported_events(&mut all_detected_events, &imported, import_config.strategy);
+    }
+
+    fs::create_dir_all(output_path).expect("Error creating output directory");
 
     // Build and serialize the export
-    let export = DribbleEventsExport {
+    let mut export_videos: Vec<VideoDribbleEvents> = all_detected_events
+        .iter()
+        .filter_map(|(key, events)| {
+            let video = videos_by_key.get(key)?;
+            Some(VideoDribbleEvents::from_video(
+                video,
+                Some(subset),
+                events,
+                all_shielding_events.get(key).map_or(&[], Vec::as_slice),
+            ))
+        })
+        .collect();
+    export_videos.sort_by(|a, b| a.video_id.cmp(&b.video_id));
+
+    // Dataset roots may contain the same clip under different names
+    let (export_videos, num_duplicates) = deduplicate_videos(export_videos);
+    if num_duplicates > 0 {
+        progress.println(format!("Merged {} duplicate clips in {}", num_duplicates, subset));
+    }
+    let export = write_dribble_events(output_path, export_videos.clone(), cancel.is_cancelled());
+
+    let possessions_export = PossessionsExport {
         info: ExportInfo {
-            version: "dribble_events_1.0".to_string(),
+            version: "possessions_1.0".to_string(),
             generated_at: Utc::now().to_rfc3339(),
+            incomplete: cancel.is_cancelled(),
         },
-        videos: all_detected_events
+        videos: all_possessions
             .iter()
-            .map(|(video_id, events)| VideoDribbleEvents {
-                video_id: video_id.clone(),
-                dribble_events: events
-                    .clone()
-                    .iter()
-                    .map(|e| Into::<DribbleLabel>::into(e))
-                    .collect(),
+            .filter_map(|(key, possessions)| {
+                let video = videos_by_key.get(key)?;
+                Some(VideoPossessions {
+                    video_id: video.name(),
+                    dataset: video.dataset.clone(),
+                    possessions: possessions.clone(),
+                })
             })
             .collect(),
     };
+    let json_data = serde_json::to_string_pretty(&possessions_export)
+        .expect("Error serializing possessions to JSON");
+    let json_path = output_path.join("possessions.json");
+    fs::write(json_path, json_data).expect("Error writing possessions.json file");
+
+    if config.analytics.as_ref().is_some_and(|a| a.enabled) {
+        let num_players = player_stats.len();
+        match write_player_stats(output_path, player_stats, cancel.is_cancelled()) {
+            Ok(()) => progress.println(format!(
+                "Wrote the statistics of {} players in {} to {}",
+                num_players, subset, PLAYER_STATS_FILE
+            )),
+            Err(e) => eprintln!("Error writing {}: {}", PLAYER_STATS_FILE, e),
+        }
+        print_team_zones(subset, &summarize_zones(&zone_counts));
+        if let Err(e) = write_team_zones(output_path, zone_counts, cancel.is_cancelled()) {
+            eprintln!("Error writing {}: {}", TEAM_ZONES_FILE, e);
+        }
+        rank_by_difficulty(&mut event_difficulty);
+        print_hardest_dribbles(subset, &event_difficulty);
+        if let Err(e) =
+            write_event_difficulty(output_path, event_difficulty, cancel.is_cancelled())
+        {
+            eprintln!("Error writing {}: {}", EVENT_DIFFICULTY_FILE, e);
+        }
+    }
 
-    let json_data =
-        serde_json::to_string_pretty(&export).expect("Error serializing dribble events to JSON");
+    if config.general.video_timeout_secs.is_some() {
+        if !failures.is_empty() {
+            progress.println(format!(
+                "{} videos in {} timed out, see {}",
+                failures.len(),
+                subset,
+                FAILURES_FILE
+            ));
+        }
+        if let Err(e) = write_failures(output_path, failures) {
+            eprintln!("Error writing {}: {}", FAILURES_FILE, e);
+        }
+    }
 
-    let json_path = Path::new(&config.data.output_path).join("dribble_events.json");
-    fs::write(json_path, json_data).expect("Error writing dribble_events.json file");
+    write_exports(output_path, &videos, &all_detected_events, &export, config);
 
-    if config.general.log_level == "debug" {
-        println!("\n\nFinal detected dribble events:");
+    if config.general.log_level == LogLevel::Debug {
+        println!("\n\nFinal detected dribble events in {}:", subset);
         for (video, events) in &all_detected_events {
             println!("Video: {}", video);
             for event in events {
@@ -299,458 +1081,1071 @@ fn main() {
         }
     }
 
-    let cur_time = Utc::now();
-    let duration = cur_time - start_time;
+    let events = all_detected_events.values().flatten();
+    let mut summary = SubsetSummary {
+        subset: subset.to_string(),
+        output_path: config.data.output_path.clone(),
+        videos: export_videos,
+        num_videos: videos.len(),
+        num_dribbles: events.clone().filter(|e| !e.detected_tackle).count(),
+        num_tackles: events.filter(|e| e.detected_tackle).count(),
+        num_shielding: all_shielding_events.values().flatten().count(),
+        causes: Vec::new(),
+        video_records,
+    };
 
-    println!(
-        "\n\nDetected {} dribble events in {}H:{}M:{}S",
-        all_detected_events.values().flatten().count(),
-        duration.num_hours(),
-        duration.num_minutes() % 60,
-        duration.num_seconds() % 60
-    );
+    // An empty output is almost always a configuration mistake, so try to explain it
+    if videos.is_empty() {
+        summary.causes.push(diagnose_no_videos(dataset, subset));
+    } else if summary.num_events() == 0 && !cancel.is_cancelled() {
+        summary.causes = diagnose_no_events(&videos, config);
+    }
+    summary
+}
+
+/// The status of every sequence of a subset for the run manifest. `videos` are the sequences
+/// whose labels were read, of which `pending` were not finished by an earlier run and
+/// `detected` were processed in this one, before it was `cancelled` or not.
+fn video_records(
+    subset: &str,
+    dataset: &Dataset,
+    videos: &[VideoData],
+    pending: &[VideoData],
+    detected: &[ProcessedVideo],
+    failures: &[VideoFailure],
+    cancelled: bool,
+) -> Vec<VideoRecord> {
+    let pending: HashSet<String> = pending.iter().map(VideoData::key).collect();
+    let detected: HashSet<String> = detected.iter().map(ProcessedVideo::key).collect();
+    let mut records: Vec<VideoRecord> = videos
+        .iter()
+        .map(|video| {
+            let name = video.key();
+            let failure = failures.iter().find(|f| f.video_id == name);
+            let (status, reason) = if !pending.contains(&name) {
+                (VideoStatus::Skipped, Some("processed by an earlier run"))
+            } else if let Some(failure) = failure {
+                (VideoStatus::Error, Some(failure.reason.as_str()))
+            } else if detected.contains(&name) {
+                (VideoStatus::Processed, None)
+            } else if cancelled {
+                (VideoStatus::Skipped, Some("cancelled"))
+            } else {
+                (VideoStatus::Error, Some("annotations or frames could not be read"))
+            };
+            VideoRecord::new(&name, subset, status, reason)
+        })
+        .collect();
+
+    // `iter_subset` leaves out sequences whose labels can not be read, and stops after
+    // `max_videos` readable ones
+    let loaded: HashSet<&Path> = videos.iter().map(|v| v.dir_path.as_path()).collect();
+    let seq_dirs = dataset.filtered_sequence_dirs(subset);
+    let end = if videos.len() < dataset.config.data.max_videos.unwrap_or(usize::MAX) {
+        seq_dirs.len()
+    } else {
+        seq_dirs
+            .iter()
+            .rposition(|dir| loaded.contains(dir.as_path()))
+            .map_or(0, |i| i + 1)
+    };
+    for seq_dir in seq_dirs[..end].iter().filter(|dir| !loaded.contains(dir.as_path())) {
+        let name = dataset.sequence_key(seq_dir);
+        let reason = failures
+            .iter()
+            .find(|f| f.video_id == name)
+            .map_or("labels could not be read", |f| f.reason.as_str());
+        records.push(VideoRecord::new(
+            &name,
+            subset,
+            VideoStatus::Error,
+            Some(reason),
+        ));
+    }
+    records.sort_by(|a, b| a.video_id.cmp(&b.video_id));
+    records
 }
 
-/// Processes a single video and returns its name plus the merged dribble events.
-fn detect_events(
-    vid_name: String,
-    video_data: VideoData,
-    config: Config,
-    video_mode: &String,
-    mut dribble_detector: DribbleDetector,
-    dribble_events_map: &Option<HashMap<String, Vec<(u32, u32)>>>,
-    all_reviewed_video_data: Arc<Mutex<Option<Vec<ReviewedVideoData>>>>,
-    inner_rad: f64,
-    outer_rad: f64,
-) -> Option<(String, Vec<DribbleEvent>)> {
-    let review_mode = config.general.review_mode.unwrap_or(false);
-    let log_level = config.general.log_level.clone();
+/// Writes the exports of the detected `events` that are enabled in `config` to `output_path`:
+/// the montage, event clips, report, subtitles, audit pack, CVAT annotations and spotting
+/// results.
+fn write_exports(
+    output_path: &Path,
+    videos: &[VideoData],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+    export: &DribbleEventsExport,
+    config: &Config,
+) {
+    if config.visualization.event_montage.unwrap_or(false) {
+        let theme = ColorTheme::from_config(config.visualization.colors.as_ref());
+        if let Err(e) = create_event_montage(output_path, videos, events, &theme) {
+            eprintln!("Error creating event montage: {}", e);
+        }
+    }
+
+    if config.visualization.event_clips.unwrap_or(false) {
+        if let Err(e) = create_event_clips(output_path, videos, events, config) {
+            eprintln!("Error creating event clips: {}", e);
+        }
+    }
 
-    let mut reviewed_video_data = review_mode.then(|| ReviewedVideoData::default());
+    if config.visualization.report.unwrap_or(false) {
+        if let Err(e) = create_report(output_path, export, videos, config) {
+            eprintln!("Error creating report: {}", e);
+        }
+    }
 
-    if config.general.log_level == "debug" {
-        println!("Processing video {}", vid_name);
+    if let Some(format) = config.visualization.subtitles {
+        if let Err(e) = write_subtitles(output_path, videos, events, format) {
+            eprintln!("Error writing subtitles: {}", e);
+        }
     }
 
-    let mut vid_events = if review_mode {
-        if dribble_events_map.is_none() {
-            println!("Skipping video {}, found no dribble events file", vid_name);
-            return None;
+    if let Some(audit_config) = config.audit.as_ref().filter(|a| a.enabled) {
+        if let Err(e) = create_audit_pack(output_path, videos, events, audit_config) {
+            eprintln!("Error creating audit pack: {}", e);
         }
+    }
 
-        let dribble_events = dribble_events_map.as_ref().unwrap();
+    if let Some(cvat_config) = config.cvat.as_ref().filter(|c| c.enabled) {
+        match write_cvat_annotations(output_path, videos, events, cvat_config) {
+            Ok(num_files) => println!("Wrote {} CVAT annotation files", num_files),
+            Err(e) => eprintln!("Error writing CVAT annotations: {}", e),
+        }
+    }
 
-        if let Some(event) = dribble_events.get(&vid_name) {
-            println!(
-                " * Found dribble events for video {}: {:?}",
-                vid_name, event
-            );
-            event.clone()
-        } else {
-            return None;
+    if let Some(spotting_config) = config.spotting.as_ref().filter(|s| s.enabled) {
+        match write_spotting_results(output_path, videos, events, spotting_config) {
+            Ok(num_games) => println!("Wrote the spotting results of {} games", num_games),
+            Err(e) => eprintln!("Error writing spotting results: {}", e),
         }
-    } else {
-        Vec::new()
-    };
+    }
+}
 
-    let total_num_events = vid_events.len();
-    let processed_events = 0;
+/// Writes the enabled exports of the events in `events_path`, e.g. the report of an earlier
+/// run, without running the detector again.
+fn run_export(config: &Config, dataset: &Dataset, events_path: &Path) {
+    // The events of a run that crashed are only in the event stream next to it
+    let stream_path = events_path.with_file_name(EVENT_STREAM_FILE);
+    let missing = events_path.ends_with("dribble_events.json") && !events_path.exists();
+    if missing && stream_path.exists() {
+        match read_event_stream(&stream_path) {
+            Ok(videos) => {
+                println!("Compacting {} into {}", stream_path.display(), events_path.display());
+                let output_dir = events_path.parent().unwrap_or(Path::new("."));
+                write_dribble_events(output_dir, videos, true);
+            }
+            Err(e) => eprintln!("Error reading {}: {}", stream_path.display(), e),
+        }
+    }
 
-    let image_map: HashMap<String, String> = video_data
-        .labels
-        .images
+    let export: DribbleEventsExport = match fs::read_to_string(events_path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(export) => export,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", events_path.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let events: HashMap<String, Vec<DribbleEvent>> = export
+        .videos
         .iter()
-        .map(|image| (image.file_name.clone(), image.image_id.clone()))
+        .map(|video| (video.key(), video.to_events()))
         .collect();
-
-    let category_map: HashMap<String, u32> = video_data
-        .labels
-        .categories
+    let videos: Vec<VideoData> = dataset
+        .subsets
         .iter()
-        .map(|c| (c.name.clone(), c.id))
+        .flat_map(|subset| dataset.iter_subset(subset))
+        .filter_map(|v| v.ok())
+        .filter(|v| events.contains_key(&v.key()))
         .collect();
+    println!(
+        "Exporting {} events of {} videos from {}",
+        events.values().map(Vec::len).sum::<usize>(),
+        videos.len(),
+        events_path.display()
+    );
 
-    let annotations: Vec<Annotation> = video_data.labels.annotations.clone();
-    // let file_name = format!("video_{}", vid_num);
-    let file_name = vid_name.clone();
-
-    let mut visualization_builder =
-        VisualizationBuilder::new(video_mode.as_str(), &file_name, &config)
-            .expect("Failed to create visualization builder");
-
-    let mut detected_events: Vec<DribbleEvent> = Vec::new();
+    let output_path = Path::new(&config.data.output_path);
+    fs::create_dir_all(output_path).expect("Error creating output directory");
+    write_exports(output_path, &videos, &events, &export, config);
+}
 
-    // Store a clone of vid_events
-    let mut current_interval = if !vid_events.is_empty() {
-        vid_events.remove(0)
-    } else {
-        (0, video_data.image_paths.len() as u32 - 1)
+/// Lets the user label the events of `dribble_events.json` and exports the reviewed clips.
+fn run_review(
+    config: &Config,
+    videos: &[VideoData],
+    pool: &ThreadPool,
+    cancel: &CancellationToken,
+    start_time: DateTime<Utc>,
+) {
+    let review = config.review.clone().unwrap_or_default();
+    if let Some(Err(e)) = review.annotator.as_deref().map(check_annotator) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let queue = match load_review_queue(Path::new(&config.data.dribble_events_path), &review) {
+        Ok(queue) => queue,
+        Err(e) => {
+            eprintln!(
+                "Error reading dribble events file {}: {}",
+                config.data.dribble_events_path, e
+            );
+            std::process::exit(1);
+        }
     };
+    let videos_by_name: HashMap<String, &VideoData> =
+        videos.iter().map(|video| (video.name(), video)).collect();
+    println!(
+        "Reviewing {} events in {:?} order",
+        queue.len(),
+        review.order
+    );
+    let all_reviewed_video_data = Mutex::new(Some(Vec::new()));
 
-    let mut start = current_interval.0;
-    let mut end = current_interval.1;
-
-    let mut frame_num;
+    if review.resume {
+        let last_decision = ReviewLog::open(
+            Path::new(&config.data.output_path),
+            Path::new(&config.data.dribble_events_path),
+            review.annotator.as_deref(),
+        )
+        .ok()
+        .and_then(|log| log.last_decision().cloned());
+        match last_decision {
+            Some(position) => println!(
+                "Resuming the review, the last decision was on event {} of {}",
+                position.event_index + 1,
+                position.video_id
+            ),
+            None => println!("Resuming the review, no decisions were made yet"),
+        }
+    }
 
-    let iterator_start = video_data.image_paths.clone().into_iter();
+    // The clips are reviewed in queue order, one run of clips of the same video at a time
+    for (video_id, intervals) in group_by_video(&queue) {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let Some(video) = videos_by_name.get(&video_id) else {
+            println!("Skipping events of {}, the video is not in the dataset", video_id);
+            continue;
+        };
+        let dribble_events_map = Some(HashMap::from([(video_id, intervals)]));
+        run_detection(
+            std::slice::from_ref(*video),
+            config,
+            pool,
+            cancel,
+            &dribble_events_map,
+            &all_reviewed_video_data,
+            None,
+            None,
+            None,
+            &ProgressSender::disabled(),
+            &Watchdog::disabled(),
+        );
+    }
 
-    let mut iterator = iterator_start.clone();
-    let mut cur_path = iterator.next();
+    let cur_time = Utc::now();
+    let duration = cur_time - start_time;
+    let all_reviewed_video_data = all_reviewed_video_data
+        .into_inner()
+        .unwrap()
+        .unwrap_or_default();
 
-    let mut replay = false;
+    println!(
+        "\n\nReview mode done in {}H:{}M:{}S",
+        duration.num_hours(),
+        duration.num_minutes() % 60,
+        duration.num_seconds() % 60
+    );
 
-    let mut current_frames = current_interval.clone();
+    let total_dribbles: usize = all_reviewed_video_data
+        .iter()
+        .map(|r| r.dribble_data.len())
+        .sum();
+    let total_tackles: usize = all_reviewed_video_data
+        .iter()
+        .map(|r| r.tackle_data.len())
+        .sum();
+    let total_others: usize = all_reviewed_video_data
+        .iter()
+        .map(|r| r.other_data.len())
+        .sum();
 
-    while cur_path.is_some() && end != 0 {
-        if current_frames != current_interval {
-            println!("Displaying frames ({start}-{end})");
-            current_frames = current_interval.clone();
-        };
+    println!(
+        "Approved {} dribbles, {} tackles and disaproved {} events",
+        total_dribbles, total_tackles, total_others,
+    );
+    if review.blind {
+        let counts =
+            VerdictCounts::from_events(all_reviewed_video_data.iter().flat_map(|r| &r.events));
+        if let Some(agreement) = counts.agreement() {
+            println!(
+                "Blind review: agreed with the detector on {} of {} events ({:.1}%), \
+                 reclassified {} and rejected {}",
+                counts.accepted,
+                counts.total(),
+                agreement * 100.0,
+                counts.reclassified,
+                counts.rejected
+            );
+        }
+    }
+    println!(
+        "All decisions are in {}",
+        Path::new(&config.data.output_path)
+            .join(review_log_file(review.annotator.as_deref()))
+            .display()
+    );
 
-        let image_path = cur_path.clone().unwrap();
-        let image_name = cur_path
-            .clone()?
-            .file_stem()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
+    println!(
+        "\n\nExporting reviewed data to {}...",
+        config.data.output_path
+    );
 
-        frame_num = image_name
-            .parse::<usize>()
-            .expect("Failed to parse frame number from image name");
+    if let Err(e) = export_reviewed_data(
+        Path::new(&config.data.output_path),
+        &all_reviewed_video_data,
+    ) {
+        eprintln!("Error exporting reviewed data: {}", e);
+    }
 
-        if EXIT_FLAG.load(Ordering::Relaxed) {
-            break;
-        }
+    match export_training_labels(config, &review, videos) {
+        Ok(()) => println!(
+            "Wrote the accepted events to {}",
+            Path::new(&config.data.output_path)
+                .join(TRAINING_LABELS_FILE)
+                .display()
+        ),
+        Err(e) => eprintln!("Error writing {}: {}", TRAINING_LABELS_FILE, e),
+    }
+}
 
-        if review_mode {
-            if processed_events >= total_num_events {
-                println!("No more events to process (1)");
-                break;
-            }
+/// Writes the events accepted in all review sessions of the annotator, with their reviewed
+/// classes and boundaries, as training labels.
+fn export_training_labels(
+    config: &Config,
+    review: &ReviewConfig,
+    videos: &[VideoData],
+) -> std::io::Result<()> {
+    let output_path = Path::new(&config.data.output_path);
+    let events: DribbleEventsExport =
+        serde_json::from_str(&fs::read_to_string(&config.data.dribble_events_path)?)?;
+    let reviewed: ReviewedEventsExport = serde_json::from_str(&fs::read_to_string(
+        output_path.join(review_log_file(review.annotator.as_deref())),
+    )?)?;
+    let timings: HashMap<String, ClipTiming> = videos
+        .iter()
+        .map(|video| (video.name(), ClipTiming::new(&video.labels.info)))
+        .collect();
+    let labels = training_labels(&events, &reviewed.videos, &timings);
+    write_training_labels(output_path, &labels, review.frame_labels)
+}
 
-            if frame_num < start as usize {
-                cur_path = iterator.next();
-                continue;
-            }
-            if frame_num > end as usize {
-                if processed_events >= total_num_events {
-                    println!("No more events to process (2)");
-                    break;
+/// Derives a ground truth event from the action of every sequence of the configured subsets,
+/// see [`dribbling_detection_algorithm::bootstrap`], and writes it to `BOOTSTRAP_FILE`.
+fn run_bootstrap(config: &Config, dataset: &Dataset) {
+    let bootstrap = config.bootstrap.clone().unwrap_or_default();
+    let mut videos = Vec::new();
+    let mut table = Table::new();
+    table.add_row(row!["Subset", "Sequences", "Dribbles", "Tackles"]);
+    for subset in &dataset.subsets {
+        let mut num_sequences = 0;
+        let mut subset_videos = Vec::new();
+        for video_data in dataset.iter_subset(subset) {
+            match video_data {
+                Ok(video_data) => {
+                    num_sequences += 1;
+                    subset_videos.extend(bootstrap_video(&video_data, Some(subset), &bootstrap));
                 }
-                current_interval = if !vid_events.is_empty() {
-                    vid_events.remove(0)
-                } else {
-                    (0, 0)
-                };
-                start = current_interval.0;
-                end = current_interval.1;
-                continue;
+                Err(e) => eprintln!("Error loading a sequence of {}: {}", subset, e),
             }
         }
+        let events = subset_videos.iter().flat_map(|v| &v.dribble_events);
+        let num_tackles = events.clone().filter(|e| e.detected_tackle).count();
+        table.add_row(row![
+            subset,
+            num_sequences,
+            events.count() - num_tackles,
+            num_tackles
+        ]);
+        videos.extend(subset_videos);
+    }
+    table.printstd();
+
+    let num_videos = videos.len();
+    let output_path = Path::new(&config.data.output_path);
+    match write_bootstrap(output_path, videos) {
+        Ok(()) => println!(
+            "Wrote the events of {} sequences to {}, evaluate with --ground-truth",
+            num_videos,
+            output_path.join(BOOTSTRAP_FILE).display()
+        ),
+        Err(e) => eprintln!("Error writing {}: {}", BOOTSTRAP_FILE, e),
+    }
+}
 
-        let image_file_name = image_path
-            .to_string_lossy()
-            .split('/')
-            .last()
-            .unwrap_or("")
-            .to_string();
-
-        let image_id = image_map.get(&image_file_name).unwrap_or(&image_file_name);
+/// Compares the events of `events_path` with the ground truth of `[evaluation]`, prints the
+/// precision, recall, confusion and errors of every video and writes `evaluation.json`, and
+/// the clips of the errors if enabled.
+fn run_evaluation(config: &Config, dataset: &Dataset, events_path: &Path) {
+    let evaluation = config.evaluation.clone().unwrap_or_default();
+    let Some(ground_truth) = &evaluation.ground_truth else {
+        eprintln!("No ground truth to evaluate against, set --ground-truth or [evaluation]");
+        std::process::exit(1);
+    };
+    let iou_threshold = evaluation.iou_threshold();
+    let report = match evaluate_files(events_path, Path::new(ground_truth), &evaluation) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error evaluating {}: {}", events_path.display(), e);
+            std::process::exit(1);
+        }
+    };
 
-        let mut frame =
-            imgcodecs::imread(image_path.to_str().unwrap(), imgcodecs::IMREAD_COLOR).unwrap();
+    println!("\nEvaluation against {} (IoU >= {})", ground_truth, iou_threshold);
+    let mut table = Table::new();
+    table.add_row(row![
+        "Class",
+        "Matched",
+        "Detected",
+        "Ground truth",
+        "Precision",
+        "Recall",
+        "F1"
+    ]);
+    let classes = std::iter::once(("all", &report.overall))
+        .chain(report.classes.iter().map(|(class, metrics)| (class.as_str(), metrics)));
+    for (class, metrics) in classes {
+        table.add_row(row![
+            class,
+            metrics.true_positives,
+            metrics.num_detected,
+            metrics.num_ground_truth,
+            format!("{:.3}", metrics.precision),
+            format!("{:.3}", metrics.recall),
+            format!("{:.3}", metrics.f1)
+        ]);
+    }
+    table.printstd();
+
+    // Ground truth classes in rows, detected classes in columns
+    let labels = ["dribble", "tackle", "none"];
+    let mut confusion = Table::new();
+    confusion.add_row(row!["Ground truth", "Detected dribble", "Detected tackle", "Missed"]);
+    for expected in labels {
+        let counts: Vec<usize> = labels
+            .iter()
+            .map(|found| {
+                report
+                    .confusion
+                    .get(expected)
+                    .and_then(|row| row.get(*found))
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let expected = if expected == "none" { "False detection" } else { expected };
+        confusion.add_row(row![expected, counts[0], counts[1], counts[2]]);
+    }
+    confusion.printstd();
 
-        let filtered_annotations = filter_annotations(
-            image_id,
-            annotations.clone(),
-            &category_map,
-            config.dribbling_detection.ignore_person_classes,
-            config.dribbling_detection.ignore_teams,
+    if let Some(boundaries) = &report.boundaries {
+        println!(
+            "\nBoundary offsets of the {} matches (detected minus ground truth)",
+            boundaries.num_matched
         );
-        let ball_model = get_ball_model(&category_map, &filtered_annotations, &config);
-        let player_models = get_player_models(&category_map, &filtered_annotations, &config);
-
-        if player_models.is_none() {
-            println!("(In main): No players found in frame. Skipping frame...");
-            // frame_num += 1;
-            cur_path = iterator.next();
-            continue;
+        let mut offsets = Table::new();
+        offsets.add_row(row![
+            "Boundary",
+            "Mean (frames)",
+            "Median (frames)",
+            "Mean absolute (frames)",
+            "Mean (s)",
+            "Median (s)",
+            "Mean absolute (s)"
+        ]);
+        for (boundary, stats) in [("start", &boundaries.start), ("end", &boundaries.end)] {
+            offsets.add_row(row![
+                boundary,
+                format!("{:.2}", stats.mean_frames),
+                format!("{:.1}", stats.median_frames),
+                format!("{:.2}", stats.mean_abs_frames),
+                format!("{:.3}", stats.mean_seconds),
+                format!("{:.3}", stats.median_seconds),
+                format!("{:.3}", stats.mean_abs_seconds)
+            ]);
         }
+        offsets.printstd();
+        println!(
+            "{:.1}% of the matches have both boundaries within {} s",
+            boundaries.within_tolerance * 100.0,
+            boundaries.tolerance_secs
+        );
+    }
 
-        let dribble_frame = DribbleFrame {
-            frame_number: frame_num as u32,
-            players: player_models.unwrap(),
-            ball: ball_model.unwrap_or(Ball { x: 0.0, y: 0.0 }),
-        };
-
-        let potential_event = dribble_detector.process_frame(dribble_frame);
+    let ranges = |events: &[EventRange]| {
+        events
+            .iter()
+            .map(|event| format!("{}-{} {}", event.start_frame, event.end_frame, event.class))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let mut errors = Table::new();
+    errors.add_row(row!["Video", "Matched", "False positives", "Misses"]);
+    for video in &report.videos {
+        errors.add_row(row![
+            video.video_id,
+            video.matched.len(),
+            ranges(&video.false_positives),
+            ranges(&video.misses)
+        ]);
+    }
+    errors.printstd();
+    if !report.missing_videos.is_empty() {
+        println!(
+            "{} videos of the ground truth are not in {} and were left out",
+            report.missing_videos.len(),
+            events_path.display()
+        );
+    }
 
-        if let Some(mut dribble_event) = potential_event.clone() {
-            if !replay && (dribble_event.detected_dribble || dribble_event.detected_tackle) {
-                // println!("\n\n\nDetected dribble event: {:?}", dribble_event.frames);
-                let extra_frames_before = 60;
-                let extra_frames_after = 60;
-                dribble_event.start_frame = dribble_event
-                    .start_frame
-                    .saturating_sub(extra_frames_before);
+    let output_path = Path::new(&config.data.output_path);
+    match write_evaluation(output_path, &report) {
+        Ok(()) => println!("Wrote {}", output_path.join(EVALUATION_FILE).display()),
+        Err(e) => eprintln!("Error writing {}: {}", EVALUATION_FILE, e),
+    }
 
-                if let Some(cur_end) = dribble_event.end_frame {
-                    dribble_event.end_frame = Some(cur_end + extra_frames_after);
-                };
+    if evaluation.pr_curve || evaluation.pr_curve_image {
+        run_pr_curve(&evaluation, Path::new(ground_truth), events_path, output_path);
+    }
 
-                detected_events.push(dribble_event);
-            }
+    if evaluation.error_clips {
+        let videos: Vec<VideoData> = dataset
+            .subsets
+            .iter()
+            .flat_map(|subset| dataset.iter_subset(subset))
+            .filter_map(|v| v.ok())
+            .filter(|video| {
+                let name = video.name();
+                report
+                    .videos
+                    .iter()
+                    .any(|v| v.video_id == name && v.has_errors())
+            })
+            .collect();
+        if let Err(e) = create_error_clips(output_path, &videos, &report, config) {
+            eprintln!("Error creating error clips: {}", e);
         }
+    }
+}
 
-        if config.general.video_mode == "display" {
-            visualization_builder
-                .add_frame(
-                    &mut frame,
-                    Some(image_id),
-                    Some(&filtered_annotations),
-                    &category_map,
-                    inner_rad,
-                    outer_rad,
-                )
-                .expect("Failed to add frame");
+/// Evaluates the events of `events_path` at every confidence threshold and writes the
+/// precision-recall curve, see [`pr_curve`].
+fn run_pr_curve(
+    evaluation: &EvaluationConfig,
+    ground_truth: &Path,
+    events_path: &Path,
+    output_path: &Path,
+) {
+    let points = match (read_events(events_path), read_events(ground_truth)) {
+        (Ok(detected), Ok(expected)) => {
+            pr_curve(&detected, &expected, evaluation.iou_threshold())
         }
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error computing the precision-recall curve: {}", e);
+            return;
+        }
+    };
+    let Some(best) = best_f1(&points) else {
+        println!("No detected event has a confidence, there is no precision-recall curve");
+        return;
+    };
+    println!(
+        "\nPrecision-recall curve over {} confidence thresholds, best F1 {:.3} at confidence >= {} \
+         (precision {:.3}, recall {:.3})",
+        points.len(),
+        best.metrics.f1,
+        best.threshold,
+        best.metrics.precision,
+        best.metrics.recall
+    );
 
-        let input_value =
-            wait_for_keyboard_input(&config).expect("There was an error with keyboard input");
-
-        match input_value {
-            KeyboardInput::Quit => {
-                EXIT_FLAG.store(true, Ordering::Relaxed);
-                visualization_builder
-                    .finish()
-                    .expect("Failed to finish visualization");
-                println!("Quitting...");
-                break;
-            }
-            KeyboardInput::NextFrame => {
-                cur_path = iterator.next();
-            }
-            KeyboardInput::PreviousFrame => {}
-            KeyboardInput::NextClip => {
-                cur_path = iterator.next();
-                replay = false;
-
-                current_interval = if !vid_events.is_empty() {
-                    vid_events.remove(0)
-                } else {
-                    (0, 0)
-                };
-
-                start = current_interval.0;
-                end = current_interval.1;
+    match write_pr_curve_csv(output_path, &points) {
+        Ok(()) => println!("Wrote {}", output_path.join(PR_CURVE_FILE).display()),
+        Err(e) => eprintln!("Error writing {}: {}", PR_CURVE_FILE, e),
+    }
+    if evaluation.pr_curve_image {
+        match write_pr_curve_image(output_path, &points) {
+            Ok(()) => println!("Wrote {}", output_path.join(PR_CURVE_IMAGE).display()),
+            Err(e) => eprintln!("Error writing {}: {}", PR_CURVE_IMAGE, e),
+        }
+    }
+}
 
-                visualization_builder
-                    .finish()
-                    .expect("Failed to finish visualization");
-            }
-            KeyboardInput::Dribble => {
-                if review_mode {
-                    println!("Adding dribble event");
-
-                    let filtered_video_data = filter_video_data(video_data.clone(), start, end);
-                    reviewed_video_data
-                        .as_mut()
-                        .unwrap()
-                        .dribble_data
-                        .push(filtered_video_data);
-
-                    cur_path = iterator.next();
-                    replay = false;
-
-                    current_interval = if !vid_events.is_empty() {
-                        vid_events.remove(0)
-                    } else {
-                        (0, 0)
-                    };
+/// Prints the key codes of the display window, see [`probe_keys`].
+fn run_key_probe(config: &Config) {
+    let keymap = Keymap::from_config(config).unwrap_or_default();
+    if let Err(e) = probe_keys(&keymap) {
+        eprintln!("Error probing keys: {}", e);
+        std::process::exit(1);
+    }
+}
 
-                    start = current_interval.0;
-                    end = current_interval.1;
+/// Merges the review logs of all annotators in the output directory, prints their agreement
+/// and writes `review_report.json`.
+fn run_review_report(config: &Config) {
+    let output_path = Path::new(&config.data.output_path);
+    let logs = match load_review_logs(output_path) {
+        Ok(logs) => logs,
+        Err(e) => {
+            eprintln!("Error reading the review logs in {}: {}", output_path.display(), e);
+            std::process::exit(1);
+        }
+    };
+    if logs.len() < 2 {
+        eprintln!(
+            "Found {} review logs in {}, agreement needs at least two annotators",
+            logs.len(),
+            output_path.display()
+        );
+        std::process::exit(1);
+    }
+    let report = create_review_report(&logs);
+
+    let kappa = |kappa: Option<f64>| kappa.map_or("-".to_string(), |k| format!("{:.3}", k));
+    let mut table = Table::new();
+    table.add_row(row![
+        "Annotators",
+        "Shared events",
+        "Verdict agreement",
+        "Verdict kappa",
+        "Class agreement",
+        "Class kappa"
+    ]);
+    for pair in &report.agreement {
+        table.add_row(row![
+            format!("{} / {}", pair.annotators.0, pair.annotators.1),
+            pair.num_events,
+            format!("{:.1}%", pair.verdict_agreement * 100.0),
+            kappa(pair.verdict_kappa),
+            format!("{:.1}%", pair.class_agreement * 100.0),
+            kappa(pair.class_kappa)
+        ]);
+    }
+    table.printstd();
+
+    if !report.disagreements.is_empty() {
+        let mut disagreements = Table::new();
+        disagreements.add_row(row!["Video", "Frames", "Labels"]);
+        for disagreement in &report.disagreements {
+            let labels: Vec<String> = disagreement
+                .labels
+                .iter()
+                .map(|(annotator, label)| format!("{}: {}", annotator, label))
+                .collect();
+            disagreements.add_row(row![
+                disagreement.video_id,
+                format!("{}-{}", disagreement.start_frame, disagreement.end_frame),
+                labels.join(", ")
+            ]);
+        }
+        disagreements.printstd();
+    }
+    println!(
+        "{} events to adjudicate, {} annotators",
+        report.disagreements.len(),
+        report.annotators.len()
+    );
 
-                    all_reviewed_video_data
-                        .lock()
-                        .unwrap()
-                        .as_mut()
-                        .unwrap()
-                        .push(reviewed_video_data.clone().unwrap());
-                    continue;
-                }
-            }
-            KeyboardInput::Tackle => {
-                if review_mode {
-                    println!("Adding tackle event");
-                    let filtered_video_data = filter_video_data(video_data.clone(), start, end);
-                    reviewed_video_data
-                        .as_mut()
-                        .unwrap()
-                        .tackle_data
-                        .push(filtered_video_data);
-                    cur_path = iterator.next();
-                    replay = false;
-
-                    current_interval = if !vid_events.is_empty() {
-                        vid_events.remove(0)
-                    } else {
-                        (0, 0)
-                    };
+    match write_review_report(output_path, &report) {
+        Ok(()) => println!("Wrote {}", output_path.join(REVIEW_REPORT_FILE).display()),
+        Err(e) => {
+            eprintln!("Error writing {}: {}", REVIEW_REPORT_FILE, e);
+            std::process::exit(1);
+        }
+    }
+}
 
-                    start = current_interval.0;
-                    end = current_interval.1;
+/// Writes a video with the annotations of every video of the configured subsets to the output
+/// directory, without running the detector. Like in a detection run, every subset has its own
+/// output directory if there are several of them.
+fn run_render_only(
+    config: &Config,
+    dataset: &Dataset,
+    pool: &ThreadPool,
+    cancel: &CancellationToken,
+) {
+    let multiple_subsets = dataset.subsets.len() > 1;
+    for subset in &dataset.subsets {
+        let mut subset_config = config.clone();
+        if multiple_subsets {
+            subset_config.data.output_path = Path::new(&config.data.output_path)
+                .join(subset)
+                .to_string_lossy()
+                .to_string();
+        }
+        let videos: Vec<VideoData> = dataset
+            .iter_subset(subset)
+            .filter_map(|v| v.ok())
+            .collect();
+        println!("Rendering {} videos of {}", videos.len(), subset);
 
-                    all_reviewed_video_data
-                        .lock()
-                        .unwrap()
-                        .as_mut()
-                        .unwrap()
-                        .push(reviewed_video_data.clone().unwrap());
-                    continue;
-                }
-            }
-            KeyboardInput::None => {
-                if review_mode {
-                    println!("Adding other event");
-                    let filtered_video_data = filter_video_data(video_data.clone(), start, end);
-
-                    reviewed_video_data
-                        .as_mut()
-                        .unwrap()
-                        .other_data
-                        .push(filtered_video_data);
-                    cur_path = iterator.next();
-                    replay = false;
-
-                    current_interval = if !vid_events.is_empty() {
-                        vid_events.remove(0)
-                    } else {
-                        (0, 0)
-                    };
+        let num_rendered = pool.install(|| render_videos(&videos, &subset_config, cancel));
+        println!(
+            "Wrote {} annotated videos to {}",
+            num_rendered, subset_config.data.output_path
+        );
+    }
+}
 
-                    start = current_interval.0;
-                    end = current_interval.1;
+/// Validates all configured subsets and writes `validation_report.json` to the output directory.
+fn run_validation(config: &Config, dataset: &Dataset) {
+    println!("Validating subsets {:?}", dataset.subsets);
+    let report = dataset.validate();
 
-                    all_reviewed_video_data
-                        .lock()
-                        .unwrap()
-                        .as_mut()
-                        .unwrap()
-                        .push(reviewed_video_data.clone().unwrap());
+    let mut table = Table::new();
+    table.add_row(row!["Subset", "Sequence", "Issues"]);
+    for sequence in report.sequences.iter().filter(|s| !s.issues.is_empty()) {
+        table.add_row(row![sequence.subset, sequence.sequence, sequence.issues.len()]);
+    }
+    if !report.is_valid() {
+        table.printstd();
+    }
 
-                    continue;
-                }
-            }
-        }
+    println!(
+        "Found {} issues in {} of {} sequences",
+        report.num_issues, report.num_invalid_sequences, report.num_sequences
+    );
 
-        // Replay clip
-        if review_mode && (frame_num >= end as usize || cur_path.is_none()) {
-            iterator = iterator_start
-                .clone()
-                // .skip(frame_num)
-                .collect::<Vec<_>>()
-                .into_iter();
-            cur_path = iterator.next();
-            replay = true;
-        }
+    let report_path = Path::new(&config.data.output_path).join("validation_report.json");
+    let result = fs::create_dir_all(&config.data.output_path).and_then(|_| {
+        let json_data = serde_json::to_string_pretty(&report)?;
+        fs::write(&report_path, json_data)
+    });
+    match result {
+        Ok(_) => println!("Wrote validation report to {}", report_path.display()),
+        Err(e) => eprintln!("Error writing validation report: {}", e),
     }
+}
 
-    let merged_events = combine_consecutive_events(detected_events);
-
-    if log_level == "debug" {
-        if review_mode {
-            println!(" * Finished processing {} events\n", total_num_events);
-        } else {
-            println!(" * Finished processing {} events\n", merged_events.len());
+/// Serves detection over gRPC on `addr` until the process is stopped.
+#[cfg(feature = "grpc")]
+fn run_server(config: &Config, addr: &str) {
+    let addr = match addr.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("Invalid address {:?}: {}", addr, e);
+            std::process::exit(1);
         }
+    };
+    let rt = Runtime::new().unwrap();
+    if let Err(e) = rt.block_on(dribbling_detection_algorithm::grpc::serve(addr, config.clone())) {
+        eprintln!("Error serving on {}: {}", addr, e);
+        std::process::exit(1);
     }
-
-    Some((file_name, merged_events))
 }
 
-/// Merges consecutive dribble events if the start of one event
-/// is immediately after (or within max_event_gap) the end of the previous event,
-/// and both events are of the same type (dribble or tackle).
-fn combine_consecutive_events(mut events: Vec<DribbleEvent>) -> Vec<DribbleEvent> {
-    events.sort_by_key(|e| e.start_frame);
+/// Without the `grpc` feature there is no server, so this exits with an error.
+#[cfg(not(feature = "grpc"))]
+fn run_server(_config: &Config, _addr: &str) {
+    eprintln!("The serve command needs the grpc feature: cargo run --features grpc -- serve");
+    std::process::exit(1);
+}
 
-    let max_event_gap = 8;
+/// Prints the sequences a detection run would process, with their estimated output size and
+/// runtime, without processing them.
+fn run_dry_run(config: &Config, dataset: &Dataset) {
+    println!(
+        "Dry run of {} with video mode {:?} on {} cores",
+        config
+            .source
+            .as_ref()
+            .map_or("the default config".to_string(), |s| s.path.display().to_string()),
+        config.general.video_mode,
+        config.general.num_cores
+    );
 
-    let mut merged: Vec<DribbleEvent> = Vec::new();
-    for event in events {
-        if let Some(last) = merged.last_mut() {
-            if let Some(last_end) = last.end_frame {
-                let same_type = (last.detected_tackle && event.detected_tackle)
-                    || (last.detected_dribble && event.detected_dribble);
+    let plans: Vec<SubsetPlan> = dataset
+        .subsets
+        .iter()
+        .map(|subset| dataset.plan_subset(subset))
+        .collect();
 
-                if event.start_frame <= last_end + max_event_gap && same_type {
-                    last.extend(&event);
+    let mut table = Table::new();
+    table.add_row(row!["Subset", "Sequence", "Frames", "Resolution", "Output", "Runtime"]);
+    for plan in &plans {
+        for s in &plan.sequences {
+            table.add_row(row![
+                plan.subset,
+                s.name,
+                s.num_frames,
+                format!("{}x{}", s.width, s.height),
+                format_bytes(s.estimated_output_bytes(config)),
+                format_duration(estimate_runtime(s.num_frames, config))
+            ]);
+        }
+    }
+    table.printstd();
+
+    let mut summary = Table::new();
+    summary.add_row(row!["Subset", "Sequences", "Frames", "Output", "Runtime"]);
+    for plan in &plans {
+        summary.add_row(row![
+            plan.subset,
+            format!(
+                "{} ({} unreadable)",
+                plan.sequences.len(),
+                plan.num_unreadable_sequences
+            ),
+            plan.num_frames(),
+            format_bytes(plan.estimated_output_bytes(config)),
+            format_duration(estimate_runtime(plan.num_frames(), config))
+        ]);
+    }
+    let num_frames: usize = plans.iter().map(|p| p.num_frames()).sum();
+    let output_bytes: u64 = plans.iter().map(|p| p.estimated_output_bytes(config)).sum();
+    summary.add_row(row![
+        "Total",
+        plans.iter().map(|p| p.sequences.len()).sum::<usize>(),
+        num_frames,
+        format_bytes(output_bytes),
+        format_duration(estimate_runtime(num_frames, config))
+    ]);
+    summary.printstd();
+}
 
-                    if let Some(end) = event.end_frame {
-                        last.end_frame = Some(end);
-                    }
+/// Prints statistics of all configured subsets and writes them to `dataset_stats.json` in the
+/// output directory.
+fn run_stats(config: &Config, dataset: &Dataset) {
+    let stats: Vec<SubsetStats> = dataset
+        .subsets
+        .iter()
+        .map(|subset| dataset.stats(subset))
+        .collect();
 
-                    last.detected_tackle |= event.detected_tackle;
-                    last.detected_dribble |= event.detected_dribble;
-                    last.ever_contested |= event.ever_contested;
-                    continue;
-                }
-            }
+    let mut table = Table::new();
+    table.add_row(row![
+        "Subset",
+        "Sequences",
+        "Frames",
+        "Annotations",
+        "Missing ball",
+        "Pitch coords"
+    ]);
+    for s in &stats {
+        table.add_row(row![
+            s.subset,
+            format!("{} ({} unreadable)", s.num_sequences, s.num_unreadable_sequences),
+            s.num_frames,
+            s.num_annotations,
+            format!("{} ({:.1}%)", s.frames_missing_ball, s.missing_ball_ratio() * 100.0),
+            format!("{:.1}%", s.pitch_coverage * 100.0)
+        ]);
+    }
+    table.printstd();
+
+    let mut category_table = Table::new();
+    category_table.add_row(row!["Subset", "Category", "Annotations", "Per frame"]);
+    for s in &stats {
+        for (category, count) in &s.annotations_per_category {
+            let per_frame = s.avg_per_frame.get(category).copied().unwrap_or_default();
+            category_table.add_row(row![s.subset, category, count, format!("{:.2}", per_frame)]);
         }
-        merged.push(event);
     }
-    merged
+    category_table.printstd();
+
+    let stats_path = Path::new(&config.data.output_path).join("dataset_stats.json");
+    let result = fs::create_dir_all(&config.data.output_path).and_then(|_| {
+        let json_data = serde_json::to_string_pretty(&stats)?;
+        fs::write(&stats_path, json_data)
+    });
+    match result {
+        Ok(_) => println!("Wrote dataset statistics to {}", stats_path.display()),
+        Err(e) => eprintln!("Error writing dataset statistics: {}", e),
+    }
 }
 
-fn filter_video_data(video_data: VideoData, start: u32, end: u32) -> VideoData {
-    let mut filtered_data = VideoData::default();
-    filtered_data.dir_path = video_data.dir_path.clone();
+/// Assigns every sequence to a train/val/test split and writes the assignment to
+/// `splits.json` in the output directory.
+fn run_write_splits(config: &Config, dataset: &Dataset) {
+    let splits_config = config.splits.clone().unwrap_or_default();
+    let manifest = dataset.split_manifest(&splits_config);
+
+    let mut table = Table::new();
+    table.add_row(row!["Split", "Sequences"]);
+    for split in [Split::Train, Split::Val, Split::Test] {
+        let count = manifest.sequences.iter().filter(|s| s.split == split).count();
+        table.add_row(row![split, count]);
+    }
+    table.printstd();
+
+    let splits_path = Path::new(&config.data.output_path).join("splits.json");
+    let result = fs::create_dir_all(&config.data.output_path).and_then(|_| {
+        let json_data = serde_json::to_string_pretty(&manifest)?;
+        fs::write(&splits_path, json_data)
+    });
+    match result {
+        Ok(_) => println!("Wrote splits to {}", splits_path.display()),
+        Err(e) => eprintln!("Error writing splits: {}", e),
+    }
+}
 
-    // Helper to parse the zero-padded frame number from the filename (e.g. "0001.jpg" -> 1).
-    let in_range = |name: &str| -> bool {
-        if let Some(stem) = std::path::Path::new(name).file_stem() {
-            if let Ok(num) = stem.to_string_lossy().parse::<u32>() {
-                return num >= start && num <= end;
-            }
-        }
-        false
-    };
+/// Samples distances from the first `num_samples` videos and writes a config with suggested
+/// inner/outer radii to `suggested_config.toml` in the output directory.
+fn run_autotune(config: &Config, dataset: &Dataset, pool: &ThreadPool, num_samples: usize) {
+    println!("Running autotune on up to {} videos", num_samples);
 
-    let new_image_paths: Vec<PathBuf> = video_data
-        .image_paths
-        .into_iter()
-        .filter(|p| {
-            if let Some(fname) = p.file_name().map(|f| f.to_string_lossy().to_string()) {
-                in_range(&fname)
-            } else {
-                false
+    let videos: Vec<VideoData> = dataset
+        .subsets
+        .iter()
+        .flat_map(|subset| dataset.iter_subset(subset))
+        .filter_map(|v| v.ok())
+        .take(num_samples)
+        .filter_map(|mut v| match v.load_annotations() {
+            Ok(_) => Some(v),
+            Err(e) => {
+                eprintln!("Failed to load annotations for {:?}: {}", v.dir_path, e);
+                None
             }
         })
         .collect();
 
-    let new_images: Vec<Image> = video_data
-        .labels
-        .images
-        .into_iter()
-        .filter(|img| in_range(&img.file_name))
-        .collect();
+    let samples: Vec<_> = pool.install(|| {
+        videos
+            .par_iter()
+            .flat_map(|video_data| collect_distance_samples(video_data, config))
+            .collect()
+    });
+
+    let Some(suggestion) = suggest_radii(&samples) else {
+        eprintln!("Not enough ball/player data to suggest radii");
+        return;
+    };
+
+    let mut table = Table::new();
+    table.add_row(row!["", "Current", "Suggested"]);
+    table.add_row(row![
+        "inner_radius",
+        format!("{:.2}", config.dribbling_detection.inner_radius),
+        format!("{:.2}", suggestion.inner_radius)
+    ]);
+    table.add_row(row![
+        "outer_radius",
+        format!("{:.2}", config.dribbling_detection.outer_radius),
+        format!("{:.2}", suggestion.outer_radius)
+    ]);
+    table.printstd();
+
+    println!(
+        "Sampled {} frames from {} videos ({} possession frames with defenders)",
+        suggestion.num_frames,
+        videos.len(),
+        suggestion.num_possession_frames
+    );
+    println!(
+        "Median holder-ball distance: {:.2}, median nearest-defender distance: {:.2}",
+        suggestion.holder_ball_median, suggestion.nearest_defender_median
+    );
 
-    let valid_ids: std::collections::HashSet<String> =
-        new_images.iter().map(|img| img.image_id.clone()).collect();
+    let suggested_path = Path::new(&config.data.output_path).join("suggested_config.toml");
+    match write_suggested_config(config, &suggestion, &suggested_path) {
+        Ok(_) => println!("Wrote suggested config to {}", suggested_path.display()),
+        Err(e) => eprintln!("Error writing suggested config: {}", e),
+    }
+}
 
-    let new_annotations: Vec<Annotation> = video_data
-        .labels
-        .annotations
-        .into_iter()
-        .filter(|ann| valid_ids.contains(&ann.image_id))
+/// Runs detection on the videos of the configured subsets for every combination of the
+/// `[sweep]` parameters, and writes the event counts (and metrics, with a ground truth) of each
+/// to `sweep.csv` in the output directory.
+fn run_parameter_sweep(
+    config: &Config,
+    dataset: &Dataset,
+    pool: &ThreadPool,
+    cancel: &CancellationToken,
+) {
+    let Some(sweep) = &config.sweep else {
+        eprintln!("The sweep command needs a [sweep] section in the config");
+        std::process::exit(1);
+    };
+    let videos: Vec<VideoData> = dataset
+        .subsets
+        .iter()
+        .flat_map(|subset| dataset.iter_subset(subset))
+        .filter_map(|v| v.ok())
         .collect();
 
-    filtered_data.image_paths = new_image_paths;
-    filtered_data.labels.images = new_images;
-    filtered_data.labels.annotations = new_annotations;
-    filtered_data.labels.categories = video_data.labels.categories;
+    let reporter = if config.general.progress.unwrap_or(true) {
+        ProgressReporter::start()
+    } else {
+        ProgressReporter::disabled()
+    };
+    let results = run_sweep(&videos, config, sweep, pool, cancel, reporter.sender());
+    reporter.finish();
+    let results = match results {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("Error running the sweep: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("Swept {} combinations over {} videos", results.len(), videos.len());
+
+    let mut table = Table::new();
+    table.add_row(row![
+        "Inner radius",
+        "Outer radius",
+        "Thresholds (inner/outer/in/out)",
+        "Dribbles",
+        "Tackles",
+        "Precision",
+        "Recall",
+        "F1",
+        "Start offset (s)",
+        "End offset (s)"
+    ]);
+    for result in &results {
+        let p = &result.params;
+        let metric = |value: fn(&EventMatches) -> f64| {
+            result
+                .matches
+                .as_ref()
+                .map_or(String::new(), |m| format!("{:.3}", value(m)))
+        };
+        // Median offsets, detected minus ground truth
+        let (start_offset, end_offset) = result.boundaries.map_or_else(
+            || (String::new(), String::new()),
+            |b| {
+                (
+                    format!("{:+.2}", b.start.median_seconds),
+                    format!("{:+.2}", b.end.median_seconds),
+                )
+            },
+        );
+        table.add_row(row![
+            p.inner_radius,
+            p.outer_radius,
+            format!(
+                "{}/{}/{}/{}",
+                p.inner_threshold, p.outer_threshold, p.outer_in_threshold, p.outer_out_threshold
+            ),
+            result.num_dribbles,
+            result.num_tackles,
+            metric(EventMatches::precision),
+            metric(EventMatches::recall),
+            metric(EventMatches::f1),
+            start_offset,
+            end_offset
+        ]);
+    }
+    table.printstd();
 
-    filtered_data
+    let output_path = Path::new(&config.data.output_path);
+    match write_sweep_csv(output_path, &results) {
+        Ok(_) => println!("Wrote sweep results to {}", output_path.join(SWEEP_FILE).display()),
+        Err(e) => eprintln!("Error writing sweep results: {}", e),
+    }
 }
@@ -0,0 +1,283 @@
+//! Webhook notifications of the `[notifications]` section: a summary JSON is POSTed when a run
+//! completes or fails, and optionally every `every_n_events` detected events. The payload has a
+//! `text` field, so it can be sent to Slack and Teams incoming webhooks as is.
+//!
+//! The notifications are POSTed by a thread of their own, so slow webhooks do not hold up the
+//! workers, and a panic hook can report a panic even if it happens inside an async runtime.
+
+use crate::config::{Config, NotificationsConfig};
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    Completed,
+    Failed,
+    /// Another `every_n_events` events were detected
+    Progress,
+}
+
+/// The numbers of a run when it finished, or so far.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub num_videos: usize,
+    pub num_errors: usize,
+    pub num_dribbles: usize,
+    pub num_tackles: usize,
+    pub num_shielding: usize,
+    pub duration_secs: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+    /// Message shown by Slack and Teams
+    pub text: String,
+    pub kind: NotificationKind,
+    pub subsets: Vec<String>,
+    pub output_path: String,
+    /// Why the run failed
+    pub reason: Option<String>,
+    /// Events detected so far, for progress notifications
+    pub num_events: Option<usize>,
+    pub summary: Option<RunSummary>,
+}
+
+/// Sends the notifications of a run. Webhooks that can not be reached are reported, but never
+/// stop the run.
+pub struct Notifier {
+    config: NotificationsConfig,
+    subsets: Vec<String>,
+    output_path: String,
+    num_events: AtomicUsize,
+    /// Set once the run finished or failed, so a panic after that is not reported again
+    finished: AtomicBool,
+    /// Notifications for the sender thread, closed once the run finished
+    queue: Mutex<Option<Sender<Notification>>>,
+    sender: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Notifier {
+    /// A notifier for a run of `subsets`, `None` if notifications are disabled or no webhooks
+    /// are configured.
+    pub fn new(config: &Config, subsets: &[String]) -> Option<Self> {
+        let notifications = config.notifications.clone()?;
+        if !notifications.enabled || notifications.webhooks.is_empty() {
+            return None;
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(notifications.timeout_secs))
+            .build()
+            .unwrap_or_default();
+        let (queue, queued) = mpsc::channel::<Notification>();
+        let webhooks = notifications.webhooks.clone();
+        let sender = thread::spawn(move || {
+            let runtime = match Runtime::new() {
+                Ok(runtime) => runtime,
+                Err(e) => return eprintln!("Error starting the notifications runtime: {}", e),
+            };
+            for notification in queued {
+                send(&runtime, &client, &webhooks, &notification);
+            }
+        });
+        Some(Self {
+            config: notifications,
+            subsets: subsets.to_vec(),
+            output_path: config.data.output_path.clone(),
+            num_events: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+            queue: Mutex::new(Some(queue)),
+            sender: Mutex::new(Some(sender)),
+        })
+    }
+
+    /// Counts the `num_events` events of a finished video, and queues a progress notification
+    /// every time the total passes a multiple of `every_n_events`.
+    pub fn events_detected(&self, num_events: usize) {
+        let previous = self.num_events.fetch_add(num_events, Ordering::Relaxed);
+        let total = previous + num_events;
+        match self.config.every_n_events {
+            Some(every) if every > 0 && previous / every != total / every => {
+                self.queue(Notification {
+                    text: format!("Dribble detection: {} events detected so far", total),
+                    kind: NotificationKind::Progress,
+                    subsets: self.subsets.clone(),
+                    output_path: self.output_path.clone(),
+                    reason: None,
+                    num_events: Some(total),
+                    summary: None,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Reports the end of a run, as failed if there is a `reason`, and waits until the queued
+    /// notifications are sent.
+    pub fn run_finished(&self, summary: RunSummary, reason: Option<&str>) {
+        if self.finished.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let (kind, send) = match reason {
+            Some(_) => (NotificationKind::Failed, self.config.on_failure),
+            None => (NotificationKind::Completed, self.config.on_complete),
+        };
+        if send {
+            self.queue(self.final_notification(kind, summary, reason));
+        }
+        self.flush();
+    }
+
+    fn final_notification(
+        &self,
+        kind: NotificationKind,
+        summary: RunSummary,
+        reason: Option<&str>,
+    ) -> Notification {
+        let num_events = summary.num_dribbles + summary.num_tackles + summary.num_shielding;
+        let text = match reason {
+            Some(reason) => format!(
+                "Dribble detection of {} failed after {}s: {}. {} events in {} videos",
+                self.subsets.join(", "),
+                summary.duration_secs,
+                reason,
+                num_events,
+                summary.num_videos
+            ),
+            None => format!(
+                "Dribble detection of {} completed in {}s: {} events in {} videos, see {}",
+                self.subsets.join(", "),
+                summary.duration_secs,
+                num_events,
+                summary.num_videos,
+                self.output_path
+            ),
+        };
+        Notification {
+            text,
+            kind,
+            subsets: self.subsets.clone(),
+            output_path: self.output_path.clone(),
+            reason: reason.map(str::to_string),
+            num_events: Some(num_events),
+            summary: Some(summary),
+        }
+    }
+
+    /// Hands `notification` to the sender thread. Does nothing once the run finished.
+    fn queue(&self, notification: Notification) {
+        if let Some(queue) = lock(&self.queue).as_ref() {
+            let _ = queue.send(notification);
+        }
+    }
+
+    /// Closes the queue and waits for the sender thread to send what is left in it.
+    fn flush(&self) {
+        lock(&self.queue).take();
+        let Some(sender) = lock(&self.sender).take() else {
+            return;
+        };
+        // A panic of the sender thread itself is reported by it, so it can not wait for itself
+        if sender.thread().id() != thread::current().id() {
+            let _ = sender.join();
+        }
+    }
+}
+
+/// Locks `mutex` even if a panic poisoned it, as the panic hook still has to notify.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Posts `notification` to every webhook. Only called on the sender thread, which is not part of
+/// an async runtime.
+fn send(
+    runtime: &Runtime,
+    client: &reqwest::Client,
+    webhooks: &[String],
+    notification: &Notification,
+) {
+    for webhook in webhooks {
+        if let Err(e) = runtime.block_on(post(client, webhook, notification)) {
+            eprintln!("Error notifying {}: {}", webhook, e);
+        }
+    }
+}
+
+async fn post(
+    client: &reqwest::Client,
+    webhook: &str,
+    notification: &Notification,
+) -> io::Result<()> {
+    let response = client
+        .post(webhook)
+        .header(CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(notification)?)
+        .send()
+        .await
+        .map_err(io::Error::other)?;
+    if !response.status().is_success() {
+        return Err(io::Error::other(format!(
+            "webhook responded with status {}",
+            response.status()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifier() {
+        let mut config = Config::default();
+        assert!(Notifier::new(&config, &config.data.subsets).is_none());
+
+        // Nothing listens on port 9, so the posts fail without failing the run
+        config.notifications = Some(NotificationsConfig {
+            webhooks: vec!["http://127.0.0.1:9/hook".to_string()],
+            ..Default::default()
+        });
+        // Notifications are opt-in
+        assert!(Notifier::new(&config, &config.data.subsets).is_none());
+
+        config.notifications = Some(NotificationsConfig {
+            enabled: true,
+            webhooks: vec!["http://127.0.0.1:9/hook".to_string()],
+            every_n_events: Some(10),
+            timeout_secs: 1,
+            ..Default::default()
+        });
+        let notifier = Notifier::new(&config, &["valid".to_string()]).unwrap();
+        notifier.events_detected(6);
+        notifier.events_detected(7);
+        assert_eq!(notifier.num_events.load(Ordering::Relaxed), 13);
+
+        notifier.run_finished(RunSummary::default(), Some("interrupted"));
+        assert!(notifier.finished.load(Ordering::Relaxed));
+        assert!(lock(&notifier.sender).is_none());
+        // Notifications after the end of the run are dropped
+        notifier.events_detected(10);
+
+        let notification = Notification {
+            text: "done".to_string(),
+            kind: NotificationKind::Completed,
+            subsets: vec!["valid".to_string()],
+            output_path: "data/output".to_string(),
+            reason: None,
+            num_events: Some(13),
+            summary: Some(RunSummary::default()),
+        };
+        let json = serde_json::to_string(&notification).unwrap();
+        assert!(json.contains("\"kind\":\"completed\""));
+        assert!(json.contains("\"text\":\"done\""));
+    }
+}
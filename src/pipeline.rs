@@ -0,0 +1,1257 @@
+use crate::analytics::{EventDifficulty, PlayerStats, TrackRecorder, ZoneCounts};
+use crate::checkpoint::Checkpoint;
+use crate::config::{Config, LogLevel, VideoMode, DEFAULT_FRAME_RATE};
+use crate::data::annotation_index::AnnotationIndex;
+use crate::data::dataset::load_event_intervals;
+use crate::data::formats::actions::{dead_ball_periods, suppress_dead_ball_events};
+use crate::data::models::{
+    video_key, Annotation, Image, ReviewLabel, ReviewVerdict, ReviewedEvent, ReviewedVideoData,
+    VideoData, VideoDribbleEvents,
+};
+use crate::dribbling_detection::ball_speed::{refine_event_end_frames, BallSpeedTracker};
+use crate::dribbling_detection::create_dribble_models::{
+    get_ball_model, get_player_models, Kinematics,
+};
+use crate::dribbling_detection::dribble_detector::DribbleDetector;
+use crate::dribbling_detection::dribble_models::{
+    Ball, DribbleEvent, DribbleFrame, ShieldingEvent,
+};
+use crate::dribbling_detection::possession::{
+    link_events_to_possessions, Possession, PossessionTracker,
+};
+use crate::dribbling_detection::shielding_detector::ShieldingDetector;
+use crate::dribbling_detection::smoothing::FrameSmoother;
+use crate::event_stream::EventStream;
+use crate::notifications::Notifier;
+use crate::preprocessing::preprocess_tracks;
+use crate::progress::{ProgressSender, VideoProgress};
+use crate::review_log::{adjust_boundaries, ReviewLog, BOUNDARY_STEP};
+use crate::utils::annotation_calculations::filter_annotations;
+use crate::utils::annotation_filters::AnnotationFilter;
+use crate::utils::clip_list::ClipList;
+use crate::utils::comparison::ComparisonFrame;
+use crate::utils::config_reload::ConfigWatcher;
+use crate::utils::frame_cursor::FrameCursor;
+use crate::utils::frame_source::{first_frame_number, FrameSource};
+use crate::utils::hud::HudInfo;
+use crate::utils::keyboard_input::{wait_for_keyboard_input, KeyboardInput, Playback};
+use crate::utils::keymap::Keymap;
+use crate::utils::review_context::{roll_frames, ClipPhase, DEFAULT_ROLL_SECS};
+use crate::utils::visualizations::VisualizationBuilder;
+use crate::watchdog::{VideoWatch, Watchdog};
+use opencv::core::Mat;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Shared flag used to stop a detection job early. Clones share the same flag, so a job
+/// can be cancelled from another thread while it is running.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Everything detected in a single video.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProcessedVideo {
+    pub file_name: String,
+    /// Dataset of the video, if there are several
+    #[serde(default)]
+    pub dataset: Option<String>,
+    pub dribble_events: Vec<DribbleEvent>,
+    pub shielding_events: Vec<ShieldingEvent>,
+    pub possessions: Vec<Possession>,
+    /// Statistics of the players with events, if `[analytics]` is enabled
+    #[serde(default)]
+    pub player_stats: Vec<PlayerStats>,
+    /// Events by team and pitch zone, if `[analytics]` is enabled
+    #[serde(default)]
+    pub zone_counts: Vec<ZoneCounts>,
+    /// Difficulty of the completed dribbles, if `[analytics]` is enabled
+    #[serde(default)]
+    pub event_difficulty: Vec<EventDifficulty>,
+}
+
+impl ProcessedVideo {
+    /// See [`video_key`].
+    pub fn key(&self) -> String {
+        video_key(self.dataset.as_deref(), &self.file_name)
+    }
+}
+
+/// Number of worker threads to use. Display mode always uses a single thread.
+pub fn num_threads(config: &Config) -> usize {
+    if config.general.video_mode == VideoMode::Display {
+        1
+    } else {
+        config.general.num_cores as usize
+    }
+}
+
+/// Returns `true` if the frames are drawn while detecting. Otherwise the detection only
+/// reads the annotations and the frames are never decoded.
+pub fn draws_frames(config: &Config) -> bool {
+    matches!(
+        config.general.video_mode,
+        VideoMode::Display | VideoMode::Layers | VideoMode::Download
+    )
+}
+
+/// Returns `true` if the frames are shown in a window, which is the only mode that uses
+/// `highgui`.
+pub fn shows_window(config: &Config) -> bool {
+    config.general.video_mode == VideoMode::Display
+}
+
+/// Scale of the configured distances of a video. With image coordinates the distances are
+/// relative to the average player height, so they do not depend on the camera zoom.
+pub fn distance_scale(
+    video_data: &VideoData,
+    config: &Config,
+    category_map: &HashMap<String, u32>,
+) -> f64 {
+    match config.dribbling_detection.use_2d {
+        true => 1.0,
+        false => video_data.average_player_bbox_height(category_map) * 0.2,
+    }
+}
+
+/// Builds a thread pool for a detection job. Each job owns its pool, so several jobs can
+/// run side by side without sharing the global rayon pool.
+pub fn build_thread_pool(config: &Config) -> Result<ThreadPool, ThreadPoolBuildError> {
+    ThreadPoolBuilder::new()
+        .num_threads(num_threads(config))
+        .build()
+}
+
+/// Runs dribble detection on all videos using the threads of `pool`.
+///
+/// In review mode, `dribble_events_map` holds the events to review per video and the
+/// reviewed clips are pushed to `reviewed_video_data`. If a `checkpoint` is given, every
+/// video is recorded in it as soon as it is finished, and likewise the events of every video
+/// in `event_stream`, and its number of events is counted by `notifier`. Events that were
+/// written to the stream are not kept in the returned videos, so they are read back with
+/// [`read_event_stream`](crate::event_stream::read_event_stream). The frames and videos
+/// processed are reported to `progress`. Videos that exceed the timeout of `watchdog` are
+/// stopped and return the events detected until then, but are not checkpointed.
+#[allow(clippy::too_many_arguments)]
+pub fn run_detection(
+    videos: &[VideoData],
+    config: &Config,
+    pool: &ThreadPool,
+    cancel: &CancellationToken,
+    dribble_events_map: &Option<HashMap<String, Vec<(u32, u32)>>>,
+    reviewed_video_data: &Mutex<Option<Vec<ReviewedVideoData>>>,
+    checkpoint: Option<&Checkpoint>,
+    event_stream: Option<&EventStream>,
+    notifier: Option<&Notifier>,
+    progress: &ProgressSender,
+    watchdog: &Watchdog,
+) -> Vec<ProcessedVideo> {
+    progress.videos_queued(videos.len());
+    let process_item = |video_data: &VideoData| {
+        let video_progress = progress.start_video(&video_data.name(), video_data.image_paths.len());
+        let watch = watchdog.watch(&video_data.key());
+        let mut processed = process_video(
+            video_data,
+            config,
+            cancel,
+            dribble_events_map,
+            reviewed_video_data,
+            &video_progress,
+            &watch,
+        )?;
+        let num_events = processed.dribble_events.len() + processed.shielding_events.len();
+
+        // Videos stopped by a cancellation or timeout are incomplete and must be processed again
+        let complete = !cancel.is_cancelled() && !watch.is_expired();
+        if let Some(checkpoint) = checkpoint.filter(|_| complete) {
+            if let Err(e) = checkpoint.record(&processed) {
+                eprintln!("Error writing checkpoint for {}: {}", processed.file_name, e);
+            }
+        }
+        if let Some(event_stream) = event_stream.filter(|_| !cancel.is_cancelled()) {
+            let video = VideoDribbleEvents::from_video(
+                video_data,
+                Some(event_stream.subset()),
+                &processed.dribble_events,
+                &processed.shielding_events,
+            );
+            match event_stream.record(&video) {
+                // They are read back from the stream at the end of the run
+                Ok(()) => {
+                    processed.dribble_events = Vec::new();
+                    processed.shielding_events = Vec::new();
+                }
+                Err(e) => eprintln!("Error writing the events of {}: {}", processed.file_name, e),
+            }
+        }
+        if let Some(notifier) = notifier {
+            notifier.events_detected(num_events);
+        }
+        Some(processed)
+    };
+
+    // Use parallel or sequential iteration based on the number of threads
+    if pool.current_num_threads() > 1 {
+        pool.install(|| videos.par_iter().filter_map(process_item).collect())
+    } else {
+        videos.iter().filter_map(process_item).collect()
+    }
+}
+
+/// Sets up the detectors for a single video and processes it.
+pub fn process_video(
+    video_data: &VideoData,
+    config: &Config,
+    cancel: &CancellationToken,
+    dribble_events_map: &Option<HashMap<String, Vec<(u32, u32)>>>,
+    reviewed_video_data: &Mutex<Option<Vec<ReviewedVideoData>>>,
+    progress: &VideoProgress,
+    watch: &VideoWatch,
+) -> Option<ProcessedVideo> {
+    // Check for early exit
+    if cancel.is_cancelled() {
+        return None;
+    }
+
+    // Review mode replays clips and exports their annotations, the window can seek and the
+    // preprocessing works on whole tracks, so they need all of them in memory. Otherwise the
+    // annotations are streamed frame by frame.
+    let mut video_data = video_data.clone();
+    if config.general.review_mode.unwrap_or(false)
+        || shows_window(config)
+        || config.preprocessing.is_some()
+    {
+        if let Err(e) = video_data.load_annotations() {
+            eprintln!(
+                "Failed to load annotations for {:?}: {}",
+                video_data.dir_path, e
+            );
+            return None;
+        }
+    }
+
+    let category_map: HashMap<String, u32> = video_data
+        .labels
+        .categories
+        .iter()
+        .map(|c| (c.name.clone(), c.id))
+        .collect();
+
+    if let Some(preprocessing) = &config.preprocessing {
+        let frame_rate = f64::from(video_data.labels.info.frame_rate);
+        let stats = preprocess_tracks(
+            &mut video_data.labels,
+            preprocessing,
+            &category_map,
+            frame_rate,
+        );
+        if config.general.log_level == LogLevel::Debug {
+            println!(
+                "Preprocessed {} tracks of {:?}: stitched {} tracks, removed {} teleports and \
+                 filled {} frames",
+                stats.num_tracks,
+                video_data.dir_path,
+                stats.tracks_stitched,
+                stats.outliers_removed,
+                stats.frames_filled
+            );
+        }
+    }
+
+    let distance_scale = distance_scale(&video_data, config, &category_map);
+    let inner_rad = config.dribbling_detection.inner_radius * distance_scale;
+    let outer_rad = config.dribbling_detection.outer_radius * distance_scale;
+
+    let video_name = video_data
+        .dir_path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+
+    // Build a DribbleDetector for this video
+    let dribble_detector =
+        DribbleDetector::from_config(video_name.clone(), distance_scale, config.clone());
+
+    let shielding_detector = config
+        .shielding
+        .as_ref()
+        .filter(|s| s.enabled)
+        .map(|s| {
+            ShieldingDetector::new(
+                video_name.clone(),
+                inner_rad,
+                outer_rad,
+                s.min_frames,
+                s.max_movement * distance_scale,
+            )
+        });
+
+    let ball_speed_tracker = config
+        .ball_speed
+        .as_ref()
+        .filter(|b| b.enabled)
+        .map(|b| BallSpeedTracker::new(b.spike_threshold * distance_scale));
+
+    detect_events(
+        video_name,
+        video_data,
+        config.clone(),
+        dribble_detector,
+        shielding_detector,
+        ball_speed_tracker,
+        dribble_events_map,
+        reviewed_video_data,
+        cancel,
+        progress,
+        watch,
+        inner_rad,
+        outer_rad,
+    )
+}
+
+/// Processes a single video and returns its name plus the merged dribble events, any
+/// shielding events and the video's possession spells.
+#[allow(clippy::too_many_arguments)]
+fn detect_events(
+    vid_name: String,
+    video_data: VideoData,
+    config: Config,
+    mut dribble_detector: DribbleDetector,
+    mut shielding_detector: Option<ShieldingDetector>,
+    mut ball_speed_tracker: Option<BallSpeedTracker>,
+    dribble_events_map: &Option<HashMap<String, Vec<(u32, u32)>>>,
+    all_reviewed_video_data: &Mutex<Option<Vec<ReviewedVideoData>>>,
+    cancel: &CancellationToken,
+    progress: &VideoProgress,
+    watch: &VideoWatch,
+    inner_rad: f64,
+    outer_rad: f64,
+) -> Option<ProcessedVideo> {
+    let review_mode = config.general.review_mode.unwrap_or(false);
+    let log_level = config.general.log_level;
+
+    let mut reviewed_video_data = review_mode.then(|| ReviewedVideoData::default());
+
+    if config.general.log_level == LogLevel::Debug {
+        println!("Processing video {}", vid_name);
+    }
+
+    let mut vid_events = if review_mode {
+        if dribble_events_map.is_none() {
+            println!("Skipping video {}, found no dribble events file", vid_name);
+            return None;
+        }
+
+        let dribble_events = dribble_events_map.as_ref().unwrap();
+
+        if let Some(event) = dribble_events.get(&vid_name) {
+            println!(
+                " * Found dribble events for video {}: {:?}",
+                vid_name, event
+            );
+            event.clone()
+        } else {
+            return None;
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Decisions are written to reviewed_events.json as soon as they are made
+    let mut review_log = if review_mode {
+        let annotator = config.review.as_ref().and_then(|r| r.annotator.as_deref());
+        match ReviewLog::open(
+            Path::new(&config.data.output_path),
+            Path::new(&config.data.dribble_events_path),
+            annotator,
+        ) {
+            Ok(review_log) => Some(review_log),
+            Err(e) => {
+                eprintln!("Error opening the review log, not saving decisions: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // When resuming a review, clips that already have a decision are not shown again
+    if let Some(log) = review_log
+        .as_ref()
+        .filter(|_| config.review.as_ref().is_some_and(|r| r.resume))
+    {
+        vid_events.retain(|&(start, end)| log.get(&vid_name, start, end).is_none());
+        if vid_events.is_empty() {
+            println!("Skipping video {}, all its clips were reviewed", vid_name);
+            return None;
+        }
+    }
+
+    let total_num_events = vid_events.len();
+    let processed_events = 0;
+
+    let image_map: HashMap<String, String> = video_data
+        .labels
+        .images
+        .iter()
+        .map(|image| (image.file_name.clone(), image.image_id.clone()))
+        .collect();
+
+    let category_map: HashMap<String, u32> = video_data
+        .labels
+        .categories
+        .iter()
+        .map(|c| (c.name.clone(), c.id))
+        .collect();
+
+    let annotation_filter = config
+        .annotation_filters
+        .as_ref()
+        .map(|filters| AnnotationFilter::new(filters, &category_map, video_data.track_lengths()));
+
+    // Ground truth dribbles of the video, shown next to the detector output
+    let ground_truth: Option<Vec<(u32, u32)>> = config
+        .visualization
+        .comparison_labels
+        .as_ref()
+        .filter(|_| draws_frames(&config))
+        .map(|path| {
+            load_event_intervals(path)
+                .and_then(|mut intervals| intervals.remove(&vid_name))
+                .unwrap_or_default()
+        });
+
+    // Only used if the annotations are loaded, otherwise they are streamed per frame
+    let annotation_index = AnnotationIndex::new(&video_data.labels.annotations);
+    // Review mode jumps back to replay clips and the window can seek, so frames are not read
+    // in order
+    let prefetch_frames = if review_mode || shows_window(&config) {
+        0
+    } else {
+        config.general.prefetch_frames.unwrap_or(0)
+    };
+    // Detection only uses the annotations. Headless runs skip decoding the frames, which are
+    // read afterwards for the videos with events if clips are exported (montage, audit pack).
+    let mut frame_source = if draws_frames(&config) {
+        match FrameSource::prefetched(&video_data, prefetch_frames) {
+            Ok(frame_source) => Some(frame_source),
+            Err(e) => {
+                eprintln!("Skipping video {}: {}", vid_name, e);
+                return None;
+            }
+        }
+    } else {
+        None
+    };
+    let mut frame_annotations = video_data
+        .labels_reader
+        .as_ref()
+        .map(|reader| reader.frame_annotations(&video_data.labels.images));
+    // let file_name = format!("video_{}", vid_num);
+    let file_name = vid_name.clone();
+
+    let mut visualization_builder =
+        VisualizationBuilder::new(config.general.video_mode, &file_name, &config)
+            .expect("Failed to create visualization builder")
+            .with_frame_rate(video_data.labels.info.frame_rate);
+
+    let mut frame_smoother = config.smoothing.as_ref().map(FrameSmoother::new);
+    let mut kinematics = Kinematics::new(
+        f64::from(video_data.labels.info.frame_rate),
+        &config
+            .smoothing
+            .as_ref()
+            .map(|smoothing| smoothing.velocity.clone())
+            .unwrap_or_default(),
+    );
+
+    let mut detected_events: Vec<DribbleEvent> = Vec::new();
+    let mut shielding_events: Vec<ShieldingEvent> = Vec::new();
+    let mut possession_tracker = PossessionTracker::new(
+        inner_rad,
+        config.dribbling_detection.possession_max_gap.unwrap_or(5),
+    );
+    let mut track_recorder = config
+        .analytics
+        .as_ref()
+        .filter(|analytics| analytics.enabled)
+        .map(|_| TrackRecorder::new());
+
+    // Store a clone of vid_events
+    let mut current_interval = if !vid_events.is_empty() {
+        vid_events.remove(0)
+    } else {
+        (0, video_data.image_paths.len() as u32 - 1)
+    };
+
+    let mut start = current_interval.0;
+    let mut end = current_interval.1;
+
+    if review_mode {
+        let clips = std::iter::once(current_interval)
+            .chain(vid_events.iter().copied())
+            .collect();
+        let mut clip_list = ClipList::new(clips);
+        clip_list.set_current(current_interval);
+        visualization_builder.set_clip_list(clip_list);
+    }
+
+    let mut frame_num;
+
+    let mut cursor = FrameCursor::new(video_data.image_paths.clone());
+
+    let mut playback = Playback::new(video_data.labels.info.frame_rate);
+    // The bindings are checked when the config is loaded
+    let keymap = Keymap::from_config(&config).unwrap_or_default();
+    let review_config = config.review.clone().unwrap_or_default();
+    if let Some(speed) = review_config.speed.filter(|_| review_mode) {
+        playback = playback.with_speed(speed);
+    }
+    // The detected kind is only revealed after the verdict, so it does not bias the reviewer
+    let blind = review_mode && review_config.blind;
+    // The clip under review is replayed until a decision is made, counting the loops
+    let mut clip_loops: u32 = 0;
+    // Frames shown before and after the event under review
+    let pre_roll = roll_frames(
+        review_config.pre_roll_secs.unwrap_or(DEFAULT_ROLL_SECS),
+        playback.frame_rate(),
+    );
+    let post_roll = roll_frames(
+        review_config.post_roll_secs.unwrap_or(DEFAULT_ROLL_SECS),
+        playback.frame_rate(),
+    );
+
+    // Visualization settings are reloaded while the window is open
+    let mut config_watcher = ConfigWatcher::new(&config).filter(|_| shows_window(&config));
+
+    // Tags and note for the clip currently under review
+    let mut pending_tags: Vec<String> = Vec::new();
+    let mut pending_note: Option<String> = None;
+
+    let mut current_frames = current_interval.clone();
+
+    while end != 0 {
+        let Some(image_path) = cursor.current().map(Path::to_path_buf) else {
+            break;
+        };
+        if current_frames != current_interval {
+            println!("Displaying frames ({start}-{end})");
+            current_frames = current_interval.clone();
+            clip_loops = 0;
+            if let Some(clip_list) = visualization_builder.clip_list_mut() {
+                clip_list.set_current(current_interval);
+            }
+        };
+
+        let image_name = image_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        frame_num = image_name
+            .parse::<usize>()
+            .expect("Failed to parse frame number from image name");
+
+        // A video that timed out keeps the events detected so far
+        if cancel.is_cancelled() || watch.is_expired() {
+            break;
+        }
+
+        if review_mode {
+            if processed_events >= total_num_events {
+                println!("No more events to process (1)");
+                break;
+            }
+
+            if frame_num < start.saturating_sub(pre_roll) as usize {
+                cursor.next();
+                continue;
+            }
+            if frame_num > (end + post_roll) as usize {
+                if processed_events >= total_num_events {
+                    println!("No more events to process (2)");
+                    break;
+                }
+                current_interval = next_review_clip(&mut vid_events, &mut cursor, pre_roll);
+                start = current_interval.0;
+                end = current_interval.1;
+                continue;
+            }
+        }
+
+        // Frames before the furthest processed one are shown again, but not counted twice
+        let replay = cursor.is_replay();
+
+        let image_file_name = image_path
+            .to_string_lossy()
+            .split('/')
+            .last()
+            .unwrap_or("")
+            .to_string();
+
+        let image_id = image_map.get(&image_file_name).unwrap_or(&image_file_name);
+
+        progress.frame_processed();
+
+        let frame_annotation_list = match frame_annotations.as_mut() {
+            Some(frame_annotations) => frame_annotations.annotations_for(image_id),
+            None => annotation_index.get(image_id).to_vec(),
+        };
+        let frame_annotation_list = match &annotation_filter {
+            Some(filter) => filter.apply(frame_annotation_list),
+            None => frame_annotation_list,
+        };
+        let filtered_annotations = filter_annotations(
+            &frame_annotation_list,
+            &category_map,
+            config.dribbling_detection.ignore_person_classes,
+            config.dribbling_detection.ignore_teams,
+        );
+        let mut ball_model = get_ball_model(&category_map, &filtered_annotations, &config);
+        let mut player_models = get_player_models(&category_map, &filtered_annotations, &config);
+        // Like the detector, the smoother has to see the frames in order
+        if let Some(smoother) = frame_smoother.as_mut().filter(|_| !replay) {
+            ball_model = smoother.process_frame(
+                frame_num as u32,
+                ball_model,
+                player_models.as_deref_mut().unwrap_or_default(),
+            );
+        }
+        kinematics.process_frame(
+            frame_num as u32,
+            ball_model.as_mut(),
+            player_models.as_deref_mut().unwrap_or_default(),
+        );
+        if !replay {
+            if let Some(tracker) = ball_speed_tracker.as_mut() {
+                tracker.process_frame(frame_num as u32, ball_model);
+            }
+        }
+
+        if player_models.is_none() {
+            if log_level == LogLevel::Debug {
+                println!("No players found in frame {}. Skipping frame...", frame_num);
+            }
+            // frame_num += 1;
+            cursor.mark_processed();
+            cursor.next();
+            continue;
+        }
+
+        let dribble_frame = DribbleFrame {
+            frame_number: frame_num as u32,
+            players: player_models.unwrap(),
+            ball: ball_model.unwrap_or(Ball::new(0.0, 0.0)),
+        };
+
+        if !replay {
+            possession_tracker.process_frame(&dribble_frame);
+            if let Some(recorder) = track_recorder.as_mut() {
+                recorder.process_frame(&dribble_frame, &filtered_annotations);
+            }
+            if let Some(detector) = shielding_detector.as_mut() {
+                shielding_events.extend(detector.process_frame(&dribble_frame));
+            }
+        }
+
+        // Also used for the possession indicator, so it is read even without the HUD
+        let hud_info = draws_frames(&config)
+            .then(|| HudInfo::new(&dribble_frame, &dribble_detector, review_mode));
+
+        // The detector has to see the frames in order, so replayed frames are only shown
+        let potential_event = if replay {
+            None
+        } else {
+            dribble_detector.process_frame(dribble_frame)
+        };
+        let hud_info = hud_info
+            .map(|info| info.with_state(dribble_detector.state()))
+            .map(|info| {
+                if review_mode {
+                    info.with_review_loop(clip_loops + 1, playback.speed())
+                        .with_review_phase(ClipPhase::of(frame_num as u32, (start, end)))
+                } else {
+                    info
+                }
+            });
+        cursor.mark_processed();
+
+        if let Some(dribble_event) = potential_event.clone() {
+            if !replay && (dribble_event.detected_dribble || dribble_event.detected_tackle) {
+                // println!("\n\n\nDetected dribble event: {:?}", dribble_event.frames);
+                if !blind {
+                    visualization_builder.log_event(&dribble_event);
+                }
+                detected_events.push(dribble_event);
+            }
+        }
+
+        if let Some(after) = cursor.seeking_event_after() {
+            let event_started = dribble_detector
+                .current_active_event()
+                .is_some_and(|event| event.start_frame > after);
+            if event_started {
+                cursor.stop_seeking();
+            }
+        }
+        // Frames on the way to a seek target are only processed, not shown
+        if cursor.is_seeking() {
+            cursor.next();
+            continue;
+        }
+
+        let mut frame = match frame_source.as_mut() {
+            Some(frame_source) => frame_source.read(&image_path).expect("Failed to read frame"),
+            None => Mat::default(),
+        };
+
+        if let Some(visualization) = config_watcher
+            .as_mut()
+            .and_then(ConfigWatcher::changed_visualization)
+        {
+            visualization_builder.set_visualization(visualization);
+        }
+
+        if draws_frames(&config) {
+            visualization_builder
+                .add_frame(
+                    &mut frame,
+                    Some(image_id),
+                    Some(&filtered_annotations),
+                    &category_map,
+                    inner_rad,
+                    outer_rad,
+                    hud_info.as_ref(),
+                    ground_truth.as_ref().map(|ground_truth| {
+                        ComparisonFrame::new(
+                            ground_truth,
+                            frame_num as u32,
+                            dribble_detector.current_active_event().is_some(),
+                        )
+                    }),
+                )
+                .expect("Failed to add frame");
+        }
+
+        let input_value =
+            wait_for_keyboard_input(&config, &keymap, &mut playback, &mut visualization_builder)
+                .expect("There was an error with keyboard input");
+
+        match input_value {
+            KeyboardInput::Quit => {
+                cancel.cancel();
+                visualization_builder
+                    .finish()
+                    .expect("Failed to finish visualization");
+                println!("Quitting...");
+                break;
+            }
+            KeyboardInput::NextFrame => {
+                cursor.next();
+            }
+            KeyboardInput::PreviousFrame => {
+                cursor.previous();
+            }
+            KeyboardInput::JumpToFrame => {
+                if let Some(frame) = read_frame_number() {
+                    if !cursor.seek(frame) {
+                        println!("Frame {} is not in video {}", frame, vid_name);
+                    }
+                }
+            }
+            KeyboardInput::NextEvent if !review_mode => {
+                let current = cursor.frame_number().unwrap_or(0);
+                // Events that were already detected, e.g. after moving back
+                let next_start = detected_events
+                    .iter()
+                    .map(|event| event.start_frame)
+                    .filter(|start| *start > current)
+                    .min();
+                match next_start {
+                    Some(start) => {
+                        cursor.seek(start);
+                    }
+                    None => cursor.seek_next_event(),
+                }
+            }
+            // In review mode the events are the clips
+            KeyboardInput::NextClip | KeyboardInput::NextEvent => {
+                cursor.next();
+
+                current_interval = next_review_clip(&mut vid_events, &mut cursor, pre_roll);
+
+                start = current_interval.0;
+                end = current_interval.1;
+
+                visualization_builder
+                    .finish()
+                    .expect("Failed to finish visualization");
+            }
+            KeyboardInput::Tag(index) => {
+                if review_mode {
+                    toggle_review_tag(&config, &mut pending_tags, index);
+                }
+            }
+            KeyboardInput::Note => {
+                if review_mode {
+                    pending_note = read_review_note();
+                }
+            }
+            KeyboardInput::Accept if blind => {
+                println!("Accepting is disabled in blind review, press d, t or n");
+            }
+            KeyboardInput::Accept
+            | KeyboardInput::Dribble
+            | KeyboardInput::Tackle
+            | KeyboardInput::None
+                if review_mode =>
+            {
+                let (event_start, event_end) = current_interval;
+                let detected = review_log
+                    .as_ref()
+                    .and_then(|log| log.detected_label(&vid_name, event_start, event_end));
+                let label = match input_value {
+                    KeyboardInput::Accept => detected.unwrap_or(ReviewLabel::Dribble),
+                    KeyboardInput::Dribble => ReviewLabel::Dribble,
+                    KeyboardInput::Tackle => ReviewLabel::Tackle,
+                    _ => ReviewLabel::Other,
+                };
+                let verdict = ReviewVerdict::new(detected, label);
+                println!("Adding {} event ({:?})", label, verdict);
+                if let Some(detected) = detected.filter(|_| blind) {
+                    let agreement = if detected == label { "agree" } else { "disagree" };
+                    println!("The detector found a {}, you {}", detected, agreement);
+                }
+
+                let filtered_video_data = filter_video_data(video_data.clone(), start, end);
+                let reviewed = reviewed_video_data.as_mut().unwrap();
+                match label {
+                    ReviewLabel::Dribble => reviewed.dribble_data.push(filtered_video_data),
+                    ReviewLabel::Tackle => reviewed.tackle_data.push(filtered_video_data),
+                    ReviewLabel::Other => reviewed.other_data.push(filtered_video_data),
+                }
+                let event = ReviewedEvent {
+                    video_id: vid_name.clone(),
+                    start_frame: event_start,
+                    end_frame: event_end,
+                    label,
+                    verdict: Some(verdict),
+                    corrected_start_frame: (start != event_start).then_some(start),
+                    corrected_end_frame: (end != event_end).then_some(end),
+                    tags: std::mem::take(&mut pending_tags),
+                    note: pending_note.take(),
+                };
+                if let Some(clip_list) = visualization_builder.clip_list_mut() {
+                    clip_list.set_label(current_interval, Some(label));
+                }
+                if let Some(log) = review_log.as_mut() {
+                    if let Err(e) = log.record(event.clone()) {
+                        eprintln!("Error writing {}: {}", log.path().display(), e);
+                    }
+                }
+                reviewed.events.push(event);
+
+                cursor.next();
+
+                current_interval = next_review_clip(&mut vid_events, &mut cursor, pre_roll);
+
+                start = current_interval.0;
+                end = current_interval.1;
+
+                all_reviewed_video_data
+                    .lock()
+                    .unwrap()
+                    .as_mut()
+                    .unwrap()
+                    .push(std::mem::take(reviewed));
+                continue;
+            }
+            KeyboardInput::Undo if review_mode => {
+                let undone = match review_log.as_mut().map(ReviewLog::undo) {
+                    Some(Ok(undone)) => undone,
+                    Some(Err(e)) => {
+                        eprintln!("Error writing the review log: {}", e);
+                        continue;
+                    }
+                    None => None,
+                };
+                let Some(undone) = undone else {
+                    println!("No decision to undo in video {}", vid_name);
+                    continue;
+                };
+                println!(
+                    "Undid {} of frames {}-{}",
+                    undone.label, undone.start_frame, undone.end_frame
+                );
+
+                if let Some(clip_list) = visualization_builder.clip_list_mut() {
+                    clip_list.set_label((undone.start_frame, undone.end_frame), None);
+                }
+                // The clip of the decision is reviewed again, followed by the current one
+                vid_events.insert(0, current_interval);
+                current_interval = (undone.start_frame, undone.end_frame);
+                start = undone.corrected_start_frame.unwrap_or(undone.start_frame);
+                end = undone.corrected_end_frame.unwrap_or(undone.end_frame);
+                pending_tags = undone.tags;
+                pending_note = undone.note;
+                if let Some(all_reviewed) = all_reviewed_video_data.lock().unwrap().as_mut() {
+                    all_reviewed.pop();
+                }
+                cursor.rewind();
+                continue;
+            }
+            KeyboardInput::JumpToClip | KeyboardInput::OpenClip(_) if review_mode => {
+                let index = match input_value {
+                    KeyboardInput::OpenClip(index) => Some(index),
+                    _ => read_clip_number().map(|number| number.saturating_sub(1)),
+                };
+                let Some(index) = index else {
+                    continue;
+                };
+                let clip_list = visualization_builder.clip_list();
+                let Some(clip) = clip_list.and_then(|clip_list| clip_list.get(index)) else {
+                    println!("There is no clip {} in video {}", index + 1, vid_name);
+                    continue;
+                };
+                if clip_list.is_some_and(|clip_list| clip_list.is_reviewed(index)) {
+                    println!("Clip {} was already reviewed, press u to undo it", index + 1);
+                    continue;
+                }
+                // The skipped clip is reviewed next
+                if clip != current_interval {
+                    vid_events.retain(|interval| *interval != clip);
+                    vid_events.insert(0, current_interval);
+                    current_interval = clip;
+                    (start, end) = clip;
+                    pending_tags.clear();
+                    pending_note = None;
+                    visualization_builder
+                        .finish()
+                        .expect("Failed to finish visualization");
+                }
+                println!("Opening clip {}, frames {}-{}", index + 1, start, end);
+                cursor.rewind();
+                continue;
+            }
+            KeyboardInput::StartEarlier
+            | KeyboardInput::StartLater
+            | KeyboardInput::EndEarlier
+            | KeyboardInput::EndLater
+                if review_mode =>
+            {
+                let (start_delta, end_delta) = match input_value {
+                    KeyboardInput::StartEarlier => (-BOUNDARY_STEP, 0),
+                    KeyboardInput::StartLater => (BOUNDARY_STEP, 0),
+                    KeyboardInput::EndEarlier => (0, -BOUNDARY_STEP),
+                    _ => (0, BOUNDARY_STEP),
+                };
+                let last_frame = video_data.image_paths.len() as u32;
+                (start, end) = adjust_boundaries((start, end), start_delta, end_delta, last_frame);
+                println!(
+                    "Clip frames {}-{} (detected {}-{})",
+                    start, end, current_interval.0, current_interval.1
+                );
+                // Replay the adjusted clip from its start
+                cursor.rewind();
+                continue;
+            }
+            KeyboardInput::Accept
+            | KeyboardInput::Dribble
+            | KeyboardInput::Tackle
+            | KeyboardInput::None
+            | KeyboardInput::Undo
+            | KeyboardInput::StartEarlier
+            | KeyboardInput::StartLater
+            | KeyboardInput::EndEarlier
+            | KeyboardInput::EndLater
+            | KeyboardInput::JumpToClip
+            | KeyboardInput::OpenClip(_) => {}
+            // Handled while waiting for a key, see `wait_for_keyboard_input`
+            KeyboardInput::Faster
+            | KeyboardInput::Slower
+            | KeyboardInput::TogglePause
+            | KeyboardInput::ZoomIn
+            | KeyboardInput::ZoomOut
+            | KeyboardInput::Snapshot => {}
+        }
+
+        // Replay clip
+        if review_mode && (frame_num >= (end + post_roll) as usize || cursor.current().is_none()) {
+            clip_loops += 1;
+            cursor.rewind();
+        }
+    }
+
+    visualization_builder
+        .finish()
+        .expect("Failed to finish visualization");
+
+    if let Some(detector) = shielding_detector.as_mut() {
+        shielding_events.extend(detector.finish());
+    }
+
+    // The end frames are refined before padding, so the padding is relative to the release
+    if let (Some(tracker), Some(ball_speed)) = (ball_speed_tracker, config.ball_speed.as_ref()) {
+        refine_event_end_frames(&mut detected_events, &tracker.finish(), ball_speed.search_window);
+    }
+    for event in &mut detected_events {
+        pad_event(event);
+    }
+
+    let mut merged_events = combine_consecutive_events(detected_events);
+    if let Some(actions_config) = config
+        .actions
+        .as_ref()
+        .filter(|a| a.suppress_dead_ball_events)
+    {
+        let periods = dead_ball_periods(
+            &video_data.actions,
+            actions_config,
+            video_data.labels.info.frame_rate,
+        );
+        suppress_dead_ball_events(&mut merged_events, &periods);
+    }
+    let possessions = possession_tracker.finish();
+    link_events_to_possessions(&mut merged_events, &possessions);
+    let (player_stats, zone_counts, event_difficulty) = match track_recorder {
+        Some(recorder) => {
+            let frame_rate = video_data.labels.info.frame_rate;
+            let frame_rate = if frame_rate > 0.0 {
+                f64::from(frame_rate)
+            } else {
+                DEFAULT_FRAME_RATE
+            };
+            let pitch = config.visualization.pitch.clone().unwrap_or_default();
+            (
+                recorder.player_stats(
+                    &file_name,
+                    &merged_events,
+                    &possessions,
+                    inner_rad,
+                    frame_rate,
+                ),
+                recorder.zone_counts(&file_name, &merged_events, &pitch),
+                recorder.event_difficulty(
+                    &file_name,
+                    &merged_events,
+                    inner_rad,
+                    outer_rad,
+                    frame_rate,
+                    &pitch,
+                ),
+            )
+        }
+        None => (Vec::new(), Vec::new(), Vec::new()),
+    };
+
+    if log_level == LogLevel::Debug {
+        if review_mode {
+            println!(" * Finished processing {} events\n", total_num_events);
+        } else {
+            println!(" * Finished processing {} events\n", merged_events.len());
+        }
+    }
+
+    Some(ProcessedVideo {
+        file_name,
+        dataset: video_data.dataset.clone(),
+        dribble_events: merged_events,
+        shielding_events,
+        possessions,
+        player_stats,
+        zone_counts,
+        event_difficulty,
+    })
+}
+
+/// The next clip to review, `(0, 0)` if there is none. Clips are in the order of the review
+/// queue rather than of the frames, so the cursor moves back if the clip, with the `pre_roll`
+/// frames before it, starts before it.
+fn next_review_clip(
+    vid_events: &mut Vec<(u32, u32)>,
+    cursor: &mut FrameCursor,
+    pre_roll: u32,
+) -> (u32, u32) {
+    let interval = if vid_events.is_empty() {
+        (0, 0)
+    } else {
+        vid_events.remove(0)
+    };
+    if cursor
+        .frame_number()
+        .is_some_and(|frame| frame > interval.0.saturating_sub(pre_roll))
+    {
+        cursor.rewind();
+    }
+    interval
+}
+
+/// Toggles the configured review tag with the given index for the clip under review.
+fn toggle_review_tag(config: &Config, pending_tags: &mut Vec<String>, index: usize) {
+    let Some(tag) = config.review.as_ref().and_then(|r| r.tags.get(index)) else {
+        println!("No review tag configured for key {}", index + 1);
+        return;
+    };
+
+    if let Some(pos) = pending_tags.iter().position(|t| t == tag) {
+        pending_tags.remove(pos);
+    } else {
+        pending_tags.push(tag.clone());
+    }
+    println!("Tags for this clip: {:?}", pending_tags);
+}
+
+/// Reads a free-text note for the clip under review from the terminal.
+fn read_review_note() -> Option<String> {
+    print!("Note for this clip (empty to clear): ");
+    io::stdout().flush().ok()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).ok()?;
+    let note = line.trim();
+    (!note.is_empty()).then(|| note.to_string())
+}
+
+/// Reads the one-based number of the clip to open from the terminal.
+fn read_clip_number() -> Option<usize> {
+    print!("Open clip: ");
+    io::stdout().flush().ok()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).ok()?;
+    line.trim().parse().ok()
+}
+
+/// Reads the frame number to jump to from the terminal.
+fn read_frame_number() -> Option<u32> {
+    print!("Jump to frame: ");
+    io::stdout().flush().ok()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).ok()?;
+    line.trim().parse().ok()
+}
+
+/// Adds context frames before and after a detected event.
+fn pad_event(event: &mut DribbleEvent) {
+    let extra_frames_before = 60;
+    let extra_frames_after = 60;
+    event.start_frame = event.start_frame.saturating_sub(extra_frames_before);
+
+    if let Some(cur_end) = event.end_frame {
+        event.end_frame = Some(cur_end + extra_frames_after);
+    };
+}
+
+/// Merges consecutive dribble events if the start of one event
+/// is immediately after (or within max_event_gap) the end of the previous event,
+/// and both events are of the same type (dribble or tackle).
+pub fn combine_consecutive_events(mut events: Vec<DribbleEvent>) -> Vec<DribbleEvent> {
+    events.sort_by_key(|e| e.start_frame);
+
+    let max_event_gap = 8;
+
+    let mut merged: Vec<DribbleEvent> = Vec::new();
+    for event in events {
+        if let Some(last) = merged.last_mut() {
+            if let Some(last_end) = last.end_frame {
+                let same_type = (last.detected_tackle && event.detected_tackle)
+                    || (last.detected_dribble && event.detected_dribble);
+
+                if event.start_frame <= last_end + max_event_gap && same_type {
+                    last.extend(&event);
+
+                    if let Some(end) = event.end_frame {
+                        last.end_frame = Some(end);
+                    }
+
+                    last.detected_tackle |= event.detected_tackle;
+                    last.detected_dribble |= event.detected_dribble;
+                    last.ever_contested |= event.ever_contested;
+                    continue;
+                }
+            }
+        }
+        merged.push(event);
+    }
+    merged
+}
+
+/// Returns a copy of `video_data` containing only the images and annotations of frames
+/// `start..=end`.
+pub fn filter_video_data(video_data: VideoData, start: u32, end: u32) -> VideoData {
+    let mut filtered_data = VideoData::default();
+    filtered_data.dir_path = video_data.dir_path.clone();
+    filtered_data.video_path = video_data.video_path.clone();
+    // The clip's frames are still read from the video of the whole sequence
+    filtered_data.video_first_frame = Some(first_frame_number(&video_data));
+    filtered_data.dataset = video_data.dataset.clone();
+    filtered_data.actions = video_data.actions.clone();
+
+    // Helper to parse the zero-padded frame number from the filename (e.g. "0001.jpg" -> 1).
+    let in_range = |name: &str| -> bool {
+        if let Some(stem) = std::path::Path::new(name).file_stem() {
+            if let Ok(num) = stem.to_string_lossy().parse::<u32>() {
+                return num >= start && num <= end;
+            }
+        }
+        false
+    };
+
+    let new_image_paths: Vec<PathBuf> = video_data
+        .image_paths
+        .into_iter()
+        .filter(|p| {
+            if let Some(fname) = p.file_name().map(|f| f.to_string_lossy().to_string()) {
+                in_range(&fname)
+            } else {
+                false
+            }
+        })
+        .collect();
+
+    let new_images: Vec<Image> = video_data
+        .labels
+        .images
+        .into_iter()
+        .filter(|img| in_range(&img.file_name))
+        .collect();
+
+    let valid_ids: std::collections::HashSet<String> =
+        new_images.iter().map(|img| img.image_id.clone()).collect();
+
+    let new_annotations: Vec<Annotation> = video_data
+        .labels
+        .annotations
+        .into_iter()
+        .filter(|ann| valid_ids.contains(&ann.image_id))
+        .collect();
+
+    filtered_data.image_paths = new_image_paths;
+    filtered_data.labels.images = new_images;
+    filtered_data.labels.annotations = new_annotations;
+    filtered_data.labels.categories = video_data.labels.categories;
+
+    filtered_data
+}
@@ -0,0 +1,317 @@
+//! Precision-recall curve of detectors that score their events. The detected events are
+//! evaluated at every confidence they have as the threshold, keeping the events with at least
+//! that confidence, so an operating point can be picked for every use case: a high threshold
+//! for few false detections, a low one to find most events for labeling. Events without a
+//! confidence are kept at every threshold.
+
+use crate::data::models::DribbleEventsExport;
+use crate::dribbling_detection::sweep::EventMatches;
+use crate::evaluation::{intervals, match_events, EventRange, Metrics};
+use opencv::core::{self, Mat, Point, Scalar, Vector, CV_8UC3};
+use opencv::imgcodecs;
+use opencv::imgproc;
+use serde::Serialize;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the curve points in the output directory
+pub const PR_CURVE_FILE: &str = "pr_curve.csv";
+/// Name of the image of the curve in the output directory
+pub const PR_CURVE_IMAGE: &str = "pr_curve.png";
+
+const IMAGE_WIDTH: i32 = 640;
+const IMAGE_HEIGHT: i32 = 480;
+/// Distance of the plot area from the edges of the image, with room for the axis labels
+const MARGIN: i32 = 60;
+
+/// The metrics of the events with a confidence of at least `threshold`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct CurvePoint {
+    pub threshold: f64,
+    pub metrics: Metrics,
+}
+
+/// The points of the curve by descending threshold, one per distinct confidence of the
+/// detected events of the videos of the ground truth. Empty if none of them has a confidence.
+pub fn pr_curve(
+    detected: &DribbleEventsExport,
+    ground_truth: &DribbleEventsExport,
+    iou_threshold: f64,
+) -> Vec<CurvePoint> {
+    let detected = intervals(detected);
+    let ground_truth = intervals(ground_truth);
+    // Videos without detector output are left out, as in the evaluation
+    let videos: Vec<(&Vec<EventRange>, &Vec<EventRange>)> = ground_truth
+        .iter()
+        .filter_map(|(video_id, expected)| Some((detected.get(video_id)?, expected)))
+        .collect();
+
+    let mut thresholds: Vec<f64> = videos
+        .iter()
+        .flat_map(|(found, _)| found.iter().filter_map(|event| event.confidence))
+        .collect();
+    thresholds.sort_by(|a, b| b.total_cmp(a));
+    thresholds.dedup();
+
+    thresholds
+        .into_iter()
+        .map(|threshold| {
+            let mut matches = EventMatches::default();
+            for (found, expected) in &videos {
+                let kept: Vec<EventRange> = found
+                    .iter()
+                    .filter(|event| event.confidence.map_or(true, |c| c >= threshold))
+                    .copied()
+                    .collect();
+                matches.add(EventMatches {
+                    true_positives: match_events(&kept, expected, iou_threshold).len(),
+                    num_detected: kept.len(),
+                    num_ground_truth: expected.len(),
+                });
+            }
+            CurvePoint {
+                threshold,
+                metrics: matches.into(),
+            }
+        })
+        .collect()
+}
+
+/// The point with the highest F1, the one with the highest threshold on ties.
+pub fn best_f1(points: &[CurvePoint]) -> Option<&CurvePoint> {
+    points.iter().fold(None, |best, point| match best {
+        Some(best) if best.metrics.f1 >= point.metrics.f1 => Some(best),
+        _ => Some(point),
+    })
+}
+
+/// Writes one CSV row per point to [`PR_CURVE_FILE`] in `output_dir`.
+pub fn write_pr_curve_csv(output_dir: &Path, points: &[CurvePoint]) -> io::Result<()> {
+    let mut csv =
+        String::from("threshold,true_positives,detected,ground_truth,precision,recall,f1\n");
+    for point in points {
+        let m = &point.metrics;
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{:.4},{:.4},{:.4}",
+            point.threshold,
+            m.true_positives,
+            m.num_detected,
+            m.num_ground_truth,
+            m.precision,
+            m.recall,
+            m.f1
+        );
+    }
+    fs::create_dir_all(output_dir)?;
+    fs::write(output_dir.join(PR_CURVE_FILE), csv)
+}
+
+/// Position of `(recall, precision)` in the plot area.
+fn plot_point(recall: f64, precision: f64) -> Point {
+    let width = f64::from(IMAGE_WIDTH - MARGIN * 2);
+    let height = f64::from(IMAGE_HEIGHT - MARGIN * 2);
+    Point::new(
+        MARGIN + (recall.clamp(0.0, 1.0) * width).round() as i32,
+        IMAGE_HEIGHT - MARGIN - (precision.clamp(0.0, 1.0) * height).round() as i32,
+    )
+}
+
+fn put_text(image: &mut Mat, text: &str, origin: Point, color: Scalar) -> opencv::Result<()> {
+    imgproc::put_text(
+        image,
+        text,
+        origin,
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        0.45,
+        color,
+        1,
+        imgproc::LINE_AA,
+        false,
+    )
+}
+
+/// Draws the curve with recall on the x axis and precision on the y axis, black on white,
+/// with the point of the best F1 in red.
+pub fn draw_pr_curve(points: &[CurvePoint]) -> opencv::Result<Mat> {
+    let black = Scalar::all(0.0);
+    let gray = Scalar::all(200.0);
+    let red = Scalar::new(0.0, 0.0, 220.0, 0.0);
+    let mut image =
+        Mat::new_rows_cols_with_default(IMAGE_HEIGHT, IMAGE_WIDTH, CV_8UC3, Scalar::all(255.0))?;
+
+    for tick in 0..=4 {
+        let value = f64::from(tick) / 4.0;
+        let label = format!("{:.2}", value);
+        imgproc::line(
+            &mut image,
+            plot_point(value, 0.0),
+            plot_point(value, 1.0),
+            gray,
+            1,
+            imgproc::LINE_8,
+            0,
+        )?;
+        imgproc::line(
+            &mut image,
+            plot_point(0.0, value),
+            plot_point(1.0, value),
+            gray,
+            1,
+            imgproc::LINE_8,
+            0,
+        )?;
+        put_text(
+            &mut image,
+            &label,
+            plot_point(value, 0.0) + Point::new(-16, 20),
+            black,
+        )?;
+        put_text(
+            &mut image,
+            &label,
+            plot_point(0.0, value) + Point::new(-44, 5),
+            black,
+        )?;
+    }
+    imgproc::line(
+        &mut image,
+        plot_point(0.0, 0.0),
+        plot_point(1.0, 0.0),
+        black,
+        1,
+        imgproc::LINE_8,
+        0,
+    )?;
+    imgproc::line(
+        &mut image,
+        plot_point(0.0, 0.0),
+        plot_point(0.0, 1.0),
+        black,
+        1,
+        imgproc::LINE_8,
+        0,
+    )?;
+    put_text(
+        &mut image,
+        "Recall",
+        Point::new(IMAGE_WIDTH / 2 - 24, IMAGE_HEIGHT - 15),
+        black,
+    )?;
+    put_text(&mut image, "Precision", Point::new(10, MARGIN - 20), black)?;
+
+    // By ascending threshold, so the line goes from high to low recall
+    let curve: Vector<Point> = points
+        .iter()
+        .rev()
+        .map(|point| plot_point(point.metrics.recall, point.metrics.precision))
+        .collect();
+    let mut lines: Vector<Vector<Point>> = Vector::new();
+    lines.push(curve.clone());
+    imgproc::polylines(&mut image, &lines, false, black, 2, imgproc::LINE_AA, 0)?;
+    for point in &curve {
+        imgproc::circle(&mut image, point, 3, black, -1, imgproc::LINE_AA, 0)?;
+    }
+
+    if let Some(best) = best_f1(points) {
+        let center = plot_point(best.metrics.recall, best.metrics.precision);
+        imgproc::circle(&mut image, center, 6, red, -1, imgproc::LINE_AA, 0)?;
+        let label = format!(
+            "best F1 {:.3} at confidence >= {}",
+            best.metrics.f1, best.threshold
+        );
+        put_text(
+            &mut image,
+            &label,
+            Point::new(IMAGE_WIDTH - MARGIN - 260, MARGIN - 20),
+            red,
+        )?;
+    }
+    Ok(image)
+}
+
+/// Draws the curve to [`PR_CURVE_IMAGE`] in `output_dir`.
+pub fn write_pr_curve_image(output_dir: &Path, points: &[CurvePoint]) -> opencv::Result<()> {
+    let image = draw_pr_curve(points)?;
+    let path = output_dir.join(PR_CURVE_IMAGE);
+    if !imgcodecs::imwrite(path.to_str().unwrap_or_default(), &image, &Vector::new())? {
+        return Err(opencv::Error::new(
+            core::StsError,
+            format!("Failed to write {}", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(videos: &[(&str, &[(u32, u32, Option<f64>)])]) -> DribbleEventsExport {
+        let videos: Vec<String> = videos
+            .iter()
+            .map(|(video_id, events)| {
+                let events: Vec<String> = events
+                    .iter()
+                    .map(|&(start, end, confidence)| {
+                        let confidence = confidence
+                            .map_or(String::new(), |c| format!(r#", "confidence": {}"#, c));
+                        format!(
+                            r#"{{"finished": true, "detected_dribble": true,
+                                "detected_tackle": false, "ever_contested": false,
+                                "possession_holder": 7,
+                                "start_frame": {}, "end_frame": {}{}}}"#,
+                            start, end, confidence
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"{{"video_id": "{}", "dribble_events": [{}]}}"#,
+                    video_id,
+                    events.join(",")
+                )
+            })
+            .collect();
+        let json = format!(
+            r#"{{"info": {{"version": "1.0", "generated_at": "2025-03-14T09:26:53Z"}},
+                "videos": [{}]}}"#,
+            videos.join(",")
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_pr_curve() {
+        let ground_truth = export(&[
+            ("SNGS-001", &[(100, 199, None), (300, 349, None)]),
+            ("SNGS-002", &[(10, 59, None)]),
+        ]);
+        // A confident hit, a false detection, an unscored hit and a barely scored hit
+        let detected = export(&[
+            (
+                "SNGS-001",
+                &[
+                    (100, 199, Some(0.9)),
+                    (500, 549, Some(0.6)),
+                    (300, 349, None),
+                ],
+            ),
+            ("SNGS-002", &[(10, 59, Some(0.2))]),
+        ]);
+
+        let points = pr_curve(&detected, &ground_truth, 0.5);
+        let thresholds: Vec<f64> = points.iter().map(|p| p.threshold).collect();
+        assert_eq!(thresholds, vec![0.9, 0.6, 0.2]);
+        // The unscored event is kept at every threshold
+        assert_eq!(points[0].metrics.true_positives, 2);
+        assert_eq!(points[0].metrics.num_detected, 2);
+        assert_eq!(points[1].metrics.precision, 2.0 / 3.0);
+        assert_eq!(points[2].metrics.recall, 1.0);
+        assert_eq!(best_f1(&points).unwrap().threshold, 0.2);
+
+        let unscored = export(&[("SNGS-001", &[(100, 199, None)])]);
+        assert!(pr_curve(&unscored, &ground_truth, 0.5).is_empty());
+    }
+}
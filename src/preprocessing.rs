@@ -0,0 +1,556 @@
+//! Cleans up the player tracks of a video before detection. Tracking noise, such as that of the
+//! interpolated predictions, otherwise shows up as defenders that jump in and out of the zones
+//! of the holder and start or end events.
+//!
+//! First, tracks are stitched: the tracker sometimes gives a player a new track id, e.g. after
+//! an occlusion, which ends the events of the player as if they left. A track that starts
+//! within `max_stitch_gap_frames` frames after another one ended, within `stitch_distance` of
+//! where it ended, is taken to be the same player and gets the id of the earlier track. Tracks
+//! of different categories, or with different jersey numbers or teams in their attributes, are
+//! never stitched, and tracks with the same jersey number are stitched first, then the closest.
+//!
+//! Then every track is processed as a whole, in three steps on the pitch positions of its
+//! annotations:
+//! 1. Teleports are removed: single positions the track jumps to and back from faster than
+//!    `max_speed`, while it moves slower than that without them.
+//! 2. The positions are smoothed with the filter of `smoothing`, run forward and backward over
+//!    the track and averaged, so the smoothed track does not lag behind.
+//! 3. Gaps of up to `max_gap_frames` frames where the track is missing are filled with
+//!    positions and image bounding boxes interpolated between the frames around the gap.
+//!
+//! Longer gaps split a track into parts that are smoothed separately. The ball is left as it
+//! is, as it moves far faster and less smoothly than the players.
+
+use crate::config::{SmoothingFilterConfig, DEFAULT_FRAME_RATE};
+use crate::data::models::{Annotation, BboxImage, BboxPitch, Labels};
+use crate::dribbling_detection::smoothing::build_smoother;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The `[preprocessing]` section. Tracks are preprocessed if the section is present.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PreprocessingConfig {
+    /// Filter of the pitch positions of every track
+    pub smoothing: SmoothingFilterConfig,
+    /// Longest gap in frames that is filled
+    pub max_gap_frames: u32,
+    /// Fastest plausible movement of a player in pitch units (meters) per second
+    pub max_speed: f64,
+    /// Give fragments of the same player's track the same id
+    pub stitch_tracks: bool,
+    /// Longest gap in frames between two fragments of a track
+    pub max_stitch_gap_frames: u32,
+    /// Largest distance in pitch units between the end of a fragment and the start of the next
+    pub stitch_distance: f64,
+}
+
+impl Default for PreprocessingConfig {
+    fn default() -> Self {
+        Self {
+            smoothing: SmoothingFilterConfig::SavitzkyGolay {
+                window: 7,
+                order: 2,
+            },
+            max_gap_frames: 5,
+            max_speed: 12.0,
+            stitch_tracks: true,
+            max_stitch_gap_frames: 25,
+            stitch_distance: 2.0,
+        }
+    }
+}
+
+/// What the preprocessing of a video changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PreprocessingStats {
+    /// Tracks that were given the id of an earlier track
+    pub tracks_stitched: usize,
+    /// Tracks after stitching
+    pub num_tracks: usize,
+    pub outliers_removed: usize,
+    pub frames_filled: usize,
+}
+
+/// Position of an annotation on the pitch, the middle of the bottom of its box.
+fn pitch_position(bbox: &BboxPitch) -> (f64, f64) {
+    (
+        (bbox.x_bottom_left + bbox.x_bottom_right) / 2.0,
+        (bbox.y_bottom_left + bbox.y_bottom_right) / 2.0,
+    )
+}
+
+/// Moves the box so its position is `position`.
+fn move_to(bbox: &mut BboxPitch, position: (f64, f64)) {
+    let current = pitch_position(bbox);
+    let (dx, dy) = (position.0 - current.0, position.1 - current.1);
+    bbox.x_bottom_left += dx;
+    bbox.x_bottom_right += dx;
+    bbox.x_bottom_middle += dx;
+    bbox.y_bottom_left += dy;
+    bbox.y_bottom_right += dy;
+    bbox.y_bottom_middle += dy;
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+fn lerp_image(a: &BboxImage, b: &BboxImage, t: f64) -> BboxImage {
+    BboxImage {
+        x: lerp(a.x, b.x, t),
+        y: lerp(a.y, b.y, t),
+        x_center: lerp(a.x_center, b.x_center, t),
+        y_center: lerp(a.y_center, b.y_center, t),
+        w: lerp(a.w, b.w, t),
+        h: lerp(a.h, b.h, t),
+    }
+}
+
+/// A position of a track: frame index, annotation index and pitch position.
+type TrackPoint = (usize, usize, (f64, f64));
+
+/// The points of `track` without its teleports, see the module docs.
+fn remove_teleports(
+    track: &[TrackPoint],
+    max_speed: f64,
+    frame_rate: f64,
+) -> (Vec<TrackPoint>, usize) {
+    let speed = |a: &TrackPoint, b: &TrackPoint| {
+        let seconds = b.0.saturating_sub(a.0).max(1) as f64 / frame_rate;
+        let (dx, dy) = (b.2 .0 - a.2 .0, b.2 .1 - a.2 .1);
+        (dx * dx + dy * dy).sqrt() / seconds
+    };
+    let mut kept: Vec<TrackPoint> = Vec::with_capacity(track.len());
+    let mut removed = 0;
+    for (i, point) in track.iter().enumerate() {
+        if let (Some(previous), Some(next)) = (kept.last(), track.get(i + 1)) {
+            if speed(previous, point) > max_speed
+                && speed(point, next) > max_speed
+                && speed(previous, next) <= max_speed
+            {
+                removed += 1;
+                continue;
+            }
+        }
+        kept.push(*point);
+    }
+    (kept, removed)
+}
+
+/// The positions of `points` smoothed forward and backward with `filter` and averaged.
+fn smooth_part(points: &[TrackPoint], filter: &SmoothingFilterConfig) -> Vec<(f64, f64)> {
+    let mut forward = build_smoother(filter);
+    let forward: Vec<(f64, f64)> = points.iter().map(|p| forward.smooth(p.2)).collect();
+    let mut backward = build_smoother(filter);
+    let mut backward: Vec<(f64, f64)> = points.iter().rev().map(|p| backward.smooth(p.2)).collect();
+    backward.reverse();
+    forward
+        .iter()
+        .zip(&backward)
+        .map(|(f, b)| ((f.0 + b.0) / 2.0, (f.1 + b.1) / 2.0))
+        .collect()
+}
+
+/// The image ids of `labels` ordered by the file names of the images, i.e. by frame.
+fn frame_order(labels: &Labels) -> Vec<String> {
+    let mut images: Vec<(&str, &str)> = labels
+        .images
+        .iter()
+        .map(|image| (image.file_name.as_str(), image.image_id.as_str()))
+        .collect();
+    images.sort();
+    images.iter().map(|(_, id)| id.to_string()).collect()
+}
+
+/// A track before stitching: its first and last frame and pitch position, and the category,
+/// jersey number and team of its annotations.
+#[derive(Clone, Debug, Default)]
+struct Fragment {
+    category_id: u32,
+    first: (usize, (f64, f64)),
+    last: (usize, (f64, f64)),
+    jersey: Option<String>,
+    team: Option<String>,
+}
+
+/// `true` if both values are known and differ.
+fn conflicts(a: &Option<String>, b: &Option<String>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a != b)
+}
+
+/// Gives the fragments of the same player's track the id of the first fragment, see the module
+/// docs. Returns the number of fragments that got a new id.
+pub fn stitch_tracks(
+    labels: &mut Labels,
+    config: &PreprocessingConfig,
+    category_map: &HashMap<String, u32>,
+) -> usize {
+    let image_ids = frame_order(labels);
+    let frames: HashMap<&str, usize> = image_ids
+        .iter()
+        .enumerate()
+        .map(|(frame, id)| (id.as_str(), frame))
+        .collect();
+
+    let ball = category_map.get("ball");
+    let mut fragments: HashMap<u32, Fragment> = HashMap::new();
+    for annotation in &labels.annotations {
+        if Some(&annotation.category_id) == ball {
+            continue;
+        }
+        let (Some(track_id), Some(bbox), Some(&frame)) = (
+            annotation.track_id,
+            &annotation.bbox_pitch,
+            frames.get(annotation.image_id.as_str()),
+        ) else {
+            continue;
+        };
+        let point = (frame, pitch_position(bbox));
+        let fragment = fragments.entry(track_id).or_insert_with(|| Fragment {
+            category_id: annotation.category_id,
+            first: point,
+            last: point,
+            ..Default::default()
+        });
+        if frame < fragment.first.0 {
+            fragment.first = point;
+        }
+        if frame > fragment.last.0 {
+            fragment.last = point;
+        }
+        if let Some(attributes) = &annotation.attributes {
+            fragment.jersey = fragment.jersey.take().or(attributes.jersey.clone());
+            fragment.team = fragment.team.take().or(attributes.team.clone());
+        }
+    }
+
+    // Pairs of a fragment and one that may continue it, the same jersey first, then closest
+    let mut candidates: Vec<(bool, f64, u32, u32)> = Vec::new();
+    for (&earlier_id, earlier) in &fragments {
+        for (&later_id, later) in &fragments {
+            let gap = later.first.0.saturating_sub(earlier.last.0 + 1);
+            if later.first.0 <= earlier.last.0
+                || gap > config.max_stitch_gap_frames as usize
+                || later.category_id != earlier.category_id
+                || conflicts(&earlier.jersey, &later.jersey)
+                || conflicts(&earlier.team, &later.team)
+            {
+                continue;
+            }
+            let (a, b) = (earlier.last.1, later.first.1);
+            let distance = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+            if distance <= config.stitch_distance {
+                let same_jersey = earlier.jersey.is_some() && earlier.jersey == later.jersey;
+                candidates.push((!same_jersey, distance, earlier_id, later_id));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| {
+        (a.0, a.1, a.2, a.3)
+            .partial_cmp(&(b.0, b.1, b.2, b.3))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut previous: HashMap<u32, u32> = HashMap::new();
+    let mut continued: HashSet<u32> = HashSet::new();
+    for (_, _, earlier_id, later_id) in candidates {
+        if continued.contains(&earlier_id) || previous.contains_key(&later_id) {
+            continue;
+        }
+        continued.insert(earlier_id);
+        previous.insert(later_id, earlier_id);
+    }
+
+    let stable_ids: HashMap<u32, u32> = previous
+        .keys()
+        .map(|&track_id| {
+            let mut first = track_id;
+            while let Some(&earlier_id) = previous.get(&first) {
+                first = earlier_id;
+            }
+            (track_id, first)
+        })
+        .collect();
+    for annotation in labels.annotations.iter_mut() {
+        if let Some(track_id) = annotation.track_id.as_mut() {
+            if let Some(&stable_id) = stable_ids.get(track_id) {
+                *track_id = stable_id;
+            }
+        }
+    }
+    stable_ids.len()
+}
+
+/// Preprocesses the player tracks of `labels`, see the module docs. Frames are ordered by the
+/// file names of the images. Annotations without a track id, a pitch position or a known image
+/// are left as they are.
+pub fn preprocess_tracks(
+    labels: &mut Labels,
+    config: &PreprocessingConfig,
+    category_map: &HashMap<String, u32>,
+    frame_rate: f64,
+) -> PreprocessingStats {
+    let frame_rate = if frame_rate > 0.0 {
+        frame_rate
+    } else {
+        DEFAULT_FRAME_RATE
+    };
+    let tracks_stitched = if config.stitch_tracks {
+        stitch_tracks(labels, config, category_map)
+    } else {
+        0
+    };
+    let image_ids = frame_order(labels);
+    let frames: HashMap<&str, usize> = image_ids
+        .iter()
+        .enumerate()
+        .map(|(frame, id)| (id.as_str(), frame))
+        .collect();
+
+    let ball = category_map.get("ball");
+    let mut tracks: HashMap<u32, Vec<TrackPoint>> = HashMap::new();
+    for (index, annotation) in labels.annotations.iter().enumerate() {
+        if Some(&annotation.category_id) == ball {
+            continue;
+        }
+        let (Some(track_id), Some(bbox), Some(&frame)) = (
+            annotation.track_id,
+            &annotation.bbox_pitch,
+            frames.get(annotation.image_id.as_str()),
+        ) else {
+            continue;
+        };
+        tracks
+            .entry(track_id)
+            .or_default()
+            .push((frame, index, pitch_position(bbox)));
+    }
+
+    let mut stats = PreprocessingStats {
+        tracks_stitched,
+        num_tracks: tracks.len(),
+        ..Default::default()
+    };
+    let mut removed: HashSet<usize> = HashSet::new();
+    let mut smoothed: Vec<(usize, (f64, f64))> = Vec::new();
+    let mut filled: Vec<Annotation> = Vec::new();
+    let max_step = config.max_gap_frames as usize + 1;
+    for (track_id, mut track) in tracks {
+        track.sort_by_key(|&(frame, index, _)| (frame, index));
+        let (kept, num_removed) = remove_teleports(&track, config.max_speed, frame_rate);
+        stats.outliers_removed += num_removed;
+        let kept_indices: HashSet<usize> = kept.iter().map(|&(_, index, _)| index).collect();
+        removed.extend(
+            track
+                .iter()
+                .map(|&(_, index, _)| index)
+                .filter(|index| !kept_indices.contains(index)),
+        );
+
+        // Parts of the track without gaps that are too long to fill
+        let mut start = 0;
+        for end in 1..=kept.len() {
+            if end < kept.len() && kept[end].0 - kept[end - 1].0 <= max_step {
+                continue;
+            }
+            let part = &kept[start..end];
+            let positions = smooth_part(part, &config.smoothing);
+            for (point, &position) in part.iter().zip(&positions) {
+                smoothed.push((point.1, position));
+            }
+
+            for (pair, positions) in part.windows(2).zip(positions.windows(2)) {
+                let (before, after) = (
+                    &labels.annotations[pair[0].1],
+                    &labels.annotations[pair[1].1],
+                );
+                let gap = pair[1].0 - pair[0].0;
+                for frame in pair[0].0 + 1..pair[1].0 {
+                    let t = (frame - pair[0].0) as f64 / gap as f64;
+                    let mut annotation = before.clone();
+                    annotation.id = format!("{}-filled-{}", track_id, image_ids[frame]);
+                    annotation.image_id = image_ids[frame].clone();
+                    if let Some(bbox) = annotation.bbox_pitch.as_mut() {
+                        let position = (
+                            lerp(positions[0].0, positions[1].0, t),
+                            lerp(positions[0].1, positions[1].1, t),
+                        );
+                        move_to(bbox, position);
+                    }
+                    annotation.bbox_pitch_raw = None;
+                    annotation.bbox_image = match (&before.bbox_image, &after.bbox_image) {
+                        (Some(a), Some(b)) => Some(lerp_image(a, b, t)),
+                        _ => None,
+                    };
+                    filled.push(annotation);
+                }
+            }
+            start = end;
+        }
+    }
+
+    for (index, position) in smoothed {
+        if let Some(bbox) = labels.annotations[index].bbox_pitch.as_mut() {
+            move_to(bbox, position);
+        }
+    }
+    stats.frames_filled = filled.len();
+    let mut index = 0;
+    labels.annotations.retain(|_| {
+        index += 1;
+        !removed.contains(&(index - 1))
+    });
+    labels.annotations.extend(filled);
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::{Attribute, Image};
+
+    fn annotation(track_id: u32, frame: u32, x: f64) -> Annotation {
+        Annotation {
+            id: format!("{}-{}", track_id, frame),
+            image_id: frame.to_string(),
+            track_id: Some(track_id),
+            category_id: 1,
+            bbox_pitch: Some(BboxPitch {
+                x_bottom_left: x - 0.5,
+                y_bottom_left: 0.0,
+                x_bottom_right: x + 0.5,
+                y_bottom_right: 0.0,
+                x_bottom_middle: x,
+                y_bottom_middle: 0.0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn images(num_frames: u32) -> Vec<Image> {
+        (0..num_frames)
+            .map(|frame| Image {
+                is_labeled: true,
+                image_id: frame.to_string(),
+                file_name: format!("{:06}.jpg", frame),
+                height: 1080,
+                width: 1920,
+                has_labeled_person: None,
+                has_labeled_pitch: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stitch_tracks() {
+        let fragment = |track_id, frames: std::ops::Range<u32>, x: f64, jersey: Option<&str>| {
+            frames
+                .map(|frame| Annotation {
+                    attributes: Some(Attribute {
+                        role: None,
+                        jersey: jersey.map(str::to_string),
+                        team: Some("left".to_string()),
+                    }),
+                    ..annotation(track_id, frame, x)
+                })
+                .collect::<Vec<_>>()
+        };
+        // 1 continues as 7 and then as 9. 8 is closer to where 1 ended, but has another
+        // jersey number, and 3 is too far away.
+        let annotations = [
+            fragment(1, 0..5, 0.0, Some("10")),
+            fragment(8, 6..10, 0.2, Some("5")),
+            fragment(7, 7..10, 0.8, Some("10")),
+            fragment(3, 6..10, 30.0, None),
+            fragment(9, 12..15, 1.0, None),
+        ]
+        .concat();
+        let mut labels = Labels {
+            images: images(15),
+            annotations,
+            ..Default::default()
+        };
+        let config = PreprocessingConfig::default();
+        let category_map = HashMap::from([("ball".to_string(), 4)]);
+
+        assert_eq!(stitch_tracks(&mut labels, &config, &category_map), 2);
+        let track_ids: HashSet<u32> = labels
+            .annotations
+            .iter()
+            .filter_map(|a| a.track_id)
+            .collect();
+        assert_eq!(track_ids, HashSet::from([1, 3, 8]));
+
+        let strict = PreprocessingConfig {
+            max_stitch_gap_frames: 1,
+            ..config
+        };
+        let mut labels = Labels {
+            images: images(15),
+            annotations: [fragment(1, 0..5, 0.0, None), fragment(7, 7..10, 0.0, None)].concat(),
+            ..Default::default()
+        };
+        assert_eq!(stitch_tracks(&mut labels, &strict, &category_map), 0);
+    }
+
+    #[test]
+    fn test_preprocess_tracks() {
+        let images = images(20);
+        // Player 1 walks at 0.1 per frame, jumps 20 away in frame 5 and is missing in frames
+        // 10 to 12. Player 2 is missing for longer than the largest gap.
+        let mut annotations: Vec<Annotation> = (0..20)
+            .filter(|frame| !(10..13).contains(frame))
+            .map(|frame| {
+                let x = if frame == 5 {
+                    20.5
+                } else {
+                    f64::from(frame) * 0.1
+                };
+                annotation(1, frame, x)
+            })
+            .collect();
+        annotations.extend([annotation(2, 0, 5.0), annotation(2, 10, 5.0)]);
+        let mut labels = Labels {
+            images,
+            annotations,
+            ..Default::default()
+        };
+        let config = PreprocessingConfig {
+            smoothing: SmoothingFilterConfig::None,
+            ..Default::default()
+        };
+        let category_map = HashMap::from([("ball".to_string(), 4)]);
+
+        let stats = preprocess_tracks(&mut labels, &config, &category_map, 25.0);
+        assert_eq!(stats.num_tracks, 2);
+        assert_eq!(stats.outliers_removed, 1);
+        // Frame 5 of the teleport is filled as well
+        assert_eq!(stats.frames_filled, 4);
+        let frames = |track_id| {
+            labels
+                .annotations
+                .iter()
+                .filter(|a| a.track_id == Some(track_id))
+                .count()
+        };
+        assert_eq!((frames(1), frames(2)), (20, 2));
+        let filled = labels
+            .annotations
+            .iter()
+            .find(|a| a.image_id == "11")
+            .unwrap();
+        let x = pitch_position(filled.bbox_pitch.as_ref().unwrap()).0;
+        assert!((x - 1.1).abs() < 1e-9);
+
+        // Smoothing forward and backward does not shift a steady walk
+        let mut part: Vec<TrackPoint> = (0..10)
+            .map(|frame| (frame, frame, (frame as f64, 0.0)))
+            .collect();
+        part[4].2 .1 = 1.0;
+        let filter = SmoothingFilterConfig::MovingAverage { window: 3 };
+        let positions = smooth_part(&part, &filter);
+        assert!((positions[6].0 - 6.0).abs() < 1e-9);
+        assert!(positions[4].1 < 1.0 && positions[4].1 > 0.0);
+    }
+}
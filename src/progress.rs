@@ -0,0 +1,231 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Progress of a detection run, sent from the worker threads to the reporter thread.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgressEvent {
+    /// More videos will be processed, e.g. once the videos of a subset are listed
+    VideosQueued(usize),
+    VideoStarted {
+        id: usize,
+        name: String,
+        num_frames: usize,
+    },
+    FrameProcessed {
+        id: usize,
+    },
+    VideoFinished {
+        id: usize,
+    },
+    /// A line printed above the progress bars
+    Message(String),
+}
+
+/// Sends progress events to a [`ProgressReporter`]. A disabled sender prints messages
+/// directly and drops all other events.
+#[derive(Clone, Debug, Default)]
+pub struct ProgressSender {
+    sender: Option<Sender<ProgressEvent>>,
+    next_id: Arc<AtomicUsize>,
+}
+
+impl ProgressSender {
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    fn send(&self, event: ProgressEvent) {
+        if let Some(sender) = &self.sender {
+            // The reporter only stops once all senders are dropped
+            let _ = sender.send(event);
+        }
+    }
+
+    pub fn videos_queued(&self, num_videos: usize) {
+        self.send(ProgressEvent::VideosQueued(num_videos));
+    }
+
+    /// Prints a line without breaking up the progress bars.
+    pub fn println(&self, message: impl Into<String>) {
+        match &self.sender {
+            Some(_) => self.send(ProgressEvent::Message(message.into())),
+            None => println!("{}", message.into()),
+        }
+    }
+
+    /// Starts the progress bar of a video. The video counts as finished when the returned
+    /// handle is dropped, also if it was skipped or cancelled.
+    pub fn start_video(&self, name: &str, num_frames: usize) -> VideoProgress {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.send(ProgressEvent::VideoStarted {
+            id,
+            name: name.to_string(),
+            num_frames,
+        });
+        VideoProgress {
+            id,
+            sender: self.clone(),
+        }
+    }
+}
+
+/// Progress of a single video, see [`ProgressSender::start_video`].
+#[derive(Debug)]
+pub struct VideoProgress {
+    id: usize,
+    sender: ProgressSender,
+}
+
+impl VideoProgress {
+    pub fn frame_processed(&self) {
+        self.sender.send(ProgressEvent::FrameProcessed { id: self.id });
+    }
+}
+
+impl Drop for VideoProgress {
+    fn drop(&mut self) {
+        self.sender.send(ProgressEvent::VideoFinished { id: self.id });
+    }
+}
+
+/// Shows the number of finished videos with an ETA and the frame progress of every video
+/// that is being processed. The bars are drawn by a separate thread, which receives the
+/// progress of the workers through a channel.
+pub struct ProgressReporter {
+    sender: ProgressSender,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ProgressReporter {
+    pub fn start() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || report(receiver));
+        Self {
+            sender: ProgressSender {
+                sender: Some(sender),
+                next_id: Arc::new(AtomicUsize::new(0)),
+            },
+            handle: Some(handle),
+        }
+    }
+
+    /// A reporter without progress bars, for interactive modes that print to the terminal.
+    pub fn disabled() -> Self {
+        Self {
+            sender: ProgressSender::disabled(),
+            handle: None,
+        }
+    }
+
+    pub fn sender(&self) -> &ProgressSender {
+        &self.sender
+    }
+
+    /// Waits until all events are shown. Senders that are still alive keep the reporter
+    /// running, so they must be dropped first.
+    pub fn finish(mut self) {
+        self.sender = ProgressSender::disabled();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn report(receiver: Receiver<ProgressEvent>) {
+    let multi = MultiProgress::new();
+    let overall = multi.add(ProgressBar::new(0));
+    overall.set_style(
+        ProgressStyle::with_template(
+            "{prefix:>20} [{elapsed_precise}] {wide_bar} {pos}/{len} videos, ETA {eta}",
+        )
+        .unwrap(),
+    );
+    overall.set_prefix("Total");
+    let frame_style =
+        ProgressStyle::with_template("{prefix:>20} {wide_bar} {pos}/{len} frames").unwrap();
+
+    let mut videos: HashMap<usize, ProgressBar> = HashMap::new();
+    for event in receiver {
+        match event {
+            ProgressEvent::VideosQueued(num_videos) => overall.inc_length(num_videos as u64),
+            ProgressEvent::VideoStarted {
+                id,
+                name,
+                num_frames,
+            } => {
+                let bar = multi.add(ProgressBar::new(num_frames as u64));
+                bar.set_style(frame_style.clone());
+                bar.set_prefix(name);
+                videos.insert(id, bar);
+            }
+            ProgressEvent::FrameProcessed { id } => {
+                if let Some(bar) = videos.get(&id) {
+                    bar.inc(1);
+                }
+            }
+            ProgressEvent::VideoFinished { id } => {
+                if let Some(bar) = videos.remove(&id) {
+                    bar.finish_and_clear();
+                    multi.remove(&bar);
+                }
+                overall.inc(1);
+            }
+            ProgressEvent::Message(message) => {
+                let _ = multi.println(message);
+            }
+        }
+    }
+    overall.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_progress_events() {
+        let (sender, receiver) = mpsc::channel();
+        let progress = ProgressSender {
+            sender: Some(sender),
+            next_id: Arc::new(AtomicUsize::new(0)),
+        };
+
+        progress.videos_queued(2);
+        {
+            let first = progress.start_video("SNGS-001", 750);
+            first.frame_processed();
+        }
+        drop(progress.start_video("SNGS-002", 750));
+        drop(progress);
+
+        let events: Vec<ProgressEvent> = receiver.into_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                ProgressEvent::VideosQueued(2),
+                ProgressEvent::VideoStarted {
+                    id: 0,
+                    name: "SNGS-001".to_string(),
+                    num_frames: 750
+                },
+                ProgressEvent::FrameProcessed { id: 0 },
+                ProgressEvent::VideoFinished { id: 0 },
+                ProgressEvent::VideoStarted {
+                    id: 1,
+                    name: "SNGS-002".to_string(),
+                    num_frames: 750
+                },
+                ProgressEvent::VideoFinished { id: 1 },
+            ]
+        );
+        assert!(!ProgressSender::disabled().is_enabled());
+    }
+}
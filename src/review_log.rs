@@ -0,0 +1,412 @@
+//! `reviewed_events.json`: the verdicts of review mode, keyed by video and event. The file is
+//! rewritten after every decision, so a review session can be stopped at any time without
+//! losing work, and decisions of earlier sessions are kept. Every annotator has their own
+//! file, see [`review_log_file`].
+
+use crate::data::models::{
+    DribbleEventsExport, ExportInfo, ReviewLabel, ReviewVerdict, ReviewedEvent,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the review log in the output directory
+pub const REVIEW_LOG_FILE: &str = "reviewed_events.json";
+
+/// Name of the review log of `annotator`, e.g. `reviewed_events-alice.json`, or
+/// [`REVIEW_LOG_FILE`] without an annotator.
+pub fn review_log_file(annotator: Option<&str>) -> String {
+    match annotator {
+        Some(annotator) => format!("reviewed_events-{}.json", annotator),
+        None => REVIEW_LOG_FILE.to_string(),
+    }
+}
+
+/// Frames the boundary keys move the start or end of a clip by
+pub const BOUNDARY_STEP: i32 = 5;
+
+/// Checks that `annotator` can be used in a file name: letters, digits, `-` and `_` only.
+pub fn check_annotator(annotator: &str) -> io::Result<()> {
+    let valid = !annotator.is_empty()
+        && annotator
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "invalid annotator \"{}\", use letters, digits, - and _ only",
+                annotator
+            ),
+        ))
+    }
+}
+
+/// Moves the start and end frame of a clip by the given number of frames, keeping the start
+/// before the end and the end at most `last_frame`.
+pub fn adjust_boundaries(
+    (start, end): (u32, u32),
+    start_delta: i32,
+    end_delta: i32,
+    last_frame: u32,
+) -> (u32, u32) {
+    let start = start
+        .saturating_add_signed(start_delta)
+        .min(end.saturating_sub(1));
+    let end = end
+        .saturating_add_signed(end_delta)
+        .min(last_frame)
+        .max(start + 1);
+    (start, end)
+}
+
+/// Key of an event within its video, e.g. `120-185`.
+pub fn event_key(start_frame: u32, end_frame: u32) -> String {
+    format!("{}-{}", start_frame, end_frame)
+}
+
+/// How often the verdicts of a session agreed with the detector.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerdictCounts {
+    pub accepted: usize,
+    pub rejected: usize,
+    pub reclassified: usize,
+}
+
+impl VerdictCounts {
+    /// Counts the verdicts of `events`. Events of earlier versions without a verdict are
+    /// skipped.
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a ReviewedEvent>) -> Self {
+        let mut counts = Self::default();
+        for event in events {
+            match event.verdict {
+                Some(ReviewVerdict::Accepted) => counts.accepted += 1,
+                Some(ReviewVerdict::Rejected) => counts.rejected += 1,
+                Some(ReviewVerdict::Reclassified) => counts.reclassified += 1,
+                None => {}
+            }
+        }
+        counts
+    }
+
+    pub fn total(&self) -> usize {
+        self.accepted + self.rejected + self.reclassified
+    }
+
+    /// Share of the verdicts that accepted the event as detected, `None` without verdicts.
+    pub fn agreement(&self) -> Option<f64> {
+        (self.total() > 0).then(|| self.accepted as f64 / self.total() as f64)
+    }
+}
+
+/// Reviewed events by video id and [`event_key`]
+pub type ReviewedVideos = BTreeMap<String, BTreeMap<String, ReviewedEvent>>;
+
+/// The event of the last decision, to tell where a review session stopped.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewPosition {
+    pub video_id: String,
+    /// Index of the event in the video's events in the events file
+    pub event_index: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReviewedEventsExport {
+    pub info: ExportInfo,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotator: Option<String>,
+    #[serde(default)]
+    pub last_decision: Option<ReviewPosition>,
+    pub videos: ReviewedVideos,
+}
+
+/// A decision that can be undone, with the entry and position it replaced.
+type Decision = (ReviewedEvent, Option<ReviewedEvent>, Option<ReviewPosition>);
+
+/// The decisions of a review session, written to `reviewed_events.json` as they are made.
+/// Review mode shows a single window, so the log is not shared between threads.
+pub struct ReviewLog {
+    path: PathBuf,
+    annotator: Option<String>,
+    videos: ReviewedVideos,
+    last_decision: Option<ReviewPosition>,
+    /// The event keys and detected kinds of the events under review by video id, in the order
+    /// of the events file
+    detected: HashMap<String, Vec<(String, ReviewLabel)>>,
+    history: Vec<Decision>,
+}
+
+impl ReviewLog {
+    /// Opens the review log of `annotator` in `output_dir`, keeping the decisions it already
+    /// has. The kinds of the events under review are read from `dribble_events_path`.
+    pub fn open(
+        output_dir: &Path,
+        dribble_events_path: &Path,
+        annotator: Option<&str>,
+    ) -> io::Result<Self> {
+        if let Some(annotator) = annotator {
+            check_annotator(annotator)?;
+        }
+        fs::create_dir_all(output_dir)?;
+        let path = output_dir.join(review_log_file(annotator));
+        let (videos, last_decision) = if path.exists() {
+            let export: ReviewedEventsExport = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            (export.videos, export.last_decision)
+        } else {
+            (ReviewedVideos::new(), None)
+        };
+
+        let events: DribbleEventsExport =
+            serde_json::from_str(&fs::read_to_string(dribble_events_path)?)?;
+        let detected = events
+            .videos
+            .iter()
+            .map(|video| {
+                let kinds = video
+                    .dribble_events
+                    .iter()
+                    .map(|event| {
+                        let end_frame = event.end_frame.unwrap_or(event.start_frame);
+                        let kind = if event.detected_tackle {
+                            ReviewLabel::Tackle
+                        } else {
+                            ReviewLabel::Dribble
+                        };
+                        (event_key(event.start_frame, end_frame), kind)
+                    })
+                    .collect();
+                (video.video_id.clone(), kinds)
+            })
+            .collect();
+
+        Ok(Self {
+            path,
+            annotator: annotator.map(str::to_string),
+            videos,
+            last_decision,
+            detected,
+            history: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The detected kind of an event, if it is in the events file.
+    pub fn detected_label(
+        &self,
+        video_id: &str,
+        start_frame: u32,
+        end_frame: u32,
+    ) -> Option<ReviewLabel> {
+        let key = event_key(start_frame, end_frame);
+        let events = self.detected.get(video_id)?;
+        events.iter().find(|(k, _)| *k == key).map(|(_, kind)| *kind)
+    }
+
+    /// Where the last decision was made, also if it was made in an earlier session.
+    pub fn last_decision(&self) -> Option<&ReviewPosition> {
+        self.last_decision.as_ref()
+    }
+
+    /// Records a decision, replacing an earlier one for the same event, and writes the log.
+    pub fn record(&mut self, event: ReviewedEvent) -> io::Result<()> {
+        let key = event_key(event.start_frame, event.end_frame);
+        let event_index = self
+            .detected
+            .get(&event.video_id)
+            .and_then(|events| events.iter().position(|(k, _)| *k == key));
+        let position = event_index.map(|event_index| ReviewPosition {
+            video_id: event.video_id.clone(),
+            event_index,
+        });
+        let previous_position = match position {
+            Some(position) => self.last_decision.replace(position),
+            None => self.last_decision.clone(),
+        };
+
+        let previous = self
+            .videos
+            .entry(event.video_id.clone())
+            .or_default()
+            .insert(key, event.clone());
+        self.history.push((event, previous, previous_position));
+        self.write(&self.videos, self.last_decision.as_ref())
+    }
+
+    /// Reverts the last decision and writes the log. Returns the reverted decision, or `None`
+    /// if there is nothing left to undo. Nothing is reverted if the log can not be written.
+    pub fn undo(&mut self) -> io::Result<Option<ReviewedEvent>> {
+        let Some((event, previous, previous_position)) = self.history.last().cloned() else {
+            return Ok(None);
+        };
+        let mut videos = self.videos.clone();
+        let video = videos.entry(event.video_id.clone()).or_default();
+        let key = event_key(event.start_frame, event.end_frame);
+        match previous {
+            Some(previous) => {
+                video.insert(key, previous);
+            }
+            None => {
+                video.remove(&key);
+            }
+        }
+        if video.is_empty() {
+            videos.remove(&event.video_id);
+        }
+
+        self.write(&videos, previous_position.as_ref())?;
+        self.videos = videos;
+        self.last_decision = previous_position;
+        self.history.pop();
+        Ok(Some(event))
+    }
+
+    /// The recorded decision of an event, if any.
+    pub fn get(&self, video_id: &str, start_frame: u32, end_frame: u32) -> Option<&ReviewedEvent> {
+        self.videos
+            .get(video_id)?
+            .get(&event_key(start_frame, end_frame))
+    }
+
+    /// Writes the whole log to a temporary file first, so a crash never leaves it half written.
+    fn write(
+        &self,
+        videos: &ReviewedVideos,
+        last_decision: Option<&ReviewPosition>,
+    ) -> io::Result<()> {
+        let export = ReviewedEventsExport {
+            info: ExportInfo {
+                version: "reviewed_events_1.0".to_string(),
+                generated_at: Utc::now().to_rfc3339(),
+                incomplete: false,
+            },
+            annotator: self.annotator.clone(),
+            last_decision: last_decision.cloned(),
+            videos: videos.clone(),
+        };
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&export)?)?;
+        fs::rename(tmp_path, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::ReviewVerdict;
+
+    const EVENTS_JSON: &str = r#"{
+        "info": { "version": "1.0", "generated_at": "2025-03-14T09:26:53Z" },
+        "videos": [{
+            "video_id": "SNGS-001",
+            "dribble_events": [{
+                "finished": true,
+                "detected_dribble": false,
+                "detected_tackle": true,
+                "ever_contested": true,
+                "possession_holder": 7,
+                "start_frame": 120,
+                "end_frame": 185
+            }]
+        }]
+    }"#;
+
+    fn reviewed(start_frame: u32, end_frame: u32, label: ReviewLabel) -> ReviewedEvent {
+        ReviewedEvent {
+            video_id: "SNGS-001".to_string(),
+            start_frame,
+            end_frame,
+            label,
+            verdict: Some(ReviewVerdict::new(Some(ReviewLabel::Tackle), label)),
+            corrected_start_frame: None,
+            corrected_end_frame: None,
+            tags: Vec::new(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_review_log() {
+        let output_dir = std::env::temp_dir().join(format!("review_log_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+        fs::create_dir_all(&output_dir).unwrap();
+        let events_path = output_dir.join("dribble_events.json");
+        fs::write(&events_path, EVENTS_JSON).unwrap();
+
+        let mut log = ReviewLog::open(&output_dir, &events_path, None).unwrap();
+        assert_eq!(log.detected_label("SNGS-001", 120, 185), Some(ReviewLabel::Tackle));
+        assert_eq!(log.detected_label("SNGS-001", 1, 2), None);
+
+        log.record(reviewed(120, 185, ReviewLabel::Tackle)).unwrap();
+        log.record(reviewed(120, 185, ReviewLabel::Dribble)).unwrap();
+        assert_eq!(
+            log.get("SNGS-001", 120, 185).unwrap().verdict,
+            Some(ReviewVerdict::Reclassified)
+        );
+
+        // Undo restores the decision that was replaced
+        let undone = log.undo().unwrap().unwrap();
+        assert_eq!(undone.label, ReviewLabel::Dribble);
+        assert_eq!(log.get("SNGS-001", 120, 185).unwrap().label, ReviewLabel::Tackle);
+
+        // Decisions are kept when the log is opened again, but can not be undone
+        let mut log = ReviewLog::open(&output_dir, &events_path, None).unwrap();
+        let position = ReviewPosition {
+            video_id: "SNGS-001".to_string(),
+            event_index: 0,
+        };
+        assert_eq!(log.last_decision(), Some(&position));
+        assert_eq!(
+            log.get("SNGS-001", 120, 185).unwrap().verdict,
+            Some(ReviewVerdict::Accepted)
+        );
+        assert!(log.undo().unwrap().is_none());
+
+        // Annotators have their own log
+        let log = ReviewLog::open(&output_dir, &events_path, Some("alice")).unwrap();
+        assert!(log.path().ends_with("reviewed_events-alice.json"));
+        assert!(log.get("SNGS-001", 120, 185).is_none());
+        assert!(ReviewLog::open(&output_dir, &events_path, Some("../alice")).is_err());
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn test_verdict_counts() {
+        let mut events = vec![
+            reviewed(1, 2, ReviewLabel::Tackle),
+            reviewed(3, 4, ReviewLabel::Dribble),
+            reviewed(5, 6, ReviewLabel::Other),
+            reviewed(7, 8, ReviewLabel::Tackle),
+        ];
+        events[3].verdict = None;
+        let counts = VerdictCounts::from_events(&events);
+        assert_eq!(
+            counts,
+            VerdictCounts {
+                accepted: 1,
+                rejected: 1,
+                reclassified: 1,
+            }
+        );
+        assert_eq!(counts.agreement(), Some(1.0 / 3.0));
+        assert_eq!(VerdictCounts::default().agreement(), None);
+    }
+
+    #[test]
+    fn test_adjust_boundaries() {
+        assert_eq!(adjust_boundaries((120, 185), -BOUNDARY_STEP, 0, 750), (115, 185));
+        assert_eq!(adjust_boundaries((120, 185), 0, BOUNDARY_STEP, 750), (120, 190));
+        // The clip keeps at least two frames and stays within the video
+        assert_eq!(adjust_boundaries((120, 122), BOUNDARY_STEP, 0, 750), (121, 122));
+        assert_eq!(adjust_boundaries((120, 122), 0, -BOUNDARY_STEP, 750), (120, 121));
+        assert_eq!(adjust_boundaries((3, 748), -BOUNDARY_STEP, BOUNDARY_STEP, 750), (0, 750));
+    }
+}
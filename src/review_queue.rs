@@ -0,0 +1,160 @@
+//! The clips of a review session. The events of `dribble_events.json` are filtered and ordered
+//! by the `[review]` section, so the clips are not shown in the order of the data directory.
+
+use crate::config::{ReviewConfig, ReviewOrder};
+use crate::data::models::DribbleEventsExport;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// An event to review.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReviewItem {
+    pub video_id: String,
+    pub start_frame: u32,
+    pub end_frame: u32,
+    pub tackle: bool,
+    pub contested: bool,
+    pub confidence: Option<f64>,
+}
+
+impl ReviewItem {
+    pub fn num_frames(&self) -> u32 {
+        self.end_frame.saturating_sub(self.start_frame)
+    }
+
+    fn kind(&self) -> &'static str {
+        if self.tackle {
+            "tackle"
+        } else {
+            "dribble"
+        }
+    }
+}
+
+/// Reads the events of `dribble_events_path` and filters and orders them as configured.
+pub fn load_review_queue(
+    dribble_events_path: &Path,
+    review: &ReviewConfig,
+) -> io::Result<Vec<ReviewItem>> {
+    let export: DribbleEventsExport =
+        serde_json::from_str(&fs::read_to_string(dribble_events_path)?)?;
+    let items = export
+        .videos
+        .iter()
+        .flat_map(|video| {
+            video.dribble_events.iter().map(|event| ReviewItem {
+                video_id: video.video_id.clone(),
+                start_frame: event.start_frame,
+                end_frame: event.end_frame.unwrap_or(event.start_frame),
+                tackle: event.detected_tackle,
+                contested: event.ever_contested,
+                confidence: event.confidence,
+            })
+        })
+        .collect();
+    Ok(review_queue(items, review))
+}
+
+/// Filters and orders `items`. Sorting is stable, so events that compare equal keep the order
+/// of the events file.
+pub fn review_queue(items: Vec<ReviewItem>, review: &ReviewConfig) -> Vec<ReviewItem> {
+    let mut queue: Vec<ReviewItem> = items
+        .into_iter()
+        .filter(|item| !review.only_contested || item.contested)
+        .filter(|item| {
+            review.kinds.is_empty()
+                || review
+                    .kinds
+                    .iter()
+                    .any(|kind| kind.eq_ignore_ascii_case(item.kind()))
+        })
+        .filter(|item| review.videos.is_empty() || review.videos.contains(&item.video_id))
+        .collect();
+
+    match review.order {
+        ReviewOrder::File => {}
+        ReviewOrder::Shortest => queue.sort_by_key(ReviewItem::num_frames),
+        // Events without a confidence come last
+        ReviewOrder::LowestConfidence => queue.sort_by(|a, b| {
+            let a = a.confidence.unwrap_or(f64::INFINITY);
+            let b = b.confidence.unwrap_or(f64::INFINITY);
+            a.total_cmp(&b)
+        }),
+    }
+    queue
+}
+
+/// Groups the queue into runs of events of the same video, in queue order. A video can appear
+/// in more than one run if the order moves between videos.
+pub fn group_by_video(queue: &[ReviewItem]) -> Vec<(String, Vec<(u32, u32)>)> {
+    let mut groups: Vec<(String, Vec<(u32, u32)>)> = Vec::new();
+    for item in queue {
+        let interval = (item.start_frame, item.end_frame);
+        match groups.last_mut() {
+            Some((video_id, intervals)) if *video_id == item.video_id => intervals.push(interval),
+            _ => groups.push((item.video_id.clone(), vec![interval])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(video_id: &str, start_frame: u32, end_frame: u32, tackle: bool) -> ReviewItem {
+        ReviewItem {
+            video_id: video_id.to_string(),
+            start_frame,
+            end_frame,
+            tackle,
+            contested: tackle,
+            confidence: Some(f64::from(end_frame - start_frame) / 100.0),
+        }
+    }
+
+    #[test]
+    fn test_review_queue() {
+        let items = vec![
+            item("SNGS-001", 100, 180, false),
+            item("SNGS-002", 10, 30, true),
+            item("SNGS-001", 300, 340, true),
+        ];
+
+        let shortest = ReviewConfig {
+            order: ReviewOrder::Shortest,
+            ..Default::default()
+        };
+        let queue = review_queue(items.clone(), &shortest);
+        assert_eq!(
+            group_by_video(&queue),
+            vec![
+                ("SNGS-002".to_string(), vec![(10, 30)]),
+                ("SNGS-001".to_string(), vec![(300, 340), (100, 180)]),
+            ]
+        );
+
+        let tackles = ReviewConfig {
+            kinds: vec!["Tackle".to_string()],
+            videos: vec!["SNGS-001".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            review_queue(items.clone(), &tackles),
+            vec![items[2].clone()]
+        );
+
+        let mut unscored = items.clone();
+        unscored[1].confidence = None;
+        let lowest_confidence = ReviewConfig {
+            order: ReviewOrder::LowestConfidence,
+            only_contested: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            review_queue(unscored.clone(), &lowest_confidence),
+            vec![unscored[2].clone(), unscored[1].clone()]
+        );
+    }
+}
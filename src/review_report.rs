@@ -0,0 +1,295 @@
+//! `review_report.json`: merges the review logs of all annotators in the output directory,
+//! measures how much they agree and lists the events they disagree on for adjudication.
+//!
+//! Agreement is computed for every pair of annotators over the events both reviewed, on the
+//! verdict (accepted as a dribble or tackle, or rejected) and on the class (`dribble`,
+//! `tackle` or `other`), as raw agreement and as Cohen's kappa.
+
+use crate::data::models::{ExportInfo, ReviewLabel, ReviewedEvent};
+use crate::review_log::{ReviewedEventsExport, ReviewedVideos, REVIEW_LOG_FILE};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the report in the output directory
+pub const REVIEW_REPORT_FILE: &str = "review_report.json";
+
+/// Name of the annotator of a review log written without `--annotator`
+const UNNAMED_ANNOTATOR: &str = "unnamed";
+
+/// Agreement of two annotators on the events both of them reviewed.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PairAgreement {
+    pub annotators: (String, String),
+    pub num_events: usize,
+    /// Share of events both accepted or both rejected
+    pub verdict_agreement: f64,
+    /// `None` if there are no shared events
+    pub verdict_kappa: Option<f64>,
+    /// Share of events with the same label
+    pub class_agreement: f64,
+    pub class_kappa: Option<f64>,
+}
+
+/// An event that annotators labeled differently.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Disagreement {
+    pub video_id: String,
+    pub start_frame: u32,
+    pub end_frame: u32,
+    /// Label by annotator
+    pub labels: BTreeMap<String, ReviewLabel>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReviewReport {
+    pub info: ExportInfo,
+    pub annotators: Vec<String>,
+    pub agreement: Vec<PairAgreement>,
+    pub disagreements: Vec<Disagreement>,
+    /// The events with the label of the majority of their annotators. Events without a
+    /// majority are left out until they are adjudicated
+    pub merged: ReviewedVideos,
+}
+
+/// Reads every review log in `output_dir`, by annotator.
+pub fn load_review_logs(output_dir: &Path) -> io::Result<BTreeMap<String, ReviewedVideos>> {
+    let mut logs = BTreeMap::new();
+    for entry in fs::read_dir(output_dir)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let name = if file_name == REVIEW_LOG_FILE {
+            UNNAMED_ANNOTATOR
+        } else {
+            match file_name
+                .strip_prefix("reviewed_events-")
+                .and_then(|name| name.strip_suffix(".json"))
+            {
+                Some(name) => name,
+                None => continue,
+            }
+        };
+        let export: ReviewedEventsExport = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            })?;
+        let annotator = export.annotator.unwrap_or_else(|| name.to_string());
+        logs.insert(annotator, export.videos);
+    }
+    Ok(logs)
+}
+
+/// Cohen's kappa of the label pairs of two annotators: their agreement corrected for the
+/// agreement expected by chance. `None` if there are no pairs. If both annotators used the
+/// same single label throughout, their agreement is perfect and the kappa is 1.
+pub fn cohens_kappa<T: PartialEq + Copy>(pairs: &[(T, T)]) -> Option<f64> {
+    if pairs.is_empty() {
+        return None;
+    }
+    let total = pairs.len() as f64;
+    let observed = pairs.iter().filter(|(a, b)| a == b).count() as f64 / total;
+
+    let mut categories: Vec<T> = Vec::new();
+    for &(a, b) in pairs {
+        for label in [a, b] {
+            if !categories.contains(&label) {
+                categories.push(label);
+            }
+        }
+    }
+    let expected: f64 = categories
+        .iter()
+        .map(|category| {
+            let first = pairs.iter().filter(|(a, _)| a == category).count() as f64;
+            let second = pairs.iter().filter(|(_, b)| b == category).count() as f64;
+            (first / total) * (second / total)
+        })
+        .sum();
+
+    if expected >= 1.0 {
+        Some(1.0)
+    } else {
+        Some((observed - expected) / (1.0 - expected))
+    }
+}
+
+fn agreement<T: PartialEq>(pairs: &[(T, T)]) -> f64 {
+    if pairs.is_empty() {
+        return 0.0;
+    }
+    pairs.iter().filter(|(a, b)| a == b).count() as f64 / pairs.len() as f64
+}
+
+/// Merges the review logs of `logs` into a report.
+pub fn create_review_report(logs: &BTreeMap<String, ReviewedVideos>) -> ReviewReport {
+    let annotators: Vec<String> = logs.keys().cloned().collect();
+
+    let mut agreements = Vec::new();
+    for (i, first) in annotators.iter().enumerate() {
+        for second in &annotators[i + 1..] {
+            let labels: Vec<(ReviewLabel, ReviewLabel)> = logs[first]
+                .iter()
+                .flat_map(|(video_id, events)| {
+                    events.iter().filter_map(move |(key, event)| {
+                        let other = logs[second].get(video_id)?.get(key)?;
+                        Some((event.label, other.label))
+                    })
+                })
+                .collect();
+            let verdicts: Vec<(bool, bool)> = labels
+                .iter()
+                .map(|&(a, b)| (a != ReviewLabel::Other, b != ReviewLabel::Other))
+                .collect();
+            agreements.push(PairAgreement {
+                annotators: (first.clone(), second.clone()),
+                num_events: labels.len(),
+                verdict_agreement: agreement(&verdicts),
+                verdict_kappa: cohens_kappa(&verdicts),
+                class_agreement: agreement(&labels),
+                class_kappa: cohens_kappa(&labels),
+            });
+        }
+    }
+
+    // The decisions of every event, by annotator
+    let mut events: BTreeMap<(String, String), BTreeMap<String, &ReviewedEvent>> = BTreeMap::new();
+    for (annotator, videos) in logs {
+        for (video_id, video_events) in videos {
+            for (key, event) in video_events {
+                events
+                    .entry((video_id.clone(), key.clone()))
+                    .or_default()
+                    .insert(annotator.clone(), event);
+            }
+        }
+    }
+
+    let mut disagreements = Vec::new();
+    let mut merged = ReviewedVideos::new();
+    for ((video_id, key), decisions) in &events {
+        let labels: BTreeMap<String, ReviewLabel> = decisions
+            .iter()
+            .map(|(annotator, event)| (annotator.clone(), event.label))
+            .collect();
+        let first = decisions.values().next().unwrap();
+        if labels.values().any(|label| *label != first.label) {
+            disagreements.push(Disagreement {
+                video_id: video_id.clone(),
+                start_frame: first.start_frame,
+                end_frame: first.end_frame,
+                labels: labels.clone(),
+            });
+        }
+
+        let majority = decisions.values().find(|event| {
+            let votes = labels
+                .values()
+                .filter(|label| **label == event.label)
+                .count();
+            votes * 2 > labels.len()
+        });
+        if let Some(event) = majority {
+            merged
+                .entry(video_id.clone())
+                .or_default()
+                .insert(key.clone(), (*event).clone());
+        }
+    }
+
+    ReviewReport {
+        info: ExportInfo {
+            version: "review_report_1.0".to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            incomplete: false,
+        },
+        annotators,
+        agreement: agreements,
+        disagreements,
+        merged,
+    }
+}
+
+/// Writes `report` to [`REVIEW_REPORT_FILE`] in `output_dir`.
+pub fn write_review_report(output_dir: &Path, report: &ReviewReport) -> io::Result<()> {
+    fs::write(
+        output_dir.join(REVIEW_REPORT_FILE),
+        serde_json::to_string_pretty(report)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::review_log::event_key;
+
+    fn log(labels: &[(u32, ReviewLabel)]) -> ReviewedVideos {
+        let events = labels
+            .iter()
+            .map(|&(start_frame, label)| {
+                let event = ReviewedEvent {
+                    video_id: "SNGS-001".to_string(),
+                    start_frame,
+                    end_frame: start_frame + 50,
+                    label,
+                    verdict: None,
+                    corrected_start_frame: None,
+                    corrected_end_frame: None,
+                    tags: Vec::new(),
+                    note: None,
+                };
+                (event_key(start_frame, start_frame + 50), event)
+            })
+            .collect();
+        ReviewedVideos::from([("SNGS-001".to_string(), events)])
+    }
+
+    #[test]
+    fn test_cohens_kappa() {
+        assert_eq!(cohens_kappa::<bool>(&[]), None);
+        assert_eq!(cohens_kappa(&[(true, true), (true, true)]), Some(1.0));
+        // Half of the labels agree, as many as expected by chance
+        let kappa = cohens_kappa(&[(true, true), (false, false), (true, false), (false, true)]);
+        assert_eq!(kappa, Some(0.0));
+        let kappa = cohens_kappa(&[(true, true), (false, false), (true, true), (false, true)]);
+        assert!((kappa.unwrap() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_review_report() {
+        use ReviewLabel::*;
+        let logs = BTreeMap::from([
+            (
+                "alice".to_string(),
+                log(&[(100, Dribble), (200, Tackle), (300, Other)]),
+            ),
+            (
+                "bob".to_string(),
+                log(&[(100, Dribble), (200, Dribble), (300, Other)]),
+            ),
+            ("carol".to_string(), log(&[(100, Dribble), (200, Other)])),
+        ]);
+        let report = create_review_report(&logs);
+
+        assert_eq!(report.annotators, vec!["alice", "bob", "carol"]);
+        let alice_bob = &report.agreement[0];
+        assert_eq!(alice_bob.num_events, 3);
+        assert_eq!(alice_bob.verdict_agreement, 1.0);
+        assert!((alice_bob.class_agreement - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(report.agreement[1].num_events, 2);
+
+        // Every annotator labeled frames 200 differently, so there is no majority
+        assert_eq!(report.disagreements.len(), 1);
+        assert_eq!(report.disagreements[0].start_frame, 200);
+        let merged = &report.merged["SNGS-001"];
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged["100-150"].label, Dribble);
+    }
+}
@@ -0,0 +1,223 @@
+//! `run_manifest.json`: everything needed to tell how the outputs of a detection run were
+//! produced, i.e. the effective config, the build, the host and what happened to every video.
+
+use crate::config::{Config, REDACTED};
+use crate::data::models::ExportInfo;
+use crate::run_statistics::RunStatistics;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the manifest in the output directory
+pub const RUN_MANIFEST_FILE: &str = "run_manifest.json";
+
+/// What happened to a video in a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoStatus {
+    /// All frames were processed
+    Processed,
+    /// Not processed in this run, e.g. because an earlier run finished it
+    Skipped,
+    /// Not or only partly processed because something went wrong
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoRecord {
+    pub video_id: String,
+    pub subset: String,
+    pub status: VideoStatus,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl VideoRecord {
+    pub fn new(video_id: &str, subset: &str, status: VideoStatus, reason: Option<&str>) -> Self {
+        Self {
+            video_id: video_id.to_string(),
+            subset: subset.to_string(),
+            status,
+            reason: reason.map(str::to_string),
+        }
+    }
+}
+
+/// The binary that produced the outputs. The commit is set by `build.rs` if the crate was
+/// built from a git checkout.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub crate_version: String,
+    pub git_commit: Option<String>,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: option_env!("GIT_COMMIT").map(str::to_string),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HostInfo {
+    pub hostname: Option<String>,
+    pub os: String,
+    pub arch: String,
+    pub num_cpus: usize,
+}
+
+impl HostInfo {
+    pub fn current() -> Self {
+        let hostname = env::var("HOSTNAME").ok().or_else(|| {
+            fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|name| name.trim().to_string())
+        });
+        Self {
+            hostname: hostname.filter(|name| !name.is_empty()),
+            os: env::consts::OS.to_string(),
+            arch: env::consts::ARCH.to_string(),
+            num_cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub info: ExportInfo,
+    pub build: BuildInfo,
+    pub host: HostInfo,
+    /// The command line the run was started with, with the `--webhook` URLs redacted
+    pub args: Vec<String>,
+    pub started_at: String,
+    pub finished_at: String,
+    pub subsets: Vec<String>,
+    /// The effective config, with the profile, environment and command line overrides applied
+    /// and the secrets redacted, see [`Config::redacted`]
+    pub config: Config,
+    pub videos: Vec<VideoRecord>,
+    /// Statistics of the detected events, see [`RunStatistics`]
+    #[serde(default)]
+    pub statistics: Option<RunStatistics>,
+}
+
+impl RunManifest {
+    /// A manifest of a run of `subsets` that started at `started_at` and finishes now.
+    pub fn new(
+        config: &Config,
+        subsets: &[String],
+        started_at: DateTime<Utc>,
+        videos: Vec<VideoRecord>,
+    ) -> Self {
+        let finished_at = Utc::now().to_rfc3339();
+        Self {
+            info: ExportInfo {
+                version: "run_manifest_1.0".to_string(),
+                generated_at: finished_at.clone(),
+                incomplete: false,
+            },
+            build: BuildInfo::current(),
+            host: HostInfo::current(),
+            args: redact_args(env::args()),
+            started_at: started_at.to_rfc3339(),
+            finished_at,
+            subsets: subsets.to_vec(),
+            config: config.redacted(),
+            videos,
+            statistics: None,
+        }
+    }
+
+    pub fn with_statistics(mut self, statistics: RunStatistics) -> Self {
+        self.statistics = Some(statistics);
+        self
+    }
+
+    /// Number of videos with `status`.
+    pub fn count(&self, status: VideoStatus) -> usize {
+        self.videos.iter().filter(|v| v.status == status).count()
+    }
+
+    pub fn write(&self, output_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(output_dir)?;
+        let json_data = serde_json::to_string_pretty(self)?;
+        fs::write(output_dir.join(RUN_MANIFEST_FILE), json_data)
+    }
+}
+
+/// Replaces the values of `--webhook`, which are secret URLs.
+fn redact_args(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut redact_next = false;
+    args.map(|arg| {
+        if std::mem::take(&mut redact_next) {
+            return REDACTED.to_string();
+        }
+        match arg.strip_prefix("--webhook") {
+            Some("") => {
+                redact_next = true;
+                arg
+            }
+            Some(value) if value.starts_with('=') => format!("--webhook={}", REDACTED),
+            _ => arg,
+        }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{NotificationsConfig, StorageConfig};
+
+    #[test]
+    fn test_run_manifest_round_trip() {
+        let videos = vec![
+            VideoRecord::new("SNGS-001", "train", VideoStatus::Processed, None),
+            VideoRecord::new("SNGS-002", "train", VideoStatus::Skipped, Some("resumed")),
+        ];
+        let config = Config::default();
+        let manifest = RunManifest::new(&config, &config.data.subsets, Utc::now(), videos);
+        assert_eq!(manifest.count(VideoStatus::Processed), 1);
+        assert_eq!(manifest.count(VideoStatus::Error), 0);
+        assert_eq!(manifest.build.crate_version, env!("CARGO_PKG_VERSION"));
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert!(json.contains("\"status\":\"skipped\""));
+        let parsed: RunManifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.videos, manifest.videos);
+        assert_eq!(parsed.subsets, config.data.subsets);
+    }
+
+    #[test]
+    fn test_run_manifest_redacts_secrets() {
+        let mut config = Config::default();
+        config.storage = Some(StorageConfig {
+            http_headers: [("Authorization".to_string(), "Bearer abc".to_string())].into(),
+            ..Default::default()
+        });
+        config.notifications = Some(NotificationsConfig {
+            webhooks: vec!["https://hooks.slack.com/services/T0/B0/secret".to_string()],
+            ..Default::default()
+        });
+        let manifest = RunManifest::new(&config, &[], Utc::now(), Vec::new());
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert!(!json.contains("Bearer") && !json.contains("secret"));
+        assert!(json.contains("Authorization"));
+
+        let args = [
+            "detect",
+            "--webhook",
+            "https://a/secret",
+            "--webhook=https://b/secret",
+        ];
+        assert_eq!(
+            redact_args(args.into_iter().map(str::to_string)),
+            ["detect", "--webhook", REDACTED, "--webhook=<redacted>"]
+        );
+    }
+}
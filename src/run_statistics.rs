@@ -0,0 +1,227 @@
+//! Statistics of the events of a run for the end-of-run summary and `run_manifest.json`: how
+//! long the events last, how far apart they are, how they are spread over the videos, and the
+//! share of contested events and of tackles.
+
+use crate::config::DEFAULT_FRAME_RATE;
+use crate::data::models::{DribbleLabel, VideoDribbleEvents};
+use serde::{Deserialize, Serialize};
+
+/// Lower edges of the bins of the event durations in seconds; the last bin is open
+const DURATION_BINS: &[f64] = &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 7.5, 10.0];
+/// Lower edges of the bins of the gaps between events in seconds
+const GAP_BINS: &[f64] = &[0.0, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0];
+/// Lower edges of the bins of the number of events per video
+const EVENTS_PER_VIDEO_BINS: &[f64] = &[0.0, 1.0, 2.0, 3.0, 5.0, 10.0, 20.0];
+
+/// Values from `start` up to `end`, which is `None` for the last bin.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBin {
+    pub start: f64,
+    pub end: Option<f64>,
+    pub count: usize,
+}
+
+impl HistogramBin {
+    /// The range of the bin, e.g. `2-3` or `10+`.
+    pub fn label(&self) -> String {
+        match self.end {
+            Some(end) => format!("{}-{}", self.start, end),
+            None => format!("{}+", self.start),
+        }
+    }
+}
+
+/// Summary of a set of values. All values are 0 and the histogram is empty without values.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub histogram: Vec<HistogramBin>,
+}
+
+impl Distribution {
+    /// The distribution of `values`, with a bin from every edge in `bins` to the next.
+    pub fn new(values: &[f64], bins: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        };
+        let histogram = bins
+            .iter()
+            .enumerate()
+            .map(|(i, &start)| {
+                let end = bins.get(i + 1).copied();
+                let count = values
+                    .iter()
+                    .filter(|&&v| v >= start && end.map_or(true, |end| v < end))
+                    .count();
+                HistogramBin { start, end, count }
+            })
+            .collect();
+        Self {
+            count: values.len(),
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            median,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            histogram,
+        }
+    }
+}
+
+/// Statistics of the events of the videos of a run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStatistics {
+    pub num_videos: usize,
+    pub num_dribbles: usize,
+    pub num_tackles: usize,
+    pub num_contested: usize,
+    /// Share of the events that were contested
+    pub contested_fraction: f64,
+    /// Tackles per dribble, `None` without dribbles
+    pub tackles_per_dribble: Option<f64>,
+    /// Durations of the events in seconds
+    pub durations: Distribution,
+    /// Seconds from the end of an event to the start of the next one in the same video
+    pub gaps: Distribution,
+    /// Number of events of every video, including videos without events
+    pub events_per_video: Distribution,
+}
+
+/// Start and end of `label` in seconds, from its timing if the video has a frame rate.
+fn seconds(label: &DribbleLabel) -> (f64, f64) {
+    let end_frame = label.end_frame.unwrap_or(label.start_frame);
+    match (label.start_seconds, label.end_seconds) {
+        (Some(start), Some(end)) => (start, end),
+        _ => (
+            f64::from(label.start_frame) / DEFAULT_FRAME_RATE,
+            f64::from(end_frame) / DEFAULT_FRAME_RATE,
+        ),
+    }
+}
+
+impl RunStatistics {
+    pub fn new(videos: &[VideoDribbleEvents]) -> Self {
+        let events: Vec<&DribbleLabel> = videos.iter().flat_map(|v| &v.dribble_events).collect();
+        let num_tackles = events.iter().filter(|e| e.detected_tackle).count();
+        let num_dribbles = events.len() - num_tackles;
+        let num_contested = events.iter().filter(|e| e.ever_contested).count();
+
+        let durations: Vec<f64> = events
+            .iter()
+            .map(|event| {
+                let (start, end) = seconds(event);
+                (end - start).max(0.0)
+            })
+            .collect();
+        let gaps: Vec<f64> = videos
+            .iter()
+            .flat_map(|video| {
+                let mut times: Vec<(f64, f64)> = video.dribble_events.iter().map(seconds).collect();
+                times.sort_by(|a, b| a.0.total_cmp(&b.0));
+                times
+                    .windows(2)
+                    .map(|pair| (pair[1].0 - pair[0].1).max(0.0))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let events_per_video: Vec<f64> = videos
+            .iter()
+            .map(|video| video.dribble_events.len() as f64)
+            .collect();
+
+        Self {
+            num_videos: videos.len(),
+            num_dribbles,
+            num_tackles,
+            num_contested,
+            contested_fraction: if events.is_empty() {
+                0.0
+            } else {
+                num_contested as f64 / events.len() as f64
+            },
+            tackles_per_dribble: (num_dribbles > 0)
+                .then(|| num_tackles as f64 / num_dribbles as f64),
+            durations: Distribution::new(&durations, DURATION_BINS),
+            gaps: Distribution::new(&gaps, GAP_BINS),
+            events_per_video: Distribution::new(&events_per_video, EVENTS_PER_VIDEO_BINS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_statistics() {
+        let video = |video_id: &str, events: &[(u32, u32, bool, bool)]| {
+            let events: Vec<String> = events
+                .iter()
+                .map(|&(start, end, tackle, contested)| {
+                    format!(
+                        r#"{{"finished": true, "detected_dribble": {}, "detected_tackle": {},
+                            "ever_contested": {}, "possession_holder": 7,
+                            "start_frame": {}, "end_frame": {}}}"#,
+                        !tackle, tackle, contested, start, end
+                    )
+                })
+                .collect();
+            let json = format!(
+                r#"{{"video_id": "{}", "dribble_events": [{}]}}"#,
+                video_id,
+                events.join(",")
+            );
+            serde_json::from_str::<VideoDribbleEvents>(&json).unwrap()
+        };
+        let videos = [
+            video(
+                "SNGS-001",
+                &[
+                    (100, 150, false, true),
+                    (0, 25, false, false),
+                    (300, 400, true, true),
+                ],
+            ),
+            video("SNGS-002", &[(10, 35, false, false)]),
+            video("SNGS-003", &[]),
+        ];
+
+        let stats = RunStatistics::new(&videos);
+        assert_eq!(
+            (stats.num_dribbles, stats.num_tackles, stats.num_contested),
+            (3, 1, 2)
+        );
+        assert_eq!(stats.contested_fraction, 0.5);
+        assert_eq!(stats.tackles_per_dribble, Some(1.0 / 3.0));
+
+        // 2, 1, 4 and 1 seconds at the default frame rate
+        assert_eq!(stats.durations.count, 4);
+        assert_eq!(stats.durations.median, 1.5);
+        assert_eq!((stats.durations.min, stats.durations.max), (1.0, 4.0));
+        assert_eq!(stats.durations.histogram[1].count, 2);
+        assert_eq!(stats.durations.histogram[4].label(), "4-5");
+        assert_eq!(stats.durations.histogram[4].count, 1);
+
+        // The events of SNGS-001 are sorted by start before the gaps are taken
+        assert_eq!(stats.gaps.count, 2);
+        assert_eq!((stats.gaps.min, stats.gaps.max), (3.0, 6.0));
+
+        assert_eq!(stats.events_per_video.count, 3);
+        assert_eq!(stats.events_per_video.mean, 4.0 / 3.0);
+        let last = stats.events_per_video.histogram.last().unwrap();
+        assert_eq!((last.label(), last.count), ("20+".to_string(), 0));
+
+        assert_eq!(RunStatistics::new(&[]).tackles_per_dribble, None);
+    }
+}
@@ -0,0 +1,208 @@
+//! `training_labels.json`: the events accepted in review mode, with the classes and boundaries
+//! set by the reviewer, in the format of `dribble_events.json`, so they can be used as training
+//! labels without editing the events by hand. Optionally also written as one class per frame.
+
+use crate::data::models::{ClipTiming, DribbleEventsExport, ExportInfo, ReviewLabel};
+use crate::review_log::{event_key, ReviewedVideos};
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the training labels in the output directory
+pub const TRAINING_LABELS_FILE: &str = "training_labels.json";
+/// Name of the per-frame classes in the output directory
+pub const FRAME_LABELS_FILE: &str = "training_labels_frames.csv";
+
+/// The reviewed videos of `events` with the events that were accepted, with the class and the
+/// corrected boundaries of their decision. Rejected and unreviewed events are left out, as are
+/// shielding events, which are not reviewed. Videos with a `timing` get the timestamps of the
+/// corrected boundaries.
+pub fn training_labels(
+    events: &DribbleEventsExport,
+    reviewed: &ReviewedVideos,
+    timings: &HashMap<String, ClipTiming>,
+) -> DribbleEventsExport {
+    let videos = events
+        .videos
+        .iter()
+        .filter_map(|video| {
+            let decisions = reviewed.get(&video.video_id)?;
+            let timing = timings.get(&video.video_id);
+            let dribble_events = video
+                .dribble_events
+                .iter()
+                .filter_map(|event| {
+                    let end_frame = event.end_frame.unwrap_or(event.start_frame);
+                    let decision = decisions.get(&event_key(event.start_frame, end_frame))?;
+                    if decision.label == ReviewLabel::Other {
+                        return None;
+                    }
+                    let start_frame = decision.corrected_start_frame.unwrap_or(event.start_frame);
+                    let end_frame = decision.corrected_end_frame.unwrap_or(end_frame);
+                    let mut label = event.clone();
+                    label.detected_dribble = decision.label == ReviewLabel::Dribble;
+                    label.detected_tackle = decision.label == ReviewLabel::Tackle;
+                    label.start_frame = start_frame;
+                    label.end_frame = Some(end_frame);
+                    label.confidence = None;
+                    if let Some(timing) = timing {
+                        label.start_seconds = timing.seconds(start_frame);
+                        label.end_seconds = timing.seconds(end_frame);
+                        label.start_game_time = timing.game_time(start_frame);
+                        label.end_game_time = timing.game_time(end_frame);
+                    }
+                    Some(label)
+                })
+                .collect();
+            let mut video = video.clone();
+            video.dribble_events = dribble_events;
+            video.shielding_events = Vec::new();
+            Some(video)
+        })
+        .collect();
+
+    DribbleEventsExport {
+        info: ExportInfo {
+            version: "dribble_events_1.0".to_string(),
+            generated_at: Utc::now().to_rfc3339(),
+            incomplete: false,
+        },
+        videos,
+    }
+}
+
+/// The class of every frame of the events in `labels`, as `video_id,frame,class` lines. Frames
+/// that are not listed are in no event.
+pub fn frame_labels_csv(labels: &DribbleEventsExport) -> String {
+    let mut classes: BTreeMap<(&str, u32), &str> = BTreeMap::new();
+    for video in &labels.videos {
+        for event in &video.dribble_events {
+            let class = if event.detected_tackle {
+                "tackle"
+            } else {
+                "dribble"
+            };
+            let end_frame = event.end_frame.unwrap_or(event.start_frame);
+            for frame in event.start_frame..=end_frame {
+                classes.insert((&video.video_id, frame), class);
+            }
+        }
+    }
+
+    let mut csv = String::from("video_id,frame,class\n");
+    for ((video_id, frame), class) in classes {
+        let _ = writeln!(csv, "{},{},{}", video_id, frame, class);
+    }
+    csv
+}
+
+/// Writes `labels` to [`TRAINING_LABELS_FILE`] in `output_dir`, and the classes of their
+/// frames to [`FRAME_LABELS_FILE`] if `frame_labels` is set.
+pub fn write_training_labels(
+    output_dir: &Path,
+    labels: &DribbleEventsExport,
+    frame_labels: bool,
+) -> io::Result<()> {
+    fs::write(
+        output_dir.join(TRAINING_LABELS_FILE),
+        serde_json::to_string_pretty(labels)?,
+    )?;
+    if frame_labels {
+        fs::write(output_dir.join(FRAME_LABELS_FILE), frame_labels_csv(labels))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::ReviewedEvent;
+
+    const EVENTS_JSON: &str = r#"{
+        "info": { "version": "1.0", "generated_at": "2025-03-14T09:26:53Z" },
+        "videos": [{
+            "video_id": "SNGS-001",
+            "dribble_events": [
+                {
+                    "finished": true,
+                    "detected_dribble": true,
+                    "detected_tackle": false,
+                    "ever_contested": true,
+                    "possession_holder": 7,
+                    "start_frame": 10,
+                    "end_frame": 14
+                },
+                {
+                    "finished": true,
+                    "detected_dribble": true,
+                    "detected_tackle": false,
+                    "ever_contested": false,
+                    "possession_holder": 9,
+                    "start_frame": 40,
+                    "end_frame": 60
+                },
+                {
+                    "finished": true,
+                    "detected_dribble": false,
+                    "detected_tackle": true,
+                    "ever_contested": true,
+                    "possession_holder": 4,
+                    "start_frame": 80,
+                    "end_frame": 95
+                }
+            ]
+        }]
+    }"#;
+
+    fn decision(start_frame: u32, end_frame: u32, label: ReviewLabel) -> ReviewedEvent {
+        ReviewedEvent {
+            video_id: "SNGS-001".to_string(),
+            start_frame,
+            end_frame,
+            label,
+            verdict: None,
+            corrected_start_frame: None,
+            corrected_end_frame: None,
+            tags: Vec::new(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_training_labels() {
+        let events: DribbleEventsExport = serde_json::from_str(EVENTS_JSON).unwrap();
+        // The first event is reclassified with new boundaries, the second rejected and the
+        // third not reviewed
+        let mut tackle = decision(10, 14, ReviewLabel::Tackle);
+        tackle.corrected_start_frame = Some(11);
+        tackle.corrected_end_frame = Some(13);
+        let decisions = BTreeMap::from([
+            ("10-14".to_string(), tackle),
+            ("40-60".to_string(), decision(40, 60, ReviewLabel::Other)),
+        ]);
+        let reviewed = ReviewedVideos::from([("SNGS-001".to_string(), decisions)]);
+        let timing = ClipTiming {
+            first_frame: 1,
+            frame_rate: Some(25.0),
+            game_time_start: None,
+            clip_start: None,
+        };
+        let timings = HashMap::from([("SNGS-001".to_string(), timing)]);
+
+        let labels = training_labels(&events, &reviewed, &timings);
+        let events = &labels.videos[0].dribble_events;
+        assert_eq!(events.len(), 1);
+        assert!(events[0].detected_tackle && !events[0].detected_dribble);
+        assert_eq!((events[0].start_frame, events[0].end_frame), (11, Some(13)));
+        assert_eq!(events[0].start_seconds, Some(0.4));
+        assert_eq!(events[0].possession_holder, 7);
+
+        assert_eq!(
+            frame_labels_csv(&labels),
+            "video_id,frame,class\nSNGS-001,11,tackle\nSNGS-001,12,tackle\nSNGS-001,13,tackle\n"
+        );
+    }
+}
@@ -1,12 +1,11 @@
 use crate::data::models::{Annotation, Attribute};
-use opencv::core::Scalar;
 use rand::RngCore;
 use std::collections::HashMap;
 
-/// Filters annotations based on team and category. Also handles if we ignore person_class or teams.
+/// Filters the annotations of a single frame based on team and category. Also handles if we
+/// ignore person_class or teams.
 pub fn filter_annotations(
-    image_id: &String,
-    annotations: Vec<Annotation>,
+    annotations: &[Annotation],
     categories: &HashMap<String, u32>,
     ignore_person_class: bool,
     ignore_teams: bool,
@@ -17,24 +16,21 @@ pub fn filter_annotations(
     let cat_player = categories.get("player");
 
     annotations
-        .into_iter()
-        .filter_map(|annotation| {
-            if annotation.image_id != *image_id {
-                return None;
-            }
-
+        .iter()
+        .map(|annotation| {
             // Destructure for readability
             let Annotation {
                 category_id,
                 attributes,
                 ..
             } = annotation;
+            let category_id = *category_id;
 
             // If ignoring teams, just assign a random placeholder team id
             let new_team = if ignore_teams {
                 Some(rand::rng().next_u32().to_string())
             } else {
-                attributes.clone().unwrap_or_default().team
+                attributes.as_ref().and_then(|a| a.team.clone())
             };
 
             // If ignoring person_class, map "goalkeeper"/"referee" => "player"
@@ -48,34 +44,19 @@ pub fn filter_annotations(
                 category_id
             };
 
-            Some(Annotation {
+            Annotation {
                 category_id: new_category_id,
                 attributes: Some(Attribute {
                     role: attributes.as_ref().and_then(|a| a.role.clone()),
                     jersey: attributes.as_ref().and_then(|a| a.jersey.clone()),
                     team: new_team,
                 }),
-                ..annotation
-            })
+                ..annotation.clone()
+            }
         })
         .collect()
 }
 
-pub fn get_annotation_color(annotation: &Annotation, categories: &HashMap<String, u32>) -> Scalar {
-    let team_id = &annotation.attributes.as_ref().unwrap().team;
-
-    if annotation.category_id == categories["ball"] {
-        return Scalar::new(237.0, 237.0, 237.0, 255.0); // Gray for ball
-    }
-
-    // Determine color based on team_id
-    match team_id.as_deref() {
-        Some("left") => Scalar::new(0.0, 0.0, 255.0, 255.0), // Red for team A
-        Some("right") => Scalar::new(255.0, 0.0, 0.0, 255.0), // Blue for team B
-        _ => Scalar::new(0.0, 255.0, 0.0, 255.0),            // Default: Green
-    }
-}
-
 /// Finds the closest annotation to the base annotation
 pub fn annotation_comparator(
     base_annotation: Annotation,
@@ -167,9 +148,46 @@ pub fn compute_average_player_bbox_height(
     }
 }
 
+/// Assumed height of a player in meters, to estimate the image scale from a bounding box
+const PLAYER_HEIGHT_METERS: f64 = 1.8;
+
+/// The player closest to the ball on the pitch, who holds the ball as far as the detector is
+/// concerned. Annotations without a pitch bounding box are ignored.
+pub fn find_possession_holder(annotations: &[Annotation], ball_id: u32) -> Option<&Annotation> {
+    let ball = annotations
+        .iter()
+        .find(|a| a.category_id == ball_id)
+        .and_then(|a| calculate_bbox_pitch_coordinates(a.clone(), true))?;
+    annotations
+        .iter()
+        .filter(|a| a.category_id != ball_id)
+        .filter_map(|a| {
+            let (x, y) = calculate_bbox_pitch_coordinates(a.clone(), true)?;
+            Some((a, (x - ball.0).powi(2) + (y - ball.1).powi(2)))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(a, _)| a)
+}
+
+/// Approximate number of image pixels per meter at the feet of a player. Uses the width of the
+/// bounding box in the image and on the pitch, or else its height and the average height of
+/// a player.
+pub fn pixels_per_meter(annotation: &Annotation) -> Option<f64> {
+    let bbox_image = annotation.bbox_image.as_ref()?;
+    let pitch_width = annotation
+        .bbox_pitch
+        .as_ref()
+        .map(|b| (b.x_bottom_right - b.x_bottom_left).hypot(b.y_bottom_right - b.y_bottom_left));
+    match pitch_width {
+        Some(width) if width > 0.1 => Some(bbox_image.w / width),
+        _ if bbox_image.h > 0.0 => Some(bbox_image.h / PLAYER_HEIGHT_METERS),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::data::models::BboxPitch;
+    use crate::data::models::{BboxImage, BboxPitch};
 
     use super::*;
 
@@ -210,6 +228,34 @@ mod tests {
         assert!(result); // sqrt((2-1)^2 + (2-1)^2) = sqrt(2) = 1.414 < 2.0
     }
 
+    #[test]
+    fn test_possession_holder_scale() {
+        let mut ball = create_annotation(0.0, 0.0, 0.2, 0.0);
+        ball.category_id = 4;
+        let mut far = create_annotation(5.0, 0.0, 5.5, 0.0);
+        far.category_id = 1;
+        let mut near = create_annotation(1.0, 0.0, 1.5, 0.0);
+        near.category_id = 1;
+        near.bbox_image = Some(BboxImage {
+            x: 100.0,
+            y: 100.0,
+            x_center: 110.0,
+            y_center: 140.0,
+            w: 20.0,
+            h: 80.0,
+        });
+        let annotations = vec![ball, far, near];
+
+        let holder = find_possession_holder(&annotations, 4).unwrap();
+        assert_eq!(holder.bbox_pitch.as_ref().unwrap().x_bottom_left, 1.0);
+        // 20 pixels for half a meter
+        assert_eq!(pixels_per_meter(holder), Some(40.0));
+
+        let mut without_pitch = holder.clone();
+        without_pitch.bbox_pitch = None;
+        assert_eq!(pixels_per_meter(&without_pitch), Some(80.0 / 1.8));
+    }
+
     #[test]
     fn test_annotation_comparator() {
         let base_annotation = create_annotation(0.0, 0.0, 2.0, 2.0); // center = (1, 1)
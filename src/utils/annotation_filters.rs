@@ -0,0 +1,134 @@
+use crate::config::AnnotationFilterConfig;
+use crate::data::models::Annotation;
+use std::collections::HashMap;
+
+/// Drops implausible annotations, such as one-frame tracks or bounding boxes far smaller or
+/// larger than a person, which otherwise show up as phantom players.
+#[derive(Clone, Debug, Default)]
+pub struct AnnotationFilter {
+    /// Filter settings per category id
+    filters: HashMap<u32, AnnotationFilterConfig>,
+    /// Number of annotations of each track id in the video
+    track_lengths: HashMap<u32, usize>,
+}
+
+impl AnnotationFilter {
+    /// Builds a filter from the per category name settings. Settings for categories that are
+    /// not in `category_map` are ignored.
+    pub fn new(
+        filters: &HashMap<String, AnnotationFilterConfig>,
+        category_map: &HashMap<String, u32>,
+        track_lengths: HashMap<u32, usize>,
+    ) -> Self {
+        Self {
+            filters: filters
+                .iter()
+                .filter_map(|(name, filter)| Some((*category_map.get(name)?, filter.clone())))
+                .collect(),
+            track_lengths,
+        }
+    }
+
+    /// Returns `false` if the annotation fails one of the filters of its category. The track
+    /// length is not checked for annotations without a track id, and the size is not checked
+    /// for annotations without an image bounding box.
+    pub fn is_plausible(&self, annotation: &Annotation) -> bool {
+        let Some(filter) = self.filters.get(&annotation.category_id) else {
+            return true;
+        };
+
+        if let (Some(min_length), Some(track_id)) = (filter.min_track_length, annotation.track_id)
+        {
+            let length = self.track_lengths.get(&track_id).copied().unwrap_or(0);
+            if length < min_length {
+                return false;
+            }
+        }
+
+        let Some(bbox) = &annotation.bbox_image else {
+            return true;
+        };
+        within(bbox.h, filter.min_bbox_height, filter.max_bbox_height)
+            && within(bbox.w, filter.min_bbox_width, filter.max_bbox_width)
+    }
+
+    pub fn apply(&self, annotations: Vec<Annotation>) -> Vec<Annotation> {
+        annotations
+            .into_iter()
+            .filter(|annotation| self.is_plausible(annotation))
+            .collect()
+    }
+}
+
+fn within(value: f64, min: Option<f64>, max: Option<f64>) -> bool {
+    min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+}
+
+/// Counts the annotations (i.e. frames) of each track id.
+pub fn count_track_lengths(annotations: &[Annotation]) -> HashMap<u32, usize> {
+    let mut track_lengths = HashMap::new();
+    for track_id in annotations.iter().filter_map(|a| a.track_id) {
+        *track_lengths.entry(track_id).or_default() += 1;
+    }
+    track_lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::BboxImage;
+
+    fn annotation(track_id: u32, category_id: u32, h: f64) -> Annotation {
+        Annotation {
+            track_id: Some(track_id),
+            category_id,
+            bbox_image: Some(BboxImage {
+                x: 0.0,
+                y: 0.0,
+                x_center: h / 4.0,
+                y_center: h / 2.0,
+                w: h / 2.0,
+                h,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_annotation_filter() {
+        let annotations = vec![
+            annotation(1, 1, 100.0),
+            annotation(1, 1, 100.0),
+            annotation(1, 1, 100.0),
+            // One-frame track
+            annotation(2, 1, 100.0),
+            // Too small to be a person
+            annotation(3, 1, 5.0),
+            annotation(3, 1, 5.0),
+            annotation(3, 1, 5.0),
+            // The ball has no filters
+            annotation(4, 4, 5.0),
+        ];
+
+        let filters = HashMap::from([(
+            "player".to_string(),
+            AnnotationFilterConfig {
+                min_track_length: Some(3),
+                min_bbox_height: Some(20.0),
+                max_bbox_height: Some(400.0),
+                ..Default::default()
+            },
+        )]);
+        let category_map =
+            HashMap::from([("player".to_string(), 1), ("ball".to_string(), 4)]);
+        let filter =
+            AnnotationFilter::new(&filters, &category_map, count_track_lengths(&annotations));
+
+        let track_ids: Vec<Option<u32>> = filter
+            .apply(annotations)
+            .iter()
+            .map(|a| a.track_id)
+            .collect();
+        assert_eq!(track_ids, vec![Some(1), Some(1), Some(1), Some(4)]);
+    }
+}
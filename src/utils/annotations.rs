@@ -1,5 +1,8 @@
-use super::annotation_calculations::get_annotation_color;
+use super::annotation_calculations::{find_possession_holder, pixels_per_meter};
+use super::colors::{scalar, scalar_with_alpha, ColorTheme};
 use super::draw_pitch_minimap::draw_pitch_markings_on_minimap;
+use super::draw_style::DrawStyle;
+use super::pitch_model::MinimapProjection;
 use crate::config::Config;
 use crate::data::models::{Annotation, BboxImage};
 use opencv::core::{self, Mat, Rect, Scalar};
@@ -7,6 +10,12 @@ use opencv::imgproc;
 use opencv::prelude::*;
 use std::collections::HashMap;
 
+/// Ratio of the height and width of a circle on the pitch in the camera view, which looks at
+/// the pitch at a shallow angle
+const GROUND_PLANE_SQUASH: f64 = 0.35;
+/// Size of the possession ring relative to the bounding box of the holder
+const POSSESSION_RING_SCALE: f64 = 1.2;
+
 pub fn draw_annotations(
     frame: &mut Mat,
     annotations: &[Annotation],
@@ -22,148 +31,325 @@ pub fn draw_annotations(
         .cloned()
         .collect();
 
+    draw_frame_overlay(
+        frame,
+        &annotations,
+        categories,
+        config,
+        config.visualization.scale_factor,
+        inner_rad,
+        outer_rad,
+    )?;
+    let minimap = draw_minimap(
+        &annotations,
+        categories,
+        config,
+        frame.typ(),
+        inner_rad,
+        outer_rad,
+    )?;
+
+    // Prepare extended frame with the minimap below the main frame
+    let minimap_height = config.visualization.minimap_height;
+    let minimap_width = config.visualization.minimap_width;
+    let extended_height = frame.rows() + minimap_height;
+    let extended_width = frame.cols().max(minimap_width);
+    let mut extended_frame = Mat::zeros(extended_height, extended_width, frame.typ())?.to_mat()?;
+    let roi_main = Rect::new(0, 0, frame.cols(), frame.rows());
+    let mut extended_roi_main = Mat::roi_mut(&mut extended_frame, roi_main)?;
+    frame.copy_to(&mut extended_roi_main)?;
+
+    let minimap_x_offset = (frame.cols() - minimap_width) / 2;
+    let roi_minimap = Rect::new(
+        minimap_x_offset,
+        frame.rows(),
+        minimap_width,
+        minimap_height,
+    );
+    let mut extended_roi_minimap = Mat::roi_mut(&mut extended_frame, roi_minimap)?;
+    minimap.copy_to(&mut extended_roi_minimap)?;
+    *frame = extended_frame;
+    Ok(())
+}
+
+/// Draws the camera-view overlay: bounding boxes with track ids, and the inner/outer radius
+/// circles around the ball, or with 2d coordinates the zones around the possession holder.
+/// `annotations` should already be filtered to a single image. `target` can be the frame
+/// itself or a transparent layer.
+pub fn draw_frame_overlay(
+    target: &mut Mat,
+    annotations: &[Annotation],
+    categories: &HashMap<String, u32>,
+    config: &Config,
+    scale_factor: f64,
+    inner_rad: f64,
+    outer_rad: f64,
+) -> opencv::Result<()> {
     let ball_id = categories.get("ball").unwrap_or(&4);
-    let scale_factor = config.visualization.scale_factor;
+    let theme = ColorTheme::from_config(config.visualization.colors.as_ref());
+    let style = DrawStyle::from_config(&config.visualization, target.rows());
 
     // Draw main 2D boxes
-    for annotation in &annotations {
+    for annotation in annotations {
         if let Some(bbox_image) = &annotation.bbox_image {
             let track_id = if annotation.category_id == *ball_id { None } else { annotation.track_id };
             draw_bbox_image(
-                frame,
+                target,
                 bbox_image,
                 scale_factor,
-                get_annotation_color(annotation, categories),
+                scalar(theme.annotation_color(annotation, categories)),
                 track_id,
+                &style,
+            )?;
+        }
+    }
+
+    if config.dribbling_detection.use_2d {
+        return draw_holder_zones(
+            target,
+            annotations,
+            *ball_id,
+            &theme,
+            &style,
+            scale_factor,
+            inner_rad,
+            outer_rad,
+        );
+    }
+
+    // Draw circles on the main image based on ball bounding box center
+    for annotation in annotations {
+        if annotation.category_id != *ball_id {
+            continue;
+        }
+        if let Some(bbox_image) = &annotation.bbox_image {
+            let cx = ((bbox_image.x + bbox_image.w / 2.0) * scale_factor) as i32;
+            let cy = ((bbox_image.y + bbox_image.h / 2.0) * scale_factor) as i32;
+
+            // Inner/outer circle sizes in pixels. Adjust as needed for your scale.
+            let inner_px = inner_rad as i32;
+            let outer_px = outer_rad as i32;
+
+            imgproc::circle(
+                target,
+                core::Point::new(cx, cy),
+                outer_px,
+                scalar_with_alpha(theme.outer_zone, 154.0),
+                style.thickness,
+                imgproc::LINE_AA,
+                0,
+            )?;
+            imgproc::circle(
+                target,
+                core::Point::new(cx, cy),
+                inner_px,
+                scalar(theme.inner_zone),
+                style.thickness,
+                imgproc::LINE_AA,
+                0,
             )?;
         }
     }
 
-    // Prepare extended frame and minimap
+    Ok(())
+}
+
+/// Draws the inner/outer zones, whose radii are in meters, around the feet of the possession
+/// holder. The scale of the holder's bounding box is used to convert the radii to pixels, and
+/// the circles are flattened into ellipses to roughly match the perspective of the camera.
+#[allow(clippy::too_many_arguments)]
+fn draw_holder_zones(
+    target: &mut Mat,
+    annotations: &[Annotation],
+    ball_id: u32,
+    theme: &ColorTheme,
+    style: &DrawStyle,
+    scale_factor: f64,
+    inner_rad: f64,
+    outer_rad: f64,
+) -> opencv::Result<()> {
+    let Some(holder) = find_possession_holder(annotations, ball_id) else {
+        return Ok(());
+    };
+    let (Some(bbox_image), Some(holder_scale)) = (&holder.bbox_image, pixels_per_meter(holder))
+    else {
+        return Ok(());
+    };
+
+    let center = core::Point::new(
+        ((bbox_image.x + bbox_image.w / 2.0) * scale_factor) as i32,
+        ((bbox_image.y + bbox_image.h) * scale_factor) as i32,
+    );
+    let zones = [
+        (outer_rad, scalar_with_alpha(theme.outer_zone, 154.0)),
+        (inner_rad, scalar(theme.inner_zone)),
+    ];
+    for (radius, color) in zones {
+        let width = radius * holder_scale * scale_factor;
+        imgproc::ellipse(
+            target,
+            center,
+            core::Size::new(width as i32, (width * GROUND_PLANE_SQUASH) as i32),
+            0.0,
+            0.0,
+            360.0,
+            color,
+            style.thickness,
+            imgproc::LINE_AA,
+            0,
+        )?;
+    }
+    Ok(())
+}
+
+/// Draws a ring around the bounding box of the possession holder, the player with track id
+/// `holder_id`, and a line from the feet of the holder to the ball. The color switches from
+/// the possession to the contested color of `theme` while possession is `contested`.
+/// `annotations` should already be filtered to a single image.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_possession_indicator(
+    target: &mut Mat,
+    annotations: &[Annotation],
+    categories: &HashMap<String, u32>,
+    holder_id: u32,
+    contested: bool,
+    theme: &ColorTheme,
+    style: &DrawStyle,
+    scale_factor: f64,
+) -> opencv::Result<()> {
+    let ball_id = categories.get("ball").unwrap_or(&4);
+    let Some(holder) = annotations
+        .iter()
+        .filter(|a| a.category_id != *ball_id && a.track_id == Some(holder_id))
+        .find_map(|a| a.bbox_image.as_ref())
+    else {
+        return Ok(());
+    };
+    let color = scalar(if contested {
+        theme.contested
+    } else {
+        theme.possession
+    });
+
+    let center = core::Point::new(
+        ((holder.x + holder.w / 2.0) * scale_factor) as i32,
+        ((holder.y + holder.h / 2.0) * scale_factor) as i32,
+    );
+    let axes = core::Size::new(
+        (holder.w / 2.0 * POSSESSION_RING_SCALE * scale_factor) as i32,
+        (holder.h / 2.0 * POSSESSION_RING_SCALE * scale_factor) as i32,
+    );
+    imgproc::ellipse(
+        target,
+        center,
+        axes,
+        0.0,
+        0.0,
+        360.0,
+        color,
+        style.thickness * 2,
+        imgproc::LINE_AA,
+        0,
+    )?;
+
+    let ball = annotations
+        .iter()
+        .filter(|a| a.category_id == *ball_id)
+        .find_map(|a| a.bbox_image.as_ref());
+    if let Some(ball) = ball {
+        let feet = core::Point::new(center.x, ((holder.y + holder.h) * scale_factor) as i32);
+        let ball_center = core::Point::new(
+            ((ball.x + ball.w / 2.0) * scale_factor) as i32,
+            ((ball.y + ball.h / 2.0) * scale_factor) as i32,
+        );
+        imgproc::line(
+            target,
+            feet,
+            ball_center,
+            color,
+            style.thickness * 2,
+            imgproc::LINE_AA,
+            0,
+        )?;
+    }
+    Ok(())
+}
+
+/// Draws the 2d pitch minimap with all annotated positions, and the inner/outer radius circles
+/// around the ball when using 2d coordinates. `annotations` should already be filtered to a
+/// single image.
+pub fn draw_minimap(
+    annotations: &[Annotation],
+    categories: &HashMap<String, u32>,
+    config: &Config,
+    typ: i32,
+    inner_rad: f64,
+    outer_rad: f64,
+) -> opencv::Result<Mat> {
+    let ball_id = categories.get("ball").unwrap_or(&4);
+    let theme = ColorTheme::from_config(config.visualization.colors.as_ref());
     let minimap_height = config.visualization.minimap_height;
     let minimap_width = config.visualization.minimap_width;
-    let extended_height = frame.rows() + minimap_height;
-    let extended_width = frame.cols().max(minimap_width);
-    let mut extended_frame = Mat::zeros(extended_height, extended_width, frame.typ())?.to_mat()?;
-    let roi_main = Rect::new(0, 0, frame.cols(), frame.rows());
-    let mut extended_roi_main = Mat::roi_mut(&mut extended_frame, roi_main)?;
-    frame.copy_to(&mut extended_roi_main)?;
 
-    let mut minimap = Mat::zeros(minimap_height, minimap_width, frame.typ())?.to_mat()?;
+    let mut minimap = Mat::zeros(minimap_height, minimap_width, typ)?.to_mat()?;
     imgproc::rectangle(
         &mut minimap,
         Rect::new(0, 0, minimap_width, minimap_height),
-        Scalar::new(69.0, 160.0, 40.0, 255.0),
+        scalar(theme.pitch),
         -1,
         imgproc::LINE_8,
         0,
     )?;
     draw_pitch_markings_on_minimap(&mut minimap, config)?;
+    let style = DrawStyle::from_config(&config.visualization, minimap_height);
+    let projection = MinimapProjection::new(
+        &config.visualization,
+        core::Size::new(minimap_width, minimap_height),
+    );
+
+    for annotation in annotations {
+        if annotation.category_id == *ball_id && config.dribbling_detection.use_2d {
+            // Draw circles in pitch space on the minimap
+            if let Some(bbox_pitch) = &annotation.bbox_pitch {
+                let center =
+                    projection.point(bbox_pitch.x_bottom_middle, bbox_pitch.y_bottom_middle);
 
-    for annotation in &annotations {
-        if annotation.category_id == *ball_id {
-            if config.dribbling_detection.use_2d {
-                // Draw circles in pitch space on the minimap
-                if let Some(bbox_pitch) = &annotation.bbox_pitch {
-                    let mx = ((bbox_pitch.x_bottom_middle - config.visualization.x_min)
-                        / (config.visualization.x_max - config.visualization.x_min)
-                        * minimap_width as f64) as i32;
-                    let my = ((bbox_pitch.y_bottom_middle - config.visualization.y_min)
-                        / (config.visualization.y_max - config.visualization.y_min)
-                        * minimap_height as f64) as i32;
-
-                    let rx_inner = (inner_rad
-                        / (config.visualization.x_max - config.visualization.x_min)
-                        * minimap_width as f64)
-                        .min(
-                            inner_rad / (config.visualization.y_max - config.visualization.y_min)
-                                * minimap_height as f64,
-                        ) as i32;
-                    let rx_outer = (outer_rad
-                        / (config.visualization.x_max - config.visualization.x_min)
-                        * minimap_width as f64)
-                        .min(
-                            outer_rad / (config.visualization.y_max - config.visualization.y_min)
-                                * minimap_height as f64,
-                        ) as i32;
-
-                    imgproc::circle(
-                        &mut minimap,
-                        core::Point::new(mx, my),
-                        rx_outer,
-                        Scalar::new(0.0, 242.0, 254.0, 154.0),
-                        2,
-                        imgproc::LINE_8,
-                        0,
-                    )?;
-                    imgproc::circle(
-                        &mut minimap,
-                        core::Point::new(mx, my),
-                        rx_inner,
-                        Scalar::new(55.0, 166.0, 255.0, 0.0),
-                        1,
-                        imgproc::LINE_8,
-                        0,
-                    )?;
-                }
-            } else {
-                // Draw circles on the main image based on bounding box center
-                if let Some(bbox_image) = &annotation.bbox_image {
-                    let cx = ((bbox_image.x + bbox_image.w / 2.0) * scale_factor) as i32;
-                    let cy = ((bbox_image.y + bbox_image.h / 2.0) * scale_factor) as i32;
-
-                    // Inner/outer circle sizes in pixels. Adjust as needed for your scale.
-                    let inner_px = inner_rad as i32;
-                    let outer_px = outer_rad as i32;
-
-                    imgproc::circle(
-                        &mut extended_roi_main, // draw onto main frame region
-                        core::Point::new(cx, cy),
-                        outer_px,
-                        Scalar::new(0.0, 242.0, 254.0, 154.0),
-                        1,
-                        imgproc::LINE_8,
-                        0,
-                    )?;
-                    imgproc::circle(
-                        &mut extended_roi_main,
-                        core::Point::new(cx, cy),
-                        inner_px,
-                        Scalar::new(55.0, 166.0, 255.0, 0.0),
-                        1,
-                        imgproc::LINE_8,
-                        0,
-                    )?;
-                }
+                imgproc::circle(
+                    &mut minimap,
+                    center,
+                    projection.radius(outer_rad),
+                    scalar_with_alpha(theme.outer_zone, 154.0),
+                    style.thickness * 2,
+                    imgproc::LINE_AA,
+                    0,
+                )?;
+                imgproc::circle(
+                    &mut minimap,
+                    center,
+                    projection.radius(inner_rad),
+                    scalar(theme.inner_zone),
+                    style.thickness,
+                    imgproc::LINE_AA,
+                    0,
+                )?;
             }
         }
 
         // Draw basic positions for players/others on minimap if needed.
         if let Some(bbox_pitch) = &annotation.bbox_pitch {
-            let color = get_annotation_color(annotation, categories);
+            let color = scalar(theme.annotation_color(annotation, categories));
             let track_id = annotation.track_id;
             draw_pitch_point_on_minimap(
                 &mut minimap,
-                bbox_pitch.x_bottom_middle,
-                bbox_pitch.y_bottom_middle,
-                config,
+                projection.point(bbox_pitch.x_bottom_middle, bbox_pitch.y_bottom_middle),
                 color,
                 track_id,
+                &style,
             )?;
         }
     }
 
-    let minimap_x_offset = (frame.cols() - minimap_width) / 2;
-    let roi_minimap = Rect::new(
-        minimap_x_offset,
-        frame.rows(),
-        minimap_width,
-        minimap_height,
-    );
-    let mut extended_roi_minimap = Mat::roi_mut(&mut extended_frame, roi_minimap)?;
-    minimap.copy_to(&mut extended_roi_minimap)?;
-    *frame = extended_frame;
-    Ok(())
+    Ok(minimap)
 }
 
 // Basic bounding-box drawing in 2D
@@ -173,6 +359,7 @@ fn draw_bbox_image(
     scale: f64,
     color: Scalar,
     number: Option<u32>,
+    style: &DrawStyle,
 ) -> opencv::Result<()> {
     let x = (bb.x * scale) as i32;
     let y = (bb.y * scale) as i32;
@@ -181,19 +368,19 @@ fn draw_bbox_image(
 
     // Draw the bounding box
     let rect = Rect::new(x, y, w, h);
-    imgproc::rectangle(frame, rect, color, 1, imgproc::LINE_8, 0)?;
+    imgproc::rectangle(frame, rect, color, style.thickness, imgproc::LINE_AA, 0)?;
 
     if let Some(number) = number {
-        let text_point = core::Point::new(x + 5, y + 5);
+        let text_point = core::Point::new(x + style.text_offset, y + style.text_offset);
         imgproc::put_text(
             frame,
             &number.to_string(),
             text_point,
             imgproc::FONT_HERSHEY_SIMPLEX,
-            0.5,
+            style.font_scale,
             Scalar::new(0.0, 0.0, 0.0, 255.0),
-            1,
-            imgproc::LINE_8,
+            style.text_thickness,
+            imgproc::LINE_AA,
             false,
         )?;
     }
@@ -220,38 +407,25 @@ fn draw_bbox_image(
 
 fn draw_pitch_point_on_minimap(
     minimap: &mut Mat,
-    pitch_x: f64,
-    pitch_y: f64,
-    config: &Config,
+    point: core::Point,
     color: Scalar,
-    number: Option<u32>
+    number: Option<u32>,
+    style: &DrawStyle,
 ) -> opencv::Result<()> {
-    let minimap_height = config.visualization.minimap_height;
-    let minimap_width = config.visualization.minimap_width;
-    let y_min = config.visualization.y_min;
-    let y_max = config.visualization.y_max;
-    let x_min = config.visualization.x_min;
-    let x_max = config.visualization.x_max;
-
-    // Convert pitch coordinates to minimap coordinates
-    let mx = ((pitch_x - x_min) / (x_max - x_min) * minimap_width as f64) as i32;
-    let my = ((pitch_y - y_min) / (y_max - y_min) * minimap_height as f64) as i32;
-
     // Draw an opaque dot for the player's (or ball's) position
-    let point = core::Point::new(mx, my);
-    imgproc::circle(minimap, point, 5, color, -1, imgproc::LINE_8, 0)?;
+    imgproc::circle(minimap, point, style.dot_radius, color, -1, imgproc::LINE_AA, 0)?;
 
     if let Some(number) = number {
-        let text_point = core::Point::new(mx + 5, my + 5);
+        let text_point = core::Point::new(point.x + style.text_offset, point.y + style.text_offset);
         imgproc::put_text(
             minimap,
             &number.to_string(),
             text_point,
             imgproc::FONT_HERSHEY_SIMPLEX,
-            0.5,
+            style.font_scale,
             Scalar::new(0.0, 0.0, 0.0, 255.0),
-            1,
-            imgproc::LINE_8,
+            style.text_thickness,
+            imgproc::LINE_AA,
             false,
         )?;
     }
@@ -0,0 +1,287 @@
+use crate::config::{AuditConfig, DEFAULT_FRAME_RATE};
+use crate::data::models::VideoData;
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use crate::utils::frame_source::{frame_number, FrameSource};
+use opencv::core::{Point, Scalar, Size};
+use opencv::imgproc;
+use opencv::prelude::*;
+use opencv::videoio::VideoWriter;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Number of random positions tried per non-event clip before giving up on a video
+const MAX_PLACEMENT_ATTEMPTS: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditClipKind {
+    Dribble,
+    Tackle,
+    /// An interval that does not overlap any detected event
+    NonEvent,
+}
+
+/// A clip in the audit pack. `verdict` is left empty for QA to fill in.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditClip {
+    pub video_id: String,
+    pub kind: AuditClipKind,
+    pub start_frame: u32,
+    pub end_frame: u32,
+    pub file: Option<String>,
+    pub verdict: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditManifest {
+    pub generated_at: String,
+    pub seed: u64,
+    pub clips: Vec<AuditClip>,
+}
+
+/// Selects a random sample of detected events and non-event intervals, renders a clip of each
+/// and writes them with an `audit_pack.json` manifest to `<output_path>/audit_pack/`.
+pub fn create_audit_pack(
+    output_path: &Path,
+    videos: &[VideoData],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+    audit_config: &AuditConfig,
+) -> io::Result<PathBuf> {
+    let seed = audit_config.seed.unwrap_or_else(|| rand::rng().random());
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let frame_ranges: Vec<(String, u32, u32)> = videos
+        .iter()
+        .filter_map(|v| {
+            let first = frame_number(v.image_paths.first()?)?;
+            let last = frame_number(v.image_paths.last()?)?;
+            Some((v.key(), first, last))
+        })
+        .collect();
+    let mut clips = select_audit_clips(
+        &frame_ranges,
+        events,
+        audit_config.sample_size,
+        audit_config.clip_length,
+        &mut rng,
+    );
+
+    let pack_dir = output_path.join("audit_pack");
+    if pack_dir.exists() {
+        fs::remove_dir_all(&pack_dir)?;
+    }
+    fs::create_dir_all(&pack_dir)?;
+
+    for clip in clips.iter_mut() {
+        let Some(video_data) = videos.iter().find(|v| v.key() == clip.video_id) else {
+            continue;
+        };
+        let file_name = format!(
+            "{:?}_{}_{}-{}.avi",
+            clip.kind,
+            video_data.name(),
+            clip.start_frame,
+            clip.end_frame
+        )
+        .to_lowercase();
+
+        match render_clip(&pack_dir.join(&file_name), video_data, clip) {
+            Ok(()) => clip.file = Some(file_name),
+            Err(e) => eprintln!("Error rendering audit clip {}: {}", file_name, e),
+        }
+    }
+
+    let manifest = AuditManifest {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        seed,
+        clips,
+    };
+    let manifest_file = File::create(pack_dir.join("audit_pack.json"))?;
+    serde_json::to_writer_pretty(manifest_file, &manifest)?;
+
+    println!(
+        "Wrote audit pack with {} clips (seed {}) to {}",
+        manifest.clips.len(),
+        seed,
+        pack_dir.display()
+    );
+    Ok(pack_dir)
+}
+
+/// Picks up to `sample_size` detected events and `sample_size` intervals of `clip_length`
+/// frames that do not overlap any event. `frame_ranges` holds the first and last frame number
+/// of each video.
+pub fn select_audit_clips<R: Rng>(
+    frame_ranges: &[(String, u32, u32)],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+    sample_size: usize,
+    clip_length: u32,
+    rng: &mut R,
+) -> Vec<AuditClip> {
+    let clip_length = clip_length.max(1);
+
+    // Sorted first, so the same seed always picks the same clips
+    let mut all_events: Vec<(&String, &DribbleEvent)> = events
+        .iter()
+        .flat_map(|(video_id, events)| events.iter().map(move |e| (video_id, e)))
+        .collect();
+    all_events.sort_by(|a, b| (a.0, a.1.start_frame).cmp(&(b.0, b.1.start_frame)));
+    all_events.shuffle(rng);
+
+    let mut clips: Vec<AuditClip> = all_events
+        .into_iter()
+        .take(sample_size)
+        .map(|(video_id, event)| AuditClip {
+            video_id: video_id.clone(),
+            kind: if event.detected_tackle {
+                AuditClipKind::Tackle
+            } else {
+                AuditClipKind::Dribble
+            },
+            start_frame: event.start_frame,
+            end_frame: event.end_frame.unwrap_or(event.start_frame),
+            file: None,
+            verdict: None,
+        })
+        .collect();
+
+    let candidates: Vec<&(String, u32, u32)> = frame_ranges
+        .iter()
+        .filter(|(_, first, last)| last.saturating_sub(*first) + 1 >= clip_length)
+        .collect();
+    let mut non_events: Vec<AuditClip> = Vec::new();
+    if candidates.is_empty() {
+        return clips;
+    }
+
+    for _ in 0..sample_size * MAX_PLACEMENT_ATTEMPTS {
+        if non_events.len() == sample_size {
+            break;
+        }
+        let (video_id, first, last) = candidates[rng.random_range(0..candidates.len())];
+        let start = rng.random_range(*first..=last + 1 - clip_length);
+        let end = start + clip_length - 1;
+
+        let overlaps = |s: u32, e: u32| start <= e && s <= end;
+        let overlaps_event = events.get(video_id).is_some_and(|events| {
+            events
+                .iter()
+                .any(|ev| overlaps(ev.start_frame, ev.end_frame.unwrap_or(ev.start_frame)))
+        });
+        let overlaps_clip = non_events
+            .iter()
+            .any(|c| &c.video_id == video_id && overlaps(c.start_frame, c.end_frame));
+        if overlaps_event || overlaps_clip {
+            continue;
+        }
+
+        non_events.push(AuditClip {
+            video_id: video_id.clone(),
+            kind: AuditClipKind::NonEvent,
+            start_frame: start,
+            end_frame: end,
+            file: None,
+            verdict: None,
+        });
+    }
+
+    clips.extend(non_events);
+    clips
+}
+
+/// Writes the frames of a clip to a video, labeled with the clip kind and frame number.
+fn render_clip(path: &Path, video_data: &VideoData, clip: &AuditClip) -> opencv::Result<()> {
+    let mut frame_source = FrameSource::new(video_data)?;
+    let mut writer: Option<VideoWriter> = None;
+    let fps = match video_data.labels.info.frame_rate {
+        fps if fps > 0.0 => fps as f64,
+        _ => DEFAULT_FRAME_RATE,
+    };
+
+    for image_path in &video_data.image_paths {
+        let Some(frame_num) = frame_number(image_path) else {
+            continue;
+        };
+        if frame_num < clip.start_frame || frame_num > clip.end_frame {
+            continue;
+        }
+
+        let mut frame = frame_source.read(image_path)?;
+        if frame.empty() {
+            continue;
+        }
+
+        imgproc::put_text(
+            &mut frame,
+            &format!("{:?} {} frame {}", clip.kind, clip.video_id, frame_num),
+            Point::new(20, 40),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            1.0,
+            Scalar::new(0.0, 255.0, 255.0, 255.0),
+            2,
+            imgproc::LINE_8,
+            false,
+        )?;
+
+        if writer.is_none() {
+            writer = Some(VideoWriter::new(
+                path.to_str().unwrap_or_default(),
+                VideoWriter::fourcc('M', 'J', 'P', 'G')?,
+                fps,
+                Size::new(frame.cols(), frame.rows()),
+                true,
+            )?);
+        }
+        if let Some(writer) = writer.as_mut() {
+            writer.write(&frame)?;
+        }
+    }
+
+    if let Some(mut writer) = writer {
+        writer.release()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_audit_clips() {
+        let mut event = DribbleEvent::new(1, 100, "a".to_string());
+        event.end_frame = Some(200);
+        let events = HashMap::from([("a".to_string(), vec![event])]);
+        let frame_ranges = vec![("a".to_string(), 1, 300), ("b".to_string(), 1, 20)];
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let clips = select_audit_clips(&frame_ranges, &events, 3, 50, &mut rng);
+
+        assert_eq!(clips[0].kind, AuditClipKind::Dribble);
+        assert_eq!((clips[0].start_frame, clips[0].end_frame), (100, 200));
+
+        // Only video "a" is long enough, and non-event clips never overlap the event
+        let non_events: Vec<&AuditClip> = clips
+            .iter()
+            .filter(|c| c.kind == AuditClipKind::NonEvent)
+            .collect();
+        assert!(!non_events.is_empty());
+        for clip in non_events {
+            assert_eq!(clip.video_id, "a");
+            assert_eq!(clip.end_frame - clip.start_frame + 1, 50);
+            assert!(clip.end_frame < 100 || clip.start_frame > 200);
+        }
+
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(
+            select_audit_clips(&frame_ranges, &events, 3, 50, &mut rng),
+            clips
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! The list of the clips of a video in review mode, drawn next to the camera view. Clicking a
+//! clip opens it, so reviewers do not have to step through every clip before it.
+
+use crate::data::models::ReviewLabel;
+use opencv::core::{Mat, Point, Rect, Scalar};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+/// Width of the list next to the camera view
+const CLIP_LIST_WIDTH: i32 = 260;
+const FONT_SCALE: f64 = 0.45;
+const LINE_HEIGHT: i32 = 20;
+const PADDING: i32 = 8;
+const TITLE: &str = "Clips (click or j to open)";
+
+/// The clips of a video under review, in the order of the review queue, with their decisions.
+#[derive(Clone, Debug, Default)]
+pub struct ClipList {
+    clips: Vec<(u32, u32)>,
+    labels: Vec<Option<ReviewLabel>>,
+    current: Option<usize>,
+    /// Index of the first clip shown by the last `draw`, to find the clicked clip
+    first_row: usize,
+}
+
+impl ClipList {
+    pub fn new(clips: Vec<(u32, u32)>) -> Self {
+        Self {
+            labels: vec![None; clips.len()],
+            clips,
+            current: None,
+            first_row: 0,
+        }
+    }
+
+    /// The interval of clip `index`, zero-based.
+    pub fn get(&self, index: usize) -> Option<(u32, u32)> {
+        self.clips.get(index).copied()
+    }
+
+    fn index_of(&self, clip: (u32, u32)) -> Option<usize> {
+        self.clips.iter().position(|c| *c == clip)
+    }
+
+    /// Marks `clip` as the one under review.
+    pub fn set_current(&mut self, clip: (u32, u32)) {
+        self.current = self.index_of(clip);
+    }
+
+    /// Sets the decision of `clip`, `None` after it was undone.
+    pub fn set_label(&mut self, clip: (u32, u32), label: Option<ReviewLabel>) {
+        if let Some(index) = self.index_of(clip) {
+            self.labels[index] = label;
+        }
+    }
+
+    pub fn is_reviewed(&self, index: usize) -> bool {
+        self.labels.get(index).is_some_and(Option::is_some)
+    }
+
+    /// Line of clip `index`, e.g. `> 3  1200-1260  dribble`.
+    fn line(&self, index: usize) -> String {
+        let marker = if self.current == Some(index) {
+            ">"
+        } else {
+            " "
+        };
+        let (start, end) = self.clips[index];
+        let label = self.labels[index].map_or(String::new(), |label| label.to_string());
+        format!("{} {}  {}-{}  {}", marker, index + 1, start, end, label)
+            .trim_end()
+            .to_string()
+    }
+
+    /// Number of clips that fit into a list of `height` pixels.
+    fn visible_rows(height: i32) -> usize {
+        ((height - PADDING * 2) / LINE_HEIGHT - 1).max(1) as usize
+    }
+
+    /// Index of the first shown clip, so the current one is in the middle of a long list.
+    fn first_visible(&self, rows: usize) -> usize {
+        let current = self.current.unwrap_or(0);
+        current
+            .saturating_sub(rows / 2)
+            .min(self.clips.len().saturating_sub(rows))
+    }
+
+    /// Index of the clip at `y` pixels from the top of the last drawn list.
+    pub fn clip_at(&self, y: i32) -> Option<usize> {
+        let row = (y - PADDING) / LINE_HEIGHT - 1;
+        if y < PADDING || row < 0 {
+            return None;
+        }
+        let index = self.first_row + row as usize;
+        (index < self.clips.len()).then_some(index)
+    }
+
+    /// Draws the list as text on black, the current clip in yellow, accepted clips in green
+    /// and rejected ones in gray.
+    pub fn draw(&mut self, height: i32, typ: i32) -> opencv::Result<Mat> {
+        let mut panel = Mat::zeros(height, CLIP_LIST_WIDTH, typ)?.to_mat()?;
+        let rows = Self::visible_rows(height);
+        self.first_row = self.first_visible(rows);
+
+        let white = Scalar::new(255.0, 255.0, 255.0, 255.0);
+        let mut lines = vec![(TITLE.to_string(), white)];
+        for index in (self.first_row..self.clips.len()).take(rows) {
+            let color = match (self.current == Some(index), self.labels[index]) {
+                (true, _) => Scalar::new(0.0, 220.0, 255.0, 255.0),
+                (false, Some(ReviewLabel::Other)) => Scalar::new(128.0, 128.0, 128.0, 255.0),
+                (false, Some(_)) => Scalar::new(0.0, 200.0, 0.0, 255.0),
+                (false, None) => white,
+            };
+            lines.push((self.line(index), color));
+        }
+        for (i, (line, color)) in lines.iter().enumerate() {
+            imgproc::put_text(
+                &mut panel,
+                line,
+                Point::new(PADDING, PADDING + LINE_HEIGHT * (i as i32 + 1) - 6),
+                imgproc::FONT_HERSHEY_SIMPLEX,
+                FONT_SCALE,
+                *color,
+                1,
+                imgproc::LINE_AA,
+                false,
+            )?;
+        }
+        // Separates the list from the camera view
+        imgproc::rectangle(
+            &mut panel,
+            Rect::new(0, 0, 1, height),
+            white,
+            1,
+            imgproc::LINE_8,
+            0,
+        )?;
+        Ok(panel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_list() {
+        let clips: Vec<(u32, u32)> = (0..40).map(|i| (i * 100, i * 100 + 50)).collect();
+        let mut list = ClipList::new(clips);
+        list.set_current((2000, 2050));
+        list.set_label((100, 150), Some(ReviewLabel::Tackle));
+        assert_eq!(list.line(20), "> 21  2000-2050");
+        assert_eq!(list.line(1), "  2  100-150  tackle");
+        assert!(list.is_reviewed(1) && !list.is_reviewed(2));
+
+        // 10 rows fit, with the current clip in the middle
+        let rows = ClipList::visible_rows(PADDING * 2 + LINE_HEIGHT * 11);
+        assert_eq!(rows, 10);
+        assert_eq!(list.first_visible(rows), 15);
+        list.first_row = list.first_visible(rows);
+        assert_eq!(list.clip_at(PADDING + LINE_HEIGHT + 1), Some(15));
+        assert_eq!(list.clip_at(PADDING + 1), None);
+
+        // The end of the list is not scrolled past
+        list.set_current((3900, 3950));
+        assert_eq!(list.first_visible(rows), 30);
+        list.first_row = 30;
+        assert_eq!(list.clip_at(PADDING + LINE_HEIGHT * 11 + 1), None);
+    }
+}
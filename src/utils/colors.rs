@@ -0,0 +1,207 @@
+use crate::data::models::Annotation;
+use opencv::core::Scalar;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A color as blue, green and red values from 0 to 255, the channel order of OpenCV.
+pub type Bgr = [u8; 3];
+
+/// Built-in color themes that `[visualization.colors]` adjusts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorPreset {
+    /// Red and blue teams with green for everyone else
+    #[default]
+    Default,
+    /// The Okabe-Ito palette, which stays distinguishable with color vision deficiencies
+    Colorblind,
+}
+
+/// The `[visualization.colors]` table. Every color is optional and overrides the preset.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ColorsConfig {
+    #[serde(default)]
+    pub preset: ColorPreset,
+    /// Per category name, e.g. `ball = [237, 237, 237]`. Takes precedence over team colors.
+    #[serde(default)]
+    pub categories: HashMap<String, Bgr>,
+    /// Per team side in the `team` attribute, "left" or "right"
+    #[serde(default)]
+    pub teams: HashMap<String, Bgr>,
+    /// Annotations without a category or team color
+    pub default: Option<Bgr>,
+    pub inner_zone: Option<Bgr>,
+    pub outer_zone: Option<Bgr>,
+    pub pitch: Option<Bgr>,
+    /// Highlight of dribble events
+    pub dribble: Option<Bgr>,
+    /// Highlight of tackle events
+    pub tackle: Option<Bgr>,
+    /// Ring around the possession holder
+    pub possession: Option<Bgr>,
+    /// Ring around the possession holder while a defender is in the inner zone
+    pub contested: Option<Bgr>,
+}
+
+/// The colors of everything that is drawn, see [`ColorsConfig`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorTheme {
+    pub categories: HashMap<String, Bgr>,
+    pub teams: HashMap<String, Bgr>,
+    pub default: Bgr,
+    pub inner_zone: Bgr,
+    pub outer_zone: Bgr,
+    pub pitch: Bgr,
+    pub dribble: Bgr,
+    pub tackle: Bgr,
+    pub possession: Bgr,
+    pub contested: Bgr,
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        Self::preset(ColorPreset::Default)
+    }
+}
+
+impl ColorTheme {
+    pub fn preset(preset: ColorPreset) -> Self {
+        match preset {
+            ColorPreset::Default => Self {
+                categories: HashMap::from([("ball".to_string(), [237, 237, 237])]),
+                teams: HashMap::from([
+                    ("left".to_string(), [0, 0, 255]),
+                    ("right".to_string(), [255, 0, 0]),
+                ]),
+                default: [0, 255, 0],
+                inner_zone: [55, 166, 255],
+                outer_zone: [0, 242, 254],
+                pitch: [69, 160, 40],
+                dribble: [0, 255, 0],
+                tackle: [0, 0, 255],
+                possession: [255, 255, 0],
+                contested: [0, 140, 255],
+            },
+            ColorPreset::Colorblind => Self {
+                categories: HashMap::from([("ball".to_string(), [237, 237, 237])]),
+                teams: HashMap::from([
+                    ("left".to_string(), [0, 159, 230]),
+                    ("right".to_string(), [178, 114, 0]),
+                ]),
+                default: [167, 121, 204],
+                inner_zone: [0, 94, 213],
+                outer_zone: [66, 228, 240],
+                pitch: [69, 160, 40],
+                dribble: [115, 158, 0],
+                tackle: [0, 94, 213],
+                possession: [233, 180, 86],
+                contested: [0, 94, 213],
+            },
+        }
+    }
+
+    /// The preset of `colors` with its overrides, or the default theme.
+    pub fn from_config(colors: Option<&ColorsConfig>) -> Self {
+        let Some(colors) = colors else {
+            return Self::default();
+        };
+        let mut theme = Self::preset(colors.preset);
+        theme.categories.extend(colors.categories.clone());
+        theme.teams.extend(colors.teams.clone());
+        theme.default = colors.default.unwrap_or(theme.default);
+        theme.inner_zone = colors.inner_zone.unwrap_or(theme.inner_zone);
+        theme.outer_zone = colors.outer_zone.unwrap_or(theme.outer_zone);
+        theme.pitch = colors.pitch.unwrap_or(theme.pitch);
+        theme.dribble = colors.dribble.unwrap_or(theme.dribble);
+        theme.tackle = colors.tackle.unwrap_or(theme.tackle);
+        theme.possession = colors.possession.unwrap_or(theme.possession);
+        theme.contested = colors.contested.unwrap_or(theme.contested);
+        theme
+    }
+
+    /// Color of an annotation: the color of its category name, or else of its team, or else
+    /// the default color.
+    pub fn annotation_color(
+        &self,
+        annotation: &Annotation,
+        categories: &HashMap<String, u32>,
+    ) -> Bgr {
+        let category_color = categories
+            .iter()
+            .find(|(_, id)| **id == annotation.category_id)
+            .and_then(|(name, _)| self.categories.get(name));
+        let team = annotation
+            .attributes
+            .as_ref()
+            .and_then(|attributes| attributes.team.as_deref());
+        category_color
+            .or_else(|| team.and_then(|team| self.teams.get(team)))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Converts a color to an opaque `Scalar`.
+pub fn scalar(color: Bgr) -> Scalar {
+    scalar_with_alpha(color, 255.0)
+}
+
+/// Converts a color to a `Scalar` with the given alpha, which is used by transparent layers.
+pub fn scalar_with_alpha(color: Bgr, alpha: f64) -> Scalar {
+    Scalar::new(color[0] as f64, color[1] as f64, color[2] as f64, alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::Attribute;
+
+    #[test]
+    fn test_color_theme_from_config() {
+        let colors: ColorsConfig = toml::from_str(
+            r#"
+            preset = "colorblind"
+            default = [10, 20, 30]
+            categories = { referee = [0, 0, 0] }
+            teams = { left = [1, 2, 3] }
+            "#,
+        )
+        .unwrap();
+        let theme = ColorTheme::from_config(Some(&colors));
+        assert_eq!(theme.teams["left"], [1, 2, 3]);
+        assert_eq!(theme.teams["right"], [178, 114, 0]);
+        assert_eq!(theme.tackle, [0, 94, 213]);
+
+        let categories = HashMap::from([
+            ("player".to_string(), 1),
+            ("referee".to_string(), 3),
+            ("ball".to_string(), 4),
+        ]);
+        let annotation = |category_id: u32, team: Option<&str>| Annotation {
+            category_id,
+            attributes: Some(Attribute {
+                team: team.map(String::from),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            theme.annotation_color(&annotation(1, Some("left")), &categories),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            theme.annotation_color(&annotation(3, None), &categories),
+            [0, 0, 0]
+        );
+        assert_eq!(
+            theme.annotation_color(&annotation(1, None), &categories),
+            [10, 20, 30]
+        );
+        assert_eq!(
+            theme.annotation_color(&annotation(4, None), &categories),
+            [237, 237, 237]
+        );
+
+        assert_eq!(ColorTheme::from_config(None), ColorTheme::default());
+    }
+}
@@ -0,0 +1,185 @@
+use super::annotations::{draw_frame_overlay, draw_minimap};
+use crate::config::Config;
+use crate::data::models::Annotation;
+use opencv::core::{self, Mat, Rect, Scalar};
+use opencv::imgproc;
+use opencv::prelude::*;
+use std::collections::HashMap;
+
+/// Height of the label above each minimap
+const BANNER_HEIGHT: i32 = 30;
+const BORDER_THICKNESS: i32 = 4;
+
+/// Whether the detector agrees with the ground truth at a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Agreement {
+    /// Both see a dribble
+    TruePositive,
+    /// Neither sees a dribble
+    TrueNegative,
+    /// Only the detector sees a dribble
+    FalsePositive,
+    /// Only the ground truth has a dribble
+    FalseNegative,
+}
+
+impl Agreement {
+    pub fn new(ground_truth: bool, prediction: bool) -> Self {
+        match (ground_truth, prediction) {
+            (true, true) => Self::TruePositive,
+            (false, false) => Self::TrueNegative,
+            (false, true) => Self::FalsePositive,
+            (true, false) => Self::FalseNegative,
+        }
+    }
+
+    /// Green if the detector agrees with the ground truth, red for false positives and yellow
+    /// for missed dribbles.
+    pub fn color(&self) -> Scalar {
+        match self {
+            Self::TruePositive => Scalar::new(0.0, 200.0, 0.0, 255.0),
+            Self::TrueNegative => Scalar::new(128.0, 128.0, 128.0, 255.0),
+            Self::FalsePositive => Scalar::new(0.0, 0.0, 255.0, 255.0),
+            Self::FalseNegative => Scalar::new(0.0, 215.0, 255.0, 255.0),
+        }
+    }
+}
+
+/// Ground truth and detector output at the frame that is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComparisonFrame {
+    /// The frame is within a ground truth dribble interval
+    pub ground_truth: bool,
+    /// The detector has an ongoing event at the frame
+    pub prediction: bool,
+}
+
+impl ComparisonFrame {
+    pub fn new(ground_truth: &[(u32, u32)], frame_number: u32, prediction: bool) -> Self {
+        Self {
+            ground_truth: ground_truth
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&frame_number)),
+            prediction,
+        }
+    }
+
+    pub fn agreement(&self) -> Agreement {
+        Agreement::new(self.ground_truth, self.prediction)
+    }
+}
+
+/// Like `draw_annotations`, but with two minimaps below the frame: the ground truth on the left
+/// and the detector output on the right. Both are framed in the color of their
+/// [`Agreement`].
+#[allow(clippy::too_many_arguments)]
+pub fn draw_comparison(
+    frame: &mut Mat,
+    annotations: &[Annotation],
+    categories: &HashMap<String, u32>,
+    image_id: &str,
+    config: &Config,
+    inner_rad: f64,
+    outer_rad: f64,
+    comparison: ComparisonFrame,
+) -> opencv::Result<()> {
+    let annotations: Vec<Annotation> = annotations
+        .iter()
+        .filter(|ann| ann.image_id == *image_id)
+        .cloned()
+        .collect();
+
+    draw_frame_overlay(
+        frame,
+        &annotations,
+        categories,
+        config,
+        config.visualization.scale_factor,
+        inner_rad,
+        outer_rad,
+    )?;
+    let minimap = draw_minimap(
+        &annotations,
+        categories,
+        config,
+        frame.typ(),
+        inner_rad,
+        outer_rad,
+    )?;
+
+    let minimap_width = config.visualization.minimap_width;
+    let minimap_height = config.visualization.minimap_height;
+    let panel_height = BANNER_HEIGHT + minimap_height;
+    let extended_width = frame.cols().max(2 * minimap_width);
+    let extended_height = frame.rows() + panel_height;
+    let mut extended_frame = Mat::zeros(extended_height, extended_width, frame.typ())?.to_mat()?;
+    let mut extended_roi_main =
+        Mat::roi_mut(&mut extended_frame, Rect::new(0, 0, frame.cols(), frame.rows()))?;
+    frame.copy_to(&mut extended_roi_main)?;
+
+    let agreement = comparison.agreement();
+    let panels = [
+        ("Ground truth", comparison.ground_truth),
+        ("Detector", comparison.prediction),
+    ];
+    let x_offset = (extended_width - 2 * minimap_width) / 2;
+    for (i, (title, dribble)) in panels.into_iter().enumerate() {
+        let x = x_offset + i as i32 * minimap_width;
+        let panel = Rect::new(x, frame.rows(), minimap_width, panel_height);
+
+        let mut roi_minimap = Mat::roi_mut(
+            &mut extended_frame,
+            Rect::new(x, frame.rows() + BANNER_HEIGHT, minimap_width, minimap_height),
+        )?;
+        minimap.copy_to(&mut roi_minimap)?;
+
+        imgproc::put_text(
+            &mut extended_frame,
+            &format!("{}: {}", title, if dribble { "dribble" } else { "-" }),
+            core::Point::new(x + 10, frame.rows() + BANNER_HEIGHT - 9),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            0.6,
+            agreement.color(),
+            2,
+            imgproc::LINE_AA,
+            false,
+        )?;
+        imgproc::rectangle(
+            &mut extended_frame,
+            panel,
+            agreement.color(),
+            BORDER_THICKNESS,
+            imgproc::LINE_8,
+            0,
+        )?;
+    }
+
+    *frame = extended_frame;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparison_frame_agreement() {
+        let ground_truth = [(10, 20), (50, 60)];
+        assert_eq!(
+            ComparisonFrame::new(&ground_truth, 15, true).agreement(),
+            Agreement::TruePositive
+        );
+        assert_eq!(
+            ComparisonFrame::new(&ground_truth, 20, false).agreement(),
+            Agreement::FalseNegative
+        );
+        assert_eq!(
+            ComparisonFrame::new(&ground_truth, 30, true).agreement(),
+            Agreement::FalsePositive
+        );
+        assert_eq!(
+            ComparisonFrame::new(&[], 30, false).agreement(),
+            Agreement::TrueNegative
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::config::{Config, VisualizationConfig};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The file a config was loaded from, with its modification time when it was read.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+}
+
+impl ConfigSource {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: modified_time(path),
+        }
+    }
+}
+
+/// Wait before the file is checked again after a change
+const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+/// Longest wait between checks of a file that does not change
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Reloads the `[visualization]` section when the config file changes, so colors, the scale
+/// and the minimap can be tuned in display mode without restarting a run. The file is polled
+/// by its modification time, and the wait between checks doubles up to
+/// [`MAX_CHECK_INTERVAL`] while it does not change.
+#[derive(Clone, Debug)]
+pub struct ConfigWatcher {
+    source: ConfigSource,
+    profile: Option<String>,
+    interval: Duration,
+    next_check: Option<Instant>,
+}
+
+impl ConfigWatcher {
+    /// Watches the file `config` was loaded from, `None` if it was not loaded from a file.
+    /// Changes made before the watcher is created are found by the first poll, so a reload
+    /// during one video also applies to the next ones.
+    pub fn new(config: &Config) -> Option<Self> {
+        Some(Self {
+            source: config.source.clone()?,
+            profile: config.profile.clone(),
+            interval: MIN_CHECK_INTERVAL,
+            next_check: None,
+        })
+    }
+
+    /// The `[visualization]` section of the file, with the profile and environment overrides
+    /// applied, if the file changed since it was last read. A file that can not be loaded,
+    /// e.g. while it is saved, is reported and read again after its next change. Returns
+    /// `None` without checking the file until the next check is due.
+    pub fn changed_visualization(&mut self) -> Option<VisualizationConfig> {
+        let now = Instant::now();
+        if self.next_check.is_some_and(|next_check| now < next_check) {
+            return None;
+        }
+
+        let modified = modified_time(&self.source.path);
+        if modified == self.source.modified {
+            self.interval = (self.interval * 2).min(MAX_CHECK_INTERVAL);
+            self.next_check = Some(now + self.interval);
+            return None;
+        }
+        self.source.modified = modified;
+        self.interval = MIN_CHECK_INTERVAL;
+        self.next_check = Some(now + self.interval);
+
+        match Config::load(&self.source.path, self.profile.as_deref()) {
+            Ok(config) => {
+                println!("Reloaded the visualization settings of {}", self.source.path.display());
+                Some(config.visualization)
+            }
+            Err(e) => {
+                eprintln!("Error reloading {}: {}", self.source.path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_CONFIG;
+    use std::fs::File;
+
+    fn watcher(path: &Path) -> ConfigWatcher {
+        ConfigWatcher {
+            source: ConfigSource {
+                path: path.to_path_buf(),
+                modified: None,
+            },
+            profile: None,
+            interval: MIN_CHECK_INTERVAL,
+            next_check: None,
+        }
+    }
+
+    #[test]
+    fn test_reload_after_change() {
+        let path = std::env::temp_dir().join(format!("config_reload_{}.toml", std::process::id()));
+        fs::write(&path, DEFAULT_CONFIG).unwrap();
+        let mut watcher = watcher(&path);
+
+        // The file was not read before, so it counts as changed once
+        assert!(watcher.changed_visualization().is_some());
+        assert_eq!(watcher.source, ConfigSource::new(&path));
+        watcher.next_check = None;
+        assert!(watcher.changed_visualization().is_none());
+        assert_eq!(watcher.interval, MIN_CHECK_INTERVAL * 2);
+
+        let changed = DEFAULT_CONFIG.replace("autoplay = true", "autoplay = false");
+        fs::write(&path, changed).unwrap();
+        // Later than the first write even on file systems with a coarse modification time
+        let later = SystemTime::now() + Duration::from_secs(10);
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(later).unwrap();
+        // Not checked again before the next check is due
+        assert!(watcher.changed_visualization().is_none());
+        watcher.next_check = None;
+        let visualization = watcher.changed_visualization().unwrap();
+        assert!(!visualization.autoplay);
+        assert_eq!(watcher.interval, MIN_CHECK_INTERVAL);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
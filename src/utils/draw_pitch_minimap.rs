@@ -1,88 +1,98 @@
 use opencv::prelude::MatTraitConst;
 use opencv::{
-    core::{Mat, Point, Rect, Scalar},
+    core::{Mat, Rect, Scalar},
     imgproc,
 };
 
 use crate::config::Config;
+use crate::utils::pitch_model::{MinimapProjection, PitchModel};
 
-// Draw the pitch markings on the minimap. Includes center line, goal lines, and penalty areas
-// Are based on standard pitch dimensions
+// Draw the pitch markings on the minimap: touch and goal lines, halfway line, center circle,
+// penalty and goal areas, penalty spots and arcs. The dimensions come from the pitch model of
+// the config and are placed with the same projection as the annotations, so the markings match
+// the coordinate ranges of the minimap.
 pub fn draw_pitch_markings_on_minimap(minimap: &mut Mat, config: &Config) -> opencv::Result<()> {
     let minimap_width = minimap.cols();
     let minimap_height = minimap.rows();
-    // Get pitch bounds from the config (assumed to be f64)
-    let x_min = config.visualization.x_min;
-    let x_max = config.visualization.x_max;
-    let y_min = config.visualization.y_min;
-    let y_max = config.visualization.y_max;
-    let mid_x = (x_min + x_max) / 2.0;
-    let mid_y = (y_min + y_max) / 2.0;
+    let pitch = config.visualization.pitch.clone().unwrap_or_default();
+    let projection = MinimapProjection::new(&config.visualization, minimap.size()?);
+    let white = Scalar::new(255.0, 255.0, 255.0, 255.0);
+    let half_length = pitch.half_length();
+    let half_width = pitch.half_width();
 
-    if minimap_width >= minimap_height {
-        // Draw the center line (vertical)
-        let center_line_x = (((mid_x - x_min) / (x_max - x_min)) * minimap_width as f64) as i32;
-        let pt1 = Point::new(center_line_x, 0);
-        let pt2 = Point::new(center_line_x, minimap_height);
-        imgproc::line(
-            minimap,
-            pt1,
-            pt2,
-            Scalar::new(255.0, 255.0, 255.0, 255.0), // white center line
-            2,
-            imgproc::LINE_8,
-            0,
-        )?;
+    // Touch lines and goal lines
+    draw_pitch_rect(
+        minimap,
+        &projection,
+        (-half_length, -half_width),
+        (half_length, half_width),
+        white,
+    )?;
 
-        // Draw penalty boxes on the left and right
-        // Use fractions similar to standard pitch dimensions:
-        // For a 105m x 68m pitch the 16 meter mark is about 15.7% and the box height about 59.3%.
-        // The penaly box is about 10% height
-        let penalty_depth = (x_max - x_min) * 0.157;
-        let penalty_box_height = (y_max - y_min) * 0.593;
-        let box_top = mid_y + penalty_box_height / 2.0;
-        let box_bottom = mid_y - penalty_box_height / 2.0;
+    // Halfway line, center circle and center spot
+    imgproc::line(
+        minimap,
+        projection.point(0.0, -half_width),
+        projection.point(0.0, half_width),
+        white,
+        2,
+        imgproc::LINE_8,
+        0,
+    )?;
+    let center = projection.point(0.0, 0.0);
+    imgproc::ellipse(
+        minimap,
+        center,
+        projection.size(pitch.center_circle_radius),
+        0.0,
+        0.0,
+        360.0,
+        white,
+        2,
+        imgproc::LINE_AA,
+        0,
+    )?;
+    imgproc::circle(minimap, center, 3, white, -1, imgproc::LINE_8, 0)?;
 
-        // Left penalty box: from x_min to x_min + penalty_depth
-        let left_box_right = ((penalty_depth / (x_max - x_min)) * minimap_width as f64) as i32;
-        let left_box_y1 = (((box_bottom - y_min) / (y_max - y_min)) * minimap_height as f64) as i32;
-        let left_box_y2 = (((box_top - y_min) / (y_max - y_min)) * minimap_height as f64) as i32;
-        let left_box = Rect::new(0, left_box_y1, left_box_right, left_box_y2 - left_box_y1);
-        imgproc::rectangle(
-            minimap,
-            left_box,
-            Scalar::new(255.0, 255.0, 255.0, 255.0), // white outline
-            2,
-            imgproc::LINE_8,
-            0,
-        )?;
+    // Penalty and goal areas, penalty spots and arcs at both ends. `side` is -1 for the left
+    // goal and 1 for the right one.
+    for side in [-1.0, 1.0] {
+        let goal_line = side * half_length;
+        draw_end(minimap, &projection, &pitch, goal_line, side, white)?;
+    }
 
-        // Right penalty box: from x_max - penalty_depth to x_max
-        let right_box_left =
-            (((x_max - penalty_depth - x_min) / (x_max - x_min)) * minimap_width as f64) as i32;
-        let right_box = Rect::new(
-            right_box_left,
-            left_box_y1,
-            minimap_width - right_box_left,
-            left_box_y2 - left_box_y1,
-        );
-        imgproc::rectangle(
-            minimap,
-            right_box,
-            Scalar::new(255.0, 255.0, 255.0, 255.0),
-            2,
-            imgproc::LINE_8,
-            0,
-        )?;
-    } else {
-        // (If needed, add a branch here for vertical orientation.)
+    // Thirds and the lines between the flanks and the center, see [`PitchModel::third`]
+    if config.visualization.pitch_zones.unwrap_or(false) {
+        let gray = Scalar::new(160.0, 160.0, 160.0, 255.0);
+        for x in pitch.third_lines() {
+            imgproc::line(
+                minimap,
+                projection.point(x, -half_width),
+                projection.point(x, half_width),
+                gray,
+                1,
+                imgproc::LINE_8,
+                0,
+            )?;
+        }
+        for y in pitch.channel_lines() {
+            imgproc::line(
+                minimap,
+                projection.point(-half_length, y),
+                projection.point(half_length, y),
+                gray,
+                1,
+                imgproc::LINE_8,
+                0,
+            )?;
+        }
     }
 
-    // Draw a thin black border around the pitch
+    // Draw a thin border around the minimap
     imgproc::rectangle(
         minimap,
         Rect::new(0, 0, minimap_width, minimap_height),
-        Scalar::new(255.0, 255.0, 255.0, 255.0), // black
+        white,
         1,
         imgproc::LINE_8,
         0,
@@ -90,3 +100,66 @@ pub fn draw_pitch_markings_on_minimap(minimap: &mut Mat, config: &Config) -> ope
 
     Ok(())
 }
+
+/// Draws the penalty area, goal area, penalty spot and penalty arc in front of the goal at
+/// `goal_line`. The field of play is in direction `-side` of it.
+fn draw_end(
+    minimap: &mut Mat,
+    projection: &MinimapProjection,
+    pitch: &PitchModel,
+    goal_line: f64,
+    side: f64,
+    color: Scalar,
+) -> opencv::Result<()> {
+    for (length, width) in [
+        (pitch.penalty_area_length, pitch.penalty_area_width),
+        (pitch.goal_area_length, pitch.goal_area_width),
+    ] {
+        draw_pitch_rect(
+            minimap,
+            projection,
+            (goal_line, -width / 2.0),
+            (goal_line - side * length, width / 2.0),
+            color,
+        )?;
+    }
+
+    let spot = projection.point(goal_line - side * pitch.penalty_spot_distance, 0.0);
+    imgproc::circle(minimap, spot, 3, color, -1, imgproc::LINE_8, 0)?;
+
+    // The arc is the part of the circle around the spot outside the penalty area. Angles
+    // start at the positive x-axis, so the arc of the left goal is around 0 degrees.
+    let half_angle = pitch.penalty_arc_half_angle();
+    let direction = if side < 0.0 { 0.0 } else { 180.0 };
+    imgproc::ellipse(
+        minimap,
+        spot,
+        projection.size(pitch.center_circle_radius),
+        0.0,
+        direction - half_angle,
+        direction + half_angle,
+        color,
+        2,
+        imgproc::LINE_AA,
+        0,
+    )
+}
+
+/// Draws a rectangle between two corners in pitch coordinates.
+fn draw_pitch_rect(
+    minimap: &mut Mat,
+    projection: &MinimapProjection,
+    corner: (f64, f64),
+    opposite_corner: (f64, f64),
+    color: Scalar,
+) -> opencv::Result<()> {
+    imgproc::rectangle_points(
+        minimap,
+        projection.point(corner.0, corner.1),
+        projection.point(opposite_corner.0, opposite_corner.1),
+        color,
+        2,
+        imgproc::LINE_8,
+        0,
+    )
+}
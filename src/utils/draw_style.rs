@@ -0,0 +1,84 @@
+use crate::config::VisualizationConfig;
+use serde::{Deserialize, Serialize};
+
+/// Height in pixels of the `FONT_HERSHEY_SIMPLEX` capitals at a font scale of 1
+const FONT_BASE_HEIGHT: f64 = 22.0;
+
+/// The `[visualization.sizes]` table. Sizes are fractions of the height of the image they are
+/// drawn on, the scaled frame or the minimap, so drawings look the same at any resolution.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DrawSizes {
+    /// Width of bounding boxes, zones and rings
+    pub line_width: f64,
+    /// Height of track ids and other labels
+    pub font_height: f64,
+    /// Radius of the position dots on the minimap
+    pub dot_radius: f64,
+}
+
+impl Default for DrawSizes {
+    fn default() -> Self {
+        Self {
+            line_width: 0.0015,
+            font_height: 0.018,
+            dot_radius: 0.008,
+        }
+    }
+}
+
+/// Sizes in pixels for drawing on an image of a given height. Lines and dots are at least one
+/// pixel, so they stay visible at small scale factors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawStyle {
+    pub thickness: i32,
+    pub font_scale: f64,
+    pub text_thickness: i32,
+    pub dot_radius: i32,
+    /// Distance of labels from what they belong to
+    pub text_offset: i32,
+}
+
+impl DrawStyle {
+    pub fn new(sizes: &DrawSizes, height: i32) -> Self {
+        let height = height as f64;
+        let font_height = sizes.font_height * height;
+        Self {
+            thickness: pixels(sizes.line_width * height),
+            font_scale: font_height / FONT_BASE_HEIGHT,
+            text_thickness: pixels(font_height / FONT_BASE_HEIGHT),
+            dot_radius: pixels(sizes.dot_radius * height),
+            text_offset: pixels(font_height * 0.45),
+        }
+    }
+
+    /// The style of the configured sizes, or the default ones.
+    pub fn from_config(config: &VisualizationConfig, height: i32) -> Self {
+        Self::new(&config.sizes.clone().unwrap_or_default(), height)
+    }
+}
+
+fn pixels(size: f64) -> i32 {
+    (size.round() as i32).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_style_scales_with_height() {
+        let sizes = DrawSizes::default();
+        let full_hd = DrawStyle::new(&sizes, 1080);
+        assert_eq!(full_hd.thickness, 2);
+        assert_eq!(full_hd.dot_radius, 9);
+        assert!((full_hd.font_scale - 0.884).abs() < 0.001);
+
+        // At a small scale factor everything shrinks, but stays visible
+        let small = DrawStyle::new(&sizes, 216);
+        assert_eq!(small.thickness, 1);
+        assert_eq!(small.text_thickness, 1);
+        assert_eq!(small.dot_radius, 2);
+        assert!(small.font_scale < full_hd.font_scale / 4.0);
+    }
+}
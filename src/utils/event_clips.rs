@@ -0,0 +1,399 @@
+use crate::config::{Config, DEFAULT_FRAME_RATE};
+use crate::data::annotation_index::AnnotationIndex;
+use crate::data::models::VideoData;
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use crate::pipeline::distance_scale;
+use crate::utils::annotations::draw_annotations;
+use crate::utils::frame_source::{frame_number, FrameSource};
+use crate::utils::image_calculations::scale_frame;
+use opencv::core::{self, Mat, Size, Vector};
+use opencv::prelude::*;
+use opencv::videoio::VideoWriter;
+use opencv::{imgcodecs, imgproc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+/// Frames added before and after every event if `event_clip_padding` is not set
+pub const DEFAULT_EVENT_CLIP_PADDING: u32 = 25;
+/// Every Nth frame is shown in a strip if `event_strip_step` is not set
+pub const DEFAULT_EVENT_STRIP_STEP: u32 = 10;
+/// Every Nth frame is used in a GIF, which keeps the files small enough to share
+const GIF_FRAME_STEP: u32 = 2;
+const GIF_WIDTH: i32 = 480;
+const STRIP_TILE_HEIGHT: i32 = 240;
+
+/// File format of the event clips.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventExportFormat {
+    /// A video with every frame
+    #[default]
+    Mp4,
+    /// An animated GIF with every second frame, scaled down to be shared in chats and issues
+    Gif,
+    /// A horizontal contact sheet PNG with every `event_strip_step`th frame
+    Strip,
+}
+
+impl EventExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Gif => "gif",
+            Self::Strip => "png",
+        }
+    }
+}
+
+/// Writes a clip with the overlay of every detected event to `<output_path>/events/`, named
+/// `<video>_<event_idx>_<dribble|tackle>.<mp4|gif|png>`, where `event_idx` is the index of the
+/// event in its video and the extension depends on `event_export_format`. The clips show the
+/// frames from `start_frame - padding` to `end_frame + padding`.
+///
+/// Returns the number of clips written.
+pub fn create_event_clips(
+    output_path: &Path,
+    videos: &[VideoData],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+    config: &Config,
+) -> io::Result<usize> {
+    let padding = config
+        .visualization
+        .event_clip_padding
+        .unwrap_or(DEFAULT_EVENT_CLIP_PADDING);
+    let format = config.visualization.event_export_format;
+    let clips_dir = output_path.join("events");
+    if clips_dir.exists() {
+        fs::remove_dir_all(&clips_dir)?;
+    }
+
+    let mut num_clips = 0;
+    for video_data in videos {
+        let video_id = video_data.name();
+        let Some(video_events) = events.get(&video_data.key()).filter(|e| !e.is_empty()) else {
+            continue;
+        };
+        let clips: Vec<(String, (u32, u32))> = video_events
+            .iter()
+            .enumerate()
+            .map(|(index, event)| {
+                let file_name = event_clip_name(&video_id, index, event.detected_tackle, format);
+                (file_name, clip_frame_range(event, padding))
+            })
+            .collect();
+        num_clips += render_clips(&clips_dir, video_data, &clips, config)?;
+    }
+
+    if num_clips > 0 {
+        println!("Wrote {} event clips to {}", num_clips, clips_dir.display());
+    }
+    Ok(num_clips)
+}
+
+/// Writes a clip of the overlay of `video_data` for every `(file name, (first frame, last
+/// frame))` of `clips` to `clips_dir`, in the configured `event_export_format`. Clips that can
+/// not be rendered are reported and skipped.
+///
+/// Returns the number of clips written.
+pub fn render_clips(
+    clips_dir: &Path,
+    video_data: &VideoData,
+    clips: &[(String, (u32, u32))],
+    config: &Config,
+) -> io::Result<usize> {
+    if clips.is_empty() {
+        return Ok(0);
+    }
+    fs::create_dir_all(clips_dir)?;
+
+    // The overlay needs the annotations of every frame of the clips
+    let mut video_data = video_data.clone();
+    if let Err(e) = video_data.load_annotations() {
+        eprintln!("Skipping the clips of {}: {}", video_data.name(), e);
+        return Ok(0);
+    }
+    let overlay = ClipOverlay::new(&video_data, config);
+
+    let mut num_clips = 0;
+    for (file_name, frames) in clips {
+        let clip_path = clips_dir.join(file_name);
+        let frames = *frames;
+        let written = match config.visualization.event_export_format {
+            EventExportFormat::Mp4 => {
+                render_event_clip(&clip_path, &video_data, frames, &overlay, config)
+            }
+            EventExportFormat::Gif => {
+                render_event_gif(&clip_path, &video_data, frames, &overlay, config)
+            }
+            EventExportFormat::Strip => {
+                render_event_strip(&clip_path, &video_data, frames, &overlay, config)
+            }
+        };
+        match written {
+            Ok(()) => num_clips += 1,
+            Err(e) => eprintln!("Error rendering clip {}: {}", file_name, e),
+        }
+    }
+    Ok(num_clips)
+}
+
+/// File name of the clip of the `index`th event of a video.
+pub fn event_clip_name(
+    video_id: &str,
+    index: usize,
+    detected_tackle: bool,
+    format: EventExportFormat,
+) -> String {
+    let kind = if detected_tackle {
+        "tackle"
+    } else {
+        "dribble"
+    };
+    format!("{}_{}_{}.{}", video_id, index, kind, format.extension())
+}
+
+/// First and last frame of the clip of an event.
+pub fn clip_frame_range(event: &DribbleEvent, padding: u32) -> (u32, u32) {
+    let end_frame = event.end_frame.unwrap_or(event.start_frame);
+    (
+        event.start_frame.saturating_sub(padding),
+        end_frame.saturating_add(padding),
+    )
+}
+
+/// Everything needed to draw the annotations of a video onto its frames, like in display mode.
+pub(crate) struct ClipOverlay {
+    pub(crate) annotation_index: AnnotationIndex,
+    /// Image id of every frame file name
+    pub(crate) image_ids: HashMap<String, String>,
+    pub(crate) category_map: HashMap<String, u32>,
+    pub(crate) inner_rad: f64,
+    pub(crate) outer_rad: f64,
+}
+
+impl ClipOverlay {
+    /// `video_data` must have its annotations loaded.
+    pub(crate) fn new(video_data: &VideoData, config: &Config) -> Self {
+        let category_map: HashMap<String, u32> = video_data
+            .labels
+            .categories
+            .iter()
+            .map(|c| (c.name.clone(), c.id))
+            .collect();
+        let distance_scale = distance_scale(video_data, config, &category_map);
+        Self {
+            annotation_index: AnnotationIndex::new(&video_data.labels.annotations),
+            image_ids: video_data
+                .labels
+                .images
+                .iter()
+                .map(|image| (image.file_name.clone(), image.image_id.clone()))
+                .collect(),
+            category_map,
+            inner_rad: config.dribbling_detection.inner_radius * distance_scale,
+            outer_rad: config.dribbling_detection.outer_radius * distance_scale,
+        }
+    }
+}
+
+fn clip_fps(video_data: &VideoData) -> f64 {
+    match video_data.labels.info.frame_rate {
+        fps if fps > 0.0 => fps as f64,
+        _ => DEFAULT_FRAME_RATE,
+    }
+}
+
+fn render_event_clip(
+    path: &Path,
+    video_data: &VideoData,
+    frames: (u32, u32),
+    overlay: &ClipOverlay,
+    config: &Config,
+) -> opencv::Result<()> {
+    let mut writer: Option<VideoWriter> = None;
+    let fps = clip_fps(video_data);
+
+    for_each_clip_frame(video_data, frames, 1, overlay, config, |frame| {
+        if writer.is_none() {
+            writer = Some(VideoWriter::new(
+                path.to_str().unwrap_or_default(),
+                VideoWriter::fourcc('m', 'p', '4', 'v')?,
+                fps,
+                Size::new(frame.cols(), frame.rows()),
+                true,
+            )?);
+        }
+        if let Some(writer) = writer.as_mut() {
+            writer.write(frame)?;
+        }
+        Ok(())
+    })?;
+
+    if let Some(mut writer) = writer {
+        writer.release()?;
+    }
+    Ok(())
+}
+
+/// Writes every `GIF_FRAME_STEP`th frame of a clip to an animated GIF that loops forever.
+fn render_event_gif(
+    path: &Path,
+    video_data: &VideoData,
+    frames: (u32, u32),
+    overlay: &ClipOverlay,
+    config: &Config,
+) -> opencv::Result<()> {
+    // GIF delays are in hundredths of a second
+    let delay = (100.0 * GIF_FRAME_STEP as f64 / clip_fps(video_data)).round() as u16;
+    let mut encoder: Option<gif::Encoder<File>> = None;
+
+    for_each_clip_frame(video_data, frames, GIF_FRAME_STEP, overlay, config, |frame| {
+        let height = GIF_WIDTH * frame.rows() / frame.cols().max(1);
+        let resized = resize_frame(frame, Size::new(GIF_WIDTH, height))?;
+        let mut rgba = Mat::default();
+        imgproc::cvt_color_def(&resized, &mut rgba, imgproc::COLOR_BGR2RGBA)?;
+        let mut pixels = rgba.data_bytes()?.to_vec();
+
+        if encoder.is_none() {
+            let file = File::create(path).map_err(gif_error)?;
+            let mut new_encoder = gif::Encoder::new(file, GIF_WIDTH as u16, height as u16, &[])
+                .map_err(gif_error)?;
+            new_encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(gif_error)?;
+            encoder = Some(new_encoder);
+        }
+        let mut gif_frame =
+            gif::Frame::from_rgba_speed(GIF_WIDTH as u16, height as u16, &mut pixels, 10);
+        gif_frame.delay = delay;
+        if let Some(encoder) = encoder.as_mut() {
+            encoder.write_frame(&gif_frame).map_err(gif_error)?;
+        }
+        Ok(())
+    })
+}
+
+/// Writes every `event_strip_step`th frame of a clip side by side to a PNG.
+fn render_event_strip(
+    path: &Path,
+    video_data: &VideoData,
+    frames: (u32, u32),
+    overlay: &ClipOverlay,
+    config: &Config,
+) -> opencv::Result<()> {
+    let step = config
+        .visualization
+        .event_strip_step
+        .unwrap_or(DEFAULT_EVENT_STRIP_STEP)
+        .max(1);
+    let mut tiles: Vector<Mat> = Vector::new();
+    for_each_clip_frame(video_data, frames, step, overlay, config, |frame| {
+        let width = STRIP_TILE_HEIGHT * frame.cols() / frame.rows().max(1);
+        tiles.push(resize_frame(frame, Size::new(width, STRIP_TILE_HEIGHT))?);
+        Ok(())
+    })?;
+    if tiles.is_empty() {
+        return Ok(());
+    }
+
+    let mut strip = Mat::default();
+    core::hconcat(&tiles, &mut strip)?;
+    if !imgcodecs::imwrite(path.to_str().unwrap_or_default(), &strip, &Vector::new())? {
+        return Err(opencv::Error::new(
+            core::StsError,
+            format!("Failed to write {}", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+fn resize_frame(frame: &Mat, size: Size) -> opencv::Result<Mat> {
+    let mut resized = Mat::default();
+    imgproc::resize(frame, &mut resized, size, 0.0, 0.0, imgproc::INTER_AREA)?;
+    Ok(resized)
+}
+
+fn gif_error(e: impl std::fmt::Display) -> opencv::Error {
+    opencv::Error::new(core::StsError, format!("Failed to write GIF: {}", e))
+}
+
+/// Draws the overlay onto every `step`th frame from `first_frame` to `last_frame` and passes
+/// it to `write`.
+fn for_each_clip_frame(
+    video_data: &VideoData,
+    (first_frame, last_frame): (u32, u32),
+    step: u32,
+    overlay: &ClipOverlay,
+    config: &Config,
+    mut write: impl FnMut(&Mat) -> opencv::Result<()>,
+) -> opencv::Result<()> {
+    let mut frame_source = FrameSource::new(video_data)?;
+
+    for image_path in &video_data.image_paths {
+        let Some(frame_num) = frame_number(image_path) else {
+            continue;
+        };
+        if frame_num < first_frame || frame_num > last_frame {
+            continue;
+        }
+        if (frame_num - first_frame) % step != 0 {
+            continue;
+        }
+
+        let mut frame = frame_source.read(image_path)?;
+        if frame.empty() {
+            continue;
+        }
+
+        let file_name = image_file_name(image_path);
+        let image_id = overlay.image_ids.get(&file_name).unwrap_or(&file_name);
+        scale_frame(&mut frame, config)?;
+        draw_annotations(
+            &mut frame,
+            overlay.annotation_index.get(image_id),
+            &overlay.category_map,
+            image_id,
+            config,
+            overlay.inner_rad,
+            overlay.outer_rad,
+        )?;
+        write(&frame)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn image_file_name(image_path: &Path) -> String {
+    image_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_clip_name_and_range() {
+        let mut event = DribbleEvent::new(1, 10, "SNGS-001".to_string());
+        event.end_frame = Some(80);
+        assert_eq!(
+            event_clip_name("SNGS-001", 0, event.detected_tackle, EventExportFormat::Mp4),
+            "SNGS-001_0_dribble.mp4"
+        );
+        assert_eq!(clip_frame_range(&event, 25), (0, 105));
+
+        event.detected_tackle = true;
+        event.end_frame = None;
+        assert_eq!(
+            event_clip_name("SNGS-001", 3, event.detected_tackle, EventExportFormat::Gif),
+            "SNGS-001_3_tackle.gif"
+        );
+        assert_eq!(EventExportFormat::Strip.extension(), "png");
+        assert_eq!(clip_frame_range(&event, 5), (5, 15));
+    }
+}
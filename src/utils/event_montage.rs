@@ -0,0 +1,219 @@
+use crate::data::models::VideoData;
+use crate::data::storage::Storage;
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use crate::utils::colors::{scalar, ColorTheme};
+use crate::utils::frame_source::{first_frame_number, read_image, FrameSource};
+use opencv::core::{self, Mat, Rect, Scalar, Size, CV_8UC3};
+use opencv::prelude::*;
+use opencv::videoio::VideoWriter;
+use opencv::imgproc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const TILE_WIDTH: i32 = 320;
+const TILE_HEIGHT: i32 = 180;
+const GRID_COLUMNS: i32 = 4;
+const GRID_ROWS: i32 = 3;
+const MONTAGE_FPS: f64 = 10.0;
+/// Total length the montage should roughly fit into, in seconds.
+const TARGET_DURATION_SECS: f64 = 30.0;
+
+/// One tile in the montage: the representative frame of a detected event and its label.
+struct MontageTile {
+    image_path: PathBuf,
+    /// Storage of the video the image is read from
+    storage: Arc<Storage>,
+    /// Video file and its first frame number, if the frame is read from a video file
+    video: Option<(PathBuf, u32)>,
+    label: String,
+    is_tackle: bool,
+}
+
+/// Creates a single montage video sampling one representative frame from every detected event.
+/// Frames are laid out in a labeled grid, and pages are paced so the whole montage takes
+/// roughly `TARGET_DURATION_SECS` to watch. Tiles are framed in the dribble or tackle color
+/// of `theme`.
+pub fn create_event_montage(
+    output_path: &Path,
+    videos: &[VideoData],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+    theme: &ColorTheme,
+) -> opencv::Result<Option<PathBuf>> {
+    let tiles = collect_tiles(videos, events);
+    if tiles.is_empty() {
+        println!("No detected events, skipping event montage");
+        return Ok(None);
+    }
+
+    let tiles_per_page = (GRID_COLUMNS * GRID_ROWS) as usize;
+    let num_pages = tiles.len().div_ceil(tiles_per_page);
+    let secs_per_page = (TARGET_DURATION_SECS / num_pages as f64).clamp(1.0, 5.0);
+    let frames_per_page = (secs_per_page * MONTAGE_FPS).round() as usize;
+
+    let montage_path = output_path.join("event_montage.avi");
+    let page_size = Size::new(TILE_WIDTH * GRID_COLUMNS, TILE_HEIGHT * GRID_ROWS);
+    let mut writer = VideoWriter::new(
+        montage_path.to_str().unwrap(),
+        VideoWriter::fourcc('M', 'J', 'P', 'G')?,
+        MONTAGE_FPS,
+        page_size,
+        true,
+    )?;
+
+    for page_tiles in tiles.chunks(tiles_per_page) {
+        let page = render_page(page_tiles, page_size, theme)?;
+        for _ in 0..frames_per_page {
+            writer.write(&page)?;
+        }
+    }
+    writer.release()?;
+
+    println!(
+        "Wrote event montage with {} events ({} pages) to {}",
+        tiles.len(),
+        num_pages,
+        montage_path.display()
+    );
+    Ok(Some(montage_path))
+}
+
+/// Picks the middle detected frame of each event and resolves it to an image on disk.
+fn collect_tiles(
+    videos: &[VideoData],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+) -> Vec<MontageTile> {
+    let mut video_names: Vec<&String> = events.keys().collect();
+    video_names.sort();
+
+    let mut tiles = Vec::new();
+    for video_name in video_names {
+        let Some(video_data) = videos.iter().find(|v| v.key() == *video_name) else {
+            continue;
+        };
+
+        for event in &events[video_name] {
+            let frame_num = event
+                .frames
+                .get(event.frames.len() / 2)
+                .copied()
+                .unwrap_or(event.start_frame);
+
+            let Some(image_path) = find_frame_path(&video_data.image_paths, frame_num) else {
+                continue;
+            };
+
+            tiles.push(MontageTile {
+                image_path,
+                storage: video_data.storage.clone(),
+                video: video_data
+                    .video_path
+                    .clone()
+                    .map(|path| (path, first_frame_number(video_data))),
+                label: format!(
+                    "{} {}-{}",
+                    video_name,
+                    event.start_frame,
+                    event.end_frame.unwrap_or(event.start_frame)
+                ),
+                is_tackle: event.detected_tackle,
+            });
+        }
+    }
+    tiles
+}
+
+/// Image paths are named after their zero-padded frame number (e.g. "000042.jpg").
+fn find_frame_path(image_paths: &[PathBuf], frame_num: u32) -> Option<PathBuf> {
+    image_paths
+        .iter()
+        .find(|p| {
+            p.file_stem()
+                .and_then(|s| s.to_string_lossy().parse::<u32>().ok())
+                == Some(frame_num)
+        })
+        .cloned()
+}
+
+fn render_page(
+    tiles: &[MontageTile],
+    page_size: Size,
+    theme: &ColorTheme,
+) -> opencv::Result<Mat> {
+    let mut page = Mat::zeros(page_size.height, page_size.width, CV_8UC3)?.to_mat()?;
+
+    for (i, tile) in tiles.iter().enumerate() {
+        let col = i as i32 % GRID_COLUMNS;
+        let row = i as i32 / GRID_COLUMNS;
+        let roi = Rect::new(col * TILE_WIDTH, row * TILE_HEIGHT, TILE_WIDTH, TILE_HEIGHT);
+
+        let image = match &tile.video {
+            Some((video_path, first_frame)) => {
+                FrameSource::open_video(video_path, *first_frame)?.read(&tile.image_path)?
+            }
+            None => read_image(&tile.storage, &tile.image_path)?,
+        };
+        if image.empty() {
+            eprintln!("Could not read montage frame {}", tile.image_path.display());
+            continue;
+        }
+
+        let mut resized = Mat::default();
+        imgproc::resize(
+            &image,
+            &mut resized,
+            Size::new(TILE_WIDTH, TILE_HEIGHT),
+            0.0,
+            0.0,
+            imgproc::INTER_AREA,
+        )?;
+        draw_tile_label(&mut resized, tile, theme)?;
+
+        let mut page_roi = Mat::roi_mut(&mut page, roi)?;
+        resized.copy_to(&mut page_roi)?;
+    }
+
+    Ok(page)
+}
+
+fn draw_tile_label(
+    tile_image: &mut Mat,
+    tile: &MontageTile,
+    theme: &ColorTheme,
+) -> opencv::Result<()> {
+    let (kind, color) = if tile.is_tackle {
+        ("T", scalar(theme.tackle))
+    } else {
+        ("D", scalar(theme.dribble))
+    };
+
+    imgproc::rectangle(
+        tile_image,
+        Rect::new(0, 0, TILE_WIDTH, 22),
+        Scalar::new(0.0, 0.0, 0.0, 255.0),
+        -1,
+        imgproc::LINE_8,
+        0,
+    )?;
+    imgproc::put_text(
+        tile_image,
+        &format!("[{}] {}", kind, tile.label),
+        core::Point::new(4, 16),
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        0.45,
+        color,
+        1,
+        imgproc::LINE_8,
+        false,
+    )?;
+    imgproc::rectangle(
+        tile_image,
+        Rect::new(0, 0, TILE_WIDTH, TILE_HEIGHT),
+        color,
+        1,
+        imgproc::LINE_8,
+        0,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use crate::utils::frame_source::frame_number;
+use std::path::{Path, PathBuf};
+
+/// Where a seek stops. Frames before it are processed by the detector without being shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SeekTarget {
+    /// The frame at this index in the video
+    Index(usize),
+    /// The first frame of an event that starts after this frame number
+    EventAfter(u32),
+}
+
+/// Random access position in the frames of a video, addressed by their image paths.
+///
+/// The detector has to see the frames in order, so the cursor remembers the furthest frame
+/// that was processed. Frames before it are replayed: they are shown again, but not counted a
+/// second time. Seeking past it processes the frames in between without showing them.
+#[derive(Clone, Debug)]
+pub struct FrameCursor {
+    paths: Vec<PathBuf>,
+    index: usize,
+    /// Index of the furthest processed frame, `None` before the first one
+    furthest: Option<usize>,
+    seek_target: Option<SeekTarget>,
+}
+
+impl FrameCursor {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self {
+            paths,
+            index: 0,
+            furthest: None,
+            seek_target: None,
+        }
+    }
+
+    /// Image path of the current frame, `None` after the last frame.
+    pub fn current(&self) -> Option<&Path> {
+        self.paths.get(self.index).map(PathBuf::as_path)
+    }
+
+    pub fn frame_number(&self) -> Option<u32> {
+        self.current().and_then(frame_number)
+    }
+
+    /// `true` if the current frame was already processed by the detector.
+    pub fn is_replay(&self) -> bool {
+        self.furthest.is_some_and(|furthest| self.index <= furthest)
+    }
+
+    /// Marks the current frame as processed by the detector.
+    pub fn mark_processed(&mut self) {
+        self.furthest = Some(self.furthest.map_or(self.index, |f| f.max(self.index)));
+    }
+
+    /// `true` while frames are processed on the way to a seek target, which are not shown.
+    pub fn is_seeking(&self) -> bool {
+        match self.seek_target {
+            Some(SeekTarget::Index(target)) => self.index < target,
+            Some(SeekTarget::EventAfter(_)) => true,
+            None => false,
+        }
+    }
+
+    /// The frame number a next-event seek started at, if one is running.
+    pub fn seeking_event_after(&self) -> Option<u32> {
+        match self.seek_target {
+            Some(SeekTarget::EventAfter(frame)) => Some(frame),
+            _ => None,
+        }
+    }
+
+    pub fn stop_seeking(&mut self) {
+        self.seek_target = None;
+    }
+
+    pub fn next(&mut self) {
+        self.index = (self.index + 1).min(self.paths.len());
+        if self.seek_target == Some(SeekTarget::Index(self.index)) {
+            self.seek_target = None;
+        }
+    }
+
+    pub fn previous(&mut self) {
+        self.index = self.index.saturating_sub(1);
+        self.seek_target = None;
+    }
+
+    /// Moves back to the first frame, e.g. to replay a clip.
+    pub fn rewind(&mut self) {
+        self.index = 0;
+        self.seek_target = None;
+    }
+
+    /// Moves to the first frame with a frame number of at least `frame`. Frames between the
+    /// furthest processed frame and the target are processed on the way. Returns `false` and
+    /// stays at the current frame if the video ends before `frame`.
+    pub fn seek(&mut self, frame: u32) -> bool {
+        let Some(target) = self
+            .paths
+            .iter()
+            .position(|path| frame_number(path).is_some_and(|number| number >= frame))
+        else {
+            return false;
+        };
+        let first_unprocessed = self.furthest.map_or(0, |furthest| furthest + 1);
+        if target > first_unprocessed {
+            self.index = first_unprocessed;
+            self.seek_target = Some(SeekTarget::Index(target));
+        } else {
+            self.index = target;
+            self.seek_target = None;
+        }
+        true
+    }
+
+    /// Processes the next frames without showing them, until an event starts. The caller
+    /// stops the seek with [`FrameCursor::stop_seeking`] once it detects one.
+    pub fn seek_next_event(&mut self) {
+        let after = self.frame_number().unwrap_or(0);
+        self.next();
+        self.seek_target = Some(SeekTarget::EventAfter(after));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor() -> FrameCursor {
+        FrameCursor::new((1..=10).map(|i| PathBuf::from(format!("{:06}.jpg", i))).collect())
+    }
+
+    #[test]
+    fn test_frame_cursor_replay_and_seek() {
+        let mut cursor = cursor();
+        for _ in 0..3 {
+            assert!(!cursor.is_replay());
+            cursor.mark_processed();
+            cursor.next();
+        }
+        assert_eq!(cursor.frame_number(), Some(4));
+
+        cursor.previous();
+        assert_eq!(cursor.frame_number(), Some(3));
+        assert!(cursor.is_replay());
+
+        // Seeking back is immediate
+        assert!(cursor.seek(2));
+        assert_eq!(cursor.frame_number(), Some(2));
+        assert!(!cursor.is_seeking());
+
+        // Seeking ahead processes the frames in between first
+        assert!(cursor.seek(7));
+        assert_eq!(cursor.frame_number(), Some(4));
+        while cursor.is_seeking() {
+            cursor.mark_processed();
+            cursor.next();
+        }
+        assert_eq!(cursor.frame_number(), Some(7));
+        assert!(!cursor.seek(11));
+        assert_eq!(cursor.frame_number(), Some(7));
+
+        cursor.seek_next_event();
+        assert_eq!(cursor.seeking_event_after(), Some(7));
+        assert_eq!(cursor.frame_number(), Some(8));
+        cursor.stop_seeking();
+        assert!(!cursor.is_seeking());
+
+        cursor.rewind();
+        assert_eq!(cursor.frame_number(), Some(1));
+        for _ in 0..10 {
+            cursor.next();
+        }
+        assert_eq!(cursor.current(), None);
+    }
+}
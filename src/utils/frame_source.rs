@@ -0,0 +1,175 @@
+use crate::data::models::VideoData;
+use crate::data::storage::Storage;
+use opencv::core::{Mat, Vector};
+use opencv::imgcodecs;
+use opencv::prelude::*;
+use opencv::videoio::{VideoCapture, CAP_ANY, CAP_PROP_POS_FRAMES};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+/// Video file extensions that can be read instead of extracted frames
+pub const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "avi", "mkv", "mov"];
+
+/// Reads the frames of a video, either from the extracted images or directly from a video file.
+///
+/// Frames are always addressed by their image path, whose zero-padded name is the frame
+/// number (e.g. "000042.jpg"). When reading from a video file, the frame number relative to
+/// the first image is used as the index into the video.
+pub enum FrameSource {
+    /// Images read from the storage of the video
+    Images { storage: Arc<Storage> },
+    /// Images decoded ahead of time on a background thread, see [`FrameSource::prefetched`]
+    PrefetchedImages {
+        receiver: Receiver<(PathBuf, opencv::Result<Mat>)>,
+        storage: Arc<Storage>,
+    },
+    Video {
+        capture: VideoCapture,
+        first_frame: u32,
+        next_index: i64,
+    },
+}
+
+impl FrameSource {
+    pub fn new(video_data: &VideoData) -> opencv::Result<Self> {
+        match &video_data.video_path {
+            Some(video_path) => Self::open_video(video_path, first_frame_number(video_data)),
+            None => Ok(Self::Images {
+                storage: video_data.storage.clone(),
+            }),
+        }
+    }
+
+    /// Like [`FrameSource::new`], but images are read and decoded up to `prefetch_frames` frames
+    /// ahead on a background thread, so slow storage does not stall the caller. Frames must
+    /// then be read in the order of `image_paths`; skipping frames is fine. Video files are
+    /// read directly.
+    pub fn prefetched(video_data: &VideoData, prefetch_frames: usize) -> opencv::Result<Self> {
+        if prefetch_frames == 0 || video_data.video_path.is_some() {
+            return Self::new(video_data);
+        }
+
+        let (sender, receiver) = mpsc::sync_channel(prefetch_frames);
+        let image_paths = video_data.image_paths.clone();
+        let storage = video_data.storage.clone();
+        thread::spawn(move || {
+            for image_path in image_paths {
+                let frame = read_image(&storage, &image_path);
+                // The receiver is dropped when the video is finished or cancelled
+                if sender.send((image_path, frame)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self::PrefetchedImages {
+            receiver,
+            storage: video_data.storage.clone(),
+        })
+    }
+
+    /// Opens a video file whose first frame has the frame number `first_frame`.
+    pub fn open_video(video_path: &Path, first_frame: u32) -> opencv::Result<Self> {
+        let capture = VideoCapture::from_file(video_path.to_str().unwrap_or_default(), CAP_ANY)?;
+        if !capture.is_opened()? {
+            return Err(opencv::Error::new(
+                opencv::core::StsError,
+                format!("Failed to open video file {}", video_path.display()),
+            ));
+        }
+
+        Ok(Self::Video {
+            capture,
+            first_frame,
+            next_index: 0,
+        })
+    }
+
+    /// Reads the frame of `image_path`. Returns an empty `Mat` if the frame does not exist.
+    pub fn read(&mut self, image_path: &Path) -> opencv::Result<Mat> {
+        match self {
+            Self::Images { storage } => read_image(storage, image_path),
+            Self::PrefetchedImages { receiver, storage } => {
+                // Frames before the requested one were skipped by the caller
+                while let Ok((path, frame)) = receiver.recv() {
+                    if path == image_path {
+                        return frame;
+                    }
+                }
+                read_image(storage, image_path)
+            }
+            Self::Video {
+                capture,
+                first_frame,
+                next_index,
+            } => {
+                let frame_num = frame_number(image_path).unwrap_or(*first_frame);
+                let index = frame_num.saturating_sub(*first_frame) as i64;
+
+                // Seeking is slow, so frames that are read in order are decoded sequentially
+                if index != *next_index {
+                    capture.set(CAP_PROP_POS_FRAMES, index as f64)?;
+                }
+                let mut frame = Mat::default();
+                capture.read(&mut frame)?;
+                *next_index = index + 1;
+                Ok(frame)
+            }
+        }
+    }
+}
+
+/// Reads an image file, which may be inside a zip archive or in remote storage. Returns an
+/// empty `Mat` if it can not be read, like `imread`.
+pub fn read_image(storage: &Storage, image_path: &Path) -> opencv::Result<Mat> {
+    if storage.is_local_file(image_path) {
+        return imgcodecs::imread(
+            image_path.to_str().unwrap_or_default(),
+            imgcodecs::IMREAD_COLOR,
+        );
+    }
+    match storage.read(image_path) {
+        Ok(bytes) => {
+            imgcodecs::imdecode(&Vector::<u8>::from_slice(&bytes), imgcodecs::IMREAD_COLOR)
+        }
+        Err(_) => Ok(Mat::default()),
+    }
+}
+
+/// Parses the zero-padded frame number from an image path (e.g. "0001.jpg" -> 1).
+pub fn frame_number(image_path: &Path) -> Option<u32> {
+    image_path
+        .file_stem()?
+        .to_string_lossy()
+        .parse::<u32>()
+        .ok()
+}
+
+/// Frame number of the first frame of the video file of a video. Unless it is a clip, this is
+/// the frame number of its first image.
+pub fn first_frame_number(video_data: &VideoData) -> u32 {
+    video_data.video_first_frame.unwrap_or_else(|| {
+        video_data
+            .image_paths
+            .first()
+            .and_then(|p| frame_number(p))
+            .unwrap_or(0)
+    })
+}
+
+/// Returns the first video file in a sequence directory, ordered alphabetically.
+pub fn find_video_file(seq_dir: &Path) -> Option<PathBuf> {
+    let mut videos: Vec<PathBuf> = std::fs::read_dir(seq_dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| {
+                VIDEO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+            })
+        })
+        .collect();
+    videos.sort();
+    videos.into_iter().next()
+}
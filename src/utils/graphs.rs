@@ -0,0 +1,238 @@
+use crate::dribbling_detection::dribble_detector::DribbleDetector;
+use crate::utils::colors::{scalar, ColorTheme};
+use crate::utils::hud::HudInfo;
+use opencv::core::{Mat, Point, Rect, Scalar};
+use opencv::imgproc;
+use opencv::prelude::*;
+use std::collections::VecDeque;
+
+/// Frames shown in the graphs if `graph_frames` is not set
+pub const DEFAULT_GRAPH_FRAMES: usize = 100;
+const GRAPH_WIDTH: i32 = 260;
+const GRAPH_HEIGHT: i32 = 80;
+/// Height of the title above the plot area of a graph
+const TITLE_HEIGHT: i32 = 20;
+/// Distance of the graphs from each other and the edges of the frame
+const MARGIN: i32 = 10;
+/// Part of the pressure graph above the outer radius
+const PRESSURE_HEADROOM: f64 = 1.5;
+
+/// Values of the graphs at a frame. `None` leaves a gap in the line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct GraphSample {
+    /// Distance from the possession holder to the closest other player
+    pressure: Option<f64>,
+    /// Distance the possession holder moved since the previous frame, per frame
+    speed: Option<f64>,
+}
+
+/// Scrolling line charts of the pressure on the possession holder and of its speed over the
+/// last frames, computed from the positions the detector uses. The pressure graph shows the
+/// distance to the closest defender, with the inner and outer radius as reference lines, so
+/// it is visible when a defender enters a zone.
+#[derive(Clone, Debug)]
+pub struct FrameGraphs {
+    capacity: usize,
+    samples: VecDeque<GraphSample>,
+    /// Id, position and frame number of the holder of the last sample, to compute the speed
+    last_holder: Option<(u32, (f64, f64), u32)>,
+}
+
+impl FrameGraphs {
+    /// Graphs of the last `capacity` frames.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(2),
+            samples: VecDeque::new(),
+            last_holder: None,
+        }
+    }
+
+    /// Adds the values of a frame. The speed is only known if the holder did not change since
+    /// the previous frame.
+    pub fn push(&mut self, info: &HudInfo) {
+        let holder = info.possession_holder.zip(info.holder_position);
+        let speed = match (self.last_holder, holder) {
+            (Some((last_id, last_position, last_frame)), Some((id, position)))
+                if last_id == id && info.frame_number > last_frame =>
+            {
+                let frames = (info.frame_number - last_frame) as f64;
+                Some(DribbleDetector::distance(last_position, position) / frames)
+            }
+            _ => None,
+        };
+        self.last_holder = holder.map(|(id, position)| (id, position, info.frame_number));
+
+        self.samples.push_back(GraphSample {
+            pressure: info.nearest_defender,
+            speed,
+        });
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Draws the graphs in the bottom right corner of `target`, if it is large enough.
+    pub fn draw(
+        &self,
+        target: &mut Mat,
+        inner_rad: f64,
+        outer_rad: f64,
+        theme: &ColorTheme,
+    ) -> opencv::Result<()> {
+        let x = target.cols() - GRAPH_WIDTH - MARGIN;
+        let speed_area = Rect::new(
+            x,
+            target.rows() - GRAPH_HEIGHT - MARGIN,
+            GRAPH_WIDTH,
+            GRAPH_HEIGHT,
+        );
+        let pressure_area = Rect::new(
+            x,
+            speed_area.y - GRAPH_HEIGHT - MARGIN,
+            GRAPH_WIDTH,
+            GRAPH_HEIGHT,
+        );
+        if pressure_area.x < 0 || pressure_area.y < 0 {
+            return Ok(());
+        }
+
+        let pressure: Vec<Option<f64>> = self.samples.iter().map(|s| s.pressure).collect();
+        draw_graph(
+            target,
+            pressure_area,
+            &graph_title("Nearest defender", &pressure),
+            &pressure,
+            self.capacity,
+            outer_rad * PRESSURE_HEADROOM,
+            &[
+                (inner_rad, scalar(theme.inner_zone)),
+                (outer_rad, scalar(theme.outer_zone)),
+            ],
+        )?;
+
+        let speed: Vec<Option<f64>> = self.samples.iter().map(|s| s.speed).collect();
+        let max_speed = speed.iter().flatten().fold(0.0, |max: f64, s| max.max(*s));
+        draw_graph(
+            target,
+            speed_area,
+            &graph_title("Holder speed / frame", &speed),
+            &speed,
+            self.capacity,
+            (max_speed * 1.2).max(f64::EPSILON),
+            &[],
+        )
+    }
+}
+
+/// Title of a graph with its latest value.
+fn graph_title(name: &str, values: &[Option<f64>]) -> String {
+    match values.last().copied().flatten() {
+        Some(value) => format!("{}: {:.2}", name, value),
+        None => format!("{}: -", name),
+    }
+}
+
+/// Draws `values` from `0` to `max` as a line, with the newest value on the right, and a
+/// horizontal line at each reference value.
+fn draw_graph(
+    target: &mut Mat,
+    area: Rect,
+    title: &str,
+    values: &[Option<f64>],
+    capacity: usize,
+    max: f64,
+    references: &[(f64, Scalar)],
+) -> opencv::Result<()> {
+    let white = Scalar::new(255.0, 255.0, 255.0, 255.0);
+    imgproc::rectangle(
+        target,
+        area,
+        Scalar::new(0.0, 0.0, 0.0, 200.0),
+        -1,
+        imgproc::LINE_8,
+        0,
+    )?;
+    imgproc::put_text(
+        target,
+        title,
+        Point::new(area.x + 5, area.y + TITLE_HEIGHT - 6),
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        0.45,
+        white,
+        1,
+        imgproc::LINE_AA,
+        false,
+    )?;
+
+    let plot_height = (area.height - TITLE_HEIGHT) as f64;
+    let bottom = area.y + area.height;
+    let to_y = |value: f64| bottom - ((value / max).clamp(0.0, 1.0) * plot_height) as i32;
+    let step = area.width as f64 / (capacity - 1) as f64;
+    let offset = capacity - values.len();
+    let to_x = |index: usize| area.x + ((offset + index) as f64 * step) as i32;
+
+    for (value, color) in references {
+        let y = to_y(*value);
+        imgproc::line(
+            target,
+            Point::new(area.x, y),
+            Point::new(area.x + area.width, y),
+            *color,
+            1,
+            imgproc::LINE_8,
+            0,
+        )?;
+    }
+
+    for (index, pair) in values.windows(2).enumerate() {
+        if let [Some(a), Some(b)] = pair {
+            imgproc::line(
+                target,
+                Point::new(to_x(index), to_y(*a)),
+                Point::new(to_x(index + 1), to_y(*b)),
+                white,
+                1,
+                imgproc::LINE_AA,
+                0,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dribbling_detection::dribble_detector::DetectorState;
+
+    fn info(frame_number: u32, holder: u32, position: (f64, f64)) -> HudInfo {
+        HudInfo {
+            frame_number,
+            state: DetectorState::Idle,
+            inner_defenders: 0,
+            outer_defenders: 0,
+            possession_holder: Some(holder),
+            holder_position: Some(position),
+            nearest_defender: Some(2.0),
+            review_mode: false,
+            review_status: None,
+            review_phase: None,
+        }
+    }
+
+    #[test]
+    fn test_frame_graphs_speed_and_capacity() {
+        let mut graphs = FrameGraphs::new(3);
+        graphs.push(&info(1, 7, (0.0, 0.0)));
+        // Two frames later, e.g. with frame_skip
+        graphs.push(&info(3, 7, (3.0, 4.0)));
+        // The holder changed
+        graphs.push(&info(4, 9, (3.0, 4.0)));
+        graphs.push(&info(5, 9, (3.0, 5.0)));
+
+        let speed: Vec<Option<f64>> = graphs.samples.iter().map(|s| s.speed).collect();
+        assert_eq!(speed, vec![Some(2.5), None, Some(1.0)]);
+        assert_eq!(graph_title("Holder speed", &speed), "Holder speed: 1.00");
+    }
+}
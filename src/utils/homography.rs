@@ -0,0 +1,136 @@
+use crate::data::models::{Annotation, BboxImage, BboxPitch};
+use crate::data::storage::Storage;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+/// A 3x3 matrix mapping image pixel coordinates to pitch coordinates (in meters, with the
+/// origin at the center spot, like `bbox_pitch`).
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Homography(pub [[f64; 3]; 3]);
+
+impl Homography {
+    /// Projects an image point onto the pitch. Returns `None` for points on the horizon of the
+    /// homography, which have no position on the pitch.
+    pub fn project(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let [a, b, c] = self.0;
+        let w = c[0] * x + c[1] * y + c[2];
+        if w.abs() < 1e-12 {
+            return None;
+        }
+        Some((
+            (a[0] * x + a[1] * y + a[2]) / w,
+            (b[0] * x + b[1] * y + b[2]) / w,
+        ))
+    }
+
+    /// Projects the bottom edge of an image bounding box, where the player touches the pitch.
+    pub fn project_bbox(&self, bbox: &BboxImage) -> Option<BboxPitch> {
+        let bottom = bbox.y + bbox.h;
+        let (x_bottom_left, y_bottom_left) = self.project(bbox.x, bottom)?;
+        let (x_bottom_right, y_bottom_right) = self.project(bbox.x + bbox.w, bottom)?;
+        let (x_bottom_middle, y_bottom_middle) = self.project(bbox.x_center, bottom)?;
+        Some(BboxPitch {
+            x_bottom_left,
+            y_bottom_left,
+            x_bottom_right,
+            y_bottom_right,
+            x_bottom_middle,
+            y_bottom_middle,
+        })
+    }
+}
+
+/// The homographies of a sequence, read from a JSON sidecar next to its labels file:
+///
+/// ```json
+/// {
+///     "homography": [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+///     "images": { "3060000001": [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]] }
+/// }
+/// ```
+///
+/// Both keys are optional. A moving camera needs a homography per image id, a fixed camera
+/// only the one for the whole sequence.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Homographies {
+    /// Used for images without a homography of their own
+    #[serde(default)]
+    pub homography: Option<Homography>,
+    #[serde(default)]
+    pub images: HashMap<String, Homography>,
+}
+
+impl Homographies {
+    pub fn load(path: &Path, storage: &Storage) -> io::Result<Self> {
+        storage.with_reader(path, |reader| Ok(serde_json::from_reader(reader)?))
+    }
+
+    /// The homography of an image.
+    pub fn for_image(&self, image_id: &str) -> Option<&Homography> {
+        self.images.get(image_id).or(self.homography.as_ref())
+    }
+
+    /// Computes the pitch bounding box of an annotation that only has an image bounding box.
+    /// Annotations that already have a pitch bounding box are left as they are.
+    pub fn reproject(&self, annotation: &mut Annotation) {
+        if annotation.bbox_pitch.is_some() {
+            return;
+        }
+        let Some(bbox) = &annotation.bbox_image else {
+            return;
+        };
+        annotation.bbox_pitch = self
+            .for_image(&annotation.image_id)
+            .and_then(|homography| homography.project_bbox(bbox));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproject_annotations() {
+        // Scales pixels to tenths of a meter around the center of a 1920x1080 image
+        let homographies: Homographies = serde_json::from_str(
+            r#"{
+                "homography": [[0.1, 0.0, -96.0], [0.0, 0.1, -54.0], [0.0, 0.0, 1.0]],
+                "images": { "2": [[0.2, 0.0, 0.0], [0.0, 0.2, 0.0], [0.0, 0.0, 2.0]] }
+            }"#,
+        )
+        .unwrap();
+
+        let annotation = |image_id: &str| Annotation {
+            image_id: image_id.to_string(),
+            bbox_image: Some(BboxImage {
+                x: 950.0,
+                y: 500.0,
+                x_center: 960.0,
+                y_center: 540.0,
+                w: 20.0,
+                h: 80.0,
+            }),
+            ..Default::default()
+        };
+
+        let mut first = annotation("1");
+        homographies.reproject(&mut first);
+        let pitch = first.bbox_pitch.unwrap();
+        assert_eq!((pitch.x_bottom_left, pitch.y_bottom_left), (-1.0, 4.0));
+        assert_eq!((pitch.x_bottom_right, pitch.y_bottom_right), (1.0, 4.0));
+        assert_eq!(pitch.x_bottom_middle, 0.0);
+
+        let mut second = annotation("2");
+        homographies.reproject(&mut second);
+        assert_eq!(second.bbox_pitch.unwrap().x_bottom_middle, 96.0);
+
+        // Existing pitch boxes are kept
+        homographies.reproject(&mut first);
+        assert_eq!(first.bbox_pitch.unwrap().x_bottom_left, -1.0);
+
+        assert_eq!(Homography([[0.0; 3]; 3]).project(1.0, 1.0), None);
+    }
+}
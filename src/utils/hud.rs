@@ -0,0 +1,247 @@
+use crate::dribbling_detection::dribble_detector::{DetectorState, DribbleDetector};
+use crate::dribbling_detection::dribble_models::DribbleFrame;
+use crate::utils::keyboard_input::hotkey_help;
+use crate::utils::review_context::ClipPhase;
+use opencv::core::{Mat, Point, Rect, Scalar, Size};
+use opencv::imgproc;
+use opencv::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const FONT_SCALE: f64 = 0.5;
+const LINE_HEIGHT: i32 = 20;
+const PADDING: i32 = 8;
+/// Distance of the HUD box from the edges of the frame
+const MARGIN: i32 = 10;
+
+/// Corner of the frame the HUD is drawn in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HudCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// What the detector sees at a frame, shown in the HUD while debugging and by the possession
+/// indicator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HudInfo {
+    pub frame_number: u32,
+    pub state: DetectorState,
+    pub inner_defenders: usize,
+    pub outer_defenders: usize,
+    pub possession_holder: Option<u32>,
+    /// Position of the possession holder, in the coordinates of the detector
+    pub holder_position: Option<(f64, f64)>,
+    /// Distance from the holder to the closest other player, in the units of the radii
+    pub nearest_defender: Option<f64>,
+    pub review_mode: bool,
+    /// Loop and speed of the clip under review, shown even without the HUD
+    pub review_status: Option<String>,
+    /// Where the frame is relative to the event under review
+    pub review_phase: Option<ClipPhase>,
+}
+
+impl HudInfo {
+    /// Reads the defenders around the possession holder of `frame`. The holder is the one of
+    /// the ongoing event, or else the player closest to the ball. Call [`HudInfo::with_state`]
+    /// once the detector processed the frame.
+    pub fn new(frame: &DribbleFrame, detector: &DribbleDetector, review_mode: bool) -> Self {
+        let ball = (frame.ball.x, frame.ball.y);
+        let holder = match detector.current_active_event() {
+            Some(event) => frame
+                .players
+                .iter()
+                .find(|p| p.id == event.possession_holder),
+            None => frame.players.iter().min_by(|a, b| {
+                DribbleDetector::distance((a.x, a.y), ball)
+                    .total_cmp(&DribbleDetector::distance((b.x, b.y), ball))
+            }),
+        };
+        let (outer, inner) = holder
+            .map(|holder| {
+                DribbleDetector::calc_defenders(
+                    &frame.players,
+                    holder,
+                    detector.outer_rad,
+                    detector.inner_rad,
+                )
+            })
+            .unwrap_or_default();
+        let nearest_defender = holder.and_then(|holder| {
+            frame
+                .players
+                .iter()
+                .filter(|p| p.id != holder.id)
+                .map(|p| DribbleDetector::distance((p.x, p.y), (holder.x, holder.y)))
+                .min_by(f64::total_cmp)
+        });
+
+        Self {
+            frame_number: frame.frame_number,
+            state: detector.state(),
+            inner_defenders: inner.len(),
+            outer_defenders: outer.len(),
+            possession_holder: holder.map(|h| h.id),
+            holder_position: holder.map(|h| (h.x, h.y)),
+            nearest_defender,
+            review_mode,
+            review_status: None,
+            review_phase: None,
+        }
+    }
+
+    /// Possession is contested while a defender is in the inner zone of the holder.
+    pub fn is_contested(&self) -> bool {
+        self.inner_defenders > 0
+    }
+
+    pub fn with_state(mut self, state: DetectorState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Shows that the clip under review is played for the `loop_number`th time at `speed`.
+    pub fn with_review_loop(mut self, loop_number: u32, speed: f64) -> Self {
+        self.review_status = Some(format!("Loop {} at {}x", loop_number, speed));
+        self
+    }
+
+    /// Marks the frame as before, in or after the event under review.
+    pub fn with_review_phase(mut self, phase: ClipPhase) -> Self {
+        self.review_phase = Some(phase);
+        self
+    }
+
+    /// The lines about the clip under review, shown even without the HUD.
+    pub fn review_lines(&self) -> Vec<String> {
+        let phase = self.review_phase.map(|phase| phase.label().to_string());
+        self.review_status.iter().cloned().chain(phase).collect()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let holder = match self.possession_holder {
+            Some(id) => format!("#{}", id),
+            None => "-".to_string(),
+        };
+        let mut lines = vec![
+            format!("Frame {}", self.frame_number),
+            format!("State: {}", self.state),
+            format!(
+                "Defenders: {} inner / {} outer",
+                self.inner_defenders, self.outer_defenders
+            ),
+            format!("Holder: {}", holder),
+        ];
+        lines.extend(self.review_lines());
+        lines.extend(hotkey_help(self.review_mode).into_iter().map(String::from));
+        lines
+    }
+}
+
+/// Draws the lines of the HUD on a dark box in a corner of `target`.
+pub fn draw_hud(target: &mut Mat, lines: &[String], corner: HudCorner) -> opencv::Result<()> {
+    let mut width = 0;
+    for line in lines {
+        let mut baseline = 0;
+        let size = imgproc::get_text_size(
+            line,
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            FONT_SCALE,
+            1,
+            &mut baseline,
+        )?;
+        width = width.max(size.width);
+    }
+    let box_size = Size::new(
+        width + 2 * PADDING,
+        lines.len() as i32 * LINE_HEIGHT + PADDING,
+    );
+    let origin = hud_origin(corner, target.size()?, box_size);
+
+    imgproc::rectangle(
+        target,
+        Rect::new(origin.x, origin.y, box_size.width, box_size.height),
+        Scalar::new(0.0, 0.0, 0.0, 200.0),
+        -1,
+        imgproc::LINE_8,
+        0,
+    )?;
+    for (i, line) in lines.iter().enumerate() {
+        imgproc::put_text(
+            target,
+            line,
+            Point::new(origin.x + PADDING, origin.y + (i as i32 + 1) * LINE_HEIGHT),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            FONT_SCALE,
+            Scalar::new(255.0, 255.0, 255.0, 255.0),
+            1,
+            imgproc::LINE_AA,
+            false,
+        )?;
+    }
+    Ok(())
+}
+
+/// Top left corner of a box of `box_size` in `corner` of a frame of `frame_size`.
+fn hud_origin(corner: HudCorner, frame_size: Size, box_size: Size) -> Point {
+    let right = (frame_size.width - box_size.width - MARGIN).max(0);
+    let bottom = (frame_size.height - box_size.height - MARGIN).max(0);
+    match corner {
+        HudCorner::TopLeft => Point::new(MARGIN, MARGIN),
+        HudCorner::TopRight => Point::new(right, MARGIN),
+        HudCorner::BottomLeft => Point::new(MARGIN, bottom),
+        HudCorner::BottomRight => Point::new(right, bottom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hud_lines_and_origin() {
+        let info = HudInfo {
+            frame_number: 42,
+            state: DetectorState::ContestedEvent,
+            inner_defenders: 1,
+            outer_defenders: 3,
+            possession_holder: Some(7),
+            holder_position: Some((0.0, 0.0)),
+            nearest_defender: Some(1.5),
+            review_mode: false,
+            review_status: None,
+            review_phase: None,
+        };
+        assert!(info.is_contested());
+        let lines = info.lines();
+        assert_eq!(
+            lines[..4],
+            [
+                "Frame 42",
+                "State: contested event",
+                "Defenders: 1 inner / 3 outer",
+                "Holder: #7"
+            ]
+        );
+        assert_eq!(lines.len(), 6);
+        let lines = info
+            .with_review_loop(3, 0.25)
+            .with_review_phase(ClipPhase::Before)
+            .lines();
+        assert_eq!(lines[4..6], ["Loop 3 at 0.25x", "Before the event"]);
+
+        let frame_size = Size::new(1920, 1080);
+        let box_size = Size::new(300, 100);
+        assert_eq!(
+            hud_origin(HudCorner::TopLeft, frame_size, box_size),
+            Point::new(10, 10)
+        );
+        assert_eq!(
+            hud_origin(HudCorner::BottomRight, frame_size, box_size),
+            Point::new(1610, 970)
+        );
+    }
+}
@@ -1,23 +1,312 @@
-use clap::{command, Parser};
-
+use crate::config::ReviewOrder;
+use crate::data::splits::Split;
+use clap::{command, Parser, Subcommand};
 
+/// What to run. Flags that override config values can be given before or after the command.
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Detect dribbles and tackles in the configured subsets (the default)
+    Detect,
+    /// Download and extract the dataset to the input path
+    Download,
+    /// Label the events of an earlier run and export the reviewed clips
+    Review,
+    /// Check the dataset for errors and write validation_report.json to the output directory
+    Validate,
+    /// Print statistics of the configured subsets and write dataset_stats.json
+    Stats,
+    /// Run detection for every combination of the `[sweep]` parameters and write sweep.csv
+    Sweep,
+    /// Write the enabled exports (montage, clips, report, ...) of an earlier run's events
+    Export {
+        /// Events to export, `<output>/dribble_events.json` by default
+        #[arg(long)]
+        events: Option<String>,
+    },
+    /// Compare the events of an earlier run with the ground truth of `[evaluation]` and write
+    /// evaluation.json
+    Evaluate {
+        /// Events to evaluate, `<output>/dribble_events.json` by default
+        #[arg(long)]
+        events: Option<String>,
+    },
+    /// Derive approximate ground truth events from the SoccerNet action of every clip and
+    /// write bootstrap_ground_truth.json
+    BootstrapGroundTruth,
+    /// Merge the decisions of all annotators, compute their agreement and write
+    /// review_report.json
+    ReviewReport,
+    /// Open a window and print the code and bound action of every key pressed in it, to set
+    /// up `[keybindings]` on a new machine
+    ProbeKeys,
+    /// Serve frame-by-frame detection over gRPC (needs the `grpc` feature)
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        addr: String,
+    },
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Config file to read
+    #[arg(long, global = true, default_value = "config.toml")]
+    pub config: String,
+
+    /// Apply the `[profiles.<name>]` section of the config, e.g. `strict`
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Write a commented config with the default values to the `--config` path and exit
+    #[arg(long)]
+    pub init_config: bool,
+
+    /// Print the effective config, with all overrides applied, as TOML and exit
+    #[arg(long, global = true)]
+    pub print_config: bool,
+
+    /// List the sequences and frames that would be processed, estimate the runtime and output
+    /// size, and exit without processing anything
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Number of threads to process videos with (overrides the config's num_cores)
+    #[arg(long, global = true)]
+    pub threads: Option<u32>,
+
     /// Overwrite the config's input path
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub input: Option<String>,
 
     /// Overwrite the config's output path
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub output: Option<String>,
 
-    /// Download dataset
-    #[arg(long)]
+    /// Name of the run, which replaces `{tag}` in the output path or is appended to it
+    #[arg(long, global = true)]
+    pub tag: Option<String>,
+
+    /// Only process this subset (overrides the config's subsets)
+    #[arg(long, global = true)]
+    pub subset: Option<String>,
+
+    /// Only process sequences matching these patterns, e.g. "SNGS-06*" (comma separated)
+    #[arg(long, global = true, value_delimiter = ',')]
+    pub sequences: Vec<String>,
+
+    /// Only process sequences numbered from START to END (inclusive)
+    #[arg(long, global = true, num_args = 2, value_names = ["START", "END"])]
+    pub sequence_range: Option<Vec<u32>>,
+
+    /// Only process the sequences of this split (train, val or test)
+    #[arg(long, global = true)]
+    pub split: Option<Split>,
+
+    /// Write the split of every sequence to splits.json in the output directory
+    #[arg(long, global = true)]
+    pub write_splits: bool,
+
+    /// Maximum number of videos to process per subset
+    #[arg(long, global = true)]
+    pub max_videos: Option<usize>,
+
+    /// Skip annotations that can not be parsed instead of skipping the whole video
+    #[arg(long, global = true)]
+    pub lenient_labels: bool,
+
+    /// Skip the videos recorded in the output directory's checkpoint.jsonl by an earlier run
+    #[arg(long, global = true)]
+    pub resume: bool,
+
+    /// In review mode, skip the clips that already have a decision in reviewed_events.json
+    #[arg(long, global = true)]
+    pub resume_review: bool,
+
+    /// In review mode, hide whether the detector found a dribble or a tackle until the verdict
+    #[arg(long, global = true)]
+    pub blind: bool,
+
+    /// Name of the reviewer, whose decisions are written to reviewed_events-<name>.json
+    #[arg(long, global = true)]
+    pub annotator: Option<String>,
+
+    /// In review mode, the playback speed, e.g. 0.25 for slow motion
+    #[arg(long, global = true)]
+    pub review_speed: Option<f64>,
+
+    /// In review mode, the order of the clips (file, shortest or lowest_confidence)
+    #[arg(long, global = true)]
+    pub review_order: Option<ReviewOrder>,
+
+    /// In review mode, only review events in which a defender contested the ball
+    #[arg(long, global = true)]
+    pub only_contested: bool,
+
+    /// In review mode, only review events of these kinds, dribble or tackle (comma separated)
+    #[arg(long, global = true, value_delimiter = ',')]
+    pub review_kinds: Vec<String>,
+
+    /// In review mode, only review the events of these videos (comma separated)
+    #[arg(long, global = true, value_delimiter = ',')]
+    pub review_videos: Vec<String>,
+
+    /// Ground truth events to evaluate against, in the format of dribble_events.json
+    #[arg(long, global = true)]
+    pub ground_truth: Option<String>,
+
+    /// Minimum temporal IoU of a detected and a ground truth event to match them
+    #[arg(long, global = true)]
+    pub iou_threshold: Option<f64>,
+
+    /// Largest start and end offset in seconds of a match with accurate boundaries
+    #[arg(long, global = true)]
+    pub boundary_tolerance: Option<f64>,
+
+    /// Render a clip of every false positive and miss of the evaluation
+    #[arg(long, global = true)]
+    pub error_clips: bool,
+
+    /// Also write the precision-recall curve over the confidences of the detected events
+    #[arg(long, global = true)]
+    pub pr_curve: bool,
+
+    /// Also draw the precision-recall curve to pr_curve.png
+    #[arg(long, global = true)]
+    pub pr_curve_image: bool,
+
+    /// Write the statistics of every player with events to player_stats.json and .csv
+    #[arg(long, global = true)]
+    pub player_stats: bool,
+
+    /// Download the dataset before detecting, see the `download` command
+    #[arg(long, global = true)]
     pub download: bool,
 
-    /// Review mode
-    #[arg(long)]
+    /// Review mode, same as the `review` command
+    #[arg(long, global = true)]
     pub review: Option<bool>,
+
+    /// Create a montage video sampling one frame from every detected event
+    #[arg(long, global = true)]
+    pub montage: bool,
+
+    /// Write a clip with the overlay of every detected event to `<output>/events/`
+    #[arg(long, global = true)]
+    pub event_clips: bool,
+
+    /// Write an HTML gallery of the detected events to `<output>/report/`
+    #[arg(long, global = true)]
+    pub report: bool,
+
+    /// Write an audit pack with clips of randomly sampled events and non-event intervals
+    #[arg(long, global = true)]
+    pub audit: bool,
+
+    /// Merge the events of these files of other detectors with the detected events before
+    /// exporting them (comma separated, added to the config's `[import] files`)
+    #[arg(long, global = true, value_delimiter = ',')]
+    pub import: Vec<String>,
+
+    /// Notify these webhooks when the run completes or fails (comma separated, added to the
+    /// config's `[notifications] webhooks`)
+    #[arg(long, global = true, value_delimiter = ',')]
+    pub webhook: Vec<String>,
+
+    /// Write CVAT annotation files of the detected events to `<output>/cvat/`
+    #[arg(long, global = true)]
+    pub cvat: bool,
+
+    /// Write the detected events in the SoccerNet action spotting format to `<output>/spotting/`
+    #[arg(long, global = true)]
+    pub spotting: bool,
+
+    /// Analyze distances in a sample of videos and write a config with suggested radii
+    #[arg(long, global = true)]
+    pub autotune: bool,
+
+    /// Number of videos to sample in autotune mode
+    #[arg(long, global = true, default_value_t = 10)]
+    pub autotune_samples: usize,
+
+    /// Same as the `stats` command
+    #[arg(long, global = true)]
+    pub stats: bool,
+
+    /// Same as the `validate` command
+    #[arg(long, global = true)]
+    pub validate: bool,
+
+    /// Exit with a non-zero code if no videos were processed or no events were detected
+    #[arg(long, global = true)]
+    pub fail_on_empty: bool,
+
+    /// Do not show progress bars, e.g. when the output is written to a log file
+    #[arg(long, global = true)]
+    pub no_progress: bool,
+
+    /// Seconds a video may take before it is skipped and reported in `failures.json`
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub video_timeout: Option<u64>,
+
+    /// Write annotated videos of the configured subsets without running the detector
+    #[arg(long, global = true)]
+    pub render_only: bool,
+}
+
+impl Args {
+    /// The command to run. Without one, the `--validate`, `--stats` and `--review true` flags of
+    /// earlier versions select it, and otherwise detection runs.
+    pub fn command(&self) -> Command {
+        if let Some(command) = &self.command {
+            return command.clone();
+        }
+        if self.validate {
+            Command::Validate
+        } else if self.stats {
+            Command::Stats
+        } else if self.review == Some(true) {
+            Command::Review
+        } else {
+            Command::Detect
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let args = Args::parse_from(["dribbling", "detect", "--subset", "train", "--threads", "4"]);
+        assert_eq!(args.command(), Command::Detect);
+        assert_eq!(args.subset.as_deref(), Some("train"));
+        assert_eq!(args.threads, Some(4));
+
+        let args = Args::parse_from(["dribbling", "--output", "out", "export"]);
+        assert_eq!(args.command(), Command::Export { events: None });
+        assert_eq!(args.output.as_deref(), Some("out"));
+        assert_eq!(args.config, "config.toml");
+
+        let args = Args::parse_from(["dribbling", "review", "--annotator", "alice"]);
+        assert_eq!(args.command(), Command::Review);
+        assert_eq!(args.annotator.as_deref(), Some("alice"));
+        let args = Args::parse_from(["dribbling", "review-report"]);
+        assert_eq!(args.command(), Command::ReviewReport);
+        let args = Args::parse_from(["dribbling", "evaluate", "--iou-threshold", "0.3"]);
+        assert_eq!(args.command(), Command::Evaluate { events: None });
+        assert_eq!(args.iou_threshold, Some(0.3));
+        let args = Args::parse_from(["dribbling", "bootstrap-ground-truth"]);
+        assert_eq!(args.command(), Command::BootstrapGroundTruth);
+        let args = Args::parse_from(["dribbling", "probe-keys"]);
+        assert_eq!(args.command(), Command::ProbeKeys);
+
+        // Flags of earlier versions still select the command
+        assert_eq!(Args::parse_from(["dribbling", "--stats"]).command(), Command::Stats);
+        assert_eq!(Args::parse_from(["dribbling"]).command(), Command::Detect);
+    }
 }
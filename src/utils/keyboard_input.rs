@@ -1,8 +1,17 @@
 use opencv::highgui;
+use std::time::Instant;
 
-use crate::config::Config;
+use crate::config::{Config, DEFAULT_FRAME_RATE};
+use crate::pipeline::shows_window;
+use crate::utils::keymap::Keymap;
+use crate::utils::visualizations::{display_available, VisualizationBuilder};
 
-#[derive(Debug)]
+pub const MIN_PLAYBACK_SPEED: f64 = 0.25;
+pub const MAX_PLAYBACK_SPEED: f64 = 4.0;
+/// How often the mouse wheel is checked while waiting for a key
+const WHEEL_POLL_MS: i32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum KeyboardInput {
     NextFrame,
     PreviousFrame,
@@ -10,71 +19,246 @@ pub enum KeyboardInput {
     Quit,
     Dribble,
     Tackle,
+    /// Reject the clip under review
     None,
+    /// Accept the clip under review as detected
+    Accept,
+    /// Revert the last review decision of the video
+    Undo,
+    /// Move the start of the clip under review earlier
+    StartEarlier,
+    /// Move the start of the clip under review later
+    StartLater,
+    /// Move the end of the clip under review earlier
+    EndEarlier,
+    /// Move the end of the clip under review later
+    EndLater,
+    /// Toggle the review tag with this (zero-based) index
+    Tag(usize),
+    /// Add a free-text note to the current clip
+    Note,
+    /// Double the playback speed
+    Faster,
+    /// Halve the playback speed
+    Slower,
+    /// Pause or resume autoplay
+    TogglePause,
+    ZoomIn,
+    ZoomOut,
+    /// Save the shown frame as a PNG
+    Snapshot,
+    /// Jump to the start of the next event
+    NextEvent,
+    /// Jump to a frame number typed in the terminal
+    JumpToFrame,
+    /// Open the clip with a number typed in the terminal, in review mode
+    JumpToClip,
+    /// Open the clip with this (zero-based) index, clicked in the clip list
+    OpenClip(usize),
 }
 
-/// Parse OpenCV key code to KeyboardInput enum.
-/// If `filtered_keys` is set, only the provided key codes are processed.
-/// Otherwise, all key codes are processed.
-///
-/// Key codes are mapped as follows:
-///  - q: Quit
-///  - right arrow: NextFrame
-///  - left arrow: PreviousFrame
-///  - down arrow: NextClip
-///  - space: NextClip
-///  - d: Dribble
-///  - t: Tackle
-///  - n: None
-/// Any other key is ignored and returns NextFrame.
-fn parse_input_code(
-    code: opencv::Result<i32>,
-    filtered_keys: Option<&[i32]>,
-) -> Result<KeyboardInput, opencv::Error> {
-    let key_code = code?;
-
-    // If filtered_keys is provided, only process key if it's in the allowed list
-    if let Some(allowed_keys) = filtered_keys {
-        if !allowed_keys.contains(&key_code) {
-            return Ok(KeyboardInput::NextFrame);
+/// Speed and pause state of autoplay in display mode. Frames are shown at the frame rate of
+/// the sequence times the speed.
+#[derive(Clone, Debug)]
+pub struct Playback {
+    frame_rate: f64,
+    speed: f64,
+    paused: bool,
+    /// When the last key was read, to subtract the time spent processing the frame
+    last_frame: Option<Instant>,
+}
+
+impl Playback {
+    pub fn new(frame_rate: f32) -> Self {
+        let frame_rate = if frame_rate > 0.0 {
+            frame_rate as f64
+        } else {
+            DEFAULT_FRAME_RATE
+        };
+        Self {
+            frame_rate,
+            speed: 1.0,
+            paused: false,
+            last_frame: None,
         }
     }
 
-    match key_code {
-        113 => Ok(KeyboardInput::Quit),         // q
-        39 => Ok(KeyboardInput::NextFrame),     // right arrow
-        37 => Ok(KeyboardInput::PreviousFrame), // left arrow
-        40 => Ok(KeyboardInput::NextClip),      // down arrow
-        32 => Ok(KeyboardInput::NextClip),      // space
-        100 => Ok(KeyboardInput::Dribble),      // d
-        116 => Ok(KeyboardInput::Tackle),       // t
-        110 => Ok(KeyboardInput::None),         // n
-        _ => Ok(KeyboardInput::NextFrame),
+    /// Starts playback at `speed`, within the allowed speeds.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed.clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED);
+        self
+    }
+
+    /// Frame rate of the sequence, or the default if it has none.
+    pub fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(MAX_PLAYBACK_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(MIN_PLAYBACK_SPEED);
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Time between two frames at the current speed, in milliseconds.
+    pub fn frame_delay_ms(&self) -> i32 {
+        (1000.0 / (self.frame_rate * self.speed)).round() as i32
     }
+
+    /// Milliseconds to wait for a key before showing the next frame. At least 1, since
+    /// `highgui::wait_key(0)` waits forever.
+    fn remaining_delay_ms(&self) -> i32 {
+        let elapsed = self
+            .last_frame
+            .map_or(0, |last| last.elapsed().as_millis() as i32);
+        (self.frame_delay_ms() - elapsed).max(1)
+    }
+}
+
+/// Short help for the default keys of [`Keymap`], one line per group. The labeling keys are
+/// only listed in review mode.
+pub fn hotkey_help(review_mode: bool) -> Vec<&'static str> {
+    let mut help = vec![
+        "q: quit | right/left: frame | down: next clip | e: next event | g: go to frame",
+        "space: pause | +/-: speed | z/x, wheel: zoom | arrows: pan when zoomed | s: snapshot",
+    ];
+    if review_mode {
+        help.push("y: accept | n: reject | d: dribble | t: tackle | u: undo | 1-9: tag | c: note");
+        help.push("[/]: move start | {/}: move end | j, click: open clip");
+    }
+    help
 }
 
-/// Wait for user input. If autoplay is on, it quickly returns NextFrame; otherwise it blocks.
-/// Press:
+/// Wait for user input. If autoplay is on and not paused, it returns NextFrame once the frame
+/// was shown for its duration at the playback speed; otherwise it blocks. Keys are looked up
+/// in `keymap`; with the default bindings, press:
 ///   - 'q' to quit,
 ///   - right/left arrow for next/prev,
 ///   - down arrow for next clip,
-///   - d/t/n to label the clip.
-pub fn wait_for_keyboard_input(config: &Config) -> opencv::Result<KeyboardInput> {
-    if config.visualization.autoplay{
-        // Autoplay => proceed automatically
+///   - e to jump to the next event and g to jump to a typed frame number,
+///   - space to pause or resume and +/- to change the speed,
+///   - z/x or the mouse wheel to zoom and the arrow keys to pan while zoomed in,
+///   - s to save a snapshot of the shown frame,
+///   - y/n to accept or reject the clip, d/t to label it as a dribble or tackle and u to undo,
+///   - [/] and {/} to move the start and end of the clip,
+///   - 1-9 to toggle review tags and c to add a note,
+///   - j to open a clip by number, or click it in the clip list.
+///
+/// The playback, zoom and snapshot keys are handled here, so they never advance the frame.
+///
+/// Without a window there is nothing to read keys from, so other modes always proceed to the
+/// next frame without calling `highgui`, which aborts on servers without a display.
+pub fn wait_for_keyboard_input(
+    config: &Config,
+    keymap: &Keymap,
+    playback: &mut Playback,
+    visualization: &mut VisualizationBuilder,
+) -> opencv::Result<KeyboardInput> {
+    if !shows_window(config) || !display_available() {
+        return Ok(KeyboardInput::NextFrame);
+    }
 
-        let wait_time = if config.general.video_mode == "display" {
-            20
+    loop {
+        // Autoplay => proceed automatically. Otherwise the wait is split up so the mouse
+        // wheel is handled while blocking.
+        // The builder has the reloaded settings if the config file changed
+        let blocking = !visualization.config().visualization.autoplay || playback.is_paused();
+        let wait_time = if blocking {
+            WHEEL_POLL_MS
         } else {
-            1
+            playback.remaining_delay_ms()
         };
+        let key_code = highgui::wait_key(wait_time)?;
 
-        return parse_input_code(highgui::wait_key(wait_time), None);
+        let wheel_steps = visualization.take_wheel_steps();
+        if wheel_steps != 0 {
+            visualization.zoom(wheel_steps)?;
+            continue;
+        }
+        if let Some(index) = visualization.take_clicked_clip() {
+            playback.last_frame = Some(Instant::now());
+            return Ok(KeyboardInput::OpenClip(index));
+        }
+        if blocking && key_code == -1 {
+            continue;
+        }
+        if visualization.is_zoomed() {
+            if let Some((dx, dy)) = keymap.pan_direction(key_code) {
+                visualization.pan(dx, dy)?;
+                continue;
+            }
+        }
+
+        match keymap.input(key_code) {
+            KeyboardInput::ZoomIn => visualization.zoom(1)?,
+            KeyboardInput::ZoomOut => visualization.zoom(-1)?,
+            KeyboardInput::Snapshot => {
+                if let Some(path) = visualization.save_snapshot()? {
+                    println!("Saved snapshot to {}", path.display());
+                }
+            }
+            KeyboardInput::Faster => {
+                playback.faster();
+                println!("Playback speed: {}x", playback.speed());
+            }
+            KeyboardInput::Slower => {
+                playback.slower();
+                println!("Playback speed: {}x", playback.speed());
+            }
+            KeyboardInput::TogglePause => {
+                playback.toggle_pause();
+                println!("{}", if playback.is_paused() { "Paused" } else { "Resumed" });
+            }
+            input => {
+                playback.last_frame = Some(Instant::now());
+                return Ok(input);
+            }
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_speed() {
+        let mut playback = Playback::new(25.0);
+        assert_eq!(playback.frame_delay_ms(), 40);
+        playback.faster();
+        assert_eq!(playback.frame_delay_ms(), 20);
+        for _ in 0..5 {
+            playback.faster();
+        }
+        assert_eq!(playback.speed(), MAX_PLAYBACK_SPEED);
+        for _ in 0..10 {
+            playback.slower();
+        }
+        assert_eq!(playback.speed(), MIN_PLAYBACK_SPEED);
+        assert_eq!(playback.frame_delay_ms(), 160);
 
-    if config.general.video_mode == "display" {
-        parse_input_code(highgui::wait_key(0), None)
-    } else {
-        Ok(KeyboardInput::NextFrame)
+        assert_eq!(Playback::new(0.0).frame_delay_ms(), 40);
+        assert_eq!(Playback::new(25.0).with_speed(0.1).speed(), MIN_PLAYBACK_SPEED);
+        let keymap = Keymap::default();
+        assert_eq!(keymap.input(32), KeyboardInput::TogglePause);
+        assert_eq!(keymap.input(45), KeyboardInput::Slower);
+        assert_eq!(keymap.input(115), KeyboardInput::Snapshot);
+        assert_eq!(keymap.input(103), KeyboardInput::JumpToFrame);
+        assert_eq!(keymap.input(121), KeyboardInput::Accept);
+        assert_eq!(keymap.input(117), KeyboardInput::Undo);
     }
 }
@@ -0,0 +1,316 @@
+//! Key bindings of the display window. Every action has a list of keys, which can be changed
+//! in the `[keybindings]` section of the config, e.g. `next_frame = ["right", "l"]`.
+//!
+//! Which codes `highgui::wait_key` returns for the arrow and other special keys depends on the
+//! platform and on whether OpenCV was built with GTK, Qt or Cocoa, so the names of those keys
+//! stand for all of their known codes. Other codes can be bound by number; run the
+//! `probe-keys` command to see the codes of the keys of a machine.
+
+use crate::config::Config;
+use crate::utils::keyboard_input::KeyboardInput;
+use crate::utils::visualizations::display_available;
+use opencv::core::{Mat, Point, Scalar, CV_8UC3};
+use opencv::highgui;
+use opencv::imgproc;
+use std::collections::HashMap;
+
+const PROBE_WINDOW: &str = "Key probe";
+const ESCAPE: i32 = 27;
+
+/// Codes of the arrow keys: Windows, GTK and Qt (also masked to the low byte by `wait_key`),
+/// Windows with `wait_key_ex` and Cocoa
+const LEFT: &[i32] = &[37, 65361, 81, 2424832, 63234];
+const UP: &[i32] = &[38, 65362, 82, 2490368, 63232];
+const RIGHT: &[i32] = &[39, 65363, 83, 2555904, 63235];
+const DOWN: &[i32] = &[40, 65364, 84, 2621440, 63233];
+
+/// The actions and their keys without a `[keybindings]` section
+const DEFAULT_BINDINGS: &[(&str, &[&str])] = &[
+    ("quit", &["q"]),
+    ("next_frame", &["right"]),
+    ("previous_frame", &["left"]),
+    ("next_clip", &["down"]),
+    ("toggle_pause", &["space"]),
+    ("faster", &["+", "="]),
+    ("slower", &["-"]),
+    ("zoom_in", &["z"]),
+    ("zoom_out", &["x"]),
+    ("snapshot", &["s"]),
+    ("next_event", &["e"]),
+    ("jump_to_frame", &["g"]),
+    ("jump_to_clip", &["j"]),
+    ("dribble", &["d"]),
+    ("tackle", &["t"]),
+    ("reject", &["n"]),
+    ("accept", &["y"]),
+    ("undo", &["u"]),
+    ("start_earlier", &["["]),
+    ("start_later", &["]"]),
+    ("end_earlier", &["{"]),
+    ("end_later", &["}"]),
+    ("tag_1", &["1"]),
+    ("tag_2", &["2"]),
+    ("tag_3", &["3"]),
+    ("tag_4", &["4"]),
+    ("tag_5", &["5"]),
+    ("tag_6", &["6"]),
+    ("tag_7", &["7"]),
+    ("tag_8", &["8"]),
+    ("tag_9", &["9"]),
+    ("note", &["c"]),
+    ("pan_left", &["left"]),
+    ("pan_up", &["up"]),
+    ("pan_right", &["right"]),
+    ("pan_down", &["down"]),
+];
+
+/// What a key does. Panning only applies while zoomed in, so its keys may also be bound to
+/// an input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Input(KeyboardInput),
+    Pan(i32, i32),
+}
+
+fn parse_action(name: &str) -> Option<Action> {
+    let input = match name {
+        "quit" => KeyboardInput::Quit,
+        "next_frame" => KeyboardInput::NextFrame,
+        "previous_frame" => KeyboardInput::PreviousFrame,
+        "next_clip" => KeyboardInput::NextClip,
+        "toggle_pause" => KeyboardInput::TogglePause,
+        "faster" => KeyboardInput::Faster,
+        "slower" => KeyboardInput::Slower,
+        "zoom_in" => KeyboardInput::ZoomIn,
+        "zoom_out" => KeyboardInput::ZoomOut,
+        "snapshot" => KeyboardInput::Snapshot,
+        "next_event" => KeyboardInput::NextEvent,
+        "jump_to_frame" => KeyboardInput::JumpToFrame,
+        "jump_to_clip" => KeyboardInput::JumpToClip,
+        "dribble" => KeyboardInput::Dribble,
+        "tackle" => KeyboardInput::Tackle,
+        "reject" => KeyboardInput::None,
+        "accept" => KeyboardInput::Accept,
+        "undo" => KeyboardInput::Undo,
+        "start_earlier" => KeyboardInput::StartEarlier,
+        "start_later" => KeyboardInput::StartLater,
+        "end_earlier" => KeyboardInput::EndEarlier,
+        "end_later" => KeyboardInput::EndLater,
+        "note" => KeyboardInput::Note,
+        "pan_left" => return Some(Action::Pan(-1, 0)),
+        "pan_up" => return Some(Action::Pan(0, -1)),
+        "pan_right" => return Some(Action::Pan(1, 0)),
+        "pan_down" => return Some(Action::Pan(0, 1)),
+        _ => {
+            let index: usize = name.strip_prefix("tag_")?.parse().ok()?;
+            if !(1..=9).contains(&index) {
+                return None;
+            }
+            KeyboardInput::Tag(index - 1)
+        }
+    };
+    Some(Action::Input(input))
+}
+
+/// The codes of a key name: a single character, a special key like `space` or `left`, or a
+/// key code with more than one digit, as printed by `probe-keys`.
+fn parse_key(name: &str) -> Option<Vec<i32>> {
+    let codes = match name.to_lowercase().as_str() {
+        "left" => LEFT.to_vec(),
+        "up" => UP.to_vec(),
+        "right" => RIGHT.to_vec(),
+        "down" => DOWN.to_vec(),
+        "space" => vec![32],
+        "enter" => vec![13, 10],
+        "tab" => vec![9],
+        "backspace" => vec![8, 127],
+        "esc" | "escape" => vec![ESCAPE],
+        _ if name.chars().count() == 1 => vec![name.chars().next()? as i32],
+        _ => vec![name.parse().ok()?],
+    };
+    Some(codes)
+}
+
+/// The actions of the key codes of the display window.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    /// The actions of every bound code, with their names
+    actions: HashMap<i32, Vec<(&'static str, Action)>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&HashMap::new()).expect("the default key bindings are valid")
+    }
+}
+
+impl Keymap {
+    /// The default bindings, with the keys of the actions in `bindings` replaced. Fails on
+    /// unknown actions or keys, and on keys bound to two actions.
+    pub fn new(bindings: &HashMap<String, Vec<String>>) -> Result<Self, String> {
+        for action in bindings.keys() {
+            if parse_action(action).is_none() {
+                return Err(format!("unknown action \"{}\" in [keybindings]", action));
+            }
+        }
+
+        let mut actions: HashMap<i32, Vec<(&'static str, Action)>> = HashMap::new();
+        for &(name, default_keys) in DEFAULT_BINDINGS {
+            let action = parse_action(name).expect("default actions are valid");
+            let keys: Vec<&str> = match bindings.get(name) {
+                Some(keys) => keys.iter().map(String::as_str).collect(),
+                None => default_keys.to_vec(),
+            };
+            for key in keys {
+                let codes = parse_key(key)
+                    .ok_or_else(|| format!("unknown key \"{}\" for action {}", key, name))?;
+                for code in codes {
+                    let bound = actions.entry(code).or_default();
+                    // Pan keys only work while zoomed in, so they may share a key with an input
+                    let conflict = bound.iter().find(|(_, other)| {
+                        matches!(
+                            (other, action),
+                            (Action::Input(_), Action::Input(_))
+                                | (Action::Pan(..), Action::Pan(..))
+                        )
+                    });
+                    if let Some((other, _)) = conflict {
+                        return Err(format!(
+                            "key \"{}\" is bound to both {} and {}",
+                            key, other, name
+                        ));
+                    }
+                    bound.push((name, action));
+                }
+            }
+        }
+        Ok(Self { actions })
+    }
+
+    /// The keymap of the `[keybindings]` section of `config`.
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        match &config.keybindings {
+            Some(bindings) => Self::new(bindings),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The input of a key code. Keys without a binding advance a single frame.
+    pub fn input(&self, code: i32) -> KeyboardInput {
+        self.actions
+            .get(&code)
+            .and_then(|actions| {
+                actions.iter().find_map(|(_, action)| match action {
+                    Action::Input(input) => Some(*input),
+                    Action::Pan(..) => None,
+                })
+            })
+            .unwrap_or(KeyboardInput::NextFrame)
+    }
+
+    /// Direction of a key that pans the window while zoomed in.
+    pub fn pan_direction(&self, code: i32) -> Option<(i32, i32)> {
+        self.actions
+            .get(&code)?
+            .iter()
+            .find_map(|(_, action)| match action {
+                Action::Pan(dx, dy) => Some((*dx, *dy)),
+                Action::Input(_) => None,
+            })
+    }
+
+    /// The names of the actions bound to a key code.
+    fn action_names(&self, code: i32) -> Vec<&'static str> {
+        self.actions
+            .get(&code)
+            .map(|actions| actions.iter().map(|(name, _)| *name).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Opens a window and prints the code and bound action of every key pressed in it, until
+/// Escape is pressed. The codes are the ones the display window gets on this machine.
+pub fn probe_keys(keymap: &Keymap) -> opencv::Result<()> {
+    if !display_available() {
+        return Err(opencv::Error::new(
+            opencv::core::StsError,
+            "probing keys needs an X11 or Wayland display",
+        ));
+    }
+    let mut image = Mat::new_rows_cols_with_default(120, 480, CV_8UC3, Scalar::all(0.0))?;
+    imgproc::put_text(
+        &mut image,
+        "Press keys to see their codes, Esc to quit",
+        Point::new(10, 65),
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        0.5,
+        Scalar::all(255.0),
+        1,
+        imgproc::LINE_AA,
+        false,
+    )?;
+    highgui::named_window(PROBE_WINDOW, highgui::WINDOW_AUTOSIZE)?;
+    highgui::imshow(PROBE_WINDOW, &image)?;
+    println!("Press keys in the \"{}\" window, Esc to quit", PROBE_WINDOW);
+
+    loop {
+        let code = highgui::wait_key(0)?;
+        if code == -1 {
+            continue;
+        }
+        let names = keymap.action_names(code);
+        if names.is_empty() {
+            println!("Key code {}: unbound", code);
+        } else {
+            println!("Key code {}: {}", code, names.join(", "));
+        }
+        if code == ESCAPE {
+            break;
+        }
+    }
+    highgui::destroy_window(PROBE_WINDOW)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_keymap() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.input(113), KeyboardInput::Quit);
+        assert_eq!(keymap.input(61), KeyboardInput::Faster);
+        assert_eq!(keymap.input(52), KeyboardInput::Tag(3));
+        assert_eq!(keymap.input(1000), KeyboardInput::NextFrame);
+        // The arrow keys of GTK builds step through frames and pan while zoomed in
+        assert_eq!(keymap.input(65361), KeyboardInput::PreviousFrame);
+        assert_eq!(keymap.input(84), KeyboardInput::NextClip);
+        assert_eq!(keymap.pan_direction(65362), Some((0, -1)));
+        assert_eq!(keymap.pan_direction(113), None);
+    }
+
+    #[test]
+    fn test_keybindings() {
+        let bindings = |action: &str, keys: &[&str]| {
+            HashMap::from([(
+                action.to_string(),
+                keys.iter().map(|key| key.to_string()).collect(),
+            )])
+        };
+
+        let keymap = Keymap::new(&bindings("next_frame", &["l", "65363"])).unwrap();
+        assert_eq!(keymap.input(108), KeyboardInput::NextFrame);
+        assert_eq!(keymap.input(65363), KeyboardInput::NextFrame);
+        // The default keys of a rebound action are no longer bound
+        assert_eq!(keymap.input(39), KeyboardInput::NextFrame);
+        assert_eq!(keymap.input(37), KeyboardInput::PreviousFrame);
+
+        let keymap = Keymap::new(&bindings("reject", &["esc"])).unwrap();
+        assert_eq!(keymap.input(27), KeyboardInput::None);
+
+        assert!(Keymap::new(&bindings("fly", &["f"])).is_err());
+        assert!(Keymap::new(&bindings("quit", &["not a key"])).is_err());
+        assert!(Keymap::new(&bindings("quit", &["d"])).is_err());
+        assert!(Keymap::new(&bindings("tag_10", &["0"])).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use opencv::core::{Mat, Point, Rect, Scalar};
+use opencv::imgproc;
+use opencv::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Name of the window the minimap is shown in, with the `windows` layout
+pub const MINIMAP_WINDOW_NAME: &str = "Minimap";
+/// Name of the window the event log is shown in, with the `windows` layout
+pub const EVENT_LOG_WINDOW_NAME: &str = "Detected events";
+
+/// Events listed in the event log, the oldest ones are dropped
+const EVENT_LOG_LINES: usize = 12;
+const FONT_SCALE: f64 = 0.5;
+const LINE_HEIGHT: i32 = 20;
+const PADDING: i32 = 8;
+/// Height of the event log window, and the minimum height of the event log in the dashboard
+pub const EVENT_LOG_HEIGHT: i32 = PADDING * 2 + LINE_HEIGHT * (EVENT_LOG_LINES as i32 + 1);
+
+/// How the camera view, the minimap and the event log are arranged in display and download
+/// mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// The minimap below the camera view, without the event log
+    #[default]
+    Stacked,
+    /// Separate windows for the camera view, the full-size minimap and the event log. Only in
+    /// display mode, videos are written with the `dashboard` layout.
+    Windows,
+    /// A single frame with the camera view on the left, and the full-size minimap above the
+    /// event log on the right
+    Dashboard,
+}
+
+/// The most recently detected events of a video, newest first.
+#[derive(Clone, Debug, Default)]
+pub struct EventLog {
+    lines: VecDeque<String>,
+}
+
+impl EventLog {
+    pub fn push(&mut self, event: &DribbleEvent) {
+        self.lines.push_front(event_log_line(event));
+        self.lines.truncate(EVENT_LOG_LINES);
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// Draws the log as white text on black, with a title line.
+    pub fn draw(&self, width: i32, height: i32, typ: i32) -> opencv::Result<Mat> {
+        let mut panel = Mat::zeros(height, width, typ)?.to_mat()?;
+        let title = std::iter::once(EVENT_LOG_WINDOW_NAME);
+        let empty = self.lines.is_empty().then_some("No events yet");
+        for (i, line) in title.chain(empty).chain(self.lines()).enumerate() {
+            imgproc::put_text(
+                &mut panel,
+                line,
+                Point::new(PADDING, PADDING + LINE_HEIGHT * (i as i32 + 1) - 6),
+                imgproc::FONT_HERSHEY_SIMPLEX,
+                FONT_SCALE,
+                Scalar::new(255.0, 255.0, 255.0, 255.0),
+                1,
+                imgproc::LINE_AA,
+                false,
+            )?;
+        }
+        Ok(panel)
+    }
+}
+
+/// A line of the event log, e.g. `Dribble by 7, frames 120-180`.
+fn event_log_line(event: &DribbleEvent) -> String {
+    let kind = if event.detected_tackle {
+        "Tackle"
+    } else {
+        "Dribble"
+    };
+    let end = event.end_frame.map_or(String::new(), |end| end.to_string());
+    format!(
+        "{} by {}, frames {}-{}",
+        kind, event.possession_holder, event.start_frame, end
+    )
+}
+
+/// Composes the camera view, the minimap and the event log into a single frame. The camera
+/// view is on the left, the minimap and below it the event log on the right.
+pub fn compose_dashboard(camera: &Mat, minimap: &Mat, log: &EventLog) -> opencv::Result<Mat> {
+    let height = camera.rows().max(minimap.rows() + EVENT_LOG_HEIGHT);
+    let width = camera.cols() + minimap.cols();
+    let log_panel = log.draw(minimap.cols(), height - minimap.rows(), camera.typ())?;
+    let mut dashboard = Mat::zeros(height, width, camera.typ())?.to_mat()?;
+
+    for (part, x, y) in [
+        (camera, 0, 0),
+        (minimap, camera.cols(), 0),
+        (&log_panel, camera.cols(), minimap.rows()),
+    ] {
+        let roi = Rect::new(x, y, part.cols(), part.rows());
+        let mut target = Mat::roi_mut(&mut dashboard, roi)?;
+        part.copy_to(&mut target)?;
+    }
+    Ok(dashboard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(start_frame: u32, tackle: bool) -> DribbleEvent {
+        let mut event = DribbleEvent::new(7, start_frame, "video".to_string());
+        event.end_frame = Some(start_frame + 60);
+        event.detected_dribble = !tackle;
+        event.detected_tackle = tackle;
+        event
+    }
+
+    #[test]
+    fn test_event_log_newest_first() {
+        let mut log = EventLog::default();
+        for i in 0..EVENT_LOG_LINES as u32 + 2 {
+            log.push(&event(i * 100, i % 2 == 1));
+        }
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), EVENT_LOG_LINES);
+        assert_eq!(lines[0], "Tackle by 7, frames 1300-1360");
+        assert_eq!(lines[1], "Dribble by 7, frames 1200-1260");
+
+        let layout: Layout = serde_json::from_str("\"dashboard\"").unwrap();
+        assert_eq!(layout, Layout::Dashboard);
+    }
+}
@@ -1,8 +1,32 @@
 pub mod annotation_calculations;
+pub mod annotation_filters;
 pub mod annotations;
+pub mod audit_pack;
+pub mod clip_list;
+pub mod colors;
+pub mod comparison;
+pub mod config_reload;
 pub mod draw_pitch_minimap;
+pub mod draw_style;
+pub mod event_clips;
+pub mod event_montage;
+pub mod frame_cursor;
+pub mod frame_source;
+pub mod graphs;
+pub mod homography;
+pub mod hud;
 pub mod image_calculations;
 pub mod keyboard_args;
 pub mod keyboard_input;
+pub mod keymap;
+pub mod layout;
+pub mod output_template;
+pub mod overlay_layers;
+pub mod pitch_model;
+pub mod render_only;
+pub mod report;
+pub mod review_context;
+pub mod subtitles;
 pub mod video_processing;
+pub mod viewport;
 pub mod visualizations;
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Local};
+use std::io;
+use std::path::Path;
+
+/// Value of `{profile}` if no profile is selected
+const DEFAULT_PROFILE_NAME: &str = "default";
+/// Value of `{tag}` if no tag is set
+const UNTAGGED: &str = "untagged";
+
+/// Resolves the placeholders of a templated `output_path`, so the outputs of several runs can
+/// coexist, e.g. `outputs/{date}_{profile}` becomes `outputs/2025-03-14_strict`:
+/// - `{date}`: the date the run started, `YYYY-MM-DD`
+/// - `{time}`: the time the run started, `HH-MM-SS`
+/// - `{run_id}`: the date and time, `YYYYMMDD-HHMMSS`
+/// - `{profile}`: the selected profile, or `default`
+/// - `{tag}`: the tag, or `untagged`
+///
+/// A tag in a path without `{tag}` is appended as a directory, so `--tag` alone also keeps
+/// runs apart. Unknown placeholders are an error.
+pub fn resolve_output_path(
+    template: &str,
+    started_at: DateTime<Local>,
+    profile: Option<&str>,
+    tag: Option<&str>,
+) -> io::Result<String> {
+    let mut resolved = String::new();
+    let mut has_tag = false;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        resolved.push_str(&rest[..start]);
+        let value = match &rest[start + 1..start + len] {
+            "date" => started_at.format("%Y-%m-%d").to_string(),
+            "time" => started_at.format("%H-%M-%S").to_string(),
+            "run_id" => started_at.format("%Y%m%d-%H%M%S").to_string(),
+            "profile" => profile.unwrap_or(DEFAULT_PROFILE_NAME).to_string(),
+            "tag" => {
+                has_tag = true;
+                tag.unwrap_or(UNTAGGED).to_string()
+            }
+            name => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "unknown placeholder {{{}}} in output path {:?}, expected {{date}}, \
+                         {{time}}, {{run_id}}, {{profile}} or {{tag}}",
+                        name, template
+                    ),
+                ))
+            }
+        };
+        resolved.push_str(&value);
+        rest = &rest[start + len + 1..];
+    }
+    resolved.push_str(rest);
+
+    match tag {
+        Some(tag) if !has_tag => Ok(Path::new(&resolved).join(tag).to_string_lossy().to_string()),
+        _ => Ok(resolved),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_resolve_output_path() {
+        let started_at = Local.with_ymd_and_hms(2025, 3, 14, 9, 26, 53).unwrap();
+        let resolve = |template, profile, tag| {
+            resolve_output_path(template, started_at, profile, tag).unwrap()
+        };
+
+        assert_eq!(resolve("data/output", None, None), "data/output");
+        assert_eq!(
+            resolve("outputs/{date}_{profile}", Some("strict"), None),
+            "outputs/2025-03-14_strict"
+        );
+        assert_eq!(
+            resolve("outputs/{run_id}_{tag}", None, Some("baseline")),
+            "outputs/20250314-092653_baseline"
+        );
+        assert_eq!(resolve("outputs/{time}", None, None), "outputs/09-26-53");
+        // Without {tag} the tag becomes a directory
+        assert_eq!(resolve("data/output", None, Some("baseline")), "data/output/baseline");
+
+        assert!(resolve_output_path("outputs/{day}", started_at, None, None).is_err());
+    }
+}
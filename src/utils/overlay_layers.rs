@@ -0,0 +1,141 @@
+use super::annotations::{draw_frame_overlay, draw_minimap, draw_possession_indicator};
+use super::colors::ColorTheme;
+use super::draw_style::DrawStyle;
+use super::hud::{draw_hud, HudInfo};
+use crate::config::Config;
+use crate::data::models::Annotation;
+use opencv::core::{self, Mat, Scalar, Size, Vector, CV_8UC4};
+use opencv::prelude::*;
+use opencv::{imgcodecs, imgproc};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Writes the drawn overlay as separate transparent PNG layers instead of burning it into the
+/// frame, so the graphics can be composited over the original footage at full quality.
+///
+/// Each layer is written to its own directory inside `layer_dir`, named by frame index:
+///  - `overlay/`: bounding boxes, track ids, radius circles and the possession indicator, at the
+///    original frame resolution
+///  - `minimap/`: the 2d pitch minimap
+///  - `hud/`: text with the video name and frame number, or the HUD if it is enabled
+pub struct OverlayLayerWriter {
+    video_name: String,
+    layer_dir: PathBuf,
+}
+
+impl OverlayLayerWriter {
+    pub fn new(output_path: &Path, file_name: &str) -> opencv::Result<Self> {
+        let layer_dir = output_path.join(format!("{}_layers", file_name));
+        for layer in ["overlay", "minimap", "hud"] {
+            fs::create_dir_all(layer_dir.join(layer)).map_err(|e| {
+                opencv::Error::new(
+                    core::StsError,
+                    format!("Failed to create layer directory: {}", e),
+                )
+            })?;
+        }
+        Ok(Self {
+            video_name: file_name.to_string(),
+            layer_dir,
+        })
+    }
+
+    /// Draws and writes all layers for a single frame. `frame_size` is the size of the
+    /// original (unscaled) frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_frame(
+        &self,
+        frame_index: usize,
+        frame_size: Size,
+        image_id: &str,
+        annotations: &[Annotation],
+        categories: &HashMap<String, u32>,
+        config: &Config,
+        inner_rad: f64,
+        outer_rad: f64,
+        hud_info: Option<&HudInfo>,
+    ) -> opencv::Result<()> {
+        let annotations: Vec<Annotation> = annotations
+            .iter()
+            .filter(|ann| ann.image_id == *image_id)
+            .cloned()
+            .collect();
+        let file_name = format!("{:06}.png", frame_index);
+
+        let mut overlay = transparent_layer(frame_size)?;
+        draw_frame_overlay(
+            &mut overlay,
+            &annotations,
+            categories,
+            config,
+            1.0,
+            inner_rad,
+            outer_rad,
+        )?;
+        let possession =
+            hud_info.filter(|_| config.visualization.possession_indicator.unwrap_or(true));
+        if let Some(hud_info) = possession {
+            if let Some(holder_id) = hud_info.possession_holder {
+                let style = DrawStyle::from_config(&config.visualization, overlay.rows());
+                draw_possession_indicator(
+                    &mut overlay,
+                    &annotations,
+                    categories,
+                    holder_id,
+                    hud_info.is_contested(),
+                    &ColorTheme::from_config(config.visualization.colors.as_ref()),
+                    &style,
+                    1.0,
+                )?;
+            }
+        }
+        self.write_layer("overlay", &file_name, &overlay)?;
+
+        let minimap = draw_minimap(
+            &annotations,
+            categories,
+            config,
+            CV_8UC4,
+            inner_rad,
+            outer_rad,
+        )?;
+        self.write_layer("minimap", &file_name, &minimap)?;
+
+        let mut hud = transparent_layer(frame_size)?;
+        match hud_info.filter(|_| config.visualization.hud.unwrap_or(false)) {
+            Some(hud_info) => {
+                let mut lines = vec![self.video_name.clone()];
+                lines.extend(hud_info.lines());
+                draw_hud(&mut hud, &lines, config.visualization.hud_corner)?;
+            }
+            None => imgproc::put_text(
+                &mut hud,
+                &format!("{} | frame {}", self.video_name, frame_index),
+                core::Point::new(20, 40),
+                imgproc::FONT_HERSHEY_SIMPLEX,
+                1.0,
+                Scalar::new(255.0, 255.0, 255.0, 255.0),
+                2,
+                imgproc::LINE_AA,
+                false,
+            )?,
+        }
+        self.write_layer("hud", &file_name, &hud)?;
+
+        Ok(())
+    }
+
+    fn write_layer(&self, layer: &str, file_name: &str, image: &Mat) -> opencv::Result<()> {
+        let path = self.layer_dir.join(layer).join(file_name);
+        if !imgcodecs::imwrite(path.to_str().unwrap(), image, &Vector::new())? {
+            eprintln!("Failed to write layer {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// A fully transparent BGRA image.
+fn transparent_layer(size: Size) -> opencv::Result<Mat> {
+    Mat::zeros(size.height, size.width, CV_8UC4)?.to_mat()
+}
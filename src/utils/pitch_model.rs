@@ -0,0 +1,227 @@
+use crate::config::VisualizationConfig;
+use opencv::core::{Point, Size};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Dimensions of the pitch in meters. Pitch coordinates have their origin at the center spot,
+/// with x along the length of the pitch and y along its width. Defaults to the standard
+/// 105 x 68 m pitch.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PitchModel {
+    pub length: f64,
+    pub width: f64,
+    /// Depth of the penalty area from the goal line
+    pub penalty_area_length: f64,
+    pub penalty_area_width: f64,
+    /// Depth of the goal area from the goal line
+    pub goal_area_length: f64,
+    pub goal_area_width: f64,
+    /// Radius of the center circle and the penalty arcs
+    pub center_circle_radius: f64,
+    /// Distance of the penalty spots from the goal line
+    pub penalty_spot_distance: f64,
+}
+
+impl Default for PitchModel {
+    fn default() -> Self {
+        Self {
+            length: 105.0,
+            width: 68.0,
+            penalty_area_length: 16.5,
+            penalty_area_width: 40.32,
+            goal_area_length: 5.5,
+            goal_area_width: 18.32,
+            center_circle_radius: 9.15,
+            penalty_spot_distance: 11.0,
+        }
+    }
+}
+
+impl PitchModel {
+    pub fn half_length(&self) -> f64 {
+        self.length / 2.0
+    }
+
+    pub fn half_width(&self) -> f64 {
+        self.width / 2.0
+    }
+
+    /// Half of the angle of the penalty arc that lies outside the penalty area, in degrees.
+    pub fn penalty_arc_half_angle(&self) -> f64 {
+        let inside = self.penalty_area_length - self.penalty_spot_distance;
+        (inside / self.center_circle_radius)
+            .clamp(-1.0, 1.0)
+            .acos()
+            .to_degrees()
+    }
+
+    /// x of the lines between the thirds.
+    pub fn third_lines(&self) -> [f64; 2] {
+        let third = self.length / 6.0;
+        [-third, third]
+    }
+
+    /// y of the lines between the flanks and the center.
+    pub fn channel_lines(&self) -> [f64; 2] {
+        let center = self.penalty_area_width / 2.0;
+        [-center, center]
+    }
+
+    /// Third of a point at `x` for a team that attacks the goal at positive x if
+    /// `attacking_right`, and the one at negative x otherwise.
+    pub fn third(&self, x: f64, attacking_right: bool) -> Third {
+        let x = if attacking_right { x } else { -x };
+        let [defensive, attacking] = self.third_lines();
+        if x < defensive {
+            Third::Defensive
+        } else if x > attacking {
+            Third::Attacking
+        } else {
+            Third::Middle
+        }
+    }
+
+    pub fn channel(&self, y: f64) -> Channel {
+        let [_, center] = self.channel_lines();
+        if y.abs() <= center {
+            Channel::Center
+        } else {
+            Channel::Flank
+        }
+    }
+}
+
+/// Whether `team` attacks the goal at positive x: the `left` team does and the `right` team
+/// does not. `None` for other and unknown teams, whose thirds are not known.
+pub fn attacks_right(team: Option<&str>) -> Option<bool> {
+    match team? {
+        "left" => Some(true),
+        "right" => Some(false),
+        _ => None,
+    }
+}
+
+/// Third of the pitch along its length, seen from the team in possession.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Third {
+    Defensive,
+    Middle,
+    Attacking,
+}
+
+impl fmt::Display for Third {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Defensive => "defensive",
+            Self::Middle => "middle",
+            Self::Attacking => "attacking",
+        })
+    }
+}
+
+/// Part of the pitch across its width. The center is as wide as the penalty areas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Flank,
+    Center,
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Flank => "flank",
+            Self::Center => "center",
+        })
+    }
+}
+
+/// Maps pitch coordinates in meters to the pixels of a minimap that shows the area from
+/// `x_min`/`y_min` to `x_max`/`y_max` of the config.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MinimapProjection {
+    x_min: f64,
+    y_min: f64,
+    /// Pixels per meter along x and y
+    scale_x: f64,
+    scale_y: f64,
+}
+
+impl MinimapProjection {
+    pub fn new(config: &VisualizationConfig, minimap_size: Size) -> Self {
+        Self {
+            x_min: config.x_min,
+            y_min: config.y_min,
+            scale_x: minimap_size.width as f64 / (config.x_max - config.x_min),
+            scale_y: minimap_size.height as f64 / (config.y_max - config.y_min),
+        }
+    }
+
+    pub fn point(&self, x: f64, y: f64) -> Point {
+        Point::new(
+            ((x - self.x_min) * self.scale_x) as i32,
+            ((y - self.y_min) * self.scale_y) as i32,
+        )
+    }
+
+    /// A length in meters along x and along y in pixels, e.g. the axes of a circle, which is
+    /// an ellipse if the minimap does not have the aspect ratio of the shown area.
+    pub fn size(&self, length: f64) -> Size {
+        Size::new((length * self.scale_x) as i32, (length * self.scale_y) as i32)
+    }
+
+    /// Radius in pixels of a circle with `radius` meters that fits in both directions.
+    pub fn radius(&self, radius: f64) -> i32 {
+        (radius * self.scale_x.min(self.scale_y)) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimap_projection() {
+        let config: VisualizationConfig = toml::from_str(
+            r#"
+            autoplay = true
+            scale_factor = 1.0
+            minimap_x = 0
+            minimap_y = 0
+            minimap_width = 1050
+            minimap_height = 680
+            x_min = -52.5
+            x_max = 52.5
+            y_min = -34.0
+            y_max = 34.0
+            "#,
+        )
+        .unwrap();
+        let projection = MinimapProjection::new(&config, Size::new(1050, 680));
+        let pitch = PitchModel::default();
+
+        assert_eq!(projection.point(0.0, 0.0), Point::new(525, 340));
+        assert_eq!(
+            projection.point(-pitch.half_length(), pitch.half_width()),
+            Point::new(0, 680)
+        );
+        assert_eq!(projection.size(pitch.penalty_area_length), Size::new(165, 165));
+        assert_eq!(projection.radius(pitch.center_circle_radius), 91);
+        assert!((pitch.penalty_arc_half_angle() - 53.05).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_pitch_zones() {
+        let pitch = PitchModel::default();
+        assert_eq!(pitch.third(-30.0, true), Third::Defensive);
+        assert_eq!(pitch.third(-30.0, false), Third::Attacking);
+        assert_eq!(pitch.third(17.0, true), Third::Middle);
+        assert_eq!(pitch.third(18.0, true), Third::Attacking);
+        assert_eq!(attacks_right(Some("right")), Some(false));
+        assert_eq!(attacks_right(None), None);
+        assert_eq!(pitch.channel(-20.0), Channel::Center);
+        assert_eq!(pitch.channel(25.0), Channel::Flank);
+    }
+}
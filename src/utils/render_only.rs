@@ -0,0 +1,73 @@
+use crate::config::{Config, VideoMode};
+use crate::data::models::VideoData;
+use crate::pipeline::CancellationToken;
+use crate::utils::event_clips::{image_file_name, ClipOverlay};
+use crate::utils::frame_source::FrameSource;
+use crate::utils::visualizations::VisualizationBuilder;
+use opencv::prelude::*;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+/// Writes a video with the annotations of every video to the output directory of `config`,
+/// without running the dribble detector. The videos are drawn like in download mode, with the
+/// bounding boxes, track ids and minimap, and use the same codec and container.
+///
+/// Returns the number of videos written. Runs on the current rayon thread pool.
+pub fn render_videos(videos: &[VideoData], config: &Config, cancel: &CancellationToken) -> usize {
+    videos
+        .par_iter()
+        .filter(|_| !cancel.is_cancelled())
+        .filter(|video_data| match render_video(video_data, config, cancel) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Error rendering {}: {}", video_data.name(), e);
+                false
+            }
+        })
+        .count()
+}
+
+/// Writes the annotated frames of a single video, see [`render_videos`].
+pub fn render_video(
+    video_data: &VideoData,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> opencv::Result<()> {
+    let video_id = video_data.name();
+
+    // The annotations of every frame are drawn, so they can not be streamed
+    let mut video_data = video_data.clone();
+    video_data.load_annotations().map_err(|e| {
+        opencv::Error::new(
+            opencv::core::StsError,
+            format!("Failed to load annotations: {}", e),
+        )
+    })?;
+    let overlay = ClipOverlay::new(&video_data, config);
+
+    let mut frame_source = FrameSource::new(&video_data)?;
+    let mut builder = VisualizationBuilder::new(VideoMode::Download, &video_id, config)?
+        .with_frame_rate(video_data.labels.info.frame_rate);
+    for image_path in &video_data.image_paths {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let mut frame = frame_source.read(image_path)?;
+        if frame.empty() {
+            continue;
+        }
+
+        let file_name = image_file_name(image_path);
+        let image_id = overlay.image_ids.get(&file_name).unwrap_or(&file_name);
+        builder.add_frame(
+            &mut frame,
+            Some(image_id),
+            Some(overlay.annotation_index.get(image_id)),
+            &overlay.category_map,
+            overlay.inner_rad,
+            overlay.outer_rad,
+            None,
+            None,
+        )?;
+    }
+    builder.finish()
+}
@@ -0,0 +1,214 @@
+use crate::config::Config;
+use crate::data::models::{DribbleEventsExport, DribbleLabel, VideoData};
+use crate::utils::event_clips::event_clip_name;
+use crate::utils::frame_source::{frame_number, FrameSource};
+use opencv::core::{Size, Vector};
+use opencv::prelude::*;
+use opencv::{imgcodecs, imgproc};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Directory in the output directory the report is written to
+pub const REPORT_DIR: &str = "report";
+const THUMBNAIL_WIDTH: i32 = 320;
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; } \
+    .gallery { display: flex; flex-wrap: wrap; gap: 1em; } \
+    .event { width: 320px; border: 1px solid #ccc; padding: 0.5em; } \
+    .event img { width: 100%; } \
+    .missing { height: 180px; background: #eee; text-align: center; line-height: 180px; }";
+
+/// Thumbnail and clip of an event, as paths relative to the report.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct EventAssets {
+    thumbnail: Option<String>,
+    clip: Option<String>,
+}
+
+/// Writes a static HTML gallery of the detected events in `export` to
+/// `<output_path>/report/index.html`, with a thumbnail of the middle frame of every event, its
+/// metadata and a link to its clip in `events/`, if event clips were written.
+///
+/// Returns the path of the report.
+pub fn create_report(
+    output_path: &Path,
+    export: &DribbleEventsExport,
+    videos: &[VideoData],
+    config: &Config,
+) -> io::Result<PathBuf> {
+    let report_dir = output_path.join(REPORT_DIR);
+    let thumbnails_dir = report_dir.join("thumbnails");
+    fs::create_dir_all(&thumbnails_dir)?;
+    let videos_by_key: HashMap<String, &VideoData> = videos.iter().map(|v| (v.key(), v)).collect();
+    let clip_format = config.visualization.event_export_format;
+
+    let mut html = String::new();
+    let num_events: usize = export.videos.iter().map(|v| v.dribble_events.len()).sum();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Detected events</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <h1>Detected events</h1>\n<p>{} events in {} videos, generated at {}</p>\n",
+        STYLE,
+        num_events,
+        export.videos.len(),
+        escape_html(&export.info.generated_at)
+    );
+
+    for video in export.videos.iter().filter(|v| !v.dribble_events.is_empty()) {
+        let _ = write!(
+            html,
+            "<h2>{}</h2>\n<div class=\"gallery\">\n",
+            escape_html(&video.video_id)
+        );
+        let mut frame_source = videos_by_key
+            .get(&video.key())
+            .and_then(|video_data| Some((*video_data, FrameSource::new(video_data).ok()?)));
+
+        for (index, label) in video.dribble_events.iter().enumerate() {
+            let mut assets = EventAssets::default();
+
+            let thumbnail_name = format!("{}_{}.jpg", video.video_id, index);
+            if let Some((video_data, source)) = frame_source.as_mut() {
+                let thumbnail_path = thumbnails_dir.join(&thumbnail_name);
+                match write_thumbnail(&thumbnail_path, video_data, source, middle_frame(label)) {
+                    Ok(true) => assets.thumbnail = Some(format!("thumbnails/{}", thumbnail_name)),
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Error writing thumbnail {}: {}", thumbnail_name, e),
+                }
+            }
+
+            let clip_name =
+                event_clip_name(&video.video_id, index, label.detected_tackle, clip_format);
+            if output_path.join("events").join(&clip_name).exists() {
+                assets.clip = Some(format!("../events/{}", clip_name));
+            }
+
+            html.push_str(&event_card(label, &assets));
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+
+    let report_path = report_dir.join("index.html");
+    fs::write(&report_path, html)?;
+    println!("Wrote report of {} events to {}", num_events, report_path.display());
+    Ok(report_path)
+}
+
+fn middle_frame(label: &DribbleLabel) -> u32 {
+    let end_frame = label.end_frame.unwrap_or(label.start_frame);
+    label.start_frame + end_frame.saturating_sub(label.start_frame) / 2
+}
+
+/// Writes frame `frame` of the video, scaled down to the thumbnail width. Returns `false` if
+/// the video has no such frame.
+fn write_thumbnail(
+    path: &Path,
+    video_data: &VideoData,
+    frame_source: &mut FrameSource,
+    frame: u32,
+) -> opencv::Result<bool> {
+    let Some(image_path) = video_data
+        .image_paths
+        .iter()
+        .find(|path| frame_number(path) == Some(frame))
+    else {
+        return Ok(false);
+    };
+    let image = frame_source.read(image_path)?;
+    if image.empty() {
+        return Ok(false);
+    }
+
+    let height = THUMBNAIL_WIDTH * image.rows() / image.cols().max(1);
+    let mut thumbnail = Mat::default();
+    imgproc::resize(
+        &image,
+        &mut thumbnail,
+        Size::new(THUMBNAIL_WIDTH, height),
+        0.0,
+        0.0,
+        imgproc::INTER_AREA,
+    )?;
+    imgcodecs::imwrite(path.to_str().unwrap_or_default(), &thumbnail, &Vector::new())
+}
+
+/// The card of an event in the gallery.
+fn event_card(label: &DribbleLabel, assets: &EventAssets) -> String {
+    let kind = if label.detected_tackle {
+        "Tackle"
+    } else {
+        "Dribble"
+    };
+    let mut card = String::from("<div class=\"event\">\n");
+    match &assets.thumbnail {
+        Some(thumbnail) => {
+            let _ = writeln!(card, "<img src=\"{}\" alt=\"{}\">", escape_html(thumbnail), kind);
+        }
+        None => card.push_str("<div class=\"missing\">No frame</div>\n"),
+    }
+
+    let frames = match label.end_frame {
+        Some(end_frame) => format!("{}-{}", label.start_frame, end_frame),
+        None => label.start_frame.to_string(),
+    };
+    let _ = write!(
+        card,
+        "<b>{} by #{}</b><br>\nFrames {}",
+        kind, label.possession_holder, frames
+    );
+    if let Some(seconds) = label.start_seconds {
+        let _ = write!(card, " ({:.1} s)", seconds);
+    }
+    if let Some(game_time) = &label.start_game_time {
+        let _ = write!(card, "<br>\nGame time {}", escape_html(game_time));
+    }
+    if label.ever_contested {
+        card.push_str("<br>\nContested");
+    }
+    if let Some(possession_id) = label.possession_id {
+        let _ = write!(card, "<br>\nPossession {}", possession_id);
+    }
+    if let Some(clip) = &assets.clip {
+        let _ = write!(card, "<br>\n<a href=\"{}\">Clip</a>", escape_html(clip));
+    }
+    card.push_str("\n</div>\n");
+    card
+}
+
+/// Escapes text for HTML and XML attributes and elements.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dribbling_detection::dribble_models::DribbleEvent;
+
+    #[test]
+    fn test_event_card() {
+        let mut event = DribbleEvent::new(7, 100, "SNGS-001".to_string());
+        event.end_frame = Some(151);
+        event.ever_contested = true;
+        let label = DribbleLabel::from(&event);
+        assert_eq!(middle_frame(&label), 125);
+
+        let assets = EventAssets {
+            thumbnail: Some("thumbnails/SNGS-001_0.jpg".to_string()),
+            clip: None,
+        };
+        assert_eq!(
+            event_card(&label, &assets),
+            "<div class=\"event\">\n<img src=\"thumbnails/SNGS-001_0.jpg\" alt=\"Dribble\">\n\
+             <b>Dribble by #7</b><br>\nFrames 100-151<br>\nContested\n</div>\n"
+        );
+        assert_eq!(escape_html("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+}
@@ -0,0 +1,83 @@
+//! Context around the clip under review. Review mode also shows the frames just before and
+//! after the event, with a border whose color tells whether a frame is part of the event, so
+//! reviewers can judge whether the boundaries are right.
+
+use opencv::core::{Mat, Rect, Scalar};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+/// Seconds shown before and after the event, if not configured
+pub const DEFAULT_ROLL_SECS: f64 = 2.0;
+const BORDER_THICKNESS: i32 = 6;
+
+/// Where a frame is relative to the event under review.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipPhase {
+    Before,
+    Event,
+    After,
+}
+
+impl ClipPhase {
+    /// The phase of `frame` for the event from `start` to `end`, both included.
+    pub fn of(frame: u32, (start, end): (u32, u32)) -> Self {
+        if frame < start {
+            Self::Before
+        } else if frame > end {
+            Self::After
+        } else {
+            Self::Event
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Before => "Before the event",
+            Self::Event => "Event",
+            Self::After => "After the event",
+        }
+    }
+
+    /// Green during the event, gray in the context around it (BGR)
+    fn color(self) -> Scalar {
+        match self {
+            Self::Event => Scalar::new(0.0, 200.0, 0.0, 255.0),
+            Self::Before | Self::After => Scalar::new(128.0, 128.0, 128.0, 255.0),
+        }
+    }
+}
+
+/// Number of frames in `secs` seconds at `frame_rate`.
+pub fn roll_frames(secs: f64, frame_rate: f64) -> u32 {
+    (secs.max(0.0) * frame_rate).round() as u32
+}
+
+/// Draws a border in the color of `phase` around `frame`.
+pub fn draw_phase_border(frame: &mut Mat, phase: ClipPhase) -> opencv::Result<()> {
+    let size = frame.size()?;
+    imgproc::rectangle(
+        frame,
+        Rect::new(0, 0, size.width, size.height),
+        phase.color(),
+        BORDER_THICKNESS,
+        imgproc::LINE_8,
+        0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_phase() {
+        assert_eq!(ClipPhase::of(99, (100, 150)), ClipPhase::Before);
+        assert_eq!(ClipPhase::of(100, (100, 150)), ClipPhase::Event);
+        assert_eq!(ClipPhase::of(150, (100, 150)), ClipPhase::Event);
+        assert_eq!(ClipPhase::of(151, (100, 150)), ClipPhase::After);
+
+        assert_eq!(roll_frames(DEFAULT_ROLL_SECS, 25.0), 50);
+        assert_eq!(roll_frames(0.5, 29.97), 15);
+        assert_eq!(roll_frames(-1.0, 25.0), 0);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::config::DEFAULT_FRAME_RATE;
+use crate::data::models::{ClipTiming, VideoData};
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use crate::utils::frame_source::first_frame_number;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// File format of the subtitles of the detected events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleFormat {
+    /// SubRip, supported by almost every video player
+    Srt,
+    /// WebVTT, for browsers and HTML5 players
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+        }
+    }
+}
+
+/// Writes a subtitle file for every video with detected events to `<output_path>/<video>.srt`
+/// (or `.vtt`), next to the video written in download mode, so players show the events on top
+/// of it.
+///
+/// Returns the number of files written.
+pub fn write_subtitles(
+    output_path: &Path,
+    videos: &[VideoData],
+    events: &HashMap<String, Vec<DribbleEvent>>,
+    format: SubtitleFormat,
+) -> io::Result<usize> {
+    let mut num_files = 0;
+    for video_data in videos {
+        let video_id = video_data.name();
+        let Some(video_events) = events.get(&video_data.key()).filter(|e| !e.is_empty()) else {
+            continue;
+        };
+        let mut timing = ClipTiming::new(&video_data.labels.info);
+        timing.frame_rate = timing.frame_rate.or(Some(DEFAULT_FRAME_RATE));
+        // The written video starts at the first frame of the clip, which is not always frame 1
+        timing.first_frame = first_frame_number(video_data);
+
+        let path = output_path.join(format!("{}.{}", video_id, format.extension()));
+        fs::write(path, render_subtitles(video_events, &timing, format))?;
+        num_files += 1;
+    }
+    Ok(num_files)
+}
+
+/// The subtitles of `events`, one cue per event from its first to the end of its last frame.
+pub fn render_subtitles(
+    events: &[DribbleEvent],
+    timing: &ClipTiming,
+    format: SubtitleFormat,
+) -> String {
+    let mut subtitles = String::new();
+    if format == SubtitleFormat::Vtt {
+        subtitles.push_str("WEBVTT\n\n");
+    }
+    for (index, event) in events.iter().enumerate() {
+        let end_frame = event
+            .end_frame
+            .or(event.frames.last().copied())
+            .unwrap_or(event.start_frame);
+        let start = timing.seconds(event.start_frame).unwrap_or_default();
+        let end = timing.seconds(end_frame + 1).unwrap_or_default();
+        if format == SubtitleFormat::Srt {
+            let _ = writeln!(subtitles, "{}", index + 1);
+        }
+        let _ = writeln!(
+            subtitles,
+            "{} --> {}\n{}\n",
+            timestamp(start, format),
+            timestamp(end, format),
+            subtitle_text(event)
+        );
+    }
+    subtitles
+}
+
+/// Text of the cue of an event, e.g. `Dribble by #7, contested`.
+fn subtitle_text(event: &DribbleEvent) -> String {
+    let kind = if event.detected_tackle {
+        "Tackle"
+    } else {
+        "Dribble"
+    };
+    let mut text = format!("{} by #{}", kind, event.possession_holder);
+    if event.ever_contested {
+        text.push_str(", contested");
+    }
+    text
+}
+
+/// `seconds` as `HH:MM:SS,mmm` for SRT and `HH:MM:SS.mmm` for WebVTT.
+fn timestamp(seconds: f64, format: SubtitleFormat) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    let separator = match format {
+        SubtitleFormat::Srt => ',',
+        SubtitleFormat::Vtt => '.',
+    };
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_subtitles() {
+        let mut event = DribbleEvent::new(7, 121, "video".to_string());
+        event.end_frame = Some(180);
+        event.detected_dribble = true;
+        event.ever_contested = true;
+        let timing = ClipTiming {
+            first_frame: 1,
+            frame_rate: Some(25.0),
+            game_time_start: None,
+            clip_start: None,
+        };
+
+        assert_eq!(
+            render_subtitles(&[event.clone()], &timing, SubtitleFormat::Srt),
+            "1\n00:00:04,800 --> 00:00:07,200\nDribble by #7, contested\n\n"
+        );
+        assert_eq!(
+            render_subtitles(&[event.clone()], &timing, SubtitleFormat::Vtt),
+            "WEBVTT\n\n00:00:04.800 --> 00:00:07.200\nDribble by #7, contested\n\n"
+        );
+        assert_eq!(timestamp(3725.5, SubtitleFormat::Srt), "01:02:05,500");
+
+        // In a clip starting at frame 101, the event starts 20 frames into the video
+        let timing = ClipTiming {
+            first_frame: 101,
+            ..timing
+        };
+        assert_eq!(
+            render_subtitles(&[event], &timing, SubtitleFormat::Srt),
+            "1\n00:00:00,800 --> 00:00:03,200\nDribble by #7, contested\n\n"
+        );
+    }
+
+    #[test]
+    fn test_write_subtitles_of_named_dataset() {
+        let output_dir =
+            std::env::temp_dir().join(format!("subtitles_dataset_{}", std::process::id()));
+        fs::create_dir_all(&output_dir).unwrap();
+        let video_data = VideoData {
+            dir_path: Path::new("inhouse/train/SNGS-001").to_path_buf(),
+            dataset: Some("inhouse".to_string()),
+            ..Default::default()
+        };
+        let mut event = DribbleEvent::new(7, 121, "SNGS-001".to_string());
+        event.end_frame = Some(180);
+        // The events of a run are keyed by `<dataset>/<sequence>`
+        let events = HashMap::from([(video_data.key(), vec![event])]);
+
+        let num_files =
+            write_subtitles(&output_dir, &[video_data], &events, SubtitleFormat::Srt).unwrap();
+        assert_eq!(num_files, 1);
+        assert!(output_dir.join("SNGS-001.srt").exists());
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
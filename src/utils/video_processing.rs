@@ -1,10 +1,14 @@
 use chrono::Utc;
+use opencv::core::Vector;
+use opencv::imgcodecs::imwrite;
 use serde_json::to_writer_pretty;
 use std::fs::{copy, create_dir_all, File};
-use std::io::Result;
+use std::io::{Error, Result};
 use std::path::Path;
 
+use crate::data::layout::{DEFAULT_IMAGE_DIR, DEFAULT_LABELS_FILE_NAME};
 use crate::data::models::{ReviewedVideoData, VideoData};
+use crate::utils::frame_source::FrameSource;
 
 pub fn export_reviewed_data(
     config_output: &Path,
@@ -20,6 +24,14 @@ pub fn export_reviewed_data(
     create_dir_all(&tackles_folder)?;
     create_dir_all(&none_folder)?;
 
+    // Labels, tags and notes of all reviewed clips
+    let events: Vec<_> = all_reviewed_video_data
+        .iter()
+        .flat_map(|r| r.events.iter())
+        .collect();
+    let events_file = File::create(config_output.join(format!("review_events-{}.json", now)))?;
+    to_writer_pretty(events_file, &events)?;
+
     for (idx, reviewed) in all_reviewed_video_data.iter().enumerate() {
         // Export dribble data
         for (vid_idx, video_data) in reviewed.dribble_data.iter().enumerate() {
@@ -42,19 +54,106 @@ pub fn export_reviewed_data(
     Ok(())
 }
 
+/// Writes a clip in the SoccerNet layout, whatever the layout of the dataset it came from.
 fn store_video_data(folder: &Path, video_data: &VideoData) -> Result<()> {
     create_dir_all(folder)?;
-    let img_folder = folder.join("img1");
+    let img_folder = folder.join(DEFAULT_IMAGE_DIR);
     create_dir_all(&img_folder)?;
 
+    // Frames read from a video file are written out as images, like extracted frames
+    let mut frame_source = match video_data.video_path {
+        Some(_) => Some(FrameSource::new(video_data).map_err(Error::other)?),
+        None => None,
+    };
+
     for image_path in &video_data.image_paths {
-        if let Some(name) = image_path.file_name() {
-            copy(image_path, img_folder.join(name))?;
+        let Some(name) = image_path.file_name() else {
+            continue;
+        };
+        match frame_source.as_mut() {
+            Some(frame_source) => {
+                let frame = frame_source.read(image_path).map_err(Error::other)?;
+                let target = img_folder.join(name);
+                imwrite(target.to_str().unwrap(), &frame, &Vector::new()).map_err(Error::other)?;
+            }
+            None => {
+                copy(image_path, img_folder.join(name))?;
+            }
         }
     }
 
-    let labels_file = folder.join("Labels-GameState.json");
+    let labels_file = folder.join(DEFAULT_LABELS_FILE_NAME);
     let file = File::create(labels_file)?;
     to_writer_pretty(file, &video_data.labels)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::Image;
+    use crate::pipeline::filter_video_data;
+    use opencv::core::{mean, Mat, Scalar, Size, CV_8UC3};
+    use opencv::imgcodecs::imread;
+    use opencv::prelude::*;
+    use opencv::videoio::VideoWriter;
+    use std::fs::remove_dir_all;
+
+    #[test]
+    fn test_store_clip_from_video() {
+        let dir = std::env::temp_dir().join(format!("store_clip_{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let video_path = dir.join("video.avi");
+
+        // Frame n of the video is filled with the brightness 40 * n
+        let size = Size::new(64, 48);
+        let fourcc = VideoWriter::fourcc('M', 'J', 'P', 'G').unwrap();
+        let mut writer =
+            VideoWriter::new(video_path.to_str().unwrap(), fourcc, 25.0, size, true).unwrap();
+        for n in 1..=6 {
+            let value = f64::from(40 * n);
+            let frame = Mat::new_size_with_default(size, CV_8UC3, Scalar::all(value)).unwrap();
+            writer.write(&frame).unwrap();
+        }
+        writer.release().unwrap();
+
+        let mut video_data = VideoData {
+            dir_path: dir.join("SNGS-001"),
+            video_path: Some(video_path),
+            ..Default::default()
+        };
+        for n in 1..=6 {
+            let file_name = format!("{:06}.jpg", n);
+            video_data
+                .image_paths
+                .push(dir.join("img1").join(&file_name));
+            video_data.labels.images.push(Image {
+                is_labeled: true,
+                image_id: n.to_string(),
+                file_name,
+                height: 48,
+                width: 64,
+                has_labeled_person: None,
+                has_labeled_pitch: None,
+            });
+        }
+
+        let clip = filter_video_data(video_data, 4, 5);
+        let clip_folder = dir.join("clip");
+        store_video_data(&clip_folder, &clip).unwrap();
+
+        for n in [4, 5] {
+            let path = clip_folder
+                .join(DEFAULT_IMAGE_DIR)
+                .join(format!("{:06}.jpg", n));
+            let frame = imread(path.to_str().unwrap(), opencv::imgcodecs::IMREAD_COLOR).unwrap();
+            let brightness = mean(&frame, &Mat::default()).unwrap()[0];
+            assert!(
+                (brightness - f64::from(40 * n)).abs() < 10.0,
+                "{}",
+                brightness
+            );
+        }
+        remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use opencv::core::{Mat, Rect, Size};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+/// Zoom factor of a single zoom step
+const ZOOM_STEP: f64 = 1.5;
+const MAX_ZOOM: f64 = 8.0;
+/// Part of the visible area a single pan step moves
+const PAN_STEP: f64 = 0.2;
+
+/// Zoomed in area of the display window. The center is relative to the frame, from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    zoom: f64,
+    center: (f64, f64),
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            center: (0.5, 0.5),
+        }
+    }
+}
+
+impl Viewport {
+    pub fn is_zoomed(&self) -> bool {
+        self.zoom > 1.0
+    }
+
+    /// Zooms in for positive `steps` and out for negative ones, around the center of the
+    /// visible area.
+    pub fn zoom(&mut self, steps: i32) {
+        self.zoom = (self.zoom * ZOOM_STEP.powi(steps)).clamp(1.0, MAX_ZOOM);
+        self.clamp_center();
+    }
+
+    /// Moves the visible area by `dx` and `dy` steps, e.g. `(1, 0)` to the right.
+    pub fn pan(&mut self, dx: i32, dy: i32) {
+        let step = PAN_STEP / self.zoom;
+        self.center.0 += dx as f64 * step;
+        self.center.1 += dy as f64 * step;
+        self.clamp_center();
+    }
+
+    /// Keeps the visible area within the frame
+    fn clamp_center(&mut self) {
+        let half = 0.5 / self.zoom;
+        self.center.0 = self.center.0.clamp(half, 1.0 - half);
+        self.center.1 = self.center.1.clamp(half, 1.0 - half);
+    }
+
+    /// The visible area of a frame of `frame_size`.
+    pub fn crop_rect(&self, frame_size: Size) -> Rect {
+        let width = ((frame_size.width as f64 / self.zoom).round() as i32).max(1);
+        let height = ((frame_size.height as f64 / self.zoom).round() as i32).max(1);
+        let x = (self.center.0 * frame_size.width as f64 - width as f64 / 2.0).round() as i32;
+        let y = (self.center.1 * frame_size.height as f64 - height as f64 / 2.0).round() as i32;
+        Rect::new(
+            x.clamp(0, frame_size.width - width),
+            y.clamp(0, frame_size.height - height),
+            width,
+            height,
+        )
+    }
+
+    /// Crops `frame` to the visible area and resizes it back to the size of `frame`.
+    pub fn apply(&self, frame: &Mat) -> opencv::Result<Mat> {
+        if !self.is_zoomed() {
+            return Ok(frame.clone());
+        }
+        let frame_size = frame.size()?;
+        let crop = Mat::roi(frame, self.crop_rect(frame_size))?;
+        let mut zoomed = Mat::default();
+        imgproc::resize(
+            &crop,
+            &mut zoomed,
+            frame_size,
+            0.0,
+            0.0,
+            imgproc::INTER_LINEAR,
+        )?;
+        Ok(zoomed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_zoom_and_pan() {
+        let frame_size = Size::new(1920, 1080);
+        let mut viewport = Viewport::default();
+        assert_eq!(
+            viewport.crop_rect(frame_size),
+            Rect::new(0, 0, 1920, 1080)
+        );
+        // Panning does nothing without zoom
+        viewport.pan(1, 0);
+        assert!(!viewport.is_zoomed());
+        assert_eq!(viewport.crop_rect(frame_size).x, 0);
+
+        viewport.zoom(2);
+        assert!(viewport.is_zoomed());
+        assert_eq!(
+            viewport.crop_rect(frame_size),
+            Rect::new(534, 300, 853, 480)
+        );
+
+        // The visible area stops at the edges of the frame
+        for _ in 0..20 {
+            viewport.pan(-1, 1);
+        }
+        assert_eq!(
+            viewport.crop_rect(frame_size),
+            Rect::new(0, 600, 853, 480)
+        );
+
+        viewport.zoom(10);
+        assert_eq!(viewport.crop_rect(frame_size).width, 240);
+        viewport.zoom(-10);
+        assert_eq!(viewport, Viewport::default());
+    }
+}
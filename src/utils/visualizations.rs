@@ -1,27 +1,174 @@
 use crate::data::models::Annotation;
-use crate::config::Config;
-use opencv::{core::Mat, highgui, prelude::*, videoio::VideoWriter};
+use crate::config::{Config, VideoMode, VisualizationConfig, DEFAULT_FRAME_RATE};
+use crate::dribbling_detection::dribble_models::DribbleEvent;
+use opencv::{core::Mat, core::Vector, highgui, imgcodecs, prelude::*, videoio::VideoWriter};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
 use std::{
-    fs,
+    env, fs,
     path::{Path, PathBuf},
 };
 
-use super::annotations::draw_annotations;
+use super::annotations::{
+    draw_annotations, draw_frame_overlay, draw_minimap, draw_possession_indicator,
+};
+use super::clip_list::ClipList;
+use super::colors::ColorTheme;
+use super::comparison::{draw_comparison, ComparisonFrame};
+use super::draw_style::DrawStyle;
+use super::graphs::{FrameGraphs, DEFAULT_GRAPH_FRAMES};
+use super::hud::{draw_hud, HudInfo};
 use super::image_calculations::scale_frame;
+use super::layout::{
+    compose_dashboard, EventLog, Layout, EVENT_LOG_HEIGHT, EVENT_LOG_WINDOW_NAME,
+    MINIMAP_WINDOW_NAME,
+};
+use super::overlay_layers::OverlayLayerWriter;
+use super::review_context::draw_phase_border;
+use super::viewport::Viewport;
+
+/// Codec of the videos if `video_codec` is not set
+pub const DEFAULT_VIDEO_CODEC: &str = "MJPG";
+/// Container of the videos if `video_extension` is not set
+pub const DEFAULT_VIDEO_EXTENSION: &str = "avi";
+
+/// Name of the window frames are shown in, in display mode
+const WINDOW_NAME: &str = "Image Sequence Visualization";
+
+/// Directory in the output directory that snapshots of the window are saved to
+const SNAPSHOT_DIR: &str = "snapshots";
+
+/// Codecs tried for a container if the configured one is not available, in order
+const FALLBACK_CODECS: [(&str, &[&str]); 3] = [
+    ("mp4", &["avc1", "H264", "mp4v"]),
+    ("mkv", &["H264", "X264", "mp4v"]),
+    ("avi", &["MJPG", "XVID"]),
+];
+
+/// Container and codec of the videos written in download mode.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoFormat {
+    /// Four character code of the codec, e.g. "avc1" for H.264
+    pub codec: String,
+    /// File extension, which selects the container
+    pub extension: String,
+}
+
+impl VideoFormat {
+    pub fn new(codec: &str, extension: &str) -> Self {
+        Self {
+            codec: codec.to_string(),
+            extension: extension.trim_start_matches('.').to_lowercase(),
+        }
+    }
+
+    pub fn from_config(config: &VisualizationConfig) -> Self {
+        Self::new(
+            config.video_codec.as_deref().unwrap_or(DEFAULT_VIDEO_CODEC),
+            config
+                .video_extension
+                .as_deref()
+                .unwrap_or(DEFAULT_VIDEO_EXTENSION),
+        )
+    }
+
+    /// The formats to try when opening a writer: this one, the other codecs of its container,
+    /// and finally MJPG in an `.avi`, which every OpenCV build with video support can write.
+    pub fn candidates(&self) -> Vec<VideoFormat> {
+        let mut candidates = vec![self.clone()];
+        let fallbacks = FALLBACK_CODECS
+            .iter()
+            .filter(|(extension, _)| *extension == self.extension)
+            .flat_map(|(extension, codecs)| codecs.iter().map(|c| Self::new(c, extension)))
+            .chain([Self::new(DEFAULT_VIDEO_CODEC, DEFAULT_VIDEO_EXTENSION)]);
+        for format in fallbacks {
+            if !candidates.contains(&format) {
+                candidates.push(format);
+            }
+        }
+        candidates
+    }
+
+    /// The codec as the integer code expected by `VideoWriter`.
+    pub fn fourcc(&self) -> opencv::Result<i32> {
+        let chars: Vec<char> = self.codec.chars().collect();
+        let [a, b, c, d] = chars[..] else {
+            return Err(opencv::Error::new(
+                opencv::core::StsBadArg,
+                format!(
+                    "Invalid video_codec \"{}\": a codec is a four character code like \"avc1\"",
+                    self.codec
+                ),
+            ));
+        };
+        VideoWriter::fourcc(a, b, c, d)
+    }
+}
+
+impl std::fmt::Display for VideoFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in .{}", self.codec, self.extension)
+    }
+}
+
+/// Returns `true` if a window can be opened. On Linux this needs an X11 or Wayland display,
+/// without one `highgui` aborts the process.
+pub fn display_available() -> bool {
+    if !cfg!(target_os = "linux") {
+        return true;
+    }
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| env::var_os(var).is_some_and(|value| !value.is_empty()))
+}
 
 /// A builder to handle video creation or visualization,
 /// allowing you to add frames, one at a time.
+///
+/// Rendering the overlay is independent of showing it, so `"download"` mode never touches
+/// `highgui` and works on servers without a display.
 pub struct VisualizationBuilder<'a> {
-    mode: &'a str,
+    mode: VideoMode,
+    file_name: &'a str,
+    /// A window is shown, only in display mode and if a display is available
+    show_window: bool,
+    /// Path of the video without the extension, which depends on the format that is used
     output_path: PathBuf,
-    config: &'a Config,
+    format: VideoFormat,
+    /// Frame rate of the written videos
+    fps: f64,
+    /// The run's config, with the `[visualization]` section of the config file once it was
+    /// reloaded
+    config: Cow<'a, Config>,
     writer: Option<VideoWriter>,
+    layer_writer: Option<OverlayLayerWriter>,
     frame_count: usize,
+    /// Zoomed in area of the window in display mode
+    viewport: Viewport,
+    /// The last frame shown in the window before zooming, to show it again when the zoom
+    /// changes
+    shown_frame: Option<Mat>,
+    /// Frame number of `shown_frame`, used in the name of its snapshot
+    shown_frame_number: u32,
+    /// Mouse wheel steps since the last call of `take_wheel_steps`. `None` until the mouse
+    /// callback of the window is set.
+    wheel_steps: Option<Arc<AtomicI32>>,
+    /// Position of the last left click in the window that was not handled yet
+    click: Option<Arc<Mutex<Option<(i32, i32)>>>>,
+    /// The clips of the video in review mode, shown right of the camera view in the window
+    clip_list: Option<ClipList>,
+    /// Pressure and speed graphs of the last frames, if they are enabled
+    graphs: Option<FrameGraphs>,
+    /// Events detected so far, shown with the `windows` and `dashboard` layouts
+    event_log: EventLog,
+    /// The minimap of the last frame, shown in its own window with the `windows` layout
+    minimap: Option<Mat>,
 }
 
 impl<'a> VisualizationBuilder<'a> {
-    pub fn new(mode: &'a str, file_name: &'a str, config: &'a Config) -> opencv::Result<Self> {
+    pub fn new(mode: VideoMode, file_name: &'a str, config: &'a Config) -> opencv::Result<Self> {
         let output_dir_path = Path::new(&config.data.output_path);
 
         if !output_dir_path.exists() {
@@ -33,21 +180,90 @@ impl<'a> VisualizationBuilder<'a> {
             })?;
         }
 
-        let output_path = output_dir_path.join(format!("{}.avi", file_name));
+        let output_path = output_dir_path.join(file_name);
+
+        // In "layers" mode the overlay is exported as transparent PNGs instead of a video
+        let layer_writer = if mode == VideoMode::Layers {
+            Some(OverlayLayerWriter::new(output_dir_path, file_name)?)
+        } else {
+            None
+        };
 
         // println!(
         //     "\nCreating visualization for {file_name}, Output path: {}",
         //     output_path.display()
         // );
+        let show_window = mode == VideoMode::Display && display_available();
+        if mode == VideoMode::Display && !show_window {
+            eprintln!("No display available, frames of {} are not shown", file_name);
+        }
+
         Ok(Self {
             mode,
+            file_name,
+            show_window,
             output_path: output_path.to_path_buf(),
-            config,
+            format: VideoFormat::from_config(&config.visualization),
+            fps: DEFAULT_FRAME_RATE,
+            config: Cow::Borrowed(config),
             writer: None,
+            layer_writer,
             frame_count: 0,
+            viewport: Viewport::default(),
+            shown_frame: None,
+            shown_frame_number: 0,
+            wheel_steps: None,
+            click: None,
+            clip_list: None,
+            graphs: frame_graphs(&config.visualization),
+            event_log: EventLog::default(),
+            minimap: None,
         })
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Replaces the `[visualization]` section, e.g. after the config file changed. The new
+    /// settings are used from the next frame on.
+    pub fn set_visualization(&mut self, visualization: VisualizationConfig) {
+        let current = &self.config.visualization;
+        let graphs_changed = current.graphs != visualization.graphs
+            || current.graph_frames != visualization.graph_frames;
+        if graphs_changed {
+            self.graphs = frame_graphs(&visualization);
+        }
+        self.config.to_mut().visualization = visualization;
+    }
+
+    /// Writes the videos at the frame rate of the sequence, so they play at the original speed
+    /// and line up with the exported subtitles.
+    pub fn with_frame_rate(mut self, frame_rate: f32) -> Self {
+        if frame_rate > 0.0 {
+            self.fps = frame_rate as f64;
+        }
+        self
+    }
+
+    /// The layout of the frames. Only a window can show the `windows` layout, videos use the
+    /// `dashboard` layout instead.
+    fn layout(&self) -> Layout {
+        match self.config.visualization.layout {
+            Layout::Windows if !self.show_window => Layout::Dashboard,
+            layout => layout,
+        }
+    }
+
+    /// Adds a detected event to the event log.
+    pub fn log_event(&mut self, event: &DribbleEvent) {
+        self.event_log.push(event);
+    }
+
+    /// Draws the annotations onto `frame` and shows or writes it, see
+    /// [`VisualizationBuilder::render_frame`]. In layers mode the overlay is written as
+    /// separate layers instead.
+    #[allow(clippy::too_many_arguments)]
     pub fn add_frame(
         &mut self,
         frame: &mut Mat,
@@ -56,50 +272,357 @@ impl<'a> VisualizationBuilder<'a> {
         categories: &HashMap<String, u32>,
         inner_rad: f64,
         outer_rad: f64,
+        hud: Option<&HudInfo>,
+        comparison: Option<ComparisonFrame>,
     ) -> opencv::Result<()> {
         if frame.empty() {
             eprintln!("Warning: Empty frame was provided.");
             return Ok(());
         }
 
-        scale_frame(frame, self.config)?;
+        if let Some(layer_writer) = &self.layer_writer {
+            if let (Some(id), Some(ann)) = (image_id, annotations) {
+                layer_writer.write_frame(
+                    self.frame_count + 1,
+                    frame.size()?,
+                    id,
+                    ann,
+                    categories,
+                    &self.config,
+                    inner_rad,
+                    outer_rad,
+                    hud,
+                )?;
+            }
+            self.frame_count += 1;
+            return Ok(());
+        }
+
+        self.render_frame(
+            frame,
+            image_id,
+            annotations,
+            categories,
+            inner_rad,
+            outer_rad,
+            hud,
+            comparison,
+        )?;
+        self.shown_frame_number = hud.map_or(self.frame_count as u32 + 1, |hud| hud.frame_number);
+        self.output_frame(frame)?;
+
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Draws the annotations, minimap, possession indicator, HUD and graphs onto `frame`
+    /// without showing or writing it, arranged by the configured [`Layout`]. With
+    /// `comparison`, the ground truth and detector output are shown side by side, see
+    /// [`draw_comparison`], which always uses the stacked layout.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_frame(
+        &mut self,
+        frame: &mut Mat,
+        image_id: Option<&str>,
+        annotations: Option<&[Annotation]>,
+        categories: &HashMap<String, u32>,
+        inner_rad: f64,
+        outer_rad: f64,
+        hud: Option<&HudInfo>,
+        comparison: Option<ComparisonFrame>,
+    ) -> opencv::Result<()> {
+        scale_frame(frame, &self.config)?;
+        // Drawn before the minimap is added below the frame, so the corners are those of the
+        // camera view
+        if let Some(hud) = hud {
+            if self.config.visualization.hud.unwrap_or(false) {
+                draw_hud(frame, &hud.lines(), self.config.visualization.hud_corner)?;
+            } else if hud.review_status.is_some() || hud.review_phase.is_some() {
+                draw_hud(frame, &hud.review_lines(), self.config.visualization.hud_corner)?;
+            }
+            if let Some(phase) = hud.review_phase {
+                draw_phase_border(frame, phase)?;
+            }
+            if let (Some(id), Some(ann)) = (image_id, annotations) {
+                self.draw_possession(frame, id, ann, categories, hud)?;
+            }
+        }
 
-        if let (Some(id), Some(ann)) = (image_id, annotations) {
-            draw_annotations(
+        match (image_id, annotations, comparison) {
+            (Some(id), Some(ann), Some(comparison)) => draw_comparison(
                 frame,
                 ann,
                 categories,
-                &id,
-                self.config,
+                id,
+                &self.config,
                 inner_rad,
                 outer_rad,
-            )?;
+                comparison,
+            )?,
+            (Some(id), Some(ann), None) if self.layout() == Layout::Stacked => {
+                draw_annotations(
+                    frame,
+                    ann,
+                    categories,
+                    id,
+                    &self.config,
+                    inner_rad,
+                    outer_rad,
+                )?
+            }
+            (Some(id), Some(ann), None) => {
+                let annotations: Vec<Annotation> = ann
+                    .iter()
+                    .filter(|ann| ann.image_id == *id)
+                    .cloned()
+                    .collect();
+                draw_frame_overlay(
+                    frame,
+                    &annotations,
+                    categories,
+                    &self.config,
+                    self.config.visualization.scale_factor,
+                    inner_rad,
+                    outer_rad,
+                )?;
+                // The graphs stay in the camera view, instead of covering the event log
+                self.draw_graphs(frame, hud, inner_rad, outer_rad)?;
+                let minimap = draw_minimap(
+                    &annotations,
+                    categories,
+                    &self.config,
+                    frame.typ(),
+                    inner_rad,
+                    outer_rad,
+                )?;
+                if self.layout() == Layout::Dashboard {
+                    *frame = compose_dashboard(frame, &minimap, &self.event_log)?;
+                } else {
+                    self.minimap = Some(minimap);
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        // Drawn last, so the graphs are in the corner of the frame with the minimap below it
+        self.draw_graphs(frame, hud, inner_rad, outer_rad)
+    }
+
+    /// Adds the values of `hud` to the graphs and draws them onto `frame`, if they are
+    /// enabled.
+    fn draw_graphs(
+        &mut self,
+        frame: &mut Mat,
+        hud: Option<&HudInfo>,
+        inner_rad: f64,
+        outer_rad: f64,
+    ) -> opencv::Result<()> {
+        if let (Some(graphs), Some(hud)) = (self.graphs.as_mut(), hud) {
+            graphs.push(hud);
+            let theme = ColorTheme::from_config(self.config.visualization.colors.as_ref());
+            graphs.draw(frame, inner_rad, outer_rad, &theme)?;
         }
+        Ok(())
+    }
 
+    /// Draws the possession indicator of the holder in `hud` onto the scaled `frame`, if it is
+    /// enabled.
+    fn draw_possession(
+        &self,
+        frame: &mut Mat,
+        image_id: &str,
+        annotations: &[Annotation],
+        categories: &HashMap<String, u32>,
+        hud: &HudInfo,
+    ) -> opencv::Result<()> {
+        let Some(holder_id) = hud.possession_holder else {
+            return Ok(());
+        };
+        if !self.config.visualization.possession_indicator.unwrap_or(true) {
+            return Ok(());
+        }
+        let annotations: Vec<Annotation> = annotations
+            .iter()
+            .filter(|ann| ann.image_id == *image_id)
+            .cloned()
+            .collect();
+        let style = DrawStyle::from_config(&self.config.visualization, frame.rows());
+        draw_possession_indicator(
+            frame,
+            &annotations,
+            categories,
+            holder_id,
+            hud.is_contested(),
+            &ColorTheme::from_config(self.config.visualization.colors.as_ref()),
+            &style,
+            self.config.visualization.scale_factor,
+        )
+    }
+
+    /// Writes a rendered frame in download mode, or shows it in display mode.
+    fn output_frame(&mut self, frame: &Mat) -> opencv::Result<()> {
         match self.mode {
-            "download" => {
+            VideoMode::Download => {
                 if self.writer.is_none() {
-                    self.writer = Some(initialize_writer(&self.output_path, frame)?);
+                    self.writer = Some(initialize_writer(
+                        &self.output_path,
+                        &self.format,
+                        self.fps,
+                        frame,
+                    )?);
                 }
                 if let Some(ref mut writer) = self.writer {
                     writer.write(frame)?;
                 }
             }
-            _ => {
-                // Show frame, but do not initialize the writer at all
-                highgui::imshow("Image Sequence Visualization", frame)?;
+            // Show frame, but do not initialize the writer at all
+            _ if self.show_window => {
+                self.shown_frame = Some(frame.clone());
+                self.show_zoomed()?;
+                if self.layout() == Layout::Windows {
+                    self.show_side_windows(frame.typ())?;
+                }
             }
+            _ => {}
         }
+        Ok(())
+    }
 
-        self.frame_count += 1;
+    /// Shows the last frame in the window, zoomed in to the viewport.
+    fn show_zoomed(&mut self) -> opencv::Result<()> {
+        let Some(frame) = &self.shown_frame else {
+            return Ok(());
+        };
+        let mut shown = self.viewport.apply(frame)?;
+        // The list is not zoomed, so it stays readable and clickable
+        if let Some(clip_list) = self.clip_list.as_mut() {
+            let list = clip_list.draw(shown.rows(), shown.typ())?;
+            let mut with_list = Mat::default();
+            opencv::core::hconcat2(&shown, &list, &mut with_list)?;
+            shown = with_list;
+        }
+        highgui::imshow(WINDOW_NAME, &shown)?;
+
+        // The window exists once a frame was shown
+        if self.wheel_steps.is_none() {
+            let wheel_steps = Arc::new(AtomicI32::new(0));
+            let callback_steps = Arc::clone(&wheel_steps);
+            let click = Arc::new(Mutex::new(None));
+            let callback_click = Arc::clone(&click);
+            highgui::set_mouse_callback(
+                WINDOW_NAME,
+                Some(Box::new(move |event, x, y, flags| {
+                    if event == highgui::EVENT_MOUSEWHEEL {
+                        let delta = highgui::get_mouse_wheel_delta(flags).unwrap_or(0);
+                        callback_steps.fetch_add(delta.signum(), Ordering::Relaxed);
+                    } else if event == highgui::EVENT_LBUTTONDOWN {
+                        *callback_click.lock().unwrap() = Some((x, y));
+                    }
+                })),
+            )?;
+            self.wheel_steps = Some(wheel_steps);
+            self.click = Some(click);
+        }
         Ok(())
     }
 
+    /// Shows the minimap of the last frame and the event log in their own windows.
+    fn show_side_windows(&self, typ: i32) -> opencv::Result<()> {
+        if let Some(minimap) = &self.minimap {
+            highgui::imshow(MINIMAP_WINDOW_NAME, minimap)?;
+        }
+        let log_panel = self.event_log.draw(
+            self.config.visualization.minimap_width,
+            EVENT_LOG_HEIGHT,
+            typ,
+        )?;
+        highgui::imshow(EVENT_LOG_WINDOW_NAME, &log_panel)
+    }
+
+    /// Saves the frame as currently shown in the window, zoomed in to the viewport, to
+    /// `<output>/snapshots/<video>_<frame>.png`. Returns the path, or `None` if no frame was
+    /// shown yet.
+    pub fn save_snapshot(&self) -> opencv::Result<Option<PathBuf>> {
+        let Some(frame) = &self.shown_frame else {
+            return Ok(None);
+        };
+        let path = snapshot_path(
+            Path::new(&self.config.data.output_path),
+            self.file_name,
+            self.shown_frame_number,
+        );
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                opencv::Error::new(
+                    opencv::core::StsError,
+                    format!("Failed to create snapshot directory: {}", e),
+                )
+            })?;
+        }
+        let shown = self.viewport.apply(frame)?;
+        if !imgcodecs::imwrite(path.to_str().unwrap_or_default(), &shown, &Vector::new())? {
+            return Err(opencv::Error::new(
+                opencv::core::StsError,
+                format!("Failed to write {}", path.display()),
+            ));
+        }
+        Ok(Some(path))
+    }
+
+    pub fn is_zoomed(&self) -> bool {
+        self.viewport.is_zoomed()
+    }
+
+    /// Zooms the window in for positive `steps` and out for negative ones, and shows the
+    /// current frame again.
+    pub fn zoom(&mut self, steps: i32) -> opencv::Result<()> {
+        self.viewport.zoom(steps);
+        self.show_zoomed()
+    }
+
+    /// Moves the zoomed in area of the window, see [`Viewport::pan`].
+    pub fn pan(&mut self, dx: i32, dy: i32) -> opencv::Result<()> {
+        self.viewport.pan(dx, dy);
+        self.show_zoomed()
+    }
+
+    /// Mouse wheel steps since the last call, positive for scrolling up. Mouse events are
+    /// only received while `highgui::wait_key` is waiting.
+    pub fn take_wheel_steps(&self) -> i32 {
+        self.wheel_steps
+            .as_ref()
+            .map_or(0, |steps| steps.swap(0, Ordering::Relaxed))
+    }
+
+    /// Shows `clip_list` right of the camera view in the window.
+    pub fn set_clip_list(&mut self, clip_list: ClipList) {
+        self.clip_list = Some(clip_list);
+    }
+
+    pub fn clip_list(&self) -> Option<&ClipList> {
+        self.clip_list.as_ref()
+    }
+
+    pub fn clip_list_mut(&mut self) -> Option<&mut ClipList> {
+        self.clip_list.as_mut()
+    }
+
+    /// Index of the clip of the clip list that was clicked since the last call, if any.
+    /// Clicks on the camera view are ignored.
+    pub fn take_clicked_clip(&self) -> Option<usize> {
+        let (x, y) = self.click.as_ref()?.lock().unwrap().take()?;
+        let camera_width = self.shown_frame.as_ref()?.cols();
+        if x < camera_width {
+            return None;
+        }
+        self.clip_list.as_ref()?.clip_at(y)
+    }
+
+    /// Releases the video of the current clip. The next frame starts a new video.
     pub fn finish(&mut self) -> opencv::Result<()> {
-        if self.mode == "download" {
-            if let Some(ref mut writer) = self.writer {
-                writer.release()?;
-            }
+        if let Some(mut writer) = self.writer.take() {
+            writer.release()?;
         }
         // else {
         //     eprintln!("No video writer was used for mode: {}", self.mode);
@@ -108,36 +631,104 @@ impl<'a> VisualizationBuilder<'a> {
     }
 }
 
-fn initialize_writer(video_path: &Path, frame: &opencv::core::Mat) -> opencv::Result<VideoWriter> {
-    let frame_size = frame.size()?;
+/// Path of the snapshot of frame `frame_number` of the video `file_name`.
+pub fn snapshot_path(output_dir: &Path, file_name: &str, frame_number: u32) -> PathBuf {
+    output_dir
+        .join(SNAPSHOT_DIR)
+        .join(format!("{}_{}.png", file_name, frame_number))
+}
 
-    if frame_size.width > 0 && frame_size.height > 0 {
-        // println!("Initializing writer for path: {}", video_path.display());
-        let writer = VideoWriter::new(
-            video_path.to_str().unwrap(),
-            // Use a codec that is more widely supported, e.g., 'MJPG'
-            opencv::videoio::VideoWriter::fourcc('M', 'J', 'P', 'G')?,
-            20.0,
-            frame_size,
-            true,
-        )?;
-        if !writer
-            .is_opened()
-            .expect("failed to check if writer is opened")
-        {
-            eprintln!(
-                "VideoWriter failed to open for path: {}",
-                video_path.display()
-            );
-        }
-        Ok(writer)
-    } else {
-        Err(opencv::Error::new(
+/// The pressure and speed graphs, if they are enabled.
+fn frame_graphs(visualization: &VisualizationConfig) -> Option<FrameGraphs> {
+    visualization
+        .graphs
+        .unwrap_or(false)
+        .then(|| FrameGraphs::new(visualization.graph_frames.unwrap_or(DEFAULT_GRAPH_FRAMES)))
+}
+
+/// Opens a writer for `<base_path>.<extension>`, falling back to the other candidates of
+/// `format` if its codec is not available in the OpenCV build.
+fn initialize_writer(
+    base_path: &Path,
+    format: &VideoFormat,
+    fps: f64,
+    frame: &opencv::core::Mat,
+) -> opencv::Result<VideoWriter> {
+    let frame_size = frame.size()?;
+    if frame_size.width <= 0 || frame_size.height <= 0 {
+        return Err(opencv::Error::new(
             opencv::core::StsError,
             format!(
                 "Frame size is zero, skipping writer initialization for path: {}",
-                video_path.display()
+                base_path.display()
             ),
-        ))
+        ));
+    }
+
+    let mut tried = Vec::new();
+    for (i, candidate) in format.candidates().into_iter().enumerate() {
+        let mut video_path = base_path.as_os_str().to_owned();
+        video_path.push(format!(".{}", candidate.extension));
+        let video_path = PathBuf::from(video_path);
+
+        // Some backends fail to open instead of returning an error
+        let writer = VideoWriter::new(
+            video_path.to_str().unwrap_or_default(),
+            candidate.fourcc()?,
+            fps,
+            frame_size,
+            true,
+        );
+        match writer {
+            Ok(writer) if writer.is_opened().unwrap_or(false) => {
+                if i > 0 {
+                    eprintln!(
+                        "Codec {} is not available, writing {} instead",
+                        format,
+                        video_path.display()
+                    );
+                }
+                return Ok(writer);
+            }
+            _ => tried.push(candidate.to_string()),
+        }
+    }
+
+    Err(opencv::Error::new(
+        opencv::core::StsError,
+        format!(
+            "None of the video codecs could be opened for {} (tried {}). H.264 needs an OpenCV \
+             build with FFmpeg or GStreamer, see `cv::getBuildInformation()`.",
+            base_path.display(),
+            tried.join(", ")
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_format_candidates() {
+        let format = VideoFormat::new("avc1", ".MP4");
+        assert_eq!(format.extension, "mp4");
+        let candidates: Vec<String> = format.candidates().iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            candidates,
+            vec!["avc1 in .mp4", "H264 in .mp4", "mp4v in .mp4", "MJPG in .avi"]
+        );
+
+        let default = VideoFormat::new(DEFAULT_VIDEO_CODEC, DEFAULT_VIDEO_EXTENSION);
+        assert_eq!(default.candidates().len(), 2);
+        assert!(VideoFormat::new("h264x", "mp4").fourcc().is_err());
+    }
+
+    #[test]
+    fn test_snapshot_path() {
+        assert_eq!(
+            snapshot_path(Path::new("output"), "SNMOT-116", 42),
+            Path::new("output/snapshots/SNMOT-116_42.png")
+        );
     }
 }
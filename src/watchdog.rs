@@ -0,0 +1,228 @@
+//! Per-video timeouts, so a corrupted sequence with a pathological number of annotations can
+//! not hang a worker forever. Videos that run out of time are stopped, their events so far
+//! are kept and the incident is reported in `failures.json`.
+
+use crate::data::models::ExportInfo;
+use crate::pipeline::CancellationToken;
+use crate::progress::ProgressSender;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Name of the failures report in the output directory
+pub const FAILURES_FILE: &str = "failures.json";
+
+/// How often the watchdog checks the deadlines
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A video that was stopped before all its frames were processed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoFailure {
+    pub video_id: String,
+    pub reason: String,
+    pub timeout_secs: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FailuresExport {
+    pub info: ExportInfo,
+    pub failures: Vec<VideoFailure>,
+}
+
+struct WatchedVideo {
+    name: String,
+    deadline: Instant,
+    expired: CancellationToken,
+}
+
+#[derive(Default)]
+struct WatchdogState {
+    videos: HashMap<usize, WatchedVideo>,
+    failures: Vec<VideoFailure>,
+}
+
+/// Stops videos that take longer than the timeout. A thread checks the deadlines of the
+/// videos that are being processed and expires the [`VideoWatch`] of those that are late,
+/// which makes their detection loop stop after the current frame.
+pub struct Watchdog {
+    timeout: Option<Duration>,
+    state: Arc<Mutex<WatchdogState>>,
+    next_id: AtomicUsize,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Starts a watchdog that gives every video `timeout` to finish. Timeouts are reported to
+    /// `progress`.
+    pub fn start(timeout: Duration, progress: ProgressSender) -> Self {
+        let state = Arc::new(Mutex::new(WatchdogState::default()));
+        let (stop, stopped) = mpsc::channel();
+        let handle = {
+            let state = Arc::clone(&state);
+            thread::spawn(move || loop {
+                match stopped.recv_timeout(POLL_INTERVAL.min(timeout)) {
+                    Err(RecvTimeoutError::Timeout) => {
+                        expire_late_videos(&state, timeout, &progress)
+                    }
+                    _ => break,
+                }
+            })
+        };
+        Self {
+            timeout: Some(timeout),
+            state,
+            next_id: AtomicUsize::new(0),
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// A watchdog without a timeout, whose videos never expire.
+    pub fn disabled() -> Self {
+        Self {
+            timeout: None,
+            state: Arc::default(),
+            next_id: AtomicUsize::new(0),
+            stop: None,
+            handle: None,
+        }
+    }
+
+    /// Starts the timeout of a video. The video is no longer watched once the returned handle
+    /// is dropped.
+    pub fn watch(&self, name: &str) -> VideoWatch<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let expired = CancellationToken::new();
+        if let Some(timeout) = self.timeout {
+            self.state.lock().unwrap().videos.insert(
+                id,
+                WatchedVideo {
+                    name: name.to_string(),
+                    deadline: Instant::now() + timeout,
+                    expired: expired.clone(),
+                },
+            );
+        }
+        VideoWatch {
+            id,
+            expired,
+            watchdog: self,
+        }
+    }
+
+    /// Stops the watchdog and returns the videos that timed out.
+    pub fn finish(mut self) -> Vec<VideoFailure> {
+        self.stop_thread();
+        std::mem::take(&mut self.state.lock().unwrap().failures)
+    }
+
+    fn stop_thread(&mut self) {
+        self.stop = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+fn expire_late_videos(
+    state: &Mutex<WatchdogState>,
+    timeout: Duration,
+    progress: &ProgressSender,
+) {
+    let now = Instant::now();
+    let mut state = state.lock().unwrap();
+    let late: Vec<usize> = state
+        .videos
+        .iter()
+        .filter(|(_, video)| video.deadline <= now)
+        .map(|(id, _)| *id)
+        .collect();
+    for id in late {
+        let video = state.videos.remove(&id).unwrap();
+        video.expired.cancel();
+        progress.println(format!(
+            "Video {} timed out after {} s, skipping the rest of it",
+            video.name,
+            timeout.as_secs()
+        ));
+        state.failures.push(VideoFailure {
+            video_id: video.name,
+            reason: "timeout".to_string(),
+            timeout_secs: timeout.as_secs(),
+        });
+    }
+}
+
+/// The timeout of a single video, see [`Watchdog::watch`].
+pub struct VideoWatch<'a> {
+    id: usize,
+    expired: CancellationToken,
+    watchdog: &'a Watchdog,
+}
+
+impl VideoWatch<'_> {
+    /// Returns `true` once the video ran out of time.
+    pub fn is_expired(&self) -> bool {
+        self.expired.is_cancelled()
+    }
+}
+
+impl Drop for VideoWatch<'_> {
+    fn drop(&mut self) {
+        self.watchdog.state.lock().unwrap().videos.remove(&self.id);
+    }
+}
+
+/// Writes the videos that timed out to `failures.json` in `output_dir`.
+pub fn write_failures(output_dir: &Path, failures: Vec<VideoFailure>) -> io::Result<()> {
+    let export = FailuresExport {
+        info: ExportInfo {
+            version: "failures_1.0".to_string(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            incomplete: false,
+        },
+        failures,
+    };
+    let json_data = serde_json::to_string_pretty(&export)?;
+    fs::write(output_dir.join(FAILURES_FILE), json_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_expires_late_videos() {
+        let watchdog = Watchdog::start(Duration::from_millis(50), ProgressSender::disabled());
+        let slow = watchdog.watch("SNGS-001");
+        {
+            let fast = watchdog.watch("SNGS-002");
+            assert!(!fast.is_expired());
+        }
+        thread::sleep(Duration::from_millis(300));
+        assert!(slow.is_expired());
+        drop(slow);
+
+        let failures = watchdog.finish();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].video_id, "SNGS-001");
+
+        let disabled = Watchdog::disabled();
+        assert!(!disabled.watch("SNGS-001").is_expired());
+        assert!(disabled.finish().is_empty());
+    }
+}